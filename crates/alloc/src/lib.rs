@@ -1,11 +1,22 @@
 //! Alloc support for the Flipper Zero.
-//! *Note:* This currently requires using nightly.
+//!
+//! Linking this crate sets the global allocator to the heap of the firmware, so that
+//! `Box`, `Vec` and `String` from `alloc` can be used:
+//!
+//! ```ignore
+//! extern crate alloc;
+//! extern crate flipperzero_alloc;
+//! ```
+//!
+//! When the heap is exhausted, the failed allocation is logged with the number of bytes
+//! requested and free. Fallible APIs such as `Vec::try_reserve` then return an error,
+//! and all others call the panic handler, which ends the app with a crash.
 
 #![no_std]
 #![deny(rustdoc::broken_intra_doc_links)]
 
 use core::alloc::{GlobalAlloc, Layout};
-use core::ffi::c_void;
+use core::ffi::{c_uint, c_void};
 
 use flipperzero_sys as sys;
 
@@ -14,7 +25,11 @@ pub struct FuriAlloc;
 unsafe impl GlobalAlloc for FuriAlloc {
     #[inline]
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-        sys::aligned_malloc(layout.size(), layout.align()) as *mut u8
+        let ptr = sys::aligned_malloc(layout.size(), layout.align()) as *mut u8;
+        if ptr.is_null() {
+            log_out_of_memory(layout);
+        }
+        ptr
     }
 
     #[inline]
@@ -29,5 +44,22 @@ unsafe impl GlobalAlloc for FuriAlloc {
     }
 }
 
+/// Logs a failed allocation of `layout`.
+///
+/// This uses the firmware's formatting, so that it works without allocating and adds no
+/// formatting code to the binary.
+#[cold]
+fn log_out_of_memory(layout: Layout) {
+    unsafe {
+        sys::furi_log_print_format(
+            sys::FuriLogLevel_FuriLogLevelError,
+            c"alloc".as_ptr(),
+            c"out of memory: %u bytes requested, %u bytes free".as_ptr(),
+            layout.size() as c_uint,
+            sys::memmgr_get_free_heap() as c_uint,
+        );
+    }
+}
+
 #[global_allocator]
 static ALLOCATOR: FuriAlloc = FuriAlloc;
@@ -10,7 +10,7 @@ from subprocess import run
 PYTHON = 'python'
 TOOLS_PATH = '../tools'
 INSTALL_PATH = PurePosixPath('/ext/apps/Examples')
-EXAMPLES = ["dialog", "example_images", "gpio", "gui", "hello-rust", "notification", "storage"]
+EXAMPLES = ["ble_log", "block_cache_bench", "buffered_bench", "conformance", "dialog", "dict_bench", "dir_browser", "edge_logger", "edit_uid", "example_images", "file_browser", "flipper_format", "free_space", "gpio", "gui", "hello-rust", "input_logger", "ir_send", "notification", "play_pcm", "progress", "scenes", "settings", "spi_flash_dump", "splash", "storage", "storage_bench", "storage_events", "text_viewer", "uart_log", "uart_send", "usb_drive", "usb_echo", "walk_dir", "ymodem_receive"]
 
 
 def parse_args():
@@ -0,0 +1,70 @@
+//! Demonstrates the Bluetooth LE serial profile.
+//!
+//! This app waits for a phone to connect to the serial profile, for example with a BLE
+//! terminal app, and then sends it the file given as argument, or `/ext/log.txt`.
+
+#![no_main]
+#![no_std]
+
+// Required for panic handler
+extern crate flipperzero_rt;
+
+// Required for allocator
+extern crate flipperzero_alloc;
+
+use core::ffi::CStr;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
+
+use flipperzero::ble::SerialProfile;
+use flipperzero::furi::thread::sleep;
+use flipperzero::io::*;
+use flipperzero::println;
+use flipperzero::storage::*;
+use flipperzero_rt::{entry, manifest};
+
+manifest!(name = "Rust BLE log example");
+entry!(main);
+
+const DEFAULT_PATH: &CStr = c"/ext/log.txt";
+
+static CONNECTED: AtomicBool = AtomicBool::new(false);
+
+fn send(path: &CStr, profile: &mut SerialProfile) -> Result<u64, Error> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .open_existing(true)
+        .open(path)?;
+    let sent = copy(&mut file, profile)?;
+    profile.flush()?;
+    Ok(sent)
+}
+
+fn main(args: Option<&CStr>) -> i32 {
+    let path = args.filter(|args| !args.is_empty()).unwrap_or(DEFAULT_PATH);
+
+    let mut profile = match SerialProfile::start() {
+        Ok(profile) => profile,
+        Err(e) => {
+            println!("couldn't start the serial profile: {}", e);
+            return 1;
+        }
+    };
+    profile.set_connection_callback(|connected| CONNECTED.store(connected, Ordering::Release));
+
+    println!("waiting for a phone to connect");
+    while !CONNECTED.load(Ordering::Acquire) {
+        sleep(Duration::from_millis(100));
+    }
+
+    match send(path, &mut profile) {
+        Ok(sent) => println!("sent {} bytes", sent),
+        Err(e) if !profile.is_connected() => println!("phone disconnected: {}", e),
+        Err(e) => {
+            println!("couldn't send {}: {}", path.to_str().unwrap_or("file"), e);
+            return 2;
+        }
+    }
+
+    0
+}
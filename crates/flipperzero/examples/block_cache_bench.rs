@@ -0,0 +1,133 @@
+//! Compares the reads of a hex viewer's access pattern with and without a `BlockCache`.
+//!
+//! This app writes a test file to the SD card and reads it the way a hex viewer does: a
+//! page at a time while scrolling down and back up a row at a time, and while jumping
+//! between a few offsets. It prints how many reads reached the file and how long the
+//! pattern took, first on the plain `File` and then through a `BlockCache`.
+
+#![no_main]
+#![no_std]
+
+// Required for panic handler
+extern crate flipperzero_rt;
+
+// Required for allocator
+extern crate flipperzero_alloc;
+
+use core::ffi::CStr;
+
+use flipperzero::furi::time::{Duration, Instant};
+use flipperzero::io::{Error, Read, Seek, SeekFrom, Write};
+use flipperzero::println;
+use flipperzero::storage::{BlockCache, OpenOptions, Storage};
+use flipperzero_rt::{entry, manifest};
+
+manifest!(name = "Rust block cache benchmark example");
+entry!(main);
+
+const PATH: &CStr = c"/ext/.block_cache_bench";
+const FILE_SIZE: usize = 16 * 1024;
+
+/// Bytes per row and rows per page, as shown by the hex viewer.
+const ROW_SIZE: usize = 8;
+const PAGE_SIZE: usize = 4 * ROW_SIZE;
+
+/// Rows scrolled down and back up.
+const SCROLL_ROWS: usize = 256;
+
+/// Offsets jumped between, as when following offsets in a file's header.
+const JUMPS: [usize; 4] = [0, 12 * 1024, 512, 12 * 1024 + 64];
+
+const BLOCK_SIZE: usize = 512;
+const BLOCK_COUNT: usize = 4;
+
+/// Counts the reads that reach the wrapped reader.
+struct Counting<R> {
+    inner: R,
+    reads: u32,
+}
+
+impl<R: Read> Read for Counting<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        self.reads += 1;
+        self.inner.read(buf)
+    }
+}
+
+impl<R: Seek> Seek for Counting<R> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<usize, Error> {
+        self.inner.seek(pos)
+    }
+}
+
+fn main(_args: Option<&CStr>) -> i32 {
+    if let Err(e) = run() {
+        println!("benchmark failed: {}", e);
+        let _ = Storage::open().remove(PATH);
+        return 1;
+    }
+    let _ = Storage::open().remove(PATH);
+    0
+}
+
+fn run() -> Result<(), Error> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_always(true)
+        .open(PATH)?;
+    let mut chunk = [0; 256];
+    for (i, byte) in chunk.iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+    for _ in 0..FILE_SIZE / chunk.len() {
+        file.write_all(&chunk)?;
+    }
+
+    let mut plain = Counting {
+        inner: file,
+        reads: 0,
+    };
+    let elapsed = access_pattern(&mut plain)?;
+    println!("File: {} reads, {} ms", plain.reads, elapsed.as_millis());
+
+    let mut cache = BlockCache::new(
+        Counting {
+            inner: plain.inner,
+            reads: 0,
+        },
+        BLOCK_SIZE,
+        BLOCK_COUNT,
+    );
+    let elapsed = access_pattern(&mut cache)?;
+    let stats = cache.stats();
+    println!(
+        "BlockCache: {} reads, {} ms, {} hits, {} misses",
+        cache.get_ref().reads,
+        elapsed.as_millis(),
+        stats.hits,
+        stats.misses
+    );
+    Ok(())
+}
+
+/// Reads pages of `reader` as a hex viewer does, returning how long it took.
+fn access_pattern(reader: &mut (impl Read + Seek)) -> Result<Duration, Error> {
+    let start = Instant::now();
+    let tops = (0..SCROLL_ROWS)
+        .chain((0..SCROLL_ROWS).rev())
+        .map(|row| row * ROW_SIZE)
+        .chain(JUMPS.iter().copied().cycle().take(4 * JUMPS.len()));
+    let mut page = [0; PAGE_SIZE];
+    for top in tops {
+        reader.seek(SeekFrom::Start(top as u64))?;
+        let mut filled = 0;
+        while filled < page.len() {
+            match reader.read(&mut page[filled..])? {
+                0 => break,
+                n => filled += n,
+            }
+        }
+    }
+    Ok(Instant::now().duration_since(start))
+}
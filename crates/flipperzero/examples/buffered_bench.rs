@@ -0,0 +1,110 @@
+//! Compares how long a capture waits on SD card writes with `BufWriter` and
+//! `DoubleBufferedWriter`.
+//!
+//! This app writes a SubGhz RAW capture, one `RAW_Data` line at a time with a pause in
+//! between as if receiving the timings, to `/ext/bench_buf.sub` and
+//! `/ext/bench_double.sub`, and prints the total and longest time the producer spent
+//! blocked in `write`.
+
+#![no_main]
+#![no_std]
+
+// Required for panic handler
+extern crate flipperzero_rt;
+
+// Required for allocator
+extern crate flipperzero_alloc;
+
+extern crate alloc;
+
+use alloc::string::String;
+use core::ffi::CStr;
+use core::fmt::Write as _;
+
+use flipperzero::io::{Error, Write};
+use flipperzero::println;
+use flipperzero::storage::{BufWriter, DoubleBufferedWriter, File, OpenOptions};
+use flipperzero_rt::{entry, manifest};
+use flipperzero_sys as sys;
+
+manifest!(name = "Rust buffered writer benchmark example");
+entry!(main);
+
+const HEADER: &str = "Filetype: Flipper SubGhz RAW File\nVersion: 1\nFrequency: 433920000\nPreset: FuriHalSubGhzPresetOok650Async\nProtocol: RAW\n";
+const LINES: u32 = 200;
+const TIMINGS_PER_LINE: i32 = 512;
+const BUF_SIZE: usize = 4096;
+
+/// Time spent in `write`, in microseconds.
+#[derive(Default)]
+struct Stall {
+    total: u32,
+    longest: u32,
+}
+
+fn main(_args: Option<&CStr>) -> i32 {
+    let mut line = String::from("RAW_Data:");
+    for i in 0..TIMINGS_PER_LINE {
+        let duration = 300 + (i * 37) % 900;
+        let _ = write!(line, " {}", if i % 2 == 0 { duration } else { -duration });
+    }
+    line.push('\n');
+
+    let buffered = open(c"/ext/bench_buf.sub").and_then(|file| {
+        let mut writer = BufWriter::with_capacity(BUF_SIZE, file);
+        let stall = capture(&mut writer, line.as_bytes())?;
+        writer.flush()?;
+        Ok(stall)
+    });
+    let double = open(c"/ext/bench_double.sub").and_then(|file| {
+        // The same memory as the `BufWriter`, split into two buffers.
+        let mut writer = DoubleBufferedWriter::with_capacity(BUF_SIZE / 2, file);
+        let stall = capture(&mut writer, line.as_bytes())?;
+        writer.finish()?;
+        Ok(stall)
+    });
+
+    match (buffered, double) {
+        (Ok(buffered), Ok(double)) => {
+            println!(
+                "BufWriter: {} us stalled, longest {} us",
+                buffered.total, buffered.longest
+            );
+            println!(
+                "DoubleBufferedWriter: {} us stalled, longest {} us",
+                double.total, double.longest
+            );
+            0
+        }
+        (Err(e), _) | (_, Err(e)) => {
+            println!("couldn't write the capture: {}", e);
+            1
+        }
+    }
+}
+
+fn open(path: &CStr) -> Result<File, Error> {
+    OpenOptions::new()
+        .write(true)
+        .create_always(true)
+        .open(path)
+}
+
+/// Writes the capture to `writer`, returning how long the writes blocked.
+fn capture(writer: &mut impl Write, line: &[u8]) -> Result<Stall, Error> {
+    let cycles_per_us = unsafe { sys::furi_hal_cortex_instructions_per_microsecond() };
+    let mut stall = Stall::default();
+    writer.write_all(HEADER.as_bytes())?;
+    for _ in 0..LINES {
+        // Stands in for receiving the next timings.
+        unsafe { sys::furi_delay_ms(5) };
+
+        let start = unsafe { sys::furi_hal_cortex_timer_get(0).start };
+        writer.write_all(line)?;
+        let end = unsafe { sys::furi_hal_cortex_timer_get(0).start };
+        let us = end.wrapping_sub(start) / cycles_per_us;
+        stall.total += us;
+        stall.longest = stall.longest.max(us);
+    }
+    Ok(stall)
+}
@@ -0,0 +1,81 @@
+//! Runs the conformance checks of `flipperzero::testsuite` against files.
+//!
+//! This app checks `File` on the SD card and on the internal storage, opened for
+//! reading and for reading and writing, and a `BlockCache` over a file on the SD card.
+//! It prints the result of each, and the first check that failed.
+
+#![no_main]
+#![no_std]
+
+// Required for panic handler
+extern crate flipperzero_rt;
+
+// Required for allocator
+extern crate flipperzero_alloc;
+
+use core::ffi::CStr;
+
+use flipperzero::io::{Error, Seek, Write};
+use flipperzero::println;
+use flipperzero::storage::{BlockCache, File, OpenOptions, Storage};
+use flipperzero::testsuite::{self, Failure};
+use flipperzero_rt::{entry, manifest};
+
+manifest!(name = "Rust conformance example");
+entry!(main);
+
+const EXT_PATH: &CStr = c"/ext/.conformance";
+const INT_PATH: &CStr = c"/int/.conformance";
+
+fn main(_args: Option<&CStr>) -> i32 {
+    let results = [
+        ("File on /ext, read", check_read(EXT_PATH)),
+        ("File on /ext, read-write", check_read_write(EXT_PATH)),
+        ("File on /int, read", check_read(INT_PATH)),
+        ("File on /int, read-write", check_read_write(INT_PATH)),
+        ("BlockCache on /ext", check_block_cache(EXT_PATH)),
+    ];
+
+    let storage = Storage::open();
+    let _ = storage.remove(EXT_PATH);
+    let _ = storage.remove(INT_PATH);
+
+    let mut failed = 0;
+    for (name, result) in results {
+        match result {
+            Ok(()) => println!("{}: ok", name),
+            Err(failure) => {
+                println!("{}: failed {}", name, failure);
+                failed += 1;
+            }
+        }
+    }
+    failed
+}
+
+/// Creates the file at `path` with `contents`, open for reading and writing.
+fn create(path: &CStr, contents: &[u8]) -> Result<File, Error> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_always(true)
+        .open(path)?;
+    file.write_all(contents)?;
+    file.rewind()?;
+    Ok(file)
+}
+
+fn check_read(path: &CStr) -> Result<(), Failure> {
+    testsuite::check_read_seek(|contents| {
+        drop(create(path, contents)?);
+        OpenOptions::new().read(true).open(path)
+    })
+}
+
+fn check_read_write(path: &CStr) -> Result<(), Failure> {
+    testsuite::check_read_write_seek(|contents| create(path, contents))
+}
+
+fn check_block_cache(path: &CStr) -> Result<(), Failure> {
+    testsuite::check_read_write_seek(|contents| Ok(BlockCache::new(create(path, contents)?, 16, 4)))
+}
@@ -0,0 +1,98 @@
+//! Measures scanning an NFC key dictionary with and without `ReadAhead`.
+//!
+//! This app parses the MIFARE Classic dictionary given as argument, or the system one,
+//! in 64-byte reads with a short pause per key as if trying it on a card. It scans once
+//! reading the file directly and once through a `ReadAhead`, and prints both times.
+
+#![no_main]
+#![no_std]
+
+// Required for panic handler
+extern crate flipperzero_rt;
+
+// Required for allocator
+extern crate flipperzero_alloc;
+
+use core::ffi::CStr;
+
+use flipperzero::furi::time::{Duration, Instant};
+use flipperzero::io::{Error, Read};
+use flipperzero::println;
+use flipperzero::storage::{File, OpenOptions, ReadAhead};
+use flipperzero_rt::{entry, manifest};
+use flipperzero_sys as sys;
+
+manifest!(name = "Rust dictionary benchmark example");
+entry!(main);
+
+/// Read ahead 4 KiB, plus the stack of the worker thread.
+const CHUNKS: usize = 8;
+const CHUNK_SIZE: usize = 512;
+
+fn main(args: Option<&CStr>) -> i32 {
+    let path = args
+        .filter(|args| !args.is_empty())
+        .unwrap_or(c"/ext/nfc/assets/mf_classic_dict.nfc");
+
+    let direct = open(path).and_then(scan);
+    let read_ahead = open(path).and_then(|file| {
+        let reader = ReadAhead::with_capacity(CHUNKS, CHUNK_SIZE, file);
+        if !reader.is_prefetching() {
+            println!("not enough memory to read ahead");
+        }
+        scan(reader)
+    });
+
+    match (direct, read_ahead) {
+        (Ok((keys, direct)), Ok((_, read_ahead))) => {
+            println!(
+                "{} keys: {} ms reading directly, {} ms with ReadAhead",
+                keys,
+                direct.as_millis(),
+                read_ahead.as_millis()
+            );
+            0
+        }
+        (Err(e), _) | (_, Err(e)) => {
+            println!("couldn't scan the dictionary: {}", e);
+            1
+        }
+    }
+}
+
+fn open(path: &CStr) -> Result<File, Error> {
+    OpenOptions::new().read(true).open_existing(true).open(path)
+}
+
+/// Counts the keys in the dictionary, returning how many there were and how long it
+/// took.
+fn scan(mut reader: impl Read) -> Result<(u32, Duration), Error> {
+    let start = Instant::now();
+    let mut keys = 0;
+    let mut line_len = 0;
+    let mut comment = false;
+    let mut buf = [0; 64];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        for &byte in &buf[..n] {
+            match byte {
+                b'\n' => {
+                    if !comment && line_len == 12 {
+                        keys += 1;
+                        // Stands in for trying the key.
+                        unsafe { sys::furi_delay_us(200) };
+                    }
+                    line_len = 0;
+                    comment = false;
+                }
+                b'#' if line_len == 0 => comment = true,
+                b'\r' => {}
+                _ => line_len += 1,
+            }
+        }
+    }
+    Ok((keys, Instant::now().duration_since(start)))
+}
@@ -0,0 +1,163 @@
+//! Demonstrates use of the view dispatcher.
+//!
+//! This app lists the files in the root directory of the SD card. Up and Down select a
+//! file, OK shows its contents in the text viewer, and Back exits.
+
+#![no_main]
+#![no_std]
+
+// Required for panic handler
+extern crate flipperzero_rt;
+
+// Required for allocator
+extern crate flipperzero_alloc;
+
+extern crate alloc;
+
+use alloc::ffi::CString;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::ffi::{c_char, CStr};
+
+use flipperzero::furi::string::FuriString;
+use flipperzero::furi::sync::Mutex;
+use flipperzero::gui::canvas::Font;
+use flipperzero::gui::{TextViewer, View, ViewDispatcher, ViewDispatcherType};
+use flipperzero::input::{Key, Kind};
+use flipperzero::println;
+use flipperzero::storage::OpenOptions;
+use flipperzero_rt::{entry, manifest};
+use flipperzero_sys as sys;
+use flipperzero_sys::furi::UnsafeRecord;
+
+manifest!(name = "Rust directory browser example");
+entry!(main);
+
+const DIR: &CStr = c"/ext";
+const MAX_ENTRIES: usize = 64;
+const VISIBLE_ENTRIES: usize = 5;
+
+const LIST_VIEW: u32 = 0;
+const OPEN_EVENT: u32 = 0;
+
+struct Listing {
+    names: Vec<CString>,
+    selected: usize,
+}
+
+/// Returns the names of the first [`MAX_ENTRIES`] files in [`DIR`].
+fn list_files() -> Vec<CString> {
+    let mut names = Vec::new();
+    unsafe {
+        let storage = UnsafeRecord::<sys::Storage>::open(c"storage".as_ptr());
+        let dir = sys::storage_file_alloc(storage.as_ptr());
+        if sys::storage_dir_open(dir, DIR.as_ptr()) {
+            let mut info = sys::FileInfo { flags: 0, size: 0 };
+            let mut name = [0 as c_char; 256];
+            while names.len() < MAX_ENTRIES
+                && sys::storage_dir_read(dir, &mut info, name.as_mut_ptr(), name.len() as u16)
+            {
+                if !sys::file_info_is_dir(&info) {
+                    names.push(CStr::from_ptr(name.as_ptr()).into());
+                }
+            }
+        }
+        sys::storage_dir_close(dir);
+        sys::storage_file_free(dir);
+    }
+    names
+}
+
+fn main(_args: Option<&CStr>) -> i32 {
+    let listing = Arc::new(Mutex::new(Listing {
+        names: list_files(),
+        selected: 0,
+    }));
+
+    let mut view_dispatcher = ViewDispatcher::new();
+    let sender = view_dispatcher.custom_event_sender();
+
+    let mut list = View::new();
+    list.on_draw({
+        let listing = listing.clone();
+        move |canvas| {
+            let listing = listing.lock();
+            canvas.set_font(Font::Secondary);
+            if listing.names.is_empty() {
+                canvas.draw_str(2, 10, c"No files");
+                return;
+            }
+
+            let first = listing
+                .selected
+                .saturating_sub(VISIBLE_ENTRIES - 1)
+                .min(listing.names.len().saturating_sub(VISIBLE_ENTRIES));
+            for (row, name) in listing
+                .names
+                .iter()
+                .skip(first)
+                .take(VISIBLE_ENTRIES)
+                .enumerate()
+            {
+                let y = 10 + 12 * row as i32;
+                if first + row == listing.selected {
+                    canvas.draw_str(2, y, c">");
+                }
+                canvas.draw_str(10, y, name);
+            }
+        }
+    });
+    list.on_input({
+        let listing = listing.clone();
+        move |event| {
+            if event.kind != Kind::Short && event.kind != Kind::Repeat {
+                return false;
+            }
+            let mut listing = listing.lock();
+            match event.key {
+                Key::Up => listing.selected = listing.selected.saturating_sub(1),
+                Key::Down => {
+                    listing.selected =
+                        (listing.selected + 1).min(listing.names.len().saturating_sub(1))
+                }
+                Key::Ok if !listing.names.is_empty() => {
+                    sender.send(OPEN_EVENT);
+                }
+                _ => return false,
+            }
+            true
+        }
+    });
+    view_dispatcher.add_view(LIST_VIEW, list);
+
+    view_dispatcher.on_custom_event(move |_, event| {
+        if event != OPEN_EVENT {
+            return false;
+        }
+
+        // Don't hold the lock while the file is shown, as the list may be redrawn.
+        let name = {
+            let listing = listing.lock();
+            listing.names[listing.selected].clone()
+        };
+        let mut path = FuriString::from(DIR);
+        path.push('/');
+        path.push_c_str(&name);
+
+        let result = OpenOptions::new()
+            .read(true)
+            .open_existing(true)
+            .open(path.as_c_str())
+            .and_then(|file| TextViewer::show(file, &name.to_string_lossy()));
+        if let Err(e) = result {
+            println!("couldn't show file: {}", e);
+        }
+        true
+    });
+
+    view_dispatcher.attach_to_gui(ViewDispatcherType::Fullscreen);
+    view_dispatcher.switch_to(LIST_VIEW);
+    view_dispatcher.run();
+
+    0
+}
@@ -0,0 +1,71 @@
+//! Demonstrates logging the edges of a GPIO pin.
+//!
+//! This app logs the rising and falling edges of pin C0 (header pin 16) for ten
+//! seconds, to `/ext/edges.csv`, or in the compact binary format to `/ext/edges.bin`
+//! if the argument is `binary`.
+
+#![no_main]
+#![no_std]
+
+// Required for panic handler
+extern crate flipperzero_rt;
+
+// Required for allocator
+extern crate flipperzero_alloc;
+
+use core::ffi::CStr;
+use core::time::Duration;
+
+use flipperzero::furi::thread::sleep;
+use flipperzero::gpio::{Binary, EdgeLogger, Pin};
+use flipperzero::io::Error;
+use flipperzero::println;
+use flipperzero::storage::{BufWriter, OpenOptions};
+use flipperzero_rt::{entry, manifest};
+
+manifest!(name = "Rust GPIO edge logger example");
+entry!(main);
+
+fn main(args: Option<&CStr>) -> i32 {
+    let binary = args == Some(c"binary");
+    let path = if binary {
+        c"/ext/edges.bin"
+    } else {
+        c"/ext/edges.csv"
+    };
+
+    match log_edges(path, binary) {
+        Ok(()) => 0,
+        Err(e) => {
+            println!("couldn't log edges: {}", e);
+            1
+        }
+    }
+}
+
+fn log_edges(path: &CStr, binary: bool) -> Result<(), Error> {
+    let file = OpenOptions::new()
+        .write(true)
+        .create_always(true)
+        .open(path)?;
+    let output = BufWriter::new(file);
+
+    // Timestamps in microseconds.
+    let logger = if binary {
+        EdgeLogger::start_with_format(Pin::Pc0, output, 1, Binary::new())?
+    } else {
+        EdgeLogger::start(Pin::Pc0, output, 1)?
+    };
+    println!(
+        "logging edges of pin C0 to {}",
+        path.to_str().unwrap_or("file")
+    );
+    sleep(Duration::from_secs(10));
+
+    let stats = logger.stop()?;
+    println!(
+        "logged {} edges in {} us, dropped {}",
+        stats.edges, stats.duration, stats.dropped
+    );
+    Ok(())
+}
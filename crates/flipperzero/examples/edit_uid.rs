@@ -0,0 +1,47 @@
+//! Demonstrates editing bytes of a file in place.
+//!
+//! This app lets the user pick a binary MIFARE Classic dump (`.mfd`) from `/ext/nfc`
+//! and edit the four UID bytes at its start. Note that the `.nfc` files saved by the
+//! firmware are text files, and can't be edited this way.
+
+#![no_main]
+#![no_std]
+
+// Required for panic handler
+extern crate flipperzero_rt;
+
+// Required for allocator
+extern crate flipperzero_alloc;
+
+use core::ffi::CStr;
+
+use flipperzero::gui::{edit_bytes, show_error, Submenu};
+use flipperzero::println;
+use flipperzero_rt::{entry, manifest};
+
+manifest!(name = "Rust UID editor example");
+entry!(main);
+
+const UID_OFFSET: u64 = 0;
+const UID_LEN: usize = 4;
+
+fn main(_args: Option<&CStr>) -> i32 {
+    let path = match Submenu::from_dir(c"/ext/nfc", |entry| {
+        !entry.is_dir() && entry.name().to_bytes().ends_with(b".mfd")
+    }) {
+        Ok(Some(path)) => path,
+        Ok(None) => return 0,
+        Err(e) => {
+            println!("couldn't list dumps: {}", e);
+            return 1;
+        }
+    };
+
+    match edit_bytes(path.as_c_str(), UID_OFFSET, UID_LEN) {
+        Ok(true) => println!("UID changed"),
+        Ok(false) => println!("UID unchanged"),
+        Err(e) => show_error(&e, "Editing UID"),
+    }
+
+    0
+}
@@ -9,26 +9,18 @@ extern crate flipperzero_alloc;
 
 use core::ffi::{c_void, CStr};
 use core::mem::{self, MaybeUninit};
-use core::ptr::addr_of;
 
 use flipperzero_sys::furi::UnsafeRecord;
 
 use flipperzero_rt as rt;
 use flipperzero_sys as sys;
 
-use sys::Icon;
+use flipperzero::gui::icon::Icon;
 
 rt::manifest!(name = "Example: Images");
 rt::entry!(main);
 
-static mut TARGET_ICON: Icon = Icon {
-    width: 48,
-    height: 32,
-    frame_count: 1,
-    frame_rate: 0,
-    frames: unsafe { TARGET_FRAMES.as_ptr() },
-};
-static mut TARGET_FRAMES: [*const u8; 1] = [include_bytes!("icons/rustacean-48x32.icon").as_ptr()];
+static TARGET_ICON: &Icon = flipperzero::icon!(48, 32, "icons/rustacean-48x32.icon");
 
 static mut IMAGE_POSITION: ImagePosition = ImagePosition { x: 0, y: 0 };
 
@@ -46,7 +38,7 @@ extern "C" fn app_draw_callback(canvas: *mut sys::Canvas, _ctx: *mut c_void) {
             canvas,
             IMAGE_POSITION.x,
             IMAGE_POSITION.y,
-            addr_of!(TARGET_ICON) as *const Icon as *const c_void as *const sys::Icon,
+            TARGET_ICON.as_sys(),
         );
     }
 }
@@ -0,0 +1,63 @@
+//! Demonstrates the options available for the file browser dialog.
+//!
+//! This app opens a file browser that only offers `.txt` files below `/ext`, and then
+//! prints the selected path to the console.
+
+#![no_main]
+#![no_std]
+
+// Required for panic handler
+extern crate flipperzero_rt;
+
+// Required for allocator
+extern crate flipperzero_alloc;
+
+use core::ffi::CStr;
+use core::ptr::addr_of;
+
+use flipperzero::dialogs::{DialogsApp, FileBrowserOptions};
+use flipperzero::furi::string::FuriString;
+use flipperzero::println;
+use flipperzero_rt::{entry, manifest};
+use flipperzero_sys as sys;
+
+manifest!(name = "Rust file browser example");
+entry!(main);
+
+static mut ICON: sys::Icon = sys::Icon {
+    width: 10,
+    height: 10,
+    frame_count: 1,
+    frame_rate: 0,
+    frames: unsafe { ICON_FRAMES.as_ptr() },
+};
+static mut ICON_FRAMES: [*const u8; 1] = [include_bytes!("icons/rustacean-10x10.icon").as_ptr()];
+
+fn main(_args: Option<&CStr>) -> i32 {
+    // SAFETY: `ICON` is never mutated.
+    let icon = unsafe { &*addr_of!(ICON) };
+
+    // SAFETY: both strings are valid UTF-8.
+    let options = unsafe { FileBrowserOptions::with_extension(c"txt").set_base_path(c"/ext") }
+        .set_icon(icon)
+        .set_skip_assets(true)
+        .set_hide_dot_files(true)
+        .set_hide_ext(false);
+
+    let mut dialogs = DialogsApp::open();
+    let mut start_path = FuriString::from("/ext");
+
+    match dialogs.show_file_browser(Some(&mut start_path), Some(&options)) {
+        Ok(Some(path)) => println!("file selected: {}", path),
+        Ok(None) => println!("no file selected"),
+        Err(e) => println!("couldn't show file browser: {}", e),
+    }
+
+    // The start path must be within the base path, otherwise the browser isn't shown.
+    let mut outside = FuriString::from("/int");
+    if let Err(e) = dialogs.show_file_browser(Some(&mut outside), Some(&options)) {
+        println!("rejected {}: {}", outside, e);
+    }
+
+    0
+}
@@ -0,0 +1,78 @@
+//! Demonstrates reading and writing FlipperFormat files.
+//!
+//! This app writes a Sub-GHz key file in the style of a `.sub` file to
+//! `/data/remote.sub` (the app's data directory), then reads it back and prints its
+//! contents to the console.
+
+#![no_main]
+#![no_std]
+
+// Required for panic handler
+extern crate flipperzero_rt;
+
+// Required for allocator
+extern crate flipperzero_alloc;
+
+use core::ffi::CStr;
+
+use flipperzero::println;
+use flipperzero::storage::{FlipperFormat, FormatError};
+use flipperzero_rt::{entry, manifest};
+
+manifest!(name = "Rust FlipperFormat example");
+entry!(main);
+
+const PATH: &CStr = c"/data/remote.sub";
+
+fn write() -> Result<(), FormatError> {
+    let mut file = FlipperFormat::open_always(PATH)?;
+    file.write_header(c"Flipper SubGhz Key File", 1)?;
+    file.write_comment(c"Written by the Rust FlipperFormat example")?;
+    file.write_u32(c"Frequency", 433920000)?;
+    file.write_string(c"Preset", c"FuriHalSubGhzPresetOok650Async")?;
+    file.write_string(c"Protocol", c"Princeton")?;
+    file.write_u32(c"Bit", 24)?;
+    file.write_hex(c"Key", &[0x00, 0x00, 0x00, 0x00, 0x00, 0x95, 0xD5, 0xD4])?;
+    file.write_u32(c"TE", 400)?;
+    file.close()?;
+    Ok(())
+}
+
+fn read() -> Result<(), FormatError> {
+    let mut file = FlipperFormat::open_read(PATH)?;
+
+    let (file_type, version) = file.read_header()?;
+    println!("{} v{}", file_type, version);
+
+    println!("keys:");
+    for key in file.keys() {
+        println!("  {}", key?);
+    }
+
+    file.rewind()?;
+    let frequency = file.read_u32(c"Frequency")?;
+    let protocol = file.read_string(c"Protocol")?;
+    let mut key = [0; 8];
+    file.read_hex(c"Key", &mut key)?;
+    println!("{} at {} Hz, key {:?}", protocol, frequency, &key[..]);
+
+    match file.read_u32(c"Repeat") {
+        Err(FormatError::MissingKey) => println!("no repeat count, sending once"),
+        Ok(repeat) => println!("repeating {} times", repeat),
+        Err(e) => return Err(e),
+    }
+
+    Ok(())
+}
+
+fn main(_args: Option<&CStr>) -> i32 {
+    if let Err(e) = write() {
+        println!("couldn't write the file: {}", e);
+        return 1;
+    }
+    if let Err(e) = read() {
+        println!("couldn't read the file: {}", e);
+        return 1;
+    }
+    0
+}
@@ -0,0 +1,82 @@
+//! Demonstrates use of the safe view port wrapper.
+//!
+//! This app shows how much space is used on the SD card as a progress bar, and exits
+//! when Back is pressed.
+
+#![no_main]
+#![no_std]
+
+// Required for panic handler
+extern crate flipperzero_rt;
+
+// Required for allocator
+extern crate flipperzero_alloc;
+
+extern crate alloc;
+
+use alloc::ffi::CString;
+use alloc::format;
+use alloc::sync::Arc;
+use core::ffi::CStr;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
+
+use flipperzero::fmt::usage_percent;
+use flipperzero::furi::thread::sleep;
+use flipperzero::gui::canvas::{Align, Font};
+use flipperzero::gui::{GuiLayer, ViewPort};
+use flipperzero::input::{Key, Kind};
+use flipperzero::println;
+use flipperzero::storage::Storage;
+use flipperzero_rt::{entry, manifest};
+use flipperzero_sys as sys;
+
+manifest!(name = "Rust free space example");
+entry!(main);
+
+fn main(_args: Option<&CStr>) -> i32 {
+    let info = match Storage::open().fs_info(c"/ext") {
+        Ok(info) => info,
+        Err(e) => {
+            println!("couldn't get SD card info: {}", e);
+            return 1;
+        }
+    };
+
+    // The draw callback runs on the GUI thread, so the label must be `Send`.
+    let label = CString::new(format!("{}", info)).unwrap();
+    let used = info.total_space.saturating_sub(info.free_space);
+    let percent = usage_percent(used, info.total_space);
+    let header = CString::new(format!("SD card: {}% used", percent)).unwrap();
+
+    let exit = Arc::new(AtomicBool::new(false));
+
+    let mut view_port = ViewPort::new();
+    view_port.on_draw(move |canvas| {
+        canvas.set_font(Font::Primary);
+        canvas.draw_str_aligned(64, 12, Align::Center, Align::Center, &header);
+        unsafe {
+            sys::elements_progress_bar(canvas.as_ptr(), 4, 26, 120, f32::from(percent) / 100.0)
+        };
+        canvas.set_font(Font::Secondary);
+        canvas.draw_str_aligned(64, 50, Align::Center, Align::Center, &label);
+    });
+    view_port.on_input({
+        let exit = exit.clone();
+        move |event| {
+            if event.key == Key::Back && event.kind == Kind::Short {
+                exit.store(true, Ordering::Relaxed);
+            }
+        }
+    });
+    view_port.attach_to_gui(GuiLayer::Fullscreen);
+
+    while !exit.load(Ordering::Relaxed) {
+        sleep(Duration::from_millis(100));
+    }
+
+    // Dropping the view port removes it from the GUI.
+    drop(view_port);
+
+    0
+}
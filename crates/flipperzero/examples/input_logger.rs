@@ -0,0 +1,77 @@
+//! Demonstrates subscribing to input events without a GUI.
+//!
+//! This app runs in the background and logs every button event to
+//! `/ext/input-log.txt`. Holding OK starts and stops logging, and holding Back exits.
+
+#![no_main]
+#![no_std]
+
+// Required for panic handler
+extern crate flipperzero_rt;
+
+// Required for allocator
+extern crate flipperzero_alloc;
+
+extern crate alloc;
+
+use alloc::format;
+use core::ffi::CStr;
+use core::time::Duration;
+
+use flipperzero::input::{self, InputEvent, Key, Kind};
+use flipperzero::io::*;
+use flipperzero::println;
+use flipperzero::storage::*;
+use flipperzero_rt::{entry, manifest};
+
+manifest!(name = "Rust input logger example");
+entry!(main);
+
+const LOG_PATH: &CStr = c"/ext/input-log.txt";
+
+fn log_event(file: &mut File, event: &InputEvent) -> Result<(), Error> {
+    let line = format!("{:?} {:?}\n", event.key, event.kind);
+    file.write_all(line.as_bytes())
+}
+
+fn main(_args: Option<&CStr>) -> i32 {
+    let events = input::subscribe_queue(8);
+    let mut log: Option<File> = None;
+
+    loop {
+        let Ok(event) = events.get(Duration::MAX) else {
+            continue;
+        };
+
+        match (event.key, event.kind) {
+            (Key::Ok, Kind::Long) => {
+                if log.take().is_some() {
+                    println!("stopped logging");
+                } else {
+                    match OpenOptions::new()
+                        .write(true)
+                        .open_append(true)
+                        .open(LOG_PATH)
+                    {
+                        Ok(file) => {
+                            println!("started logging");
+                            log = Some(file);
+                        }
+                        Err(e) => println!("couldn't open log: {}", e),
+                    }
+                }
+            }
+            (Key::Back, Kind::Long) => break,
+            _ => {
+                if let Some(file) = log.as_mut() {
+                    if let Err(e) = log_event(file, &event) {
+                        println!("couldn't write to log: {}", e);
+                        log = None;
+                    }
+                }
+            }
+        }
+    }
+
+    0
+}
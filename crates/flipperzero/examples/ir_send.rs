@@ -0,0 +1,43 @@
+//! Demonstrates sending an infrared signal from a file.
+//!
+//! This app sends the `POWER` signal of the `.ir` file given as argument, or of
+//! `/ext/infrared/TV.ir`, as saved by the Infrared app.
+
+#![no_main]
+#![no_std]
+
+// Required for panic handler
+extern crate flipperzero_rt;
+
+// Required for allocator
+extern crate flipperzero_alloc;
+
+use core::ffi::CStr;
+use core::sync::atomic::AtomicBool;
+
+use flipperzero::infrared;
+use flipperzero::println;
+use flipperzero_rt::{entry, manifest};
+
+manifest!(name = "Rust infrared send example");
+entry!(main);
+
+fn main(args: Option<&CStr>) -> i32 {
+    let path = args
+        .filter(|args| !args.is_empty())
+        .unwrap_or(c"/ext/infrared/TV.ir");
+
+    // Nothing cancels this short transmission, but a GUI app would set the flag when
+    // the user presses Back.
+    let cancel = AtomicBool::new(false);
+    match infrared::send_file(path, Some(c"POWER"), 1, &cancel) {
+        Ok(()) => {
+            println!("sent POWER");
+            0
+        }
+        Err(e) => {
+            println!("couldn't send POWER: {}", e);
+            1
+        }
+    }
+}
@@ -0,0 +1,67 @@
+//! Demonstrates streaming audio from the SD card to the speaker.
+//!
+//! This app plays the raw unsigned 8-bit mono samples at 8 kHz in the file given as
+//! argument, or `/ext/sound.raw`, showing the progress on screen. Pressing Back stops
+//! playback.
+//!
+//! Such a file can be made with e.g. `sox input.wav -r 8000 -c 1 -b 8 -e unsigned sound.raw`.
+
+#![no_main]
+#![no_std]
+
+// Required for panic handler
+extern crate flipperzero_rt;
+
+// Required for allocator
+extern crate flipperzero_alloc;
+
+use core::ffi::CStr;
+
+use flipperzero::audio::play_pcm;
+use flipperzero::gui::ProgressView;
+use flipperzero::io::*;
+use flipperzero::println;
+use flipperzero::storage::*;
+use flipperzero_rt::{entry, manifest};
+
+manifest!(name = "Rust PCM playback example");
+entry!(main);
+
+const DEFAULT_PATH: &CStr = c"/ext/sound.raw";
+const SAMPLE_RATE: u32 = 8000;
+
+fn main(args: Option<&CStr>) -> i32 {
+    let path = args.filter(|args| !args.is_empty()).unwrap_or(DEFAULT_PATH);
+    let mut file = match OpenOptions::new().read(true).open_existing(true).open(path) {
+        Ok(file) => file,
+        Err(e) => {
+            println!("couldn't open {}: {}", path.to_str().unwrap_or("file"), e);
+            return 1;
+        }
+    };
+    let total = file.stream_len().unwrap_or(0);
+
+    let view = ProgressView::new(c"Playing...");
+    let result = play_pcm(file, SAMPLE_RATE, |played| {
+        view.set_progress(played as usize, total);
+        !view.is_cancelled()
+    });
+    drop(view);
+
+    match result {
+        Ok(playback) => {
+            println!(
+                "played {} samples with {} underruns",
+                playback.samples, playback.underruns
+            );
+            if playback.cancelled {
+                println!("playback stopped");
+            }
+            0
+        }
+        Err(e) => {
+            println!("couldn't play: {}", e);
+            2
+        }
+    }
+}
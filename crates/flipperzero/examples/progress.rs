@@ -0,0 +1,147 @@
+//! Demonstrates use of the progress view for long storage operations.
+//!
+//! This app lets the user select a file, then copies it on a worker thread while showing
+//! the progress on screen, or unpacks it if it's a tar archive. Pressing Back cancels
+//! the operation.
+
+#![no_main]
+#![no_std]
+
+// Required for panic handler
+extern crate flipperzero_rt;
+
+// Required for allocator
+extern crate flipperzero_alloc;
+
+extern crate alloc;
+
+use alloc::ffi::CString;
+use core::ffi::CStr;
+
+use flipperzero::dialogs::{DialogsApp, FileBrowserOptions};
+use flipperzero::furi::thread;
+use flipperzero::gui::{ProgressHandle, ProgressView};
+use flipperzero::io::*;
+use flipperzero::println;
+use flipperzero::storage::tar::TarArchive;
+use flipperzero::storage::*;
+use flipperzero_rt::{entry, manifest};
+
+manifest!(name = "Rust progress view example");
+entry!(main);
+
+const DESTINATION: &CStr = c"/ext/progress-copy.bin";
+const UNPACK_DESTINATION: &CStr = c"/ext/progress-unpacked";
+
+/// A reader that reaches its end as soon as the operation is cancelled.
+struct Cancellable<'a, R> {
+    inner: R,
+    progress: &'a ProgressHandle,
+}
+
+impl<R: Read> Read for Cancellable<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        if self.progress.is_cancelled() {
+            Ok(0)
+        } else {
+            self.inner.read(buf)
+        }
+    }
+}
+
+/// Copies `source` to [`DESTINATION`], reporting progress to `progress`.
+fn copy(source: &CStr, progress: &ProgressHandle) -> Result<(), Error> {
+    let mut from = OpenOptions::new()
+        .read(true)
+        .open_existing(true)
+        .open(source)?;
+    let mut to = OpenOptions::new()
+        .write(true)
+        .create_always(true)
+        .open(DESTINATION)?;
+
+    let total = from.stream_len()?;
+    progress.set_progress(0, total);
+
+    let mut from = Cancellable {
+        inner: from,
+        progress,
+    };
+    let mut buf = [0; 512];
+    copy_with_progress(&mut from, &mut to, &mut buf, |copied| {
+        progress.set_progress(copied as usize, total)
+    })?;
+
+    Ok(())
+}
+
+/// Unpacks the archive at `source` into [`UNPACK_DESTINATION`], reporting progress to
+/// `progress`.
+///
+/// The archive only reports progress per entry, so this counts entries, not bytes. Once
+/// the operation is cancelled, the remaining entries are skipped.
+fn unpack(source: &CStr, progress: &ProgressHandle) -> Result<(), Error> {
+    let mut archive = TarArchive::open_read(source)?;
+    let total = archive.entry_count()?;
+    let mut done = 0;
+    progress.set_progress(done, total);
+
+    archive.unpack_to_with(UNPACK_DESTINATION, |_| {
+        if progress.is_cancelled() {
+            return false;
+        }
+        done += 1;
+        progress.set_progress(done, total);
+        true
+    })
+}
+
+fn main(_args: Option<&CStr>) -> i32 {
+    let mut dialogs = DialogsApp::open();
+    let options = FileBrowserOptions::new().set_hide_ext(false);
+    let source = match dialogs.show_file_browser(None, Some(&options)) {
+        Ok(Some(path)) => CString::from(path.as_c_str()),
+        _ => {
+            println!("no file selected");
+            return 0;
+        }
+    };
+
+    let is_archive = source.to_bytes().ends_with(b".tar");
+    let view = ProgressView::new(if is_archive {
+        c"Unpacking..."
+    } else {
+        c"Copying..."
+    });
+    view.on_cancel(|| println!("cancel requested"));
+
+    let progress = view.handle();
+    let worker = thread::spawn(move || {
+        let result = if is_archive {
+            unpack(&source, &progress)
+        } else {
+            copy(&source, &progress)
+        };
+        match result {
+            Ok(()) if progress.is_cancelled() => 1,
+            Ok(()) => 0,
+            Err(e) => {
+                println!("failed: {}", e);
+                2
+            }
+        }
+    });
+
+    let result = worker.join();
+    // The view is removed from the screen here, however the operation went.
+    drop(view);
+
+    match result {
+        0 if is_archive => println!("unpacked to {}", UNPACK_DESTINATION.to_str().unwrap()),
+        0 => println!("copied to {}", DESTINATION.to_str().unwrap()),
+        1 => println!("cancelled"),
+        _ => {}
+    }
+
+    0
+}
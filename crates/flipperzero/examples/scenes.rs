@@ -0,0 +1,102 @@
+//! Demonstrates use of the scene manager.
+//!
+//! This app shows a menu of two screens. Back returns from a screen to the menu, and
+//! exits from the menu. The app prints how many screens were opened when it exits.
+
+#![no_main]
+#![no_std]
+
+// Required for panic handler
+extern crate flipperzero_rt;
+
+// Required for allocator
+extern crate flipperzero_alloc;
+
+extern crate alloc;
+
+use core::ffi::CStr;
+
+use flipperzero::gui::canvas::{Align, Font};
+use flipperzero::gui::{
+    Scene, SceneContext, SceneEvent, SceneManager, Submenu, View, ViewDispatcher,
+    ViewDispatcherType,
+};
+use flipperzero::println;
+use flipperzero_rt::{entry, manifest};
+
+manifest!(name = "Rust scenes example");
+entry!(main);
+
+// The IDs of the scenes, which are also the IDs of the views they show.
+const MENU: u32 = 0;
+const HELLO: u32 = 1;
+const ABOUT: u32 = 2;
+
+/// The state of the app, shared by the scenes.
+struct App {
+    opened: u32,
+}
+
+struct MenuScene;
+
+impl Scene<App> for MenuScene {
+    fn on_enter(&mut self, scenes: &mut SceneContext<'_, App>) {
+        scenes.view_dispatcher().switch_to(MENU);
+    }
+
+    fn on_event(&mut self, scenes: &mut SceneContext<'_, App>, event: SceneEvent) -> bool {
+        match event {
+            // The menu sends the IDs of the scenes as custom events.
+            SceneEvent::Custom(scene) => {
+                scenes.next_scene(scene);
+                true
+            }
+            SceneEvent::Back => false,
+        }
+    }
+}
+
+/// A scene that shows its view, counting how often it was opened.
+struct ScreenScene(u32);
+
+impl Scene<App> for ScreenScene {
+    fn on_enter(&mut self, scenes: &mut SceneContext<'_, App>) {
+        scenes.state().opened += 1;
+        scenes.view_dispatcher().switch_to(self.0);
+    }
+}
+
+fn text_view(text: &'static CStr) -> View {
+    let mut view = View::new();
+    view.on_draw(move |canvas| {
+        canvas.set_font(Font::Primary);
+        canvas.draw_str_aligned(64, 32, Align::Center, Align::Center, text);
+    });
+    view
+}
+
+fn main(_args: Option<&CStr>) -> i32 {
+    let mut view_dispatcher = ViewDispatcher::new();
+
+    let mut menu = Submenu::new();
+    for (label, scene) in [(c"Hello", HELLO), (c"About", ABOUT)] {
+        let sender = view_dispatcher.custom_event_sender();
+        menu.add_item(label, scene, move |scene| {
+            sender.send(scene);
+        });
+    }
+    view_dispatcher.add_view(MENU, menu);
+    view_dispatcher.add_view(HELLO, text_view(c"Hello, Rust!"));
+    view_dispatcher.add_view(ABOUT, text_view(c"Made with scenes"));
+    view_dispatcher.attach_to_gui(ViewDispatcherType::Fullscreen);
+
+    let mut scenes = SceneManager::new(App { opened: 0 });
+    scenes.add_scene(MENU, MenuScene);
+    scenes.add_scene(HELLO, ScreenScene(HELLO));
+    scenes.add_scene(ABOUT, ScreenScene(ABOUT));
+    let app = scenes.run(&mut view_dispatcher, MENU);
+
+    println!("opened {} screens", app.opened);
+
+    0
+}
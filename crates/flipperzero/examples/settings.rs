@@ -0,0 +1,102 @@
+//! Demonstrates a settings screen whose values are persisted.
+//!
+//! This app shows three settings, which are saved to `/data/settings.txt` (the app's
+//! data directory) when the screen is closed, and loaded again on the next start.
+
+#![no_main]
+#![no_std]
+
+// Required for panic handler
+extern crate flipperzero_rt;
+
+// Required for allocator
+extern crate flipperzero_alloc;
+
+use core::ffi::CStr;
+
+use flipperzero::gui::SettingsScreen;
+use flipperzero::println;
+use flipperzero::storage::Settings;
+use flipperzero_rt::{entry, manifest};
+
+manifest!(name = "Rust settings example");
+entry!(main);
+
+#[derive(Clone, Copy)]
+enum Speed {
+    Slow,
+    Normal,
+    Fast,
+}
+
+impl Speed {
+    const ALL: [Speed; 3] = [Speed::Slow, Speed::Normal, Speed::Fast];
+    const LABELS: &'static [&'static CStr] = &[c"Slow", c"Normal", c"Fast"];
+
+    fn from_index(index: usize) -> Self {
+        Self::ALL.get(index).copied().unwrap_or(Speed::Normal)
+    }
+}
+
+struct Config {
+    speed: Speed,
+    sound: bool,
+    timeout: u32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            speed: Speed::Normal,
+            sound: true,
+            timeout: 30,
+        }
+    }
+}
+
+fn main(_args: Option<&CStr>) -> i32 {
+    let mut settings = Settings::new(
+        c"/data/settings.txt",
+        c"Rust Settings Example",
+        1,
+        Config::default(),
+    )
+    .field(
+        c"Speed",
+        |c| c.speed as u32,
+        |c, v| c.speed = Speed::from_index(v as usize),
+    )
+    .field(c"Sound", |c| c.sound.into(), |c, v| c.sound = v != 0)
+    .field(c"Timeout", |c| c.timeout, |c, v| c.timeout = v);
+
+    match settings.load() {
+        Ok(true) => println!("loaded settings"),
+        Ok(false) => println!("using default settings"),
+        Err(e) => println!("couldn't load settings: {}", e),
+    }
+
+    let result = SettingsScreen::<Config>::new()
+        .choice(
+            c"Speed",
+            Speed::LABELS,
+            |c| c.speed as usize,
+            |c, i| c.speed = Speed::from_index(i),
+        )
+        .toggle(c"Sound", |c| c.sound, |c, v| c.sound = v)
+        .number(
+            c"Timeout (s)",
+            5..=120,
+            5,
+            |c| c.timeout,
+            |c, v| c.timeout = v,
+        )
+        .show(&mut settings);
+
+    match result {
+        Ok(true) => println!("saved settings"),
+        Ok(false) => println!("settings unchanged"),
+        Err(e) => println!("couldn't save settings: {}", e),
+    }
+
+    0
+}
@@ -0,0 +1,81 @@
+//! Demonstrates dumping an SPI flash chip to the SD card.
+//!
+//! Connect a 25-series flash chip such as a W25Q16 to the external SPI pins (`A4`, `A6`,
+//! `A7` and `B3`), powered from 3.3V. The app detects the chip and copies its contents
+//! to a file, showing the progress on screen. Pressing Back cancels the dump.
+
+#![no_main]
+#![no_std]
+
+// Required for panic handler
+extern crate flipperzero_rt;
+
+// Required for allocator
+extern crate flipperzero_alloc;
+
+use core::ffi::CStr;
+
+use flipperzero::devices::SpiFlash;
+use flipperzero::gui::ProgressView;
+use flipperzero::io::*;
+use flipperzero::println;
+use flipperzero::storage::*;
+use flipperzero_rt::{entry, manifest};
+
+manifest!(name = "Rust SPI flash dump example");
+entry!(main);
+
+const DESTINATION: &CStr = c"/ext/flash.bin";
+
+/// Copies the whole chip to [`DESTINATION`], reporting progress to `view`.
+///
+/// Returns `false` if the dump was cancelled.
+fn dump(flash: &mut SpiFlash, view: &ProgressView) -> Result<bool, Error> {
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_always(true)
+        .open(DESTINATION)?;
+
+    let total = flash.capacity() as usize;
+    let mut done = 0;
+    let mut buf = [0; 4096];
+    view.set_progress(done, total);
+
+    while done < total {
+        if view.is_cancelled() {
+            return Ok(false);
+        }
+        let n = flash.read(&mut buf)?;
+        file.write_all(&buf[..n])?;
+        done += n;
+        view.set_progress(done, total);
+    }
+
+    Ok(true)
+}
+
+fn main(_args: Option<&CStr>) -> i32 {
+    let mut flash = match SpiFlash::detect() {
+        Ok(flash) => flash,
+        Err(e) => {
+            println!("no flash chip detected: {}", e);
+            return 1;
+        }
+    };
+    println!("found a {} KiB chip", flash.capacity() / 1024);
+
+    let view = ProgressView::new(c"Dumping flash...");
+    let result = dump(&mut flash, &view);
+    drop(view);
+
+    match result {
+        Ok(true) => println!("dumped to {}", DESTINATION.to_str().unwrap()),
+        Ok(false) => println!("dump cancelled"),
+        Err(e) => {
+            println!("dump failed: {}", e);
+            return 2;
+        }
+    }
+
+    0
+}
@@ -0,0 +1,103 @@
+//! Demonstrates drawing images loaded from the SD card.
+//!
+//! This app loads a 128x64 splash image from `splash.bm` in its data directory
+//! (`/ext/apps_data/splash`) and shows it for a few seconds. If the directory also
+//! contains animation frames (`frames/frame_0.bm`, `frames/frame_1.bm`, ...) of the same
+//! size, these are played afterwards.
+
+#![no_main]
+#![no_std]
+
+// Required for panic handler
+extern crate flipperzero_rt;
+
+// Required for allocator
+extern crate flipperzero_alloc;
+
+use core::ffi::{c_void, CStr};
+use core::ptr;
+use core::time::Duration;
+
+use flipperzero::furi::thread::sleep;
+use flipperzero::gui::canvas::{draw_loaded_image, Canvas};
+use flipperzero::gui::image::{BmImage, ImageAnimation};
+use flipperzero::println;
+use flipperzero_rt::{entry, manifest};
+use flipperzero_sys as sys;
+use flipperzero_sys::furi::UnsafeRecord;
+
+manifest!(name = "Rust splash example");
+entry!(main);
+
+const WIDTH: usize = 128;
+const HEIGHT: usize = 64;
+
+enum Screen {
+    Splash(BmImage),
+    Animation(ImageAnimation),
+}
+
+unsafe extern "C" fn draw_callback(canvas: *mut sys::Canvas, context: *mut c_void) {
+    let screen = unsafe { &*(context as *const Screen) };
+    let mut canvas = unsafe { Canvas::from_raw(canvas) };
+    match screen {
+        Screen::Splash(image) => draw_loaded_image(&mut canvas, 0, 0, image),
+        Screen::Animation(animation) => animation.draw(&mut canvas, 0, 0),
+    }
+}
+
+/// Shows `screen` for `duration`.
+fn show(gui: &UnsafeRecord<sys::Gui>, screen: &mut Screen, duration: Duration) {
+    unsafe {
+        let view_port = sys::view_port_alloc();
+        sys::view_port_draw_callback_set(
+            view_port,
+            Some(draw_callback),
+            screen as *mut Screen as *mut c_void,
+        );
+        sys::gui_add_view_port(gui.as_ptr(), view_port, sys::GuiLayer_GuiLayerFullscreen);
+
+        if let Screen::Animation(animation) = screen {
+            // The timer callback must be `Send`, so pass the pointer as an address.
+            let view_port = view_port as usize;
+            let started = animation.start(10, move || {
+                sys::view_port_update(view_port as *mut sys::ViewPort);
+            });
+            if started.is_err() {
+                println!("couldn't start animation");
+            }
+        }
+
+        sleep(duration);
+
+        if let Screen::Animation(animation) = screen {
+            // Make sure the timer no longer refers to the view port before freeing it.
+            animation.stop();
+        }
+        sys::view_port_enabled_set(view_port, false);
+        sys::gui_remove_view_port(gui.as_ptr(), view_port);
+        sys::view_port_draw_callback_set(view_port, None, ptr::null_mut());
+        sys::view_port_free(view_port);
+    }
+}
+
+fn main(_args: Option<&CStr>) -> i32 {
+    let gui = unsafe { UnsafeRecord::open(c"gui".as_ptr()) };
+
+    // `/data` refers to the data directory of the app.
+    match BmImage::load(c"/data/splash.bm", WIDTH, HEIGHT) {
+        Ok(image) => show(&gui, &mut Screen::Splash(image), Duration::from_secs(2)),
+        Err(e) => println!("couldn't load splash image: {}", e),
+    }
+
+    match ImageAnimation::load_dir(c"/data/frames", WIDTH, HEIGHT) {
+        Ok(animation) => show(
+            &gui,
+            &mut Screen::Animation(animation),
+            Duration::from_secs(5),
+        ),
+        Err(e) => println!("couldn't load animation: {}", e),
+    }
+
+    0
+}
@@ -1,6 +1,7 @@
 //! Storage example for Flipper Zero.
 //! This app writes "Hello, Rust!" to a file on the SD card. Then it opens a file browser dialog and
 //! lets the user select the file. Finally, it reads the file back and prints the contents to the console.
+//! Whether the file could be saved is signalled with the LED.
 
 #![no_main]
 #![no_std]
@@ -13,9 +14,10 @@ extern crate flipperzero_alloc;
 
 use core::ffi::CStr;
 
-use flipperzero::dialogs::{DialogFileBrowserOptions, DialogsApp};
+use flipperzero::dialogs::{DialogsApp, FileBrowserOptions};
 use flipperzero::furi::string::FuriString;
 use flipperzero::io::*;
+use flipperzero::notification::{feedback, NotificationService};
 use flipperzero::println;
 use flipperzero::storage::*;
 use flipperzero_rt::{entry, manifest};
@@ -31,22 +33,34 @@ fn main(_args: Option<&CStr>) -> i32 {
         .create_always(true)
         .open(path);
 
-    match file {
-        Ok(mut handle) => {
-            if let Err(e) = handle.write(b"Hello, Rust!") {
+    let saved = match file {
+        Ok(mut handle) => match handle.write_all(b"Hello, Rust!") {
+            Ok(()) => true,
+            Err(e) => {
                 println!("couldn't write to file: {}", e);
+                false
             }
+        },
+        Err(e) => {
+            println!("couldn't open path: {}", e);
+            false
         }
-        Err(e) => println!("couldn't open path: {}", e),
-    }
+    };
+
+    let mut notification = NotificationService::open();
+    notification.notify_blocking(if saved {
+        &feedback::SAVE_SUCCESS
+    } else {
+        &feedback::SAVE_ERROR
+    });
 
     // Next, we'll open a file browser dialog and let the user select the file.
     let mut dialogs_app = DialogsApp::open();
-    let file_browser_options = DialogFileBrowserOptions::new().set_hide_ext(false);
+    let file_browser_options = FileBrowserOptions::new().set_hide_ext(false);
     let mut start_path = FuriString::from(path);
     let result_path =
         dialogs_app.show_file_browser(Some(&mut start_path), Some(&file_browser_options));
-    if let Some(result_path) = result_path {
+    if let Ok(Some(result_path)) = result_path {
         println!("file selected {}", result_path);
         let path = result_path.as_c_str();
 
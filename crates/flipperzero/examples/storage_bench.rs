@@ -0,0 +1,166 @@
+//! Measures storage performance.
+//!
+//! This app writes and reads back a file on the internal flash and on the SD card with
+//! requests of several sizes, and prints the throughput of each as a table. This is how
+//! the sizes in `flipperzero::storage::perf` are chosen. The smallest sizes show the
+//! fixed cost of each `File` request. It then writes records of an odd size to the SD
+//! card through a `BufWriter`, with and without aligning its writes to sectors.
+//!
+//! Afterwards it lists the directory given as argument, or `/ext`, and creates a
+//! `Storage` handle for each entry, as a file browser would. The scan is repeated first with the
+//! storage record opened by every handle, then with it kept open by `storage::init`.
+
+#![no_main]
+#![no_std]
+
+// Required for panic handler
+extern crate flipperzero_rt;
+
+// Required for allocator
+extern crate flipperzero_alloc;
+
+extern crate alloc;
+
+use alloc::vec;
+use core::ffi::CStr;
+
+use flipperzero::furi::time::{Duration, Instant};
+use flipperzero::io::{Error, Read, Write};
+use flipperzero::println;
+use flipperzero::storage::perf::{EXT_CHUNK_SIZE, SD_SECTOR_SIZE};
+use flipperzero::storage::{self, BufWriter, OpenOptions, Storage};
+use flipperzero_rt::{entry, manifest};
+
+manifest!(name = "Rust storage benchmark example");
+entry!(main);
+
+const ROUNDS: u32 = 20;
+
+const CHUNK_SIZES: [usize; 9] = [16, 32, 64, 128, 256, 512, 1024, 2048, 4096];
+
+/// Size of the records written through a `BufWriter`, which doesn't divide a sector.
+const RECORD_SIZE: usize = 100;
+
+/// The file written on each backend, and its size.
+const BACKENDS: [(&CStr, usize); 2] = [
+    (c"/int/.storage_bench", 16 * 1024),
+    (c"/ext/.storage_bench", 256 * 1024),
+];
+
+fn main(args: Option<&CStr>) -> i32 {
+    let path = args.filter(|args| !args.is_empty()).unwrap_or(c"/ext");
+
+    println!("chunk size: write, read throughput");
+    for (file, size) in BACKENDS {
+        println!("{}", file.to_str().unwrap_or("file"));
+        for chunk_size in CHUNK_SIZES {
+            match throughput(file, size, chunk_size) {
+                Ok((write, read)) => println!("{} B: {} KiB/s, {} KiB/s", chunk_size, write, read),
+                Err(e) => println!("{} B: failed: {}", chunk_size, e),
+            }
+        }
+        let _ = Storage::open().remove(file);
+    }
+
+    let (file, size) = BACKENDS[1];
+    println!("{} B records through a BufWriter", RECORD_SIZE);
+    for (name, align) in [("unaligned", 0), ("aligned", SD_SECTOR_SIZE)] {
+        match buffered_throughput(file, size, align) {
+            Ok(write) => println!("{}: {} KiB/s", name, write),
+            Err(e) => println!("{}: failed: {}", name, e),
+        }
+    }
+    let _ = Storage::open().remove(file);
+
+    let without = match scan(path) {
+        Ok(elapsed) => elapsed,
+        Err(e) => {
+            println!("couldn't scan the directory: {}", e);
+            return 1;
+        }
+    };
+
+    storage::init();
+    let with = scan(path);
+    storage::deinit();
+    let with = match with {
+        Ok(elapsed) => elapsed,
+        Err(e) => {
+            println!("couldn't scan the directory: {}", e);
+            return 1;
+        }
+    };
+
+    println!(
+        "{} scans: {} ms without storage::init, {} ms with it",
+        ROUNDS,
+        without.as_millis(),
+        with.as_millis()
+    );
+    0
+}
+
+/// Writes `size` bytes to `path` and reads them back, `chunk_size` bytes at a time,
+/// returning the write and read throughput in KiB/s.
+fn throughput(path: &CStr, size: usize, chunk_size: usize) -> Result<(u64, u64), Error> {
+    let mut chunk = vec![0x55; chunk_size];
+
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_always(true)
+        .open(path)?;
+    let start = Instant::now();
+    for _ in 0..size / chunk_size {
+        file.write_all(&chunk)?;
+    }
+    file.sync()?;
+    let write = Instant::now().duration_since(start);
+    drop(file);
+
+    let mut file = OpenOptions::new()
+        .read(true)
+        .open_existing(true)
+        .open(path)?;
+    let start = Instant::now();
+    while file.read(&mut chunk)? > 0 {}
+    let read = Instant::now().duration_since(start);
+
+    Ok((kib_per_sec(size, write), kib_per_sec(size, read)))
+}
+
+/// Writes `size` bytes to `path` in records of [`RECORD_SIZE`] bytes through a
+/// `BufWriter` aligned to `align`, returning the throughput in KiB/s.
+fn buffered_throughput(path: &CStr, size: usize, align: usize) -> Result<u64, Error> {
+    let record = [0x55; RECORD_SIZE];
+
+    let file = OpenOptions::new()
+        .write(true)
+        .create_always(true)
+        .open(path)?;
+    let mut writer = BufWriter::with_capacity(EXT_CHUNK_SIZE, file).align_to(align);
+    let start = Instant::now();
+    for _ in 0..size / RECORD_SIZE {
+        writer.write_all(&record)?;
+    }
+    writer.into_inner()?.sync()?;
+    let write = Instant::now().duration_since(start);
+
+    Ok(kib_per_sec(size, write))
+}
+
+fn kib_per_sec(size: usize, elapsed: Duration) -> u64 {
+    size as u64 * 1000 / 1024 / elapsed.as_millis().max(1)
+}
+
+/// Scans `path` [`ROUNDS`] times, with handles created one at a time.
+fn scan(path: &CStr) -> Result<Duration, Error> {
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        let entries = Storage::open().read_dir(path)?;
+        for entry in entries {
+            entry?;
+            drop(Storage::open());
+        }
+    }
+    Ok(Instant::now().duration_since(start))
+}
@@ -0,0 +1,92 @@
+//! Demonstrates subscribing to the events of the storage service.
+//!
+//! This app forwards the SD card events and the button events into one queue, and
+//! prints whenever the SD card is inserted or removed. Holding Back exits.
+
+#![no_main]
+#![no_std]
+
+// Required for panic handler
+extern crate flipperzero_rt;
+
+// Required for allocator
+extern crate flipperzero_alloc;
+
+extern crate alloc;
+
+use alloc::sync::Arc;
+use core::ffi::CStr;
+use core::time::Duration;
+
+use flipperzero::furi::message_queue::MessageQueue;
+use flipperzero::input::{self, InputEvent, Key, Kind};
+use flipperzero::println;
+use flipperzero::storage::{SdStatus, Storage, StorageEvent};
+use flipperzero_rt::{entry, manifest};
+
+manifest!(name = "Rust storage events example");
+entry!(main);
+
+/// The events handled by the app.
+enum AppEvent {
+    Storage(StorageEvent),
+    Input(InputEvent),
+}
+
+fn print_free_space(storage: &Storage) {
+    match storage.fs_info(c"/ext") {
+        Ok(info) => println!("{} of {} bytes free", info.free_space, info.total_space),
+        Err(e) => println!("couldn't get SD card info: {}", e),
+    }
+}
+
+fn main(_args: Option<&CStr>) -> i32 {
+    let storage = Storage::open();
+    match storage.sd_status() {
+        SdStatus::Ok => print_free_space(&storage),
+        _ => println!("no SD card, insert one"),
+    }
+
+    // Both callbacks run on the threads of their services, so they only forward the
+    // events; the queue is read on the app's thread.
+    let queue = Arc::new(MessageQueue::new(8));
+    let _storage_events = storage.subscribe({
+        let queue = queue.clone();
+        move |event: StorageEvent| {
+            if event.is_card_event() {
+                let _ = queue.put(AppEvent::Storage(event), Duration::ZERO);
+            }
+        }
+    });
+    let _input_events = input::subscribe({
+        let queue = queue.clone();
+        move |event| {
+            let _ = queue.put(AppEvent::Input(event), Duration::ZERO);
+        }
+    });
+
+    loop {
+        let Ok(event) = queue.get(Duration::MAX) else {
+            continue;
+        };
+
+        match event {
+            AppEvent::Storage(StorageEvent::CardMounted) => {
+                println!("SD card inserted");
+                print_free_space(&storage);
+            }
+            AppEvent::Storage(StorageEvent::CardUnmounted) => println!("SD card removed"),
+            AppEvent::Storage(StorageEvent::CardMountError) => {
+                println!("couldn't mount the SD card")
+            }
+            AppEvent::Storage(_) => {}
+            AppEvent::Input(InputEvent {
+                key: Key::Back,
+                kind: Kind::Long,
+            }) => break,
+            AppEvent::Input(_) => {}
+        }
+    }
+
+    0
+}
@@ -0,0 +1,61 @@
+//! Demonstrates use of the scrollable text viewer.
+//!
+//! This app lets the user select a text file and shows its contents. Only the visible
+//! part of the file is kept in memory, so large files can be viewed too.
+
+#![no_main]
+#![no_std]
+
+// Required for panic handler
+extern crate flipperzero_rt;
+
+// Required for allocator
+extern crate flipperzero_alloc;
+
+use core::ffi::CStr;
+
+use flipperzero::dialogs::{DialogsApp, FileBrowserOptions};
+use flipperzero::gui::TextViewer;
+use flipperzero::println;
+use flipperzero::storage::*;
+use flipperzero_rt::{entry, manifest};
+
+manifest!(name = "Rust text viewer example");
+entry!(main);
+
+fn main(_args: Option<&CStr>) -> i32 {
+    let mut dialogs = DialogsApp::open();
+    // SAFETY: the string is valid UTF-8.
+    let options = unsafe { FileBrowserOptions::with_extension(c"txt") }.set_hide_ext(false);
+    let Ok(Some(path)) = dialogs.show_file_browser(None, Some(&options)) else {
+        println!("no file selected");
+        return 0;
+    };
+
+    let file = match OpenOptions::new()
+        .read(true)
+        .open_existing(true)
+        .open(path.as_c_str())
+    {
+        Ok(file) => file,
+        Err(e) => {
+            println!("couldn't open path: {}", e);
+            return 1;
+        }
+    };
+
+    // Show only the file name as the title.
+    let name = path
+        .to_bytes()
+        .rsplit(|&b| b == b'/')
+        .next()
+        .unwrap_or_default();
+    let title = core::str::from_utf8(name).unwrap_or("Text");
+
+    if let Err(e) = TextViewer::show(file, title) {
+        println!("couldn't read file: {}", e);
+        return 1;
+    }
+
+    0
+}
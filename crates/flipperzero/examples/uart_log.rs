@@ -0,0 +1,70 @@
+//! Demonstrates reading from a serial port.
+//!
+//! This app appends the bytes received on the LPUART (pins 15 and 16 of the GPIO
+//! header) at 115200 baud to `/ext/uart-log.txt`, and exits once nothing has been
+//! received for 30 seconds.
+
+#![no_main]
+#![no_std]
+
+// Required for panic handler
+extern crate flipperzero_rt;
+
+// Required for allocator
+extern crate flipperzero_alloc;
+
+use core::ffi::CStr;
+use core::time::Duration;
+
+use flipperzero::io::*;
+use flipperzero::println;
+use flipperzero::serial::{Uart, UartChannel};
+use flipperzero::storage::*;
+use flipperzero_rt::{entry, manifest};
+
+manifest!(name = "Rust UART log example");
+entry!(main);
+
+fn log(uart: &mut Uart, file: &mut File) -> Result<usize, Error> {
+    let mut buf = [0; 64];
+    let mut logged = 0;
+    loop {
+        let n = uart.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        file.write_all(&buf[..n])?;
+        logged += n;
+    }
+    Ok(logged)
+}
+
+fn main(_args: Option<&CStr>) -> i32 {
+    let mut uart = match Uart::open(UartChannel::Lpuart, 115200) {
+        Ok(uart) => uart,
+        Err(e) => {
+            println!("couldn't open UART: {}", e);
+            return 1;
+        }
+    };
+    uart.set_read_timeout(Duration::from_secs(30));
+
+    let mut file = match OpenOptions::new()
+        .write(true)
+        .open_append(true)
+        .open(c"/ext/uart-log.txt")
+    {
+        Ok(file) => file,
+        Err(e) => {
+            println!("couldn't open log: {}", e);
+            return 1;
+        }
+    };
+
+    match log(&mut uart, &mut file) {
+        Ok(n) => println!("logged {} bytes", n),
+        Err(e) => println!("couldn't write to log: {}", e),
+    }
+
+    0
+}
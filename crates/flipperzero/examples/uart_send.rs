@@ -0,0 +1,68 @@
+//! Demonstrates writing to a serial port.
+//!
+//! This app sends the contents of `/ext/uart-send.txt` over the LPUART (pins 15 and 16
+//! of the GPIO header) at 115200 baud.
+
+#![no_main]
+#![no_std]
+
+// Required for panic handler
+extern crate flipperzero_rt;
+
+// Required for allocator
+extern crate flipperzero_alloc;
+
+use core::ffi::CStr;
+
+use flipperzero::io::*;
+use flipperzero::println;
+use flipperzero::serial::{Uart, UartChannel};
+use flipperzero::storage::*;
+use flipperzero_rt::{entry, manifest};
+
+manifest!(name = "Rust UART send example");
+entry!(main);
+
+fn send(file: &mut File, uart: &mut Uart) -> Result<usize, Error> {
+    let mut buf = [0; 64];
+    let mut sent = 0;
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        uart.write_all(&buf[..n])?;
+        sent += n;
+    }
+    uart.flush()?;
+    Ok(sent)
+}
+
+fn main(_args: Option<&CStr>) -> i32 {
+    let mut file = match OpenOptions::new()
+        .read(true)
+        .open_existing(true)
+        .open(c"/ext/uart-send.txt")
+    {
+        Ok(file) => file,
+        Err(e) => {
+            println!("couldn't open file: {}", e);
+            return 1;
+        }
+    };
+
+    let mut uart = match Uart::open(UartChannel::Lpuart, 115200) {
+        Ok(uart) => uart,
+        Err(e) => {
+            println!("couldn't open UART: {}", e);
+            return 1;
+        }
+    };
+
+    match send(&mut file, &mut uart) {
+        Ok(n) => println!("sent {} bytes", n),
+        Err(e) => println!("couldn't send file: {}", e),
+    }
+
+    0
+}
@@ -0,0 +1,63 @@
+//! Demonstrates USB mass storage mode.
+//!
+//! This app presents the disk image given as argument, or `/ext/disk.img`, to the
+//! computer as a USB drive, until the drive is ejected. An image can be created on a
+//! computer with `mkfs.vfat -C disk.img 65536` and copied to the SD card.
+
+#![no_main]
+#![no_std]
+
+// Required for panic handler
+extern crate flipperzero_rt;
+
+// Required for allocator
+extern crate flipperzero_alloc;
+
+use core::ffi::CStr;
+use core::time::Duration;
+
+use flipperzero::furi::thread::sleep;
+use flipperzero::println;
+use flipperzero::usb::{Activity, MassStorage};
+use flipperzero_rt::{entry, manifest};
+
+manifest!(name = "Rust USB drive example");
+entry!(main);
+
+fn main(args: Option<&CStr>) -> i32 {
+    let path = args
+        .filter(|args| !args.is_empty())
+        .unwrap_or(c"/ext/disk.img");
+
+    let session = match MassStorage::start(path) {
+        Ok(session) => session,
+        Err(e) => {
+            println!("couldn't start mass storage: {}", e);
+            return 1;
+        }
+    };
+
+    let mut reported = Activity::default();
+    while !session.is_ejected() {
+        let activity = session.activity();
+        if activity != reported {
+            println!(
+                "read {} blocks, wrote {} blocks",
+                activity.blocks_read, activity.blocks_written
+            );
+            reported = activity;
+        }
+        sleep(Duration::from_secs(1));
+    }
+
+    match session.stop() {
+        Ok(()) => {
+            println!("drive ejected");
+            0
+        }
+        Err(e) => {
+            println!("couldn't sync the image: {}", e);
+            1
+        }
+    }
+}
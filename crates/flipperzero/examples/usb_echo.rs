@@ -0,0 +1,54 @@
+//! Demonstrates a USB virtual serial port.
+//!
+//! This app switches USB to two virtual serial ports, and echoes everything received on
+//! the second one until the host closes it. The first port keeps running the CLI.
+
+#![no_main]
+#![no_std]
+
+// Required for panic handler
+extern crate flipperzero_rt;
+
+// Required for allocator
+extern crate flipperzero_alloc;
+
+use core::ffi::CStr;
+use core::time::Duration;
+
+use flipperzero::io::*;
+use flipperzero::println;
+use flipperzero::usb::{CdcInterface, CdcPort};
+use flipperzero_rt::{entry, manifest};
+
+manifest!(name = "Rust USB echo example");
+entry!(main);
+
+fn echo(port: &mut CdcPort) -> Result<(), Error> {
+    let mut buf = [0; 64];
+    loop {
+        let n = port.read(&mut buf)?;
+        port.write_all(&buf[..n])?;
+    }
+}
+
+fn main(_args: Option<&CStr>) -> i32 {
+    let mut port = match CdcPort::open(CdcInterface::Secondary) {
+        Ok(port) => port,
+        Err(e) => {
+            println!("couldn't open USB port: {}", e);
+            return 1;
+        }
+    };
+    port.set_read_timeout(Duration::MAX);
+
+    // Echoing only stops with an error, normally because the host closed the port.
+    if let Err(e) = echo(&mut port) {
+        if port.is_connected() {
+            println!("couldn't echo: {}", e);
+        } else {
+            println!("host disconnected");
+        }
+    }
+
+    0
+}
@@ -0,0 +1,48 @@
+//! Demonstrates walking a directory tree.
+//!
+//! This app collects the paths of all `.sub` files below the directory given as
+//! argument, or `/ext/subghz`, including nested folders, and prints them to the
+//! console. Folders that can't be read are reported and skipped.
+
+#![no_main]
+#![no_std]
+
+// Required for panic handler
+extern crate flipperzero_rt;
+
+// Required for allocator
+extern crate flipperzero_alloc;
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::ffi::CStr;
+
+use flipperzero::println;
+use flipperzero::storage::{PathBuf, Storage, WalkDir};
+use flipperzero_rt::{entry, manifest};
+
+manifest!(name = "Rust directory walk example");
+entry!(main);
+
+fn main(args: Option<&CStr>) -> i32 {
+    let root = args
+        .filter(|args| !args.is_empty())
+        .unwrap_or(c"/ext/subghz");
+
+    let storage = Storage::open();
+    let mut found: Vec<PathBuf> = Vec::new();
+    for entry in WalkDir::new(&storage, root).dirs(false) {
+        match entry {
+            Ok(entry) if entry.has_extension("sub") => found.push(entry.into_path()),
+            Ok(_) => {}
+            Err(e) => println!("skipping: {}", e),
+        }
+    }
+
+    println!("found {} files:", found.len());
+    for path in &found {
+        println!("  {}", path);
+    }
+    0
+}
@@ -0,0 +1,52 @@
+//! Demonstrates receiving files with YMODEM.
+//!
+//! This app switches USB to two virtual serial ports, and saves the text files sent
+//! with YMODEM on the second one (for example with `sb --ymodem`) to the SD card.
+
+#![no_main]
+#![no_std]
+
+// Required for panic handler
+extern crate flipperzero_rt;
+
+// Required for allocator
+extern crate flipperzero_alloc;
+
+use core::ffi::CStr;
+use core::time::Duration;
+
+use flipperzero::println;
+use flipperzero::protocols::ymodem;
+use flipperzero::usb::{CdcInterface, CdcPort};
+use flipperzero_rt::{entry, manifest};
+
+manifest!(name = "Rust YMODEM example");
+entry!(main);
+
+/// Largest file that is accepted.
+const MAX_SIZE: u64 = 64 * 1024;
+
+fn main(_args: Option<&CStr>) -> i32 {
+    let mut port = match CdcPort::open(CdcInterface::Secondary) {
+        Ok(port) => port,
+        Err(e) => {
+            println!("couldn't open USB port: {}", e);
+            return 1;
+        }
+    };
+    port.set_read_timeout(Duration::from_secs(1));
+
+    let result = ymodem::receive_batch(&mut port, c"/ext", |name, size| {
+        name.ends_with(".txt") && size <= MAX_SIZE
+    });
+    match result {
+        Ok(paths) => {
+            for path in paths {
+                println!("saved {}", path.to_str().unwrap_or("?"));
+            }
+        }
+        Err(e) => println!("transfer failed: {}", e),
+    }
+
+    0
+}
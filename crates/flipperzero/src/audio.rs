@@ -0,0 +1,422 @@
+//! Audio playback on the speaker of the Flipper Zero.
+//!
+//! The speaker is driven by a PWM timer, so it can only play tones through the HAL,
+//! which [`Speaker`] does, including melodies of [`Note`]s.
+//! [`play_pcm`] plays samples anyway by running the PWM at an inaudible carrier
+//! frequency and changing its duty cycle at the sample rate, which the speaker smooths
+//! into the waveform. The quality is about that of a telephone.
+
+use core::ffi::c_void;
+use core::marker::PhantomData;
+use core::ptr::{self, addr_of, addr_of_mut, NonNull};
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use core::time::Duration;
+
+use flipperzero_sys as sys;
+use flipperzero_sys::furi::duration_to_ticks;
+
+use crate::furi;
+use crate::io::{Error, Read};
+
+/// Highest sample rate supported by [`play_pcm`], in Hz.
+pub const MAX_SAMPLE_RATE: u32 = 16_000;
+
+/// Frequency of the PWM carrier, in Hz.
+const CARRIER_FREQUENCY: f32 = 62_500.0;
+/// Number of samples read from the source at once.
+///
+/// The buffer holds two chunks, so one can be read while the other is played.
+const CHUNK_SIZE: usize = 512;
+/// The sample the speaker rests at, which is also played on underruns.
+const SILENCE: u8 = 0x80;
+
+/// TIM16, which drives the speaker.
+const SPEAKER_TIMER: *mut sys::TIM_TypeDef = 0x4001_4400 as *mut sys::TIM_TypeDef;
+/// TIM17, which paces the samples.
+const SAMPLE_TIMER: *mut sys::TIM_TypeDef = 0x4001_4800 as *mut sys::TIM_TypeDef;
+const TIM_CR1_CEN: u32 = 1 << 0;
+const TIM_DIER_UIE: u32 = 1 << 0;
+const TIM_SR_UIF: u32 = 1 << 0;
+const TIM_EGR_UG: u32 = 1 << 0;
+
+/// A note of a melody played by [`Speaker::play`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Note {
+    /// Frequency in Hz, or 0 for a rest.
+    pub frequency: f32,
+    /// How long the note is held.
+    pub duration: Duration,
+}
+
+impl Note {
+    /// Creates a note of `frequency` Hz held for `duration`.
+    pub const fn new(frequency: f32, duration: Duration) -> Self {
+        Self {
+            frequency,
+            duration,
+        }
+    }
+
+    /// Creates a rest of `duration`.
+    pub const fn rest(duration: Duration) -> Self {
+        Self::new(0.0, duration)
+    }
+}
+
+/// Exclusive use of the speaker, which plays tones.
+///
+/// The speaker is shared by all apps and services, such as the notification service
+/// playing sounds. It is released when the `Speaker` is dropped. The firmware tracks
+/// the thread that acquired the speaker, so it must be used and dropped on that thread.
+///
+/// # Examples
+///
+/// ```no_run
+/// use core::time::Duration;
+///
+/// use flipperzero::audio::{Note, Speaker};
+///
+/// const BEAT: Duration = Duration::from_millis(150);
+///
+/// let mut speaker = Speaker::acquire(Duration::from_millis(100)).unwrap();
+/// speaker.play(
+///     &[
+///         Note::new(523.25, BEAT),
+///         Note::rest(BEAT),
+///         Note::new(659.25, BEAT),
+///         Note::new(783.99, 2 * BEAT),
+///     ],
+///     0.5,
+/// );
+/// ```
+pub struct Speaker {
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Speaker {
+    /// Acquires the speaker, waiting up to `timeout` for it to be released.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::AlreadyOpen`] if the speaker is still in use after `timeout`,
+    /// including by a `Speaker` of the calling thread.
+    pub fn acquire(timeout: Duration) -> Result<Self, Error> {
+        let timeout = timeout.as_millis().try_into().unwrap_or(u32::MAX);
+        if unsafe { sys::furi_hal_speaker_acquire(timeout) } {
+            Ok(Self {
+                _not_send: PhantomData,
+            })
+        } else {
+            Err(Error::AlreadyOpen)
+        }
+    }
+
+    /// Starts playing a tone of `frequency` Hz at `volume` between 0 and 1, until it is
+    /// stopped or another tone is started.
+    ///
+    /// A frequency or volume of 0 stops the speaker instead.
+    pub fn start(&mut self, frequency: f32, volume: f32) {
+        if frequency > 0.0 && volume > 0.0 {
+            unsafe { sys::furi_hal_speaker_start(frequency, volume.min(1.0)) };
+        } else {
+            self.stop();
+        }
+    }
+
+    /// Changes the volume of the playing tone, between 0 and 1.
+    pub fn set_volume(&mut self, volume: f32) {
+        unsafe { sys::furi_hal_speaker_set_volume(volume.clamp(0.0, 1.0)) };
+    }
+
+    /// Stops the playing tone.
+    pub fn stop(&mut self) {
+        unsafe { sys::furi_hal_speaker_stop() };
+    }
+
+    /// Plays `notes` one after another at `volume` between 0 and 1, blocking until the
+    /// last note has ended.
+    pub fn play(&mut self, notes: &[Note], volume: f32) {
+        for note in notes {
+            self.start(note.frequency, volume);
+            furi::thread::sleep(note.duration);
+        }
+        self.stop();
+    }
+}
+
+impl Drop for Speaker {
+    fn drop(&mut self) {
+        unsafe {
+            sys::furi_hal_speaker_stop();
+            sys::furi_hal_speaker_release();
+        }
+    }
+}
+
+/// The result of [`play_pcm`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Playback {
+    /// Number of samples played.
+    pub samples: u64,
+    /// Number of times a sample was due before it was read, usually because the source
+    /// was too slow.
+    ///
+    /// Silence is played instead of the missing samples.
+    pub underruns: u32,
+    /// Whether playback was stopped by the progress callback.
+    pub cancelled: bool,
+}
+
+/// State shared with the sample interrupt.
+struct Shared {
+    samples: NonNull<sys::FuriStreamBuffer>,
+    /// Compare value of the speaker timer for the largest sample.
+    full_scale: u32,
+    /// Whether the source has ended, so running out of samples isn't an underrun.
+    finished: AtomicBool,
+    played: AtomicU32,
+    underruns: AtomicU32,
+}
+
+/// Plays the next sample, in interrupt context.
+unsafe extern "C" fn sample_isr(context: *mut c_void) {
+    let shared = unsafe { &*(context as *const Shared) };
+    unsafe {
+        if ptr::read_volatile(addr_of!((*SAMPLE_TIMER).SR)) & TIM_SR_UIF == 0 {
+            return;
+        }
+        ptr::write_volatile(addr_of_mut!((*SAMPLE_TIMER).SR), !TIM_SR_UIF);
+    }
+
+    let mut sample = SILENCE;
+    let received = unsafe {
+        sys::furi_stream_buffer_receive(
+            shared.samples.as_ptr(),
+            &mut sample as *mut u8 as *mut c_void,
+            1,
+            0,
+        )
+    };
+    if received == 1 {
+        shared.played.fetch_add(1, Ordering::Relaxed);
+    } else if !shared.finished.load(Ordering::Relaxed) {
+        shared.underruns.fetch_add(1, Ordering::Relaxed);
+    }
+
+    let compare = u32::from(sample) * shared.full_scale / u32::from(u8::MAX);
+    unsafe { ptr::write_volatile(addr_of_mut!((*SPEAKER_TIMER).CCR1), compare) };
+}
+
+/// Plays unsigned 8-bit mono PCM samples from `samples` at `sample_rate`, such as the
+/// data of an 8-bit WAV file.
+///
+/// Samples are read in chunks while the previous chunk plays, so the source only has
+/// to keep up on average. `on_progress` is called with the number of samples played
+/// after each chunk, and stops playback when it returns `false`, for example once the
+/// user pressed Back.
+///
+/// This blocks until all samples have been played or playback was stopped.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidParameter`] if `sample_rate` is zero or above
+/// [`MAX_SAMPLE_RATE`], [`Error::AlreadyOpen`] if the speaker or the timer pacing the
+/// samples is in use, and any error from reading `samples`, which stops playback.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use flipperzero::audio::play_pcm;
+/// # use flipperzero::io::Error;
+/// # use flipperzero::storage::OpenOptions;
+/// # fn main() -> Result<(), Error> {
+/// let file = OpenOptions::new()
+///     .read(true)
+///     .open_existing(true)
+///     .open(c"/ext/sound.raw")?;
+/// let playback = play_pcm(file, 8000, |_| true)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn play_pcm<R, F>(
+    mut samples: R,
+    sample_rate: u32,
+    mut on_progress: F,
+) -> Result<Playback, Error>
+where
+    R: Read,
+    F: FnMut(u64) -> bool,
+{
+    if !(1..=MAX_SAMPLE_RATE).contains(&sample_rate) {
+        return Err(Error::InvalidParameter);
+    }
+    if unsafe { sys::furi_hal_bus_is_enabled(sys::FuriHalBus_FuriHalBusTIM17) } {
+        return Err(Error::AlreadyOpen);
+    }
+    let mut speaker = Speaker::acquire(Duration::from_secs(1))?;
+
+    speaker.start(CARRIER_FREQUENCY, 1.0);
+    let full_scale = unsafe { ptr::read_volatile(addr_of!((*SPEAKER_TIMER).ARR)) } / 2;
+    let shared = Shared {
+        samples: unsafe {
+            NonNull::new_unchecked(sys::furi_stream_buffer_alloc(2 * CHUNK_SIZE, 1))
+        },
+        full_scale,
+        finished: AtomicBool::new(false),
+        played: AtomicU32::new(0),
+        underruns: AtomicU32::new(0),
+    };
+
+    let result = stream(&shared, &mut samples, sample_rate, &mut on_progress);
+
+    stop_sample_timer();
+    drop(speaker);
+    unsafe { sys::furi_stream_buffer_free(shared.samples.as_ptr()) };
+
+    result.map(|cancelled| Playback {
+        samples: shared.played.load(Ordering::Relaxed).into(),
+        underruns: shared.underruns.load(Ordering::Relaxed),
+        cancelled,
+    })
+}
+
+/// Feeds the samples into the buffer until they end, returning whether playback was
+/// cancelled.
+fn stream<R, F>(
+    shared: &Shared,
+    samples: &mut R,
+    sample_rate: u32,
+    on_progress: &mut F,
+) -> Result<bool, Error>
+where
+    R: Read,
+    F: FnMut(u64) -> bool,
+{
+    let mut chunk = [0; CHUNK_SIZE];
+    let mut started = false;
+    loop {
+        let n = samples.read(&mut chunk)?;
+        if n == 0 {
+            break;
+        }
+        let mut sent = 0;
+        while sent < n {
+            sent += unsafe {
+                sys::furi_stream_buffer_send(
+                    shared.samples.as_ptr(),
+                    chunk[sent..n].as_ptr() as *const c_void,
+                    n - sent,
+                    duration_to_ticks(Duration::from_millis(100)),
+                )
+            };
+        }
+
+        // Start once the buffer is full, so playback doesn't begin with an underrun.
+        if !started
+            && unsafe { sys::furi_stream_buffer_spaces_available(shared.samples.as_ptr()) }
+                < CHUNK_SIZE
+        {
+            start_sample_timer(shared, sample_rate);
+            started = true;
+        }
+        if !on_progress(shared.played.load(Ordering::Relaxed).into()) {
+            return Ok(true);
+        }
+    }
+
+    shared.finished.store(true, Ordering::Relaxed);
+    if !started {
+        start_sample_timer(shared, sample_rate);
+    }
+    while !unsafe { sys::furi_stream_buffer_is_empty(shared.samples.as_ptr()) } {
+        if !on_progress(shared.played.load(Ordering::Relaxed).into()) {
+            return Ok(true);
+        }
+        unsafe { sys::furi_delay_ms(10) };
+    }
+    on_progress(shared.played.load(Ordering::Relaxed).into());
+    Ok(false)
+}
+
+/// Returns the prescaler and reload values of a timer counting at `clock` Hz to
+/// overflow at `rate` Hz.
+///
+/// The reload value of TIM17 has 16 bits, so the timer is slowed down by the prescaler
+/// for rates that take more than `u16::MAX` ticks of the clock.
+fn timer_config(clock: u32, rate: u32) -> (u32, u32) {
+    let ticks = clock / rate;
+    let prescaler = (ticks - 1) / (u32::from(u16::MAX) + 1);
+    (prescaler, ticks / (prescaler + 1) - 1)
+}
+
+/// Starts TIM17 interrupting at `sample_rate`.
+fn start_sample_timer(shared: &Shared, sample_rate: u32) {
+    let (prescaler, reload) = timer_config(unsafe { sys::SystemCoreClock }, sample_rate);
+    unsafe {
+        sys::furi_hal_bus_enable(sys::FuriHalBus_FuriHalBusTIM17);
+        ptr::write_volatile(addr_of_mut!((*SAMPLE_TIMER).PSC), prescaler);
+        ptr::write_volatile(addr_of_mut!((*SAMPLE_TIMER).ARR), reload);
+        ptr::write_volatile(addr_of_mut!((*SAMPLE_TIMER).EGR), TIM_EGR_UG);
+        ptr::write_volatile(addr_of_mut!((*SAMPLE_TIMER).SR), 0);
+        sys::furi_hal_interrupt_set_isr(
+            sys::FuriHalInterruptId_FuriHalInterruptIdTim1TrgComTim17,
+            Some(sample_isr),
+            shared as *const Shared as *mut c_void,
+        );
+        ptr::write_volatile(addr_of_mut!((*SAMPLE_TIMER).DIER), TIM_DIER_UIE);
+        ptr::write_volatile(addr_of_mut!((*SAMPLE_TIMER).CR1), TIM_CR1_CEN);
+    }
+}
+
+/// Stops TIM17 if it was started.
+fn stop_sample_timer() {
+    unsafe {
+        if !sys::furi_hal_bus_is_enabled(sys::FuriHalBus_FuriHalBusTIM17) {
+            return;
+        }
+        ptr::write_volatile(addr_of_mut!((*SAMPLE_TIMER).CR1), 0);
+        ptr::write_volatile(addr_of_mut!((*SAMPLE_TIMER).DIER), 0);
+        sys::furi_hal_interrupt_set_isr(
+            sys::FuriHalInterruptId_FuriHalInterruptIdTim1TrgComTim17,
+            None,
+            ptr::null_mut(),
+        );
+        sys::furi_hal_bus_disable(sys::FuriHalBus_FuriHalBusTIM17);
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use core::time::Duration;
+
+    use super::{timer_config, Note, Speaker};
+    use crate::io::Error;
+
+    #[test]
+    fn low_sample_rates_use_the_prescaler() {
+        const CLOCK: u32 = 64_000_000;
+        // 977 Hz is the lowest rate whose timer period fits into 16 bits.
+        assert_eq!(timer_config(CLOCK, 16_000), (0, 3999));
+        assert_eq!(timer_config(CLOCK, 977), (0, 65505));
+        assert_eq!(timer_config(CLOCK, 976), (1, 32785));
+        assert_eq!(timer_config(CLOCK, 1), (976, 65505));
+        for rate in [1, 500, 976, 977, 8000] {
+            let (prescaler, reload) = timer_config(CLOCK, rate);
+            assert!(prescaler <= u32::from(u16::MAX) && reload <= u32::from(u16::MAX));
+        }
+    }
+
+    #[test]
+    fn speaker_is_exclusive() {
+        let mut speaker = Speaker::acquire(Duration::from_millis(100)).unwrap();
+        assert!(matches!(
+            Speaker::acquire(Duration::ZERO),
+            Err(Error::AlreadyOpen)
+        ));
+        // Silent notes still take their time.
+        speaker.play(&[Note::new(440.0, Duration::from_millis(1))], 0.0);
+        speaker.play(&[Note::rest(Duration::from_millis(1))], 1.0);
+        drop(speaker);
+        assert!(Speaker::acquire(Duration::ZERO).is_ok());
+    }
+}
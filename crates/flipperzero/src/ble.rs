@@ -0,0 +1,374 @@
+//! Bluetooth LE profiles of the Flipper Zero.
+
+use alloc::boxed::Box;
+use core::ffi::c_void;
+use core::ptr::{self, NonNull};
+use core::slice;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
+
+use flipperzero_sys as sys;
+use flipperzero_sys::furi::duration_to_ticks;
+
+use crate::furi::record::Record;
+use crate::furi::sync::Mutex;
+use crate::io::{Error, Read, ReadTimeout, Write};
+
+/// `BLE_PROFILE_SERIAL_PACKET_SIZE_MAX` in the firmware.
+const PACKET_SIZE: usize = 486;
+/// Number of received bytes that are buffered until they are read.
+///
+/// The phone is told how much of it is free, so it doesn't send more than fits.
+const RX_BUFFER_SIZE: usize = 1024;
+/// How often blocked reads and writes check whether the phone is still connected.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+extern "C" {
+    /// The template of the serial profile, which the firmware exports but the bindings
+    /// don't include yet.
+    static ble_profile_serial: *const sys::FuriHalBleProfileTemplate;
+}
+
+/// A callback that is called when the phone connects or disconnects.
+type ConnectionCallback = Box<dyn FnMut(bool) + Send>;
+/// A callback that is called with the bytes of each packet from the phone.
+type ReceiveCallback = Box<dyn FnMut(&[u8]) + Send>;
+
+/// State shared with the serial service and Bt callbacks, which are called from the
+/// Bluetooth threads.
+struct Shared {
+    profile: NonNull<sys::FuriHalBleProfileBase>,
+    rx: NonNull<sys::FuriStreamBuffer>,
+    /// Available while no packet is being transmitted.
+    tx_idle: NonNull<sys::FuriSemaphore>,
+    /// Whether a phone is connected.
+    connected: AtomicBool,
+    on_connection: Mutex<Option<ConnectionCallback>>,
+    /// Takes the received bytes instead of `rx` when set.
+    on_receive: Mutex<Option<ReceiveCallback>>,
+}
+
+impl Shared {
+    /// Returns the number of bytes that still fit into `rx`.
+    fn rx_space(&self) -> u16 {
+        let space = unsafe { sys::furi_stream_buffer_spaces_available(self.rx.as_ptr()) };
+        space as u16
+    }
+
+    /// Routes the events of the serial service to us.
+    fn set_event_callback(&self) {
+        unsafe {
+            sys::ble_profile_serial_set_event_callback(
+                self.profile.as_ptr(),
+                RX_BUFFER_SIZE as u16,
+                Some(event_callback),
+                self as *const Shared as *mut c_void,
+            )
+        };
+    }
+
+    fn set_connected(&self, connected: bool) {
+        self.connected.store(connected, Ordering::Release);
+        // A packet that was in flight when the phone went away is never completed.
+        // Releasing an available semaphore fails, which is fine.
+        unsafe { sys::furi_semaphore_release(self.tx_idle.as_ptr()) };
+        if let Some(callback) = self.on_connection.lock().as_mut() {
+            callback(connected);
+        }
+    }
+
+    fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Acquire)
+    }
+}
+
+unsafe extern "C" fn event_callback(event: sys::SerialServiceEvent, context: *mut c_void) -> u16 {
+    let shared = unsafe { &*(context as *const Shared) };
+    match event.event {
+        sys::SerialServiceEventType_SerialServiceEventTypeDataReceived => {
+            if let Some(callback) = shared.on_receive.lock().as_mut() {
+                let data = if event.data.size == 0 {
+                    &[]
+                } else {
+                    unsafe { slice::from_raw_parts(event.data.buffer, event.data.size.into()) }
+                };
+                callback(data);
+            } else {
+                // Bytes that don't fit are dropped, but the phone only sends as many as
+                // we told it are free.
+                unsafe {
+                    sys::furi_stream_buffer_send(
+                        shared.rx.as_ptr(),
+                        event.data.buffer as *const c_void,
+                        event.data.size.into(),
+                        0,
+                    )
+                };
+            }
+        }
+        sys::SerialServiceEventType_SerialServiceEventTypeDataSent => unsafe {
+            sys::furi_semaphore_release(shared.tx_idle.as_ptr());
+        },
+        _ => {}
+    }
+    shared.rx_space()
+}
+
+unsafe extern "C" fn status_callback(status: sys::BtStatus, context: *mut c_void) {
+    let shared = unsafe { &*(context as *const Shared) };
+    let connected = status == sys::BtStatus_BtStatusConnected;
+    if connected {
+        // The Bt service opens an RPC session for every phone connecting to the serial
+        // profile, which takes over the serial service before the status changes.
+        shared.set_event_callback();
+        unsafe { sys::ble_profile_serial_set_rpc_active(shared.profile.as_ptr(), false) };
+    }
+    if connected != shared.is_connected() {
+        shared.set_connected(connected);
+    }
+}
+
+/// The Bluetooth LE serial profile, which lets a phone exchange bytes with the
+/// Flipper Zero.
+///
+/// Starting the profile replaces the default one, which the Flipper mobile app uses,
+/// and dropping it restores the default profile.
+///
+/// Reads and writes fail with [`Error::NotReady`] when the phone disconnects, instead of
+/// waiting for it to come back.
+pub struct SerialProfile {
+    shared: Box<Shared>,
+    bt: Record<sys::Bt>,
+    read_timeout: Duration,
+    write_timeout: Duration,
+}
+
+impl SerialProfile {
+    /// Starts the serial profile and advertises it, if Bluetooth is enabled.
+    ///
+    /// Reads don't wait for data by default, and writes wait up to one second for the
+    /// previous packet to be sent; see [`SerialProfile::set_read_timeout`] and
+    /// [`SerialProfile::set_write_timeout`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotReady`] if the profile couldn't be started.
+    pub fn start() -> Result<Self, Error> {
+        let bt = Record::<sys::Bt>::open();
+        let profile =
+            unsafe { sys::bt_profile_start(bt.as_ptr(), ble_profile_serial, ptr::null_mut()) };
+        let profile = NonNull::new(profile).ok_or(Error::NotReady)?;
+
+        let shared = Box::new(Shared {
+            profile,
+            rx: unsafe { NonNull::new_unchecked(sys::furi_stream_buffer_alloc(RX_BUFFER_SIZE, 1)) },
+            tx_idle: unsafe { NonNull::new_unchecked(sys::furi_semaphore_alloc(1, 1)) },
+            connected: AtomicBool::new(false),
+            on_connection: Mutex::new(None),
+            on_receive: Mutex::new(None),
+        });
+        shared.set_event_callback();
+        unsafe {
+            sys::bt_set_status_changed_callback(
+                bt.as_ptr(),
+                Some(status_callback),
+                &*shared as *const Shared as *mut c_void,
+            )
+        };
+
+        Ok(Self {
+            shared,
+            bt,
+            read_timeout: Duration::ZERO,
+            write_timeout: Duration::from_secs(1),
+        })
+    }
+
+    /// Returns `true` if a phone is connected.
+    pub fn is_connected(&self) -> bool {
+        self.shared.is_connected()
+    }
+
+    /// Sets a callback that is called with `true` when a phone connects and with
+    /// `false` when it disconnects, replacing any previous callback.
+    ///
+    /// The callback is called from the Bluetooth thread, so it should only signal the
+    /// app, for example through a message queue.
+    pub fn set_connection_callback<F>(&mut self, callback: F)
+    where
+        F: FnMut(bool) + Send + 'static,
+    {
+        *self.shared.on_connection.lock() = Some(Box::new(callback));
+    }
+
+    /// Sets a callback that is called with the bytes of each packet from the phone,
+    /// replacing any previous callback.
+    ///
+    /// While a callback is set, received bytes are passed to it instead of being
+    /// buffered for [`Read::read`]. The callback is called from the Bluetooth thread, so
+    /// it should return quickly, such as by putting the bytes in a message queue.
+    pub fn set_receive_callback<F>(&mut self, callback: F)
+    where
+        F: FnMut(&[u8]) + Send + 'static,
+    {
+        *self.shared.on_receive.lock() = Some(Box::new(callback));
+    }
+
+    /// Removes the callback set with [`SerialProfile::set_receive_callback`], so received
+    /// bytes are buffered for [`Read::read`] again.
+    pub fn clear_receive_callback(&mut self) {
+        *self.shared.on_receive.lock() = None;
+    }
+
+    /// Sets how long reads wait for data to arrive before returning 0.
+    ///
+    /// A timeout of zero makes reads return immediately with the bytes received so far,
+    /// and [`Duration::MAX`] makes them wait until at least one byte arrives or the
+    /// phone disconnects.
+    pub fn set_read_timeout(&mut self, timeout: Duration) {
+        self.read_timeout = timeout;
+    }
+
+    /// Sets how long writes wait for the previous packet to be sent.
+    pub fn set_write_timeout(&mut self, timeout: Duration) {
+        self.write_timeout = timeout;
+    }
+
+    /// Waits up to `timeout` for the previous packet to be sent, taking the
+    /// transmitter.
+    fn acquire_tx(&self, timeout: Duration) -> Result<(), Error> {
+        let mut remaining = timeout;
+        loop {
+            if !self.shared.is_connected() {
+                return Err(Error::NotReady);
+            }
+            let slice = remaining.min(POLL_INTERVAL);
+            let status = unsafe {
+                sys::furi_semaphore_acquire(self.shared.tx_idle.as_ptr(), duration_to_ticks(slice))
+            };
+            if status == sys::FuriStatus_FuriStatusOk {
+                return Ok(());
+            }
+            if remaining <= slice {
+                return Err(Error::NotReady);
+            }
+            remaining -= slice;
+        }
+    }
+
+    /// Waits up to `timeout` for bytes to arrive, returning 0 if none did.
+    fn receive(&self, buf: &mut [u8], timeout: Duration) -> Result<usize, Error> {
+        let mut remaining = timeout;
+        loop {
+            let slice = remaining.min(POLL_INTERVAL);
+            let n = unsafe {
+                sys::furi_stream_buffer_receive(
+                    self.shared.rx.as_ptr(),
+                    buf.as_mut_ptr() as *mut c_void,
+                    buf.len(),
+                    duration_to_ticks(slice),
+                )
+            };
+            if n > 0 && unsafe { sys::furi_stream_buffer_is_empty(self.shared.rx.as_ptr()) } {
+                // Lets the phone send a full buffer again.
+                unsafe {
+                    sys::ble_profile_serial_notify_buffer_is_empty(self.shared.profile.as_ptr())
+                };
+            }
+
+            if n > 0 {
+                return Ok(n);
+            } else if !self.shared.is_connected() {
+                return Err(Error::NotReady);
+            } else if remaining <= slice {
+                return Ok(0);
+            }
+            remaining -= slice;
+        }
+    }
+}
+
+impl Read for SerialProfile {
+    /// Reads the bytes received so far, waiting up to the [read timeout] for at least
+    /// one byte to arrive.
+    ///
+    /// Returns 0 if no byte arrived in time.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotReady`] if no bytes are left and the phone isn't connected.
+    ///
+    /// [read timeout]: SerialProfile::set_read_timeout
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        self.receive(buf, self.read_timeout)
+    }
+}
+
+impl ReadTimeout for SerialProfile {
+    fn read_with_timeout(&mut self, buf: &mut [u8], timeout: Duration) -> Result<usize, Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        match self.receive(buf, timeout)? {
+            0 => Err(Error::TimedOut),
+            n => Ok(n),
+        }
+    }
+}
+
+impl Write for SerialProfile {
+    /// Sends a packet of up to 486 bytes from `buf`, waiting up to the [write timeout]
+    /// for the previous one to be sent.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotReady`] if the phone isn't connected, or the previous packet
+    /// wasn't sent in time.
+    ///
+    /// [write timeout]: SerialProfile::set_write_timeout
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        self.acquire_tx(self.write_timeout)?;
+
+        let len = buf.len().min(PACKET_SIZE);
+        // The buffer is only read by the service.
+        let sent = unsafe {
+            sys::ble_profile_serial_tx(
+                self.shared.profile.as_ptr(),
+                buf.as_ptr() as *mut u8,
+                len as u16,
+            )
+        };
+        if !sent {
+            unsafe { sys::furi_semaphore_release(self.shared.tx_idle.as_ptr()) };
+            return Err(Error::NotReady);
+        }
+        Ok(len)
+    }
+
+    /// Waits up to the write timeout for the last packet to be sent.
+    fn flush(&mut self) -> Result<(), Error> {
+        self.acquire_tx(self.write_timeout)?;
+        unsafe { sys::furi_semaphore_release(self.shared.tx_idle.as_ptr()) };
+        Ok(())
+    }
+}
+
+impl Drop for SerialProfile {
+    fn drop(&mut self) {
+        unsafe {
+            sys::bt_set_status_changed_callback(self.bt.as_ptr(), None, ptr::null_mut());
+            sys::ble_profile_serial_set_event_callback(
+                self.shared.profile.as_ptr(),
+                0,
+                None,
+                ptr::null_mut(),
+            );
+            sys::bt_profile_restore_default(self.bt.as_ptr());
+            sys::furi_semaphore_free(self.shared.tx_idle.as_ptr());
+            sys::furi_stream_buffer_free(self.shared.rx.as_ptr());
+        }
+    }
+}
@@ -0,0 +1,266 @@
+//! Commands of the command line interface, which is reached over USB serial.
+
+use alloc::boxed::Box;
+use alloc::ffi::CString;
+use core::ffi::{c_void, CStr};
+use core::fmt;
+use core::marker::PhantomData;
+use core::ptr::NonNull;
+use core::time::Duration;
+
+use flipperzero_sys::{self as sys, furi::duration_to_ticks};
+
+use crate::furi::record::Record;
+use crate::furi::sync::Mutex;
+use crate::io::{Error, Read, Write};
+
+type CommandCallback = Box<dyn FnMut(&mut Console<'_>, Args<'_>) + Send>;
+
+/// State shared with the command callback.
+///
+/// This is never freed: the firmware copies a command out of its list and releases its
+/// lock before calling it, so the callback may still be called with this state after the
+/// command was deleted.
+struct Shared {
+    /// Held while the command runs, so that dropping the [`Command`] waits for it, and
+    /// `None` once the command was deleted.
+    callback: Mutex<Option<CommandCallback>>,
+}
+
+/// A command added to the command line interface, which calls a closure when it is
+/// entered.
+///
+/// The command is removed when this is dropped. Commands run on the thread of the
+/// command line interface, and may run while the app that added them is open. Each
+/// command leaks a few bytes of state when it is removed, as the firmware may still be
+/// about to call it.
+///
+/// # Examples
+///
+/// ```no_run
+/// use core::fmt::Write;
+/// use flipperzero::cli::Command;
+///
+/// let _command = Command::add(c"my_app_echo", |console, args| {
+///     for arg in args {
+///         let _ = write!(console, "{} ", arg);
+///     }
+///     let _ = writeln!(console);
+/// });
+/// ```
+pub struct Command {
+    // The order matters: the command must be deleted before the record is closed.
+    cli: Record<sys::Cli>,
+    name: CString,
+    shared: &'static Shared,
+}
+
+impl Command {
+    /// Adds the command `name`, calling `callback` with the console and the arguments
+    /// each time it is entered.
+    ///
+    /// The firmware replaces spaces in `name` with underscores, and replaces any command
+    /// of the same name.
+    pub fn add<F>(name: &CStr, callback: F) -> Self
+    where
+        F: FnMut(&mut Console<'_>, Args<'_>) + Send + 'static,
+    {
+        unsafe extern "C" fn run_callback(
+            cli: *mut sys::Cli,
+            args: *mut sys::FuriString,
+            context: *mut c_void,
+        ) {
+            let shared = unsafe { &*(context as *const Shared) };
+            let mut console = Console {
+                cli: unsafe { NonNull::new_unchecked(cli) },
+                _marker: PhantomData,
+            };
+            let args = unsafe { CStr::from_ptr(sys::furi_string_get_cstr(args)) };
+            let Ok(args) = args.to_str() else {
+                let _ = console.write_all(b"Arguments must be valid UTF-8\r\n");
+                return;
+            };
+            if let Some(callback) = shared.callback.lock().as_mut() {
+                callback(&mut console, Args::new(args));
+            }
+        }
+
+        let cli = Record::<sys::Cli>::open();
+        let name = CString::from(name);
+        let shared: &'static Shared = Box::leak(Box::new(Shared {
+            callback: Mutex::new(Some(Box::new(callback))),
+        }));
+        unsafe {
+            // Commands that aren't parallel safe don't run while an app is open, which
+            // would be every time for the commands of an app.
+            sys::cli_add_command(
+                cli.as_ptr(),
+                name.as_ptr(),
+                sys::CliCommandFlag_CliCommandFlagParallelSafe,
+                Some(run_callback),
+                shared as *const Shared as *mut c_void,
+            );
+        }
+
+        Self { cli, name, shared }
+    }
+
+    /// Returns the name of the command.
+    pub fn name(&self) -> &CStr {
+        &self.name
+    }
+}
+
+impl Drop for Command {
+    fn drop(&mut self) {
+        unsafe { sys::cli_delete_command(self.cli.as_ptr(), self.name.as_ptr()) };
+        // The command can't be entered anymore, but may still be running, or about to be
+        // called by the firmware. Taking the closure waits for a running call to return,
+        // and later calls find no closure. The shared state is leaked, as it can't be
+        // known when the firmware is done with it.
+        drop(self.shared.callback.lock().take());
+    }
+}
+
+/// The console that a [`Command`] was entered on, for printing back to it and reading
+/// further input.
+///
+/// This implements [`Write`] and [`core::fmt::Write`], so `write!` prints to it. Lines
+/// should end with `"\r\n"`, as terminals expect.
+pub struct Console<'a> {
+    cli: NonNull<sys::Cli>,
+    _marker: PhantomData<&'a mut sys::Cli>,
+}
+
+impl Console<'_> {
+    /// Returns `true` if the user pressed Ctrl+C, asking the command to stop.
+    ///
+    /// Long-running commands should check this regularly.
+    pub fn is_interrupted(&self) -> bool {
+        unsafe { sys::cli_cmd_interrupt_received(self.cli.as_ptr()) }
+    }
+
+    /// Returns `true` if a terminal is connected.
+    pub fn is_connected(&self) -> bool {
+        unsafe { sys::cli_is_connected(self.cli.as_ptr()) }
+    }
+
+    /// Reads the input typed so far, waiting up to `timeout` for at least one byte.
+    ///
+    /// Returns 0 if nothing was typed in time.
+    pub fn read_timeout(&mut self, buf: &mut [u8], timeout: Duration) -> usize {
+        unsafe {
+            sys::cli_read_timeout(
+                self.cli.as_ptr(),
+                buf.as_mut_ptr(),
+                buf.len(),
+                duration_to_ticks(timeout),
+            )
+        }
+    }
+}
+
+impl Read for Console<'_> {
+    /// Waits for input, and returns the bytes typed so far.
+    ///
+    /// Returns 0 if the terminal disconnects.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        Ok(unsafe { sys::cli_read(self.cli.as_ptr(), buf.as_mut_ptr(), buf.len()) })
+    }
+}
+
+impl Write for Console<'_> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        unsafe { sys::cli_write(self.cli.as_ptr(), buf.as_ptr(), buf.len()) };
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl fmt::Write for Console<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write_all(s.as_bytes()).map_err(|_| fmt::Error)
+    }
+}
+
+impl ufmt::uWrite for Console<'_> {
+    type Error = Error;
+
+    fn write_str(&mut self, s: &str) -> Result<(), Self::Error> {
+        self.write_all(s.as_bytes())
+    }
+}
+
+/// The arguments entered after a [`Command`], split at whitespace.
+///
+/// Arguments in double quotes may contain whitespace, and are returned without the
+/// quotes, like the firmware's own commands read them.
+#[derive(Debug, Clone)]
+pub struct Args<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Args<'a> {
+    /// Splits `args` into arguments.
+    pub fn new(args: &'a str) -> Self {
+        Self { rest: args }
+    }
+
+    /// Returns the arguments not yet returned by the iterator, as entered.
+    pub fn as_str(&self) -> &'a str {
+        self.rest.trim()
+    }
+}
+
+impl<'a> Iterator for Args<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let rest = self.rest.trim_start();
+        if rest.is_empty() {
+            self.rest = rest;
+            return None;
+        }
+        let (arg, rest) = match rest.strip_prefix('"') {
+            // An unterminated quote runs to the end.
+            Some(quoted) => quoted.split_once('"').unwrap_or((quoted, "")),
+            None => rest.split_once(char::is_whitespace).unwrap_or((rest, "")),
+        };
+        self.rest = rest;
+        Some(arg)
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::{Args, Command};
+
+    #[test]
+    fn args_split_at_whitespace_and_quotes() {
+        let mut args = Args::new("  read  \"/ext/my file.txt\" 16\t");
+        assert_eq!(args.as_str(), "read  \"/ext/my file.txt\" 16");
+        assert_eq!(args.next(), Some("read"));
+        assert_eq!(args.next(), Some("/ext/my file.txt"));
+        assert_eq!(args.as_str(), "16");
+        assert_eq!(args.next(), Some("16"));
+        assert!(args.next().is_none());
+        assert_eq!(args.as_str(), "");
+
+        let args: Vec<_> = Args::new("a \"unterminated b").collect();
+        assert_eq!(args, ["a", "unterminated b"]);
+        assert!(Args::new("   ").next().is_none());
+        assert_eq!(Args::new("\"\"").next(), Some(""));
+    }
+
+    #[test]
+    fn commands_are_added_and_removed() {
+        let command = Command::add(c"rs_test_command", |_, _| {});
+        assert_eq!(command.name().to_bytes(), b"rs_test_command");
+        drop(command);
+    }
+}
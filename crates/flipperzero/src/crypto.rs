@@ -0,0 +1,285 @@
+//! The AES engine of the Flipper Zero, and the keys of its secure enclave.
+//!
+//! The engine encrypts with AES-256 in CBC mode. Its key either comes from the app, or
+//! from a slot of the secure enclave, which uses the key without revealing it to the
+//! app. Stock apps encrypt their secrets with the [`UNIQUE_KEY_SLOT`], whose key is
+//! different on each Flipper Zero, so the secrets can only be decrypted on the device
+//! that encrypted them.
+
+use core::fmt;
+use core::ops::RangeInclusive;
+
+use flipperzero_sys as sys;
+
+/// Size of an AES-256 key in bytes.
+pub const KEY_SIZE: usize = 32;
+
+/// Size of the initialization vector in bytes.
+pub const IV_SIZE: usize = 16;
+
+/// Size of an AES block in bytes. Cipher inputs must be a multiple of it.
+pub const BLOCK_SIZE: usize = 16;
+
+/// The enclave slot of the key that is unique to each Flipper Zero.
+pub const UNIQUE_KEY_SLOT: u8 = 11;
+
+/// The enclave slots that apps can fill with keys of their own.
+pub const USER_KEY_SLOTS: RangeInclusive<u8> = 12..=100;
+
+/// All slots of the enclave, which the firmware crashes on outside of.
+const ENCLAVE_SLOTS: RangeInclusive<u8> = 1..=100;
+
+/// Errors of the AES engine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The key couldn't be loaded, such as from an empty enclave slot.
+    KeyUnavailable,
+    /// The enclave has no slot of this number, as slots are numbered from 1 to 100.
+    InvalidSlot,
+    /// The input is not a multiple of [`BLOCK_SIZE`], or doesn't match the length of the
+    /// output.
+    InvalidLength,
+    /// The engine failed to process a block.
+    Engine,
+}
+
+impl Error {
+    /// Description associated with [`Error`].
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::KeyUnavailable => "key unavailable",
+            Self::InvalidSlot => "invalid enclave slot",
+            Self::InvalidLength => "invalid length",
+            Self::Engine => "crypto engine failure",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.description())
+    }
+}
+
+impl ufmt::uDisplay for Error {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        f.write_str(self.description())
+    }
+}
+
+impl core::error::Error for Error {}
+
+/// Makes sure that `slot` and the slots before it hold keys, generating the missing
+/// keys with the random number generator of the chip.
+///
+/// Keys in the enclave are permanent, so this only generates keys on the first call.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidSlot`] if the enclave has no slot `slot`, and
+/// [`Error::KeyUnavailable`] if the enclave is corrupted.
+pub fn ensure_enclave_key(slot: u8) -> Result<(), Error> {
+    check_slot(slot)?;
+    if unsafe { sys::furi_hal_crypto_enclave_ensure_key(slot) } {
+        Ok(())
+    } else {
+        Err(Error::KeyUnavailable)
+    }
+}
+
+/// The AES engine with a key loaded, which encrypts and decrypts with AES-256-CBC.
+///
+/// The engine keeps the chaining state between calls, so a message can be processed in
+/// pieces. Use a new cipher for each message, and for each direction.
+///
+/// There is only one engine. Creating a cipher waits until any other cipher is dropped,
+/// so a thread must not create a second cipher while it holds one.
+///
+/// # Examples
+///
+/// ```no_run
+/// use flipperzero::crypto::{self, Cipher};
+///
+/// crypto::ensure_enclave_key(crypto::UNIQUE_KEY_SLOT).unwrap();
+///
+/// let iv = [0x42; crypto::IV_SIZE];
+/// let mut secret = *b"a TOTP seed, padded to 32 bytes.";
+/// Cipher::with_enclave_key(crypto::UNIQUE_KEY_SLOT, &iv)
+///     .unwrap()
+///     .encrypt_in_place(&mut secret)
+///     .unwrap();
+/// ```
+pub struct Cipher {
+    /// The enclave slot of the key, or `None` for a key of the app.
+    slot: Option<u8>,
+}
+
+impl Cipher {
+    /// Loads `key` into the engine.
+    pub fn with_key(key: &[u8; KEY_SIZE], iv: &[u8; IV_SIZE]) -> Result<Self, Error> {
+        if unsafe { sys::furi_hal_crypto_load_key(key.as_ptr(), iv.as_ptr()) } {
+            Ok(Self { slot: None })
+        } else {
+            Err(Error::KeyUnavailable)
+        }
+    }
+
+    /// Loads the key in `slot` of the secure enclave into the engine.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidSlot`] if the enclave has no slot `slot`, and
+    /// [`Error::KeyUnavailable`] if the slot is empty; see [`ensure_enclave_key`].
+    pub fn with_enclave_key(slot: u8, iv: &[u8; IV_SIZE]) -> Result<Self, Error> {
+        check_slot(slot)?;
+        if unsafe { sys::furi_hal_crypto_enclave_load_key(slot, iv.as_ptr()) } {
+            Ok(Self { slot: Some(slot) })
+        } else {
+            Err(Error::KeyUnavailable)
+        }
+    }
+
+    /// Encrypts `input` into `output`, which must have the same length.
+    pub fn encrypt(&mut self, input: &[u8], output: &mut [u8]) -> Result<(), Error> {
+        check_lengths(input.len(), output.len())?;
+        let ok = unsafe {
+            sys::furi_hal_crypto_encrypt(input.as_ptr(), output.as_mut_ptr(), input.len())
+        };
+        ok.then_some(()).ok_or(Error::Engine)
+    }
+
+    /// Decrypts `input` into `output`, which must have the same length.
+    pub fn decrypt(&mut self, input: &[u8], output: &mut [u8]) -> Result<(), Error> {
+        check_lengths(input.len(), output.len())?;
+        let ok = unsafe {
+            sys::furi_hal_crypto_decrypt(input.as_ptr(), output.as_mut_ptr(), input.len())
+        };
+        ok.then_some(()).ok_or(Error::Engine)
+    }
+
+    /// Encrypts `buf`, replacing its contents.
+    pub fn encrypt_in_place(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+        check_lengths(buf.len(), buf.len())?;
+        // The engine reads each block before writing it back.
+        let ptr = buf.as_mut_ptr();
+        let ok = unsafe { sys::furi_hal_crypto_encrypt(ptr, ptr, buf.len()) };
+        ok.then_some(()).ok_or(Error::Engine)
+    }
+
+    /// Decrypts `buf`, replacing its contents.
+    pub fn decrypt_in_place(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+        check_lengths(buf.len(), buf.len())?;
+        let ptr = buf.as_mut_ptr();
+        let ok = unsafe { sys::furi_hal_crypto_decrypt(ptr, ptr, buf.len()) };
+        ok.then_some(()).ok_or(Error::Engine)
+    }
+}
+
+impl Drop for Cipher {
+    fn drop(&mut self) {
+        // This clears the key from the engine and lets other ciphers be created.
+        unsafe {
+            match self.slot {
+                Some(slot) => sys::furi_hal_crypto_enclave_unload_key(slot),
+                None => sys::furi_hal_crypto_unload_key(),
+            }
+        };
+    }
+}
+
+fn check_slot(slot: u8) -> Result<(), Error> {
+    if ENCLAVE_SLOTS.contains(&slot) {
+        Ok(())
+    } else {
+        Err(Error::InvalidSlot)
+    }
+}
+
+fn check_lengths(input: usize, output: usize) -> Result<(), Error> {
+    if input % BLOCK_SIZE == 0 && input == output {
+        Ok(())
+    } else {
+        Err(Error::InvalidLength)
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use super::{ensure_enclave_key, Cipher, Error, BLOCK_SIZE, UNIQUE_KEY_SLOT};
+
+    // The CBC-AES256 example of NIST SP 800-38A, F.2.5.
+    const KEY: [u8; 32] = [
+        0x60, 0x3d, 0xeb, 0x10, 0x15, 0xca, 0x71, 0xbe, 0x2b, 0x73, 0xae, 0xf0, 0x85, 0x7d, 0x77,
+        0x81, 0x1f, 0x35, 0x2c, 0x07, 0x3b, 0x61, 0x08, 0xd7, 0x2d, 0x98, 0x10, 0xa3, 0x09, 0x14,
+        0xdf, 0xf4,
+    ];
+    const IV: [u8; 16] = [
+        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e,
+        0x0f,
+    ];
+    const PLAINTEXT: [u8; 32] = [
+        0x6b, 0xc1, 0xbe, 0xe2, 0x2e, 0x40, 0x9f, 0x96, 0xe9, 0x3d, 0x7e, 0x11, 0x73, 0x93, 0x17,
+        0x2a, 0xae, 0x2d, 0x8a, 0x57, 0x1e, 0x03, 0xac, 0x9c, 0x9e, 0xb7, 0x6f, 0xac, 0x45, 0xaf,
+        0x8e, 0x51,
+    ];
+    const CIPHERTEXT: [u8; 32] = [
+        0xf5, 0x8c, 0x4c, 0x04, 0xd6, 0xe5, 0xf1, 0xba, 0x77, 0x9e, 0xab, 0xfb, 0x5f, 0x7b, 0xfb,
+        0xd6, 0x9c, 0xfc, 0x4e, 0x96, 0x7e, 0xdb, 0x80, 0x8d, 0x67, 0x9f, 0x77, 0x7b, 0xc6, 0x70,
+        0x2c, 0x7d,
+    ];
+
+    #[test]
+    fn matches_the_nist_example() {
+        let mut out = [0; 32];
+        let mut cipher = Cipher::with_key(&KEY, &IV).unwrap();
+        // The chaining state carries over between the blocks.
+        let (first, second) = out.split_at_mut(BLOCK_SIZE);
+        cipher.encrypt(&PLAINTEXT[..BLOCK_SIZE], first).unwrap();
+        cipher.encrypt(&PLAINTEXT[BLOCK_SIZE..], second).unwrap();
+        assert_eq!(out, CIPHERTEXT);
+        drop(cipher);
+
+        let mut cipher = Cipher::with_key(&KEY, &IV).unwrap();
+        cipher.decrypt_in_place(&mut out).unwrap();
+        assert_eq!(out, PLAINTEXT);
+        assert_eq!(
+            cipher.decrypt(&CIPHERTEXT[..15], &mut [0; 15]),
+            Err(Error::InvalidLength)
+        );
+        assert_eq!(
+            cipher.decrypt(&CIPHERTEXT, &mut [0; 16]),
+            Err(Error::InvalidLength)
+        );
+    }
+
+    #[test]
+    fn rejects_slots_outside_the_enclave() {
+        for slot in [0, 101, u8::MAX] {
+            assert_eq!(ensure_enclave_key(slot), Err(Error::InvalidSlot));
+            assert!(matches!(
+                Cipher::with_enclave_key(slot, &IV),
+                Err(Error::InvalidSlot)
+            ));
+        }
+    }
+
+    #[test]
+    fn round_trips_with_the_unique_key() {
+        ensure_enclave_key(UNIQUE_KEY_SLOT).unwrap();
+        let mut buf = PLAINTEXT;
+        Cipher::with_enclave_key(UNIQUE_KEY_SLOT, &IV)
+            .unwrap()
+            .encrypt_in_place(&mut buf)
+            .unwrap();
+        assert_ne!(buf, PLAINTEXT);
+        Cipher::with_enclave_key(UNIQUE_KEY_SLOT, &IV)
+            .unwrap()
+            .decrypt_in_place(&mut buf)
+            .unwrap();
+        assert_eq!(buf, PLAINTEXT);
+    }
+}
@@ -0,0 +1,533 @@
+//! Dates and times, and the real-time clock of the Flipper Zero.
+//!
+//! The clock has no time zone; it keeps whatever local time the user set.
+
+use core::fmt;
+
+use flipperzero_sys as sys;
+
+/// Earliest year of a [`DateTime`], that of the Unix epoch.
+pub const MIN_YEAR: u16 = 1970;
+/// Latest year of a [`DateTime`], in which 32-bit Unix timestamps run out.
+pub const MAX_YEAR: u16 = 2106;
+
+/// Years that the real-time clock can keep.
+const RTC_YEARS: core::ops::RangeInclusive<u16> = 2000..=2099;
+
+const SECONDS_PER_DAY: u32 = 24 * 60 * 60;
+
+/// Length of the text of [`DateTime`]'s `Display` implementation.
+const ISO_LEN: usize = "2000-01-01T00:00:00".len();
+
+/// Errors when constructing a [`DateTime`] or setting the clock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// A field is out of its range, such as the 30th of February.
+    Invalid,
+    /// The date is valid, but can't be set on the real-time clock, which only keeps
+    /// the years 2000 to 2099.
+    OutOfRtcRange,
+}
+
+impl Error {
+    /// Description associated with [`Error`].
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Invalid => "invalid date or time",
+            Self::OutOfRtcRange => "date out of the range of the clock",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.description())
+    }
+}
+
+impl ufmt::uDisplay for Error {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        f.write_str(self.description())
+    }
+}
+
+impl core::error::Error for Error {}
+
+/// A date and time of day, with a resolution of one second.
+///
+/// The `Display` implementation formats it as in ISO 8601, such as
+/// `2024-05-01T12:34:56`, which sorts in time order. [`DateTime::date`] and
+/// [`DateTime::time`] format it for showing to the user.
+///
+/// # Examples
+///
+/// ```no_run
+/// use flipperzero::datetime::DateTime;
+/// use flipperzero::println;
+///
+/// // Prints a timestamp such as `2024-05-01T12:34:56`.
+/// println!("{}", DateTime::now());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DateTime {
+    // The order of the fields gives the order of the derived `Ord`.
+    year: u16,
+    month: u8,
+    day: u8,
+    hour: u8,
+    minute: u8,
+    second: u8,
+}
+
+impl DateTime {
+    /// Returns the date and time made of the given fields, with months and days
+    /// counting from 1 and the hour from 0 to 23.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Invalid`] if a field is out of range, or the date is outside of
+    /// the 32-bit Unix timestamps, from 1970 to early [`MAX_YEAR`].
+    pub fn new(
+        year: u16,
+        month: u8,
+        day: u8,
+        hour: u8,
+        minute: u8,
+        second: u8,
+    ) -> Result<Self, Error> {
+        let valid = (MIN_YEAR..=MAX_YEAR).contains(&year)
+            && (1..=12).contains(&month)
+            && day >= 1
+            && day <= days_in_month(year, month)
+            && hour < 24
+            && minute < 60
+            && second < 60;
+        if !valid {
+            return Err(Error::Invalid);
+        }
+        let datetime = Self {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+        };
+        u32::try_from(datetime.seconds_since_epoch())
+            .map(|_| datetime)
+            .map_err(|_| Error::Invalid)
+    }
+
+    /// Reads the real-time clock.
+    pub fn now() -> Self {
+        let mut datetime = core::mem::MaybeUninit::uninit();
+        unsafe {
+            sys::furi_hal_rtc_get_datetime(datetime.as_mut_ptr());
+            Self::from_sys(datetime.assume_init_ref())
+        }
+    }
+
+    /// Sets the real-time clock to this date and time.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::OutOfRtcRange`] if the year is outside of 2000 to 2099.
+    pub fn set_now(&self) -> Result<(), Error> {
+        if !RTC_YEARS.contains(&self.year) {
+            return Err(Error::OutOfRtcRange);
+        }
+        let mut datetime = self.to_sys();
+        unsafe { sys::furi_hal_rtc_set_datetime(&mut datetime) };
+        Ok(())
+    }
+
+    /// Returns the date and time `timestamp` seconds after the start of 1970.
+    pub fn from_unix_timestamp(timestamp: u32) -> Self {
+        let (year, month, day) = civil_from_days(timestamp / SECONDS_PER_DAY);
+        let seconds = timestamp % SECONDS_PER_DAY;
+        Self {
+            year,
+            month,
+            day,
+            hour: (seconds / 3600) as u8,
+            minute: (seconds / 60 % 60) as u8,
+            second: (seconds % 60) as u8,
+        }
+    }
+
+    /// Returns the number of seconds from the start of 1970 to this date and time.
+    pub fn to_unix_timestamp(&self) -> u32 {
+        // `new` only accepts dates whose timestamps fit.
+        self.seconds_since_epoch() as u32
+    }
+
+    /// Converts the date and time read by the firmware.
+    ///
+    /// Fields out of range, which the firmware never returns, are clamped.
+    pub fn from_sys(datetime: &sys::DateTime) -> Self {
+        // All dates before the last year have timestamps that fit.
+        let year = datetime.year.clamp(MIN_YEAR, MAX_YEAR - 1);
+        let month = datetime.month.clamp(1, 12);
+        Self {
+            year,
+            month,
+            day: datetime.day.clamp(1, days_in_month(year, month)),
+            hour: datetime.hour.min(23),
+            minute: datetime.minute.min(59),
+            second: datetime.second.min(59),
+        }
+    }
+
+    /// Returns the date and time in the format of the firmware, with its weekday.
+    pub fn to_sys(&self) -> sys::DateTime {
+        sys::DateTime {
+            hour: self.hour,
+            minute: self.minute,
+            second: self.second,
+            day: self.day,
+            month: self.month,
+            year: self.year,
+            weekday: self.weekday(),
+        }
+    }
+
+    /// Returns the year, from 1970.
+    pub fn year(&self) -> u16 {
+        self.year
+    }
+
+    /// Returns the month, from 1 for January to 12.
+    pub fn month(&self) -> u8 {
+        self.month
+    }
+
+    /// Returns the day of the month, from 1.
+    pub fn day(&self) -> u8 {
+        self.day
+    }
+
+    /// Returns the hour, from 0 to 23.
+    pub fn hour(&self) -> u8 {
+        self.hour
+    }
+
+    /// Returns the minute, from 0 to 59.
+    pub fn minute(&self) -> u8 {
+        self.minute
+    }
+
+    /// Returns the second, from 0 to 59.
+    pub fn second(&self) -> u8 {
+        self.second
+    }
+
+    /// Returns the day of the week, from 1 for Monday to 7 for Sunday.
+    pub fn weekday(&self) -> u8 {
+        // The epoch was a Thursday.
+        ((self.days_since_epoch() + 3) % 7 + 1) as u8
+    }
+
+    /// Returns the date formatted with `format`, with `separator` between its fields,
+    /// such as `01.05.2024`.
+    pub fn date(&self, format: DateFormat, separator: char) -> DateDisplay {
+        DateDisplay {
+            datetime: *self,
+            format,
+            separator,
+        }
+    }
+
+    /// Returns the time formatted with `format`, such as `12:34` or `12:34:56 PM`.
+    pub fn time(&self, format: TimeFormat, show_seconds: bool) -> TimeDisplay {
+        TimeDisplay {
+            datetime: *self,
+            format,
+            show_seconds,
+        }
+    }
+
+    fn seconds_since_epoch(&self) -> u64 {
+        u64::from(self.days_since_epoch()) * u64::from(SECONDS_PER_DAY)
+            + u64::from(self.hour) * 3600
+            + u64::from(self.minute) * 60
+            + u64::from(self.second)
+    }
+
+    fn days_since_epoch(&self) -> u32 {
+        // Howard Hinnant's `days_from_civil`, with eras of 400 years starting in March.
+        let year = u32::from(self.year) - u32::from(self.month <= 2);
+        let era = year / 400;
+        let year_of_era = year - era * 400;
+        let month = u32::from(self.month);
+        let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5
+            + u32::from(self.day)
+            - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        era * 146_097 + day_of_era - 719_468
+    }
+
+    fn iso(&self) -> [u8; ISO_LEN] {
+        let mut text = *b"0000-00-00T00:00:00";
+        write_digits(&mut text[0..4], self.year.into());
+        write_digits(&mut text[5..7], self.month.into());
+        write_digits(&mut text[8..10], self.day.into());
+        write_digits(&mut text[11..13], self.hour.into());
+        write_digits(&mut text[14..16], self.minute.into());
+        write_digits(&mut text[17..19], self.second.into());
+        text
+    }
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = self.iso();
+        f.pad(core::str::from_utf8(&text).unwrap())
+    }
+}
+
+impl ufmt::uDisplay for DateTime {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        let text = self.iso();
+        f.write_str(core::str::from_utf8(&text).unwrap())
+    }
+}
+
+/// The order of the fields of a date.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateFormat {
+    /// Day, month, year.
+    Dmy,
+    /// Month, day, year.
+    Mdy,
+    /// Year, month, day.
+    Ymd,
+}
+
+impl DateFormat {
+    /// Returns the format chosen in the settings of the Flipper Zero.
+    pub fn locale() -> Self {
+        match unsafe { sys::furi_hal_rtc_get_locale_dateformat() } {
+            sys::FuriHalRtcLocaleDateFormat_FuriHalRtcLocaleDateFormatMDY => Self::Mdy,
+            sys::FuriHalRtcLocaleDateFormat_FuriHalRtcLocaleDateFormatYMD => Self::Ymd,
+            _ => Self::Dmy,
+        }
+    }
+}
+
+/// Whether times are shown with 24 or 12 hours.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeFormat {
+    H24,
+    /// 12 hours followed by `AM` or `PM`.
+    H12,
+}
+
+impl TimeFormat {
+    /// Returns the format chosen in the settings of the Flipper Zero.
+    pub fn locale() -> Self {
+        match unsafe { sys::furi_hal_rtc_get_locale_timeformat() } {
+            sys::FuriHalRtcLocaleTimeFormat_FuriHalRtcLocaleTimeFormat12h => Self::H12,
+            _ => Self::H24,
+        }
+    }
+}
+
+/// A date formatted with [`DateTime::date`].
+#[derive(Debug, Clone, Copy)]
+pub struct DateDisplay {
+    datetime: DateTime,
+    format: DateFormat,
+    separator: char,
+}
+
+impl fmt::Display for DateDisplay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let DateTime {
+            year, month, day, ..
+        } = self.datetime;
+        let s = self.separator;
+        match self.format {
+            DateFormat::Dmy => write!(f, "{:02}{s}{:02}{s}{:04}", day, month, year),
+            DateFormat::Mdy => write!(f, "{:02}{s}{:02}{s}{:04}", month, day, year),
+            DateFormat::Ymd => write!(f, "{:04}{s}{:02}{s}{:02}", year, month, day),
+        }
+    }
+}
+
+/// A time formatted with [`DateTime::time`].
+#[derive(Debug, Clone, Copy)]
+pub struct TimeDisplay {
+    datetime: DateTime,
+    format: TimeFormat,
+    show_seconds: bool,
+}
+
+impl fmt::Display for TimeDisplay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let DateTime {
+            hour,
+            minute,
+            second,
+            ..
+        } = self.datetime;
+        let shown_hour = match self.format {
+            TimeFormat::H24 => hour,
+            TimeFormat::H12 if hour % 12 == 0 => 12,
+            TimeFormat::H12 => hour % 12,
+        };
+        write!(f, "{:02}:{:02}", shown_hour, minute)?;
+        if self.show_seconds {
+            write!(f, ":{:02}", second)?;
+        }
+        if self.format == TimeFormat::H12 {
+            f.write_str(if hour < 12 { " AM" } else { " PM" })?;
+        }
+        Ok(())
+    }
+}
+
+fn is_leap_year(year: u16) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Returns the year, month and day `days` after the epoch.
+fn civil_from_days(days: u32) -> (u16, u8, u8) {
+    // Howard Hinnant's `civil_from_days`, the inverse of `days_since_epoch`.
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + u32::from(month <= 2);
+    (year as u16, month as u8, day as u8)
+}
+
+/// Writes `value` into `out` as decimal digits, padded with zeros.
+fn write_digits(out: &mut [u8], mut value: u32) {
+    for digit in out.iter_mut().rev() {
+        *digit = b'0' + (value % 10) as u8;
+        value /= 10;
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use flipperzero_sys as sys;
+
+    use super::{DateTime, Error};
+
+    #[test]
+    fn unix_timestamps_round_trip() {
+        let epoch = DateTime::from_unix_timestamp(0);
+        assert_eq!(epoch, DateTime::new(1970, 1, 1, 0, 0, 0).unwrap());
+        assert_eq!(epoch.weekday(), 4);
+
+        let datetime = DateTime::from_unix_timestamp(1_700_000_000);
+        assert_eq!(datetime, DateTime::new(2023, 11, 14, 22, 13, 20).unwrap());
+        assert_eq!(datetime.weekday(), 2);
+
+        let leap_day = DateTime::new(2024, 2, 29, 23, 59, 59).unwrap();
+        assert_eq!(
+            DateTime::from_unix_timestamp(leap_day.to_unix_timestamp()),
+            leap_day
+        );
+        let last = DateTime::from_unix_timestamp(u32::MAX);
+        assert_eq!(last, DateTime::new(2106, 2, 7, 6, 28, 15).unwrap());
+        assert_eq!(last.to_unix_timestamp(), u32::MAX);
+        assert_eq!(DateTime::new(2106, 2, 7, 6, 28, 16), Err(Error::Invalid));
+    }
+
+    #[test]
+    fn timestamps_match_the_firmware() {
+        for timestamp in [946_684_800, 951_782_400, 1_700_000_000, 4_102_444_799] {
+            let datetime = DateTime::from_unix_timestamp(timestamp);
+            let mut raw = datetime.to_sys();
+            assert!(unsafe { sys::datetime_validate_datetime(&mut raw) });
+            assert_eq!(
+                unsafe { sys::datetime_datetime_to_timestamp(&mut raw) },
+                timestamp
+            );
+            assert_eq!(DateTime::from_sys(&raw), datetime);
+        }
+    }
+
+    #[test]
+    fn invalid_fields_are_rejected() {
+        assert_eq!(DateTime::new(2023, 2, 29, 0, 0, 0), Err(Error::Invalid));
+        assert_eq!(DateTime::new(2023, 13, 1, 0, 0, 0), Err(Error::Invalid));
+        assert_eq!(DateTime::new(2023, 4, 31, 0, 0, 0), Err(Error::Invalid));
+        assert_eq!(DateTime::new(2023, 1, 1, 24, 0, 0), Err(Error::Invalid));
+        assert_eq!(DateTime::new(1969, 12, 31, 0, 0, 0), Err(Error::Invalid));
+        assert!(DateTime::new(2000, 2, 29, 0, 0, 0).is_ok());
+
+        let epoch = DateTime::from_unix_timestamp(0);
+        assert_eq!(epoch.set_now(), Err(Error::OutOfRtcRange));
+    }
+
+    #[test]
+    fn clock_is_in_range() {
+        let now = DateTime::now();
+        assert!((2000..=2099).contains(&now.year()));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn formats_dates_and_times() {
+        use alloc::format;
+
+        use super::{DateFormat, TimeFormat};
+
+        let datetime = DateTime::new(2024, 5, 1, 0, 4, 5).unwrap();
+        assert_eq!(format!("{}", datetime), "2024-05-01T00:04:05");
+        assert_eq!(
+            format!("{}", datetime.date(DateFormat::Dmy, '.')),
+            "01.05.2024"
+        );
+        assert_eq!(
+            format!("{}", datetime.date(DateFormat::Mdy, '/')),
+            "05/01/2024"
+        );
+        assert_eq!(
+            format!("{}", datetime.date(DateFormat::Ymd, '-')),
+            "2024-05-01"
+        );
+        assert_eq!(
+            format!("{}", datetime.time(TimeFormat::H24, false)),
+            "00:04"
+        );
+        assert_eq!(
+            format!("{}", datetime.time(TimeFormat::H12, true)),
+            "12:04:05 AM"
+        );
+        let afternoon = DateTime::new(2024, 5, 1, 13, 30, 0).unwrap();
+        assert_eq!(
+            format!("{}", afternoon.time(TimeFormat::H12, false)),
+            "01:30 PM"
+        );
+    }
+}
@@ -0,0 +1,507 @@
+//! Serial EEPROMs.
+
+use crate::furi::time::{Duration, Instant};
+use crate::gpio::i2c::{Bus, DeviceAddress, Transport};
+use crate::io::{Error, Read, Seek, SeekFrom, Write};
+
+/// Largest page size supported by [`I2cEeprom`].
+pub const MAX_PAGE_SIZE: usize = 256;
+
+/// Timeout of a single transfer, in milliseconds.
+const TRANSFER_TIMEOUT_MS: u64 = 50;
+/// How long to wait for a page to be written, in milliseconds.
+///
+/// The datasheets of the 24Cxx family specify at most 5 or 10 ms.
+const WRITE_CYCLE_TIMEOUT_MS: u64 = 20;
+
+/// An EEPROM of the 24Cxx family, such as a 24C02 or 24C256, which can be read, written
+/// and seeked like a file of the size of the EEPROM.
+///
+/// EEPROMs of up to 2 KiB are addressed with one byte, and larger ones with two. Address
+/// bits that don't fit into these bytes select the device address, as on the 24C16 or
+/// 24C1024. Writes are split at page boundaries, and wait for the EEPROM to finish
+/// writing each page.
+///
+/// Whenever the EEPROM doesn't acknowledge a transfer, usually because it isn't
+/// connected, reads and writes fail with [`Error::NotReady`].
+///
+/// # Examples
+///
+/// Dumping a 24C02 to the SD card:
+///
+/// ```no_run
+/// # use flipperzero::devices::I2cEeprom;
+/// # use flipperzero::gpio::i2c::DeviceAddress;
+/// # use flipperzero::io::Error;
+/// # use flipperzero::storage::{self, OpenOptions};
+/// # fn main() -> Result<(), Error> {
+/// let mut eeprom = I2cEeprom::new(DeviceAddress::new(0x50), 256, 8);
+/// let mut file = OpenOptions::new()
+///     .write(true)
+///     .create_always(true)
+///     .open(c"/ext/eeprom.bin")?;
+/// storage::copy(&mut eeprom, &mut file)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct I2cEeprom<T = Bus> {
+    transport: T,
+    address: DeviceAddress,
+    size: u32,
+    page_size: u32,
+    position: u32,
+}
+
+impl I2cEeprom<Bus> {
+    /// Creates a driver for the EEPROM at `address` on the external I2C bus, with the
+    /// given size and page size in bytes.
+    ///
+    /// `address` is the address with all address pins low, which is `0x50` for most
+    /// EEPROMs.
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`I2cEeprom::with_transport`].
+    pub fn new(address: DeviceAddress, size: u32, page_size: u32) -> Self {
+        Self::with_transport(Bus::EXTERNAL, address, size, page_size)
+    }
+}
+
+impl<T: Transport> I2cEeprom<T> {
+    /// Creates a driver for the EEPROM at `address`, which is accessed through
+    /// `transport`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the page size is not a power of two of at most [`MAX_PAGE_SIZE`], if
+    /// the size is not a multiple of the page size, or if the EEPROM would need more
+    /// than three address bits in the device address.
+    pub fn with_transport(transport: T, address: DeviceAddress, size: u32, page_size: u32) -> Self {
+        assert!(
+            page_size.is_power_of_two() && page_size as usize <= MAX_PAGE_SIZE,
+            "invalid page size"
+        );
+        assert!(size > 0 && size % page_size == 0, "invalid size");
+        let eeprom = Self {
+            transport,
+            address,
+            size,
+            page_size,
+            position: 0,
+        };
+        assert!(
+            size <= eeprom.block_size() << 3,
+            "size too large for the addressing scheme"
+        );
+        eeprom
+    }
+
+    /// Returns the size of the EEPROM in bytes.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Returns the page size of the EEPROM in bytes.
+    pub fn page_size(&self) -> u32 {
+        self.page_size
+    }
+
+    /// Returns the number of memory address bytes sent with every transfer.
+    fn address_len(&self) -> usize {
+        if self.size <= 2048 {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// Returns the number of bytes that can be addressed with the memory address.
+    fn block_size(&self) -> u32 {
+        1 << (8 * self.address_len())
+    }
+
+    /// Returns the device address and the memory address of `offset`, and how many
+    /// bytes of the latter are used.
+    fn locate(&self, offset: u32) -> (DeviceAddress, [u8; 2], usize) {
+        let block = (offset / self.block_size()) as u8;
+        let device = DeviceAddress::new(self.address.value() | block);
+        match self.address_len() {
+            1 => (device, [offset as u8, 0], 1),
+            _ => (device, [(offset >> 8) as u8, offset as u8], 2),
+        }
+    }
+
+    /// Waits for the EEPROM to finish writing a page, which it signals by acknowledging
+    /// its address again.
+    fn wait_for_write(&mut self, device: DeviceAddress) -> Result<(), Error> {
+        let start = Instant::now();
+        let poll_timeout = Duration::from_millis(1);
+        while !self.transport.is_device_ready(device, poll_timeout) {
+            if start.elapsed() > Duration::from_millis(WRITE_CYCLE_TIMEOUT_MS) {
+                return Err(Error::NotReady);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the end of the range starting at the current position that is at most
+    /// `len` bytes long and ends within the EEPROM.
+    fn end(&self, len: usize) -> u32 {
+        let remaining = self.size - self.position;
+        self.position + u32::try_from(len).map_or(remaining, |len| len.min(remaining))
+    }
+}
+
+impl<T: Transport> Read for I2cEeprom<T> {
+    /// Reads from the current position, stopping at the end of the EEPROM.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let end = self.end(buf.len());
+        let timeout = Duration::from_millis(TRANSFER_TIMEOUT_MS);
+        let mut read = 0;
+        while self.position < end {
+            // Sequential reads wrap around at the end of the addressable block.
+            let block_end = (self.position / self.block_size() + 1) * self.block_size();
+            let chunk_end = end.min(block_end);
+            let len = (chunk_end - self.position) as usize;
+
+            let (device, address, address_len) = self.locate(self.position);
+            self.transport
+                .write_read(
+                    device,
+                    &address[..address_len],
+                    &mut buf[read..read + len],
+                    timeout,
+                )
+                .map_err(|_| Error::NotReady)?;
+            self.position = chunk_end;
+            read += len;
+        }
+        Ok(read)
+    }
+}
+
+impl<T: Transport> Write for I2cEeprom<T> {
+    /// Writes at the current position, stopping at the end of the EEPROM.
+    ///
+    /// Every page that is written to takes a write cycle of a few milliseconds.
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        let end = self.end(buf.len());
+        let timeout = Duration::from_millis(TRANSFER_TIMEOUT_MS);
+        let mut written = 0;
+        while self.position < end {
+            // Writes wrap around at the end of the page.
+            let page_end = (self.position / self.page_size + 1) * self.page_size;
+            let chunk_end = end.min(page_end);
+            let len = (chunk_end - self.position) as usize;
+
+            let (device, address, address_len) = self.locate(self.position);
+            let mut packet = [0; 2 + MAX_PAGE_SIZE];
+            packet[..address_len].copy_from_slice(&address[..address_len]);
+            packet[address_len..address_len + len].copy_from_slice(&buf[written..written + len]);
+            self.transport
+                .write(device, &packet[..address_len + len], timeout)
+                .map_err(|_| Error::NotReady)?;
+            self.wait_for_write(device)?;
+            self.position = chunk_end;
+            written += len;
+        }
+        Ok(written)
+    }
+
+    /// Does nothing, as writes only return once the EEPROM has finished writing.
+    fn flush(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<T: Transport> Seek for I2cEeprom<T> {
+    /// Moves to a position within the EEPROM, or to its end.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidParameter`] if the position is outside of the EEPROM.
+    fn seek(&mut self, pos: SeekFrom) -> Result<usize, Error> {
+        let target = match pos {
+            SeekFrom::Start(offset) => i64::try_from(offset).unwrap_or(i64::MAX),
+            SeekFrom::End(offset) => i64::from(self.size).saturating_add(offset),
+            SeekFrom::Current(offset) => i64::from(self.position).saturating_add(offset),
+        };
+        if !(0..=i64::from(self.size)).contains(&target) {
+            return Err(Error::InvalidParameter);
+        }
+        self.position = target as u32;
+        Ok(self.position as usize)
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[flipperzero_test::tests]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::I2cEeprom;
+    use crate::furi::time::Duration;
+    use crate::gpio::i2c::{self, DeviceAddress, Transport};
+    use crate::io::cursor::Cursor;
+    use crate::io::{Error, Read, Seek, SeekFrom, Write};
+    use crate::storage;
+
+    const BASE: u8 = 0x50;
+
+    /// Simulates a 24Cxx EEPROM, including its wrap-around within pages and blocks.
+    struct MockEeprom {
+        memory: Vec<u8>,
+        address_len: usize,
+        page_size: usize,
+        present: bool,
+        /// How many polls the EEPROM stays busy for after a write.
+        write_cycle_polls: usize,
+        busy: usize,
+        /// Device address and bytes of every write.
+        writes: Vec<(u8, Vec<u8>)>,
+        /// Device address and length of every read.
+        reads: Vec<(u8, usize)>,
+        polls: usize,
+    }
+
+    impl MockEeprom {
+        fn new(size: usize, page_size: usize) -> Self {
+            Self {
+                memory: (0..size).map(|i| i as u8).collect(),
+                address_len: if size <= 2048 { 1 } else { 2 },
+                page_size,
+                present: true,
+                write_cycle_polls: 0,
+                busy: 0,
+                writes: Vec::new(),
+                reads: Vec::new(),
+                polls: 0,
+            }
+        }
+
+        fn block_size(&self) -> usize {
+            1 << (8 * self.address_len)
+        }
+
+        /// Returns the memory offset a transfer starts at, or `None` if the EEPROM
+        /// doesn't acknowledge it.
+        fn start(&self, device: DeviceAddress, address: &[u8]) -> Option<usize> {
+            if !self.present || self.busy > 0 || address.len() != self.address_len {
+                return None;
+            }
+            let block = usize::from(device.value().checked_sub(BASE)?);
+            let offset = address
+                .iter()
+                .fold(0, |offset, &b| offset << 8 | usize::from(b));
+            Some(block * self.block_size() + offset)
+        }
+    }
+
+    impl Transport for MockEeprom {
+        fn write(
+            &mut self,
+            device: DeviceAddress,
+            data: &[u8],
+            _timeout: Duration,
+        ) -> Result<(), i2c::Error> {
+            let (address, data) = data.split_at(self.address_len.min(data.len()));
+            let offset = self
+                .start(device, address)
+                .ok_or(i2c::Error::TransferFailed)?;
+            let page = offset - offset % self.page_size;
+            for (i, &b) in data.iter().enumerate() {
+                self.memory[page + (offset - page + i) % self.page_size] = b;
+            }
+            self.writes.push((device.value(), [address, data].concat()));
+            self.busy = self.write_cycle_polls;
+            Ok(())
+        }
+
+        fn write_read(
+            &mut self,
+            device: DeviceAddress,
+            write: &[u8],
+            read: &mut [u8],
+            _timeout: Duration,
+        ) -> Result<(), i2c::Error> {
+            let offset = self
+                .start(device, write)
+                .ok_or(i2c::Error::TransferFailed)?;
+            let block = offset - offset % self.block_size();
+            for (i, b) in read.iter_mut().enumerate() {
+                *b = self.memory[block + (offset - block + i) % self.block_size()];
+            }
+            self.reads.push((device.value(), read.len()));
+            Ok(())
+        }
+
+        fn is_device_ready(&mut self, _device: DeviceAddress, _timeout: Duration) -> bool {
+            self.polls += 1;
+            if self.busy > 0 {
+                self.busy -= 1;
+                return false;
+            }
+            self.present
+        }
+    }
+
+    fn eeprom(size: u32, page_size: u32) -> I2cEeprom<MockEeprom> {
+        let mock = MockEeprom::new(size as usize, page_size as usize);
+        I2cEeprom::with_transport(mock, DeviceAddress::new(BASE), size, page_size)
+    }
+
+    #[test]
+    fn splits_writes_at_pages() {
+        let mut eeprom = eeprom(256, 8);
+        let data: Vec<u8> = (100..120).collect();
+        eeprom.seek(SeekFrom::Start(5)).unwrap();
+        assert_eq!(eeprom.write(&data).unwrap(), 20);
+
+        let lens: Vec<usize> = eeprom
+            .transport
+            .writes
+            .iter()
+            .map(|(_, bytes)| bytes.len() - 1)
+            .collect();
+        assert_eq!(lens, [3, 8, 8, 1]);
+        assert_eq!(eeprom.transport.memory[5..25], data);
+        assert_eq!(eeprom.transport.memory[4], 4);
+        assert_eq!(eeprom.transport.memory[25], 25);
+        assert_eq!(eeprom.stream_position().unwrap(), 25);
+    }
+
+    #[test]
+    fn single_byte_write() {
+        let mut eeprom = eeprom(256, 8);
+        eeprom.seek(SeekFrom::Start(7)).unwrap();
+        eeprom.write_all(&[0xAA]).unwrap();
+
+        assert_eq!(eeprom.transport.writes, [(BASE, Vec::from([7, 0xAA]))]);
+    }
+
+    #[test]
+    fn full_page_write() {
+        let mut eeprom = eeprom(256, 16);
+        eeprom.seek(SeekFrom::Start(32)).unwrap();
+        eeprom.write_all(&[0xEE; 16]).unwrap();
+
+        assert_eq!(eeprom.transport.writes.len(), 1);
+        assert!(eeprom.transport.memory[32..48].iter().all(|&b| b == 0xEE));
+    }
+
+    #[test]
+    fn selects_device_address_of_block() {
+        let mut eeprom = eeprom(2048, 16);
+        let data = [0x11; 12];
+        eeprom.seek(SeekFrom::Start(250)).unwrap();
+        eeprom.write_all(&data).unwrap();
+
+        let devices: Vec<u8> = eeprom.transport.writes.iter().map(|w| w.0).collect();
+        assert_eq!(devices, [BASE, BASE + 1]);
+        assert_eq!(eeprom.transport.writes[1].1[0], 0);
+
+        let mut buf = [0; 12];
+        eeprom.seek(SeekFrom::Start(250)).unwrap();
+        assert_eq!(eeprom.read(&mut buf).unwrap(), 12);
+        assert_eq!(buf, data);
+        assert_eq!(eeprom.transport.reads, [(BASE, 6), (BASE + 1, 6)]);
+    }
+
+    #[test]
+    fn two_byte_addresses() {
+        let mut eeprom = eeprom(4096, 32);
+        eeprom.seek(SeekFrom::Start(0x123)).unwrap();
+        eeprom.write_all(&[0xAA, 0xBB]).unwrap();
+
+        assert_eq!(
+            eeprom.transport.writes,
+            [(BASE, Vec::from([0x01, 0x23, 0xAA, 0xBB]))]
+        );
+        assert_eq!(eeprom.transport.memory[0x123..0x125], [0xAA, 0xBB]);
+    }
+
+    #[test]
+    fn reads_whole_two_byte_eeprom_at_once() {
+        let mut eeprom = eeprom(4096, 32);
+        let mut buf = [0; 4096];
+        assert_eq!(eeprom.read(&mut buf).unwrap(), 4096);
+
+        assert_eq!(eeprom.transport.reads, [(BASE, 4096)]);
+        assert_eq!(buf[..], eeprom.transport.memory[..]);
+    }
+
+    #[test]
+    fn waits_for_write_cycle() {
+        let mut eeprom = eeprom(256, 8);
+        eeprom.transport.write_cycle_polls = 3;
+        eeprom.write_all(&[0; 16]).unwrap();
+
+        // Three busy polls and a ready one after each of the two pages.
+        assert_eq!(eeprom.transport.polls, 8);
+        assert_eq!(eeprom.transport.writes.len(), 2);
+    }
+
+    #[test]
+    fn write_cycle_timeout() {
+        let mut eeprom = eeprom(256, 8);
+        eeprom.transport.write_cycle_polls = usize::MAX;
+
+        assert!(matches!(eeprom.write(&[0]), Err(Error::NotReady)));
+    }
+
+    #[test]
+    fn missing_device() {
+        let mut eeprom = eeprom(256, 8);
+        eeprom.transport.present = false;
+
+        assert!(matches!(eeprom.read(&mut [0; 4]), Err(Error::NotReady)));
+        assert!(matches!(eeprom.write(&[0; 4]), Err(Error::NotReady)));
+    }
+
+    #[test]
+    fn stops_at_end() {
+        let mut eeprom = eeprom(256, 8);
+        assert_eq!(eeprom.seek(SeekFrom::End(-4)).unwrap(), 252);
+        assert_eq!(eeprom.write(&[0; 8]).unwrap(), 4);
+        assert_eq!(eeprom.write(&[0; 8]).unwrap(), 0);
+        assert!(matches!(eeprom.write_all(&[0]), Err(Error::WriteZero)));
+        assert_eq!(eeprom.read(&mut [0; 8]).unwrap(), 0);
+    }
+
+    #[test]
+    fn seeks_within_device() {
+        let mut eeprom = eeprom(256, 8);
+        assert_eq!(eeprom.stream_len().unwrap(), 256);
+        assert_eq!(eeprom.seek(SeekFrom::Start(256)).unwrap(), 256);
+        assert_eq!(eeprom.seek(SeekFrom::Current(-56)).unwrap(), 200);
+        assert!(matches!(
+            eeprom.seek(SeekFrom::Start(257)),
+            Err(Error::InvalidParameter)
+        ));
+        assert!(matches!(
+            eeprom.seek(SeekFrom::Current(-201)),
+            Err(Error::InvalidParameter)
+        ));
+        assert!(matches!(
+            eeprom.seek(SeekFrom::End(1)),
+            Err(Error::InvalidParameter)
+        ));
+        assert_eq!(eeprom.stream_position().unwrap(), 200);
+    }
+
+    #[test]
+    fn dump_and_restore() {
+        let mut eeprom = eeprom(512, 16);
+        let mut dump = Cursor::empty(Vec::new());
+        assert_eq!(storage::copy(&mut eeprom, &mut dump).unwrap(), 512);
+        assert_eq!(dump.data(), eeprom.transport.memory);
+
+        let image: Vec<u8> = (0..512u32).map(|i| (i * 7) as u8).collect();
+        eeprom.rewind().unwrap();
+        assert_eq!(
+            storage::copy(&mut Cursor::new(&image), &mut eeprom).unwrap(),
+            512
+        );
+        assert_eq!(eeprom.transport.memory, image);
+    }
+}
@@ -0,0 +1,674 @@
+//! Serial NOR flash.
+
+use crate::furi::thread;
+use crate::furi::time::{Duration, Instant};
+use crate::gpio::spi::{Bus, Transport};
+use crate::io::{Error, Read, Seek, SeekFrom, Write};
+
+/// Size of the smallest area of a [`SpiFlash`] that can be erased.
+pub const SECTOR_SIZE: u32 = 4096;
+/// Size of the area that a single program command can write to.
+const PAGE_SIZE: u32 = 256;
+
+const READ_JEDEC_ID: u8 = 0x9F;
+const READ_STATUS: u8 = 0x05;
+const WRITE_ENABLE: u8 = 0x06;
+const READ_DATA: u8 = 0x03;
+const PAGE_PROGRAM: u8 = 0x02;
+const SECTOR_ERASE: u8 = 0x20;
+
+/// Set in the status register while a program or erase is in progress.
+const STATUS_BUSY: u8 = 1 << 0;
+/// Set in the status register while programs and erases are enabled.
+const STATUS_WRITE_ENABLED: u8 = 1 << 1;
+
+/// Timeout of a single transaction, in milliseconds.
+const TRANSFER_TIMEOUT_MS: u64 = 100;
+/// How long to wait for a page to be programmed, in milliseconds.
+const PROGRAM_TIMEOUT_MS: u64 = 10;
+/// How long to wait for a sector to be erased, in milliseconds.
+const SECTOR_ERASE_TIMEOUT_MS: u64 = 500;
+
+/// The JEDEC ID of a flash chip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JedecId {
+    /// The manufacturer, such as `0xEF` for Winbond.
+    pub manufacturer: u8,
+    /// The memory type, which is specific to the manufacturer.
+    pub memory_type: u8,
+    /// The capacity, as the base-2 logarithm of the size in bytes.
+    pub capacity: u8,
+}
+
+/// A NOR flash chip of the 25 series, such as a W25Q16 or MX25L3206, which can be read
+/// and seeked like a file of the size of the chip.
+///
+/// Programming a flash chip can only clear bits, so areas have to be erased (setting
+/// all their bits) before they are written to. [`SpiFlash::erase_range`] erases whole
+/// sectors, and [`Write`] and [`SpiFlash::program`] must only target erased areas.
+///
+/// Chips of up to 16 MiB, which use 3-byte addresses, are supported. Whenever a
+/// transaction fails or the chip doesn't finish a program or erase in time, operations
+/// fail with [`Error::NotReady`].
+///
+/// # Examples
+///
+/// Restoring a chip from the SD card:
+///
+/// ```no_run
+/// # use flipperzero::devices::SpiFlash;
+/// # use flipperzero::io::Error;
+/// # use flipperzero::storage::{self, OpenOptions};
+/// # fn main() -> Result<(), Error> {
+/// let mut flash = SpiFlash::detect()?;
+/// let mut file = OpenOptions::new()
+///     .read(true)
+///     .open_existing(true)
+///     .open(c"/ext/flash.bin")?;
+/// flash.erase_range(0, flash.capacity())?;
+/// storage::copy(&mut file, &mut flash)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct SpiFlash<T = Bus> {
+    transport: T,
+    id: JedecId,
+    capacity: u32,
+    position: u32,
+}
+
+impl SpiFlash<Bus> {
+    /// Detects the flash chip on the external SPI bus from its JEDEC ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::AlreadyOpen`] if the external SPI bus is in use, and otherwise
+    /// the same errors as [`SpiFlash::detect_with`].
+    pub fn detect() -> Result<Self, Error> {
+        let bus = Bus::external().ok_or(Error::AlreadyOpen)?;
+        Self::detect_with(bus)
+    }
+}
+
+impl<T: Transport> SpiFlash<T> {
+    /// Detects the flash chip accessed through `transport` from its JEDEC ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotReady`] if no chip answers, and [`Error::NotImplemented`] if
+    /// the chip is larger than 16 MiB or doesn't report its capacity.
+    pub fn detect_with(mut transport: T) -> Result<Self, Error> {
+        let mut id = [0; 3];
+        transport
+            .transaction(&[READ_JEDEC_ID], &mut id, transfer_timeout())
+            .map_err(|_| Error::NotReady)?;
+        // Without a chip, MISO stays at the same level.
+        if id == [0x00; 3] || id == [0xFF; 3] {
+            return Err(Error::NotReady);
+        }
+
+        let [manufacturer, memory_type, capacity] = id;
+        if !(0x10..=0x18).contains(&capacity) {
+            return Err(Error::NotImplemented);
+        }
+        Ok(Self {
+            transport,
+            id: JedecId {
+                manufacturer,
+                memory_type,
+                capacity,
+            },
+            capacity: 1 << capacity,
+            position: 0,
+        })
+    }
+
+    /// Returns the JEDEC ID of the chip.
+    pub fn jedec_id(&self) -> JedecId {
+        self.id
+    }
+
+    /// Returns the size of the chip in bytes.
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    /// Erases the sectors in `offset..offset + len`, setting all their bytes to `0xFF`.
+    ///
+    /// Erasing a sector takes up to a few hundred milliseconds, and a whole chip up to
+    /// a minute.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidParameter`] if `offset` or `len` isn't a multiple of
+    /// [`SECTOR_SIZE`] or the range isn't within the chip, and [`Error::Denied`] if the
+    /// chip is write-protected.
+    pub fn erase_range(&mut self, offset: u32, len: u32) -> Result<(), Error> {
+        if offset % SECTOR_SIZE != 0 || len % SECTOR_SIZE != 0 {
+            return Err(Error::InvalidParameter);
+        }
+        let end = self.check_range(offset, len)?;
+        for sector in (offset..end).step_by(SECTOR_SIZE as usize) {
+            self.write_enable()?;
+            self.command(SECTOR_ERASE, sector, &[])?;
+            self.wait_ready(SECTOR_ERASE_TIMEOUT_MS, true)?;
+        }
+        Ok(())
+    }
+
+    /// Programs `data` at `offset`, which must have been erased before.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidParameter`] if `data` doesn't fit into the chip at
+    /// `offset`, and [`Error::Denied`] if the chip is write-protected.
+    pub fn program(&mut self, offset: u32, data: &[u8]) -> Result<(), Error> {
+        let len = u32::try_from(data.len()).map_err(|_| Error::InvalidParameter)?;
+        let end = self.check_range(offset, len)?;
+
+        let mut address = offset;
+        let mut written = 0;
+        while address < end {
+            // Programs wrap around at the end of the page.
+            let page_end = (address / PAGE_SIZE + 1) * PAGE_SIZE;
+            let chunk_end = end.min(page_end);
+            let len = (chunk_end - address) as usize;
+
+            self.write_enable()?;
+            self.command(PAGE_PROGRAM, address, &data[written..written + len])?;
+            self.wait_ready(PROGRAM_TIMEOUT_MS, false)?;
+            address = chunk_end;
+            written += len;
+        }
+        Ok(())
+    }
+
+    /// Returns the end of `offset..offset + len` if it is within the chip.
+    fn check_range(&self, offset: u32, len: u32) -> Result<u32, Error> {
+        offset
+            .checked_add(len)
+            .filter(|&end| end <= self.capacity)
+            .ok_or(Error::InvalidParameter)
+    }
+
+    /// Sends `command` with the 3-byte `address` followed by `data`.
+    fn command(&mut self, command: u8, address: u32, data: &[u8]) -> Result<(), Error> {
+        let mut packet = [0; 4 + PAGE_SIZE as usize];
+        packet[0] = command;
+        packet[1..4].copy_from_slice(&address.to_be_bytes()[1..]);
+        packet[4..4 + data.len()].copy_from_slice(data);
+        self.transport
+            .transaction(&packet[..4 + data.len()], &mut [], transfer_timeout())
+            .map_err(|_| Error::NotReady)
+    }
+
+    fn read_status(&mut self) -> Result<u8, Error> {
+        let mut status = [0];
+        self.transport
+            .transaction(&[READ_STATUS], &mut status, transfer_timeout())
+            .map_err(|_| Error::NotReady)?;
+        Ok(status[0])
+    }
+
+    /// Enables the next program or erase, which the chip requires before each of them.
+    fn write_enable(&mut self) -> Result<(), Error> {
+        self.transport
+            .transaction(&[WRITE_ENABLE], &mut [], transfer_timeout())
+            .map_err(|_| Error::NotReady)?;
+        // Write protection keeps the chip from enabling writes.
+        if self.read_status()? & STATUS_WRITE_ENABLED == 0 {
+            return Err(Error::Denied);
+        }
+        Ok(())
+    }
+
+    /// Polls the status register until the chip has finished a program or erase.
+    ///
+    /// Erases take long enough that the chip is only polled every millisecond.
+    fn wait_ready(&mut self, timeout_ms: u64, sleep: bool) -> Result<(), Error> {
+        let start = Instant::now();
+        while self.read_status()? & STATUS_BUSY != 0 {
+            if start.elapsed() > Duration::from_millis(timeout_ms) {
+                return Err(Error::NotReady);
+            }
+            if sleep {
+                thread::sleep(core::time::Duration::from_millis(1));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the end of the range starting at the current position that is at most
+    /// `len` bytes long and ends within the chip.
+    fn end(&self, len: usize) -> u32 {
+        let remaining = self.capacity - self.position;
+        self.position + u32::try_from(len).map_or(remaining, |len| len.min(remaining))
+    }
+}
+
+fn transfer_timeout() -> Duration {
+    Duration::from_millis(TRANSFER_TIMEOUT_MS)
+}
+
+impl<T: Transport> Read for SpiFlash<T> {
+    /// Reads from the current position, stopping at the end of the chip.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let end = self.end(buf.len());
+        let len = (end - self.position) as usize;
+        if len == 0 {
+            return Ok(0);
+        }
+
+        let mut command = [READ_DATA, 0, 0, 0];
+        command[1..].copy_from_slice(&self.position.to_be_bytes()[1..]);
+        self.transport
+            .transaction(&command, &mut buf[..len], transfer_timeout())
+            .map_err(|_| Error::NotReady)?;
+        self.position = end;
+        Ok(len)
+    }
+}
+
+impl<T: Transport> Write for SpiFlash<T> {
+    /// Programs at the current position, stopping at the end of the chip.
+    ///
+    /// This must only target areas erased with [`SpiFlash::erase_range`], as
+    /// programming can only clear bits.
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        let end = self.end(buf.len());
+        let len = (end - self.position) as usize;
+        self.program(self.position, &buf[..len])?;
+        self.position = end;
+        Ok(len)
+    }
+
+    /// Does nothing, as writes only return once the chip has finished programming.
+    fn flush(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<T: Transport> Seek for SpiFlash<T> {
+    /// Moves to a position within the chip, or to its end.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidParameter`] if the position is outside of the chip.
+    fn seek(&mut self, pos: SeekFrom) -> Result<usize, Error> {
+        let target = match pos {
+            SeekFrom::Start(offset) => i64::try_from(offset).unwrap_or(i64::MAX),
+            SeekFrom::End(offset) => i64::from(self.capacity).saturating_add(offset),
+            SeekFrom::Current(offset) => i64::from(self.position).saturating_add(offset),
+        };
+        if !(0..=i64::from(self.capacity)).contains(&target) {
+            return Err(Error::InvalidParameter);
+        }
+        self.position = target as u32;
+        Ok(self.position as usize)
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[flipperzero_test::tests]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::{
+        SpiFlash, PAGE_PROGRAM, PAGE_SIZE, READ_DATA, READ_JEDEC_ID, READ_STATUS, SECTOR_ERASE,
+        SECTOR_SIZE, STATUS_BUSY, STATUS_WRITE_ENABLED, WRITE_ENABLE,
+    };
+    use crate::furi::time::Duration;
+    use crate::gpio::spi::{self, Transport};
+    use crate::io::{Error, Read, Seek, SeekFrom, Write};
+    use crate::storage;
+
+    /// JEDEC ID of a 64 KiB chip.
+    const ID_64K: [u8; 3] = [0xEF, 0x40, 0x10];
+
+    /// Simulates a 25-series flash chip.
+    ///
+    /// To keep tests small, the memory is one sector that is mirrored across the whole
+    /// chip, as if its upper address lines weren't connected.
+    struct MockFlash {
+        id: [u8; 3],
+        memory: Vec<u8>,
+        write_enabled: bool,
+        protected: bool,
+        /// How many status reads the chip stays busy for after a program or erase.
+        busy_polls: usize,
+        busy: usize,
+        /// Command of every transaction.
+        commands: Vec<u8>,
+        /// Address and length of every page program.
+        programs: Vec<(u32, usize)>,
+        /// Address of every sector erase.
+        erases: Vec<u32>,
+    }
+
+    impl MockFlash {
+        fn new(id: [u8; 3]) -> Self {
+            Self {
+                id,
+                memory: (0..SECTOR_SIZE).map(|i| i as u8).collect(),
+                write_enabled: false,
+                protected: false,
+                busy_polls: 0,
+                busy: 0,
+                commands: Vec::new(),
+                programs: Vec::new(),
+                erases: Vec::new(),
+            }
+        }
+
+        fn cell(&mut self, address: u32) -> &mut u8 {
+            let len = self.memory.len();
+            &mut self.memory[address as usize % len]
+        }
+
+        /// Starts a program or erase if writes are enabled.
+        fn start_write(&mut self) -> bool {
+            let enabled = self.write_enabled && self.busy == 0;
+            self.write_enabled = false;
+            if enabled {
+                self.busy = self.busy_polls;
+            }
+            enabled
+        }
+    }
+
+    impl Transport for MockFlash {
+        fn transaction(
+            &mut self,
+            write: &[u8],
+            read: &mut [u8],
+            _timeout: Duration,
+        ) -> Result<(), spi::Error> {
+            let (&command, rest) = write.split_first().ok_or(spi::Error::TransferFailed)?;
+            self.commands.push(command);
+            let address = || u32::from_be_bytes([0, rest[0], rest[1], rest[2]]);
+
+            match command {
+                READ_JEDEC_ID => read.copy_from_slice(&self.id),
+                READ_STATUS => {
+                    read[0] = if self.write_enabled {
+                        STATUS_WRITE_ENABLED
+                    } else {
+                        0
+                    };
+                    if self.busy > 0 {
+                        read[0] |= STATUS_BUSY;
+                        self.busy -= 1;
+                    }
+                }
+                WRITE_ENABLE => self.write_enabled = !self.protected && self.busy == 0,
+                READ_DATA => {
+                    let start = address();
+                    for (i, b) in read.iter_mut().enumerate() {
+                        *b = *self.cell(start + i as u32);
+                    }
+                }
+                PAGE_PROGRAM => {
+                    let start = address();
+                    let data = &rest[3..];
+                    if self.start_write() {
+                        let page = start - start % PAGE_SIZE;
+                        for (i, &b) in data.iter().enumerate() {
+                            *self.cell(page + (start - page + i as u32) % PAGE_SIZE) &= b;
+                        }
+                        self.programs.push((start, data.len()));
+                    }
+                }
+                SECTOR_ERASE => {
+                    let start = address();
+                    if self.start_write() {
+                        for offset in 0..SECTOR_SIZE {
+                            *self.cell(start + offset) = 0xFF;
+                        }
+                        self.erases.push(start);
+                    }
+                }
+                _ => return Err(spi::Error::TransferFailed),
+            }
+            Ok(())
+        }
+    }
+
+    fn flash() -> SpiFlash<MockFlash> {
+        SpiFlash::detect_with(MockFlash::new(ID_64K)).unwrap()
+    }
+
+    /// Returns the bytes of an image whose content repeats within every sector.
+    fn image_byte(offset: u64) -> u8 {
+        (offset as u8).wrapping_mul(7)
+    }
+
+    struct Image {
+        offset: u64,
+        len: u64,
+    }
+
+    impl Read for Image {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+            let n = buf.len().min((self.len - self.offset) as usize);
+            for b in &mut buf[..n] {
+                *b = image_byte(self.offset);
+                self.offset += 1;
+            }
+            Ok(n)
+        }
+    }
+
+    /// Counts the bytes written to it that don't match `expected`.
+    struct Check<F> {
+        expected: F,
+        offset: u64,
+        mismatches: usize,
+    }
+
+    impl<F: Fn(u64) -> u8> Write for Check<F> {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+            for &b in buf {
+                if b != (self.expected)(self.offset) {
+                    self.mismatches += 1;
+                }
+                self.offset += 1;
+            }
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn detects_chip() {
+        let flash = SpiFlash::detect_with(MockFlash::new([0xEF, 0x40, 0x15])).unwrap();
+        assert_eq!(flash.capacity(), 2 * 1024 * 1024);
+        assert_eq!(flash.jedec_id().manufacturer, 0xEF);
+        assert_eq!(flash.jedec_id().memory_type, 0x40);
+        assert_eq!(flash.jedec_id().capacity, 0x15);
+    }
+
+    #[test]
+    fn missing_chip() {
+        for id in [[0x00; 3], [0xFF; 3]] {
+            assert!(matches!(
+                SpiFlash::detect_with(MockFlash::new(id)),
+                Err(Error::NotReady)
+            ));
+        }
+    }
+
+    #[test]
+    fn unsupported_capacity() {
+        assert!(matches!(
+            SpiFlash::detect_with(MockFlash::new([0xEF, 0x40, 0x19])),
+            Err(Error::NotImplemented)
+        ));
+    }
+
+    #[test]
+    fn reads_until_end() {
+        let mut flash = flash();
+        assert_eq!(flash.seek(SeekFrom::End(-4)).unwrap(), 65532);
+
+        let mut buf = [0; 8];
+        assert_eq!(flash.read(&mut buf).unwrap(), 4);
+        assert_eq!(buf[..4], [0xFC, 0xFD, 0xFE, 0xFF]);
+        assert_eq!(flash.read(&mut buf).unwrap(), 0);
+        assert_eq!(flash.transport.commands, [READ_JEDEC_ID, READ_DATA]);
+    }
+
+    #[test]
+    fn program_splits_at_pages() {
+        let mut flash = flash();
+        flash.erase_range(0, SECTOR_SIZE).unwrap();
+        flash.transport.commands.clear();
+
+        let data: Vec<u8> = (0..20).collect();
+        flash.program(250, &data).unwrap();
+
+        assert_eq!(flash.transport.programs, [(250, 6), (256, 14)]);
+        assert_eq!(
+            flash.transport.commands,
+            [
+                WRITE_ENABLE,
+                READ_STATUS,
+                PAGE_PROGRAM,
+                READ_STATUS,
+                WRITE_ENABLE,
+                READ_STATUS,
+                PAGE_PROGRAM,
+                READ_STATUS,
+            ]
+        );
+        assert_eq!(flash.transport.memory[250..270], data);
+        assert_eq!(flash.transport.memory[270], 0xFF);
+    }
+
+    #[test]
+    fn write_programs_at_position() {
+        let mut flash = flash();
+        flash.erase_range(0, SECTOR_SIZE).unwrap();
+        flash.seek(SeekFrom::Start(100)).unwrap();
+        flash.write_all(&[0x12, 0x34]).unwrap();
+
+        assert_eq!(flash.stream_position().unwrap(), 102);
+        assert_eq!(flash.transport.memory[99..103], [0xFF, 0x12, 0x34, 0xFF]);
+    }
+
+    #[test]
+    fn polls_until_ready() {
+        let mut flash = flash();
+        flash.transport.busy_polls = 3;
+        flash.program(0, &[0]).unwrap();
+
+        // One read to check that writes are enabled, three busy ones and a ready one.
+        let status_reads = flash
+            .transport
+            .commands
+            .iter()
+            .filter(|&&c| c == READ_STATUS)
+            .count();
+        assert_eq!(status_reads, 5);
+    }
+
+    #[test]
+    fn program_timeout() {
+        let mut flash = flash();
+        flash.transport.busy_polls = usize::MAX;
+
+        assert!(matches!(flash.program(0, &[0]), Err(Error::NotReady)));
+    }
+
+    #[test]
+    fn write_protected() {
+        let mut flash = flash();
+        flash.transport.protected = true;
+
+        assert!(matches!(flash.program(0, &[0]), Err(Error::Denied)));
+        assert!(matches!(
+            flash.erase_range(0, SECTOR_SIZE),
+            Err(Error::Denied)
+        ));
+        assert!(flash.transport.programs.is_empty());
+        assert!(flash.transport.erases.is_empty());
+    }
+
+    #[test]
+    fn erases_sectors() {
+        let mut flash = flash();
+        flash.erase_range(SECTOR_SIZE, 2 * SECTOR_SIZE).unwrap();
+
+        assert_eq!(flash.transport.erases, [SECTOR_SIZE, 2 * SECTOR_SIZE]);
+        assert!(flash.transport.memory.iter().all(|&b| b == 0xFF));
+    }
+
+    #[test]
+    fn rejects_invalid_ranges() {
+        let mut flash = flash();
+        for (offset, len) in [
+            (100, SECTOR_SIZE),
+            (0, 100),
+            (flash.capacity() - SECTOR_SIZE, 2 * SECTOR_SIZE),
+        ] {
+            assert!(matches!(
+                flash.erase_range(offset, len),
+                Err(Error::InvalidParameter)
+            ));
+        }
+        assert!(matches!(
+            flash.program(flash.capacity() - 1, &[0, 0]),
+            Err(Error::InvalidParameter)
+        ));
+        assert!(flash.transport.erases.is_empty());
+        assert!(flash.transport.programs.is_empty());
+    }
+
+    #[test]
+    fn seeks_within_chip() {
+        let mut flash = flash();
+        assert_eq!(flash.stream_len().unwrap(), 65536);
+        assert_eq!(flash.seek(SeekFrom::Start(65536)).unwrap(), 65536);
+        assert!(matches!(
+            flash.seek(SeekFrom::Start(65537)),
+            Err(Error::InvalidParameter)
+        ));
+        assert!(matches!(
+            flash.seek(SeekFrom::Current(-65537)),
+            Err(Error::InvalidParameter)
+        ));
+        assert_eq!(flash.stream_position().unwrap(), 65536);
+        assert_eq!(flash.write(&[0]).unwrap(), 0);
+    }
+
+    #[test]
+    fn dump_and_restore() {
+        let mut flash = flash();
+        let mut dump = Check {
+            expected: |offset| offset as u8,
+            offset: 0,
+            mismatches: 0,
+        };
+        assert_eq!(storage::copy(&mut flash, &mut dump).unwrap(), 65536);
+        assert_eq!(dump.mismatches, 0);
+
+        let mut image = Image {
+            offset: 0,
+            len: 65536,
+        };
+        flash.erase_range(0, flash.capacity()).unwrap();
+        flash.rewind().unwrap();
+        assert_eq!(storage::copy(&mut image, &mut flash).unwrap(), 65536);
+
+        let mut check = Check {
+            expected: image_byte,
+            offset: 0,
+            mismatches: 0,
+        };
+        flash.rewind().unwrap();
+        storage::copy(&mut flash, &mut check).unwrap();
+        assert_eq!(check.mismatches, 0);
+    }
+}
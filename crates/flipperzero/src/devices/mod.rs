@@ -0,0 +1,7 @@
+//! Drivers for devices connected to the GPIO header.
+
+pub(crate) mod eeprom;
+pub(crate) mod flash;
+
+pub use self::eeprom::{I2cEeprom, MAX_PAGE_SIZE};
+pub use self::flash::{JedecId, SpiFlash, SECTOR_SIZE};
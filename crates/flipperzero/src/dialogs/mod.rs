@@ -3,20 +3,26 @@
 #[cfg(feature = "alloc")]
 use alloc::ffi::CString;
 
-use core::ffi::{c_void, CStr};
+use core::ffi::{c_char, c_void, CStr};
+use core::fmt;
 use core::marker::PhantomData;
 use core::mem::MaybeUninit;
 use core::ptr::{self, NonNull};
 
 use flipperzero_sys as sys;
-use sys::furi::UnsafeRecord;
 
+use crate::furi::record::Record;
 use crate::furi::string::FuriString;
 use crate::gui::canvas::Align;
+use crate::io::Error;
+use crate::storage::{self, OpenOptions, Storage};
+
+/// Maximum number of characters of a file name shown by [`confirm_overwrite`].
+const OVERWRITE_NAME_MAX_CHARS: usize = 20;
 
 /// A handle to the Dialogs app.
 pub struct DialogsApp {
-    data: UnsafeRecord<sys::DialogsApp>,
+    data: Record<sys::DialogsApp>,
 }
 
 /// A dialog message.
@@ -25,13 +31,40 @@ pub struct DialogMessage<'a> {
     _phantom: PhantomData<&'a CStr>,
 }
 
-/// A dialog file browser options.
+/// Options for the file browser dialog.
+///
+/// This is a builder over [`sys::DialogsFileBrowserOptions`], with lifetimes ensuring
+/// that the extension filter, base path and icon outlive any dialog they are used with.
 #[repr(transparent)]
-pub struct DialogFileBrowserOptions<'a> {
+pub struct FileBrowserOptions<'a> {
     data: sys::DialogsFileBrowserOptions,
     _phantom: PhantomData<&'a ()>,
 }
 
+/// Errors which can occur when showing the file browser.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FileBrowserError {
+    /// The start path is not located within the base path of the browser.
+    ///
+    /// The user cannot navigate above the base path, so the browser would be unable
+    /// to show the start path.
+    StartPathOutsideBasePath,
+}
+
+/// The choice made by the user in [`confirm_overwrite`].
+#[derive(Debug)]
+pub enum OverwriteChoice {
+    /// The file should be written to the original path.
+    ///
+    /// This is also returned when no file exists at the path.
+    Overwrite,
+    /// The file should be written to this free path in the same directory instead.
+    Rename(FuriString),
+    /// The save should be aborted.
+    Cancel,
+}
+
 /// Button pressed on a dialog.
 pub enum DialogMessageButton {
     Back,
@@ -44,7 +77,7 @@ impl DialogsApp {
     /// Obtains a handle to the Dialogs app.
     pub fn open() -> Self {
         Self {
-            data: unsafe { UnsafeRecord::open(c"dialogs".as_ptr()) },
+            data: Record::open(),
         }
     }
 
@@ -53,17 +86,32 @@ impl DialogsApp {
         let button_sys =
             unsafe { sys::dialog_message_show(self.data.as_ptr(), message.data.as_ptr()) };
 
-        DialogMessageButton::from_sys(button_sys).expect("Invalid button")
+        DialogMessageButton::from_sys(button_sys)
     }
 
     /// Displays a file browser.
     ///  - path is a optional preselected file path
     ///  - options are optional file browser options
+    ///
+    /// Returns `Ok(None)` if the user dismissed the browser without selecting a file.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FileBrowserError::StartPathOutsideBasePath`] if `options` has a base path
+    /// set and `path` does not lie within it.
     pub fn show_file_browser(
         &mut self,
         path: Option<&mut FuriString>,
-        options: Option<&DialogFileBrowserOptions>,
-    ) -> Option<FuriString> {
+        options: Option<&FileBrowserOptions>,
+    ) -> Result<Option<FuriString>, FileBrowserError> {
+        if let (Some(path), Some(base_path)) =
+            (path.as_deref(), options.and_then(|o| o.base_path()))
+        {
+            if !is_within(base_path.to_bytes(), path.to_bytes()) {
+                return Err(FileBrowserError::StartPathOutsideBasePath);
+            }
+        }
+
         let mut result_path = FuriString::new();
         // path will be unmodified but needs to be a valid FuriString.
         // We can reuse the empty result_path if path is not provided.
@@ -71,7 +119,7 @@ impl DialogsApp {
         let options = options
             .map(|opts| &opts.data as *const sys::DialogsFileBrowserOptions)
             .unwrap_or(ptr::null());
-        unsafe {
+        Ok(unsafe {
             sys::dialog_file_browser_show(
                 self.data.as_ptr(),
                 result_path.as_mut_ptr(),
@@ -79,7 +127,7 @@ impl DialogsApp {
                 options,
             )
         }
-        .then_some(result_path)
+        .then_some(result_path))
     }
 }
 
@@ -182,6 +230,20 @@ impl<'a> DialogMessage<'a> {
             );
         }
     }
+
+    /// Sets the icon, drawn with its top left corner at `(x, y)`.
+    pub fn set_icon(&mut self, icon: &'a sys::Icon, x: u8, y: u8) {
+        unsafe {
+            sys::dialog_message_set_icon(self.data.as_ptr(), icon as *const sys::Icon, x, y);
+        }
+    }
+
+    /// Clears the icon.
+    pub fn clear_icon(&mut self) {
+        unsafe {
+            sys::dialog_message_set_icon(self.data.as_ptr(), ptr::null(), 0, 0);
+        }
+    }
 }
 
 impl<'a> Drop for DialogMessage<'a> {
@@ -199,24 +261,25 @@ impl<'a> Default for DialogMessage<'a> {
 }
 
 impl DialogMessageButton {
-    fn from_sys(sys: sys::DialogMessageButton) -> Option<Self> {
+    /// Buttons unknown to this crate are treated as [`DialogMessageButton::Back`], as
+    /// the dialog was dismissed without a known choice.
+    fn from_sys(sys: sys::DialogMessageButton) -> Self {
         match sys {
-            sys::DialogMessageButton_DialogMessageButtonBack => Some(Self::Back),
-            sys::DialogMessageButton_DialogMessageButtonLeft => Some(Self::Left),
-            sys::DialogMessageButton_DialogMessageButtonCenter => Some(Self::Center),
-            sys::DialogMessageButton_DialogMessageButtonRight => Some(Self::Right),
-            _ => None,
+            sys::DialogMessageButton_DialogMessageButtonLeft => Self::Left,
+            sys::DialogMessageButton_DialogMessageButtonCenter => Self::Center,
+            sys::DialogMessageButton_DialogMessageButtonRight => Self::Right,
+            _ => Self::Back,
         }
     }
 }
 
-impl<'a> Default for DialogFileBrowserOptions<'a> {
+impl<'a> Default for FileBrowserOptions<'a> {
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl<'a> DialogFileBrowserOptions<'a> {
+impl<'a> FileBrowserOptions<'a> {
     pub fn new() -> Self {
         // SAFETY: the string is a valid UTF-8
         unsafe { Self::with_extension(c"*") }
@@ -237,8 +300,9 @@ impl<'a> DialogFileBrowserOptions<'a> {
     /// Basic usage:
     ///
     /// ```
-    /// # use flipperzero::dialogs::DialogFileBrowserOptions;
-    /// let options = DialogFileBrowserOptions::new(c"*");
+    /// # use flipperzero::dialogs::FileBrowserOptions;
+    /// // SAFETY: the string is a valid UTF-8
+    /// let options = unsafe { FileBrowserOptions::with_extension(c"*") };
     /// ```
     ///
     /// ## Lifetime covariance:
@@ -248,32 +312,34 @@ impl<'a> DialogFileBrowserOptions<'a> {
     ///
     /// ```
     /// # use core::ffi::CStr;
-    /// # use flipperzero::dialogs::DialogFileBrowserOptions;
+    /// # use flipperzero::dialogs::FileBrowserOptions;
     /// // has `'static` lifetime
     /// const EXTENSION: &CStr = c"txt";
     /// // has "local" lifetime, aka `'a`
-    /// let base_path_bytes = [b'/', b'r', b'o', b'o', b't'];
+    /// let base_path_bytes = [b'/', b'r', b'o', b'o', b't', b'\0'];
     /// let base_path = CStr::from_bytes_with_nul(&base_path_bytes).unwrap();
     /// // the most appropriate lifetime `'a` is used
     /// // SAFETY: `EXTENSION` is a valid UTF-8 string
-    /// let mut options = unsafe { DialogFileBrowserOptions::new(EXTENSION) }
-    ///     .set_base_path(base_path);
+    /// let mut options = unsafe {
+    ///     FileBrowserOptions::with_extension(EXTENSION).set_base_path(base_path)
+    /// };
     /// ```
     ///
     /// Still this should not allow the options to outlive its components:
     ///
     /// ```compile_fail
     /// # use core::ffi::CStr;
-    /// # use flipperzero::dialogs::DialogFileBrowserOptions;
-    /// # use flipperzero_sys::{cstr, DialogsFileBrowserOptions};
-    /// const EXTENSION: &CStr = cstr!("*");
+    /// # use flipperzero::dialogs::FileBrowserOptions;
+    /// const EXTENSION: &CStr = c"*";
     /// // SAFETY: `EXTENSION` is a valid UTF-8 string
-    /// let mut options = unsafe { DialogFileBrowserOptions::new(EXTENSION) };
+    /// let mut options = unsafe { FileBrowserOptions::with_extension(EXTENSION) };
     /// {
-    ///     let base_path_bytes = [b'/', b'r', b'o', b'o', b't'];
+    ///     let base_path_bytes = [b'/', b'r', b'o', b'o', b't', b'\0'];
     ///     let base_path = CStr::from_bytes_with_nul(&base_path_bytes).unwrap();
-    ///     options = options.set_base_path(base_path);
+    ///     // SAFETY: `base_path` is a valid UTF-8 string
+    ///     options = unsafe { options.set_base_path(base_path) };
     /// }
+    /// # drop(options);
     /// ```
     pub unsafe fn with_extension(extension: &'a CStr) -> Self {
         let mut options = MaybeUninit::<sys::DialogsFileBrowserOptions>::uninit();
@@ -323,6 +389,18 @@ impl<'a> DialogFileBrowserOptions<'a> {
         self
     }
 
+    /// Returns the file extension offered for selection.
+    pub fn extension(&self) -> &'a CStr {
+        // SAFETY: `extension` is always set from a `&'a CStr`.
+        unsafe { CStr::from_ptr(self.data.extension) }
+    }
+
+    /// Returns the root folder path for navigation with back key, if any.
+    pub fn base_path(&self) -> Option<&'a CStr> {
+        // SAFETY: `base_path` is either NULL or set from a `&'a CStr`.
+        (!self.data.base_path.is_null()).then(|| unsafe { CStr::from_ptr(self.data.base_path) })
+    }
+
     /// Set file icon.
     pub fn set_icon(mut self, icon: &'a sys::Icon) -> Self {
         self.data.icon = icon as *const sys::Icon;
@@ -359,6 +437,272 @@ impl<'a> DialogFileBrowserOptions<'a> {
     }
 }
 
+impl fmt::Display for FileBrowserError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.description())
+    }
+}
+
+impl ufmt::uDisplay for FileBrowserError {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        f.write_str(self.description())
+    }
+}
+
+impl FileBrowserError {
+    /// Description associated with [`FileBrowserError`].
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::StartPathOutsideBasePath => "start path is outside of the base path",
+        }
+    }
+}
+
+/// Returns `true` if `path` is `base` or lies in a (possibly nested) directory of it.
+fn is_within(base: &[u8], path: &[u8]) -> bool {
+    let base = base.strip_suffix(b"/").unwrap_or(base);
+    match path.strip_prefix(base) {
+        Some(rest) => rest.is_empty() || rest.starts_with(b"/"),
+        None => false,
+    }
+}
+
+/// Asks the user whether an existing file at `path` should be overwritten.
+///
+/// If no file exists at `path`, this returns [`OverwriteChoice::Overwrite`] without
+/// showing a dialog. Otherwise the user can choose to overwrite the file, to save under
+/// a numbered name that is not taken yet (e.g. `cookies1.txt` for `cookies.txt`), or to
+/// cancel. Pressing back is treated as cancelling.
+///
+/// # Errors
+///
+/// Returns an error if it could not be determined whether `path` exists.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use flipperzero::dialogs::{confirm_overwrite, OverwriteChoice};
+/// # use flipperzero::io::Error;
+/// # fn main() -> Result<(), Error> {
+/// let path = c"/ext/apps_data/example/save.txt";
+/// match confirm_overwrite(path)? {
+///     OverwriteChoice::Overwrite => { /* write to `path` */ }
+///     OverwriteChoice::Rename(new_path) => { /* write to `new_path` */ }
+///     OverwriteChoice::Cancel => {}
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn confirm_overwrite(path: &CStr) -> Result<OverwriteChoice, Error> {
+    let storage = Storage::open();
+    if !storage.exists(path)? {
+        return Ok(OverwriteChoice::Overwrite);
+    }
+
+    // Split `path` into its directory, file stem and extension.
+    let mut dir = FuriString::from(path);
+    let name_start = dir.rfind('/').map_or(0, |i| i + 1);
+    let mut stem = dir.split_off(name_start);
+    dir.truncate(name_start.saturating_sub(1));
+    let extension = match stem.rfind('.') {
+        // A leading `.` marks a hidden file, not an extension.
+        Some(i) if i > 0 => stem.split_off(i),
+        _ => FuriString::new(),
+    };
+
+    let mut name = stem.clone();
+    name.push_string(&extension);
+    let name = storage::display_path(name.as_c_str(), OVERWRITE_NAME_MAX_CHARS);
+
+    let mut dialogs = DialogsApp::open();
+    let mut message = DialogMessage::new();
+    message.set_header(c"File exists", 64, 0, Align::Center, Align::Top);
+    message.set_text(name.as_c_str(), 64, 26, Align::Center, Align::Center);
+    message.set_buttons(Some(c"Cancel"), Some(c"Rename"), Some(c"Overwrite"));
+
+    Ok(match dialogs.show_message(&message) {
+        DialogMessageButton::Right => OverwriteChoice::Overwrite,
+        DialogMessageButton::Center => {
+            let mut new_path = dir.clone();
+            let new_name = storage.next_free_filename(
+                dir.as_c_str(),
+                stem.as_c_str(),
+                extension.as_c_str(),
+                u8::MAX,
+            );
+            new_path.push('/');
+            new_path.push_string(&new_name);
+            new_path.push_string(&extension);
+            OverwriteChoice::Rename(new_path)
+        }
+        DialogMessageButton::Left | DialogMessageButton::Back => OverwriteChoice::Cancel,
+    })
+}
+
+/// Asks the user for the name of a new file in `dir`, and creates it.
+///
+/// The user is shown a text input pre-filled with `suggested` (made into a valid file
+/// name with [`storage::sanitize_filename`]), which only accepts names that pass
+/// [`storage::validate_filename`]. `ext` is appended to the entered name, and must
+/// include the leading `.` if it isn't empty. If the file already exists, the user is
+/// asked whether to overwrite it with [`confirm_overwrite`].
+///
+/// Returns the full path of the file, or `None` if the user cancelled. The file is
+/// created if it doesn't exist yet, but existing contents are left untouched.
+///
+/// # Errors
+///
+/// Returns an error if checking for an existing file or creating the file fails.
+pub fn prompt_filename(
+    dir: &CStr,
+    suggested: &str,
+    ext: &CStr,
+) -> Result<Option<FuriString>, Error> {
+    let name = {
+        // Declared first so that it is closed after the view dispatcher is freed.
+        let gui = Record::<sys::Gui>::open();
+        let mut prompt = FilenamePrompt {
+            name: [0; FILENAME_PROMPT_MAX_LEN + 1],
+            accepted: false,
+            view_dispatcher: unsafe { NonNull::new_unchecked(sys::view_dispatcher_alloc()) },
+            ext: ext.to_bytes().len(),
+        };
+        let suggested = storage::sanitize_filename(suggested);
+        let suggested = &suggested.to_bytes()[..suggested.len().min(FILENAME_PROMPT_MAX_LEN)];
+        prompt.name[..suggested.len()].copy_from_slice(suggested);
+
+        if !prompt.run(&gui) {
+            return Ok(None);
+        }
+        prompt.name
+    };
+
+    let name = CStr::from_bytes_until_nul(&name).unwrap();
+    let mut path = FuriString::from(dir);
+    path.push('/');
+    path.push_c_str(name);
+    path.push_c_str(ext);
+
+    let path = match confirm_overwrite(path.as_c_str())? {
+        OverwriteChoice::Overwrite => path,
+        OverwriteChoice::Rename(path) => path,
+        OverwriteChoice::Cancel => return Ok(None),
+    };
+
+    OpenOptions::new()
+        .write(true)
+        .open_always(true)
+        .open(path.as_c_str())?;
+
+    Ok(Some(path))
+}
+
+/// Maximum length of a file name entered in [`prompt_filename`], excluding the extension.
+const FILENAME_PROMPT_MAX_LEN: usize = 64;
+
+/// The state of [`prompt_filename`], shared with the text input callbacks.
+struct FilenamePrompt {
+    name: [u8; FILENAME_PROMPT_MAX_LEN + 1],
+    accepted: bool,
+    view_dispatcher: NonNull<sys::ViewDispatcher>,
+    /// Length of the extension that will be appended to the name.
+    ext: usize,
+}
+
+impl FilenamePrompt {
+    const VIEW_ID: u32 = 0;
+
+    /// Shows the text input on `gui` until the user enters a valid name or presses back,
+    /// returning `true` in the former case.
+    ///
+    /// The view dispatcher stays attached to `gui` until the prompt is dropped.
+    fn run(&mut self, gui: &Record<sys::Gui>) -> bool {
+        let view_dispatcher = self.view_dispatcher.as_ptr();
+        let context = self as *mut Self as *mut c_void;
+        unsafe {
+            let text_input = sys::text_input_alloc();
+            sys::text_input_set_header_text(text_input, c"Enter file name".as_ptr());
+            sys::text_input_set_validator(text_input, Some(Self::validator_callback), context);
+            sys::text_input_set_result_callback(
+                text_input,
+                Some(Self::result_callback),
+                context,
+                self.name.as_mut_ptr() as *mut c_char,
+                self.name.len(),
+                true,
+            );
+
+            sys::view_dispatcher_set_event_callback_context(view_dispatcher, context);
+            sys::view_dispatcher_set_navigation_event_callback(
+                view_dispatcher,
+                Some(Self::navigation_callback),
+            );
+            sys::view_dispatcher_add_view(
+                view_dispatcher,
+                Self::VIEW_ID,
+                sys::text_input_get_view(text_input),
+            );
+
+            sys::view_dispatcher_attach_to_gui(
+                view_dispatcher,
+                gui.as_ptr(),
+                sys::ViewDispatcherType_ViewDispatcherTypeFullscreen,
+            );
+            sys::view_dispatcher_switch_to_view(view_dispatcher, Self::VIEW_ID);
+            sys::view_dispatcher_run(view_dispatcher);
+
+            sys::view_dispatcher_remove_view(view_dispatcher, Self::VIEW_ID);
+            sys::text_input_free(text_input);
+        }
+
+        self.accepted
+    }
+
+    unsafe extern "C" fn validator_callback(
+        text: *const c_char,
+        error: *mut sys::FuriString,
+        context: *mut c_void,
+    ) -> bool {
+        let prompt = unsafe { &*(context as *const Self) };
+        let name = unsafe { CStr::from_ptr(text) }.to_bytes();
+
+        let result = if name.len() + prompt.ext > storage::MAX_FILENAME_LEN {
+            Err(storage::FilenameError::TooLong)
+        } else {
+            storage::validate_filename(name)
+        };
+        match result {
+            Ok(()) => true,
+            Err(e) => {
+                let mut message = FuriString::from(e.description());
+                unsafe { sys::furi_string_set(error, message.as_mut_ptr()) };
+                false
+            }
+        }
+    }
+
+    unsafe extern "C" fn result_callback(context: *mut c_void) {
+        let prompt = unsafe { &mut *(context as *mut Self) };
+        prompt.accepted = true;
+        unsafe { sys::view_dispatcher_stop(prompt.view_dispatcher.as_ptr()) };
+    }
+
+    unsafe extern "C" fn navigation_callback(context: *mut c_void) -> bool {
+        let prompt = unsafe { &*(context as *const Self) };
+        unsafe { sys::view_dispatcher_stop(prompt.view_dispatcher.as_ptr()) };
+        true
+    }
+}
+
+impl Drop for FilenamePrompt {
+    fn drop(&mut self) {
+        unsafe { sys::view_dispatcher_free(self.view_dispatcher.as_ptr()) };
+    }
+}
+
 /// Displays a simple dialog.
 #[cfg(feature = "alloc")]
 #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
@@ -373,3 +717,80 @@ pub fn alert(text: &str) {
 
     dialogs.show_message(&message);
 }
+
+#[flipperzero_test::tests]
+mod tests {
+    use core::mem;
+
+    use flipperzero_sys as sys;
+
+    use super::{is_within, DialogMessageButton, FileBrowserOptions};
+
+    #[test]
+    fn unknown_buttons_are_back() {
+        assert!(matches!(
+            DialogMessageButton::from_sys(sys::DialogMessageButton_DialogMessageButtonRight),
+            DialogMessageButton::Right
+        ));
+        assert!(matches!(
+            DialogMessageButton::from_sys(sys::DialogMessageButton::MAX),
+            DialogMessageButton::Back
+        ));
+    }
+
+    #[test]
+    fn file_browser_options_layout() {
+        assert_eq!(
+            mem::size_of::<FileBrowserOptions>(),
+            mem::size_of::<sys::DialogsFileBrowserOptions>(),
+        );
+        assert_eq!(
+            mem::align_of::<FileBrowserOptions>(),
+            mem::align_of::<sys::DialogsFileBrowserOptions>(),
+        );
+    }
+
+    #[test]
+    fn file_browser_options_mapping() {
+        let extension = c"txt";
+        let base_path = c"/ext/apps_data";
+
+        // SAFETY: both strings are valid UTF-8
+        let options = unsafe {
+            FileBrowserOptions::new()
+                .set_extension(extension)
+                .set_base_path(base_path)
+        }
+        .set_skip_assets(false)
+        .set_hide_dot_files(true)
+        .set_hide_ext(true);
+
+        assert_eq!(options.data.extension, extension.as_ptr());
+        assert_eq!(options.data.base_path, base_path.as_ptr());
+        assert!(!options.data.skip_assets);
+        assert!(options.data.hide_dot_files);
+        assert!(options.data.hide_ext);
+        assert_eq!(options.extension(), extension);
+        assert_eq!(options.base_path(), Some(base_path));
+    }
+
+    #[test]
+    fn file_browser_options_defaults() {
+        let options = FileBrowserOptions::new();
+        assert_eq!(options.extension().to_bytes(), b"*");
+        assert!(options.base_path().is_none());
+        assert!(options.data.icon.is_null());
+    }
+
+    #[test]
+    fn start_path_within_base_path() {
+        assert!(is_within(b"/ext", b"/ext"));
+        assert!(is_within(b"/ext", b"/ext/"));
+        assert!(is_within(b"/ext", b"/ext/apps/foo.fap"));
+        assert!(is_within(b"/ext/", b"/ext/apps"));
+        assert!(is_within(b"/", b"/int/file"));
+        assert!(!is_within(b"/ext", b"/int"));
+        assert!(!is_within(b"/ext/apps", b"/ext"));
+        assert!(!is_within(b"/ext/app", b"/ext/apps_data"));
+    }
+}
@@ -1,6 +1,8 @@
 //! Interact with your Dolphin!
 
-use flipperzero_sys::{self as sys, furi::UnsafeRecord};
+use flipperzero_sys as sys;
+
+use crate::furi::record::Record;
 
 pub use sys::DolphinStats as Stats;
 
@@ -9,14 +11,14 @@ pub use deed::{App, Deed};
 
 /// The dolphin in your FlipperZero!
 pub struct Dolphin {
-    data: UnsafeRecord<sys::Dolphin>,
+    data: Record<sys::Dolphin>,
 }
 
 impl Dolphin {
     /// Obtains a handle to the dolphin.
     pub fn open() -> Self {
         Self {
-            data: unsafe { UnsafeRecord::open(c"dolphin".as_ptr()) },
+            data: Record::open(),
         }
     }
 
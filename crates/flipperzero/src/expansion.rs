@@ -0,0 +1,96 @@
+//! Expansion service: detection of expansion modules on the GPIO header.
+//!
+//! While enabled in the settings, the service listens on a serial port for modules
+//! that announce themselves, and then takes over the port to serve [RPC] to the module.
+//! Apps that use the port themselves, for a module of their own or any other device,
+//! should stop the service first, so it neither holds the port nor mistakes the traffic
+//! for a module. The frames of the protocol are in [`protocols::expansion`].
+//!
+//! [RPC]: https://github.com/flipperdevices/flipperzero-protobuf
+//! [`protocols::expansion`]: crate::protocols::expansion
+
+use flipperzero_sys as sys;
+
+use crate::furi::record::Record;
+use crate::serial::UartChannel;
+
+/// A handle to the expansion service.
+///
+/// # Examples
+///
+/// ```no_run
+/// use flipperzero::expansion::Expansion;
+/// use flipperzero::serial::{Uart, UartChannel};
+///
+/// let expansion = Expansion::open();
+/// let _suspended = expansion.suspend();
+/// // The service can't take the port until `_suspended` is dropped.
+/// let uart = Uart::open(UartChannel::Usart, 115200);
+/// ```
+pub struct Expansion {
+    record: Record<sys::Expansion>,
+}
+
+impl Expansion {
+    /// Opens the expansion service.
+    pub fn open() -> Self {
+        Self {
+            record: Record::open(),
+        }
+    }
+
+    /// Enables the service with the settings of the user.
+    ///
+    /// This does nothing if the user disabled expansion modules in the settings.
+    pub fn enable(&self) {
+        unsafe { sys::expansion_enable(self.record.as_ptr()) };
+    }
+
+    /// Disables the service, ending the connection to any module and releasing the
+    /// serial port.
+    pub fn disable(&self) {
+        unsafe { sys::expansion_disable(self.record.as_ptr()) };
+    }
+
+    /// Enables the service on `channel` instead of the port in the settings, ending the
+    /// connection on the previous port.
+    pub fn set_listen_serial(&self, channel: UartChannel) {
+        unsafe { sys::expansion_set_listen_serial(self.record.as_ptr(), channel.to_sys()) };
+    }
+
+    /// Disables the service until the returned guard is dropped, which enables it
+    /// again with the settings of the user.
+    pub fn suspend(&self) -> Suspended<'_> {
+        self.disable();
+        Suspended { expansion: self }
+    }
+}
+
+/// Keeps the expansion service disabled, created by [`Expansion::suspend`].
+#[must_use = "the service is enabled again when this is dropped"]
+pub struct Suspended<'a> {
+    expansion: &'a Expansion,
+}
+
+impl Drop for Suspended<'_> {
+    fn drop(&mut self) {
+        self.expansion.enable();
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use super::Expansion;
+    use crate::serial::{Uart, UartChannel};
+
+    #[test]
+    fn suspending_frees_the_port() {
+        let expansion = Expansion::open();
+        let suspended = expansion.suspend();
+        // The LPUART isn't used by the console, so only the service could hold it.
+        let uart = Uart::open(UartChannel::Lpuart, 115200);
+        assert!(uart.is_ok());
+        drop(uart);
+        drop(suspended);
+    }
+}
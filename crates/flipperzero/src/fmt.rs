@@ -0,0 +1,206 @@
+//! Formatting helpers for user interfaces.
+//!
+//! These format into caller-provided buffers, so they can be used from draw callbacks
+//! without allocating.
+
+use core::fmt::{self, Write};
+
+/// Maximum length of the text returned by [`human_bytes`], such as `1023.9 KiB`.
+pub const HUMAN_BYTES_MAX_LEN: usize = 10;
+
+/// Binary units, in increasing order.
+const UNITS: [&str; 7] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+
+/// Formats a number of bytes with binary units, such as `3.2 MiB`.
+///
+/// Sizes below 1 KiB are shown as a whole number of bytes, and larger sizes with one
+/// decimal, rounded to the nearest tenth. Sizes that round up to 1024 of a unit are
+/// shown in the next unit instead, so `1048575` bytes are `1.0 MiB` rather than
+/// `1024.0 KiB`.
+///
+/// # Panics
+///
+/// Panics if `buf` is shorter than [`HUMAN_BYTES_MAX_LEN`].
+pub fn human_bytes(bytes: u64, buf: &mut [u8]) -> &str {
+    assert!(buf.len() >= HUMAN_BYTES_MAX_LEN, "buffer is too small");
+
+    let mut writer = BufWriter { buf, len: 0 };
+    if bytes < 1024 {
+        let _ = write!(writer, "{} B", bytes);
+    } else {
+        let mut unit = 1;
+        let tenths = loop {
+            let divisor = 1u128 << (10 * unit);
+            let tenths = (u128::from(bytes) * 10 + divisor / 2) / divisor;
+            if tenths < 10240 || unit == UNITS.len() - 1 {
+                break tenths;
+            }
+            unit += 1;
+        };
+        let _ = write!(writer, "{}.{} {}", tenths / 10, tenths % 10, UNITS[unit]);
+    }
+    writer.into_str()
+}
+
+/// Returns how much of `total` is `used`, in percent rounded to the nearest integer.
+///
+/// Returns 0 if `total` is 0, and 100 if `used` exceeds `total`.
+pub fn usage_percent(used: u64, total: u64) -> u8 {
+    scale(used, total, 100) as u8
+}
+
+/// Draws a text bar of `width` characters, of which the share of `total` that is
+/// `used` is filled with `#` and the rest with `-`.
+///
+/// The bar is empty if `total` is 0, and full if `used` exceeds `total`.
+///
+/// # Panics
+///
+/// Panics if `buf` is shorter than `width`.
+pub fn usage_bar(used: u64, total: u64, width: usize, buf: &mut [u8]) -> &str {
+    let buf = &mut buf[..width];
+    let filled = scale(used, total, width as u64) as usize;
+    buf[..filled].fill(b'#');
+    buf[filled..].fill(b'-');
+    // Only contains ASCII.
+    core::str::from_utf8(buf).unwrap()
+}
+
+/// Formats `bytes` as lowercase hex without separators, such as `d41d8c` for
+/// `[0xd4, 0x1d, 0x8c]`.
+///
+/// This is how checksums such as the digest of [`Storage::md5sum`] are usually shown
+/// and stored.
+///
+/// # Panics
+///
+/// Panics if `buf` is shorter than twice the length of `bytes`.
+///
+/// [`Storage::md5sum`]: crate::storage::Storage::md5sum
+pub fn hex<'a>(bytes: &[u8], buf: &'a mut [u8]) -> &'a str {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+    let buf = &mut buf[..2 * bytes.len()];
+    for (byte, digits) in bytes.iter().zip(buf.chunks_exact_mut(2)) {
+        digits[0] = DIGITS[usize::from(byte >> 4)];
+        digits[1] = DIGITS[usize::from(byte & 0xf)];
+    }
+    // Only contains ASCII.
+    core::str::from_utf8(buf).unwrap()
+}
+
+/// Scales `value / total` to `max`, rounding to the nearest integer and clamping to
+/// `max`.
+fn scale(value: u64, total: u64, max: u64) -> u64 {
+    if total == 0 {
+        return 0;
+    }
+    let value = u128::from(value.min(total));
+    let total = u128::from(total);
+    ((value * u128::from(max) + total / 2) / total) as u64
+}
+
+/// Writes into a byte buffer, failing once it is full.
+struct BufWriter<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> BufWriter<'a> {
+    fn into_str(self) -> &'a str {
+        // Only whole `str`s are written.
+        core::str::from_utf8(&self.buf[..self.len]).unwrap()
+    }
+}
+
+impl Write for BufWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        if end > self.buf.len() {
+            return Err(fmt::Error);
+        }
+        self.buf[self.len..end].copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use super::{hex, human_bytes, usage_bar, usage_percent, HUMAN_BYTES_MAX_LEN};
+
+    const KIB: u64 = 1024;
+    const MIB: u64 = 1024 * KIB;
+    const GIB: u64 = 1024 * MIB;
+
+    #[test]
+    fn human_bytes_rounding() {
+        let cases: [(u64, &str); 16] = [
+            (0, "0 B"),
+            (1, "1 B"),
+            (1023, "1023 B"),
+            (KIB, "1.0 KiB"),
+            (1075, "1.0 KiB"),
+            (1076, "1.1 KiB"),
+            (1536, "1.5 KiB"),
+            (10 * KIB, "10.0 KiB"),
+            // 1023.94 KiB and 1023.95 KiB
+            (1048514, "1023.9 KiB"),
+            (1048525, "1.0 MiB"),
+            (MIB - 1, "1.0 MiB"),
+            (MIB, "1.0 MiB"),
+            (3 * MIB + MIB / 5, "3.2 MiB"),
+            (GIB, "1.0 GiB"),
+            (31 * GIB + 9 * GIB / 10, "31.9 GiB"),
+            (u64::MAX, "16.0 EiB"),
+        ];
+
+        let mut buf = [0; HUMAN_BYTES_MAX_LEN];
+        for (bytes, expected) in cases {
+            assert_eq!(human_bytes(bytes, &mut buf), expected);
+        }
+    }
+
+    #[test]
+    fn usage_percentages() {
+        let cases: [(u64, u64, u8); 7] = [
+            (0, 0, 0),
+            (5, 0, 0),
+            (0, 10, 0),
+            (1, 3, 33),
+            (2, 3, 67),
+            (5, 4, 100),
+            (u64::MAX, u64::MAX, 100),
+        ];
+
+        for (used, total, expected) in cases {
+            assert_eq!(usage_percent(used, total), expected);
+        }
+    }
+
+    #[test]
+    fn usage_bars() {
+        let cases: [(u64, u64, &str); 6] = [
+            (0, 0, "----"),
+            (1, 1000, "----"),
+            (1, 2, "##--"),
+            (3, 8, "##--"),
+            (10, 10, "####"),
+            (20, 10, "####"),
+        ];
+
+        let mut buf = [0; 4];
+        for (used, total, expected) in cases {
+            assert_eq!(usage_bar(used, total, 4, &mut buf), expected);
+        }
+        assert!(usage_bar(1, 2, 0, &mut buf).is_empty());
+    }
+
+    #[test]
+    fn hex_digits() {
+        let mut buf = [0; 8];
+        assert_eq!(hex(&[0x00, 0x9f, 0xa0, 0xff], &mut buf), "009fa0ff");
+        assert_eq!(hex(&[0x1d], &mut buf), "1d");
+        assert!(hex(&[], &mut buf).is_empty());
+    }
+}
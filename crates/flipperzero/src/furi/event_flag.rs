@@ -0,0 +1,226 @@
+//! Furi event flags.
+
+use core::ptr::NonNull;
+use core::time::Duration;
+
+use flipperzero_sys as sys;
+use flipperzero_sys::furi::{duration_to_ticks, Status};
+
+use crate::furi;
+
+/// The flags that can be used, as FreeRTOS reserves the top byte of event groups.
+pub const VALID_FLAGS: u32 = 0x00FF_FFFF;
+
+/// `FuriFlagWaitAny` in the firmware.
+const FURI_FLAG_WAIT_ANY: u32 = 0;
+/// `FuriFlagWaitAll` in the firmware.
+const FURI_FLAG_WAIT_ALL: u32 = 1 << 0;
+/// `FuriFlagNoClear` in the firmware.
+const FURI_FLAG_NO_CLEAR: u32 = 1 << 1;
+/// `FuriFlagError` in the firmware, which is set in results that are a [`Status`].
+const FURI_FLAG_ERROR: u32 = 1 << 31;
+
+/// Which of the flags waited for must be set to end the wait.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wait {
+    /// Any of them.
+    Any,
+    /// All of them.
+    All,
+}
+
+impl Wait {
+    fn to_sys(self) -> u32 {
+        match self {
+            Self::Any => FURI_FLAG_WAIT_ANY,
+            Self::All => FURI_FLAG_WAIT_ALL,
+        }
+    }
+}
+
+/// A set of flags that threads can wait on, which is a FreeRTOS event group.
+///
+/// Flags are bits of a `u32`, of which only those in [`VALID_FLAGS`] can be used.
+/// Setting flags wakes the threads waiting for them, so they can be used to signal
+/// events from other threads, timers or interrupts.
+///
+/// # Examples
+///
+/// ```no_run
+/// use core::time::Duration;
+///
+/// use flipperzero::furi::event_flag::{EventFlag, Wait};
+///
+/// const DATA: u32 = 1 << 0;
+/// const STOP: u32 = 1 << 1;
+///
+/// let events = EventFlag::new();
+/// events.set(DATA).unwrap();
+///
+/// // Clears and returns the flags that were set.
+/// let flags = events.wait(DATA | STOP, Wait::Any, Duration::MAX).unwrap();
+/// assert_eq!(flags, DATA);
+/// ```
+pub struct EventFlag {
+    raw: NonNull<sys::FuriEventFlag>,
+}
+
+// SAFETY: event flags can be used from any thread.
+unsafe impl Send for EventFlag {}
+unsafe impl Sync for EventFlag {}
+
+impl EventFlag {
+    /// Creates a set of event flags, with none of them set.
+    pub fn new() -> Self {
+        Self {
+            raw: unsafe { NonNull::new_unchecked(sys::furi_event_flag_alloc()) },
+        }
+    }
+
+    /// Returns the raw pointer to the event flags, for use with the `sys` bindings.
+    pub fn as_ptr(&self) -> *mut sys::FuriEventFlag {
+        self.raw.as_ptr()
+    }
+
+    /// Sets `flags`, returning the flags that are set afterwards.
+    ///
+    /// Threads waking up from a wait for some of `flags` may already have cleared them
+    /// in the result.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `flags` contains bits outside [`VALID_FLAGS`].
+    pub fn set(&self, flags: u32) -> furi::Result<u32> {
+        check_flags(flags);
+        to_result(unsafe { sys::furi_event_flag_set(self.raw.as_ptr(), flags) })
+    }
+
+    /// Clears `flags`, returning the flags that were set before.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `flags` contains bits outside [`VALID_FLAGS`].
+    pub fn clear(&self, flags: u32) -> furi::Result<u32> {
+        check_flags(flags);
+        to_result(unsafe { sys::furi_event_flag_clear(self.raw.as_ptr(), flags) })
+    }
+
+    /// Returns the flags that are set.
+    pub fn get(&self) -> u32 {
+        unsafe { sys::furi_event_flag_get(self.raw.as_ptr()) }
+    }
+
+    /// Waits up to `timeout` for any or all of `flags` to be set, then clears `flags`.
+    ///
+    /// Returns the flags that were set when the wait ended, or
+    /// [`Status::ERR_TIMEOUT`] if it timed out.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `flags` contains bits outside [`VALID_FLAGS`].
+    pub fn wait(&self, flags: u32, wait: Wait, timeout: Duration) -> furi::Result<u32> {
+        self.wait_with_options(flags, wait.to_sys(), timeout)
+    }
+
+    /// Waits up to `timeout` for any or all of `flags` to be set, without clearing them.
+    ///
+    /// Returns the flags that were set when the wait ended, or
+    /// [`Status::ERR_TIMEOUT`] if it timed out.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `flags` contains bits outside [`VALID_FLAGS`].
+    pub fn wait_no_clear(&self, flags: u32, wait: Wait, timeout: Duration) -> furi::Result<u32> {
+        self.wait_with_options(flags, wait.to_sys() | FURI_FLAG_NO_CLEAR, timeout)
+    }
+
+    fn wait_with_options(&self, flags: u32, options: u32, timeout: Duration) -> furi::Result<u32> {
+        check_flags(flags);
+        to_result(unsafe {
+            sys::furi_event_flag_wait(
+                self.raw.as_ptr(),
+                flags,
+                options,
+                duration_to_ticks(timeout),
+            )
+        })
+    }
+}
+
+impl Drop for EventFlag {
+    fn drop(&mut self) {
+        unsafe { sys::furi_event_flag_free(self.raw.as_ptr()) };
+    }
+}
+
+impl Default for EventFlag {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn check_flags(flags: u32) {
+    assert!(
+        flags & !VALID_FLAGS == 0,
+        "event flags must be within `VALID_FLAGS`"
+    );
+}
+
+fn to_result(result: u32) -> furi::Result<u32> {
+    if result & FURI_FLAG_ERROR != 0 {
+        Err(Status(result as i32))
+    } else {
+        Ok(result)
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use core::time::Duration;
+
+    use flipperzero_sys::furi::Status;
+
+    use super::{EventFlag, Wait};
+
+    #[test]
+    fn set_and_clear() {
+        let events = EventFlag::new();
+        assert_eq!(events.get(), 0);
+
+        assert_eq!(events.set(0b101), Ok(0b101));
+        assert_eq!(events.set(0b010), Ok(0b111));
+        assert_eq!(events.clear(0b001), Ok(0b111));
+        assert_eq!(events.get(), 0b110);
+    }
+
+    #[test]
+    fn wait_clears_unless_asked_not_to() {
+        let events = EventFlag::new();
+        events.set(0b011).unwrap();
+
+        assert_eq!(
+            events.wait_no_clear(0b001, Wait::Any, Duration::ZERO),
+            Ok(0b011),
+        );
+        assert_eq!(events.get(), 0b011);
+
+        assert_eq!(events.wait(0b101, Wait::Any, Duration::ZERO), Ok(0b011));
+        assert_eq!(events.get(), 0b010);
+    }
+
+    #[test]
+    fn wait_times_out() {
+        let events = EventFlag::new();
+        events.set(0b01).unwrap();
+
+        assert_eq!(
+            events.wait(0b11, Wait::All, Duration::from_millis(10)),
+            Err(Status::ERR_TIMEOUT),
+        );
+        assert_eq!(events.wait(0b11, Wait::Any, Duration::ZERO), Ok(0b01));
+        assert_eq!(
+            events.wait(0b01, Wait::Any, Duration::ZERO),
+            Err(Status::ERR_RESOURCE),
+        );
+    }
+}
@@ -0,0 +1,160 @@
+//! A [`log::Log`] implementation that prints to the Furi log.
+
+use core::ffi::CStr;
+use core::fmt::Write;
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+use flipperzero_sys as sys;
+
+use super::{Level, LevelFilter};
+use crate::furi::string::FuriString;
+
+/// The tag given to [`init_logger`], printed before each message.
+static TAG: AtomicPtr<core::ffi::c_char> = AtomicPtr::new(c"app".as_ptr().cast_mut());
+
+static LOGGER: FuriLogger = FuriLogger;
+
+struct FuriLogger;
+
+impl log::Log for FuriLogger {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        Level::from(metadata.level()) <= LevelFilter::current()
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let mut buf = FuriString::new();
+        if buf.write_fmt(*record.args()).is_err() {
+            return;
+        }
+        let level = Level::from(record.level()).to_furi();
+        // The message is passed as an argument, as any `%` in it would be read as a
+        // conversion of the format string.
+        unsafe {
+            sys::furi_log_print_format(
+                level,
+                TAG.load(Ordering::Relaxed),
+                c"%s".as_ptr(),
+                buf.as_c_ptr(),
+            );
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Installs the logger of the [`log`] crate, so that its macros print to the Furi log
+/// with `tag`, usually the name of the app.
+///
+/// The [`log`] crate's level is set to [`LevelFilter::current`], the level chosen in the
+/// settings of the Flipper Zero; see [`set_level`] to change it. Messages more verbose
+/// than the Flipper Zero's level are never printed.
+///
+/// # Errors
+///
+/// Returns an error if a logger is already installed. The tag is changed regardless.
+///
+/// # Examples
+///
+/// ```no_run
+/// flipperzero::furi::log::init_logger(c"my_app").ok();
+/// log::info!("started with {} bytes free", 1024);
+/// ```
+pub fn init_logger(tag: &'static CStr) -> Result<(), log::SetLoggerError> {
+    TAG.store(tag.as_ptr().cast_mut(), Ordering::Relaxed);
+    log::set_logger(&LOGGER)?;
+    set_level(LevelFilter::current());
+    Ok(())
+}
+
+/// Sets the most verbose level that the macros of the [`log`] crate print.
+///
+/// This only affects the [`log`] crate, and not the level of the Flipper Zero, which
+/// still applies.
+pub fn set_level(filter: LevelFilter) {
+    log::set_max_level(filter.into());
+}
+
+impl From<log::Level> for Level {
+    fn from(level: log::Level) -> Self {
+        match level {
+            log::Level::Error => Level::ERROR,
+            log::Level::Warn => Level::WARN,
+            log::Level::Info => Level::INFO,
+            log::Level::Debug => Level::DEBUG,
+            log::Level::Trace => Level::TRACE,
+        }
+    }
+}
+
+impl From<Level> for log::Level {
+    fn from(level: Level) -> Self {
+        match level {
+            Level::ERROR => log::Level::Error,
+            Level::WARN => log::Level::Warn,
+            Level::INFO => log::Level::Info,
+            Level::DEBUG => log::Level::Debug,
+            _ => log::Level::Trace,
+        }
+    }
+}
+
+impl From<log::LevelFilter> for LevelFilter {
+    fn from(filter: log::LevelFilter) -> Self {
+        match filter.to_level() {
+            Some(level) => LevelFilter::from_level(level.into()),
+            None => LevelFilter::OFF,
+        }
+    }
+}
+
+impl From<LevelFilter> for log::LevelFilter {
+    fn from(filter: LevelFilter) -> Self {
+        match filter.into_level() {
+            Some(level) => log::Level::from(level).to_level_filter(),
+            None => log::LevelFilter::Off,
+        }
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use super::{init_logger, set_level};
+    use crate::furi::log::{Level, LevelFilter};
+
+    #[test]
+    fn levels_convert() {
+        let levels = [
+            (Level::ERROR, log::Level::Error),
+            (Level::WARN, log::Level::Warn),
+            (Level::INFO, log::Level::Info),
+            (Level::DEBUG, log::Level::Debug),
+            (Level::TRACE, log::Level::Trace),
+        ];
+        for (level, log_level) in levels {
+            assert_eq!(Level::from(log_level), level);
+            assert_eq!(log::Level::from(level), log_level);
+            let filter = LevelFilter::from_level(level);
+            assert_eq!(log::LevelFilter::from(filter), log_level.to_level_filter());
+        }
+        assert_eq!(
+            log::LevelFilter::from(LevelFilter::OFF),
+            log::LevelFilter::Off
+        );
+        assert_eq!(LevelFilter::from(log::LevelFilter::Off), LevelFilter::OFF);
+    }
+
+    #[test]
+    fn logs_through_the_log_crate() {
+        let tag = c"rs_test";
+        // The logger can only be installed once, so this fails if it was run before.
+        let _ = init_logger(tag);
+        set_level(LevelFilter::WARN);
+        assert_eq!(log::max_level(), log::LevelFilter::Warn);
+        log::warn!("logged through the log crate: {}", 42);
+        set_level(LevelFilter::current());
+    }
+}
@@ -1,7 +1,12 @@
 //! Furi Logging system.
 
+#[cfg(feature = "log")]
+pub(crate) mod logger;
 pub(crate) mod metadata;
 
+#[cfg(feature = "log")]
+#[cfg_attr(docsrs, doc(cfg(feature = "log")))]
+pub use logger::{init_logger, set_level};
 pub use metadata::{Level, LevelFilter};
 
 /// The standard logging macro.
@@ -8,11 +8,44 @@ use flipperzero_sys::furi::{duration_to_ticks, Status};
 use crate::furi;
 
 /// MessageQueue provides a safe wrapper around the furi message queue primitive.
+///
+/// Messages are moved into the queue by [`MessageQueue::put`] and out of it by
+/// [`MessageQueue::get`], so they can be of any type that is safe to send between
+/// threads. Messages still in the queue when it is dropped are dropped with it.
+///
+/// # Examples
+///
+/// ```no_run
+/// use core::time::Duration;
+///
+/// use flipperzero::furi::message_queue::MessageQueue;
+///
+/// enum Event {
+///     Tick,
+///     Exit,
+/// }
+///
+/// let queue = MessageQueue::new(8);
+/// queue.put(Event::Tick, Duration::ZERO).unwrap();
+/// queue.put(Event::Exit, Duration::ZERO).unwrap();
+///
+/// while let Ok(event) = queue.get(Duration::MAX) {
+///     match event {
+///         Event::Tick => continue,
+///         Event::Exit => break,
+///     }
+/// }
+/// ```
 pub struct MessageQueue<M: Sized> {
     hnd: NonNull<sys::FuriMessageQueue>,
     _marker: core::marker::PhantomData<M>,
 }
 
+// SAFETY: message queues can be used from any thread, and messages are moved between
+// threads.
+unsafe impl<M: Send> Send for MessageQueue<M> {}
+unsafe impl<M: Send> Sync for MessageQueue<M> {}
+
 impl<M: Sized> MessageQueue<M> {
     /// Constructs a message queue with the given capacity.
     pub fn new(capacity: usize) -> Self {
@@ -27,8 +60,12 @@ impl<M: Sized> MessageQueue<M> {
         }
     }
 
-    // Attempts to add the message to the end of the queue, waiting up to timeout ticks.
-    pub fn put(&self, msg: M, timeout: Duration) -> furi::Result<()> {
+    /// Attempts to add the message to the end of the queue, waiting up to `timeout` for
+    /// space.
+    ///
+    /// If the queue is still full, the message is returned with the timeout error, so it
+    /// can be put again later.
+    pub fn put(&self, msg: M, timeout: Duration) -> Result<(), (M, Status)> {
         let mut msg = core::mem::ManuallyDrop::new(msg);
         let timeout_ticks = duration_to_ticks(timeout);
 
@@ -41,10 +78,16 @@ impl<M: Sized> MessageQueue<M> {
             .into()
         };
 
-        status.err_or(())
+        if status.is_err() {
+            // The queue didn't copy the message, so it is still ours.
+            Err((core::mem::ManuallyDrop::into_inner(msg), status))
+        } else {
+            Ok(())
+        }
     }
 
-    // Attempts to read a message from the front of the queue within timeout ticks.
+    /// Attempts to take the message at the front of the queue, waiting up to `timeout`
+    /// for one to arrive.
     pub fn get(&self, timeout: Duration) -> furi::Result<M> {
         let timeout_ticks = duration_to_ticks(timeout);
         let mut out = core::mem::MaybeUninit::<M>::uninit();
@@ -102,6 +145,7 @@ impl<M: Sized> Drop for MessageQueue<M> {
 
 #[flipperzero_test::tests]
 mod tests {
+    use core::sync::atomic::{AtomicUsize, Ordering};
     use core::time::Duration;
 
     use flipperzero_sys::furi::Status;
@@ -131,7 +175,7 @@ mod tests {
         // Attempting to add another message should time out.
         assert_eq!(
             queue.put(7, Duration::from_millis(1)),
-            Err(Status::ERR_TIMEOUT),
+            Err((7, Status::ERR_TIMEOUT)),
         );
 
         // Removing a message from the queue frees up capacity.
@@ -140,4 +184,38 @@ mod tests {
         assert_eq!(queue.space(), 1);
         assert_eq!(queue.capacity(), 3);
     }
+
+    #[test]
+    fn messages_are_dropped() {
+        static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+        #[derive(Debug)]
+        struct Message;
+
+        impl Drop for Message {
+            fn drop(&mut self) {
+                DROPPED.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let queue = MessageQueue::new(2);
+        queue.put(Message, Duration::ZERO).unwrap();
+        queue.put(Message, Duration::ZERO).unwrap();
+        assert_eq!(DROPPED.load(Ordering::Relaxed), 0);
+
+        // A message that doesn't fit is handed back.
+        let (message, status) = queue.put(Message, Duration::ZERO).unwrap_err();
+        assert_eq!(status, Status::ERR_TIMEOUT);
+        assert_eq!(DROPPED.load(Ordering::Relaxed), 0);
+        drop(message);
+        assert_eq!(DROPPED.load(Ordering::Relaxed), 1);
+
+        // A message taken from the queue is dropped by its new owner.
+        drop(queue.get(Duration::ZERO).unwrap());
+        assert_eq!(DROPPED.load(Ordering::Relaxed), 2);
+
+        // The remaining message is dropped with the queue.
+        drop(queue);
+        assert_eq!(DROPPED.load(Ordering::Relaxed), 3);
+    }
 }
@@ -1,13 +1,22 @@
 //! Furi API.
 
+pub mod event_flag;
 pub mod io;
 pub mod log;
 pub mod message_queue;
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub mod pubsub;
+pub mod record;
 pub mod rng;
+pub mod stream_buffer;
 pub mod string;
 pub mod sync;
 pub mod thread;
 pub mod time;
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub mod timer;
 
 use flipperzero_sys as sys;
 
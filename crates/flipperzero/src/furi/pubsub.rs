@@ -0,0 +1,118 @@
+//! Furi PubSub API.
+
+use alloc::boxed::Box;
+use core::ffi::c_void;
+use core::marker::PhantomData;
+use core::ptr::NonNull;
+
+use flipperzero_sys as sys;
+
+type Callback<T> = Box<dyn FnMut(&T) + Send>;
+
+/// A subscription to a `FuriPubSub`, which calls a closure for every message published
+/// to it until it is dropped.
+///
+/// The closure is called on the thread publishing the message.
+pub struct Subscription<T> {
+    pubsub: NonNull<sys::FuriPubSub>,
+    raw: NonNull<sys::FuriPubSubSubscription>,
+    callback: NonNull<Callback<T>>,
+    _marker: PhantomData<T>,
+}
+
+impl<T> Subscription<T> {
+    /// Subscribes to `pubsub`, calling `callback` for every message published to it.
+    ///
+    /// # Safety
+    ///
+    /// `pubsub` must point to a valid `FuriPubSub` that outlives the subscription, and
+    /// the messages published to it must be valid pointers to `T`.
+    pub unsafe fn new<F>(pubsub: NonNull<sys::FuriPubSub>, callback: F) -> Self
+    where
+        F: FnMut(&T) + Send + 'static,
+    {
+        // Boxed twice for a thin pointer, see `furi::thread::Builder::spawn`.
+        let callback: Box<Callback<T>> = Box::new(Box::new(callback));
+        let callback = unsafe { NonNull::new_unchecked(Box::into_raw(callback)) };
+
+        unsafe extern "C" fn run_callback<T>(message: *const c_void, context: *mut c_void) {
+            let callback = unsafe { &mut *(context as *mut Callback<T>) };
+            callback(unsafe { &*(message as *const T) });
+        }
+
+        let raw = unsafe {
+            NonNull::new_unchecked(sys::furi_pubsub_subscribe(
+                pubsub.as_ptr(),
+                Some(run_callback::<T>),
+                callback.as_ptr() as *mut c_void,
+            ))
+        };
+
+        Self {
+            pubsub,
+            raw,
+            callback,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Drop for Subscription<T> {
+    fn drop(&mut self) {
+        unsafe {
+            // This waits for a running callback to complete.
+            sys::furi_pubsub_unsubscribe(self.pubsub.as_ptr(), self.raw.as_ptr());
+            drop(Box::from_raw(self.callback.as_ptr()));
+        }
+    }
+}
+
+/// A [`Subscription`] to the pubsub of a service, which keeps the record of the service
+/// open until it is dropped.
+pub(crate) struct RecordSubscription<R, T> {
+    // Fields are dropped in order, so this unsubscribes while the record is still open.
+    _subscription: Subscription<T>,
+    _record: R,
+}
+
+impl<R, T> RecordSubscription<R, T> {
+    /// Subscribes to `pubsub`, calling `callback` with every message converted by
+    /// `from_sys`.
+    ///
+    /// # Safety
+    ///
+    /// `pubsub` must stay valid for as long as `record` is open, and the messages
+    /// published to it must be valid pointers to `T`.
+    pub(crate) unsafe fn new<E, F>(
+        record: R,
+        pubsub: *mut sys::FuriPubSub,
+        from_sys: fn(&T) -> E,
+        mut callback: F,
+    ) -> Self
+    where
+        T: 'static,
+        E: 'static,
+        F: FnMut(E) + Send + 'static,
+    {
+        let subscription = unsafe {
+            Subscription::new(NonNull::new_unchecked(pubsub), move |message: &T| {
+                callback(from_sys(message))
+            })
+        };
+
+        Self {
+            _subscription: subscription,
+            _record: record,
+        }
+    }
+}
+
+/// An event published as just its type, such as `LoaderEvent` from the firmware's
+/// `loader.h`.
+///
+/// These events aren't in the bindings, as no function takes them. Their type is a C
+/// enum, which the firmware stores in a byte.
+#[repr(C)]
+pub(crate) struct TypeEvent {
+    pub(crate) type_: u8,
+}
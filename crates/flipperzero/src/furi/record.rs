@@ -0,0 +1,117 @@
+//! Furi records, the named instances of the firmware's services.
+
+use core::ffi::CStr;
+use core::ptr::NonNull;
+
+use flipperzero_sys as sys;
+
+/// A type of the firmware that is published as a record, such as the GUI service.
+///
+/// # Safety
+///
+/// The record named [`RecordType::NAME`] must be an instance of the implementing type,
+/// and stay valid for as long as the firmware runs.
+pub unsafe trait RecordType {
+    /// The name the record is published under.
+    const NAME: &'static CStr;
+}
+
+macro_rules! record_types {
+    ($($ty:ident => $name:literal,)*) => {
+        $(
+            // SAFETY: the firmware publishes this service under this name, and services
+            // are never freed.
+            unsafe impl RecordType for sys::$ty {
+                const NAME: &'static CStr = $name;
+            }
+        )*
+    };
+}
+
+record_types! {
+    Bt => c"bt",
+    Cli => c"cli",
+    DialogsApp => c"dialogs",
+    Dolphin => c"dolphin",
+    Expansion => c"expansion",
+    Gui => c"gui",
+    Loader => c"loader",
+    NotificationApp => c"notification",
+    Power => c"power",
+    Storage => c"storage",
+}
+
+/// An open record, which keeps its service from being freed until it is dropped.
+///
+/// Opening waits until the service has started. Cloning opens the record again, as the
+/// firmware counts the holders of each record.
+///
+/// # Examples
+///
+/// ```no_run
+/// use flipperzero::furi::record::Record;
+/// use flipperzero_sys as sys;
+///
+/// let gui = Record::<sys::Gui>::open();
+/// let canvas = unsafe { sys::gui_direct_draw_acquire(gui.as_ptr()) };
+/// // ...
+/// unsafe { sys::gui_direct_draw_release(gui.as_ptr()) };
+/// ```
+pub struct Record<T: RecordType> {
+    raw: NonNull<T>,
+}
+
+// SAFETY: records can be opened and closed from any thread. Using the pointer requires
+// `unsafe` code, which must uphold the rules of the service.
+unsafe impl<T: RecordType> Send for Record<T> {}
+unsafe impl<T: RecordType> Sync for Record<T> {}
+
+impl<T: RecordType> Record<T> {
+    /// Opens the record, waiting for the service to start if it hasn't yet.
+    pub fn open() -> Self {
+        let raw = unsafe { sys::furi_record_open(T::NAME.as_ptr()) } as *mut T;
+        Self {
+            // The firmware crashes rather than returning null.
+            raw: unsafe { NonNull::new_unchecked(raw) },
+        }
+    }
+
+    /// Returns `true` if the record has been published.
+    pub fn exists() -> bool {
+        unsafe { sys::furi_record_exists(T::NAME.as_ptr()) }
+    }
+
+    /// Returns the raw pointer to the service, for use with the `sys` bindings.
+    pub fn as_ptr(&self) -> *mut T {
+        self.raw.as_ptr()
+    }
+}
+
+impl<T: RecordType> Clone for Record<T> {
+    fn clone(&self) -> Self {
+        Self::open()
+    }
+}
+
+impl<T: RecordType> Drop for Record<T> {
+    fn drop(&mut self) {
+        unsafe { sys::furi_record_close(T::NAME.as_ptr()) };
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use flipperzero_sys as sys;
+
+    use super::Record;
+
+    #[test]
+    fn records_open_and_clone() {
+        assert!(Record::<sys::Gui>::exists());
+        let gui = Record::<sys::Gui>::open();
+        let clone = gui.clone();
+        assert_eq!(gui.as_ptr(), clone.as_ptr());
+        drop(gui);
+        assert!(!clone.as_ptr().is_null());
+    }
+}
@@ -0,0 +1,198 @@
+//! Furi stream buffers.
+
+use core::ffi::c_void;
+use core::ptr::NonNull;
+use core::time::Duration;
+
+use flipperzero_sys as sys;
+use flipperzero_sys::furi::{duration_to_ticks, Status};
+
+use crate::furi;
+
+/// A pipe of bytes from one thread or interrupt to another, which is a FreeRTOS stream
+/// buffer.
+///
+/// Receiving waits until at least the trigger level of bytes is in the buffer, or the
+/// timeout elapses, so a reader can wait for a whole record without being woken for
+/// each byte.
+///
+/// Stream buffers are meant for a single sender and a single receiver. Sending from two
+/// threads at once, or receiving from two, can interleave or lose bytes.
+///
+/// # Examples
+///
+/// ```no_run
+/// use core::time::Duration;
+///
+/// use flipperzero::furi::stream_buffer::StreamBuffer;
+///
+/// let buffer = StreamBuffer::new(64, 1);
+/// buffer.send(b"hello", Duration::ZERO);
+///
+/// let mut buf = [0; 16];
+/// let len = buffer.receive(&mut buf, Duration::from_millis(100));
+/// assert_eq!(&buf[..len], b"hello");
+/// ```
+pub struct StreamBuffer {
+    raw: NonNull<sys::FuriStreamBuffer>,
+}
+
+// SAFETY: stream buffers can be used from any thread and from interrupts, and the bytes
+// in them stay within the buffer even when misused by several senders.
+unsafe impl Send for StreamBuffer {}
+unsafe impl Sync for StreamBuffer {}
+
+impl StreamBuffer {
+    /// Creates a stream buffer with room for `size` bytes, whose receivers wait for
+    /// `trigger_level` bytes.
+    ///
+    /// A trigger level of 0 is treated as 1.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is zero or `trigger_level` is above `size`.
+    pub fn new(size: usize, trigger_level: usize) -> Self {
+        assert!(size > 0, "stream buffer size must not be zero");
+        assert!(
+            trigger_level <= size,
+            "trigger level must not exceed the size"
+        );
+        Self {
+            raw: unsafe {
+                NonNull::new_unchecked(sys::furi_stream_buffer_alloc(size, trigger_level))
+            },
+        }
+    }
+
+    /// Returns the raw pointer to the stream buffer, for use with the `sys` bindings.
+    pub fn as_ptr(&self) -> *mut sys::FuriStreamBuffer {
+        self.raw.as_ptr()
+    }
+
+    /// Sets the number of bytes that receivers wait for.
+    ///
+    /// Returns `false`, leaving the trigger level unchanged, if `trigger_level` is above
+    /// the size of the buffer.
+    pub fn set_trigger_level(&self, trigger_level: usize) -> bool {
+        unsafe { sys::furi_stream_set_trigger_level(self.raw.as_ptr(), trigger_level) }
+    }
+
+    /// Copies as much of `data` into the buffer as fits, waiting up to `timeout` for
+    /// space to become free.
+    ///
+    /// Returns the number of bytes sent, which is less than `data.len()` if the buffer
+    /// stayed full.
+    pub fn send(&self, data: &[u8], timeout: Duration) -> usize {
+        unsafe {
+            sys::furi_stream_buffer_send(
+                self.raw.as_ptr(),
+                data.as_ptr() as *const c_void,
+                data.len(),
+                duration_to_ticks(timeout),
+            )
+        }
+    }
+
+    /// Copies as much of `data` into the buffer as fits, without waiting.
+    ///
+    /// This can be called from interrupts, such as the callbacks of GPIO interrupts or
+    /// the serial port, and wakes a thread waiting to receive.
+    pub fn send_from_isr(&self, data: &[u8]) -> usize {
+        // The firmware detects the interrupt context, in which the timeout must be zero.
+        self.send(data, Duration::ZERO)
+    }
+
+    /// Moves bytes from the buffer into `buf`, waiting up to `timeout` for the trigger
+    /// level of bytes to arrive.
+    ///
+    /// Returns the number of bytes received, which is 0 if nothing arrived in time.
+    pub fn receive(&self, buf: &mut [u8], timeout: Duration) -> usize {
+        unsafe {
+            sys::furi_stream_buffer_receive(
+                self.raw.as_ptr(),
+                buf.as_mut_ptr() as *mut c_void,
+                buf.len(),
+                duration_to_ticks(timeout),
+            )
+        }
+    }
+
+    /// Moves the bytes already in the buffer into `buf`, without waiting.
+    ///
+    /// This can be called from interrupts, and wakes a thread waiting to send.
+    pub fn receive_from_isr(&self, buf: &mut [u8]) -> usize {
+        self.receive(buf, Duration::ZERO)
+    }
+
+    /// Returns the number of bytes in the buffer.
+    pub fn len(&self) -> usize {
+        unsafe { sys::furi_stream_buffer_bytes_available(self.raw.as_ptr()) }
+    }
+
+    /// Returns the number of bytes that can be sent before the buffer is full.
+    pub fn space(&self) -> usize {
+        unsafe { sys::furi_stream_buffer_spaces_available(self.raw.as_ptr()) }
+    }
+
+    /// Returns `true` if there are no bytes to receive.
+    pub fn is_empty(&self) -> bool {
+        unsafe { sys::furi_stream_buffer_is_empty(self.raw.as_ptr()) }
+    }
+
+    /// Returns `true` if there is no space to send more bytes.
+    pub fn is_full(&self) -> bool {
+        unsafe { sys::furi_stream_buffer_is_full(self.raw.as_ptr()) }
+    }
+
+    /// Discards the bytes in the buffer.
+    ///
+    /// This fails with [`Status::ERR`] if a thread is waiting to send or receive.
+    pub fn reset(&self) -> furi::Result<()> {
+        let status: Status = unsafe { sys::furi_stream_buffer_reset(self.raw.as_ptr()).into() };
+        status.err_or(())
+    }
+}
+
+impl Drop for StreamBuffer {
+    fn drop(&mut self) {
+        unsafe { sys::furi_stream_buffer_free(self.raw.as_ptr()) };
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use core::time::Duration;
+
+    use super::StreamBuffer;
+
+    #[test]
+    fn bytes_pass_through_in_order() {
+        let buffer = StreamBuffer::new(8, 1);
+        assert!(buffer.is_empty());
+        assert_eq!(buffer.send(b"abc", Duration::ZERO), 3);
+        assert_eq!(buffer.send_from_isr(b"defghij"), 5);
+        assert!(buffer.is_full());
+        assert_eq!(buffer.len(), 8);
+        assert_eq!(buffer.space(), 0);
+
+        let mut buf = [0; 5];
+        assert_eq!(buffer.receive(&mut buf, Duration::ZERO), 5);
+        assert_eq!(&buf, b"abcde");
+        assert_eq!(buffer.receive_from_isr(&mut buf), 3);
+        assert_eq!(&buf[..3], b"fgh");
+        assert_eq!(buffer.receive(&mut buf, Duration::from_millis(10)), 0);
+    }
+
+    #[test]
+    fn trigger_level_and_reset() {
+        let buffer = StreamBuffer::new(8, 4);
+        assert!(!buffer.set_trigger_level(9));
+        assert!(buffer.set_trigger_level(2));
+
+        buffer.send(b"xyz", Duration::ZERO);
+        buffer.reset().unwrap();
+        assert!(buffer.is_empty());
+        let mut buf = [0; 4];
+        assert_eq!(buffer.receive(&mut buf, Duration::ZERO), 0);
+    }
+}
@@ -10,6 +10,7 @@ use core::{
     mem::ManuallyDrop,
     ops::{Add, AddAssign},
     ptr::{self, NonNull},
+    str::Utf8Error,
 };
 
 #[cfg(feature = "alloc")]
@@ -132,6 +133,15 @@ impl FuriString {
         unsafe { sys::furi_string_cat_str(self.0.as_ptr(), string.as_ptr()) }
     }
 
+    /// Appends the given bytes as they are, which must not include a nul byte.
+    pub(crate) fn push_bytes(&mut self, bytes: &[u8]) {
+        debug_assert!(!bytes.contains(&0));
+        self.reserve(bytes.len());
+        for &b in bytes {
+            unsafe { sys::furi_string_push_back(self.0.as_ptr(), b as c_char) };
+        }
+    }
+
     /// Reserves capacity for at least `additional` bytes more than the current length.
     #[inline]
     pub fn reserve(&mut self, additional: usize) {
@@ -169,6 +179,29 @@ impl FuriString {
         self.as_c_str().to_bytes_with_nul()
     }
 
+    /// Yields a `&str` slice if the `FuriString` contains valid UTF-8.
+    ///
+    /// If the contents of the `FuriString` are valid UTF-8 data, this function will
+    /// return the corresponding `&str` slice. Otherwise, it will return an error with
+    /// details of where UTF-8 validation failed.
+    #[inline]
+    pub fn to_str(&self) -> Result<&str, Utf8Error> {
+        self.as_c_str().to_str()
+    }
+
+    /// Converts a `FuriString` into a [`Cow<str>`].
+    ///
+    /// If the contents of the `FuriString` are valid UTF-8 data, this function will
+    /// return a `Cow::Borrowed(&str)` with the corresponding `&str` slice. Otherwise, it
+    /// will replace any invalid UTF-8 sequences with `U+FFFD REPLACEMENT CHARACTER` and
+    /// return a `Cow::Owned(String)` with the result.
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+    #[inline]
+    pub fn to_string_lossy(&self) -> Cow<'_, str> {
+        self.as_c_str().to_string_lossy()
+    }
+
     /// Shortens this `FuriString` to the specified length.
     ///
     /// If `new_len` is greater than the string's current length, this has no effect.
@@ -887,5 +920,21 @@ mod tests {
         for (l, r) in s.chars_lossy().zip("f�r".chars()) {
             assert_eq!(l, r);
         }
+        assert_eq!(s.to_str().unwrap_err().valid_up_to(), 1);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn lossy_conversion_replaces_invalid_utf8() {
+        use alloc::borrow::Cow;
+
+        let s = FuriString::from("grüße");
+        assert_eq!(s.to_str(), Ok("grüße"));
+        assert!(matches!(s.to_string_lossy(), Cow::Borrowed("grüße")));
+
+        let mut s = FuriString::from("f");
+        unsafe { sys::furi_string_push_back(s.as_mut_ptr(), 0xfc_u8 as i8) };
+        s.push('r');
+        assert!(matches!(s.to_string_lossy(), Cow::Owned(lossy) if lossy == "f�r"));
     }
 }
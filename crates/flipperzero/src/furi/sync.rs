@@ -1,12 +1,14 @@
 //! Furi syncronization primitives.
 
-use core::ptr;
+use core::ptr::{self, NonNull};
 use core::sync::atomic::{AtomicPtr, Ordering};
 
 use flipperzero_sys as sys;
-use lock_api::{GuardNoSend, RawMutex, RawMutexTimed};
+use lock_api::{GuardNoSend, RawMutex, RawMutexTimed, RawRwLock, RawRwLockTimed};
 use sys::furi::Status;
 
+use crate::furi;
+
 use super::time::{Duration, Instant};
 
 const MUTEX_TYPE: u8 = sys::FuriMutexType_FuriMutexTypeNormal;
@@ -103,9 +105,267 @@ unsafe impl RawMutexTimed for FuriMutex {
 pub type Mutex<T> = lock_api::Mutex<FuriMutex, T>;
 pub type MutexGuard<'a, T> = lock_api::MutexGuard<'a, FuriMutex, T>;
 
+/// The number of readers that can hold a [`FuriRwLock`] at once.
+///
+/// Further readers block until one of them is done.
+pub const MAX_READERS: u32 = 32;
+
+/// A [`RawRwLock`] implementation backed by Furi. You probably want to use [`RwLock`]
+/// instead.
+///
+/// The firmware has no readers-writer lock, so this is a semaphore with a permit for
+/// each of [`MAX_READERS`] readers, of which a writer takes all. Writers take turns
+/// through a [`FuriMutex`].
+pub struct FuriRwLock {
+    permits: AtomicPtr<sys::FuriSemaphore>,
+    writer: FuriMutex,
+}
+
+impl FuriRwLock {
+    const fn new() -> Self {
+        Self {
+            permits: AtomicPtr::new(ptr::null_mut()),
+            writer: FuriMutex::new(),
+        }
+    }
+
+    unsafe fn permits(&self) -> *mut sys::FuriSemaphore {
+        let permits = self.permits.load(Ordering::Acquire);
+        if !permits.is_null() {
+            permits
+        } else {
+            self.create()
+        }
+    }
+
+    unsafe fn create(&self) -> *mut sys::FuriSemaphore {
+        let permits = unsafe { sys::furi_semaphore_alloc(MAX_READERS, MAX_READERS) };
+
+        match self.permits.compare_exchange(
+            ptr::null_mut(),
+            permits,
+            Ordering::Release,
+            Ordering::Relaxed,
+        ) {
+            Ok(_) => permits,
+            Err(global_ptr) => {
+                unsafe { sys::furi_semaphore_free(permits) };
+                global_ptr
+            }
+        }
+    }
+
+    /// Attempts to take a permit within `timeout` ticks, or without blocking if
+    /// `timeout` is zero.
+    fn try_acquire_shared(&self, timeout: u32) -> bool {
+        let status: Status = unsafe { sys::furi_semaphore_acquire(self.permits(), timeout).into() };
+        status.is_ok()
+    }
+
+    /// Attempts to take all permits within `timeout` ticks, or without blocking if
+    /// `timeout` is zero.
+    fn try_acquire_exclusive(&self, timeout: u32) -> bool {
+        let start = Instant::now();
+        let remaining = || match timeout {
+            u32::MAX => u32::MAX,
+            timeout => timeout.saturating_sub(start.elapsed().0),
+        };
+
+        if !self.writer.try_acquire(remaining()) {
+            return false;
+        }
+        let mut taken = 0;
+        while taken < MAX_READERS && self.try_acquire_shared(remaining()) {
+            taken += 1;
+        }
+        unsafe { self.writer.unlock() };
+
+        if taken < MAX_READERS {
+            // Give back the permits taken before timing out.
+            for _ in 0..taken {
+                unsafe { self.release_shared() };
+            }
+            return false;
+        }
+        true
+    }
+
+    unsafe fn release_shared(&self) {
+        let status: Status = unsafe { sys::furi_semaphore_release(self.permits()).into() };
+        if status.is_err() {
+            panic!("furi_semaphore_release failed: {}", status);
+        }
+    }
+}
+
+impl Drop for FuriRwLock {
+    fn drop(&mut self) {
+        let permits = self.permits.load(Ordering::Acquire);
+        if !permits.is_null() {
+            unsafe { sys::furi_semaphore_free(permits) };
+        }
+    }
+}
+
+unsafe impl RawRwLock for FuriRwLock {
+    // See docs to the parent definition
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = FuriRwLock::new();
+    type GuardMarker = GuardNoSend;
+
+    fn lock_shared(&self) {
+        // See `FuriMutex::lock` for why this blocks indefinitely.
+        assert!(self.try_acquire_shared(u32::MAX));
+    }
+
+    fn try_lock_shared(&self) -> bool {
+        self.try_acquire_shared(0)
+    }
+
+    unsafe fn unlock_shared(&self) {
+        unsafe { self.release_shared() };
+    }
+
+    fn lock_exclusive(&self) {
+        assert!(self.try_acquire_exclusive(u32::MAX));
+    }
+
+    fn try_lock_exclusive(&self) -> bool {
+        self.try_acquire_exclusive(0)
+    }
+
+    unsafe fn unlock_exclusive(&self) {
+        for _ in 0..MAX_READERS {
+            unsafe { self.release_shared() };
+        }
+    }
+}
+
+unsafe impl RawRwLockTimed for FuriRwLock {
+    type Duration = Duration;
+    type Instant = Instant;
+
+    fn try_lock_shared_for(&self, timeout: Self::Duration) -> bool {
+        self.try_acquire_shared(timeout.0)
+    }
+
+    fn try_lock_shared_until(&self, timeout: Self::Instant) -> bool {
+        let now = Instant::now();
+        self.try_lock_shared_for(timeout - now)
+    }
+
+    fn try_lock_exclusive_for(&self, timeout: Self::Duration) -> bool {
+        self.try_acquire_exclusive(timeout.0)
+    }
+
+    fn try_lock_exclusive_until(&self, timeout: Self::Instant) -> bool {
+        let now = Instant::now();
+        self.try_lock_exclusive_for(timeout - now)
+    }
+}
+
+pub type RwLock<T> = lock_api::RwLock<FuriRwLock, T>;
+pub type RwLockReadGuard<'a, T> = lock_api::RwLockReadGuard<'a, FuriRwLock, T>;
+pub type RwLockWriteGuard<'a, T> = lock_api::RwLockWriteGuard<'a, FuriRwLock, T>;
+
+/// A counting semaphore, which hands out up to a fixed number of permits.
+///
+/// Permits are not tied to a thread, so one thread can release a permit for another to
+/// acquire, for instance to signal that work is available.
+///
+/// # Examples
+///
+/// ```no_run
+/// use flipperzero::furi::sync::Semaphore;
+///
+/// // Allow two workers to use the radio at once.
+/// let radio = Semaphore::new(2, 2);
+///
+/// radio.acquire();
+/// // ...
+/// radio.release().unwrap();
+/// ```
+pub struct Semaphore {
+    raw: NonNull<sys::FuriSemaphore>,
+}
+
+// SAFETY: semaphores can be used from any thread.
+unsafe impl Send for Semaphore {}
+unsafe impl Sync for Semaphore {}
+
+impl Semaphore {
+    /// Creates a semaphore with `initial` of at most `max` permits available.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max` is zero or `initial` is above `max`.
+    pub fn new(max: u32, initial: u32) -> Self {
+        assert!(max > 0, "a semaphore needs at least one permit");
+        assert!(
+            initial <= max,
+            "a semaphore can't start with more than `max` permits"
+        );
+        Self {
+            raw: unsafe { NonNull::new_unchecked(sys::furi_semaphore_alloc(max, initial)) },
+        }
+    }
+
+    /// Returns the raw pointer to the semaphore, for use with the `sys` bindings.
+    pub fn as_ptr(&self) -> *mut sys::FuriSemaphore {
+        self.raw.as_ptr()
+    }
+
+    /// Takes a permit, blocking until one is available.
+    pub fn acquire(&self) {
+        // See `FuriMutex::lock` for why this blocks indefinitely.
+        assert!(self.try_acquire_ticks(u32::MAX));
+    }
+
+    /// Takes a permit if one is available, without blocking.
+    pub fn try_acquire(&self) -> bool {
+        self.try_acquire_ticks(0)
+    }
+
+    /// Takes a permit, waiting up to `timeout` for one to become available.
+    pub fn try_acquire_for(&self, timeout: Duration) -> bool {
+        self.try_acquire_ticks(timeout.0)
+    }
+
+    fn try_acquire_ticks(&self, timeout: u32) -> bool {
+        let status: Status =
+            unsafe { sys::furi_semaphore_acquire(self.raw.as_ptr(), timeout).into() };
+        status.is_ok()
+    }
+
+    /// Gives back a permit.
+    ///
+    /// Returns an error if all permits are already available.
+    pub fn release(&self) -> furi::Result<()> {
+        let status: Status = unsafe { sys::furi_semaphore_release(self.raw.as_ptr()).into() };
+        status.err_or(())
+    }
+
+    /// Returns the number of available permits.
+    pub fn count(&self) -> u32 {
+        unsafe { sys::furi_semaphore_get_count(self.raw.as_ptr()) }
+    }
+
+    /// Returns the number of permits that have been taken.
+    pub fn space(&self) -> u32 {
+        unsafe { sys::furi_semaphore_get_space(self.raw.as_ptr()) }
+    }
+}
+
+impl Drop for Semaphore {
+    fn drop(&mut self) {
+        unsafe { sys::furi_semaphore_free(self.raw.as_ptr()) };
+    }
+}
+
 #[flipperzero_test::tests]
 mod tests {
-    use super::Mutex;
+    use super::{Mutex, RwLock, Semaphore, MAX_READERS};
+    use crate::furi::time::Duration;
 
     #[test]
     fn unshared_mutex_does_not_block() {
@@ -122,4 +382,58 @@ mod tests {
             assert_eq!(*value, 42);
         }
     }
+
+    #[test]
+    fn held_mutex_times_out() {
+        let mutex = Mutex::new(());
+
+        let guard = mutex.lock();
+        assert!(mutex.try_lock().is_none());
+        assert!(mutex.try_lock_for(Duration::from_millis(10)).is_none());
+
+        drop(guard);
+        assert!(mutex.try_lock_for(Duration::from_millis(10)).is_some());
+    }
+
+    #[test]
+    fn rw_lock_has_readers_or_a_writer() {
+        let lock = RwLock::new(1);
+
+        {
+            let readers: [_; MAX_READERS as usize] = core::array::from_fn(|_| lock.read());
+            assert!(readers.iter().all(|value| **value == 1));
+            assert!(lock.try_read().is_none());
+            assert!(lock.try_write_for(Duration::from_millis(10)).is_none());
+        }
+
+        {
+            let mut value = lock.try_write().unwrap();
+            *value = 2;
+            assert!(lock.try_read_for(Duration::from_millis(10)).is_none());
+        }
+
+        // A writer that timed out gives back the permits it took.
+        let reader = lock.read();
+        assert!(lock.try_write().is_none());
+        drop(reader);
+        assert_eq!(*lock.read(), 2);
+        assert!(lock.try_write().is_some());
+    }
+
+    #[test]
+    fn semaphore_counts_permits() {
+        let semaphore = Semaphore::new(2, 1);
+        assert_eq!(semaphore.count(), 1);
+        assert_eq!(semaphore.space(), 1);
+
+        assert!(semaphore.try_acquire());
+        assert!(!semaphore.try_acquire());
+        assert!(!semaphore.try_acquire_for(Duration::from_millis(10)));
+        assert_eq!(semaphore.count(), 0);
+
+        semaphore.release().unwrap();
+        semaphore.release().unwrap();
+        assert_eq!(semaphore.count(), 2);
+        assert!(semaphore.release().is_err());
+    }
 }
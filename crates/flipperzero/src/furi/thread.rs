@@ -29,6 +29,7 @@ pub struct Builder {
     name: Option<CString>,
     stack_size: Option<usize>,
     heap_trace_enabled: Option<bool>,
+    priority: Option<Priority>,
 }
 
 #[cfg(feature = "alloc")]
@@ -47,6 +48,7 @@ impl Builder {
             name: None,
             stack_size: None,
             heap_trace_enabled: None,
+            priority: None,
         }
     }
 
@@ -66,6 +68,14 @@ impl Builder {
         self
     }
 
+    /// Sets the priority of the new thread.
+    ///
+    /// By default, threads run at [`Priority::Normal`].
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
     /// Enables heap tracing.
     ///
     /// By default, heap tracing is enabled if the Flipper Zero's "heap track mode" is
@@ -86,9 +96,10 @@ impl Builder {
             name,
             stack_size,
             heap_trace_enabled,
+            priority,
         } = self;
         #[allow(clippy::arc_with_non_send_sync)] // TODO: is using `Arc` neccessary/sound here?
-        let thread = Arc::new(Thread::new(name, stack_size, heap_trace_enabled));
+        let thread = Arc::new(Thread::new(name, stack_size, heap_trace_enabled, priority));
 
         // We need to box twice because trait objects are fat pointers, so we need the
         // second box to obtain a thin pointer to use as the context.
@@ -186,6 +197,57 @@ pub fn current() -> Thread {
     Thread { name, thread }
 }
 
+/// The priority of a thread, which the scheduler runs before any threads of a lower
+/// priority that are ready.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    /// Only runs when nothing else is ready.
+    Idle,
+    Lowest,
+    Low,
+    /// The priority of app threads.
+    Normal,
+    High,
+    Highest,
+}
+
+impl Priority {
+    pub fn from_sys(priority: sys::FuriThreadPriority) -> Option<Self> {
+        match priority {
+            sys::FuriThreadPriority_FuriThreadPriorityIdle => Some(Self::Idle),
+            sys::FuriThreadPriority_FuriThreadPriorityLowest => Some(Self::Lowest),
+            sys::FuriThreadPriority_FuriThreadPriorityLow => Some(Self::Low),
+            sys::FuriThreadPriority_FuriThreadPriorityNormal => Some(Self::Normal),
+            sys::FuriThreadPriority_FuriThreadPriorityHigh => Some(Self::High),
+            sys::FuriThreadPriority_FuriThreadPriorityHighest => Some(Self::Highest),
+            _ => None,
+        }
+    }
+
+    pub fn to_sys(&self) -> sys::FuriThreadPriority {
+        match self {
+            Self::Idle => sys::FuriThreadPriority_FuriThreadPriorityIdle,
+            Self::Lowest => sys::FuriThreadPriority_FuriThreadPriorityLowest,
+            Self::Low => sys::FuriThreadPriority_FuriThreadPriorityLow,
+            Self::Normal => sys::FuriThreadPriority_FuriThreadPriorityNormal,
+            Self::High => sys::FuriThreadPriority_FuriThreadPriorityHigh,
+            Self::Highest => sys::FuriThreadPriority_FuriThreadPriorityHighest,
+        }
+    }
+}
+
+/// Returns the priority of the current thread.
+///
+/// Returns `None` if the priority is not one of [`Priority`], as for the timer thread.
+pub fn current_priority() -> Option<Priority> {
+    Priority::from_sys(unsafe { sys::furi_thread_get_current_priority() })
+}
+
+/// Changes the priority of the current thread.
+pub fn set_current_priority(priority: Priority) {
+    unsafe { sys::furi_thread_set_current_priority(priority.to_sys()) };
+}
+
 /// Cooperatively gives up a timeslice to the OS scheduler.
 pub fn yield_now() {
     unsafe { sys::furi_thread_yield() };
@@ -206,9 +268,15 @@ pub fn sleep(duration: core::time::Duration) {
 /// A unique identifier for a running thread.
 #[cfg(feature = "alloc")]
 #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
-#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct ThreadId(sys::FuriThreadId);
 
+// SAFETY: the ID is only compared, never dereferenced.
+#[cfg(feature = "alloc")]
+unsafe impl Send for ThreadId {}
+#[cfg(feature = "alloc")]
+unsafe impl Sync for ThreadId {}
+
 /// A handle to a thread.
 #[cfg(feature = "alloc")]
 #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
@@ -224,6 +292,7 @@ impl Thread {
         name: Option<CString>,
         stack_size: Option<usize>,
         heap_trace_enabled: Option<bool>,
+        priority: Option<Priority>,
     ) -> Self {
         let stack_size = stack_size.unwrap_or(MIN_STACK_SIZE);
 
@@ -238,6 +307,9 @@ impl Thread {
                     sys::furi_thread_enable_heap_trace(thread);
                 }
             }
+            if let Some(priority) = priority {
+                sys::furi_thread_set_priority(thread, priority.to_sys());
+            }
             Thread {
                 name,
                 thread: NonNull::new_unchecked(thread),
@@ -358,3 +430,26 @@ impl ufmt::uDebug for JoinHandle {
         f.debug_struct("JoinHandle")?.finish()
     }
 }
+
+#[cfg(feature = "alloc")]
+#[flipperzero_test::tests]
+mod tests {
+    use super::{current, current_priority, Builder, Priority};
+
+    #[test]
+    fn spawned_threads_have_their_priority() {
+        let handle = Builder::new()
+            .priority(Priority::Low)
+            .spawn(|| (current_priority() == Some(Priority::Low)) as i32);
+        assert_eq!(handle.join(), 1);
+    }
+
+    #[test]
+    fn threads_have_distinct_ids() {
+        let this = current().id().unwrap();
+        assert_eq!(current().id(), Some(this));
+
+        let handle = super::spawn(move || (current().id() != Some(this)) as i32);
+        assert_eq!(handle.join(), 1);
+    }
+}
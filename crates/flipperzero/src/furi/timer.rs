@@ -0,0 +1,205 @@
+//! Furi Timer API.
+
+use alloc::boxed::Box;
+use core::ffi::c_void;
+use core::ptr::NonNull;
+use core::time::Duration;
+
+use flipperzero_sys as sys;
+use flipperzero_sys::furi::{duration_to_ticks, Status};
+
+use crate::furi;
+
+type Callback = Box<dyn FnMut() + Send>;
+
+/// A timer which calls a closure on the timer service thread when it expires.
+///
+/// The timer owns the closure, and stops before the closure is dropped, so the closure
+/// may only be called while the `Timer` exists. It must be quick and must not block,
+/// as all timers share the service thread.
+///
+/// # Examples
+///
+/// ```no_run
+/// use core::sync::atomic::{AtomicBool, Ordering};
+/// use core::time::Duration;
+///
+/// use flipperzero::furi::timer::Timer;
+///
+/// static IDLE: AtomicBool = AtomicBool::new(false);
+///
+/// let mut timeout = Timer::one_shot(|| IDLE.store(true, Ordering::Relaxed));
+/// timeout.start(Duration::from_secs(30)).unwrap();
+///
+/// // On each key press, wait another 30 seconds.
+/// timeout.restart().unwrap();
+/// ```
+pub struct Timer {
+    raw: NonNull<sys::FuriTimer>,
+    callback: NonNull<Callback>,
+    interval: Option<u32>,
+}
+
+impl Timer {
+    /// Creates a stopped timer which calls `callback` every time its interval elapses.
+    pub fn periodic<F>(callback: F) -> Self
+    where
+        F: FnMut() + Send + 'static,
+    {
+        Self::new(sys::FuriTimerType_FuriTimerTypePeriodic, Box::new(callback))
+    }
+
+    /// Creates a stopped timer which calls `callback` once its interval elapses, and then
+    /// stops until it is started again.
+    pub fn one_shot<F>(callback: F) -> Self
+    where
+        F: FnMut() + Send + 'static,
+    {
+        Self::new(sys::FuriTimerType_FuriTimerTypeOnce, Box::new(callback))
+    }
+
+    fn new(timer_type: sys::FuriTimerType, callback: Callback) -> Self {
+        // Boxed twice for a thin pointer, see `furi::thread::Builder::spawn`.
+        let callback = unsafe { NonNull::new_unchecked(Box::into_raw(Box::new(callback))) };
+
+        unsafe extern "C" fn run_callback(context: *mut c_void) {
+            let callback = unsafe { &mut *(context as *mut Callback) };
+            callback();
+        }
+
+        let raw = unsafe {
+            NonNull::new_unchecked(sys::furi_timer_alloc(
+                Some(run_callback),
+                timer_type,
+                callback.as_ptr() as *mut c_void,
+            ))
+        };
+
+        Self {
+            raw,
+            callback,
+            interval: None,
+        }
+    }
+
+    /// Starts the timer, or restarts it if it is already running, with the given
+    /// interval.
+    pub fn start(&mut self, interval: Duration) -> furi::Result<()> {
+        let ticks = duration_to_ticks(interval);
+        let status: Status = unsafe { sys::furi_timer_start(self.raw.as_ptr(), ticks) }.into();
+        if status.is_ok() {
+            self.interval = Some(ticks);
+        }
+        status.err_or(())
+    }
+
+    /// Starts the timer again with the interval it was last started with, counting from
+    /// now, whether or not it is running.
+    ///
+    /// Returns [`Status::ERR_RESOURCE`] if the timer has never been started.
+    pub fn restart(&mut self) -> furi::Result<()> {
+        let ticks = self.interval.ok_or(Status::ERR_RESOURCE)?;
+        let status: Status = unsafe { sys::furi_timer_restart(self.raw.as_ptr(), ticks) }.into();
+        status.err_or(())
+    }
+
+    /// Stops the timer.
+    pub fn stop(&mut self) -> furi::Result<()> {
+        let status: Status = unsafe { sys::furi_timer_stop(self.raw.as_ptr()) }.into();
+        status.err_or(())
+    }
+
+    /// Returns `true` if the timer is running.
+    ///
+    /// As starting and stopping the timer happens asynchronously on the timer service
+    /// thread, this may not yet reflect recent calls to [`Timer::start`] or
+    /// [`Timer::stop`].
+    pub fn is_running(&self) -> bool {
+        unsafe { sys::furi_timer_is_running(self.raw.as_ptr()) != 0 }
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        unsafe {
+            // This stops the timer and waits for any running callback to complete.
+            sys::furi_timer_free(self.raw.as_ptr());
+            drop(Box::from_raw(self.callback.as_ptr()));
+        }
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use alloc::sync::Arc;
+    use core::sync::atomic::{AtomicU32, Ordering};
+    use core::time::Duration;
+
+    use flipperzero_sys::furi::Status;
+
+    use super::Timer;
+    use crate::furi::event_flag::{EventFlag, Wait};
+
+    const FIRED: u32 = 1;
+
+    /// Long enough for a timer of a few milliseconds to fire even on a busy device.
+    const TIMEOUT: Duration = Duration::from_secs(1);
+
+    /// How long a timer that shouldn't fire again is watched.
+    const QUIET: Duration = Duration::from_millis(200);
+
+    /// Returns a timer callback counting its calls in `calls` and setting [`FIRED`].
+    fn counter(calls: &'static AtomicU32, fired: &Arc<EventFlag>) -> impl FnMut() + Send {
+        let fired = fired.clone();
+        move || {
+            calls.fetch_add(1, Ordering::Relaxed);
+            fired.set(FIRED).unwrap();
+        }
+    }
+
+    #[test]
+    fn one_shot_timer_fires_once() {
+        static CALLS: AtomicU32 = AtomicU32::new(0);
+        let fired = Arc::new(EventFlag::new());
+
+        let mut timer = Timer::one_shot(counter(&CALLS, &fired));
+        assert_eq!(timer.restart(), Err(Status::ERR_RESOURCE));
+
+        timer.start(Duration::from_millis(10)).unwrap();
+        fired.wait(FIRED, Wait::Any, TIMEOUT).unwrap();
+        assert_eq!(
+            fired.wait(FIRED, Wait::Any, QUIET),
+            Err(Status::ERR_TIMEOUT)
+        );
+        assert_eq!(CALLS.load(Ordering::Relaxed), 1);
+        assert!(!timer.is_running());
+
+        timer.restart().unwrap();
+        fired.wait(FIRED, Wait::Any, TIMEOUT).unwrap();
+        assert_eq!(CALLS.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn periodic_timer_fires_until_stopped() {
+        static CALLS: AtomicU32 = AtomicU32::new(0);
+        let fired = Arc::new(EventFlag::new());
+
+        let mut timer = Timer::periodic(counter(&CALLS, &fired));
+        timer.start(Duration::from_millis(10)).unwrap();
+        for _ in 0..3 {
+            fired.wait(FIRED, Wait::Any, TIMEOUT).unwrap();
+        }
+        timer.stop().unwrap();
+        // A call that was already due may still run while the stop is processed.
+        fired.clear(FIRED).unwrap();
+        let _ = fired.wait(FIRED, Wait::Any, QUIET);
+
+        let calls = CALLS.load(Ordering::Relaxed);
+        assert!(calls >= 3);
+        assert_eq!(
+            fired.wait(FIRED, Wait::Any, QUIET),
+            Err(Status::ERR_TIMEOUT)
+        );
+        assert_eq!(CALLS.load(Ordering::Relaxed), calls);
+    }
+}
@@ -0,0 +1,299 @@
+//! Analog-to-digital converter of the Flipper Zero.
+
+use core::ptr::NonNull;
+
+use flipperzero_sys as sys;
+
+use super::Pin;
+
+/// The voltage reference, which is the highest voltage that can be measured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scale {
+    /// 2.048V, which gives whole numbers of millivolts per step.
+    V2048,
+    /// 2.5V.
+    V2500,
+}
+
+impl Scale {
+    pub fn to_sys(&self) -> sys::FuriHalAdcScale {
+        match self {
+            Self::V2048 => sys::FuriHalAdcScale_FuriHalAdcScale2048,
+            Self::V2500 => sys::FuriHalAdcScale_FuriHalAdcScale2500,
+        }
+    }
+}
+
+/// The clock of the converter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Clock {
+    Sync16,
+    Sync32,
+    Sync64,
+}
+
+impl Clock {
+    pub fn to_sys(&self) -> sys::FuriHalAdcClock {
+        match self {
+            Self::Sync16 => sys::FuriHalAdcClock_FuriHalAdcClockSync16,
+            Self::Sync32 => sys::FuriHalAdcClock_FuriHalAdcClockSync32,
+            Self::Sync64 => sys::FuriHalAdcClock_FuriHalAdcClockSync64,
+        }
+    }
+}
+
+/// The number of samples averaged into each value, which reduces noise for signals that
+/// don't change during the conversion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Oversample {
+    None,
+    X2,
+    X4,
+    X8,
+    X16,
+    X32,
+    X64,
+    X128,
+    X256,
+}
+
+impl Oversample {
+    pub fn to_sys(&self) -> sys::FuriHalAdcOversample {
+        match self {
+            Self::None => sys::FuriHalAdcOversample_FuriHalAdcOversampleNone,
+            Self::X2 => sys::FuriHalAdcOversample_FuriHalAdcOversample2,
+            Self::X4 => sys::FuriHalAdcOversample_FuriHalAdcOversample4,
+            Self::X8 => sys::FuriHalAdcOversample_FuriHalAdcOversample8,
+            Self::X16 => sys::FuriHalAdcOversample_FuriHalAdcOversample16,
+            Self::X32 => sys::FuriHalAdcOversample_FuriHalAdcOversample32,
+            Self::X64 => sys::FuriHalAdcOversample_FuriHalAdcOversample64,
+            Self::X128 => sys::FuriHalAdcOversample_FuriHalAdcOversample128,
+            Self::X256 => sys::FuriHalAdcOversample_FuriHalAdcOversample256,
+        }
+    }
+}
+
+/// The time each sample is taken over, in cycles of the [`Clock`].
+///
+/// Longer times suit sources with a higher impedance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SamplingTime {
+    Cycles2_5,
+    Cycles6_5,
+    Cycles12_5,
+    Cycles24_5,
+    Cycles47_5,
+    Cycles92_5,
+    Cycles247_5,
+    Cycles640_5,
+}
+
+impl SamplingTime {
+    pub fn to_sys(&self) -> sys::FuriHalAdcSamplingTime {
+        match self {
+            Self::Cycles2_5 => sys::FuriHalAdcSamplingTime_FuriHalAdcSamplingtime2_5,
+            Self::Cycles6_5 => sys::FuriHalAdcSamplingTime_FuriHalAdcSamplingtime6_5,
+            Self::Cycles12_5 => sys::FuriHalAdcSamplingTime_FuriHalAdcSamplingtime12_5,
+            Self::Cycles24_5 => sys::FuriHalAdcSamplingTime_FuriHalAdcSamplingtime24_5,
+            Self::Cycles47_5 => sys::FuriHalAdcSamplingTime_FuriHalAdcSamplingtime47_5,
+            Self::Cycles92_5 => sys::FuriHalAdcSamplingTime_FuriHalAdcSamplingtime92_5,
+            Self::Cycles247_5 => sys::FuriHalAdcSamplingTime_FuriHalAdcSamplingtime247_5,
+            Self::Cycles640_5 => sys::FuriHalAdcSamplingTime_FuriHalAdcSamplingtime640_5,
+        }
+    }
+}
+
+/// The settings of the converter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    pub scale: Scale,
+    pub clock: Clock,
+    pub oversample: Oversample,
+    pub sampling_time: SamplingTime,
+}
+
+impl Default for Config {
+    /// The firmware's defaults, which suit slowly changing signals of up to 2.048V from
+    /// sources below 10kΩ, taking 260µs per value.
+    fn default() -> Self {
+        Self {
+            scale: Scale::V2048,
+            clock: Clock::Sync64,
+            oversample: Oversample::X64,
+            sampling_time: SamplingTime::Cycles247_5,
+        }
+    }
+}
+
+/// What is measured by [`Adc::read`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    /// A pin of the GPIO header, which should be in the
+    /// [`Mode::Analog`](super::Mode::Analog) state.
+    Pin(AnalogPin),
+    /// The internal voltage reference, which is used to calibrate the [`Scale`].
+    ///
+    /// [`Adc::read_millivolts`] returns the actual voltage of the scale for it.
+    Vref,
+    /// A third of the voltage of the backup battery input.
+    ///
+    /// This needs a sampling time of at least 12µs.
+    Battery,
+}
+
+impl Channel {
+    pub fn to_sys(&self) -> sys::FuriHalAdcChannel {
+        match self {
+            Self::Pin(pin) => pin.to_sys(),
+            Self::Vref => sys::FuriHalAdcChannel_FuriHalAdcChannelVREFINT,
+            Self::Battery => sys::FuriHalAdcChannel_FuriHalAdcChannelVBAT,
+        }
+    }
+}
+
+/// A [`Pin`] that can be measured by the converter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnalogPin(Pin);
+
+impl AnalogPin {
+    /// Returns the analog channel of `pin`, or `None` for [`Pin::Pb3`] and [`Pin::Pb2`],
+    /// which have none.
+    pub fn new(pin: Pin) -> Option<Self> {
+        match pin {
+            Pin::Pb3 | Pin::Pb2 => None,
+            pin => Some(Self(pin)),
+        }
+    }
+
+    /// Returns the pin.
+    pub fn pin(self) -> Pin {
+        self.0
+    }
+
+    pub fn to_sys(&self) -> sys::FuriHalAdcChannel {
+        match self.0 {
+            Pin::Pa7 => sys::FuriHalAdcChannel_FuriHalAdcChannel12,
+            Pin::Pa6 => sys::FuriHalAdcChannel_FuriHalAdcChannel11,
+            Pin::Pa4 => sys::FuriHalAdcChannel_FuriHalAdcChannel9,
+            Pin::Pc3 => sys::FuriHalAdcChannel_FuriHalAdcChannel4,
+            Pin::Pc1 => sys::FuriHalAdcChannel_FuriHalAdcChannel2,
+            Pin::Pc0 => sys::FuriHalAdcChannel_FuriHalAdcChannel1,
+            Pin::Pb3 | Pin::Pb2 => unreachable!(),
+        }
+    }
+}
+
+/// A handle to the analog-to-digital converter.
+///
+/// Only one handle exists at a time, so acquiring blocks until any other is dropped.
+///
+/// # Examples
+///
+/// ```no_run
+/// use flipperzero::gpio::adc::{Adc, AnalogPin, Channel};
+/// use flipperzero::gpio::Pin;
+///
+/// let mut adc = Adc::acquire();
+/// let pin = AnalogPin::new(Pin::Pc3).unwrap();
+/// let millivolts = adc.read_millivolts(Channel::Pin(pin));
+/// ```
+pub struct Adc {
+    handle: NonNull<sys::FuriHalAdcHandle>,
+}
+
+impl Adc {
+    /// Acquires and enables the converter with [`Config::default`].
+    pub fn acquire() -> Self {
+        Self::with_config(Config::default())
+    }
+
+    /// Acquires and enables the converter with `config`.
+    ///
+    /// To change the settings, drop the handle and acquire the converter again.
+    pub fn with_config(config: Config) -> Self {
+        let handle = unsafe { NonNull::new_unchecked(sys::furi_hal_adc_acquire()) };
+        unsafe {
+            sys::furi_hal_adc_configure_ex(
+                handle.as_ptr(),
+                config.scale.to_sys(),
+                config.clock.to_sys(),
+                config.oversample.to_sys(),
+                config.sampling_time.to_sys(),
+            )
+        };
+        Self { handle }
+    }
+
+    /// Measures `channel`, returning the 12-bit value.
+    pub fn read(&mut self, channel: Channel) -> u16 {
+        unsafe { sys::furi_hal_adc_read(self.handle.as_ptr(), channel.to_sys()) }
+    }
+
+    /// Measures `channel`, returning its voltage in millivolts.
+    pub fn read_millivolts(&mut self, channel: Channel) -> f32 {
+        let value = self.read(channel);
+        let handle = self.handle.as_ptr();
+        match channel {
+            Channel::Pin(_) => unsafe { sys::furi_hal_adc_convert_to_voltage(handle, value) },
+            Channel::Vref => unsafe { sys::furi_hal_adc_convert_vref(handle, value) },
+            Channel::Battery => unsafe { sys::furi_hal_adc_convert_vbat(handle, value) },
+        }
+    }
+
+    /// Measures the temperature of the chip in degrees Celsius.
+    ///
+    /// This needs a sampling time of at least 5µs.
+    pub fn read_temperature(&mut self) -> f32 {
+        let handle = self.handle.as_ptr();
+        unsafe {
+            let value =
+                sys::furi_hal_adc_read(handle, sys::FuriHalAdcChannel_FuriHalAdcChannelTEMPSENSOR);
+            sys::furi_hal_adc_convert_temp(handle, value)
+        }
+    }
+}
+
+impl Drop for Adc {
+    fn drop(&mut self) {
+        unsafe { sys::furi_hal_adc_release(self.handle.as_ptr()) };
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use core::ptr::addr_of;
+    use core::slice;
+
+    use flipperzero_sys as sys;
+
+    use super::{Adc, AnalogPin, Channel, Clock, Config, SamplingTime};
+    use crate::gpio::Pin;
+
+    #[test]
+    fn pin_channels_match_the_firmware() {
+        let records: &[sys::GpioPinRecord] =
+            unsafe { slice::from_raw_parts(addr_of!(sys::gpio_pins).cast(), sys::gpio_pins_count) };
+        for record in records {
+            for pin in [Pin::Pa7, Pin::Pa6, Pin::Pa4, Pin::Pc3, Pin::Pc1, Pin::Pc0] {
+                if record.pin == pin.as_raw() {
+                    assert_eq!(AnalogPin::new(pin).unwrap().to_sys(), record.channel);
+                }
+            }
+        }
+        assert!(AnalogPin::new(Pin::Pb2).is_none());
+    }
+
+    #[test]
+    fn reads_internal_channels() {
+        let mut adc = Adc::with_config(Config {
+            clock: Clock::Sync32,
+            sampling_time: SamplingTime::Cycles640_5,
+            ..Config::default()
+        });
+        // Both are only checked to be plausible, as they vary between chips.
+        let vref = adc.read_millivolts(Channel::Vref);
+        assert!((1000.0..3600.0).contains(&vref));
+        let temperature = adc.read_temperature();
+        assert!((-20.0..100.0).contains(&temperature));
+    }
+}
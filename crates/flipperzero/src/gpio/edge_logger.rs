@@ -0,0 +1,567 @@
+//! Logging the edges of a GPIO pin.
+
+use alloc::sync::Arc;
+use core::ffi::c_void;
+use core::ptr::NonNull;
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use core::time::Duration;
+
+use flipperzero_sys as sys;
+use flipperzero_sys::furi::duration_to_ticks;
+
+use super::Pin;
+use crate::furi::sync::Mutex;
+use crate::furi::thread::{self, JoinHandle};
+use crate::io::{Error, Write};
+
+/// Number of edges the interrupt can buffer before the writer thread drains them.
+const BUFFER_LEN: usize = 1024;
+
+/// Size of an edge in the buffer: the cycle counter with the level in the lowest bit.
+const RECORD_SIZE: usize = 4;
+
+/// How often the writer thread wakes without any edges, which keeps the cycle counter
+/// from wrapping unnoticed.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+const WRITER_STACK_SIZE: usize = 2048;
+
+/// Writes the edges captured by an [`EdgeLogger`] in some file format.
+pub trait EdgeFormat {
+    /// Writes what comes before the first edge, such as a header.
+    fn begin<W: Write>(&mut self, out: &mut W, resolution_us: u32) -> Result<(), Error> {
+        let _ = (out, resolution_us);
+        Ok(())
+    }
+
+    /// Writes that the pin changed to `level` after `timestamp` units of the timer
+    /// resolution since logging started.
+    ///
+    /// The first call is the level of the pin at timestamp zero.
+    fn edge<W: Write>(&mut self, out: &mut W, timestamp: u64, level: bool) -> Result<(), Error>;
+
+    /// Writes what comes after the last edge.
+    fn end<W: Write>(&mut self, out: &mut W, stats: &EdgeStats) -> Result<(), Error> {
+        let _ = (out, stats);
+        Ok(())
+    }
+}
+
+/// Edges as CSV, one `time_us,level` line per edge with the level as `0` or `1`.
+#[derive(Debug, Default)]
+pub struct Csv {
+    resolution_us: u32,
+}
+
+impl Csv {
+    /// Creates the CSV format.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl EdgeFormat for Csv {
+    fn begin<W: Write>(&mut self, out: &mut W, resolution_us: u32) -> Result<(), Error> {
+        self.resolution_us = resolution_us;
+        out.write_all(b"time_us,level\n")
+    }
+
+    fn edge<W: Write>(&mut self, out: &mut W, timestamp: u64, level: bool) -> Result<(), Error> {
+        let mut line = [0; 24];
+        let mut start = line.len() - 3;
+        line[start..].copy_from_slice(if level { b",1\n" } else { b",0\n" });
+        let mut time = timestamp.saturating_mul(self.resolution_us.into());
+        loop {
+            start -= 1;
+            line[start] = b'0' + (time % 10) as u8;
+            time /= 10;
+            if time == 0 {
+                break;
+            }
+        }
+        out.write_all(&line[start..])
+    }
+}
+
+/// Edges in a compact binary format.
+///
+/// The file starts with [`Binary::MAGIC`] and the timer resolution in microseconds as
+/// a little-endian `u32`. Each edge follows as a LEB128 varint of the timestamps since
+/// the previous edge, shifted left by one with the new level in the lowest bit. Most
+/// edges take one or two bytes.
+#[derive(Debug, Default)]
+pub struct Binary {
+    previous: u64,
+}
+
+impl Binary {
+    /// Bytes a binary edge log starts with.
+    pub const MAGIC: [u8; 4] = *b"EDG1";
+
+    /// Creates the binary format.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl EdgeFormat for Binary {
+    fn begin<W: Write>(&mut self, out: &mut W, resolution_us: u32) -> Result<(), Error> {
+        self.previous = 0;
+        out.write_all(&Self::MAGIC)?;
+        out.write_all(&resolution_us.to_le_bytes())
+    }
+
+    fn edge<W: Write>(&mut self, out: &mut W, timestamp: u64, level: bool) -> Result<(), Error> {
+        let delta = timestamp.saturating_sub(self.previous);
+        self.previous = timestamp;
+
+        // A delta above `u64::MAX >> 1` would take centuries at a one microsecond
+        // resolution, so the lost top bit doesn't matter.
+        let mut value = delta << 1 | u64::from(level);
+        let mut varint = [0; 10];
+        let mut len = 0;
+        loop {
+            let byte = (value & 0x7F) as u8;
+            value >>= 7;
+            if value == 0 {
+                varint[len] = byte;
+                len += 1;
+                break;
+            }
+            varint[len] = byte | 0x80;
+            len += 1;
+        }
+        out.write_all(&varint[..len])
+    }
+}
+
+/// Statistics of an [`EdgeLogger`] once stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EdgeStats {
+    /// Number of edges written, not counting the initial level.
+    pub edges: u64,
+    /// Number of edges lost because the buffer was full, usually because the output
+    /// was too slow for the signal.
+    pub dropped: u32,
+    /// How long logging ran, in units of the timer resolution.
+    pub duration: u64,
+}
+
+/// Turns the 32-bit cycle counter into time since logging started.
+///
+/// The counter wraps after about a minute, so it has to be observed at least every half
+/// of that.
+#[derive(Debug)]
+struct Clock {
+    /// Cycles from the start to the last observation.
+    elapsed: u64,
+    last: u32,
+}
+
+impl Clock {
+    fn new(start: u32) -> Self {
+        Self {
+            elapsed: 0,
+            last: start,
+        }
+    }
+
+    /// Moves the clock forward to the counter value `now`.
+    fn observe(&mut self, now: u32) {
+        self.elapsed += u64::from(now.wrapping_sub(self.last));
+        self.last = now;
+    }
+
+    /// Returns the cycles since the start at the counter value `cycles`, which may be
+    /// up to half the counter range before or after the last observation.
+    fn elapsed_at(&self, cycles: u32) -> u64 {
+        let offset = cycles.wrapping_sub(self.last) as i32;
+        self.elapsed.saturating_add_signed(offset.into())
+    }
+}
+
+/// Packs a cycle count and a level into a buffer record, losing the lowest bit of the
+/// cycle count.
+fn pack(cycles: u32, level: bool) -> u32 {
+    cycles & !1 | u32::from(level)
+}
+
+fn unpack(record: u32) -> (u32, bool) {
+    (record & !1, record & 1 != 0)
+}
+
+fn cycle_counter() -> u32 {
+    unsafe { sys::furi_hal_cortex_timer_get(0).start }
+}
+
+/// State shared with the edge interrupt and the writer thread.
+struct Shared {
+    edges: NonNull<sys::FuriStreamBuffer>,
+    pin: *const sys::GpioPin,
+    dropped: AtomicU32,
+    stop: AtomicBool,
+    /// The result of the writer thread, once it has finished.
+    report: Mutex<Option<Result<EdgeStats, Error>>>,
+}
+
+// The stream buffer and pin are only accessed through the thread-safe Furi HAL.
+unsafe impl Send for Shared {}
+unsafe impl Sync for Shared {}
+
+impl Drop for Shared {
+    fn drop(&mut self) {
+        unsafe { sys::furi_stream_buffer_free(self.edges.as_ptr()) };
+    }
+}
+
+/// Buffers an edge, in interrupt context.
+unsafe extern "C" fn edge_isr(context: *mut c_void) {
+    let shared = unsafe { &*(context as *const Shared) };
+    let cycles = cycle_counter();
+    let level = unsafe { sys::furi_hal_gpio_read(shared.pin) };
+    let buffer = shared.edges.as_ptr();
+
+    // Only buffer whole records, so the writer never sees a torn one.
+    if unsafe { sys::furi_stream_buffer_spaces_available(buffer) } < RECORD_SIZE {
+        shared.dropped.fetch_add(1, Ordering::Relaxed);
+        return;
+    }
+    let record = pack(cycles, level);
+    unsafe {
+        sys::furi_stream_buffer_send(
+            buffer,
+            &record as *const u32 as *const c_void,
+            RECORD_SIZE,
+            0,
+        )
+    };
+}
+
+/// Logs the rising and falling edges of a GPIO pin to a writer.
+///
+/// An interrupt timestamps each edge with the CPU cycle counter and buffers it, and a
+/// writer thread turns the buffered edges into an [`EdgeFormat`], so a slow output like
+/// a file only has to keep up on average. Wrap files in a
+/// [`BufWriter`](crate::storage::BufWriter), as every edge is a small write.
+///
+/// Edges that arrive while the buffer is full are dropped and counted in
+/// [`EdgeStats::dropped`]. Pulses shorter than the interrupt latency of a few
+/// microseconds may be logged with the wrong level.
+///
+/// Dropping the logger stops it, ignoring any error. Call [`EdgeLogger::stop`] to
+/// handle errors and get the statistics.
+pub struct EdgeLogger {
+    shared: Arc<Shared>,
+    /// `None` once the logger has been stopped.
+    writer: Option<JoinHandle>,
+}
+
+impl EdgeLogger {
+    /// Starts logging the edges of `pin` to `output` as [`Csv`], with timestamps
+    /// rounded down to `timer_resolution_us` microseconds.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidParameter`] if `timer_resolution_us` is zero, or so
+    /// large that the timestamps can't be counted in CPU cycles.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use flipperzero::furi::thread::sleep;
+    /// # use flipperzero::gpio::{EdgeLogger, Pin};
+    /// # use flipperzero::io::Error;
+    /// # use flipperzero::storage::{BufWriter, OpenOptions};
+    /// # use core::time::Duration;
+    /// # fn main() -> Result<(), Error> {
+    /// let file = OpenOptions::new()
+    ///     .write(true)
+    ///     .create_always(true)
+    ///     .open(c"/ext/edges.csv")?;
+    /// let logger = EdgeLogger::start(Pin::Pc0, BufWriter::new(file), 1)?;
+    /// sleep(Duration::from_secs(10));
+    /// let stats = logger.stop()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn start<W>(pin: Pin, output: W, timer_resolution_us: u32) -> Result<Self, Error>
+    where
+        W: Write + Send + 'static,
+    {
+        Self::start_with_format(pin, output, timer_resolution_us, Csv::new())
+    }
+
+    /// Starts logging the edges of `pin` to `output` in `format`, with timestamps
+    /// rounded down to `timer_resolution_us` microseconds.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidParameter`] if `timer_resolution_us` is zero, or so
+    /// large that the timestamps can't be counted in CPU cycles.
+    pub fn start_with_format<W, F>(
+        pin: Pin,
+        mut output: W,
+        timer_resolution_us: u32,
+        mut format: F,
+    ) -> Result<Self, Error>
+    where
+        W: Write + Send + 'static,
+        F: EdgeFormat + Send + 'static,
+    {
+        let cycles_per_tick = unsafe { sys::furi_hal_cortex_instructions_per_microsecond() }
+            .checked_mul(timer_resolution_us)
+            .filter(|&cycles| cycles > 0)
+            .ok_or(Error::InvalidParameter)?;
+        let raw = pin.as_raw();
+
+        let shared = Arc::new(Shared {
+            edges: unsafe {
+                NonNull::new_unchecked(sys::furi_stream_buffer_alloc(
+                    BUFFER_LEN * RECORD_SIZE,
+                    RECORD_SIZE,
+                ))
+            },
+            pin: raw,
+            dropped: AtomicU32::new(0),
+            stop: AtomicBool::new(false),
+            report: Mutex::new(None),
+        });
+
+        unsafe {
+            sys::furi_hal_gpio_init(
+                raw,
+                sys::GpioMode_GpioModeInterruptRiseFall,
+                sys::GpioPull_GpioPullNo,
+                sys::GpioSpeed_GpioSpeedVeryHigh,
+            )
+        };
+        let start = cycle_counter();
+        let initial = unsafe { sys::furi_hal_gpio_read(raw) };
+        unsafe {
+            sys::furi_hal_gpio_add_int_callback(
+                raw,
+                Some(edge_isr),
+                Arc::as_ptr(&shared) as *mut c_void,
+            )
+        };
+
+        let writer_shared = shared.clone();
+        let writer = thread::Builder::new()
+            .stack_size(WRITER_STACK_SIZE)
+            .spawn(move || {
+                let clock = Clock::new(start);
+                let result = write_edges(
+                    &writer_shared,
+                    &mut output,
+                    &mut format,
+                    clock,
+                    cycles_per_tick,
+                    timer_resolution_us,
+                    initial,
+                );
+                *writer_shared.report.lock() = Some(result);
+                0
+            });
+
+        Ok(EdgeLogger {
+            shared,
+            writer: Some(writer),
+        })
+    }
+
+    /// Returns the number of edges dropped so far because the buffer was full.
+    pub fn dropped(&self) -> u32 {
+        self.shared.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Stops logging, writes out the remaining edges and flushes the output.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error from writing the output, after which the remaining
+    /// edges were discarded.
+    pub fn stop(mut self) -> Result<EdgeStats, Error> {
+        self.finish()
+    }
+
+    fn finish(&mut self) -> Result<EdgeStats, Error> {
+        let Some(writer) = self.writer.take() else {
+            return Err(Error::InvalidParameter);
+        };
+
+        let pin = self.shared.pin;
+        unsafe {
+            sys::furi_hal_gpio_remove_int_callback(pin);
+            sys::furi_hal_gpio_init_simple(pin, sys::GpioMode_GpioModeAnalog);
+        }
+        self.shared.stop.store(true, Ordering::Release);
+        writer.join();
+
+        self.shared
+            .report
+            .lock()
+            .take()
+            .unwrap_or(Err(Error::Internal))
+    }
+}
+
+impl Drop for EdgeLogger {
+    fn drop(&mut self) {
+        if self.writer.is_some() {
+            let _ = self.finish();
+        }
+    }
+}
+
+/// Writes out buffered edges until the logger is stopped and the buffer is empty.
+fn write_edges<W: Write, F: EdgeFormat>(
+    shared: &Shared,
+    out: &mut W,
+    format: &mut F,
+    mut clock: Clock,
+    cycles_per_tick: u32,
+    resolution_us: u32,
+    initial: bool,
+) -> Result<EdgeStats, Error> {
+    let mut result = format
+        .begin(out, resolution_us)
+        .and_then(|()| format.edge(out, 0, initial));
+
+    let mut records = [0u32; 64];
+    let mut edges = 0;
+    loop {
+        // Check before draining, so no edge buffered before stopping is missed.
+        let stopping = shared.stop.load(Ordering::Acquire);
+        let received = unsafe {
+            sys::furi_stream_buffer_receive(
+                shared.edges.as_ptr(),
+                records.as_mut_ptr() as *mut c_void,
+                records.len() * RECORD_SIZE,
+                if stopping {
+                    0
+                } else {
+                    duration_to_ticks(POLL_INTERVAL)
+                },
+            )
+        };
+        // Every received edge was buffered before now.
+        clock.observe(cycle_counter());
+
+        for &record in &records[..received / RECORD_SIZE] {
+            if result.is_err() {
+                break;
+            }
+            let (cycles, level) = unpack(record);
+            let timestamp = clock.elapsed_at(cycles) / u64::from(cycles_per_tick);
+            result = format.edge(out, timestamp, level);
+            edges += 1;
+        }
+
+        if stopping && received == 0 {
+            break;
+        }
+    }
+
+    let stats = EdgeStats {
+        edges,
+        dropped: shared.dropped.load(Ordering::Relaxed),
+        duration: clock.elapsed_at(clock.last) / u64::from(cycles_per_tick),
+    };
+    result?;
+    format.end(out, &stats)?;
+    out.flush()?;
+    Ok(stats)
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::{pack, unpack, Binary, Clock, Csv, EdgeFormat};
+    use crate::io::cursor::Cursor;
+
+    #[test]
+    fn records_keep_level() {
+        assert_eq!(unpack(pack(1001, false)), (1000, false));
+        assert_eq!(unpack(pack(1000, true)), (1000, true));
+        assert_eq!(unpack(pack(u32::MAX, true)), (u32::MAX - 1, true));
+    }
+
+    #[test]
+    fn clock_counts_from_start() {
+        let mut clock = Clock::new(5000);
+        assert_eq!(clock.elapsed_at(5000), 0);
+        clock.observe(8000);
+        assert_eq!(clock.elapsed_at(7000), 2000);
+        assert_eq!(clock.elapsed_at(9000), 4000);
+    }
+
+    #[test]
+    fn clock_handles_wrapping() {
+        let mut clock = Clock::new(u32::MAX - 99);
+        clock.observe(u32::MAX - 9);
+        // Buffered before the counter wrapped, but read afterwards.
+        let before = u32::MAX - 4;
+        clock.observe(50);
+        assert_eq!(clock.elapsed_at(before), 95);
+        assert_eq!(clock.elapsed_at(60), 160);
+
+        // Many wraps, observed often enough.
+        for _ in 0..8 {
+            clock.observe(clock.last.wrapping_add(1 << 30));
+        }
+        assert_eq!(clock.elapsed_at(clock.last), 150 + (1 << 32) * 2);
+    }
+
+    #[test]
+    fn clock_never_goes_negative() {
+        let clock = Clock::new(1000);
+        assert_eq!(clock.elapsed_at(900), 0);
+    }
+
+    #[test]
+    fn csv_writes_microseconds() {
+        let mut out = Cursor::empty(Vec::new());
+        let mut csv = Csv::new();
+        csv.begin(&mut out, 10).unwrap();
+        csv.edge(&mut out, 0, true).unwrap();
+        csv.edge(&mut out, 7, false).unwrap();
+        csv.edge(&mut out, 1_234_567, true).unwrap();
+        assert_eq!(
+            out.data(),
+            b"time_us,level\n0,1\n70,0\n12345670,1\n".as_slice()
+        );
+    }
+
+    #[test]
+    fn csv_writes_large_timestamps() {
+        let mut out = Cursor::empty(Vec::new());
+        let mut csv = Csv::new();
+        csv.begin(&mut Cursor::empty(Vec::new()), 1).unwrap();
+        csv.edge(&mut out, u64::MAX, false).unwrap();
+        assert_eq!(out.data(), b"18446744073709551615,0\n".as_slice());
+    }
+
+    #[test]
+    fn binary_writes_header() {
+        let mut out = Cursor::empty(Vec::new());
+        Binary::new().begin(&mut out, 500).unwrap();
+        assert_eq!(out.data(), b"EDG1\xF4\x01\x00\x00".as_slice());
+    }
+
+    #[test]
+    fn binary_writes_delta_varints() {
+        let mut out = Cursor::empty(Vec::new());
+        let mut binary = Binary::new();
+        binary.begin(&mut Cursor::empty(Vec::new()), 1).unwrap();
+
+        binary.edge(&mut out, 0, true).unwrap();
+        binary.edge(&mut out, 10, false).unwrap();
+        binary.edge(&mut out, 110, true).unwrap();
+        binary.edge(&mut out, 110 + 8192, false).unwrap();
+        assert_eq!(
+            out.data(),
+            [0x01, 0x14, 0xC9, 0x01, 0x80, 0x80, 0x01].as_slice()
+        );
+    }
+}
@@ -63,6 +63,11 @@ impl DeviceAddress {
         // number of places we need to handle the shift.
         Self(addr << 1)
     }
+
+    /// Returns the 7-bit value of the address.
+    pub const fn value(self) -> u8 {
+        self.0 >> 1
+    }
 }
 
 /// A handle to an I2C bus.
@@ -117,6 +122,56 @@ impl Bus {
     }
 }
 
+/// The transfers that device drivers such as [`I2cEeprom`] are built on.
+///
+/// This is implemented by [`Bus`], which acquires the bus for every transfer, and can
+/// be implemented by mocks to test drivers without the device.
+///
+/// [`I2cEeprom`]: crate::devices::I2cEeprom
+pub trait Transport {
+    /// Writes `data` to `device`.
+    fn write(&mut self, device: DeviceAddress, data: &[u8], timeout: Duration)
+        -> Result<(), Error>;
+
+    /// Writes `write` to `device` and then reads from it into `read`, with a repeated
+    /// start in between.
+    fn write_read(
+        &mut self,
+        device: DeviceAddress,
+        write: &[u8],
+        read: &mut [u8],
+        timeout: Duration,
+    ) -> Result<(), Error>;
+
+    /// Returns `true` if `device` acknowledges its address.
+    fn is_device_ready(&mut self, device: DeviceAddress, timeout: Duration) -> bool;
+}
+
+impl Transport for Bus {
+    fn write(
+        &mut self,
+        device: DeviceAddress,
+        data: &[u8],
+        timeout: Duration,
+    ) -> Result<(), Error> {
+        self.acquire().tx(device, data, timeout)
+    }
+
+    fn write_read(
+        &mut self,
+        device: DeviceAddress,
+        write: &[u8],
+        read: &mut [u8],
+        timeout: Duration,
+    ) -> Result<(), Error> {
+        self.acquire().write_read(device, write, read, timeout)
+    }
+
+    fn is_device_ready(&mut self, device: DeviceAddress, timeout: Duration) -> bool {
+        self.acquire().is_device_ready(device, timeout)
+    }
+}
+
 /// A handle to an I2C bus on the Flipper Zero.
 pub struct BusHandle {
     handle: *mut sys::FuriHalI2cBusHandle,
@@ -383,10 +438,13 @@ impl BusHandle {
         self.transaction_impl(device, operations, timeout)
     }
 
-    // This is similar to `trx`, the only difference being that it sends a RESTART condition
-    // between the two transfers instead of STOP + START
-    #[cfg(any(feature = "embedded-hal", feature = "embedded-hal-0"))]
-    fn write_read_impl(
+    /// Writes the data in `write` to `device` and then reads from it into the `read`
+    /// buffer, with a repeated start in between.
+    ///
+    /// This is how most devices expect a register to be read: unlike [`BusHandle::trx`],
+    /// there is no stop condition between the two transfers that could reset the register
+    /// address.
+    pub fn write_read(
         &mut self,
         device: DeviceAddress,
         write: &[u8],
@@ -620,7 +678,7 @@ impl embedded_hal::i2c::I2c for EmbeddedHalBus {
     ) -> Result<(), Self::Error> {
         self.bus
             .acquire()
-            .write_read_impl(DeviceAddress::new(address), write, read, self.timeout)
+            .write_read(DeviceAddress::new(address), write, read, self.timeout)
     }
 }
 
@@ -677,7 +735,7 @@ impl embedded_hal_0::blocking::i2c::WriteRead for EmbeddedHalBus {
     ) -> Result<(), Self::Error> {
         self.bus
             .acquire()
-            .write_read_impl(DeviceAddress::new(address), bytes, buffer, self.timeout)
+            .write_read(DeviceAddress::new(address), bytes, buffer, self.timeout)
     }
 }
 
@@ -719,4 +777,17 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn write_read_matches_register_reads() {
+        let mut bus = Bus::INTERNAL.acquire();
+        let timeout = Duration::from_millis(50);
+        // The enable register of the LED controller.
+        let expected = bus.read_u8(INTERNAL_LED_CONTROLLER, 0x00, timeout).unwrap();
+
+        let mut read = [0];
+        bus.write_read(INTERNAL_LED_CONTROLLER, &[0x00], &mut read, timeout)
+            .unwrap();
+        assert_eq!(read[0], expected);
+    }
 }
@@ -0,0 +1,156 @@
+//! Interrupts on GPIO pins.
+
+use alloc::boxed::Box;
+use core::ffi::c_void;
+use core::sync::atomic::{AtomicU16, Ordering};
+
+use flipperzero_sys as sys;
+
+use super::{Pin, Pull, Speed};
+
+type InterruptCallback = Box<dyn FnMut() + Send>;
+
+/// The interrupt lines in use, as a bit for each line.
+///
+/// Lines 3 and 6 are used by the firmware for the OK and Down buttons.
+static LINES_IN_USE: AtomicU16 = AtomicU16::new(1 << 3 | 1 << 6);
+
+/// The edges of a [`Pin`] that trigger an [`Interrupt`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edge {
+    /// When the level goes from low to high.
+    Rising,
+    /// When the level goes from high to low.
+    Falling,
+    /// Both of the above.
+    Both,
+}
+
+impl Edge {
+    pub fn to_sys(&self) -> sys::GpioMode {
+        match self {
+            Self::Rising => sys::GpioMode_GpioModeInterruptRise,
+            Self::Falling => sys::GpioMode_GpioModeInterruptFall,
+            Self::Both => sys::GpioMode_GpioModeInterruptRiseFall,
+        }
+    }
+}
+
+/// A closure called on edges of a pin.
+///
+/// The closure is called in interrupt context, so it must be quick and must not block,
+/// which rules out allocating, locking mutexes and most of the Furi APIs. It can read
+/// pins and use atomics, or put a message in a
+/// [`MessageQueue`](crate::furi::message_queue::MessageQueue) without waiting.
+///
+/// The pin is configured as an input while the `Interrupt` exists, and left in the
+/// [`Mode::Analog`](super::Mode::Analog) state once it is dropped.
+///
+/// # Examples
+///
+/// ```no_run
+/// use core::sync::atomic::{AtomicU32, Ordering};
+///
+/// use flipperzero::gpio::{Edge, Interrupt, Pin, Pull};
+///
+/// static PRESSES: AtomicU32 = AtomicU32::new(0);
+///
+/// let button = Interrupt::new(Pin::Pc0, Edge::Falling, Pull::Up, || {
+///     PRESSES.fetch_add(1, Ordering::Relaxed);
+/// })
+/// .expect("the interrupt line is free");
+/// ```
+pub struct Interrupt {
+    pin: Pin,
+    // Boxed twice for a thin pointer, see `furi::thread::Builder::spawn`.
+    #[allow(dead_code)]
+    callback: Box<InterruptCallback>,
+}
+
+impl Interrupt {
+    /// Configures `pin` as an input with the given pull resistor, and calls `callback`
+    /// on each of the given edges.
+    ///
+    /// Pins with the same number share an interrupt line. Returns `None` if another
+    /// `Interrupt` uses the line of `pin`, and always for [`Pin::Pa6`], [`Pin::Pb3`] and
+    /// [`Pin::Pc3`], whose lines are used by the firmware for the buttons.
+    pub fn new<F>(pin: Pin, edge: Edge, pull: Pull, callback: F) -> Option<Self>
+    where
+        F: FnMut() + Send + 'static,
+    {
+        unsafe extern "C" fn run_callback(context: *mut c_void) {
+            let callback = unsafe { &mut *(context as *mut InterruptCallback) };
+            callback();
+        }
+
+        let line = 1 << pin.number();
+        if LINES_IN_USE.fetch_or(line, Ordering::Acquire) & line != 0 {
+            return None;
+        }
+
+        let mut callback: Box<InterruptCallback> = Box::new(Box::new(callback));
+        let raw = pin.as_raw();
+        unsafe {
+            sys::furi_hal_gpio_init(raw, edge.to_sys(), pull.to_sys(), Speed::VeryHigh.to_sys());
+            sys::furi_hal_gpio_add_int_callback(
+                raw,
+                Some(run_callback),
+                &mut *callback as *mut InterruptCallback as *mut c_void,
+            );
+        }
+
+        Some(Self { pin, callback })
+    }
+
+    /// Returns the pin the interrupt is on.
+    pub fn pin(&self) -> Pin {
+        self.pin
+    }
+
+    /// Resumes calling the closure after [`Interrupt::disable`].
+    pub fn enable(&mut self) {
+        unsafe { sys::furi_hal_gpio_enable_int_callback(self.pin.as_raw()) };
+    }
+
+    /// Stops calling the closure until [`Interrupt::enable`] is called, without
+    /// releasing the pin.
+    pub fn disable(&mut self) {
+        unsafe { sys::furi_hal_gpio_disable_int_callback(self.pin.as_raw()) };
+    }
+}
+
+impl Drop for Interrupt {
+    fn drop(&mut self) {
+        let raw = self.pin.as_raw();
+        unsafe {
+            sys::furi_hal_gpio_remove_int_callback(raw);
+            sys::furi_hal_gpio_init_simple(raw, sys::GpioMode_GpioModeAnalog);
+        }
+        LINES_IN_USE.fetch_and(!(1 << self.pin.number()), Ordering::Release);
+        // The closure is dropped after this, once it can no longer be called.
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use super::{Edge, Interrupt};
+    use crate::gpio::{Pin, Pull};
+
+    #[test]
+    fn lines_are_used_once() {
+        let interrupt = Interrupt::new(Pin::Pa7, Edge::Both, Pull::None, || {}).unwrap();
+        assert_eq!(interrupt.pin(), Pin::Pa7);
+        assert!(Interrupt::new(Pin::Pa7, Edge::Rising, Pull::None, || {}).is_none());
+
+        drop(interrupt);
+        let interrupt = Interrupt::new(Pin::Pa7, Edge::Rising, Pull::None, || {});
+        assert!(interrupt.is_some());
+    }
+
+    #[test]
+    fn button_lines_are_refused() {
+        for pin in [Pin::Pa6, Pin::Pb3, Pin::Pc3] {
+            assert!(Interrupt::new(pin, Edge::Both, Pull::None, || {}).is_none());
+        }
+    }
+}
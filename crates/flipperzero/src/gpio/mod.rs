@@ -1,3 +1,305 @@
 //! APIs for interacting with the Flipper Zero's GPIO pins.
 
+use core::ptr::addr_of;
+
+use flipperzero_sys as sys;
+
+pub mod adc;
+#[cfg(feature = "alloc")]
+pub(crate) mod edge_logger;
 pub mod i2c;
+#[cfg(feature = "alloc")]
+pub(crate) mod interrupt;
+pub mod pwm;
+pub mod spi;
+
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use self::edge_logger::{Binary, Csv, EdgeFormat, EdgeLogger, EdgeStats};
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use self::interrupt::{Edge, Interrupt};
+
+/// A general-purpose pin of the GPIO header, named after its port and number.
+///
+/// Pins are configured with [`Pin::init`], and left in the [`Mode::Analog`] state by the
+/// firmware while unused. With the `embedded-hal` or `embedded-hal-0` features, a pin
+/// implements the digital input and output traits, so drivers built on them can be
+/// used directly.
+///
+/// # Warning
+///
+/// Outputs drive 3.3V. Don't connect an output to a supply or to another output, or you
+/// risk damaging your Flipper Zero.
+///
+/// # Examples
+///
+/// ```no_run
+/// use flipperzero::gpio::{Mode, Pin, Pull, Speed};
+///
+/// let led = Pin::Pa7;
+/// led.init(Mode::OutputPushPull, Pull::None, Speed::Low);
+/// led.write(true);
+/// led.toggle();
+/// assert!(!led.read());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pin {
+    /// Header pin 2.
+    Pa7,
+    /// Header pin 3.
+    Pa6,
+    /// Header pin 4.
+    Pa4,
+    /// Header pin 5.
+    Pb3,
+    /// Header pin 6.
+    Pb2,
+    /// Header pin 7.
+    Pc3,
+    /// Header pin 15.
+    Pc1,
+    /// Header pin 16.
+    Pc0,
+}
+
+impl Pin {
+    /// Returns the pin for the Furi HAL.
+    pub fn as_raw(self) -> *const sys::GpioPin {
+        match self {
+            Pin::Pa7 => addr_of!(sys::gpio_ext_pa7),
+            Pin::Pa6 => addr_of!(sys::gpio_ext_pa6),
+            Pin::Pa4 => addr_of!(sys::gpio_ext_pa4),
+            Pin::Pb3 => addr_of!(sys::gpio_ext_pb3),
+            Pin::Pb2 => addr_of!(sys::gpio_ext_pb2),
+            Pin::Pc3 => addr_of!(sys::gpio_ext_pc3),
+            Pin::Pc1 => addr_of!(sys::gpio_ext_pc1),
+            Pin::Pc0 => addr_of!(sys::gpio_ext_pc0),
+        }
+    }
+
+    /// Returns the number of the pin within its port, which is also its interrupt line.
+    #[cfg_attr(not(feature = "alloc"), allow(dead_code))]
+    fn number(self) -> u8 {
+        match self {
+            Pin::Pa7 => 7,
+            Pin::Pa6 => 6,
+            Pin::Pa4 => 4,
+            Pin::Pb3 | Pin::Pc3 => 3,
+            Pin::Pb2 => 2,
+            Pin::Pc1 => 1,
+            Pin::Pc0 => 0,
+        }
+    }
+
+    /// Configures the pin.
+    pub fn init(self, mode: Mode, pull: Pull, speed: Speed) {
+        unsafe {
+            sys::furi_hal_gpio_init(self.as_raw(), mode.to_sys(), pull.to_sys(), speed.to_sys())
+        };
+    }
+
+    /// Returns `true` if the level of the pin is high.
+    ///
+    /// For outputs, this is the level the pin is driven to.
+    pub fn read(self) -> bool {
+        unsafe { sys::furi_hal_gpio_read(self.as_raw()) }
+    }
+
+    /// Drives the pin high if `high` is `true`, and low otherwise.
+    ///
+    /// This has no effect unless the pin is configured as an output.
+    pub fn write(self, high: bool) {
+        unsafe { sys::furi_hal_gpio_write(self.as_raw(), high) };
+    }
+
+    /// Drives the pin to the opposite of its current level.
+    pub fn toggle(self) {
+        self.write(!self.read());
+    }
+}
+
+/// How a [`Pin`] is used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// A digital input.
+    Input,
+    /// An output driven both high and low.
+    OutputPushPull,
+    /// An output driven low, and left floating when high so several devices can share
+    /// a line with a pull-up.
+    OutputOpenDrain,
+    /// Disconnected from the digital circuitry, which uses the least power.
+    Analog,
+}
+
+impl Mode {
+    pub fn to_sys(&self) -> sys::GpioMode {
+        match self {
+            Self::Input => sys::GpioMode_GpioModeInput,
+            Self::OutputPushPull => sys::GpioMode_GpioModeOutputPushPull,
+            Self::OutputOpenDrain => sys::GpioMode_GpioModeOutputOpenDrain,
+            Self::Analog => sys::GpioMode_GpioModeAnalog,
+        }
+    }
+}
+
+/// The internal resistor pulling a [`Pin`] to a level when nothing drives it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pull {
+    None,
+    Up,
+    Down,
+}
+
+impl Pull {
+    pub fn to_sys(&self) -> sys::GpioPull {
+        match self {
+            Self::None => sys::GpioPull_GpioPullNo,
+            Self::Up => sys::GpioPull_GpioPullUp,
+            Self::Down => sys::GpioPull_GpioPullDown,
+        }
+    }
+}
+
+/// How fast an output [`Pin`] switches between levels.
+///
+/// Faster edges allow higher frequencies, but cause more ringing and interference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Speed {
+    Low,
+    Medium,
+    High,
+    VeryHigh,
+}
+
+impl Speed {
+    pub fn to_sys(&self) -> sys::GpioSpeed {
+        match self {
+            Self::Low => sys::GpioSpeed_GpioSpeedLow,
+            Self::Medium => sys::GpioSpeed_GpioSpeedMedium,
+            Self::High => sys::GpioSpeed_GpioSpeedHigh,
+            Self::VeryHigh => sys::GpioSpeed_GpioSpeedVeryHigh,
+        }
+    }
+}
+
+// embedded_hal 1.0 implementations
+
+#[cfg(feature = "embedded-hal")]
+impl embedded_hal::digital::ErrorType for Pin {
+    type Error = core::convert::Infallible;
+}
+
+#[cfg(feature = "embedded-hal")]
+impl embedded_hal::digital::InputPin for Pin {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(self.read())
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(!self.read())
+    }
+}
+
+#[cfg(feature = "embedded-hal")]
+impl embedded_hal::digital::OutputPin for Pin {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.write(false);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.write(true);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "embedded-hal")]
+impl embedded_hal::digital::StatefulOutputPin for Pin {
+    fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(self.read())
+    }
+
+    fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(!self.read())
+    }
+
+    fn toggle(&mut self) -> Result<(), Self::Error> {
+        Pin::toggle(*self);
+        Ok(())
+    }
+}
+
+// embedded_hal 0.2 implementations
+
+#[cfg(feature = "embedded-hal-0")]
+impl embedded_hal_0::digital::v2::InputPin for Pin {
+    type Error = core::convert::Infallible;
+
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        Ok(self.read())
+    }
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        Ok(!self.read())
+    }
+}
+
+#[cfg(feature = "embedded-hal-0")]
+impl embedded_hal_0::digital::v2::OutputPin for Pin {
+    type Error = core::convert::Infallible;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.write(false);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.write(true);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "embedded-hal-0")]
+impl embedded_hal_0::digital::v2::StatefulOutputPin for Pin {
+    fn is_set_high(&self) -> Result<bool, Self::Error> {
+        Ok(self.read())
+    }
+
+    fn is_set_low(&self) -> Result<bool, Self::Error> {
+        Ok(!self.read())
+    }
+}
+
+#[cfg(feature = "embedded-hal-0")]
+impl embedded_hal_0::digital::v2::ToggleableOutputPin for Pin {
+    type Error = core::convert::Infallible;
+
+    fn toggle(&mut self) -> Result<(), Self::Error> {
+        Pin::toggle(*self);
+        Ok(())
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use super::Pin;
+
+    #[test]
+    fn pin_numbers_match_the_hal() {
+        for pin in [
+            Pin::Pa7,
+            Pin::Pa6,
+            Pin::Pa4,
+            Pin::Pb3,
+            Pin::Pb2,
+            Pin::Pc3,
+            Pin::Pc1,
+            Pin::Pc0,
+        ] {
+            let raw = unsafe { &*pin.as_raw() };
+            assert_eq!(raw.pin, 1 << pin.number());
+        }
+    }
+}
@@ -0,0 +1,204 @@
+//! PWM outputs of the Flipper Zero.
+
+use flipperzero_sys as sys;
+
+use super::Pin;
+
+/// Highest duty cycle, in percent.
+pub const MAX_DUTY: u8 = 100;
+
+/// A pin of the GPIO header that a hardware timer can generate PWM on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Output {
+    /// Pin 2, driven by TIM1.
+    Pa7,
+    /// Pin 4, driven by LPTIM2.
+    Pa4,
+}
+
+impl Output {
+    /// Returns the pin of the output.
+    pub fn pin(self) -> Pin {
+        match self {
+            Self::Pa7 => Pin::Pa7,
+            Self::Pa4 => Pin::Pa4,
+        }
+    }
+
+    pub fn to_sys(&self) -> sys::FuriHalPwmOutputId {
+        match self {
+            Self::Pa7 => sys::FuriHalPwmOutputId_FuriHalPwmOutputIdTim1PA7,
+            Self::Pa4 => sys::FuriHalPwmOutputId_FuriHalPwmOutputIdLptim2PA4,
+        }
+    }
+}
+
+/// A PWM signal on an [`Output`], which runs until it is dropped.
+///
+/// The pin is configured for the timer while the signal runs, and left in the
+/// [`Mode::Analog`](super::Mode::Analog) state once it is disabled or dropped. With the
+/// `embedded-hal` or `embedded-hal-0` features, this implements the PWM traits.
+///
+/// # Examples
+///
+/// ```no_run
+/// use flipperzero::gpio::pwm::{Output, Pwm};
+///
+/// // Dim an LED on pin 2.
+/// let mut pwm = Pwm::start(Output::Pa7, 1000, 25).expect("the output is free");
+/// pwm.set_duty(75);
+/// ```
+pub struct Pwm {
+    output: Output,
+    frequency: u32,
+    duty: u8,
+    enabled: bool,
+}
+
+impl Pwm {
+    /// Starts generating a signal of `frequency` Hz, which is high for `duty` percent of
+    /// each period.
+    ///
+    /// Returns `None` if the output is already running, as it may be used by the
+    /// firmware or another app.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `frequency` is zero or `duty` is above [`MAX_DUTY`].
+    pub fn start(output: Output, frequency: u32, duty: u8) -> Option<Self> {
+        check_params(frequency, duty);
+        if unsafe { sys::furi_hal_pwm_is_running(output.to_sys()) } {
+            return None;
+        }
+        unsafe { sys::furi_hal_pwm_start(output.to_sys(), frequency, duty) };
+        Some(Self {
+            output,
+            frequency,
+            duty,
+            enabled: true,
+        })
+    }
+
+    /// Returns the output the signal is generated on.
+    pub fn output(&self) -> Output {
+        self.output
+    }
+
+    /// Returns the frequency in Hz.
+    pub fn frequency(&self) -> u32 {
+        self.frequency
+    }
+
+    /// Returns the duty cycle in percent.
+    pub fn duty(&self) -> u8 {
+        self.duty
+    }
+
+    /// Changes the frequency, keeping the duty cycle.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `frequency` is zero.
+    pub fn set_frequency(&mut self, frequency: u32) {
+        self.set_params(frequency, self.duty);
+    }
+
+    /// Changes the duty cycle, keeping the frequency.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `duty` is above [`MAX_DUTY`].
+    pub fn set_duty(&mut self, duty: u8) {
+        self.set_params(self.frequency, duty);
+    }
+
+    /// Changes both the frequency and the duty cycle.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `frequency` is zero or `duty` is above [`MAX_DUTY`].
+    pub fn set_params(&mut self, frequency: u32, duty: u8) {
+        check_params(frequency, duty);
+        self.frequency = frequency;
+        self.duty = duty;
+        if self.enabled {
+            unsafe { sys::furi_hal_pwm_set_params(self.output.to_sys(), frequency, duty) };
+        }
+    }
+
+    /// Stops the signal, leaving the pin in analog mode, until [`Pwm::enable`] is
+    /// called.
+    pub fn disable(&mut self) {
+        if self.enabled {
+            unsafe { sys::furi_hal_pwm_stop(self.output.to_sys()) };
+            self.enabled = false;
+        }
+    }
+
+    /// Resumes the signal after [`Pwm::disable`], with the current frequency and duty
+    /// cycle.
+    pub fn enable(&mut self) {
+        if !self.enabled {
+            unsafe { sys::furi_hal_pwm_start(self.output.to_sys(), self.frequency, self.duty) };
+            self.enabled = true;
+        }
+    }
+}
+
+impl Drop for Pwm {
+    fn drop(&mut self) {
+        self.disable();
+    }
+}
+
+fn check_params(frequency: u32, duty: u8) {
+    assert!(frequency > 0, "PWM frequency must not be zero");
+    assert!(duty <= MAX_DUTY, "PWM duty cycle must be at most 100%");
+}
+
+// embedded_hal 1.0 implementations
+
+#[cfg(feature = "embedded-hal")]
+impl embedded_hal::pwm::ErrorType for Pwm {
+    type Error = core::convert::Infallible;
+}
+
+#[cfg(feature = "embedded-hal")]
+impl embedded_hal::pwm::SetDutyCycle for Pwm {
+    fn max_duty_cycle(&self) -> u16 {
+        MAX_DUTY.into()
+    }
+
+    /// Values above [`MAX_DUTY`] are treated as [`MAX_DUTY`].
+    fn set_duty_cycle(&mut self, duty: u16) -> Result<(), Self::Error> {
+        self.set_duty(duty.min(MAX_DUTY.into()) as u8);
+        Ok(())
+    }
+}
+
+// embedded_hal 0.2 implementations
+
+#[cfg(feature = "embedded-hal-0")]
+impl embedded_hal_0::PwmPin for Pwm {
+    type Duty = u8;
+
+    fn disable(&mut self) {
+        Pwm::disable(self);
+    }
+
+    fn enable(&mut self) {
+        Pwm::enable(self);
+    }
+
+    fn get_duty(&self) -> Self::Duty {
+        self.duty
+    }
+
+    fn get_max_duty(&self) -> Self::Duty {
+        MAX_DUTY
+    }
+
+    fn set_duty(&mut self, duty: Self::Duty) {
+        Pwm::set_duty(self, duty);
+    }
+}
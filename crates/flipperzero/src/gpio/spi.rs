@@ -0,0 +1,312 @@
+//! SPI interface for the Flipper Zero.
+
+use core::marker::PhantomData;
+use core::ptr::addr_of_mut;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use flipperzero_sys as sys;
+
+use crate::furi::time::Duration;
+
+/// Whether the external bus is in use by a [`Bus`].
+static EXTERNAL_IN_USE: AtomicBool = AtomicBool::new(false);
+
+/// The external SPI bus, with a single device on its chip select pin.
+///
+/// - Connect `MISO` to pin `A6`.
+/// - Connect `MOSI` to pin `A7`.
+/// - Connect `SCK` to pin `B3`.
+/// - Connect `CS` to pin `A4`.
+///
+/// The pins are configured while the `Bus` exists, and left floating once it is dropped.
+///
+/// # Warning
+///
+/// Only connect 3.3V peripherals directly to your Flipper Zero, or you risk damaging
+/// it. For SPI devices that operate at different voltages, use a level shifter.
+pub struct Bus {
+    handle: *mut sys::FuriHalSpiBusHandle,
+}
+
+impl Bus {
+    /// Configures the pins of the external SPI bus.
+    ///
+    /// Returns `None` if another `Bus` for the external bus exists.
+    pub fn external() -> Option<Self> {
+        if EXTERNAL_IN_USE.swap(true, Ordering::Acquire) {
+            return None;
+        }
+        // SAFETY: The handle is only used through this `Bus`, and the HAL serializes
+        // access to the underlying bus when it is acquired.
+        let handle = addr_of_mut!(sys::furi_hal_spi_bus_handle_external);
+        unsafe { sys::furi_hal_spi_bus_handle_init(handle) };
+        Some(Self { handle })
+    }
+
+    /// Acquires the bus and selects the device, until the returned handle is dropped.
+    ///
+    /// Blocks indefinitely until the bus is available.
+    pub fn acquire(&mut self) -> BusHandle<'_> {
+        unsafe { sys::furi_hal_spi_acquire(self.handle) };
+        BusHandle {
+            handle: self.handle,
+            _bus: PhantomData,
+        }
+    }
+
+    /// Selects the device, sends `write`, then reads into `read` and deselects the
+    /// device again.
+    ///
+    /// Blocks indefinitely until the bus is available.
+    pub fn transaction(
+        &mut self,
+        write: &[u8],
+        read: &mut [u8],
+        timeout: Duration,
+    ) -> Result<(), Error> {
+        let mut handle = self.acquire();
+        handle.tx(write, timeout)?;
+        handle.rx(read, timeout)
+    }
+}
+
+impl Drop for Bus {
+    fn drop(&mut self) {
+        unsafe { sys::furi_hal_spi_bus_handle_deinit(self.handle) };
+        EXTERNAL_IN_USE.store(false, Ordering::Release);
+    }
+}
+
+/// A handle to the SPI bus, with the device selected.
+///
+/// The device stays selected across calls, so several transfers make up a single
+/// transaction. It is deselected and the bus released when the handle is dropped.
+pub struct BusHandle<'a> {
+    handle: *mut sys::FuriHalSpiBusHandle,
+    _bus: PhantomData<&'a mut Bus>,
+}
+
+impl Drop for BusHandle<'_> {
+    fn drop(&mut self) {
+        unsafe { sys::furi_hal_spi_release(self.handle) };
+    }
+}
+
+impl BusHandle<'_> {
+    /// Sends the data in `write`, discarding the data received meanwhile.
+    pub fn tx(&mut self, write: &[u8], timeout: Duration) -> Result<(), Error> {
+        if write.is_empty() {
+            return Ok(());
+        }
+        unsafe {
+            sys::furi_hal_spi_bus_tx(
+                self.handle,
+                write.as_ptr(),
+                write.len(),
+                timeout.as_millis() as u32,
+            )
+        }
+        .then_some(())
+        .ok_or(Error::TransferFailed)
+    }
+
+    /// Receives data into `read`.
+    ///
+    /// The current contents of `read` are sent meanwhile.
+    pub fn rx(&mut self, read: &mut [u8], timeout: Duration) -> Result<(), Error> {
+        if read.is_empty() {
+            return Ok(());
+        }
+        unsafe {
+            sys::furi_hal_spi_bus_rx(
+                self.handle,
+                read.as_mut_ptr(),
+                read.len(),
+                timeout.as_millis() as u32,
+            )
+        }
+        .then_some(())
+        .ok_or(Error::TransferFailed)
+    }
+
+    /// Sends the data in `write` while receiving into `read`.
+    ///
+    /// If the buffers differ in length, the longer one is sent or received on its own
+    /// once the shorter one is used up.
+    pub fn transfer(
+        &mut self,
+        write: &[u8],
+        read: &mut [u8],
+        timeout: Duration,
+    ) -> Result<(), Error> {
+        let common = write.len().min(read.len());
+        let (read, read_rest) = read.split_at_mut(common);
+        let (write, write_rest) = write.split_at(common);
+        if common > 0 {
+            unsafe {
+                sys::furi_hal_spi_bus_trx(
+                    self.handle,
+                    write.as_ptr(),
+                    read.as_mut_ptr(),
+                    common,
+                    timeout.as_millis() as u32,
+                )
+            }
+            .then_some(())
+            .ok_or(Error::TransferFailed)?;
+        }
+        self.tx(write_rest, timeout)?;
+        self.rx(read_rest, timeout)
+    }
+
+    /// Sends the data in `buffer`, replacing it with the data received meanwhile.
+    pub fn transfer_in_place(&mut self, buffer: &mut [u8], timeout: Duration) -> Result<(), Error> {
+        // Receiving sends the current contents of the buffer.
+        self.rx(buffer, timeout)
+    }
+}
+
+/// The transactions that device drivers such as [`SpiFlash`] are built on.
+///
+/// This is implemented by [`Bus`], and can be implemented by mocks to test drivers
+/// without the device.
+///
+/// [`SpiFlash`]: crate::devices::SpiFlash
+pub trait Transport {
+    /// Selects the device, sends `write`, then reads into `read` and deselects the
+    /// device again.
+    fn transaction(
+        &mut self,
+        write: &[u8],
+        read: &mut [u8],
+        timeout: Duration,
+    ) -> Result<(), Error>;
+}
+
+impl Transport for Bus {
+    fn transaction(
+        &mut self,
+        write: &[u8],
+        read: &mut [u8],
+        timeout: Duration,
+    ) -> Result<(), Error> {
+        Bus::transaction(self, write, read, timeout)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    TransferFailed,
+}
+
+// embedded_hal specific
+
+/// An SPI device implementing the embedded-hal traits, on the chip select pin of a
+/// [`Bus`].
+///
+/// It acquires the bus and selects the device for each transaction. It uses the same
+/// timeout duration for each transfer.
+#[cfg(any(feature = "embedded-hal", feature = "embedded-hal-0"))]
+pub struct EmbeddedHalDevice {
+    bus: Bus,
+    /// The timeout used for each transfer
+    timeout: Duration,
+}
+
+#[cfg(any(feature = "embedded-hal", feature = "embedded-hal-0"))]
+impl EmbeddedHalDevice {
+    pub fn new(bus: Bus, timeout: Duration) -> Self {
+        Self { bus, timeout }
+    }
+
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout
+    }
+
+    /// Returns the bus, to use it without the embedded-hal traits.
+    pub fn into_inner(self) -> Bus {
+        self.bus
+    }
+}
+
+// embedded_hal 1.0 implementations
+
+#[cfg(feature = "embedded-hal")]
+impl embedded_hal::spi::Error for Error {
+    fn kind(&self) -> embedded_hal::spi::ErrorKind {
+        embedded_hal::spi::ErrorKind::Other
+    }
+}
+
+#[cfg(feature = "embedded-hal")]
+impl embedded_hal::spi::ErrorType for EmbeddedHalDevice {
+    type Error = Error;
+}
+
+#[cfg(feature = "embedded-hal")]
+impl embedded_hal::spi::SpiDevice for EmbeddedHalDevice {
+    fn transaction(
+        &mut self,
+        operations: &mut [embedded_hal::spi::Operation<'_, u8>],
+    ) -> Result<(), Self::Error> {
+        use embedded_hal::spi::Operation;
+
+        let timeout = self.timeout;
+        let mut handle = self.bus.acquire();
+        for operation in operations {
+            match operation {
+                Operation::Read(read) => handle.rx(read, timeout)?,
+                Operation::Write(write) => handle.tx(write, timeout)?,
+                Operation::Transfer(read, write) => handle.transfer(write, read, timeout)?,
+                Operation::TransferInPlace(buffer) => handle.transfer_in_place(buffer, timeout)?,
+                Operation::DelayNs(ns) => unsafe { sys::furi_delay_us(ns.div_ceil(1000)) },
+            }
+        }
+        Ok(())
+    }
+}
+
+// embedded_hal 0.2 implementations
+
+#[cfg(feature = "embedded-hal-0")]
+impl embedded_hal_0::blocking::spi::Transfer<u8> for EmbeddedHalDevice {
+    type Error = Error;
+
+    fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Self::Error> {
+        self.bus
+            .acquire()
+            .transfer_in_place(words, self.timeout)
+            .map(|()| &*words)
+    }
+}
+
+#[cfg(feature = "embedded-hal-0")]
+impl embedded_hal_0::blocking::spi::Write<u8> for EmbeddedHalDevice {
+    type Error = Error;
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        self.bus.acquire().tx(words, self.timeout)
+    }
+}
+
+#[cfg(feature = "embedded-hal-0")]
+impl embedded_hal_0::blocking::spi::Transactional<u8> for EmbeddedHalDevice {
+    type Error = Error;
+
+    fn exec(
+        &mut self,
+        operations: &mut [embedded_hal_0::blocking::spi::Operation<'_, u8>],
+    ) -> Result<(), Self::Error> {
+        use embedded_hal_0::blocking::spi::Operation;
+
+        let timeout = self.timeout;
+        let mut handle = self.bus.acquire();
+        for operation in operations {
+            match operation {
+                Operation::Write(write) => handle.tx(write, timeout)?,
+                Operation::Transfer(buffer) => handle.transfer_in_place(buffer, timeout)?,
+            }
+        }
+        Ok(())
+    }
+}
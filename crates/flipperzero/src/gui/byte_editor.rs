@@ -0,0 +1,112 @@
+//! Editing bytes of files.
+
+use alloc::rc::Rc;
+use core::cell::RefCell;
+use core::ffi::CStr;
+use core::fmt::Write as _;
+
+use crate::furi::string::FuriString;
+use crate::gui::byte_input::ByteInput;
+use crate::gui::view_dispatcher::{ViewDispatcher, ViewDispatcherType};
+use crate::io::{Error, Read};
+use crate::storage::{File, OpenOptions};
+
+/// Maximum number of bytes that can be edited at once with [`edit_bytes`], which is the
+/// limit of the firmware's byte input.
+pub const MAX_EDIT_BYTES: usize = u8::MAX as usize;
+
+/// Lets the user edit `len` bytes of the file at `path` starting at `offset`, and
+/// writes them back if the user confirms.
+///
+/// The bytes are shown in the firmware's byte input. If the user confirms the edit by
+/// pressing Save, the bytes are written back in place and synced to the storage,
+/// leaving the rest of the file untouched. Pressing Back discards the edit.
+///
+/// Returns whether the file was changed, which is not the case if the edit was
+/// discarded or the bytes were left as they were.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidParameter`] if `len` is zero or larger than
+/// [`MAX_EDIT_BYTES`], or if the file ends before `offset + len`. Returns any error
+/// that occurs while reading or writing the file.
+pub fn edit_bytes(path: &CStr, offset: u64, len: usize) -> Result<bool, Error> {
+    if len == 0 || len > MAX_EDIT_BYTES {
+        return Err(Error::InvalidParameter);
+    }
+
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open_existing(true)
+        .open(path)?;
+    let mut original = [0; MAX_EDIT_BYTES];
+    read_exact_at(&mut file, &mut original[..len], offset)?;
+
+    let mut header = FuriString::new();
+    let _ = write!(header, "Edit at 0x{:X}", offset);
+    let mut edited = original;
+    if !input_bytes(header.as_c_str(), &mut edited[..len]) || edited == original {
+        return Ok(false);
+    }
+
+    let mut written = 0;
+    while written < len {
+        match file.write_at(&edited[written..len], offset + written as u64)? {
+            0 => return Err(Error::WriteZero),
+            n => written += n,
+        }
+    }
+    file.sync()?;
+
+    Ok(true)
+}
+
+/// Shows the byte input for editing `bytes` with the given header, returning `true` if
+/// the user confirmed the edit.
+///
+/// `bytes` is only modified if the edit is confirmed.
+///
+/// # Panics
+///
+/// Panics if `bytes` is empty or longer than [`MAX_EDIT_BYTES`].
+pub(crate) fn input_bytes(header: &CStr, bytes: &mut [u8]) -> bool {
+    const VIEW_ID: u32 = 0;
+
+    let mut view_dispatcher = ViewDispatcher::new();
+    let edited = Rc::new(RefCell::new(None));
+    let mut input = ByteInput::new();
+    input.set_header(header);
+    input.set_bytes(bytes);
+    input.on_result({
+        let edited = edited.clone();
+        let confirmed = view_dispatcher.custom_event_sender();
+        move |bytes: &[u8]| {
+            *edited.borrow_mut() = Some(bytes.to_vec());
+            confirmed.send(0);
+        }
+    });
+    view_dispatcher.add_view(VIEW_ID, input);
+    view_dispatcher.on_custom_event(|dispatcher, _| {
+        dispatcher.stop();
+        true
+    });
+    view_dispatcher.attach_to_gui(ViewDispatcherType::Fullscreen);
+    view_dispatcher.switch_to(VIEW_ID);
+    view_dispatcher.run();
+    drop(view_dispatcher);
+
+    match edited.take() {
+        Some(edited) => {
+            bytes.copy_from_slice(&edited);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Fills `buf` with the bytes of `file` starting at `offset`.
+fn read_exact_at(file: &mut File, buf: &mut [u8], offset: u64) -> Result<(), Error> {
+    let filled = file.read_at(buf, offset)?;
+    file.read_exact(&mut buf[filled..])
+}
@@ -0,0 +1,155 @@
+//! Byte inputs.
+
+use alloc::{boxed::Box, ffi::CString, vec::Vec};
+use core::ffi::{c_void, CStr};
+use core::ptr::NonNull;
+
+use flipperzero_sys as sys;
+
+use crate::gui::byte_editor::MAX_EDIT_BYTES;
+use crate::gui::view::AsView;
+
+type BytesCallback = Box<dyn FnMut(&[u8])>;
+
+/// The bytes being edited, and the closures they are passed to.
+struct InputState {
+    bytes: Vec<u8>,
+    result: Option<BytesCallback>,
+    changed: Option<BytesCallback>,
+}
+
+/// The firmware's editor for entering bytes as hex.
+///
+/// The closures are called on the thread running the view dispatcher the byte input
+/// has been added to.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use flipperzero::gui::{ByteInput, ViewDispatcher};
+/// const KEY: u32 = 0;
+///
+/// let mut view_dispatcher = ViewDispatcher::new();
+/// let sender = view_dispatcher.custom_event_sender();
+/// let mut input = ByteInput::new();
+/// input.set_header(c"Enter the key");
+/// input.set_bytes(&[0; 6]);
+/// input.on_result(move |key| {
+///     // Use `key`, then tell the app that it was entered.
+///     sender.send(0);
+/// });
+/// view_dispatcher.add_view(KEY, input);
+/// ```
+pub struct ByteInput {
+    raw: NonNull<sys::ByteInput>,
+    // The firmware keeps pointers to these.
+    header: Option<CString>,
+    state: Box<InputState>,
+}
+
+impl ByteInput {
+    /// Creates a byte input without a header or bytes.
+    pub fn new() -> Self {
+        Self {
+            raw: unsafe { NonNull::new_unchecked(sys::byte_input_alloc()) },
+            header: None,
+            state: Box::new(InputState {
+                bytes: Vec::new(),
+                result: None,
+                changed: None,
+            }),
+        }
+    }
+
+    /// Returns the raw pointer to the byte input, for use with the `sys` bindings.
+    pub fn as_ptr(&self) -> *mut sys::ByteInput {
+        self.raw.as_ptr()
+    }
+
+    /// Sets the header shown above the bytes.
+    pub fn set_header(&mut self, header: &CStr) {
+        let header = self.header.insert(header.into());
+        unsafe { sys::byte_input_set_header_text(self.raw.as_ptr(), header.as_ptr()) };
+    }
+
+    /// Starts editing `bytes`, moving the cursor to the first byte.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes` is empty or longer than [`MAX_EDIT_BYTES`].
+    pub fn set_bytes(&mut self, bytes: &[u8]) {
+        assert!(
+            !bytes.is_empty() && bytes.len() <= MAX_EDIT_BYTES,
+            "invalid number of bytes to edit"
+        );
+
+        unsafe extern "C" fn result(context: *mut c_void) {
+            let state = unsafe { &mut *(context as *mut InputState) };
+            if let Some(callback) = state.result.as_mut() {
+                callback(&state.bytes);
+            }
+        }
+
+        unsafe extern "C" fn changed(context: *mut c_void) {
+            let state = unsafe { &mut *(context as *mut InputState) };
+            if let Some(callback) = state.changed.as_mut() {
+                callback(&state.bytes);
+            }
+        }
+
+        let state = &mut *self.state;
+        state.bytes.clear();
+        state.bytes.extend_from_slice(bytes);
+        unsafe {
+            sys::byte_input_set_result_callback(
+                self.raw.as_ptr(),
+                Some(result),
+                Some(changed),
+                state as *mut InputState as *mut c_void,
+                state.bytes.as_mut_ptr(),
+                bytes.len() as u8,
+            )
+        };
+    }
+
+    /// Returns the bytes as they have been edited so far.
+    pub fn bytes(&self) -> &[u8] {
+        &self.state.bytes
+    }
+
+    /// Sets the closure which is passed the bytes once the user confirms them.
+    pub fn on_result<F>(&mut self, callback: F)
+    where
+        F: FnMut(&[u8]) + 'static,
+    {
+        self.state.result = Some(Box::new(callback));
+    }
+
+    /// Sets the closure which is passed the bytes whenever the user changes one.
+    pub fn on_change<F>(&mut self, callback: F)
+    where
+        F: FnMut(&[u8]) + 'static,
+    {
+        self.state.changed = Some(Box::new(callback));
+    }
+}
+
+unsafe impl AsView for ByteInput {
+    fn as_view_ptr(&self) -> *mut sys::View {
+        unsafe { sys::byte_input_get_view(self.raw.as_ptr()) }
+    }
+}
+
+impl Drop for ByteInput {
+    fn drop(&mut self) {
+        unsafe { sys::byte_input_free(self.raw.as_ptr()) };
+        // The header and the state are dropped after this, once they can no longer be
+        // used.
+    }
+}
+
+impl Default for ByteInput {
+    fn default() -> Self {
+        Self::new()
+    }
+}
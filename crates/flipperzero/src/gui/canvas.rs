@@ -1,7 +1,17 @@
 //! Canvases.
 
+use core::ffi::CStr;
+use core::marker::PhantomData;
+use core::ptr::NonNull;
+
 use flipperzero_sys as sys;
 
+use crate::gui::icon::Icon;
+#[cfg(feature = "alloc")]
+use crate::gui::icon::IconAnimation;
+#[cfg(feature = "alloc")]
+use crate::gui::image::BmImage;
+
 #[derive(Debug, Clone, Copy)]
 pub enum Align {
     Left,
@@ -22,3 +32,528 @@ impl Align {
         }
     }
 }
+
+/// A font of the firmware to draw text with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Font {
+    /// The bold font used for titles.
+    Primary,
+    /// The small font used for most text.
+    Secondary,
+    /// The font of the on-screen keyboard.
+    Keyboard,
+    /// Large digits, such as for clocks. It has no letters.
+    BigNumbers,
+}
+
+impl Font {
+    pub fn to_sys(&self) -> sys::Font {
+        match self {
+            Self::Primary => sys::Font_FontPrimary,
+            Self::Secondary => sys::Font_FontSecondary,
+            Self::Keyboard => sys::Font_FontKeyboard,
+            Self::BigNumbers => sys::Font_FontBigNumbers,
+        }
+    }
+}
+
+/// The color that a [`Canvas`] draws with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    /// Clears pixels.
+    White,
+    /// Sets pixels, which is the default.
+    Black,
+    /// Inverts pixels.
+    Xor,
+}
+
+impl Color {
+    pub fn to_sys(&self) -> sys::Color {
+        match self {
+            Self::White => sys::Color_ColorWhite,
+            Self::Black => sys::Color_ColorBlack,
+            Self::Xor => sys::Color_ColorXOR,
+        }
+    }
+}
+
+/// The rotation of an icon drawn with [`Canvas::draw_icon_rotated`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IconRotation {
+    Rotate0,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+}
+
+impl IconRotation {
+    pub fn to_sys(&self) -> sys::IconRotation {
+        match self {
+            Self::Rotate0 => sys::IconRotation_IconRotation0,
+            Self::Rotate90 => sys::IconRotation_IconRotation90,
+            Self::Rotate180 => sys::IconRotation_IconRotation180,
+            Self::Rotate270 => sys::IconRotation_IconRotation270,
+        }
+    }
+}
+
+/// A canvas to draw on, as passed to the draw callback of a view.
+///
+/// Positions are in pixels from the top left corner of the view port, and may be
+/// negative or off the canvas, in which case only the visible part is drawn.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use flipperzero::gui::canvas::{Align, Font};
+/// # use flipperzero::gui::ViewPort;
+/// let mut view_port = ViewPort::new();
+/// view_port.on_draw(|canvas| {
+///     canvas.clear();
+///     canvas.draw_frame(0, 0, canvas.width(), canvas.height());
+///     canvas.set_font(Font::Primary);
+///     canvas.draw_str_aligned(64, 32, Align::Center, Align::Center, c"Hello, Rust!");
+/// });
+/// ```
+pub struct Canvas<'a> {
+    raw: NonNull<sys::Canvas>,
+    _phantom: PhantomData<&'a mut sys::Canvas>,
+}
+
+impl<'a> Canvas<'a> {
+    /// Wraps a raw canvas pointer.
+    ///
+    /// # Safety
+    ///
+    /// `raw` must be a valid canvas that is not used in any other way for the lifetime
+    /// `'a`, such as the canvas passed to a draw callback for the duration of the call.
+    pub unsafe fn from_raw(raw: *mut sys::Canvas) -> Self {
+        Self {
+            raw: unsafe { NonNull::new_unchecked(raw) },
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the raw pointer to the canvas, for use with the `sys` bindings.
+    pub fn as_ptr(&self) -> *mut sys::Canvas {
+        self.raw.as_ptr()
+    }
+
+    /// Returns the width of the canvas in pixels.
+    pub fn width(&self) -> usize {
+        unsafe { sys::canvas_width(self.raw.as_ptr()) }
+    }
+
+    /// Returns the height of the canvas in pixels.
+    pub fn height(&self) -> usize {
+        unsafe { sys::canvas_height(self.raw.as_ptr()) }
+    }
+
+    /// Clears the canvas, leaving the color and font as they are.
+    pub fn clear(&mut self) {
+        unsafe { sys::canvas_clear(self.raw.as_ptr()) };
+    }
+
+    /// Sets the color that is drawn with.
+    pub fn set_color(&mut self, color: Color) {
+        unsafe { sys::canvas_set_color(self.raw.as_ptr(), color.to_sys()) };
+    }
+
+    /// Swaps drawing in black and white. Drawing in [`Color::Xor`] is left as is.
+    pub fn invert_color(&mut self) {
+        unsafe { sys::canvas_invert_color(self.raw.as_ptr()) };
+    }
+
+    /// Sets the font that text is drawn in.
+    pub fn set_font(&mut self, font: Font) {
+        unsafe { sys::canvas_set_font(self.raw.as_ptr(), font.to_sys()) };
+    }
+
+    /// Returns the height of the current font in pixels.
+    pub fn font_height(&self) -> usize {
+        unsafe { sys::canvas_current_font_height(self.raw.as_ptr()) }
+    }
+
+    /// Returns the width of `text` in the current font in pixels.
+    pub fn string_width(&self, text: &CStr) -> u16 {
+        unsafe { sys::canvas_string_width(self.raw.as_ptr(), text.as_ptr()) }
+    }
+
+    /// Draws `text` in the current font, with `(x, y)` at the left end of its baseline.
+    pub fn draw_str(&mut self, x: i32, y: i32, text: &CStr) {
+        unsafe { sys::canvas_draw_str(self.raw.as_ptr(), x, y, text.as_ptr()) };
+    }
+
+    /// Draws `text` in the current font, aligned to `(x, y)`.
+    ///
+    /// For example, [`Align::Center`] for both alignments centers the text on `(x, y)`,
+    /// and [`Align::Right`] with [`Align::Bottom`] puts its bottom right corner there.
+    pub fn draw_str_aligned(
+        &mut self,
+        x: i32,
+        y: i32,
+        horizontal: Align,
+        vertical: Align,
+        text: &CStr,
+    ) {
+        unsafe {
+            sys::canvas_draw_str_aligned(
+                self.raw.as_ptr(),
+                x,
+                y,
+                horizontal.to_sys(),
+                vertical.to_sys(),
+                text.as_ptr(),
+            )
+        };
+    }
+
+    /// Draws the pixel at `(x, y)`.
+    pub fn draw_dot(&mut self, x: i32, y: i32) {
+        unsafe { sys::canvas_draw_dot(self.raw.as_ptr(), x, y) };
+    }
+
+    /// Draws a line from `(x1, y1)` to `(x2, y2)`, including both ends.
+    pub fn draw_line(&mut self, x1: i32, y1: i32, x2: i32, y2: i32) {
+        unsafe { sys::canvas_draw_line(self.raw.as_ptr(), x1, y1, x2, y2) };
+    }
+
+    /// Draws a filled rectangle with its top left corner at `(x, y)`.
+    pub fn draw_box(&mut self, x: i32, y: i32, width: usize, height: usize) {
+        unsafe { sys::canvas_draw_box(self.raw.as_ptr(), x, y, width, height) };
+    }
+
+    /// Draws the outline of a rectangle with its top left corner at `(x, y)`.
+    pub fn draw_frame(&mut self, x: i32, y: i32, width: usize, height: usize) {
+        unsafe { sys::canvas_draw_frame(self.raw.as_ptr(), x, y, width, height) };
+    }
+
+    /// Draws a filled rectangle with corners rounded by `radius`.
+    pub fn draw_rbox(&mut self, x: i32, y: i32, width: usize, height: usize, radius: usize) {
+        unsafe { sys::canvas_draw_rbox(self.raw.as_ptr(), x, y, width, height, radius) };
+    }
+
+    /// Draws the outline of a rectangle with corners rounded by `radius`.
+    pub fn draw_rframe(&mut self, x: i32, y: i32, width: usize, height: usize, radius: usize) {
+        unsafe { sys::canvas_draw_rframe(self.raw.as_ptr(), x, y, width, height, radius) };
+    }
+
+    /// Draws the outline of a circle centered on `(x, y)`.
+    pub fn draw_circle(&mut self, x: i32, y: i32, radius: usize) {
+        unsafe { sys::canvas_draw_circle(self.raw.as_ptr(), x, y, radius) };
+    }
+
+    /// Draws a filled circle centered on `(x, y)`.
+    pub fn draw_disc(&mut self, x: i32, y: i32, radius: usize) {
+        unsafe { sys::canvas_draw_disc(self.raw.as_ptr(), x, y, radius) };
+    }
+
+    /// Draws an XBM bitmap with its top left corner at `(x, y)`.
+    ///
+    /// Each row of `bitmap` starts on a new byte, with the leftmost pixel of each byte in
+    /// its least significant bit. Only the part of the bitmap that lies on the canvas is
+    /// drawn, so it may be larger than the canvas or partially off-screen.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bitmap` is too short for the given dimensions.
+    pub fn draw_xbm(&mut self, x: i32, y: i32, width: usize, height: usize, bitmap: &[u8]) {
+        let stride = width.div_ceil(8);
+        assert!(bitmap.len() >= stride * height, "bitmap is too short");
+
+        let (Some((skip_x, x, visible_width)), Some((skip_y, y, visible_height))) =
+            (clip(x, width, self.width()), clip(y, height, self.height()))
+        else {
+            return;
+        };
+        let rows = &bitmap[skip_y * stride..(skip_y + visible_height) * stride];
+
+        if visible_width == width {
+            // Rows don't need to be cut, so the bitmap can be drawn at once.
+            unsafe {
+                sys::canvas_draw_xbm(
+                    self.raw.as_ptr(),
+                    x,
+                    y,
+                    width,
+                    visible_height,
+                    rows.as_ptr(),
+                )
+            };
+            return;
+        }
+
+        let mut row_buf = [0; ROW_BUF_LEN];
+        let visible_width = visible_width.min(ROW_BUF_LEN * 8);
+        for (i, row) in rows.chunks_exact(stride).enumerate() {
+            copy_bits(row, skip_x, visible_width, &mut row_buf);
+            unsafe {
+                sys::canvas_draw_xbm(
+                    self.raw.as_ptr(),
+                    x,
+                    y + i as i32,
+                    visible_width,
+                    1,
+                    row_buf.as_ptr(),
+                )
+            };
+        }
+    }
+
+    /// Draws the first frame of `icon` with its top left corner at `(x, y)`.
+    pub fn draw_icon(&mut self, x: i32, y: i32, icon: &Icon) {
+        unsafe { sys::canvas_draw_icon(self.raw.as_ptr(), x, y, icon.as_sys()) };
+    }
+
+    /// Draws the first frame of `icon` rotated, with its top left corner at `(x, y)`.
+    pub fn draw_icon_rotated(&mut self, x: i32, y: i32, icon: &Icon, rotation: IconRotation) {
+        unsafe {
+            sys::canvas_draw_icon_ex(self.raw.as_ptr(), x, y, icon.as_sys(), rotation.to_sys())
+        };
+    }
+
+    /// Draws the current frame of `animation` with its top left corner at `(x, y)`.
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+    pub fn draw_icon_animation(&mut self, x: i32, y: i32, animation: &IconAnimation) {
+        unsafe { sys::canvas_draw_icon_animation(self.raw.as_ptr(), x, y, animation.as_ptr()) };
+    }
+
+    /// Draws the prompt of a button at the bottom left corner, as in stock apps.
+    pub fn draw_button_left(&mut self, label: &CStr) {
+        unsafe { sys::elements_button_left(self.raw.as_ptr(), label.as_ptr()) };
+    }
+
+    /// Draws the prompt of a button at the bottom right corner.
+    pub fn draw_button_right(&mut self, label: &CStr) {
+        unsafe { sys::elements_button_right(self.raw.as_ptr(), label.as_ptr()) };
+    }
+
+    /// Draws the prompt of the OK button at the bottom center.
+    pub fn draw_button_center(&mut self, label: &CStr) {
+        unsafe { sys::elements_button_center(self.raw.as_ptr(), label.as_ptr()) };
+    }
+
+    /// Draws the prompt of the up button at the top left corner.
+    pub fn draw_button_up(&mut self, label: &CStr) {
+        unsafe { sys::elements_button_up(self.raw.as_ptr(), label.as_ptr()) };
+    }
+
+    /// Draws the prompt of the down button at the top right corner.
+    pub fn draw_button_down(&mut self, label: &CStr) {
+        unsafe { sys::elements_button_down(self.raw.as_ptr(), label.as_ptr()) };
+    }
+
+    /// Draws a progress bar with its top left corner at `(x, y)`, filled to `progress`
+    /// between 0 and 1.
+    ///
+    /// The bar is 9 pixels high. `progress` is clamped to its range, as the firmware
+    /// crashes on values outside of it.
+    pub fn draw_progress_bar(&mut self, x: i32, y: i32, width: usize, progress: f32) {
+        let progress = clamp_progress(progress);
+        unsafe { sys::elements_progress_bar(self.raw.as_ptr(), x, y, width, progress) };
+    }
+
+    /// Draws a progress bar like [`Canvas::draw_progress_bar`], with `text` in it
+    /// instead of the percentage.
+    pub fn draw_progress_bar_with_text(
+        &mut self,
+        x: i32,
+        y: i32,
+        width: usize,
+        progress: f32,
+        text: &CStr,
+    ) {
+        let progress = clamp_progress(progress);
+        unsafe {
+            sys::elements_progress_bar_with_text(
+                self.raw.as_ptr(),
+                x,
+                y,
+                width,
+                progress,
+                text.as_ptr(),
+            )
+        };
+    }
+
+    /// Draws a scrollbar along the right edge of the canvas, for item `pos` of `total`.
+    pub fn draw_scrollbar(&mut self, pos: usize, total: usize) {
+        unsafe { sys::elements_scrollbar(self.raw.as_ptr(), pos, total) };
+    }
+
+    /// Draws a scrollbar of `height` with its top right corner at `(x, y)`, for item
+    /// `pos` of `total`.
+    pub fn draw_scrollbar_at(&mut self, x: i32, y: i32, height: usize, pos: usize, total: usize) {
+        unsafe { sys::elements_scrollbar_pos(self.raw.as_ptr(), x, y, height, pos, total) };
+    }
+
+    /// Draws `text` in the current font, starting a line at each `\n`, with `(x, y)` at
+    /// the left end of the baseline of the first line.
+    pub fn draw_multiline_text(&mut self, x: i32, y: i32, text: &CStr) {
+        unsafe { sys::elements_multiline_text(self.raw.as_ptr(), x, y, text.as_ptr()) };
+    }
+
+    /// Draws `text` like [`Canvas::draw_multiline_text`], in a box with a white
+    /// background.
+    pub fn draw_multiline_text_framed(&mut self, x: i32, y: i32, text: &CStr) {
+        unsafe { sys::elements_multiline_text_framed(self.raw.as_ptr(), x, y, text.as_ptr()) };
+    }
+
+    /// Draws `text` like [`Canvas::draw_multiline_text`], with the block of lines aligned
+    /// to `(x, y)` as in [`Canvas::draw_str_aligned`].
+    pub fn draw_multiline_text_aligned(
+        &mut self,
+        x: i32,
+        y: i32,
+        horizontal: Align,
+        vertical: Align,
+        text: &CStr,
+    ) {
+        unsafe {
+            sys::elements_multiline_text_aligned(
+                self.raw.as_ptr(),
+                x,
+                y,
+                horizontal.to_sys(),
+                vertical.to_sys(),
+                text.as_ptr(),
+            )
+        };
+    }
+
+    /// Draws `text` wrapped at word boundaries to fit the rectangle with its top left
+    /// corner at `(x, y)`, aligned within it.
+    ///
+    /// Text that doesn't fit is cut off, ending with `...` if `ellipsis` is `true`. The
+    /// text may use the formatting of the firmware's text boxes, such as `\e#` to start
+    /// a bold line.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_text_box(
+        &mut self,
+        x: i32,
+        y: i32,
+        width: usize,
+        height: usize,
+        horizontal: Align,
+        vertical: Align,
+        text: &CStr,
+        ellipsis: bool,
+    ) {
+        unsafe {
+            sys::elements_text_box(
+                self.raw.as_ptr(),
+                x,
+                y,
+                width,
+                height,
+                horizontal.to_sys(),
+                vertical.to_sys(),
+                text.as_ptr(),
+                ellipsis,
+            )
+        };
+    }
+}
+
+/// Draws `image` with its top left corner at `(x, y)`.
+///
+/// Only the part of the image that lies on the canvas is drawn, so the image may be
+/// larger than the screen or partially off-screen.
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub fn draw_loaded_image(canvas: &mut Canvas, x: i32, y: i32, image: &BmImage) {
+    canvas.draw_xbm(x, y, image.width(), image.height(), image.data());
+}
+
+/// Clamps `progress` to the range accepted by the firmware, mapping NaN to 0.
+fn clamp_progress(progress: f32) -> f32 {
+    if progress > 0.0 {
+        progress.min(1.0)
+    } else {
+        0.0
+    }
+}
+
+/// Length of the buffer used for a visible row of a bitmap, which is sufficient for the
+/// width of the screen in either orientation.
+const ROW_BUF_LEN: usize = 32;
+
+/// Clips the range `pos..pos + len` to `0..bound`.
+///
+/// Returns the number of skipped pixels, the clipped position and the visible length, or
+/// `None` if nothing is visible.
+fn clip(pos: i32, len: usize, bound: usize) -> Option<(usize, i32, usize)> {
+    let start = i64::from(pos).max(0);
+    let end = (i64::from(pos) + len as i64).min(bound as i64);
+    (start < end).then(|| {
+        (
+            (start - i64::from(pos)) as usize,
+            start as i32,
+            (end - start) as usize,
+        )
+    })
+}
+
+/// Copies `len` bits of the XBM row `src`, starting at bit `start`, to the start of `dst`.
+fn copy_bits(src: &[u8], start: usize, len: usize, dst: &mut [u8]) {
+    dst[..len.div_ceil(8)].fill(0);
+    for i in 0..len {
+        let bit = (src[(start + i) / 8] >> ((start + i) % 8)) & 1;
+        dst[i / 8] |= bit << (i % 8);
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use flipperzero_sys as sys;
+
+    use super::{clamp_progress, clip, copy_bits, Color, Font};
+
+    #[test]
+    fn fonts_and_colors_map_to_sys() {
+        assert_eq!(Font::Primary.to_sys(), sys::Font_FontPrimary);
+        assert_eq!(Font::BigNumbers.to_sys(), sys::Font_FontBigNumbers);
+        assert_eq!(Color::White.to_sys(), sys::Color_ColorWhite);
+        assert_eq!(Color::Xor.to_sys(), sys::Color_ColorXOR);
+    }
+
+    #[test]
+    fn progress_is_clamped() {
+        assert_eq!(clamp_progress(0.5), 0.5);
+        assert_eq!(clamp_progress(1.5), 1.0);
+        assert_eq!(clamp_progress(-0.5), 0.0);
+        assert_eq!(clamp_progress(f32::NAN), 0.0);
+    }
+
+    #[test]
+    fn clip_visible() {
+        assert_eq!(clip(0, 10, 128), Some((0, 0, 10)));
+        assert_eq!(clip(120, 10, 128), Some((0, 120, 8)));
+        assert_eq!(clip(-3, 10, 128), Some((3, 0, 7)));
+        assert_eq!(clip(-10, 300, 128), Some((10, 0, 128)));
+    }
+
+    #[test]
+    fn clip_invisible() {
+        assert!(clip(128, 10, 128).is_none());
+        assert!(clip(-10, 10, 128).is_none());
+        assert!(clip(0, 0, 128).is_none());
+    }
+
+    #[test]
+    fn copy_aligned_bits() {
+        let mut dst = [0xff; 2];
+        copy_bits(&[0x12, 0x34, 0x56], 8, 16, &mut dst);
+        assert_eq!(dst, [0x34, 0x56]);
+    }
+
+    #[test]
+    fn copy_unaligned_bits() {
+        let mut dst = [0xff; 2];
+        // Bits 3..14 of 0b1010_1100_1111_0000 (LSB first).
+        copy_bits(&[0xf0, 0xac], 3, 11, &mut dst);
+        assert_eq!(dst, [0b1001_1110, 0b101]);
+    }
+}
@@ -0,0 +1,92 @@
+//! Dialogs reporting errors to the user.
+
+use crate::dialogs::{DialogMessage, DialogsApp};
+use crate::furi::string::FuriString;
+use crate::gui::canvas::Align;
+use crate::io::Error;
+use crate::storage;
+
+/// Maximum number of characters of the context shown by [`show_error`].
+const CONTEXT_MAX_CHARS: usize = 18;
+
+/// Shows a dialog explaining `error` to the user, and blocks until they dismiss it.
+///
+/// `context` describes what the app was doing when the error occurred, such as
+/// "Saving settings", and is shown as the header of the dialog. The error is described
+/// in terms a user can act on, such as the SD card missing or being full.
+///
+/// This can be called from any thread, and doesn't require the app to have a GUI.
+pub fn show_error(error: &Error, context: &str) {
+    let context = storage::display_path(FuriString::from(context).as_c_str(), CONTEXT_MAX_CHARS);
+    let text = FuriString::from(error_message(error));
+
+    let mut dialogs = DialogsApp::open();
+    let mut message = DialogMessage::new();
+    message.set_header(context.as_c_str(), 64, 0, Align::Center, Align::Top);
+    message.set_text(text.as_c_str(), 64, 32, Align::Center, Align::Center);
+    message.set_buttons(None, Some(c"OK"), None);
+
+    dialogs.show_message(&message);
+}
+
+/// Returns the text shown by [`show_error`] for `error`.
+fn error_message(error: &Error) -> &'static str {
+    match error {
+        Error::NotReady => "SD card is missing\nor not ready",
+        Error::Exists => "File already exists",
+        Error::NotExists => "File not found",
+        Error::InvalidParameter => "Invalid request",
+        Error::Denied => "Access denied,\nor SD card is full",
+        Error::InvalidName => "Invalid file name",
+        Error::Internal => "Storage failure,\ntry again",
+        Error::NotImplemented => "Not supported\nby this storage",
+        Error::AlreadyOpen => "File is already\nopen elsewhere",
+        Error::WriteZero => "Could not write,\nSD card may be full",
+        Error::UnexpectedEof => "File ended\nunexpectedly",
+        Error::InvalidData => "File is corrupted",
+        Error::TimedOut => "Timed out,\ntry again",
+        Error::Uncategorized(_) => "Unknown storage error",
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use flipperzero_sys as sys;
+
+    use super::error_message;
+    use crate::io::Error;
+
+    /// Returns every error, with one uncategorized error standing in for all others.
+    fn all_errors() -> impl Iterator<Item = Error> {
+        (1..=sys::FS_Error_FSE_ALREADY_OPEN + 1)
+            .map(|code| Error::try_from_sys(code).unwrap())
+            .chain([
+                Error::WriteZero,
+                Error::UnexpectedEof,
+                Error::InvalidData,
+                Error::TimedOut,
+            ])
+    }
+
+    #[test]
+    fn every_error_has_message() {
+        for code in 1..=sys::FS_Error_FSE_ALREADY_OPEN {
+            assert!(!matches!(
+                Error::try_from_sys(code).unwrap(),
+                Error::Uncategorized(_)
+            ));
+        }
+        for error in all_errors() {
+            assert!(!error_message(&error).is_empty());
+        }
+    }
+
+    #[test]
+    fn messages_fit_on_screen() {
+        for error in all_errors() {
+            let message = error_message(&error);
+            assert!(message.lines().count() <= 2);
+            assert!(message.lines().all(|line| line.len() <= 21));
+        }
+    }
+}
@@ -0,0 +1,426 @@
+//! Paged hex viewer.
+
+use alloc::vec::Vec;
+use core::ffi::c_void;
+use core::fmt::Write as _;
+use core::ptr::NonNull;
+use core::time::Duration;
+
+use flipperzero_sys as sys;
+
+use crate::furi::message_queue::MessageQueue;
+use crate::furi::record::Record;
+use crate::furi::string::FuriString;
+use crate::furi::sync::Mutex;
+use crate::gui::byte_editor::input_bytes;
+use crate::io::{Error, Read, Seek, SeekFrom};
+use crate::storage;
+
+/// Number of bytes shown on a row.
+const ROW_SIZE: usize = 8;
+/// Number of rows visible at once.
+const ROWS: usize = 4;
+const PAGE_SIZE: usize = ROW_SIZE * ROWS;
+/// Number of bytes read from the reader at a time.
+///
+/// The cache starts at a page boundary and spans two pages, so that scrolling by rows
+/// within a page doesn't require reading again.
+const CACHE_SIZE: usize = PAGE_SIZE * 2;
+
+/// Maximum number of characters of the title, leaving room for the offset.
+const TITLE_MAX_CHARS: usize = 10;
+
+const TITLE_HEIGHT: i32 = 14;
+const ROW_HEIGHT: i32 = 12;
+const COLUMN_WIDTH: i32 = 16;
+
+/// A fullscreen, read-only viewer showing the bytes of a file in hexadecimal.
+///
+/// Only the visible bytes (and the rest of the page they start on) are kept in memory,
+/// so files of any size can be viewed.
+///
+/// Up and Down scroll by a row, Left and Right by a page, and Back closes the viewer.
+/// Holding OK asks for an offset to jump to.
+pub struct HexViewer;
+
+impl HexViewer {
+    /// Shows the bytes read from `reader` with the given title, until the user presses
+    /// Back.
+    ///
+    /// The length of `reader` is checked again after every key press, so that the
+    /// viewer stays within bounds if the file is truncated while it is shown.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading from or seeking in `reader` fails.
+    pub fn show<R: Read + Seek>(reader: R, title: &str) -> Result<(), Error> {
+        let mut pager = Pager::new(reader)?;
+
+        let title = FuriString::from(title);
+        let model = Mutex::new(Model {
+            title: storage::display_path(title.as_c_str(), TITLE_MAX_CHARS),
+            offset: FuriString::new(),
+            bytes: [0; PAGE_SIZE],
+            bytes_len: 0,
+            position: 0,
+            len: 0,
+        });
+        pager.layout(&model)?;
+
+        let queue = MessageQueue::<sys::InputEvent>::new(8);
+        let view_port = unsafe { NonNull::new_unchecked(sys::view_port_alloc()) };
+        let gui = Record::<sys::Gui>::open();
+        unsafe {
+            sys::view_port_draw_callback_set(
+                view_port.as_ptr(),
+                Some(draw_callback),
+                &model as *const _ as *mut c_void,
+            );
+            sys::view_port_input_callback_set(
+                view_port.as_ptr(),
+                Some(input_callback),
+                &queue as *const _ as *mut c_void,
+            );
+            sys::gui_add_view_port(
+                gui.as_ptr(),
+                view_port.as_ptr(),
+                sys::GuiLayer_GuiLayerFullscreen,
+            );
+        }
+
+        let result = (|| loop {
+            let Ok(event) = queue.get(Duration::MAX) else {
+                continue;
+            };
+
+            match (event.key, event.type_) {
+                (sys::InputKey_InputKeyOk, sys::InputType_InputTypeLong) => {
+                    // Hide the viewer while the byte input is shown on top of it.
+                    unsafe { sys::view_port_enabled_set(view_port.as_ptr(), false) };
+                    let mut offset = (pager.top as u32).to_be_bytes();
+                    if input_bytes(c"Jump to offset", &mut offset) {
+                        pager.jump(u32::from_be_bytes(offset) as usize);
+                    }
+                    unsafe { sys::view_port_enabled_set(view_port.as_ptr(), true) };
+                }
+                (key, sys::InputType_InputTypeShort | sys::InputType_InputTypeRepeat) => {
+                    match key {
+                        sys::InputKey_InputKeyUp => pager.scroll_up(1),
+                        sys::InputKey_InputKeyDown => pager.scroll_down(1),
+                        sys::InputKey_InputKeyLeft => pager.scroll_up(ROWS),
+                        sys::InputKey_InputKeyRight => pager.scroll_down(ROWS),
+                        sys::InputKey_InputKeyBack => return Ok(()),
+                        _ => continue,
+                    }
+                }
+                _ => continue,
+            }
+            pager.refresh()?;
+            pager.layout(&model)?;
+            unsafe { sys::view_port_update(view_port.as_ptr()) };
+        })();
+
+        unsafe {
+            sys::view_port_enabled_set(view_port.as_ptr(), false);
+            sys::gui_remove_view_port(gui.as_ptr(), view_port.as_ptr());
+            sys::view_port_free(view_port.as_ptr());
+        }
+
+        result
+    }
+}
+
+/// The bytes as displayed by the GUI thread.
+struct Model {
+    title: FuriString,
+    offset: FuriString,
+    bytes: [u8; PAGE_SIZE],
+    bytes_len: usize,
+    position: usize,
+    len: usize,
+}
+
+/// The state of the viewed bytes on the viewer's thread.
+struct Pager<R> {
+    reader: R,
+    len: usize,
+    /// Offset of the first visible row, which is a multiple of [`ROW_SIZE`].
+    top: usize,
+    /// The contents of the reader starting at `cache_start`.
+    cache: Vec<u8>,
+    cache_start: usize,
+}
+
+impl<R: Read + Seek> Pager<R> {
+    fn new(mut reader: R) -> Result<Self, Error> {
+        let len = reader.stream_len()?;
+        Ok(Self {
+            reader,
+            len,
+            top: 0,
+            cache: Vec::with_capacity(CACHE_SIZE),
+            cache_start: 0,
+        })
+    }
+
+    /// Checks the length of the reader again, dropping the cache and clamping the
+    /// visible rows if it changed.
+    fn refresh(&mut self) -> Result<(), Error> {
+        let len = self.reader.stream_len()?;
+        if len != self.len {
+            self.len = len;
+            self.cache.clear();
+            self.top = self.top.min(self.last_top());
+        }
+        Ok(())
+    }
+
+    /// Returns the offset of the first visible row when the last row is at the bottom.
+    fn last_top(&self) -> usize {
+        self.len.div_ceil(ROW_SIZE).saturating_sub(ROWS) * ROW_SIZE
+    }
+
+    fn scroll_down(&mut self, rows: usize) {
+        self.top = (self.top + rows * ROW_SIZE).min(self.last_top());
+    }
+
+    fn scroll_up(&mut self, rows: usize) {
+        self.top = self.top.saturating_sub(rows * ROW_SIZE);
+    }
+
+    /// Scrolls to the row containing `offset`, or as far as possible.
+    fn jump(&mut self, offset: usize) {
+        self.top = (offset - offset % ROW_SIZE).min(self.last_top());
+    }
+
+    /// Returns the visible bytes, reading them from the reader if necessary.
+    fn page(&mut self) -> Result<&[u8], Error> {
+        let end = (self.top + PAGE_SIZE).min(self.len);
+        if self.top < self.cache_start || end > self.cache_start + self.cache.len() {
+            let start = self.top - self.top % PAGE_SIZE;
+            self.cache.resize(CACHE_SIZE, 0);
+            self.reader.seek(SeekFrom::Start(start as u64))?;
+            let mut filled = 0;
+            while filled < CACHE_SIZE {
+                match self.reader.read(&mut self.cache[filled..])? {
+                    0 => break,
+                    n => filled += n,
+                }
+            }
+            self.cache.truncate(filled);
+            self.cache_start = start;
+        }
+
+        // The reader may have become shorter since its length was last checked.
+        let from = (self.top - self.cache_start).min(self.cache.len());
+        let to = (end - self.cache_start).min(self.cache.len());
+        Ok(&self.cache[from..to])
+    }
+
+    /// Hands the visible bytes to the GUI thread.
+    fn layout(&mut self, model: &Mutex<Model>) -> Result<(), Error> {
+        let (top, len) = (self.top, self.len);
+        let page = self.page()?;
+
+        let mut model = model.lock();
+        model.bytes[..page.len()].copy_from_slice(page);
+        model.bytes_len = page.len();
+        model.offset.clear();
+        let _ = write!(model.offset, "0x{:08X}", top);
+        model.position = top;
+        model.len = len;
+        Ok(())
+    }
+}
+
+unsafe extern "C" fn draw_callback(canvas: *mut sys::Canvas, context: *mut c_void) {
+    const HEX_DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+
+    let model = unsafe { &*(context as *const Mutex<Model>) };
+    let model = model.lock();
+
+    unsafe {
+        sys::canvas_set_font(canvas, sys::Font_FontPrimary);
+        sys::canvas_draw_str_aligned(
+            canvas,
+            0,
+            0,
+            sys::Align_AlignLeft,
+            sys::Align_AlignTop,
+            model.title.as_c_ptr(),
+        );
+
+        sys::canvas_set_font(canvas, sys::Font_FontSecondary);
+        sys::canvas_draw_str_aligned(
+            canvas,
+            128,
+            0,
+            sys::Align_AlignRight,
+            sys::Align_AlignTop,
+            model.offset.as_c_ptr(),
+        );
+
+        sys::canvas_set_font(canvas, sys::Font_FontKeyboard);
+        for (i, &byte) in model.bytes[..model.bytes_len].iter().enumerate() {
+            let hex = [
+                HEX_DIGITS[usize::from(byte >> 4)],
+                HEX_DIGITS[usize::from(byte & 0xf)],
+                0,
+            ];
+            sys::canvas_draw_str(
+                canvas,
+                COLUMN_WIDTH * (i % ROW_SIZE) as i32,
+                TITLE_HEIGHT + ROW_HEIGHT * (i / ROW_SIZE + 1) as i32,
+                hex.as_ptr() as *const _,
+            );
+        }
+
+        if model.len > PAGE_SIZE {
+            sys::elements_scrollbar(canvas, model.position, model.len);
+        }
+    }
+}
+
+unsafe extern "C" fn input_callback(event: *mut sys::InputEvent, context: *mut c_void) {
+    let queue = unsafe { &*(context as *const MessageQueue<sys::InputEvent>) };
+    // Dropping events while the viewer is busy is fine.
+    let _ = queue.put(unsafe { *event }, Duration::ZERO);
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use alloc::{rc::Rc, vec::Vec};
+    use core::cell::{Cell, RefCell};
+
+    use super::{Pager, PAGE_SIZE, ROWS, ROW_SIZE};
+    use crate::io::{Error, Read, Seek, SeekFrom};
+
+    /// The contents of a [`Bytes`] reader, which can be changed while it is read, and
+    /// the number of times it was read from.
+    struct Contents {
+        data: RefCell<Vec<u8>>,
+        reads: Cell<usize>,
+    }
+
+    /// An in-memory reader.
+    struct Bytes {
+        contents: Rc<Contents>,
+        position: usize,
+    }
+
+    impl Read for Bytes {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+            let reads = &self.contents.reads;
+            reads.set(reads.get() + 1);
+            let data = self.contents.data.borrow();
+            let rest = data.get(self.position..).unwrap_or_default();
+            let n = rest.len().min(buf.len());
+            buf[..n].copy_from_slice(&rest[..n]);
+            self.position += n;
+            Ok(n)
+        }
+    }
+
+    impl Seek for Bytes {
+        fn seek(&mut self, pos: SeekFrom) -> Result<usize, Error> {
+            let len = self.contents.data.borrow().len() as i64;
+            let position = match pos {
+                SeekFrom::Start(offset) => offset as i64,
+                SeekFrom::End(offset) => len + offset,
+                SeekFrom::Current(offset) => self.position as i64 + offset,
+            };
+            if position < 0 {
+                return Err(Error::InvalidParameter);
+            }
+            self.position = position as usize;
+            Ok(self.position)
+        }
+    }
+
+    fn new_pager(len: usize) -> (Pager<Bytes>, Rc<Contents>) {
+        let contents = Rc::new(Contents {
+            data: RefCell::new((0..len).map(|i| i as u8).collect()),
+            reads: Cell::new(0),
+        });
+        let reader = Bytes {
+            contents: contents.clone(),
+            position: 0,
+        };
+        (Pager::new(reader).unwrap(), contents)
+    }
+
+    #[test]
+    fn scrolls_by_rows_and_pages() {
+        let (mut pager, _) = new_pager(100);
+        pager.scroll_down(1);
+        assert_eq!(pager.top, ROW_SIZE);
+        assert_eq!(pager.page().unwrap()[0], ROW_SIZE as u8);
+        pager.scroll_down(ROWS);
+        assert_eq!(pager.top, ROW_SIZE * (ROWS + 1));
+        pager.scroll_up(ROWS * 2);
+        assert_eq!(pager.top, 0);
+    }
+
+    #[test]
+    fn stops_at_last_row() {
+        // 13 rows, the last of which is partial.
+        let (mut pager, _) = new_pager(100);
+        pager.scroll_down(100);
+        assert_eq!(pager.top, 72);
+        assert_eq!(pager.page().unwrap().len(), 28);
+
+        let (mut pager, _) = new_pager(PAGE_SIZE - 1);
+        pager.scroll_down(1);
+        assert_eq!(pager.top, 0);
+        assert_eq!(pager.page().unwrap().len(), PAGE_SIZE - 1);
+    }
+
+    #[test]
+    fn jumps_to_row() {
+        let (mut pager, _) = new_pager(100);
+        pager.jump(21);
+        assert_eq!(pager.top, 16);
+        assert_eq!(pager.page().unwrap()[0], 16);
+        pager.jump(1000);
+        assert_eq!(pager.top, 72);
+    }
+
+    #[test]
+    fn reads_once_within_page() {
+        let (mut pager, contents) = new_pager(1000);
+        let reads = &contents.reads;
+        pager.page().unwrap();
+        let after_first_page = reads.get();
+        for _ in 1..ROWS {
+            pager.scroll_down(1);
+            pager.page().unwrap();
+        }
+        assert_eq!(reads.get(), after_first_page);
+
+        pager.scroll_down(ROWS);
+        pager.page().unwrap();
+        assert!(reads.get() > after_first_page);
+    }
+
+    #[test]
+    fn clamps_to_new_end() {
+        let (mut pager, contents) = new_pager(1000);
+        pager.jump(900);
+        pager.page().unwrap();
+
+        contents.data.borrow_mut().truncate(50);
+        pager.refresh().unwrap();
+        assert_eq!(pager.top, 24);
+        let page = pager.page().unwrap();
+        assert_eq!(page.len(), 26);
+        assert_eq!(page[0], 24);
+    }
+
+    #[test]
+    fn empty() {
+        let (mut pager, _) = new_pager(0);
+        pager.scroll_down(1);
+        assert_eq!(pager.top, 0);
+        assert!(pager.page().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,264 @@
+//! Icons stored in the app binary, in the format of the firmware's assets.
+//!
+//! An icon has one or more frames, each in the `.icon` format described in
+//! `docs/icons.md`: a header byte, followed by an XBM bitmap that is optionally
+//! compressed. The [`icon!`](crate::icon) macro embeds `.icon` files into the binary.
+
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+#[cfg(feature = "alloc")]
+use core::ffi::c_void;
+#[cfg(feature = "alloc")]
+use core::ptr::NonNull;
+
+use flipperzero_sys as sys;
+
+/// Header byte of an uncompressed frame.
+const UNCOMPRESSED: u8 = 0x00;
+/// Header byte of a heatshrink-compressed frame.
+const COMPRESSED: u8 = 0x01;
+
+/// A frame of an [`Icon`], in the `.icon` format.
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy)]
+pub struct Frame(*const u8);
+
+// SAFETY: frames point to immutable `'static` data.
+unsafe impl Send for Frame {}
+unsafe impl Sync for Frame {}
+
+impl Frame {
+    /// Wraps the contents of a `.icon` file of a `width` x `height` image.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `data` is too short for the image, or doesn't start with one of the
+    /// headers of the format. In a `static`, this fails the build instead.
+    pub const fn new(data: &'static [u8], width: u16, height: u16) -> Self {
+        assert!(!data.is_empty(), "icon frame is empty");
+        match data[0] {
+            UNCOMPRESSED => {
+                let stride = (width as usize).div_ceil(8);
+                assert!(
+                    data.len() > stride * height as usize,
+                    "icon frame is too short for its size"
+                );
+            }
+            COMPRESSED => {
+                assert!(data.len() >= 4, "compressed icon frame has no length");
+                let len = u16::from_le_bytes([data[2], data[3]]) as usize;
+                assert!(
+                    data.len() >= 4 + len,
+                    "compressed icon frame is shorter than its length"
+                );
+            }
+            _ => panic!("icon frame has an invalid header"),
+        }
+        Self(data.as_ptr())
+    }
+}
+
+/// An image of the firmware's `Icon` format, which can have several frames for an
+/// animation.
+///
+/// Icons are usually created with the [`icon!`](crate::icon) macro, and drawn with
+/// [`Canvas::draw_icon`](crate::gui::canvas::Canvas::draw_icon).
+#[repr(transparent)]
+pub struct Icon(sys::Icon);
+
+// SAFETY: icons are immutable, and their frames are `'static`.
+unsafe impl Send for Icon {}
+unsafe impl Sync for Icon {}
+
+impl Icon {
+    /// Creates a `width` x `height` icon, whose animation shows `frame_rate` of `frames`
+    /// per second.
+    ///
+    /// # Safety
+    ///
+    /// Each of `frames` must have been created for the same width and height.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are no frames, or more than 255.
+    pub const unsafe fn new(
+        width: u16,
+        height: u16,
+        frame_rate: u8,
+        frames: &'static [Frame],
+    ) -> Self {
+        assert!(
+            !frames.is_empty() && frames.len() <= u8::MAX as usize,
+            "icons have 1 to 255 frames"
+        );
+        Self(sys::Icon {
+            width,
+            height,
+            frame_count: frames.len() as u8,
+            frame_rate,
+            // `Frame` is a transparent wrapper around the pointer to the frame.
+            frames: frames.as_ptr() as *const *const u8,
+        })
+    }
+
+    /// Returns the raw icon, for use with the `sys` bindings and
+    /// [`dialogs`](crate::dialogs).
+    pub fn as_sys(&self) -> &sys::Icon {
+        &self.0
+    }
+
+    /// Returns the width of the icon in pixels.
+    pub fn width(&self) -> u16 {
+        self.0.width
+    }
+
+    /// Returns the height of the icon in pixels.
+    pub fn height(&self) -> u16 {
+        self.0.height
+    }
+
+    /// Returns the number of frames of the icon.
+    pub fn frame_count(&self) -> u8 {
+        self.0.frame_count
+    }
+}
+
+/// Embeds `.icon` files into the binary, as a `&'static` [`Icon`].
+///
+/// Paths are relative to the file calling the macro, as for `include_bytes!`. To create
+/// `.icon` files from PNG images, see `docs/icons.md`. The size of each file is checked
+/// at compile time.
+///
+/// # Examples
+///
+/// ```ignore
+/// use flipperzero::gui::icon::Icon;
+/// use flipperzero::icon;
+///
+/// static RUSTACEAN: &Icon = icon!(48, 32, "icons/rustacean-48x32.icon");
+/// // An animation with two frames per second.
+/// static BLINK: &Icon = icon!(10, 10, 2, ["icons/open.icon", "icons/closed.icon"]);
+/// ```
+#[macro_export]
+macro_rules! icon {
+    ($width:expr, $height:expr, $path:literal $(,)?) => {
+        $crate::icon!($width, $height, 0, [$path])
+    };
+    ($width:expr, $height:expr, $frame_rate:expr, [$($path:literal),+ $(,)?]) => {{
+        const FRAMES: &[$crate::gui::icon::Frame] = &[
+            $($crate::gui::icon::Frame::new(::core::include_bytes!($path), $width, $height)),+
+        ];
+        // SAFETY: all frames were checked for the size of the icon.
+        static ICON: $crate::gui::icon::Icon =
+            unsafe { $crate::gui::icon::Icon::new($width, $height, $frame_rate, FRAMES) };
+        &ICON
+    }};
+}
+
+#[cfg(feature = "alloc")]
+type UpdateCallback = Box<dyn FnMut() + Send>;
+
+/// An [`Icon`] cycling through its frames, drawn with
+/// [`Canvas::draw_icon_animation`](crate::gui::canvas::Canvas::draw_icon_animation).
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub struct IconAnimation {
+    raw: NonNull<sys::IconAnimation>,
+    // Boxed twice for a thin pointer, see `furi::thread::Builder::spawn`.
+    #[allow(dead_code)]
+    callback: Box<UpdateCallback>,
+}
+
+#[cfg(feature = "alloc")]
+impl IconAnimation {
+    /// Creates a stopped animation of `icon`, showing its first frame.
+    ///
+    /// Once started, `on_frame` is called on the timer service thread whenever the frame
+    /// changes, and would typically request the view showing the animation to be
+    /// redrawn.
+    pub fn new<F>(icon: &'static Icon, on_frame: F) -> Self
+    where
+        F: FnMut() + Send + 'static,
+    {
+        unsafe extern "C" fn run_callback(
+            _instance: *mut sys::IconAnimation,
+            context: *mut c_void,
+        ) {
+            let callback = unsafe { &mut *(context as *mut UpdateCallback) };
+            callback();
+        }
+
+        let mut callback: Box<UpdateCallback> = Box::new(Box::new(on_frame));
+        let raw = unsafe { NonNull::new_unchecked(sys::icon_animation_alloc(icon.as_sys())) };
+        unsafe {
+            sys::icon_animation_set_update_callback(
+                raw.as_ptr(),
+                Some(run_callback),
+                &mut *callback as *mut UpdateCallback as *mut c_void,
+            );
+        }
+        Self { raw, callback }
+    }
+
+    /// Returns the raw pointer to the animation, for use with the `sys` bindings.
+    pub fn as_ptr(&self) -> *mut sys::IconAnimation {
+        self.raw.as_ptr()
+    }
+
+    /// Starts cycling through the frames at the frame rate of the icon.
+    pub fn start(&mut self) {
+        unsafe { sys::icon_animation_start(self.raw.as_ptr()) };
+    }
+
+    /// Stops the animation at the current frame.
+    pub fn stop(&mut self) {
+        unsafe { sys::icon_animation_stop(self.raw.as_ptr()) };
+    }
+
+    /// Returns `true` if the last frame of the icon is shown.
+    pub fn is_last_frame(&self) -> bool {
+        unsafe { sys::icon_animation_is_last_frame(self.raw.as_ptr()) }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Drop for IconAnimation {
+    fn drop(&mut self) {
+        // This stops the animation's timer.
+        unsafe { sys::icon_animation_free(self.raw.as_ptr()) };
+        // The closure is dropped after this, once it can no longer be called.
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use flipperzero_sys as sys;
+
+    use super::{Frame, Icon};
+
+    static TWO_FRAMES: [Frame; 2] = [
+        Frame::new(&[0x00, 0x01, 0x02, 0x03, 0x04], 10, 2),
+        Frame::new(&[0x01, 0x00, 0x01, 0x00, 0xff], 10, 2),
+    ];
+    static ICON: Icon = unsafe { Icon::new(10, 2, 4, &TWO_FRAMES) };
+
+    #[test]
+    fn icons_point_to_their_frames() {
+        let icon = &ICON;
+        assert_eq!(icon.width(), 10);
+        assert_eq!(icon.height(), 2);
+        assert_eq!(icon.frame_count(), 2);
+        unsafe {
+            assert_eq!(sys::icon_get_width(icon.as_sys()), 10);
+            assert_eq!(sys::icon_get_frame_count(icon.as_sys()), 2);
+            assert_eq!(*sys::icon_get_frame_data(icon.as_sys(), 1), 0x01);
+        }
+    }
+
+    #[test]
+    fn icons_embed_files() {
+        let icon: &Icon = crate::icon!(10, 10, "../../examples/icons/rustacean-10x10.icon");
+        assert_eq!(icon.frame_count(), 1);
+        assert_eq!(icon.as_sys().frame_rate, 0);
+    }
+}
@@ -0,0 +1,253 @@
+//! Images loaded from storage.
+
+use alloc::{sync::Arc, vec, vec::Vec};
+use core::ffi::CStr;
+use core::fmt::Write as _;
+use core::ptr;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::time::Duration;
+
+use flipperzero_sys as sys;
+
+use crate::furi::{self, string::FuriString, timer::Timer};
+use crate::gui::canvas::{draw_loaded_image, Canvas};
+use crate::io::{Error, Read, Seek};
+use crate::storage::{OpenOptions, Storage};
+
+/// Header byte of uncompressed `.bm` data.
+const UNCOMPRESSED: u8 = 0x00;
+/// Header byte of heatshrink-compressed `.bm` data.
+const COMPRESSED: u8 = 0x01;
+
+/// A monochrome image in the `.bm` format used for the firmware's assets.
+///
+/// `.bm` files contain an XBM bitmap, optionally compressed, but not the dimensions of
+/// the image; these need to be known in advance.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BmImage {
+    width: usize,
+    height: usize,
+    /// The uncompressed XBM bitmap.
+    data: Vec<u8>,
+}
+
+impl BmImage {
+    /// Loads a `width` x `height` image from the `.bm` file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidParameter`] if the file is not a valid `.bm` image of the
+    /// given size, and any error that occurs while reading it.
+    pub fn load(path: &CStr, width: usize, height: usize) -> Result<Self, Error> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .open_existing(true)
+            .open(path)?;
+
+        let mut data = vec![0; file.stream_len()?];
+        let mut filled = 0;
+        while filled < data.len() {
+            match file.read(&mut data[filled..])? {
+                0 => break,
+                n => filled += n,
+            }
+        }
+        data.truncate(filled);
+
+        Self::from_bytes(&data, width, height)
+    }
+
+    /// Parses a `width` x `height` image from the contents of a `.bm` file.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidParameter`] if `bytes` is not a valid `.bm` image of the
+    /// given size.
+    pub fn from_bytes(bytes: &[u8], width: usize, height: usize) -> Result<Self, Error> {
+        let len = width.div_ceil(8) * height;
+
+        let data = match bytes.split_first() {
+            Some((&UNCOMPRESSED, data)) if data.len() >= len => data[..len].to_vec(),
+            // The header is followed by a reserved byte and the compressed length.
+            Some((&COMPRESSED, _))
+                if bytes.len() >= 4
+                    && bytes.len() >= 4 + u16::from_le_bytes([bytes[2], bytes[3]]) as usize =>
+            {
+                decompress(bytes, len)
+            }
+            _ => return Err(Error::InvalidParameter),
+        };
+
+        Ok(Self {
+            width,
+            height,
+            data,
+        })
+    }
+
+    /// Returns the width of the image in pixels.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the height of the image in pixels.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the uncompressed XBM bitmap of the image.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+/// Decompresses `.bm` data which decompresses to `len` bytes.
+fn decompress(bytes: &[u8], len: usize) -> Vec<u8> {
+    unsafe {
+        let compress = sys::compress_icon_alloc(len);
+        let mut output = ptr::null_mut();
+        sys::compress_icon_decode(compress, bytes.as_ptr(), &mut output);
+        let data = core::slice::from_raw_parts(output, len).to_vec();
+        sys::compress_icon_free(compress);
+        data
+    }
+}
+
+/// An animation cycling through a sequence of images.
+///
+/// This is the equivalent of the firmware's `IconAnimation` for images loaded from
+/// storage.
+pub struct ImageAnimation {
+    frames: Vec<BmImage>,
+    frame: Arc<AtomicUsize>,
+    timer: Option<Timer>,
+}
+
+impl ImageAnimation {
+    /// Creates a stopped animation showing the first of `frames`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `frames` is empty.
+    pub fn new(frames: Vec<BmImage>) -> Self {
+        assert!(!frames.is_empty(), "animation has no frames");
+        Self {
+            frames,
+            frame: Arc::new(AtomicUsize::new(0)),
+            timer: None,
+        }
+    }
+
+    /// Loads the `width` x `height` frames `frame_0.bm`, `frame_1.bm`, ... from `dir`.
+    ///
+    /// This is the naming used by the firmware's animations. Frames are loaded until the
+    /// next one doesn't exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotExists`] if `dir` contains no frames, and any error that
+    /// occurs while loading them.
+    pub fn load_dir(dir: &CStr, width: usize, height: usize) -> Result<Self, Error> {
+        let storage = Storage::open();
+        let mut frames = Vec::new();
+        let mut path = FuriString::new();
+        loop {
+            path.clear();
+            path.push_c_str(dir);
+            let _ = write!(path, "/frame_{}.bm", frames.len());
+            if !storage.exists(path.as_c_str())? {
+                break;
+            }
+            frames.push(BmImage::load(path.as_c_str(), width, height)?);
+        }
+
+        if frames.is_empty() {
+            Err(Error::NotExists)
+        } else {
+            Ok(Self::new(frames))
+        }
+    }
+
+    /// Starts cycling through the frames at `frame_rate` frames per second.
+    ///
+    /// `on_frame` is called on the timer service thread whenever the frame changes, and
+    /// would typically request the view showing the animation to be redrawn.
+    pub fn start<F>(&mut self, frame_rate: u32, mut on_frame: F) -> furi::Result<()>
+    where
+        F: FnMut() + Send + 'static,
+    {
+        let frame = self.frame.clone();
+        let frame_count = self.frames.len();
+        let mut timer = Timer::periodic(move || {
+            let next = (frame.load(Ordering::Relaxed) + 1) % frame_count;
+            frame.store(next, Ordering::Relaxed);
+            on_frame();
+        });
+        timer.start(Duration::from_secs(1) / frame_rate.max(1))?;
+        self.timer = Some(timer);
+        Ok(())
+    }
+
+    /// Stops the animation at the current frame.
+    pub fn stop(&mut self) {
+        self.timer = None;
+    }
+
+    /// Returns the frame currently shown.
+    pub fn current_frame(&self) -> &BmImage {
+        &self.frames[self.frame.load(Ordering::Relaxed)]
+    }
+
+    /// Draws the current frame with its top left corner at `(x, y)`.
+    pub fn draw(&self, canvas: &mut Canvas, x: i32, y: i32) {
+        draw_loaded_image(canvas, x, y, self.current_frame());
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use crate::io::Error;
+
+    use super::BmImage;
+
+    #[test]
+    fn uncompressed() {
+        let image = BmImage::from_bytes(&[0x00, 0x01, 0x02, 0x03, 0x04, 0x05], 10, 2).unwrap();
+        assert_eq!(image.width(), 10);
+        assert_eq!(image.height(), 2);
+        assert_eq!(image.data(), [0x01, 0x02, 0x03, 0x04]);
+    }
+
+    #[test]
+    fn too_short() {
+        assert!(matches!(
+            BmImage::from_bytes(&[0x00, 0x01, 0x02, 0x03], 10, 2),
+            Err(Error::InvalidParameter)
+        ));
+    }
+
+    #[test]
+    fn truncated_compressed() {
+        // The compressed data is shorter than its length of 8 bytes.
+        assert!(matches!(
+            BmImage::from_bytes(&[0x01, 0x00, 0x08, 0x00, 0xaa, 0xbb], 10, 2),
+            Err(Error::InvalidParameter)
+        ));
+        assert!(matches!(
+            BmImage::from_bytes(&[0x01, 0x00, 0x08], 10, 2),
+            Err(Error::InvalidParameter)
+        ));
+    }
+
+    #[test]
+    fn invalid_header() {
+        assert!(matches!(
+            BmImage::from_bytes(&[0x02, 0x01, 0x02, 0x03, 0x04], 10, 2),
+            Err(Error::InvalidParameter)
+        ));
+        assert!(matches!(
+            BmImage::from_bytes(&[], 10, 2),
+            Err(Error::InvalidParameter)
+        ));
+    }
+}
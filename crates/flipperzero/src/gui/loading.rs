@@ -0,0 +1,107 @@
+//! Loading view for blocking operations.
+
+use alloc::sync::Arc;
+use core::ptr::NonNull;
+
+use flipperzero_sys as sys;
+
+use crate::furi::sync::Mutex;
+use crate::furi::thread;
+use crate::gui::view::AsView;
+use crate::gui::view_dispatcher::{ViewDispatcher, ViewDispatcherType};
+
+/// The firmware's loading view, showing an hourglass.
+struct Loading {
+    raw: NonNull<sys::Loading>,
+}
+
+impl Loading {
+    fn new() -> Self {
+        Self {
+            raw: unsafe { NonNull::new_unchecked(sys::loading_alloc()) },
+        }
+    }
+}
+
+unsafe impl AsView for Loading {
+    fn as_view_ptr(&self) -> *mut sys::View {
+        unsafe { sys::loading_get_view(self.raw.as_ptr()) }
+    }
+}
+
+impl Drop for Loading {
+    fn drop(&mut self) {
+        unsafe { sys::loading_free(self.raw.as_ptr()) };
+    }
+}
+
+/// Shows the firmware's loading view while `f` runs on a worker thread, and returns what
+/// `f` returned.
+///
+/// This is meant for operations that block for a noticeable time without reporting
+/// progress, such as scanning a large directory. Use
+/// [`ProgressView`](crate::gui::ProgressView) for operations that can report how far
+/// along they are.
+///
+/// The calling thread runs the loading view until `f` has returned, ignoring all input,
+/// including Back. `f` and its result are moved to the worker thread and back; the
+/// worker only holds a handle to wake up the calling thread, which becomes inert once
+/// the view is gone. The worker is joined before this returns, so by then `f` and
+/// everything it captured have been dropped.
+///
+/// Errors are returned as-is when `f` returns a [`Result`]. A panic in `f` crashes the
+/// app, like any other panic, so there is nothing to propagate in that case.
+pub fn with_loading_view<F, T>(f: F) -> T
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    const VIEW_ID: u32 = 0;
+
+    let mut view_dispatcher = ViewDispatcher::new();
+    view_dispatcher.add_view(VIEW_ID, Loading::new());
+    view_dispatcher.on_custom_event(|dispatcher, _| {
+        dispatcher.stop();
+        true
+    });
+    // Keep Back from stopping the view dispatcher while the worker is still running.
+    view_dispatcher.on_navigation(|_| true);
+    view_dispatcher.attach_to_gui(ViewDispatcherType::Fullscreen);
+    view_dispatcher.switch_to(VIEW_ID);
+
+    let result = Arc::new(Mutex::new(None));
+    let worker = {
+        let result = result.clone();
+        let done = view_dispatcher.custom_event_sender();
+        thread::spawn(move || {
+            *result.lock() = Some(f());
+            // The event is queued even if the view dispatcher isn't running yet.
+            done.send(0);
+            0
+        })
+    };
+
+    view_dispatcher.run();
+    drop(view_dispatcher);
+    worker.join();
+
+    let result = result.lock().take();
+    result.expect("worker stored its result before stopping the view dispatcher")
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use super::with_loading_view;
+    use crate::io::Error;
+
+    #[test]
+    fn returns_value() {
+        assert_eq!(with_loading_view(|| 42), 42);
+    }
+
+    #[test]
+    fn returns_error() {
+        let result: Result<(), Error> = with_loading_view(|| Err(Error::NotExists));
+        assert!(matches!(result, Err(Error::NotExists)));
+    }
+}
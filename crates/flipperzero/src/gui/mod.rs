@@ -1,3 +1,92 @@
 //! GUI service.
 
+#[cfg(feature = "alloc")]
+pub(crate) mod byte_editor;
+#[cfg(feature = "alloc")]
+pub(crate) mod byte_input;
 pub mod canvas;
+pub(crate) mod error_dialog;
+#[cfg(feature = "alloc")]
+pub(crate) mod hex_viewer;
+pub mod icon;
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub mod image;
+#[cfg(feature = "alloc")]
+pub(crate) mod loading;
+#[cfg(feature = "alloc")]
+pub(crate) mod popup;
+#[cfg(feature = "alloc")]
+pub(crate) mod progress;
+#[cfg(feature = "alloc")]
+pub(crate) mod scene_manager;
+#[cfg(feature = "alloc")]
+pub(crate) mod settings_screen;
+#[cfg(feature = "alloc")]
+pub(crate) mod submenu;
+#[cfg(feature = "alloc")]
+pub(crate) mod text_box;
+#[cfg(feature = "alloc")]
+pub(crate) mod text_input;
+#[cfg(feature = "alloc")]
+pub(crate) mod text_viewer;
+#[cfg(feature = "alloc")]
+pub(crate) mod view;
+#[cfg(feature = "alloc")]
+pub(crate) mod view_dispatcher;
+#[cfg(feature = "alloc")]
+pub(crate) mod view_port;
+#[cfg(feature = "alloc")]
+pub(crate) mod widget;
+
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use self::byte_editor::{edit_bytes, MAX_EDIT_BYTES};
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use self::byte_input::ByteInput;
+pub use self::error_dialog::show_error;
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use self::hex_viewer::HexViewer;
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use self::loading::with_loading_view;
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use self::popup::Popup;
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use self::progress::{ProgressHandle, ProgressView};
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use self::scene_manager::{Scene, SceneContext, SceneEvent, SceneManager};
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use self::settings_screen::SettingsScreen;
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use self::submenu::{FromDirError, Submenu, MAX_DIR_ENTRIES};
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use self::text_box::{TextBox, TextBoxFocus, TextBoxFont};
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use self::text_input::TextInput;
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use self::text_viewer::TextViewer;
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use self::view::{AsView, View};
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use self::view_dispatcher::{
+    CustomEventSender, ViewDispatcher, ViewDispatcherRef, ViewDispatcherType,
+};
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use self::view_port::{GuiLayer, ViewPort};
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use self::widget::{GuiButton, Widget};
@@ -0,0 +1,157 @@
+//! Popups.
+
+use alloc::{boxed::Box, ffi::CString};
+use core::ffi::{c_void, CStr};
+use core::ptr::{self, NonNull};
+use core::time::Duration;
+
+use flipperzero_sys as sys;
+
+use crate::furi::sync::Mutex;
+use crate::gui::canvas::Align;
+use crate::gui::view::AsView;
+
+/// The dismiss closure, locked as the timer and a key press may dismiss the popup at
+/// the same time.
+type DismissCallback = Mutex<Box<dyn FnMut() + Send>>;
+
+/// The firmware's view of a message with a header, which is dismissed by a key press or
+/// after a timeout.
+///
+/// The closure is called on the thread running the view dispatcher the popup has been
+/// added to for key presses, and on the timer thread when the timeout expires, which is
+/// why it needs to be [`Send`].
+pub struct Popup {
+    raw: NonNull<sys::Popup>,
+    // The firmware keeps pointers to these.
+    header: Option<CString>,
+    text: Option<CString>,
+    // Boxed to obtain a thin pointer to use as the context.
+    dismiss: Option<Box<DismissCallback>>,
+}
+
+impl Popup {
+    /// Creates an empty popup.
+    pub fn new() -> Self {
+        Self {
+            raw: unsafe { NonNull::new_unchecked(sys::popup_alloc()) },
+            header: None,
+            text: None,
+            dismiss: None,
+        }
+    }
+
+    /// Returns the raw pointer to the popup, for use with the `sys` bindings.
+    pub fn as_ptr(&self) -> *mut sys::Popup {
+        self.raw.as_ptr()
+    }
+
+    /// Sets the header, which is drawn in the primary font aligned to `(x, y)`, and may
+    /// span several lines.
+    pub fn set_header(&mut self, header: &CStr, x: u8, y: u8, horizontal: Align, vertical: Align) {
+        let header = self.header.insert(header.into());
+        unsafe {
+            sys::popup_set_header(
+                self.raw.as_ptr(),
+                header.as_ptr(),
+                x,
+                y,
+                horizontal.to_sys(),
+                vertical.to_sys(),
+            )
+        };
+    }
+
+    /// Sets the text, which is drawn in the secondary font aligned to `(x, y)`, and may
+    /// span several lines.
+    pub fn set_text(&mut self, text: &CStr, x: u8, y: u8, horizontal: Align, vertical: Align) {
+        let text = self.text.insert(text.into());
+        unsafe {
+            sys::popup_set_text(
+                self.raw.as_ptr(),
+                text.as_ptr(),
+                x,
+                y,
+                horizontal.to_sys(),
+                vertical.to_sys(),
+            )
+        };
+    }
+
+    /// Sets the time after which the popup is dismissed once it is shown, if the timeout
+    /// is enabled.
+    ///
+    /// Durations longer than `u32::MAX` milliseconds are cut to that.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        let ms = u32::try_from(timeout.as_millis()).unwrap_or(u32::MAX);
+        unsafe { sys::popup_set_timeout(self.raw.as_ptr(), ms) };
+    }
+
+    /// Enables or disables dismissing the popup after the timeout.
+    pub fn set_timeout_enabled(&mut self, enabled: bool) {
+        if enabled {
+            unsafe { sys::popup_enable_timeout(self.raw.as_ptr()) };
+        } else {
+            unsafe { sys::popup_disable_timeout(self.raw.as_ptr()) };
+        }
+    }
+
+    /// Sets the closure which is called when the popup is dismissed, by a short key
+    /// press or when the timeout expires.
+    ///
+    /// Without a closure, key presses are passed on to the view dispatcher.
+    pub fn on_dismiss<F>(&mut self, callback: F)
+    where
+        F: FnMut() + Send + 'static,
+    {
+        unsafe extern "C" fn dismiss(context: *mut c_void) {
+            let dismiss = unsafe { &*(context as *const DismissCallback) };
+            (*dismiss.lock())();
+        }
+
+        unsafe { sys::popup_set_callback(self.raw.as_ptr(), None) };
+        let callback: &mut DismissCallback = self
+            .dismiss
+            .insert(Box::new(Mutex::new(Box::new(callback))));
+        unsafe {
+            sys::popup_set_context(
+                self.raw.as_ptr(),
+                callback as *mut DismissCallback as *mut c_void,
+            );
+            sys::popup_set_callback(self.raw.as_ptr(), Some(dismiss));
+        }
+    }
+
+    /// Removes the header, the text and the closure, and disables the timeout.
+    pub fn reset(&mut self) {
+        unsafe {
+            sys::popup_reset(self.raw.as_ptr());
+            // The closure is dropped below, so it must no longer be called.
+            sys::popup_set_callback(self.raw.as_ptr(), None);
+            sys::popup_set_context(self.raw.as_ptr(), ptr::null_mut());
+        }
+        self.header = None;
+        self.text = None;
+        self.dismiss = None;
+    }
+}
+
+unsafe impl AsView for Popup {
+    fn as_view_ptr(&self) -> *mut sys::View {
+        unsafe { sys::popup_get_view(self.raw.as_ptr()) }
+    }
+}
+
+impl Drop for Popup {
+    fn drop(&mut self) {
+        unsafe { sys::popup_free(self.raw.as_ptr()) };
+        // Freeing stops the timer. The texts and the closure are dropped after this, once
+        // they can no longer be used.
+    }
+}
+
+impl Default for Popup {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,257 @@
+//! Progress view for long-running operations.
+
+use alloc::{boxed::Box, ffi::CString, sync::Arc};
+use core::ffi::{c_void, CStr};
+use core::fmt::Write;
+use core::ptr::NonNull;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use flipperzero_sys as sys;
+
+use crate::furi::record::Record;
+use crate::furi::string::FuriString;
+use crate::furi::sync::Mutex;
+
+type CancelCallback = Box<dyn FnMut() + Send>;
+
+/// State shared between the [`ProgressView`], its [`ProgressHandle`]s and the GUI thread.
+struct Shared {
+    label: CString,
+    done: AtomicUsize,
+    total: AtomicUsize,
+    cancellable: AtomicBool,
+    cancelled: AtomicBool,
+    on_cancel: Mutex<Option<CancelCallback>>,
+    /// `None` once the view port has been removed from the GUI and freed.
+    view_port: Mutex<Option<NonNull<sys::ViewPort>>>,
+}
+
+// SAFETY: the view port pointer is only used to call `view_port_update`, which is thread
+// safe, and only while holding the lock that `ProgressView::drop` takes before freeing it.
+unsafe impl Send for Shared {}
+unsafe impl Sync for Shared {}
+
+impl Shared {
+    fn set_progress(&self, done: usize, total: usize) {
+        self.done.store(done, Ordering::Relaxed);
+        self.total.store(total, Ordering::Relaxed);
+
+        // Only requests a redraw; the view is drawn on the GUI thread.
+        if let Some(view_port) = *self.view_port.lock() {
+            unsafe { sys::view_port_update(view_port.as_ptr()) };
+        }
+    }
+
+    fn progress(&self) -> f32 {
+        let done = self.done.load(Ordering::Relaxed);
+        let total = self.total.load(Ordering::Relaxed);
+        if total == 0 {
+            0.0
+        } else {
+            (done.min(total) as f32) / (total as f32)
+        }
+    }
+}
+
+/// A fullscreen view showing a label, a progress bar and the number of bytes processed.
+///
+/// The view is shown as soon as it is created and removed from the screen when it is
+/// dropped, so returning early (e.g. with `?`) from a failed operation tears it down.
+///
+/// Progress can be reported from any thread through a [`ProgressHandle`], which stays
+/// valid (but has no effect) after the view has been dropped.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use flipperzero::gui::ProgressView;
+/// let view = ProgressView::new(c"Copying...");
+/// let progress = view.handle();
+/// for done in 0..=100 {
+///     progress.set_progress(done, 100);
+/// }
+/// ```
+pub struct ProgressView {
+    shared: Arc<Shared>,
+    view_port: NonNull<sys::ViewPort>,
+    gui: Record<sys::Gui>,
+}
+
+/// A handle for reporting progress to a [`ProgressView`] from any thread.
+#[derive(Clone)]
+pub struct ProgressHandle {
+    shared: Arc<Shared>,
+}
+
+impl ProgressView {
+    /// Creates a new progress view with the given label and shows it.
+    pub fn new(label: &CStr) -> Self {
+        let shared = Arc::new(Shared {
+            label: label.into(),
+            done: AtomicUsize::new(0),
+            total: AtomicUsize::new(0),
+            cancellable: AtomicBool::new(false),
+            cancelled: AtomicBool::new(false),
+            on_cancel: Mutex::new(None),
+            view_port: Mutex::new(None),
+        });
+
+        let view_port = unsafe { NonNull::new_unchecked(sys::view_port_alloc()) };
+        // The callbacks borrow the shared state, which `self.shared` keeps alive until
+        // the view port has been removed.
+        let context = Arc::as_ptr(&shared).cast_mut().cast::<c_void>();
+        unsafe {
+            sys::view_port_draw_callback_set(view_port.as_ptr(), Some(draw_callback), context);
+            sys::view_port_input_callback_set(view_port.as_ptr(), Some(input_callback), context);
+        }
+        *shared.view_port.lock() = Some(view_port);
+
+        let gui = Record::open();
+        unsafe {
+            sys::gui_add_view_port(
+                gui.as_ptr(),
+                view_port.as_ptr(),
+                sys::GuiLayer_GuiLayerFullscreen,
+            )
+        };
+
+        Self {
+            shared,
+            view_port,
+            gui,
+        }
+    }
+
+    /// Returns a handle for reporting progress, which can be sent to a worker thread.
+    pub fn handle(&self) -> ProgressHandle {
+        ProgressHandle {
+            shared: self.shared.clone(),
+        }
+    }
+
+    /// Sets the number of bytes processed so far, out of `total`.
+    pub fn set_progress(&self, done: usize, total: usize) {
+        self.shared.set_progress(done, total);
+    }
+
+    /// Allows the user to cancel the operation by pressing Back.
+    ///
+    /// `callback` is called on the GUI thread at most once, when Back is first pressed.
+    /// Workers can alternatively poll [`ProgressHandle::is_cancelled`].
+    pub fn on_cancel<F>(&self, callback: F)
+    where
+        F: FnMut() + Send + 'static,
+    {
+        *self.shared.on_cancel.lock() = Some(Box::new(callback));
+        self.shared.cancellable.store(true, Ordering::Release);
+        self.shared.set_progress(
+            self.shared.done.load(Ordering::Relaxed),
+            self.shared.total.load(Ordering::Relaxed),
+        );
+    }
+
+    /// Returns `true` if the user has cancelled the operation.
+    pub fn is_cancelled(&self) -> bool {
+        self.shared.cancelled.load(Ordering::Acquire)
+    }
+}
+
+impl Drop for ProgressView {
+    fn drop(&mut self) {
+        // Prevent any handle from touching the view port after it has been freed.
+        self.shared.view_port.lock().take();
+        unsafe {
+            sys::view_port_enabled_set(self.view_port.as_ptr(), false);
+            sys::gui_remove_view_port(self.gui.as_ptr(), self.view_port.as_ptr());
+            sys::view_port_free(self.view_port.as_ptr());
+        }
+        // Drop the callback now rather than when the last handle goes away, since it
+        // likely refers to the operation that was displayed.
+        self.shared.on_cancel.lock().take();
+    }
+}
+
+impl ProgressHandle {
+    /// Sets the number of bytes processed so far, out of `total`.
+    ///
+    /// This has no effect once the [`ProgressView`] has been dropped.
+    pub fn set_progress(&self, done: usize, total: usize) {
+        self.shared.set_progress(done, total);
+    }
+
+    /// Returns `true` if the user has cancelled the operation.
+    pub fn is_cancelled(&self) -> bool {
+        self.shared.cancelled.load(Ordering::Acquire)
+    }
+}
+
+unsafe extern "C" fn draw_callback(canvas: *mut sys::Canvas, context: *mut c_void) {
+    let shared = unsafe { &*(context as *const Shared) };
+
+    let mut text = FuriString::new();
+    let _ = write!(
+        text,
+        "{} / {} B",
+        shared.done.load(Ordering::Relaxed),
+        shared.total.load(Ordering::Relaxed)
+    );
+
+    unsafe {
+        sys::canvas_set_font(canvas, sys::Font_FontPrimary);
+        sys::canvas_draw_str_aligned(
+            canvas,
+            64,
+            14,
+            sys::Align_AlignCenter,
+            sys::Align_AlignBottom,
+            shared.label.as_ptr(),
+        );
+        sys::elements_progress_bar(canvas, 4, 24, 120, shared.progress());
+        sys::canvas_set_font(canvas, sys::Font_FontSecondary);
+        sys::canvas_draw_str_aligned(
+            canvas,
+            64,
+            44,
+            sys::Align_AlignCenter,
+            sys::Align_AlignBottom,
+            text.as_c_ptr(),
+        );
+        if shared.cancellable.load(Ordering::Acquire) {
+            let hint = if shared.cancelled.load(Ordering::Acquire) {
+                c"Cancelling..."
+            } else {
+                c"Back to cancel"
+            };
+            sys::canvas_draw_str_aligned(
+                canvas,
+                64,
+                63,
+                sys::Align_AlignCenter,
+                sys::Align_AlignBottom,
+                hint.as_ptr(),
+            );
+        }
+    }
+}
+
+unsafe extern "C" fn input_callback(event: *mut sys::InputEvent, context: *mut c_void) {
+    let shared = unsafe { &*(context as *const Shared) };
+    let event = unsafe { &*event };
+
+    if event.key != sys::InputKey_InputKeyBack
+        || event.type_ != sys::InputType_InputTypeShort
+        || !shared.cancellable.load(Ordering::Acquire)
+    {
+        return;
+    }
+
+    if !shared.cancelled.swap(true, Ordering::AcqRel) {
+        if let Some(callback) = shared.on_cancel.lock().as_mut() {
+            callback();
+        }
+        // We're on the GUI thread, so the view port can't be freed concurrently.
+        if let Some(view_port) = *shared.view_port.lock() {
+            unsafe { sys::view_port_update(view_port.as_ptr()) };
+        }
+    }
+}
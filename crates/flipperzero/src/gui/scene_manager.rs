@@ -0,0 +1,523 @@
+//! Scene managers.
+
+use alloc::{boxed::Box, collections::VecDeque, rc::Rc, vec::Vec};
+use core::cell::RefCell;
+
+use crate::gui::view_dispatcher::{ViewDispatcher, ViewDispatcherRef};
+
+/// An event handled by a [`Scene`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SceneEvent {
+    /// A custom event sent to the view dispatcher, such as by a
+    /// [`CustomEventSender`](crate::gui::CustomEventSender).
+    Custom(u32),
+    /// Back was pressed, and the current view didn't handle it.
+    Back,
+}
+
+/// A screen of an app run by a [`SceneManager`], which usually shows one of the views
+/// of the view dispatcher while it is the current scene.
+///
+/// All methods are called on the thread running the view dispatcher, with the state of
+/// the app, `C`, that all scenes share.
+pub trait Scene<C> {
+    /// Called when the scene becomes the current scene, either because it was entered
+    /// or because the scene entered after it was left.
+    fn on_enter(&mut self, scenes: &mut SceneContext<'_, C>);
+
+    /// Handles an event while the scene is the current scene, returning whether it
+    /// handled the event.
+    ///
+    /// Back presses that aren't handled go back to the previous scene. Custom events
+    /// that aren't handled are dropped.
+    fn on_event(&mut self, scenes: &mut SceneContext<'_, C>, event: SceneEvent) -> bool {
+        let _ = (scenes, event);
+        false
+    }
+
+    /// Called when the scene stops being the current scene, either because it was left
+    /// or because another scene was entered after it.
+    fn on_exit(&mut self, scenes: &mut SceneContext<'_, C>) {
+        let _ = scenes;
+    }
+}
+
+/// What is passed to the methods of a [`Scene`], to access the state of the app, the
+/// view dispatcher, and to switch scenes.
+///
+/// Scenes are switched once the current method of the scene returns, in the order in
+/// which they were requested.
+pub struct SceneContext<'a, C> {
+    state: &'a mut C,
+    view_dispatcher: &'a ViewDispatcherRef,
+    stack: &'a [u32],
+    pending: &'a mut VecDeque<Transition>,
+}
+
+impl<C> SceneContext<'_, C> {
+    /// Returns the state of the app.
+    pub fn state(&mut self) -> &mut C {
+        self.state
+    }
+
+    /// Returns the view dispatcher that the scenes are run by.
+    pub fn view_dispatcher(&self) -> &ViewDispatcherRef {
+        self.view_dispatcher
+    }
+
+    /// Returns the ID of the current scene, before any scene switch that is requested.
+    pub fn current_scene(&self) -> Option<u32> {
+        self.stack.last().copied()
+    }
+
+    /// Returns `true` if the scene with the given `id` was entered before the current
+    /// scene, and hasn't been left since.
+    pub fn has_previous_scene(&self, id: u32) -> bool {
+        self.stack
+            .split_last()
+            .is_some_and(|(_, previous)| previous.contains(&id))
+    }
+
+    /// Enters the scene with the given `id`, which then becomes the current scene.
+    ///
+    /// The current scene is exited, but is returned to by [`SceneContext::previous_scene`]
+    /// or by pressing Back.
+    ///
+    /// # Panics
+    ///
+    /// The scene manager panics when entering the scene if no scene is registered under
+    /// `id`.
+    pub fn next_scene(&mut self, id: u32) {
+        self.pending.push_back(Transition::Next(id));
+    }
+
+    /// Leaves the current scene, returning to the scene that was entered before it.
+    ///
+    /// Leaving the first scene stops the view dispatcher.
+    pub fn previous_scene(&mut self) {
+        self.pending.push_back(Transition::Previous);
+    }
+
+    /// Leaves the current scene and the scenes entered after the scene with the given
+    /// `id`, returning to that scene.
+    ///
+    /// Does nothing if the scene isn't a previous scene once it is switched to, as told
+    /// by [`SceneContext::has_previous_scene`].
+    pub fn search_and_switch_to_previous_scene(&mut self, id: u32) {
+        self.pending.push_back(Transition::BackTo(id));
+    }
+}
+
+/// A scene switch requested by a [`SceneContext`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Transition {
+    Next(u32),
+    Previous,
+    BackTo(u32),
+}
+
+/// The scenes and the state shared by them.
+struct Scenes<C> {
+    state: C,
+    scenes: Vec<(u32, Box<dyn Scene<C>>)>,
+    /// The IDs of the entered scenes, with the current scene last.
+    stack: Vec<u32>,
+    pending: VecDeque<Transition>,
+}
+
+impl<C> Scenes<C> {
+    /// Calls `f` with the scene with the given `id`, then switches the scenes it
+    /// requested.
+    fn call<R>(
+        &mut self,
+        view_dispatcher: &ViewDispatcherRef,
+        id: u32,
+        f: impl FnOnce(&mut dyn Scene<C>, &mut SceneContext<'_, C>) -> R,
+    ) -> R {
+        let result = self.call_once(view_dispatcher, id, f);
+        self.switch_pending(view_dispatcher);
+        result
+    }
+
+    /// Switches the scenes that were requested.
+    fn switch_pending(&mut self, view_dispatcher: &ViewDispatcherRef) {
+        // Switching calls scenes, which may request further switches.
+        while let Some(transition) = self.pending.pop_front() {
+            self.switch(view_dispatcher, transition);
+        }
+    }
+
+    fn call_once<R>(
+        &mut self,
+        view_dispatcher: &ViewDispatcherRef,
+        id: u32,
+        f: impl FnOnce(&mut dyn Scene<C>, &mut SceneContext<'_, C>) -> R,
+    ) -> R {
+        let Some((_, scene)) = self.scenes.iter_mut().find(|(scene_id, _)| *scene_id == id) else {
+            panic!("no scene with this ID");
+        };
+        let mut context = SceneContext {
+            state: &mut self.state,
+            view_dispatcher,
+            stack: &self.stack,
+            pending: &mut self.pending,
+        };
+        f(&mut **scene, &mut context)
+    }
+
+    fn switch(&mut self, view_dispatcher: &ViewDispatcherRef, transition: Transition) {
+        match transition {
+            Transition::Next(id) => {
+                if let Some(&current) = self.stack.last() {
+                    self.call_once(view_dispatcher, current, |scene, cx| scene.on_exit(cx));
+                }
+                self.stack.push(id);
+                self.call_once(view_dispatcher, id, |scene, cx| scene.on_enter(cx));
+            }
+            Transition::Previous => {
+                let Some(&current) = self.stack.last() else {
+                    return;
+                };
+                self.call_once(view_dispatcher, current, |scene, cx| scene.on_exit(cx));
+                self.stack.pop();
+                match self.stack.last() {
+                    Some(&previous) => {
+                        self.call_once(view_dispatcher, previous, |scene, cx| scene.on_enter(cx));
+                    }
+                    None => view_dispatcher.stop(),
+                }
+            }
+            Transition::BackTo(id) => {
+                let Some((&current, previous)) = self.stack.split_last() else {
+                    return;
+                };
+                let Some(index) = previous.iter().rposition(|&scene_id| scene_id == id) else {
+                    return;
+                };
+                // Only the current scene is exited, as the others were exited already.
+                self.call_once(view_dispatcher, current, |scene, cx| scene.on_exit(cx));
+                self.stack.truncate(index + 1);
+                self.call_once(view_dispatcher, id, |scene, cx| scene.on_enter(cx));
+            }
+        }
+    }
+
+    /// Passes `event` to the current scene, returning whether it was handled.
+    fn handle(&mut self, view_dispatcher: &ViewDispatcherRef, event: SceneEvent) -> bool {
+        let Some(&current) = self.stack.last() else {
+            return false;
+        };
+        let handled = self.call(view_dispatcher, current, |scene, cx| {
+            scene.on_event(cx, event)
+        });
+        if !handled && event == SceneEvent::Back {
+            self.pending.push_back(Transition::Previous);
+            self.switch_pending(view_dispatcher);
+            return true;
+        }
+        handled
+    }
+}
+
+/// Runs the scenes of an app on a [`ViewDispatcher`], keeping track of which scenes
+/// were entered to return to them when Back is pressed.
+///
+/// Scenes are registered with [`SceneManager::add_scene`] under an ID of the app's
+/// choosing, and share the state of the app, `C`. [`SceneManager::run`] enters the first
+/// scene and runs the view dispatcher, passing its custom events and unhandled Back
+/// presses to the current scene. Pressing Back in the first scene stops the view
+/// dispatcher.
+///
+/// This does what the firmware's `SceneManager` does, which isn't used as it doesn't
+/// tell its handlers which scene they are called for.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use flipperzero::gui::{
+/// #     Scene, SceneContext, SceneEvent, SceneManager, Submenu, ViewDispatcher,
+/// #     ViewDispatcherType,
+/// # };
+/// const MENU: u32 = 0;
+///
+/// struct Menu;
+///
+/// impl Scene<u32> for Menu {
+///     fn on_enter(&mut self, scenes: &mut SceneContext<'_, u32>) {
+///         scenes.view_dispatcher().switch_to(MENU);
+///     }
+///
+///     fn on_event(&mut self, scenes: &mut SceneContext<'_, u32>, event: SceneEvent) -> bool {
+///         match event {
+///             SceneEvent::Custom(item) => {
+///                 *scenes.state() = item;
+///                 scenes.view_dispatcher().stop();
+///                 true
+///             }
+///             SceneEvent::Back => false,
+///         }
+///     }
+/// }
+///
+/// let mut view_dispatcher = ViewDispatcher::new();
+/// let sender = view_dispatcher.custom_event_sender();
+/// let mut menu = Submenu::new();
+/// menu.add_item(c"First", 1, move |item| {
+///     sender.send(item);
+/// });
+/// view_dispatcher.add_view(MENU, menu);
+/// view_dispatcher.attach_to_gui(ViewDispatcherType::Fullscreen);
+///
+/// let mut scenes = SceneManager::new(0);
+/// scenes.add_scene(MENU, Menu);
+/// let selected = scenes.run(&mut view_dispatcher, MENU);
+/// ```
+pub struct SceneManager<C> {
+    scenes: Scenes<C>,
+}
+
+impl<C: 'static> SceneManager<C> {
+    /// Creates a scene manager without scenes, which holds the state of the app.
+    pub fn new(state: C) -> Self {
+        Self {
+            scenes: Scenes {
+                state,
+                scenes: Vec::new(),
+                stack: Vec::new(),
+                pending: VecDeque::new(),
+            },
+        }
+    }
+
+    /// Registers `scene` under `id`, replacing any scene already registered under it.
+    pub fn add_scene<S: Scene<C> + 'static>(&mut self, id: u32, scene: S) {
+        let scenes = &mut self.scenes.scenes;
+        scenes.retain(|(scene_id, _)| *scene_id != id);
+        scenes.push((id, Box::new(scene)));
+    }
+
+    /// Returns the state of the app.
+    pub fn state(&mut self) -> &mut C {
+        &mut self.scenes.state
+    }
+
+    /// Enters the scene with the given `id` and runs `view_dispatcher` until it is
+    /// stopped, then returns the state of the app.
+    ///
+    /// The current scene is exited once the view dispatcher is stopped, if there is one.
+    /// This replaces the custom event and navigation closures of the view dispatcher,
+    /// which are reset afterwards.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no scene is registered under `id`, or under an ID that a scene switches
+    /// to.
+    pub fn run(self, view_dispatcher: &mut ViewDispatcher, first: u32) -> C {
+        let scenes = Rc::new(RefCell::new(self.scenes));
+        view_dispatcher.on_custom_event({
+            let scenes = scenes.clone();
+            move |view_dispatcher, event| {
+                scenes
+                    .borrow_mut()
+                    .handle(view_dispatcher, SceneEvent::Custom(event))
+            }
+        });
+        view_dispatcher.on_navigation({
+            let scenes = scenes.clone();
+            move |view_dispatcher| {
+                scenes
+                    .borrow_mut()
+                    .handle(view_dispatcher, SceneEvent::Back)
+            }
+        });
+
+        {
+            let mut scenes = scenes.borrow_mut();
+            scenes.pending.push_back(Transition::Next(first));
+            scenes.switch_pending(view_dispatcher.get_ref());
+        }
+        view_dispatcher.run();
+
+        // Dropping the closures drops their references to the scenes.
+        view_dispatcher.on_custom_event(|_, _| false);
+        view_dispatcher.on_navigation(|_| false);
+        let mut scenes = Rc::into_inner(scenes)
+            .expect("only the closures referenced the scenes")
+            .into_inner();
+        scenes.pending.clear();
+        if let Some(&current) = scenes.stack.last() {
+            scenes.call_once(view_dispatcher.get_ref(), current, |scene, cx| {
+                scene.on_exit(cx)
+            });
+        }
+        scenes.state
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use alloc::{rc::Rc, vec::Vec};
+    use core::cell::RefCell;
+
+    use super::{Scene, SceneContext, SceneEvent, SceneManager, Transition};
+    use crate::gui::view_dispatcher::ViewDispatcher;
+
+    /// What the scenes were called for, in order.
+    type Log = Rc<RefCell<Vec<(u32, &'static str)>>>;
+
+    /// A scene which logs its calls, and on custom events switches scenes as told by the
+    /// event: `100 + id` enters `id`, `200` goes back and `300 + id` goes back to `id`.
+    struct Recorder {
+        id: u32,
+        log: Log,
+    }
+
+    impl Scene<u32> for Recorder {
+        fn on_enter(&mut self, scenes: &mut SceneContext<'_, u32>) {
+            self.log.borrow_mut().push((self.id, "enter"));
+            *scenes.state() += 1;
+        }
+
+        fn on_event(&mut self, scenes: &mut SceneContext<'_, u32>, event: SceneEvent) -> bool {
+            self.log.borrow_mut().push((self.id, "event"));
+            match event {
+                SceneEvent::Custom(event @ 100..200) => scenes.next_scene(event - 100),
+                SceneEvent::Custom(200) => scenes.previous_scene(),
+                SceneEvent::Custom(event @ 300..400) => {
+                    scenes.search_and_switch_to_previous_scene(event - 300)
+                }
+                SceneEvent::Custom(_) | SceneEvent::Back => return false,
+            }
+            true
+        }
+
+        fn on_exit(&mut self, _scenes: &mut SceneContext<'_, u32>) {
+            self.log.borrow_mut().push((self.id, "exit"));
+        }
+    }
+
+    /// A scene which logs its calls, and goes back when it is returned to.
+    struct Skipped {
+        id: u32,
+        log: Log,
+        entered: bool,
+    }
+
+    impl Scene<u32> for Skipped {
+        fn on_enter(&mut self, scenes: &mut SceneContext<'_, u32>) {
+            self.log.borrow_mut().push((self.id, "enter"));
+            if self.entered {
+                scenes.previous_scene();
+            }
+            self.entered = true;
+        }
+
+        fn on_exit(&mut self, _scenes: &mut SceneContext<'_, u32>) {
+            self.log.borrow_mut().push((self.id, "exit"));
+        }
+    }
+
+    fn manager(log: &Log) -> SceneManager<u32> {
+        let mut manager = SceneManager::new(0);
+        for id in 0..4 {
+            let log = log.clone();
+            manager.add_scene(id, Recorder { id, log });
+        }
+        manager
+    }
+
+    #[test]
+    fn switches_scenes_like_a_stack() {
+        let log = Log::default();
+        let mut manager = manager(&log);
+        let view_dispatcher = ViewDispatcher::new();
+        let view_dispatcher = view_dispatcher.get_ref();
+        let scenes = &mut manager.scenes;
+
+        scenes.pending.push_back(Transition::Next(0));
+        scenes.switch_pending(view_dispatcher);
+        assert!(scenes.handle(view_dispatcher, SceneEvent::Custom(101)));
+        assert!(scenes.handle(view_dispatcher, SceneEvent::Custom(102)));
+        assert_eq!(scenes.stack, [0, 1, 2]);
+        assert_eq!(scenes.state, 3);
+
+        // Unhandled events other than Back are dropped.
+        assert!(!scenes.handle(view_dispatcher, SceneEvent::Custom(7)));
+        // Unhandled Back presses go back.
+        assert!(scenes.handle(view_dispatcher, SceneEvent::Back));
+        assert_eq!(scenes.stack, [0, 1]);
+
+        log.borrow_mut().clear();
+        assert!(scenes.handle(view_dispatcher, SceneEvent::Custom(200)));
+        assert_eq!(scenes.stack, [0]);
+        assert_eq!(*log.borrow(), [(1, "event"), (1, "exit"), (0, "enter")]);
+    }
+
+    #[test]
+    fn back_switches_scenes_requested_when_returning() {
+        let log = Log::default();
+        let mut manager = manager(&log);
+        manager.add_scene(
+            4,
+            Skipped {
+                id: 4,
+                log: log.clone(),
+                entered: false,
+            },
+        );
+        let view_dispatcher = ViewDispatcher::new();
+        let view_dispatcher = view_dispatcher.get_ref();
+        let scenes = &mut manager.scenes;
+
+        scenes.pending.extend([
+            Transition::Next(0),
+            Transition::Next(4),
+            Transition::Next(1),
+        ]);
+        scenes.switch_pending(view_dispatcher);
+        assert_eq!(scenes.stack, [0, 4, 1]);
+
+        log.borrow_mut().clear();
+        assert!(scenes.handle(view_dispatcher, SceneEvent::Back));
+        assert_eq!(scenes.stack, [0]);
+        assert!(scenes.pending.is_empty());
+        assert_eq!(
+            *log.borrow(),
+            [
+                (1, "event"),
+                (1, "exit"),
+                (4, "enter"),
+                (4, "exit"),
+                (0, "enter")
+            ]
+        );
+    }
+
+    #[test]
+    fn searches_previous_scenes() {
+        let log = Log::default();
+        let mut manager = manager(&log);
+        let view_dispatcher = ViewDispatcher::new();
+        let view_dispatcher = view_dispatcher.get_ref();
+        let scenes = &mut manager.scenes;
+
+        scenes.pending.extend([
+            Transition::Next(0),
+            Transition::Next(1),
+            Transition::Next(2),
+            Transition::Next(3),
+        ]);
+        scenes.switch_pending(view_dispatcher);
+        assert_eq!(scenes.stack, [0, 1, 2, 3]);
+
+        // The current scene isn't a previous scene.
+        assert!(scenes.handle(view_dispatcher, SceneEvent::Custom(303)));
+        assert_eq!(scenes.stack, [0, 1, 2, 3]);
+
+        log.borrow_mut().clear();
+        assert!(scenes.handle(view_dispatcher, SceneEvent::Custom(301)));
+        assert_eq!(scenes.stack, [0, 1]);
+        // Only the current scene is exited.
+        assert_eq!(*log.borrow(), [(3, "event"), (3, "exit"), (1, "enter")]);
+    }
+}
@@ -0,0 +1,339 @@
+//! Settings screens.
+
+use alloc::{boxed::Box, vec::Vec};
+use core::cell::Cell;
+use core::ffi::{c_void, CStr};
+use core::ops::RangeInclusive;
+use core::ptr::NonNull;
+
+use flipperzero_sys as sys;
+
+use crate::gui::view::AsView;
+use crate::gui::view_dispatcher::{ViewDispatcher, ViewDispatcherType};
+use crate::io::Error;
+use crate::storage::Settings;
+
+type IndexGetter<T> = Box<dyn Fn(&T) -> u8>;
+type IndexSetter<T> = Box<dyn Fn(&mut T, u8)>;
+
+/// The values an item can be switched between.
+enum Values {
+    Choice(&'static [&'static CStr]),
+    Toggle,
+    Number { min: u32, step: u32 },
+}
+
+/// An item of a [`SettingsScreen`], which reads and writes the settings through the
+/// index of the selected value.
+struct Item<T> {
+    label: &'static CStr,
+    values: Values,
+    count: u8,
+    get: IndexGetter<T>,
+    set: IndexSetter<T>,
+}
+
+impl<T> Item<T> {
+    /// Returns the text shown for the value at `index`.
+    ///
+    /// Numbers are formatted into `buf`, so that changing a value doesn't allocate.
+    fn label<'a>(&self, index: u8, buf: &'a mut NumberBuf) -> &'a CStr {
+        match self.values {
+            Values::Choice(variants) => variants[usize::from(index)],
+            Values::Toggle if index == 0 => c"OFF",
+            Values::Toggle => c"ON",
+            Values::Number { min, step } => buf.format(min + u32::from(index) * step),
+        }
+    }
+}
+
+/// A list of settings, each of which can be changed with Left and Right.
+///
+/// This wraps the firmware's variable item list, whose items show the current value of
+/// a setting. The items are declared with [`SettingsScreen::choice`],
+/// [`SettingsScreen::toggle`] and [`SettingsScreen::number`], and edit the value of a
+/// [`Settings`] through closures. When the screen is closed, the settings are saved if
+/// they were changed.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use flipperzero::gui::SettingsScreen;
+/// # use flipperzero::storage::Settings;
+/// #[derive(Default)]
+/// struct Config {
+///     speed: usize,
+///     sound: bool,
+/// }
+///
+/// let path = c"/ext/apps_data/example/settings.txt";
+/// let mut settings = Settings::new(path, c"Example", 1, Config::default())
+///     .field(c"Speed", |c| c.speed as u32, |c, v| c.speed = v as usize)
+///     .field(c"Sound", |c| c.sound.into(), |c, v| c.sound = v != 0);
+/// settings.load().ok();
+///
+/// SettingsScreen::<Config>::new()
+///     .choice(c"Speed", &[c"Slow", c"Fast"], |c| c.speed, |c, v| c.speed = v)
+///     .toggle(c"Sound", |c| c.sound, |c, v| c.sound = v)
+///     .show(&mut settings)
+///     .ok();
+/// ```
+pub struct SettingsScreen<T> {
+    items: Vec<Item<T>>,
+}
+
+impl<T: 'static> SettingsScreen<T> {
+    /// Creates a screen without items.
+    pub fn new() -> Self {
+        Self { items: Vec::new() }
+    }
+
+    /// Adds an item switching between the given variants, which `get` and `set` refer to
+    /// by their index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are no variants, or more than 255.
+    pub fn choice<G, S>(
+        mut self,
+        label: &'static CStr,
+        variants: &'static [&'static CStr],
+        get: G,
+        set: S,
+    ) -> Self
+    where
+        G: Fn(&T) -> usize + 'static,
+        S: Fn(&mut T, usize) + 'static,
+    {
+        assert!(
+            (1..=usize::from(u8::MAX)).contains(&variants.len()),
+            "invalid number of variants"
+        );
+        let last = variants.len() - 1;
+        self.items.push(Item {
+            label,
+            values: Values::Choice(variants),
+            count: variants.len() as u8,
+            get: Box::new(move |settings| get(settings).min(last) as u8),
+            set: Box::new(move |settings, index| set(settings, usize::from(index))),
+        });
+        self
+    }
+
+    /// Adds an item switching between OFF and ON.
+    pub fn toggle<G, S>(mut self, label: &'static CStr, get: G, set: S) -> Self
+    where
+        G: Fn(&T) -> bool + 'static,
+        S: Fn(&mut T, bool) + 'static,
+    {
+        self.items.push(Item {
+            label,
+            values: Values::Toggle,
+            count: 2,
+            get: Box::new(move |settings| get(settings).into()),
+            set: Box::new(move |settings, index| set(settings, index != 0)),
+        });
+        self
+    }
+
+    /// Adds an item switching between the numbers in `range`, going up in `step`s.
+    ///
+    /// Values read with `get` are rounded down to the nearest step, and clamped to the
+    /// range.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `step` is zero, if the range is empty, or if it has more than 255
+    /// steps.
+    pub fn number<G, S>(
+        mut self,
+        label: &'static CStr,
+        range: RangeInclusive<u32>,
+        step: u32,
+        get: G,
+        set: S,
+    ) -> Self
+    where
+        G: Fn(&T) -> u32 + 'static,
+        S: Fn(&mut T, u32) + 'static,
+    {
+        let (min, max) = range.into_inner();
+        assert!(step > 0 && min <= max, "invalid range");
+        let count = (max - min) / step + 1;
+        assert!(count <= u32::from(u8::MAX), "too many steps");
+        let count = count as u8;
+
+        self.items.push(Item {
+            label,
+            values: Values::Number { min, step },
+            count,
+            get: Box::new(move |settings| number_index(get(settings), min, step, count)),
+            set: Box::new(move |settings, index| set(settings, min + u32::from(index) * step)),
+        });
+        self
+    }
+
+    /// Shows the screen on top of everything else until the user presses Back, and then
+    /// saves the settings if they were changed.
+    ///
+    /// Returns whether the settings were changed. Changes are made to `settings` as the
+    /// user makes them, so they are kept even if saving fails.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the settings could not be saved.
+    pub fn show(self, settings: &mut Settings<T>) -> Result<bool, Error> {
+        const VIEW_ID: u32 = 0;
+
+        let changed = Cell::new(false);
+        // Only accessed through this pointer until the view dispatcher has been dropped.
+        let value: *mut T = settings.get_mut();
+        let list = ItemList::new();
+        let mut buf = NumberBuf::new();
+        let contexts: Vec<_> = self
+            .items
+            .into_iter()
+            .map(|item| {
+                let index = (item.get)(unsafe { &*value });
+                let mut context = Box::new(ItemContext {
+                    item,
+                    settings: value,
+                    changed: &changed,
+                });
+                unsafe {
+                    let raw = sys::variable_item_list_add(
+                        list.raw.as_ptr(),
+                        context.item.label.as_ptr(),
+                        context.item.count,
+                        Some(change_callback::<T>),
+                        &mut *context as *mut ItemContext<T> as *mut c_void,
+                    );
+                    sys::variable_item_set_current_value_index(raw, index);
+                    sys::variable_item_set_current_value_text(
+                        raw,
+                        context.item.label(index, &mut buf).as_ptr(),
+                    );
+                }
+                context
+            })
+            .collect();
+
+        let mut view_dispatcher = ViewDispatcher::new();
+        view_dispatcher.add_view(VIEW_ID, list);
+        view_dispatcher.attach_to_gui(ViewDispatcherType::Fullscreen);
+        view_dispatcher.switch_to(VIEW_ID);
+        view_dispatcher.run();
+        // The contexts are only used by the list, which is freed with the dispatcher.
+        drop(view_dispatcher);
+        drop(contexts);
+
+        if changed.get() {
+            settings.save()?;
+        }
+        Ok(changed.get())
+    }
+}
+
+impl<T: 'static> Default for SettingsScreen<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The context of an item in the list, giving its change callback access to the
+/// settings.
+struct ItemContext<'a, T> {
+    item: Item<T>,
+    settings: *mut T,
+    changed: &'a Cell<bool>,
+}
+
+unsafe extern "C" fn change_callback<T>(raw: *mut sys::VariableItem) {
+    let context = unsafe { &*(sys::variable_item_get_context(raw) as *const ItemContext<T>) };
+    let index = unsafe { sys::variable_item_get_current_value_index(raw) };
+
+    (context.item.set)(unsafe { &mut *context.settings }, index);
+    context.changed.set(true);
+
+    let mut buf = NumberBuf::new();
+    unsafe {
+        sys::variable_item_set_current_value_text(raw, context.item.label(index, &mut buf).as_ptr())
+    };
+}
+
+/// Returns the index of the step of the range that `value` falls into.
+fn number_index(value: u32, min: u32, step: u32, count: u8) -> u8 {
+    (value.saturating_sub(min) / step).min(u32::from(count) - 1) as u8
+}
+
+/// A buffer for formatting a number as a C string, without allocating.
+struct NumberBuf([u8; 11]);
+
+impl NumberBuf {
+    fn new() -> Self {
+        Self([0; 11])
+    }
+
+    fn format(&mut self, mut value: u32) -> &CStr {
+        let end = self.0.len() - 1;
+        self.0[end] = 0;
+        let mut start = end;
+        loop {
+            start -= 1;
+            self.0[start] = b'0' + (value % 10) as u8;
+            value /= 10;
+            if value == 0 {
+                break;
+            }
+        }
+        CStr::from_bytes_with_nul(&self.0[start..]).unwrap()
+    }
+}
+
+/// The firmware's variable item list.
+struct ItemList {
+    raw: NonNull<sys::VariableItemList>,
+}
+
+impl ItemList {
+    fn new() -> Self {
+        Self {
+            raw: unsafe { NonNull::new_unchecked(sys::variable_item_list_alloc()) },
+        }
+    }
+}
+
+unsafe impl AsView for ItemList {
+    fn as_view_ptr(&self) -> *mut sys::View {
+        unsafe { sys::variable_item_list_get_view(self.raw.as_ptr()) }
+    }
+}
+
+impl Drop for ItemList {
+    fn drop(&mut self) {
+        unsafe { sys::variable_item_list_free(self.raw.as_ptr()) };
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use super::{number_index, NumberBuf};
+
+    #[test]
+    fn formats_numbers() {
+        let mut buf = NumberBuf::new();
+        assert_eq!(buf.format(0).to_bytes(), b"0");
+        assert_eq!(buf.format(42).to_bytes(), b"42");
+        assert_eq!(buf.format(u32::MAX).to_bytes(), b"4294967295");
+    }
+
+    #[test]
+    fn number_indices() {
+        // 5, 10, ..., 60
+        assert_eq!(number_index(5, 5, 5, 12), 0);
+        assert_eq!(number_index(12, 5, 5, 12), 1);
+        assert_eq!(number_index(60, 5, 5, 12), 11);
+        assert_eq!(number_index(0, 5, 5, 12), 0);
+        assert_eq!(number_index(1000, 5, 5, 12), 11);
+    }
+}
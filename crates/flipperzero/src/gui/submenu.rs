@@ -0,0 +1,267 @@
+//! Submenus.
+
+use alloc::{boxed::Box, rc::Rc, vec::Vec};
+use core::cell::RefCell;
+use core::cmp::Ordering;
+use core::ffi::{c_void, CStr};
+use core::fmt;
+use core::ptr::NonNull;
+
+use flipperzero_sys as sys;
+
+use crate::furi::string::FuriString;
+use crate::gui::view::AsView;
+use crate::gui::view_dispatcher::{ViewDispatcher, ViewDispatcherType};
+use crate::io::Error;
+use crate::storage::{self, DirEntry, Storage};
+
+/// Maximum number of entries shown by [`Submenu::fill_from_dir`].
+pub const MAX_DIR_ENTRIES: usize = 256;
+
+/// Maximum number of characters of the labels created by [`Submenu::fill_from_dir`].
+const DIR_LABEL_MAX_CHARS: usize = 24;
+
+type ItemCallback = Box<dyn FnMut(u32)>;
+
+/// Reasons why a [`Submenu`] could not be filled from a directory.
+#[derive(Debug, Clone, Copy)]
+pub enum FromDirError {
+    /// The directory contains more than [`MAX_DIR_ENTRIES`] matching entries.
+    TooManyFiles,
+    /// The directory could not be read.
+    Storage(Error),
+}
+
+impl From<Error> for FromDirError {
+    fn from(error: Error) -> Self {
+        Self::Storage(error)
+    }
+}
+
+impl fmt::Display for FromDirError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooManyFiles => f.write_str("too many files"),
+            Self::Storage(error) => error.fmt(f),
+        }
+    }
+}
+
+impl ufmt::uDisplay for FromDirError {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        match self {
+            Self::TooManyFiles => f.write_str("too many files"),
+            Self::Storage(error) => ufmt::uDisplay::fmt(error, f),
+        }
+    }
+}
+
+/// A menu of items with text labels, which calls a closure when an item is selected.
+///
+/// The closures are called on the thread running the view dispatcher the submenu has
+/// been added to.
+pub struct Submenu {
+    raw: NonNull<sys::Submenu>,
+    // Boxed twice for a thin pointer, see `furi::thread::Builder::spawn`.
+    // The outer box also keeps the pointer valid when the vector grows.
+    #[allow(clippy::vec_box)]
+    items: Vec<Box<ItemCallback>>,
+}
+
+impl Submenu {
+    /// Creates an empty submenu.
+    pub fn new() -> Self {
+        Self {
+            raw: unsafe { NonNull::new_unchecked(sys::submenu_alloc()) },
+            items: Vec::new(),
+        }
+    }
+
+    /// Returns the raw pointer to the submenu, for use with the `sys` bindings.
+    pub fn as_ptr(&self) -> *mut sys::Submenu {
+        self.raw.as_ptr()
+    }
+
+    /// Adds an item to the end of the menu, which calls `callback` with `index` when
+    /// selected.
+    ///
+    /// Several items may have the same index.
+    pub fn add_item<F>(&mut self, label: &CStr, index: u32, callback: F)
+    where
+        F: FnMut(u32) + 'static,
+    {
+        unsafe extern "C" fn run_callback(context: *mut c_void, index: u32) {
+            let callback = unsafe { &mut *(context as *mut ItemCallback) };
+            callback(index);
+        }
+
+        let mut callback: Box<ItemCallback> = Box::new(Box::new(callback));
+        unsafe {
+            // The label is copied by the submenu.
+            sys::submenu_add_item(
+                self.raw.as_ptr(),
+                label.as_ptr(),
+                index,
+                Some(run_callback),
+                &mut *callback as *mut ItemCallback as *mut c_void,
+            );
+        }
+        self.items.push(callback);
+    }
+
+    /// Removes all items from the menu.
+    pub fn reset(&mut self) {
+        unsafe { sys::submenu_reset(self.raw.as_ptr()) };
+        self.items.clear();
+    }
+
+    /// Sets the header shown above the items.
+    pub fn set_header(&mut self, header: &CStr) {
+        unsafe { sys::submenu_set_header(self.raw.as_ptr(), header.as_ptr()) };
+    }
+
+    /// Returns the index of the selected item.
+    pub fn selected_item(&self) -> u32 {
+        unsafe { sys::submenu_get_selected_item(self.raw.as_ptr()) }
+    }
+
+    /// Selects the first item with the given index.
+    pub fn set_selected_item(&mut self, index: u32) {
+        unsafe { sys::submenu_set_selected_item(self.raw.as_ptr(), index) };
+    }
+
+    /// Replaces the items of the menu with the entries of `dir` for which `filter`
+    /// returns `true`, returning the number of items.
+    ///
+    /// Directories are listed before files, and both are sorted by name, ignoring ASCII
+    /// case. Long names are shortened with [`storage::display_path`]. When an item is
+    /// selected, `on_select` is called with the full path of its entry.
+    ///
+    /// This can be called again to rebuild the menu after the directory has changed.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`FromDirError::TooManyFiles`] if more than [`MAX_DIR_ENTRIES`] entries
+    /// match the filter, without reading the rest of the directory, and
+    /// [`FromDirError::Storage`] if the directory could not be read. The menu is left
+    /// empty in both cases.
+    pub fn fill_from_dir<P, F>(
+        &mut self,
+        dir: &CStr,
+        mut filter: P,
+        on_select: F,
+    ) -> Result<usize, FromDirError>
+    where
+        P: FnMut(&DirEntry) -> bool,
+        F: FnMut(&CStr) + 'static,
+    {
+        self.reset();
+
+        let mut entries = Vec::new();
+        for entry in Storage::open().read_dir(dir)? {
+            let entry = entry?;
+            if !filter(&entry) {
+                continue;
+            }
+            if entries.len() == MAX_DIR_ENTRIES {
+                return Err(FromDirError::TooManyFiles);
+            }
+            entries.push(entry);
+        }
+        entries.sort_by(compare_entries);
+
+        let on_select = Rc::new(RefCell::new(on_select));
+        for (index, entry) in entries.iter().enumerate() {
+            let mut label = storage::display_path(entry.name(), DIR_LABEL_MAX_CHARS);
+            if entry.is_dir() {
+                label.push('/');
+            }
+
+            let mut path = FuriString::from(dir);
+            path.push('/');
+            path.push_c_str(entry.name());
+
+            let on_select = on_select.clone();
+            self.add_item(label.as_c_str(), index as u32, move |_| {
+                (on_select.borrow_mut())(path.as_c_str())
+            });
+        }
+
+        Ok(entries.len())
+    }
+
+    /// Shows a fullscreen menu of the entries of `dir` for which `filter` returns
+    /// `true`, and returns the full path of the entry selected by the user, or `None`
+    /// if they pressed Back.
+    ///
+    /// See [`Submenu::fill_from_dir`] for how the entries are listed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory could not be listed.
+    pub fn from_dir<P>(dir: &CStr, filter: P) -> Result<Option<FuriString>, FromDirError>
+    where
+        P: FnMut(&DirEntry) -> bool,
+    {
+        const VIEW_ID: u32 = 0;
+
+        let selected = Rc::new(RefCell::new(None));
+
+        let mut view_dispatcher = ViewDispatcher::new();
+        let sender = view_dispatcher.custom_event_sender();
+
+        let mut submenu = Submenu::new();
+        submenu.set_header(storage::display_path(dir, DIR_LABEL_MAX_CHARS).as_c_str());
+        submenu.fill_from_dir(dir, filter, {
+            let selected = selected.clone();
+            move |path| {
+                *selected.borrow_mut() = Some(FuriString::from(path));
+                sender.send(0);
+            }
+        })?;
+
+        view_dispatcher.add_view(VIEW_ID, submenu);
+        view_dispatcher.on_custom_event(|dispatcher, _| {
+            dispatcher.stop();
+            true
+        });
+        view_dispatcher.attach_to_gui(ViewDispatcherType::Fullscreen);
+        view_dispatcher.switch_to(VIEW_ID);
+        view_dispatcher.run();
+        drop(view_dispatcher);
+
+        Ok(selected.take())
+    }
+}
+
+/// Orders directories before files, and entries of the same kind by name, ignoring
+/// ASCII case.
+fn compare_entries(a: &DirEntry, b: &DirEntry) -> Ordering {
+    b.is_dir().cmp(&a.is_dir()).then_with(|| {
+        let a = a.name().to_bytes().iter().map(u8::to_ascii_lowercase);
+        let b = b.name().to_bytes().iter().map(u8::to_ascii_lowercase);
+        a.cmp(b)
+    })
+}
+
+unsafe impl AsView for Submenu {
+    fn as_view_ptr(&self) -> *mut sys::View {
+        unsafe { sys::submenu_get_view(self.raw.as_ptr()) }
+    }
+}
+
+impl Drop for Submenu {
+    fn drop(&mut self) {
+        // The callbacks are dropped after this, once they can no longer be called.
+        unsafe { sys::submenu_free(self.raw.as_ptr()) };
+    }
+}
+
+impl Default for Submenu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,112 @@
+//! Text boxes.
+
+use alloc::ffi::CString;
+use core::ffi::CStr;
+use core::ptr::NonNull;
+
+use flipperzero_sys as sys;
+
+use crate::gui::view::AsView;
+
+/// How a [`TextBox`] shows its text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextBoxFont {
+    /// The small font used for most text, wrapping lines at word boundaries.
+    Text,
+    /// A monospaced font, which suits hex dumps.
+    Hex,
+}
+
+impl TextBoxFont {
+    pub fn to_sys(&self) -> sys::TextBoxFont {
+        match self {
+            Self::Text => sys::TextBoxFont_TextBoxFontText,
+            Self::Hex => sys::TextBoxFont_TextBoxFontHex,
+        }
+    }
+}
+
+/// Where a [`TextBox`] is scrolled to when its text is set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextBoxFocus {
+    /// The start of the text.
+    Start,
+    /// The end of the text, as for logs.
+    End,
+}
+
+impl TextBoxFocus {
+    pub fn to_sys(&self) -> sys::TextBoxFocus {
+        match self {
+            Self::Start => sys::TextBoxFocus_TextBoxFocusStart,
+            Self::End => sys::TextBoxFocus_TextBoxFocusEnd,
+        }
+    }
+}
+
+/// The firmware's view of a scrollable text.
+///
+/// Unlike [`TextViewer`](crate::gui::TextViewer), this is a view to add to a
+/// [`ViewDispatcher`](crate::gui::ViewDispatcher), and the whole text is held in
+/// memory.
+pub struct TextBox {
+    raw: NonNull<sys::TextBox>,
+    // The firmware keeps a pointer to this.
+    text: Option<CString>,
+}
+
+impl TextBox {
+    /// Creates an empty text box.
+    pub fn new() -> Self {
+        Self {
+            raw: unsafe { NonNull::new_unchecked(sys::text_box_alloc()) },
+            text: None,
+        }
+    }
+
+    /// Returns the raw pointer to the text box, for use with the `sys` bindings.
+    pub fn as_ptr(&self) -> *mut sys::TextBox {
+        self.raw.as_ptr()
+    }
+
+    /// Sets the text shown, scrolling to where the focus is set.
+    pub fn set_text(&mut self, text: &CStr) {
+        let text = self.text.insert(text.into());
+        unsafe { sys::text_box_set_text(self.raw.as_ptr(), text.as_ptr()) };
+    }
+
+    /// Sets how the text is shown.
+    pub fn set_font(&mut self, font: TextBoxFont) {
+        unsafe { sys::text_box_set_font(self.raw.as_ptr(), font.to_sys()) };
+    }
+
+    /// Sets where the text box is scrolled to when its text is set.
+    pub fn set_focus(&mut self, focus: TextBoxFocus) {
+        unsafe { sys::text_box_set_focus(self.raw.as_ptr(), focus.to_sys()) };
+    }
+
+    /// Removes the text, and resets the font and focus.
+    pub fn reset(&mut self) {
+        unsafe { sys::text_box_reset(self.raw.as_ptr()) };
+        self.text = None;
+    }
+}
+
+unsafe impl AsView for TextBox {
+    fn as_view_ptr(&self) -> *mut sys::View {
+        unsafe { sys::text_box_get_view(self.raw.as_ptr()) }
+    }
+}
+
+impl Drop for TextBox {
+    fn drop(&mut self) {
+        unsafe { sys::text_box_free(self.raw.as_ptr()) };
+        // The text is dropped after this, once it can no longer be used.
+    }
+}
+
+impl Default for TextBox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
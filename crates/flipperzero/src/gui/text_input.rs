@@ -0,0 +1,175 @@
+//! Text inputs.
+
+use alloc::{boxed::Box, ffi::CString, vec, vec::Vec};
+use core::ffi::{c_char, c_void, CStr};
+use core::ptr::NonNull;
+
+use flipperzero_sys as sys;
+
+use crate::gui::view::AsView;
+
+type ResultCallback = Box<dyn FnMut(&CStr)>;
+
+/// The text being entered, and the closure it is passed to once confirmed.
+struct InputState {
+    /// The text, which always ends with a nul byte.
+    buffer: Vec<c_char>,
+    callback: ResultCallback,
+}
+
+/// The firmware's on-screen keyboard for entering a line of text.
+///
+/// The closure is called on the thread running the view dispatcher the text input has
+/// been added to.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use flipperzero::gui::{TextInput, ViewDispatcher};
+/// const NAME: u32 = 0;
+///
+/// let mut view_dispatcher = ViewDispatcher::new();
+/// let sender = view_dispatcher.custom_event_sender();
+/// let mut input = TextInput::new();
+/// input.set_header(c"Enter your name");
+/// input.on_result(c"", 16, false, move |name| {
+///     // Use `name`, then tell the app that it was entered.
+///     sender.send(0);
+/// });
+/// view_dispatcher.add_view(NAME, input);
+/// ```
+pub struct TextInput {
+    raw: NonNull<sys::TextInput>,
+    // The firmware keeps pointers to these.
+    header: Option<CString>,
+    state: Option<Box<InputState>>,
+}
+
+impl TextInput {
+    /// Creates a text input without a header, which can't be confirmed until
+    /// [`TextInput::on_result`] is called.
+    pub fn new() -> Self {
+        Self {
+            raw: unsafe { NonNull::new_unchecked(sys::text_input_alloc()) },
+            header: None,
+            state: None,
+        }
+    }
+
+    /// Returns the raw pointer to the text input, for use with the `sys` bindings.
+    pub fn as_ptr(&self) -> *mut sys::TextInput {
+        self.raw.as_ptr()
+    }
+
+    /// Sets the header shown above the keyboard.
+    pub fn set_header(&mut self, header: &CStr) {
+        let header = self.header.insert(header.into());
+        unsafe { sys::text_input_set_header_text(self.raw.as_ptr(), header.as_ptr()) };
+    }
+
+    /// Starts entering a text of up to `max_len` bytes, which is `initial` at first,
+    /// and sets the closure which is passed the text once the user confirms it.
+    ///
+    /// `initial` is cut to `max_len` bytes if it is longer. If `clear_initial` is
+    /// `true`, `initial` is selected and replaced by the first key that is typed, as the
+    /// firmware's apps do for suggested file names.
+    pub fn on_result<F>(&mut self, initial: &CStr, max_len: usize, clear_initial: bool, callback: F)
+    where
+        F: FnMut(&CStr) + 'static,
+    {
+        let state = self.state.insert(Box::new(InputState {
+            buffer: initial_buffer(initial, max_len),
+            callback: Box::new(callback),
+        }));
+        unsafe {
+            sys::text_input_set_result_callback(
+                self.raw.as_ptr(),
+                Some(result_callback),
+                &mut **state as *mut InputState as *mut c_void,
+                state.buffer.as_mut_ptr(),
+                state.buffer.len(),
+                clear_initial,
+            )
+        };
+    }
+
+    /// Removes the header, the text and the closure.
+    pub fn reset(&mut self) {
+        unsafe { sys::text_input_reset(self.raw.as_ptr()) };
+        self.header = None;
+        self.state = None;
+    }
+}
+
+unsafe extern "C" fn result_callback(context: *mut c_void) {
+    let state = unsafe { &mut *(context as *mut InputState) };
+    // The firmware keeps the text terminated.
+    let text = unsafe { CStr::from_ptr(state.buffer.as_ptr()) };
+    (state.callback)(text);
+}
+
+/// Returns a buffer for a text of up to `max_len` bytes, holding `initial` cut to that
+/// length.
+fn initial_buffer(initial: &CStr, max_len: usize) -> Vec<c_char> {
+    let mut buffer = vec![0; max_len + 1];
+    for (b, &c) in buffer
+        .iter_mut()
+        .zip(initial.to_bytes().iter().take(max_len))
+    {
+        *b = c as c_char;
+    }
+    buffer
+}
+
+unsafe impl AsView for TextInput {
+    fn as_view_ptr(&self) -> *mut sys::View {
+        unsafe { sys::text_input_get_view(self.raw.as_ptr()) }
+    }
+}
+
+impl Drop for TextInput {
+    fn drop(&mut self) {
+        unsafe { sys::text_input_free(self.raw.as_ptr()) };
+        // The header and the state are dropped after this, once they can no longer be
+        // used.
+    }
+}
+
+impl Default for TextInput {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use alloc::{rc::Rc, string::String};
+    use core::cell::RefCell;
+    use core::ffi::c_void;
+
+    use super::{initial_buffer, result_callback, InputState, TextInput};
+
+    #[test]
+    fn initial_text_is_cut() {
+        let buffer = initial_buffer(c"hello", 3);
+        assert!(buffer.iter().map(|&c| c as u8).eq(*b"hel\0"));
+        assert_eq!(initial_buffer(c"hi", 0), [0]);
+        assert!(initial_buffer(c"", 4).iter().all(|&c| c == 0));
+    }
+
+    #[test]
+    fn passes_the_text_to_the_closure() {
+        let entered = Rc::new(RefCell::new(String::new()));
+        let mut input = TextInput::new();
+        input.set_header(c"Name");
+        input.on_result(c"flipper", 16, true, {
+            let entered = entered.clone();
+            move |text| entered.borrow_mut().push_str(text.to_str().unwrap())
+        });
+
+        // Confirm the text as the firmware would.
+        let state = input.state.as_mut().unwrap();
+        unsafe { result_callback(&mut **state as *mut InputState as *mut c_void) };
+        assert_eq!(*entered.borrow(), "flipper");
+    }
+}
@@ -0,0 +1,414 @@
+//! Scrollable text viewer.
+
+use alloc::vec::Vec;
+use core::ffi::c_void;
+use core::ptr::NonNull;
+use core::time::Duration;
+
+use flipperzero_sys as sys;
+
+use crate::furi::message_queue::MessageQueue;
+use crate::furi::record::Record;
+use crate::furi::string::FuriString;
+use crate::furi::sync::Mutex;
+use crate::io::{Error, Read, Seek, SeekFrom};
+
+/// Maximum number of characters shown on a line before it is wrapped.
+const COLUMNS: usize = 21;
+/// Number of lines visible at once.
+const LINES: usize = 5;
+/// Maximum number of line offsets recorded while indexing the text.
+const MAX_INDEXED_LINES: usize = 2048;
+/// Number of bytes read from the reader at a time.
+///
+/// This is large enough to lay out a page and the first line of the next one even if
+/// every character is four bytes long, leaving the rest as margin for scrolling.
+const WINDOW_SIZE: usize = 1024;
+const PAGE_SIZE: usize = (LINES + 1) * (COLUMNS * 4 + 2);
+
+const TITLE_HEIGHT: i32 = 12;
+const LINE_HEIGHT: i32 = 10;
+
+/// A fullscreen viewer for text that is too large to be loaded into memory at once.
+///
+/// Only the visible part of the text (and a small margin around it) is kept in memory.
+/// Long lines are wrapped, and bytes which are not valid UTF-8 are shown as
+/// [`char::REPLACEMENT_CHARACTER`].
+///
+/// Up and Down scroll by a line, Left and Right by a page, and Back closes the viewer.
+pub struct TextViewer;
+
+impl TextViewer {
+    /// Shows the text read from `reader` with the given title, until the user presses
+    /// Back.
+    ///
+    /// The text is read once up front to find where lines start, which allows scrolling
+    /// up quickly. For very long texts only the first lines are indexed; beyond them,
+    /// scrolling up moves back by a fixed number of bytes instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading from or seeking in `reader` fails.
+    pub fn show<R: Read + Seek>(reader: R, title: &str) -> Result<(), Error> {
+        let mut text = Text::new(reader)?;
+
+        let model = Mutex::new(Model {
+            title: FuriString::from(title),
+            lines: Default::default(),
+            position: 0,
+            len: text.len,
+        });
+        text.layout(&model)?;
+
+        let queue = MessageQueue::<sys::InputEvent>::new(8);
+        let view_port = unsafe { NonNull::new_unchecked(sys::view_port_alloc()) };
+        let gui = Record::<sys::Gui>::open();
+        unsafe {
+            sys::view_port_draw_callback_set(
+                view_port.as_ptr(),
+                Some(draw_callback),
+                &model as *const _ as *mut c_void,
+            );
+            sys::view_port_input_callback_set(
+                view_port.as_ptr(),
+                Some(input_callback),
+                &queue as *const _ as *mut c_void,
+            );
+            sys::gui_add_view_port(
+                gui.as_ptr(),
+                view_port.as_ptr(),
+                sys::GuiLayer_GuiLayerFullscreen,
+            );
+        }
+
+        let result = (|| loop {
+            let Ok(event) = queue.get(Duration::MAX) else {
+                continue;
+            };
+            if event.type_ != sys::InputType_InputTypeShort
+                && event.type_ != sys::InputType_InputTypeRepeat
+            {
+                continue;
+            }
+
+            match event.key {
+                sys::InputKey_InputKeyUp => text.scroll_up(1),
+                sys::InputKey_InputKeyDown => text.scroll_down(1),
+                sys::InputKey_InputKeyLeft => text.scroll_up(LINES),
+                sys::InputKey_InputKeyRight => text.scroll_down(LINES),
+                sys::InputKey_InputKeyBack => return Ok(()),
+                _ => continue,
+            }
+            text.layout(&model)?;
+            unsafe { sys::view_port_update(view_port.as_ptr()) };
+        })();
+
+        unsafe {
+            sys::view_port_enabled_set(view_port.as_ptr(), false);
+            sys::gui_remove_view_port(gui.as_ptr(), view_port.as_ptr());
+            sys::view_port_free(view_port.as_ptr());
+        }
+
+        result
+    }
+}
+
+/// The text as displayed by the GUI thread.
+struct Model {
+    title: FuriString,
+    lines: [FuriString; LINES],
+    position: usize,
+    len: usize,
+}
+
+/// The state of the text on the viewer's thread.
+struct Text<R> {
+    reader: R,
+    len: usize,
+    /// Offsets of the first lines, in ascending order.
+    index: Vec<usize>,
+    /// Whether `index` contains every line of the text.
+    complete: bool,
+    /// The contents of the text starting at `window_start`.
+    window: Vec<u8>,
+    window_start: usize,
+    /// Offset of the first visible line.
+    top: usize,
+    /// Offsets of the lines following `top`, as found by the last layout.
+    next: [Option<usize>; LINES],
+}
+
+impl<R: Read + Seek> Text<R> {
+    fn new(mut reader: R) -> Result<Self, Error> {
+        let len = reader.stream_len()?;
+        reader.rewind()?;
+
+        let mut index = Vec::new();
+        index.push(0);
+        let mut complete = true;
+        let mut wrap = LineWrap::new();
+        let mut buf = [0; 256];
+        let mut offset = 0;
+        'index: loop {
+            let n = reader.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            for &byte in &buf[..n] {
+                if wrap.starts_line(byte) {
+                    if index.len() == MAX_INDEXED_LINES {
+                        complete = false;
+                        break 'index;
+                    }
+                    index.push(offset);
+                }
+                offset += 1;
+            }
+        }
+
+        Ok(Self {
+            reader,
+            len,
+            index,
+            complete,
+            window: Vec::with_capacity(WINDOW_SIZE),
+            window_start: 0,
+            top: 0,
+            next: [None; LINES],
+        })
+    }
+
+    /// Returns the visible part of the text, reading it from the reader if necessary.
+    fn page(&mut self) -> Result<&[u8], Error> {
+        let window_end = self.window_start + self.window.len();
+        let at_eof = window_end == self.len;
+        if self.top < self.window_start
+            || (self.top + PAGE_SIZE > window_end && !at_eof)
+            || self.top > window_end
+        {
+            self.window.resize(WINDOW_SIZE, 0);
+            self.reader.seek(SeekFrom::Start(self.top as u64))?;
+            let mut filled = 0;
+            while filled < WINDOW_SIZE {
+                match self.reader.read(&mut self.window[filled..])? {
+                    0 => break,
+                    n => filled += n,
+                }
+            }
+            self.window.truncate(filled);
+            self.window_start = self.top;
+        }
+
+        Ok(&self.window[self.top - self.window_start..])
+    }
+
+    /// Lays out the visible lines and hands them to the GUI thread.
+    fn layout(&mut self, model: &Mutex<Model>) -> Result<(), Error> {
+        let top = self.top;
+        let page = self.page()?;
+
+        let mut starts = [None; LINES + 1];
+        starts[0] = Some(0);
+        let mut wrap = LineWrap::new();
+        let mut line = 1;
+        for (i, &byte) in page.iter().enumerate() {
+            if wrap.starts_line(byte) {
+                starts[line] = Some(i);
+                line += 1;
+                if line > LINES {
+                    break;
+                }
+            }
+        }
+
+        let mut model = model.lock();
+        for (i, line) in model.lines.iter_mut().enumerate() {
+            line.clear();
+            if let Some(start) = starts[i] {
+                let end = starts[i + 1].unwrap_or(page.len());
+                push_lossy(line, &page[start..end]);
+            }
+        }
+        model.position = top;
+        drop(model);
+
+        for (next, start) in self.next.iter_mut().zip(&starts[1..]) {
+            *next = start.map(|start| top + start);
+        }
+        Ok(())
+    }
+
+    fn scroll_down(&mut self, lines: usize) {
+        // Only scroll as far as there is a line to show at the top.
+        if let Some(next) = self.next[..lines].iter().rev().flatten().next() {
+            self.top = *next;
+        }
+    }
+
+    fn scroll_up(&mut self, lines: usize) {
+        for _ in 0..lines {
+            self.top = self.previous_line(self.top);
+        }
+    }
+
+    fn previous_line(&self, offset: usize) -> usize {
+        let last_indexed = *self.index.last().unwrap();
+        if self.complete || offset <= last_indexed {
+            let i = self.index.partition_point(|&start| start < offset);
+            self.index[i.saturating_sub(1)]
+        } else {
+            // Beyond the indexed lines, we don't know where the previous line starts.
+            offset.saturating_sub(COLUMNS).max(last_indexed)
+        }
+    }
+}
+
+/// Determines where lines start, when fed a text one byte at a time.
+struct LineWrap {
+    column: usize,
+    newline: bool,
+}
+
+impl LineWrap {
+    fn new() -> Self {
+        Self {
+            column: 0,
+            newline: false,
+        }
+    }
+
+    /// Feeds the next byte of the text, returning `true` if a new line starts at it.
+    fn starts_line(&mut self, byte: u8) -> bool {
+        let is_char_start = byte & 0xc0 != 0x80 && byte != b'\r';
+
+        let starts_line = if self.newline {
+            self.newline = false;
+            true
+        } else {
+            is_char_start && byte != b'\n' && self.column == COLUMNS
+        };
+        if starts_line {
+            self.column = 0;
+        }
+
+        if byte == b'\n' {
+            self.newline = true;
+        } else if is_char_start {
+            self.column += 1;
+        }
+
+        starts_line
+    }
+}
+
+/// Appends `line` to `string`, replacing invalid UTF-8 and dropping line terminators.
+fn push_lossy(string: &mut FuriString, line: &[u8]) {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    for chunk in line.utf8_chunks() {
+        string.push_str(chunk.valid());
+        if !chunk.invalid().is_empty() {
+            string.push(char::REPLACEMENT_CHARACTER);
+        }
+    }
+}
+
+unsafe extern "C" fn draw_callback(canvas: *mut sys::Canvas, context: *mut c_void) {
+    let model = unsafe { &*(context as *const Mutex<Model>) };
+    let model = model.lock();
+
+    unsafe {
+        sys::canvas_set_font(canvas, sys::Font_FontPrimary);
+        sys::canvas_draw_str_aligned(
+            canvas,
+            0,
+            0,
+            sys::Align_AlignLeft,
+            sys::Align_AlignTop,
+            model.title.as_c_ptr(),
+        );
+
+        sys::canvas_set_font(canvas, sys::Font_FontSecondary);
+        for (i, line) in model.lines.iter().enumerate() {
+            sys::canvas_draw_str_aligned(
+                canvas,
+                0,
+                TITLE_HEIGHT + LINE_HEIGHT * i as i32,
+                sys::Align_AlignLeft,
+                sys::Align_AlignTop,
+                line.as_c_ptr(),
+            );
+        }
+
+        if model.len > 0 {
+            sys::elements_scrollbar(canvas, model.position, model.len);
+        }
+    }
+}
+
+unsafe extern "C" fn input_callback(event: *mut sys::InputEvent, context: *mut c_void) {
+    let queue = unsafe { &*(context as *const MessageQueue<sys::InputEvent>) };
+    // Dropping events while the viewer is busy is fine.
+    let _ = queue.put(unsafe { *event }, Duration::ZERO);
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use crate::furi::string::FuriString;
+
+    use super::{push_lossy, LineWrap, COLUMNS};
+
+    fn line_starts(text: &[u8]) -> impl Iterator<Item = usize> + '_ {
+        let mut wrap = LineWrap::new();
+        text.iter()
+            .enumerate()
+            .filter_map(move |(i, &byte)| wrap.starts_line(byte).then_some(i))
+    }
+
+    #[test]
+    fn wraps_at_newlines() {
+        let mut starts = line_starts(b"foo\nbar\n\nbaz");
+        assert_eq!(starts.next(), Some(4));
+        assert_eq!(starts.next(), Some(8));
+        assert_eq!(starts.next(), Some(9));
+        assert!(starts.next().is_none());
+    }
+
+    #[test]
+    fn wraps_long_lines() {
+        let text = [b'a'; COLUMNS * 2 + 1];
+        let mut starts = line_starts(&text);
+        assert_eq!(starts.next(), Some(COLUMNS));
+        assert_eq!(starts.next(), Some(COLUMNS * 2));
+        assert!(starts.next().is_none());
+    }
+
+    #[test]
+    fn newline_after_full_line() {
+        let mut text = [b'a'; COLUMNS + 2];
+        text[COLUMNS] = b'\n';
+        let mut starts = line_starts(&text);
+        assert_eq!(starts.next(), Some(COLUMNS + 1));
+        assert!(starts.next().is_none());
+    }
+
+    #[test]
+    fn counts_characters_not_bytes() {
+        // Each `ä` is two bytes long.
+        let mut text = [0; COLUMNS * 2 + 1];
+        for c in text[..COLUMNS * 2].chunks_mut(2) {
+            c.copy_from_slice("ä".as_bytes());
+        }
+        text[COLUMNS * 2] = b'b';
+        let mut starts = line_starts(&text);
+        assert_eq!(starts.next(), Some(COLUMNS * 2));
+        assert!(starts.next().is_none());
+    }
+
+    #[test]
+    fn lossy_lines() {
+        let mut line = FuriString::new();
+        push_lossy(&mut line, b"caf\xc3\xa9 \xff!\r\n");
+        assert_eq!(line.to_bytes(), "café \u{fffd}!".as_bytes());
+    }
+}
@@ -0,0 +1,145 @@
+//! Views.
+
+use alloc::boxed::Box;
+use core::ffi::c_void;
+use core::mem;
+use core::ptr::NonNull;
+
+use flipperzero_sys as sys;
+
+use crate::gui::canvas::Canvas;
+use crate::input::InputEvent;
+
+type DrawCallback = Box<dyn FnMut(&mut Canvas) + Send>;
+
+/// Types which own a firmware view, and can thus be added to a
+/// [`ViewDispatcher`](crate::gui::ViewDispatcher).
+///
+/// # Safety
+///
+/// [`AsView::as_view_ptr`] must return a pointer to a valid view, which stays the same
+/// for as long as the value exists, including when it is moved.
+pub unsafe trait AsView {
+    /// Returns the raw pointer to the view.
+    fn as_view_ptr(&self) -> *mut sys::View;
+}
+
+/// The input closure of a [`View`], along with the view it redraws.
+struct InputHandler {
+    view: NonNull<sys::View>,
+    callback: Box<dyn FnMut(InputEvent) -> bool>,
+}
+
+/// A screen of a [`ViewDispatcher`](crate::gui::ViewDispatcher), drawn by a closure.
+///
+/// The draw callback is called on the GUI thread, which is why it needs to be [`Send`].
+/// The input callback is called on the thread running the view dispatcher.
+///
+/// A view's callbacks can only be set before it is added to a view dispatcher, which
+/// takes ownership of it.
+pub struct View {
+    raw: NonNull<sys::View>,
+    // Boxed twice for a thin pointer, see `furi::thread::Builder::spawn`.
+    draw: Option<Box<DrawCallback>>,
+    input: Option<Box<InputHandler>>,
+}
+
+impl View {
+    /// Creates a view without callbacks.
+    pub fn new() -> Self {
+        let raw = unsafe { NonNull::new_unchecked(sys::view_alloc()) };
+        // The model holds the pointer to the draw closure, as the draw callback only
+        // receives the model and not the context.
+        unsafe {
+            sys::view_allocate_model(
+                raw.as_ptr(),
+                sys::ViewModelType_ViewModelTypeLockFree,
+                mem::size_of::<*mut DrawCallback>(),
+            );
+            *(sys::view_get_model(raw.as_ptr()) as *mut *mut DrawCallback) = core::ptr::null_mut();
+        }
+
+        Self {
+            raw,
+            draw: None,
+            input: None,
+        }
+    }
+
+    /// Returns the raw pointer to the view, for use with the `sys` bindings.
+    pub fn as_ptr(&self) -> *mut sys::View {
+        self.raw.as_ptr()
+    }
+
+    /// Sets the closure which draws the view.
+    pub fn on_draw<F>(&mut self, callback: F)
+    where
+        F: FnMut(&mut Canvas) + Send + 'static,
+    {
+        unsafe extern "C" fn draw(canvas: *mut sys::Canvas, model: *mut c_void) {
+            let draw = unsafe { *(model as *mut *mut DrawCallback) };
+            if let Some(draw) = unsafe { draw.as_mut() } {
+                // SAFETY: the canvas is only used by us until the callback returns.
+                draw(&mut unsafe { Canvas::from_raw(canvas) });
+            }
+        }
+
+        let callback: &mut DrawCallback = self.draw.insert(Box::new(Box::new(callback)));
+        unsafe {
+            *(sys::view_get_model(self.raw.as_ptr()) as *mut *mut DrawCallback) = callback;
+            sys::view_commit_model(self.raw.as_ptr(), false);
+            sys::view_set_draw_callback(self.raw.as_ptr(), Some(draw));
+        }
+    }
+
+    /// Sets the closure which handles input events while the view is shown.
+    ///
+    /// The closure returns whether it handled the event, in which case the view is
+    /// redrawn. Unhandled Back presses are passed on to the view dispatcher's
+    /// navigation callback.
+    pub fn on_input<F>(&mut self, callback: F)
+    where
+        F: FnMut(InputEvent) -> bool + 'static,
+    {
+        unsafe extern "C" fn input(event: *mut sys::InputEvent, context: *mut c_void) -> bool {
+            let handler = unsafe { &mut *(context as *mut InputHandler) };
+            let handled = (handler.callback)(InputEvent::from_sys(unsafe { &*event }));
+            if handled {
+                unsafe { sys::view_commit_model(handler.view.as_ptr(), true) };
+            }
+            handled
+        }
+
+        let handler: &mut InputHandler = self.input.insert(Box::new(InputHandler {
+            view: self.raw,
+            callback: Box::new(callback),
+        }));
+        unsafe {
+            sys::view_set_context(
+                self.raw.as_ptr(),
+                handler as *mut InputHandler as *mut c_void,
+            );
+            sys::view_set_input_callback(self.raw.as_ptr(), Some(input));
+        }
+    }
+}
+
+unsafe impl AsView for View {
+    fn as_view_ptr(&self) -> *mut sys::View {
+        self.raw.as_ptr()
+    }
+}
+
+impl Drop for View {
+    fn drop(&mut self) {
+        // This also frees the model. The callbacks are dropped after this, once they can
+        // no longer be called.
+        unsafe { sys::view_free(self.raw.as_ptr()) };
+    }
+}
+
+impl Default for View {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,304 @@
+//! View dispatcher.
+
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
+use core::ffi::c_void;
+use core::ptr::NonNull;
+
+use flipperzero_sys as sys;
+
+use crate::furi::record::Record;
+use crate::furi::sync::Mutex;
+use crate::gui::view::AsView;
+
+type CustomEventCallback = Box<dyn FnMut(&ViewDispatcherRef, u32) -> bool>;
+type NavigationCallback = Box<dyn FnMut(&ViewDispatcherRef) -> bool>;
+
+/// How a [`ViewDispatcher`] is shown on the GUI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViewDispatcherType {
+    /// On the desktop layer, used by the desktop app.
+    Desktop,
+    /// On the window layer, below the status bar.
+    Window,
+    /// On the whole screen, above the other layers.
+    Fullscreen,
+}
+
+impl ViewDispatcherType {
+    pub fn to_sys(&self) -> sys::ViewDispatcherType {
+        match self {
+            Self::Desktop => sys::ViewDispatcherType_ViewDispatcherTypeDesktop,
+            Self::Window => sys::ViewDispatcherType_ViewDispatcherTypeWindow,
+            Self::Fullscreen => sys::ViewDispatcherType_ViewDispatcherTypeFullscreen,
+        }
+    }
+}
+
+/// The event closures of a [`ViewDispatcher`], boxed so that their address stays the
+/// same.
+struct Callbacks {
+    dispatcher: ViewDispatcherRef,
+    custom_event: Option<CustomEventCallback>,
+    navigation: Option<NavigationCallback>,
+}
+
+/// A raw view dispatcher pointer, which is cleared once the dispatcher is freed.
+struct SharedDispatcher(Option<NonNull<sys::ViewDispatcher>>);
+
+// SAFETY: sending custom events is thread-safe, and this is the only operation done
+// through the pointer.
+unsafe impl Send for SharedDispatcher {}
+
+/// Switches between multiple views, such as [`View`](crate::gui::View)s, and runs an
+/// event loop to handle their input and custom events.
+///
+/// Views are registered with [`ViewDispatcher::add_view`] under an ID of the app's
+/// choosing, and shown with [`ViewDispatcher::switch_to`]. [`ViewDispatcher::run`]
+/// then handles events on the calling thread until the dispatcher is stopped, which by
+/// default happens when Back is pressed.
+///
+/// This is the equivalent of the firmware's `ViewDispatcher` with its event queue,
+/// which is always enabled.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use flipperzero::gui::{View, ViewDispatcher, ViewDispatcherType};
+/// const MAIN: u32 = 0;
+///
+/// let mut view = View::new();
+/// view.on_draw(|canvas| {
+///     // Draw on `canvas`.
+/// });
+///
+/// let mut view_dispatcher = ViewDispatcher::new();
+/// view_dispatcher.add_view(MAIN, view);
+/// view_dispatcher.attach_to_gui(ViewDispatcherType::Fullscreen);
+/// view_dispatcher.switch_to(MAIN);
+/// view_dispatcher.run();
+/// ```
+pub struct ViewDispatcher {
+    raw: NonNull<sys::ViewDispatcher>,
+    callbacks: NonNull<Callbacks>,
+    views: Vec<(u32, Box<dyn AsView>)>,
+    shared: Arc<Mutex<SharedDispatcher>>,
+    gui: Option<Record<sys::Gui>>,
+}
+
+impl ViewDispatcher {
+    /// Creates a view dispatcher without views, which is not attached to the GUI.
+    pub fn new() -> Self {
+        let raw = unsafe { NonNull::new_unchecked(sys::view_dispatcher_alloc()) };
+        let callbacks = Box::new(Callbacks {
+            dispatcher: ViewDispatcherRef { raw },
+            custom_event: None,
+            navigation: None,
+        });
+        let callbacks = unsafe { NonNull::new_unchecked(Box::into_raw(callbacks)) };
+
+        unsafe extern "C" fn custom_event(context: *mut c_void, event: u32) -> bool {
+            let callbacks = unsafe { &mut *(context as *mut Callbacks) };
+            match callbacks.custom_event.as_mut() {
+                Some(callback) => callback(&callbacks.dispatcher, event),
+                None => false,
+            }
+        }
+
+        unsafe extern "C" fn navigation(context: *mut c_void) -> bool {
+            let callbacks = unsafe { &mut *(context as *mut Callbacks) };
+            match callbacks.navigation.as_mut() {
+                Some(callback) => callback(&callbacks.dispatcher),
+                None => false,
+            }
+        }
+
+        unsafe {
+            sys::view_dispatcher_set_event_callback_context(
+                raw.as_ptr(),
+                callbacks.as_ptr() as *mut c_void,
+            );
+            sys::view_dispatcher_set_custom_event_callback(raw.as_ptr(), Some(custom_event));
+            sys::view_dispatcher_set_navigation_event_callback(raw.as_ptr(), Some(navigation));
+        }
+
+        Self {
+            raw,
+            callbacks,
+            views: Vec::new(),
+            shared: Arc::new(Mutex::new(SharedDispatcher(Some(raw)))),
+            gui: None,
+        }
+    }
+
+    /// Returns the raw pointer to the view dispatcher, for use with the `sys` bindings.
+    pub fn as_ptr(&self) -> *mut sys::ViewDispatcher {
+        self.raw.as_ptr()
+    }
+
+    /// Returns the reference to the view dispatcher that its event closures are passed.
+    pub(crate) fn get_ref(&self) -> &ViewDispatcherRef {
+        unsafe { &self.callbacks.as_ref().dispatcher }
+    }
+
+    /// Registers `view` under `id`, replacing any view already registered under it.
+    pub fn add_view<V: AsView + 'static>(&mut self, id: u32, view: V) {
+        drop(self.remove_view(id));
+        unsafe { sys::view_dispatcher_add_view(self.raw.as_ptr(), id, view.as_view_ptr()) };
+        self.views.push((id, Box::new(view)));
+    }
+
+    /// Unregisters the view with the given `id` and returns it, if there is one.
+    ///
+    /// If the view is currently shown, no view is shown afterwards.
+    pub fn remove_view(&mut self, id: u32) -> Option<Box<dyn AsView>> {
+        let index = self.views.iter().position(|(view_id, _)| *view_id == id)?;
+        unsafe { sys::view_dispatcher_remove_view(self.raw.as_ptr(), id) };
+        Some(self.views.swap_remove(index).1)
+    }
+
+    /// Shows the view with the given `id`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no view is registered under `id`.
+    pub fn switch_to(&mut self, id: u32) {
+        assert!(
+            self.views.iter().any(|(view_id, _)| *view_id == id),
+            "no view with this ID"
+        );
+        unsafe { sys::view_dispatcher_switch_to_view(self.raw.as_ptr(), id) };
+    }
+
+    /// Sends a custom event, which is handled by the closure set with
+    /// [`ViewDispatcher::on_custom_event`] once the event loop is running.
+    pub fn send_custom_event(&self, event: u32) {
+        unsafe { sys::view_dispatcher_send_custom_event(self.raw.as_ptr(), event) };
+    }
+
+    /// Returns a handle for sending custom events from other threads or closures.
+    pub fn custom_event_sender(&self) -> CustomEventSender {
+        CustomEventSender {
+            shared: self.shared.clone(),
+        }
+    }
+
+    /// Sets the closure which handles custom events.
+    ///
+    /// The closure is called on the thread running the view dispatcher, and returns
+    /// whether it handled the event.
+    pub fn on_custom_event<F>(&mut self, callback: F)
+    where
+        F: FnMut(&ViewDispatcherRef, u32) -> bool + 'static,
+    {
+        unsafe { (*self.callbacks.as_ptr()).custom_event = Some(Box::new(callback)) };
+    }
+
+    /// Sets the closure which handles Back presses that the current view didn't handle.
+    ///
+    /// The closure is called on the thread running the view dispatcher, and returns
+    /// whether it handled the event, typically by switching to the previous view. Back
+    /// presses that are not handled stop the view dispatcher, which is also what happens
+    /// if no closure is set.
+    pub fn on_navigation<F>(&mut self, callback: F)
+    where
+        F: FnMut(&ViewDispatcherRef) -> bool + 'static,
+    {
+        unsafe { (*self.callbacks.as_ptr()).navigation = Some(Box::new(callback)) };
+    }
+
+    /// Attaches the view dispatcher to the GUI.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the view dispatcher is already attached.
+    pub fn attach_to_gui(&mut self, kind: ViewDispatcherType) {
+        assert!(self.gui.is_none(), "view dispatcher is already attached");
+
+        let gui = Record::open();
+        unsafe {
+            sys::view_dispatcher_attach_to_gui(self.raw.as_ptr(), gui.as_ptr(), kind.to_sys())
+        };
+        self.gui = Some(gui);
+    }
+
+    /// Runs the event loop on the calling thread until the view dispatcher is stopped.
+    pub fn run(&mut self) {
+        unsafe { sys::view_dispatcher_run(self.raw.as_ptr()) };
+    }
+
+    /// Stops the view dispatcher, which makes [`ViewDispatcher::run`] return once it has
+    /// handled the pending events.
+    pub fn stop(&self) {
+        unsafe { sys::view_dispatcher_stop(self.raw.as_ptr()) };
+    }
+}
+
+impl Drop for ViewDispatcher {
+    fn drop(&mut self) {
+        // Senders must no longer use the view dispatcher after it is freed.
+        self.shared.lock().0 = None;
+
+        // The order matters: views must be removed before the view dispatcher is freed,
+        // which also removes it from the GUI, and may only be freed afterwards.
+        for (id, _) in &self.views {
+            unsafe { sys::view_dispatcher_remove_view(self.raw.as_ptr(), *id) };
+        }
+        unsafe {
+            sys::view_dispatcher_free(self.raw.as_ptr());
+            drop(Box::from_raw(self.callbacks.as_ptr()));
+        }
+        // The views are freed after this.
+    }
+}
+
+impl Default for ViewDispatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A reference to a [`ViewDispatcher`] passed to its event closures.
+pub struct ViewDispatcherRef {
+    raw: NonNull<sys::ViewDispatcher>,
+}
+
+impl ViewDispatcherRef {
+    /// Shows the view with the given `id`.
+    ///
+    /// The firmware crashes if no view is registered under `id`.
+    pub fn switch_to(&self, id: u32) {
+        unsafe { sys::view_dispatcher_switch_to_view(self.raw.as_ptr(), id) };
+    }
+
+    /// Sends a custom event, which is handled after the current event.
+    pub fn send_custom_event(&self, event: u32) {
+        unsafe { sys::view_dispatcher_send_custom_event(self.raw.as_ptr(), event) };
+    }
+
+    /// Stops the view dispatcher.
+    pub fn stop(&self) {
+        unsafe { sys::view_dispatcher_stop(self.raw.as_ptr()) };
+    }
+}
+
+/// A handle for sending custom events to a [`ViewDispatcher`], which can be moved to
+/// other threads.
+#[derive(Clone)]
+pub struct CustomEventSender {
+    shared: Arc<Mutex<SharedDispatcher>>,
+}
+
+impl CustomEventSender {
+    /// Sends a custom event to the view dispatcher.
+    ///
+    /// Returns `false` if the view dispatcher no longer exists.
+    pub fn send(&self, event: u32) -> bool {
+        match self.shared.lock().0 {
+            Some(raw) => {
+                unsafe { sys::view_dispatcher_send_custom_event(raw.as_ptr(), event) };
+                true
+            }
+            None => false,
+        }
+    }
+}
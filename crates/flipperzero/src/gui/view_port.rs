@@ -0,0 +1,186 @@
+//! ViewPorts.
+
+use alloc::boxed::Box;
+use core::ffi::c_void;
+use core::ptr::{self, NonNull};
+
+use flipperzero_sys as sys;
+
+use crate::furi::record::Record;
+use crate::gui::canvas::Canvas;
+use crate::input::InputEvent;
+
+type DrawCallback = Box<dyn FnMut(&mut Canvas) + Send>;
+type InputCallback = Box<dyn FnMut(InputEvent) + Send>;
+
+/// A layer of the GUI that a [`ViewPort`] can be attached to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GuiLayer {
+    /// The desktop layer, used by the desktop app.
+    Desktop,
+    /// The window layer, below the status bar.
+    Window,
+    /// The left side of the status bar.
+    StatusBarLeft,
+    /// The right side of the status bar.
+    StatusBarRight,
+    /// The whole screen, above the other layers.
+    Fullscreen,
+}
+
+impl GuiLayer {
+    pub fn to_sys(&self) -> sys::GuiLayer {
+        match self {
+            Self::Desktop => sys::GuiLayer_GuiLayerDesktop,
+            Self::Window => sys::GuiLayer_GuiLayerWindow,
+            Self::StatusBarLeft => sys::GuiLayer_GuiLayerStatusBarLeft,
+            Self::StatusBarRight => sys::GuiLayer_GuiLayerStatusBarRight,
+            Self::Fullscreen => sys::GuiLayer_GuiLayerFullscreen,
+        }
+    }
+}
+
+/// A region of the screen that is drawn by a closure and receives input events.
+///
+/// The draw and input callbacks are called on the GUI thread, which is why they need to
+/// be [`Send`]. The GUI holds the view port's lock while calling them, and replacing a
+/// callback waits for a running call to complete.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use flipperzero::gui::{GuiLayer, ViewPort};
+/// let mut view_port = ViewPort::new();
+/// view_port.on_draw(|canvas| {
+///     // Draw on `canvas`.
+/// });
+/// view_port.attach_to_gui(GuiLayer::Fullscreen);
+/// ```
+pub struct ViewPort {
+    raw: NonNull<sys::ViewPort>,
+    // Boxed twice for a thin pointer, see `furi::thread::Builder::spawn`.
+    draw: Option<Box<DrawCallback>>,
+    input: Option<Box<InputCallback>>,
+    gui: Option<Record<sys::Gui>>,
+}
+
+impl ViewPort {
+    /// Creates a view port without callbacks, which is not attached to the GUI.
+    pub fn new() -> Self {
+        Self {
+            raw: unsafe { NonNull::new_unchecked(sys::view_port_alloc()) },
+            draw: None,
+            input: None,
+            gui: None,
+        }
+    }
+
+    /// Returns the raw pointer to the view port, for use with the `sys` bindings.
+    pub fn as_ptr(&self) -> *mut sys::ViewPort {
+        self.raw.as_ptr()
+    }
+
+    /// Sets the closure which draws the view port.
+    pub fn on_draw<F>(&mut self, callback: F)
+    where
+        F: FnMut(&mut Canvas) + Send + 'static,
+    {
+        unsafe extern "C" fn draw(canvas: *mut sys::Canvas, context: *mut c_void) {
+            let draw = unsafe { &mut *(context as *mut DrawCallback) };
+            // SAFETY: the canvas is only used by us until the callback returns.
+            draw(&mut unsafe { Canvas::from_raw(canvas) });
+        }
+
+        // Unset the callback first, which waits for a running call to finish, so that
+        // the previous closure can be dropped.
+        unsafe { sys::view_port_draw_callback_set(self.raw.as_ptr(), None, ptr::null_mut()) };
+        let callback: &mut DrawCallback = self.draw.insert(Box::new(Box::new(callback)));
+        unsafe {
+            sys::view_port_draw_callback_set(
+                self.raw.as_ptr(),
+                Some(draw),
+                callback as *mut DrawCallback as *mut c_void,
+            )
+        };
+    }
+
+    /// Sets the closure which handles input events while the view port is enabled and
+    /// visible.
+    pub fn on_input<F>(&mut self, callback: F)
+    where
+        F: FnMut(InputEvent) + Send + 'static,
+    {
+        unsafe extern "C" fn input(event: *mut sys::InputEvent, context: *mut c_void) {
+            let input = unsafe { &mut *(context as *mut InputCallback) };
+            input(InputEvent::from_sys(unsafe { &*event }));
+        }
+
+        // Unset the callback first, which waits for a running call to finish, so that
+        // the previous closure can be dropped.
+        unsafe { sys::view_port_input_callback_set(self.raw.as_ptr(), None, ptr::null_mut()) };
+        let callback: &mut InputCallback = self.input.insert(Box::new(Box::new(callback)));
+        unsafe {
+            sys::view_port_input_callback_set(
+                self.raw.as_ptr(),
+                Some(input),
+                callback as *mut InputCallback as *mut c_void,
+            )
+        };
+    }
+
+    /// Attaches the view port to the given layer of the GUI, detaching it first if it
+    /// is already attached.
+    pub fn attach_to_gui(&mut self, layer: GuiLayer) {
+        self.detach_from_gui();
+
+        let gui = Record::open();
+        unsafe { sys::gui_add_view_port(gui.as_ptr(), self.raw.as_ptr(), layer.to_sys()) };
+        self.gui = Some(gui);
+    }
+
+    /// Detaches the view port from the GUI, if it is attached.
+    pub fn detach_from_gui(&mut self) {
+        if let Some(gui) = self.gui.take() {
+            unsafe { sys::gui_remove_view_port(gui.as_ptr(), self.raw.as_ptr()) };
+        }
+    }
+
+    /// Returns `true` if the view port is attached to the GUI.
+    pub fn is_attached(&self) -> bool {
+        self.gui.is_some()
+    }
+
+    /// Enables or disables the view port.
+    ///
+    /// Disabled view ports are neither drawn nor receive input events.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        unsafe { sys::view_port_enabled_set(self.raw.as_ptr(), enabled) };
+    }
+
+    /// Returns `true` if the view port is enabled.
+    pub fn is_enabled(&self) -> bool {
+        unsafe { sys::view_port_is_enabled(self.raw.as_ptr()) }
+    }
+
+    /// Requests the view port to be redrawn.
+    pub fn update(&self) {
+        unsafe { sys::view_port_update(self.raw.as_ptr()) };
+    }
+}
+
+impl Drop for ViewPort {
+    fn drop(&mut self) {
+        // The order matters: the view port must not be drawn while it is being removed,
+        // and must be removed from the GUI before it is freed.
+        self.set_enabled(false);
+        self.detach_from_gui();
+        unsafe { sys::view_port_free(self.raw.as_ptr()) };
+        // The callbacks are dropped after this, once they can no longer be called.
+    }
+}
+
+impl Default for ViewPort {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,258 @@
+//! Widgets.
+
+use alloc::{boxed::Box, vec::Vec};
+use core::ffi::{c_void, CStr};
+use core::ptr::NonNull;
+
+use flipperzero_sys as sys;
+
+use crate::gui::canvas::{Align, Font};
+use crate::gui::view::AsView;
+use crate::input::Kind;
+
+type ButtonCallback = Box<dyn FnMut(GuiButton, Kind)>;
+
+/// A button at the bottom of a [`Widget`], which is pressed with the key of the same
+/// name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GuiButton {
+    Left,
+    Center,
+    Right,
+}
+
+impl GuiButton {
+    pub fn from_sys(button: sys::GuiButtonType) -> Option<Self> {
+        match button {
+            sys::GuiButtonType_GuiButtonTypeLeft => Some(Self::Left),
+            sys::GuiButtonType_GuiButtonTypeCenter => Some(Self::Center),
+            sys::GuiButtonType_GuiButtonTypeRight => Some(Self::Right),
+            _ => None,
+        }
+    }
+
+    pub fn to_sys(&self) -> sys::GuiButtonType {
+        match self {
+            Self::Left => sys::GuiButtonType_GuiButtonTypeLeft,
+            Self::Center => sys::GuiButtonType_GuiButtonTypeCenter,
+            Self::Right => sys::GuiButtonType_GuiButtonTypeRight,
+        }
+    }
+}
+
+/// The firmware's view made of elements such as strings, text boxes and buttons, which
+/// are drawn in the order they were added.
+///
+/// Positions are in pixels from the top left corner of the screen. Texts are copied
+/// into the widget. The button closures are called on the thread running the view
+/// dispatcher the widget has been added to.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use flipperzero::gui::canvas::{Align, Font};
+/// # use flipperzero::gui::{GuiButton, ViewDispatcher, Widget};
+/// # use flipperzero::input::Kind;
+/// const RESULT: u32 = 0;
+///
+/// let mut view_dispatcher = ViewDispatcher::new();
+/// let sender = view_dispatcher.custom_event_sender();
+/// let mut widget = Widget::new();
+/// widget.add_string(64, 20, Align::Center, Align::Center, Font::Primary, c"Saved!");
+/// widget.add_button(GuiButton::Center, c"OK", move |_, kind| {
+///     if kind == Kind::Short {
+///         sender.send(0);
+///     }
+/// });
+/// view_dispatcher.add_view(RESULT, widget);
+/// ```
+pub struct Widget {
+    raw: NonNull<sys::Widget>,
+    // Boxed twice for a thin pointer, see `furi::thread::Builder::spawn`.
+    // The outer box also keeps the pointer valid when the vector grows.
+    #[allow(clippy::vec_box)]
+    buttons: Vec<Box<ButtonCallback>>,
+}
+
+impl Widget {
+    /// Creates a widget without elements.
+    pub fn new() -> Self {
+        Self {
+            raw: unsafe { NonNull::new_unchecked(sys::widget_alloc()) },
+            buttons: Vec::new(),
+        }
+    }
+
+    /// Returns the raw pointer to the widget, for use with the `sys` bindings.
+    pub fn as_ptr(&self) -> *mut sys::Widget {
+        self.raw.as_ptr()
+    }
+
+    /// Adds a single line of text, aligned to `(x, y)`.
+    pub fn add_string(
+        &mut self,
+        x: u8,
+        y: u8,
+        horizontal: Align,
+        vertical: Align,
+        font: Font,
+        text: &CStr,
+    ) {
+        unsafe {
+            sys::widget_add_string_element(
+                self.raw.as_ptr(),
+                x,
+                y,
+                horizontal.to_sys(),
+                vertical.to_sys(),
+                font.to_sys(),
+                text.as_ptr(),
+            )
+        };
+    }
+
+    /// Adds text which may span several lines, aligned to `(x, y)`.
+    pub fn add_string_multiline(
+        &mut self,
+        x: u8,
+        y: u8,
+        horizontal: Align,
+        vertical: Align,
+        font: Font,
+        text: &CStr,
+    ) {
+        unsafe {
+            sys::widget_add_string_multiline_element(
+                self.raw.as_ptr(),
+                x,
+                y,
+                horizontal.to_sys(),
+                vertical.to_sys(),
+                font.to_sys(),
+                text.as_ptr(),
+            )
+        };
+    }
+
+    /// Adds text which is wrapped to fit the given box, and aligned within it.
+    ///
+    /// Lines starting with `\e#` are drawn in bold, `\e*` in a monospaced font and `\e!`
+    /// inverted, until the next `\e`. If `strip_to_dots` is `true`, text that doesn't fit
+    /// ends with `...`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_text_box(
+        &mut self,
+        x: u8,
+        y: u8,
+        width: u8,
+        height: u8,
+        horizontal: Align,
+        vertical: Align,
+        text: &CStr,
+        strip_to_dots: bool,
+    ) {
+        unsafe {
+            sys::widget_add_text_box_element(
+                self.raw.as_ptr(),
+                x,
+                y,
+                width,
+                height,
+                horizontal.to_sys(),
+                vertical.to_sys(),
+                text.as_ptr(),
+                strip_to_dots,
+            )
+        };
+    }
+
+    /// Adds text which is wrapped to the width of the given box, and can be scrolled
+    /// with Up and Down if it is taller.
+    pub fn add_text_scroll(&mut self, x: u8, y: u8, width: u8, height: u8, text: &CStr) {
+        unsafe {
+            sys::widget_add_text_scroll_element(
+                self.raw.as_ptr(),
+                x,
+                y,
+                width,
+                height,
+                text.as_ptr(),
+            )
+        };
+    }
+
+    /// Adds the outline of a rectangle with corners rounded by `radius`.
+    pub fn add_frame(&mut self, x: u8, y: u8, width: u8, height: u8, radius: u8) {
+        unsafe { sys::widget_add_frame_element(self.raw.as_ptr(), x, y, width, height, radius) };
+    }
+
+    /// Adds a button with a label, which calls `callback` with the button and the kind
+    /// of the key press when the key of the same name is pressed or released.
+    pub fn add_button<F>(&mut self, button: GuiButton, label: &CStr, callback: F)
+    where
+        F: FnMut(GuiButton, Kind) + 'static,
+    {
+        unsafe extern "C" fn run_callback(
+            button: sys::GuiButtonType,
+            kind: sys::InputType,
+            context: *mut c_void,
+        ) {
+            let callback = unsafe { &mut *(context as *mut ButtonCallback) };
+            if let Some(button) = GuiButton::from_sys(button) {
+                callback(button, Kind::from_sys(kind));
+            }
+        }
+
+        let mut callback: Box<ButtonCallback> = Box::new(Box::new(callback));
+        unsafe {
+            sys::widget_add_button_element(
+                self.raw.as_ptr(),
+                button.to_sys(),
+                label.as_ptr(),
+                Some(run_callback),
+                &mut *callback as *mut ButtonCallback as *mut c_void,
+            )
+        };
+        self.buttons.push(callback);
+    }
+
+    /// Removes all elements.
+    pub fn reset(&mut self) {
+        unsafe { sys::widget_reset(self.raw.as_ptr()) };
+        self.buttons.clear();
+    }
+}
+
+unsafe impl AsView for Widget {
+    fn as_view_ptr(&self) -> *mut sys::View {
+        unsafe { sys::widget_get_view(self.raw.as_ptr()) }
+    }
+}
+
+impl Drop for Widget {
+    fn drop(&mut self) {
+        unsafe { sys::widget_free(self.raw.as_ptr()) };
+        // The button closures are dropped after this, once they can no longer be called.
+    }
+}
+
+impl Default for Widget {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use flipperzero_sys as sys;
+
+    use super::GuiButton;
+
+    #[test]
+    fn buttons_map_to_and_from_sys() {
+        for button in [GuiButton::Left, GuiButton::Center, GuiButton::Right] {
+            assert_eq!(GuiButton::from_sys(button.to_sys()), Some(button));
+        }
+        assert!(GuiButton::from_sys(sys::GuiButtonType_GuiButtonTypeRight + 1).is_none());
+    }
+}
@@ -0,0 +1,466 @@
+//! Reading signals from `.ir` files.
+
+use core::ffi::CStr;
+
+use super::{Error, Message, Protocol};
+use crate::io::{Read, Seek, SeekFrom};
+
+/// File types of the files that the Infrared app saves signals in.
+pub(crate) const FILETYPES: [&[u8]; 2] = [b"IR signals file", b"IR library file"];
+/// Longest key or value that is read, other than the timings of a raw signal.
+const VALUE_MAX: usize = 64;
+const BUF_SIZE: usize = 256;
+
+/// The signal that [`Parser::find`] found.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Found {
+    Parsed(Message),
+    Raw {
+        frequency: u32,
+        duty_cycle: f32,
+        /// Position of the timings in the file, which the parser is at.
+        data: u64,
+    },
+}
+
+/// The fields of a signal that have been read so far.
+#[derive(Default)]
+struct Fields {
+    raw: Option<bool>,
+    protocol: Option<Protocol>,
+    address: Option<u32>,
+    command: Option<u32>,
+    frequency: Option<u32>,
+    duty_cycle: Option<f32>,
+}
+
+/// Reads `.ir` files line by line, streaming the timings of raw signals instead of
+/// reading them into memory.
+pub(crate) struct Parser<R> {
+    reader: R,
+    buf: [u8; BUF_SIZE],
+    pos: usize,
+    len: usize,
+    /// Position of `buf` in the file.
+    offset: u64,
+}
+
+impl<R: Read + Seek> Parser<R> {
+    pub(crate) fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buf: [0; BUF_SIZE],
+            pos: 0,
+            len: 0,
+            offset: 0,
+        }
+    }
+
+    /// Checks that the file is an infrared signal file.
+    pub(crate) fn read_header(&mut self) -> Result<(), Error> {
+        let mut key = [0; VALUE_MAX];
+        let mut value = [0; VALUE_MAX];
+        if self.next_key(&mut key)? != Some(b"Filetype")
+            || !FILETYPES.contains(&self.read_value(&mut value)?)
+            || self.next_key(&mut key)? != Some(b"Version")
+            || self.read_value(&mut value)? != b"1"
+        {
+            return Err(Error::InvalidFile);
+        }
+        Ok(())
+    }
+
+    /// Finds the signal called `name`, or the first signal if `name` is `None`.
+    ///
+    /// For raw signals, the parser is left at their timings, which can then be read
+    /// with [`Parser::next_timing`].
+    pub(crate) fn find(&mut self, name: Option<&[u8]>) -> Result<Found, Error> {
+        let mut key = [0; VALUE_MAX];
+        let mut value = [0; VALUE_MAX];
+        let mut signal: Option<Fields> = None;
+
+        while let Some(key) = self.next_key(&mut key)? {
+            if key == b"name" {
+                let value = self.read_value(&mut value)?;
+                if signal.is_some() {
+                    // The signal ended before all its fields were read.
+                    return Err(Error::InvalidFile);
+                }
+                if name.map_or(true, |name| name == value) {
+                    signal = Some(Fields::default());
+                }
+                continue;
+            }
+            let Some(fields) = signal.as_mut() else {
+                self.skip_line()?;
+                continue;
+            };
+
+            if key == b"data" {
+                return match fields {
+                    Fields {
+                        raw: Some(true),
+                        frequency: Some(frequency),
+                        duty_cycle: Some(duty_cycle),
+                        ..
+                    } => Ok(Found::Raw {
+                        frequency: *frequency,
+                        duty_cycle: *duty_cycle,
+                        data: self.position(),
+                    }),
+                    _ => Err(Error::InvalidFile),
+                };
+            }
+
+            let value = self.read_value(&mut value)?;
+            match key {
+                b"type" => {
+                    fields.raw = Some(match value {
+                        b"parsed" => false,
+                        b"raw" => true,
+                        _ => return Err(Error::InvalidFile),
+                    })
+                }
+                b"protocol" => fields.protocol = Some(parse_protocol(value)?),
+                b"address" => fields.address = Some(parse_hex_le(value)?),
+                b"command" => fields.command = Some(parse_hex_le(value)?),
+                b"frequency" => fields.frequency = Some(parse(value)?),
+                b"duty_cycle" => fields.duty_cycle = Some(parse(value)?),
+                _ => {}
+            }
+
+            if let Fields {
+                raw: Some(false),
+                protocol: Some(protocol),
+                address: Some(address),
+                command: Some(command),
+                ..
+            } = *fields
+            {
+                return Ok(Found::Parsed(Message {
+                    protocol,
+                    address,
+                    command,
+                }));
+            }
+        }
+
+        Err(if signal.is_some() {
+            Error::InvalidFile
+        } else {
+            Error::NotFound
+        })
+    }
+
+    /// Reads the next timing of the raw signal the parser is at, returning `None`
+    /// at the end of the signal.
+    pub(crate) fn next_timing(&mut self) -> Result<Option<u32>, Error> {
+        loop {
+            match self.peek()? {
+                Some(b' ' | b'\r') => self.pos += 1,
+                Some(b'\n') => {
+                    self.pos += 1;
+                    return Ok(None);
+                }
+                Some(b'0'..=b'9') => break,
+                Some(_) => return Err(Error::InvalidFile),
+                None => return Ok(None),
+            }
+        }
+
+        let mut timing: u32 = 0;
+        while let Some(digit @ b'0'..=b'9') = self.peek()? {
+            timing = timing
+                .checked_mul(10)
+                .and_then(|timing| timing.checked_add(u32::from(digit - b'0')))
+                .ok_or(Error::InvalidFile)?;
+            self.pos += 1;
+        }
+        Ok(Some(timing))
+    }
+
+    /// Moves the parser back to `position`, such as the timings of a raw signal.
+    pub(crate) fn rewind_to(&mut self, position: u64) -> Result<(), Error> {
+        self.reader.seek(SeekFrom::Start(position))?;
+        self.offset = position;
+        self.pos = 0;
+        self.len = 0;
+        Ok(())
+    }
+
+    fn position(&self) -> u64 {
+        self.offset + self.pos as u64
+    }
+
+    fn peek(&mut self) -> Result<Option<u8>, Error> {
+        if self.pos == self.len {
+            self.offset += self.len as u64;
+            self.pos = 0;
+            self.len = self.reader.read(&mut self.buf)?;
+            if self.len == 0 {
+                return Ok(None);
+            }
+        }
+        Ok(Some(self.buf[self.pos]))
+    }
+
+    fn next_byte(&mut self) -> Result<Option<u8>, Error> {
+        let byte = self.peek()?;
+        if byte.is_some() {
+            self.pos += 1;
+        }
+        Ok(byte)
+    }
+
+    fn skip_line(&mut self) -> Result<(), Error> {
+        while let Some(byte) = self.next_byte()? {
+            if byte == b'\n' {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the key of the next line into `key`, skipping empty lines and comments.
+    ///
+    /// Returns `None` at the end of the file.
+    fn next_key<'k>(&mut self, key: &'k mut [u8; VALUE_MAX]) -> Result<Option<&'k [u8]>, Error> {
+        while let Some(b'#' | b'\r' | b'\n') = self.peek()? {
+            self.skip_line()?;
+        }
+        if self.peek()?.is_none() {
+            return Ok(None);
+        }
+
+        let mut len = 0;
+        loop {
+            match self.next_byte()? {
+                Some(b':') => break,
+                Some(b'\n') | None => return Err(Error::InvalidFile),
+                Some(_) if len == VALUE_MAX => return Err(Error::InvalidFile),
+                Some(byte) => {
+                    key[len] = byte;
+                    len += 1;
+                }
+            }
+        }
+        if self.peek()? == Some(b' ') {
+            self.pos += 1;
+        }
+        Ok(Some(&key[..len]))
+    }
+
+    /// Reads the rest of the line into `value`.
+    fn read_value<'v>(&mut self, value: &'v mut [u8; VALUE_MAX]) -> Result<&'v [u8], Error> {
+        let mut len = 0;
+        loop {
+            match self.next_byte()? {
+                Some(b'\n') | None => break,
+                Some(_) if len == VALUE_MAX => return Err(Error::InvalidFile),
+                Some(byte) => {
+                    value[len] = byte;
+                    len += 1;
+                }
+            }
+        }
+        Ok(value[..len].trim_ascii_end())
+    }
+}
+
+fn parse_protocol(name: &[u8]) -> Result<Protocol, Error> {
+    let mut buf = [0; VALUE_MAX + 1];
+    buf[..name.len()].copy_from_slice(name);
+    let name = CStr::from_bytes_until_nul(&buf).map_err(|_| Error::InvalidFile)?;
+    Protocol::from_name(name)
+}
+
+/// Parses a value such as `07 00 00 00`, whose bytes are in little-endian order.
+pub(crate) fn parse_hex_le(value: &[u8]) -> Result<u32, Error> {
+    let mut bytes = [0; 4];
+    let mut tokens = value
+        .split(|&b| b == b' ')
+        .filter(|token| !token.is_empty());
+    for (byte, token) in bytes.iter_mut().zip(&mut tokens) {
+        let token = core::str::from_utf8(token).map_err(|_| Error::InvalidFile)?;
+        *byte = u8::from_str_radix(token, 16).map_err(|_| Error::InvalidFile)?;
+    }
+    if tokens.next().is_some() {
+        return Err(Error::InvalidFile);
+    }
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn parse<T: core::str::FromStr>(value: &[u8]) -> Result<T, Error> {
+    core::str::from_utf8(value)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .ok_or(Error::InvalidFile)
+}
+
+#[cfg(feature = "alloc")]
+#[flipperzero_test::tests]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::{Found, Parser};
+    use crate::infrared::{check_carrier, Error, Message, Protocol};
+    use crate::io::cursor::Cursor;
+
+    const FILE: &[u8] = b"Filetype: IR signals file\n\
+        Version: 1\n\
+        # \n\
+        name: POWER\n\
+        type: parsed\n\
+        protocol: NEC\n\
+        address: 07 00 00 00\n\
+        command: 02 01 00 00\n\
+        # \n\
+        name: Vol_up\n\
+        type: raw\n\
+        frequency: 38000\n\
+        duty_cycle: 0.330000\n\
+        data: 9024 4512 579 552\n";
+
+    fn parser(data: &[u8]) -> Parser<Cursor<&[u8]>> {
+        // Short reads exercise the buffering.
+        let mut parser = Parser::new(Cursor::new(data).max(7));
+        parser.read_header().unwrap();
+        parser
+    }
+
+    fn nec() -> Protocol {
+        Protocol::from_name(c"NEC").unwrap()
+    }
+
+    fn timings(parser: &mut Parser<Cursor<&[u8]>>) -> Vec<u32> {
+        let mut timings = Vec::new();
+        while let Some(timing) = parser.next_timing().unwrap() {
+            timings.push(timing);
+        }
+        timings
+    }
+
+    #[test]
+    fn finds_parsed_signal() {
+        let found = parser(FILE).find(Some(b"POWER")).unwrap();
+        assert_eq!(
+            found,
+            Found::Parsed(Message {
+                protocol: nec(),
+                address: 0x07,
+                command: 0x0102,
+            })
+        );
+    }
+
+    #[test]
+    fn finds_first_signal() {
+        assert!(matches!(
+            parser(FILE).find(None),
+            Ok(Found::Parsed(Message { address: 0x07, .. }))
+        ));
+    }
+
+    #[test]
+    fn streams_raw_timings() {
+        let mut parser = parser(FILE);
+        let Ok(Found::Raw {
+            frequency,
+            duty_cycle,
+            data,
+        }) = parser.find(Some(b"Vol_up"))
+        else {
+            panic!("raw signal not found");
+        };
+        assert_eq!(frequency, 38000);
+        assert!(duty_cycle > 0.329 && duty_cycle < 0.331);
+        assert_eq!(timings(&mut parser), [9024, 4512, 579, 552]);
+
+        parser.rewind_to(data).unwrap();
+        assert_eq!(timings(&mut parser), [9024, 4512, 579, 552]);
+    }
+
+    #[test]
+    fn skips_long_raw_signals() {
+        let mut file = Vec::from(
+            &b"Filetype: IR signals file\nVersion: 1\nname: Long\ntype: raw\n\
+            frequency: 38000\nduty_cycle: 0.33\ndata:"[..],
+        );
+        for _ in 0..1000 {
+            file.extend_from_slice(b" 560 1690");
+        }
+        file.extend_from_slice(b"\n");
+        file.extend_from_slice(&FILE[FILE.iter().position(|&b| b == b'#').unwrap()..]);
+
+        let mut parser = parser(&file);
+        assert!(matches!(
+            parser.find(Some(b"Vol_up")),
+            Ok(Found::Raw { .. })
+        ));
+
+        let mut parser = self::parser(&file);
+        assert!(matches!(parser.find(Some(b"Long")), Ok(Found::Raw { .. })));
+        assert_eq!(timings(&mut parser).len(), 2000);
+    }
+
+    #[test]
+    fn missing_signal() {
+        assert!(matches!(
+            parser(FILE).find(Some(b"MUTE")),
+            Err(Error::NotFound)
+        ));
+    }
+
+    #[test]
+    fn unknown_protocol() {
+        let file = b"Filetype: IR signals file\nVersion: 1\nname: POWER\ntype: parsed\n\
+            protocol: Morse\naddress: 00 00 00 00\ncommand: 00 00 00 00\n";
+        assert!(matches!(
+            parser(file).find(None),
+            Err(Error::UnknownProtocol)
+        ));
+    }
+
+    #[test]
+    fn incomplete_signal() {
+        let file = b"Filetype: IR signals file\nVersion: 1\nname: POWER\ntype: parsed\n\
+            protocol: NEC\nname: MUTE\n";
+        assert!(matches!(parser(file).find(None), Err(Error::InvalidFile)));
+
+        let file = b"Filetype: IR signals file\nVersion: 1\nname: POWER\ntype: raw\n\
+            data: 100 200\n";
+        assert!(matches!(parser(file).find(None), Err(Error::InvalidFile)));
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        let mut parser = Parser::new(Cursor::new(
+            b"Filetype: Flipper SubGhz Key File\nVersion: 1\n",
+        ));
+        assert!(matches!(parser.read_header(), Err(Error::InvalidFile)));
+    }
+
+    #[test]
+    fn checks_carrier() {
+        assert!(check_carrier(38000, 0.33).is_ok());
+        assert!(matches!(
+            check_carrier(100_000, 0.33),
+            Err(Error::FrequencyOutOfRange(100_000))
+        ));
+        assert!(matches!(
+            check_carrier(38000, 0.0),
+            Err(Error::DutyCycleOutOfRange)
+        ));
+    }
+
+    #[test]
+    fn protocols_match_the_firmware() {
+        assert_eq!(nec(), Protocol::NEC);
+        assert_eq!(Protocol::NEC.name(), c"NEC");
+        assert_eq!(Protocol::SAMSUNG32.name(), c"Samsung32");
+        assert_eq!(Protocol::RC5.name(), c"RC5");
+        assert_eq!(Protocol::RC6.name(), c"RC6");
+        assert_eq!(Protocol::NEC.address_bits(), 8);
+        assert_eq!(Protocol::NEC.command_bits(), 8);
+    }
+}
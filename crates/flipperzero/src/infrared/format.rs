@@ -0,0 +1,168 @@
+//! Reading and writing signals of `.ir` files with [`FlipperFormat`].
+
+use core::ffi::CStr;
+
+#[cfg(feature = "alloc")]
+use alloc::vec;
+
+#[cfg(feature = "alloc")]
+use super::file::FILETYPES;
+#[cfg(feature = "alloc")]
+use super::IrSignalBuf;
+use super::{check_carrier, Error, IrSignal};
+#[cfg(feature = "alloc")]
+use super::{Message, Protocol};
+use crate::storage::FlipperFormat;
+#[cfg(feature = "alloc")]
+use crate::storage::FormatError;
+
+/// File type of the remotes that the Infrared app saves.
+const SIGNALS_FILETYPE: &CStr = c"IR signals file";
+const VERSION: u32 = 1;
+
+/// Writes the header of a remote file to `file`.
+pub(crate) fn write_header(file: &mut FlipperFormat) -> Result<(), Error> {
+    file.write_header(SIGNALS_FILETYPE, VERSION)?;
+    Ok(())
+}
+
+/// Checks that `file` is an infrared signal file.
+#[cfg(feature = "alloc")]
+pub(crate) fn check_header(file: &mut FlipperFormat) -> Result<(), Error> {
+    let (file_type, version) = file.read_header()?;
+    if version != VERSION || !FILETYPES.contains(&file_type.as_c_str().to_bytes()) {
+        return Err(Error::InvalidFile);
+    }
+    Ok(())
+}
+
+/// Writes `signal` to `file` as the signal called `name`, in the format of the
+/// Infrared app.
+pub(crate) fn write_signal(
+    file: &mut FlipperFormat,
+    name: &CStr,
+    signal: &IrSignal<'_>,
+) -> Result<(), Error> {
+    if let IrSignal::Raw(raw) = signal {
+        check_carrier(raw.frequency, raw.duty_cycle)?;
+    }
+    // The Infrared app separates signals with empty comments.
+    file.write_comment(c"")?;
+    file.write_string(c"name", name)?;
+    match signal {
+        IrSignal::Parsed(message) => {
+            file.write_string(c"type", c"parsed")?;
+            file.write_string(c"protocol", message.protocol.name())?;
+            file.write_hex(c"address", &message.address.to_le_bytes())?;
+            file.write_hex(c"command", &message.command.to_le_bytes())?;
+        }
+        IrSignal::Raw(raw) => {
+            file.write_string(c"type", c"raw")?;
+            file.write_u32(c"frequency", raw.frequency)?;
+            file.write_f32s(c"duty_cycle", &[raw.duty_cycle])?;
+            file.write_u32s(c"data", raw.timings)?;
+        }
+    }
+    Ok(())
+}
+
+/// Reads the signal called `name` from `file`, or the next signal if `name` is `None`.
+#[cfg(feature = "alloc")]
+pub(crate) fn read_signal(
+    file: &mut FlipperFormat,
+    name: Option<&CStr>,
+) -> Result<IrSignalBuf, Error> {
+    loop {
+        let found = match file.read_string(c"name") {
+            Ok(found) => found,
+            Err(FormatError::MissingKey) => return Err(Error::NotFound),
+            Err(error) => return Err(error.into()),
+        };
+        if name.map_or(true, |name| found.as_c_str() == name) {
+            break;
+        }
+    }
+
+    let kind = file.read_string(c"type")?;
+    if kind == "parsed" {
+        let protocol = Protocol::from_name(file.read_string(c"protocol")?.as_c_str())?;
+        let mut address = [0; 4];
+        file.read_hex(c"address", &mut address)?;
+        let mut command = [0; 4];
+        file.read_hex(c"command", &mut command)?;
+        Ok(IrSignalBuf::Parsed(Message {
+            protocol,
+            address: u32::from_le_bytes(address),
+            command: u32::from_le_bytes(command),
+        }))
+    } else if kind == "raw" {
+        let frequency = file.read_u32(c"frequency")?;
+        let mut duty_cycle = [0.0];
+        file.read_f32s(c"duty_cycle", &mut duty_cycle)?;
+        let mut timings = vec![0; file.value_count(c"data")? as usize];
+        file.read_u32s(c"data", &mut timings)?;
+        Ok(IrSignalBuf::Raw {
+            frequency,
+            duty_cycle: duty_cycle[0],
+            timings,
+        })
+    } else {
+        Err(Error::InvalidFile)
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[flipperzero_test::tests]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::{check_header, read_signal, write_header, write_signal};
+    use crate::infrared::{Error, IrSignal, IrSignalBuf, Message, Protocol, RawSignal};
+    use crate::storage::FlipperFormat;
+
+    #[test]
+    fn reads_back_written_signals() {
+        let power = Message {
+            protocol: Protocol::SAMSUNG32,
+            address: 0x07,
+            command: 0x02,
+        };
+        let timings = [9024, 4512, 579, 552];
+        let vol_up = RawSignal {
+            frequency: 38000,
+            duty_cycle: 0.33,
+            timings: &timings,
+        };
+
+        let mut file = FlipperFormat::in_memory();
+        write_header(&mut file).unwrap();
+        write_signal(&mut file, c"POWER", &IrSignal::Parsed(power)).unwrap();
+        write_signal(&mut file, c"Vol_up", &IrSignal::Raw(vol_up)).unwrap();
+        assert!(matches!(
+            write_signal(
+                &mut file,
+                c"Bad",
+                &IrSignal::Raw(RawSignal {
+                    frequency: 100_000,
+                    ..vol_up
+                })
+            ),
+            Err(Error::FrequencyOutOfRange(100_000))
+        ));
+
+        file.rewind().unwrap();
+        check_header(&mut file).unwrap();
+        let expected = IrSignalBuf::Raw {
+            frequency: 38000,
+            duty_cycle: 0.33,
+            timings: Vec::from(&timings[..]),
+        };
+        assert_eq!(read_signal(&mut file, Some(c"Vol_up")).unwrap(), expected);
+        assert!(matches!(read_signal(&mut file, None), Err(Error::NotFound)));
+
+        file.rewind().unwrap();
+        let signal = read_signal(&mut file, None).unwrap();
+        assert_eq!(signal, IrSignalBuf::Parsed(power));
+        assert_eq!(signal.as_signal(), IrSignal::Parsed(power));
+    }
+}
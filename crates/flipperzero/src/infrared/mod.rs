@@ -0,0 +1,624 @@
+//! Infrared transmission and reception.
+//!
+//! Signals can be sent directly with [`send`] or [`send_signal`], or looked up by name
+//! in the `.ir` files that the Infrared app saves with [`send_file`]. With the `alloc`
+//! feature, a `Receiver` decodes the signals of remotes, which [`save_signal`] adds to
+//! `.ir` files that the Infrared app can open.
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::ffi::{c_void, CStr};
+use core::fmt;
+use core::ptr::NonNull;
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+use flipperzero_sys as sys;
+
+use crate::io;
+use crate::storage::{FlipperFormat, FormatError, OpenOptions};
+
+pub(crate) mod file;
+pub(crate) mod format;
+#[cfg(feature = "alloc")]
+pub(crate) mod receiver;
+
+use self::file::{Found, Parser};
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use self::receiver::{Received, Receiver};
+
+/// Lowest carrier frequency of raw signals, in Hz.
+pub const MIN_FREQUENCY: u32 = 10_000;
+/// Highest carrier frequency of raw signals, in Hz.
+pub const MAX_FREQUENCY: u32 = 56_000;
+
+/// Number of timings of a raw signal that are buffered ahead of the transmission.
+const RAW_BUFFER_LEN: usize = 512;
+/// Size of a timing in the raw signal buffer.
+const TIMING_SIZE: usize = core::mem::size_of::<u32>();
+/// Space that is sent when the next timing hasn't been read in time, in microseconds.
+const UNDERRUN_SPACE_US: u32 = 100;
+
+/// Reasons why sending a signal failed.
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub enum Error {
+    /// The signal uses a protocol that the firmware doesn't know.
+    UnknownProtocol,
+    /// The carrier frequency of a raw signal is outside of
+    /// [`MIN_FREQUENCY`]`..=`[`MAX_FREQUENCY`].
+    FrequencyOutOfRange(u32),
+    /// The duty cycle of a raw signal is not above 0 and at most 1.
+    DutyCycleOutOfRange,
+    /// The file is not an infrared signal file, or a signal in it is incomplete.
+    InvalidFile,
+    /// The file has no signal with the given name.
+    NotFound,
+    /// Infrared is in use by someone else.
+    Busy,
+    /// The cancel flag was set.
+    Cancelled,
+    /// A raw signal couldn't be read from the file as fast as it was sent, so it was
+    /// sent with gaps.
+    Underrun,
+    /// The file couldn't be read.
+    Io(io::Error),
+}
+
+impl Error {
+    /// Description associated with [`Error`].
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::UnknownProtocol => "unknown protocol",
+            Self::FrequencyOutOfRange(_) => "frequency out of range",
+            Self::DutyCycleOutOfRange => "duty cycle out of range",
+            Self::InvalidFile => "invalid signal file",
+            Self::NotFound => "signal not found",
+            Self::Busy => "infrared is busy",
+            Self::Cancelled => "transmission cancelled",
+            Self::Underrun => "signal underrun",
+            Self::Io(_) => "I/O error",
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<FormatError> for Error {
+    fn from(error: FormatError) -> Self {
+        match error {
+            FormatError::Io(error) => Self::Io(error),
+            FormatError::MissingKey | FormatError::InvalidValue => Self::InvalidFile,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => error.fmt(f),
+            _ => f.write_str(self.description()),
+        }
+    }
+}
+
+impl ufmt::uDisplay for Error {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        match self {
+            Self::Io(error) => ufmt::uDisplay::fmt(error, f),
+            _ => f.write_str(self.description()),
+        }
+    }
+}
+
+/// An infrared protocol known to the firmware, such as NEC or RC5.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Protocol(sys::InfraredProtocol);
+
+impl Protocol {
+    /// The NEC protocol, with 8-bit addresses and commands.
+    pub const NEC: Self = Self(sys::InfraredProtocol_InfraredProtocolNEC);
+    /// The extended NEC protocol, with 16-bit addresses.
+    pub const NEC_EXT: Self = Self(sys::InfraredProtocol_InfraredProtocolNECext);
+    /// The 42-bit NEC protocol, with 13-bit addresses.
+    pub const NEC42: Self = Self(sys::InfraredProtocol_InfraredProtocolNEC42);
+    /// The extended 42-bit NEC protocol, with 26-bit addresses and 16-bit commands.
+    pub const NEC42_EXT: Self = Self(sys::InfraredProtocol_InfraredProtocolNEC42ext);
+    /// The 32-bit protocol of Samsung.
+    pub const SAMSUNG32: Self = Self(sys::InfraredProtocol_InfraredProtocolSamsung32);
+    /// The RC6 protocol of Philips.
+    pub const RC6: Self = Self(sys::InfraredProtocol_InfraredProtocolRC6);
+    /// The RC5 protocol of Philips.
+    pub const RC5: Self = Self(sys::InfraredProtocol_InfraredProtocolRC5);
+    /// The RC5X protocol of Philips, which has 7-bit commands.
+    pub const RC5X: Self = Self(sys::InfraredProtocol_InfraredProtocolRC5X);
+    /// The 12-bit SIRC protocol of Sony.
+    pub const SIRC: Self = Self(sys::InfraredProtocol_InfraredProtocolSIRC);
+    /// The 15-bit SIRC protocol of Sony.
+    pub const SIRC15: Self = Self(sys::InfraredProtocol_InfraredProtocolSIRC15);
+    /// The 20-bit SIRC protocol of Sony.
+    pub const SIRC20: Self = Self(sys::InfraredProtocol_InfraredProtocolSIRC20);
+    /// The Kaseikyo protocol of Panasonic and other manufacturers.
+    pub const KASEIKYO: Self = Self(sys::InfraredProtocol_InfraredProtocolKaseikyo);
+    /// The protocol of RCA.
+    pub const RCA: Self = Self(sys::InfraredProtocol_InfraredProtocolRCA);
+    /// The protocol of Pioneer.
+    pub const PIONEER: Self = Self(sys::InfraredProtocol_InfraredProtocolPioneer);
+
+    /// Looks up a protocol by the name used in `.ir` files, such as `NEC` or `RC5`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::UnknownProtocol`] if the firmware doesn't know the protocol.
+    pub fn from_name(name: &CStr) -> Result<Self, Error> {
+        let protocol = unsafe { sys::infrared_get_protocol_by_name(name.as_ptr()) };
+        if unsafe { sys::infrared_is_protocol_valid(protocol) } {
+            Ok(Self(protocol))
+        } else {
+            Err(Error::UnknownProtocol)
+        }
+    }
+
+    /// Returns the name of the protocol.
+    pub fn name(self) -> &'static CStr {
+        unsafe { CStr::from_ptr(sys::infrared_get_protocol_name(self.0)) }
+    }
+
+    /// Returns the number of bits of the addresses of the protocol.
+    pub fn address_bits(self) -> u8 {
+        unsafe { sys::infrared_get_protocol_address_length(self.0) }
+    }
+
+    /// Returns the number of bits of the commands of the protocol.
+    pub fn command_bits(self) -> u8 {
+        unsafe { sys::infrared_get_protocol_command_length(self.0) }
+    }
+
+    /// Returns the raw protocol, for use with the `sys` bindings.
+    pub fn to_sys(&self) -> sys::InfraredProtocol {
+        self.0
+    }
+}
+
+/// A signal of a known protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Message {
+    /// Protocol of the signal.
+    pub protocol: Protocol,
+    /// Address of the device, of [`Protocol::address_bits`] bits.
+    pub address: u32,
+    /// Command sent to the device, of [`Protocol::command_bits`] bits.
+    pub command: u32,
+}
+
+/// A signal given by the durations of its marks and spaces.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RawSignal<'a> {
+    /// Carrier frequency, in Hz.
+    pub frequency: u32,
+    /// Fraction of the carrier period that the LED is on during marks.
+    pub duty_cycle: f32,
+    /// Durations in microseconds, alternating between marks and spaces and starting
+    /// with a mark.
+    pub timings: &'a [u32],
+}
+
+/// An infrared signal, in the forms the Infrared app saves.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IrSignal<'a> {
+    /// A message of a known protocol.
+    Parsed(Message),
+    /// The timings of a signal, such as of an unknown protocol.
+    Raw(RawSignal<'a>),
+}
+
+/// An infrared signal that owns the timings of raw signals, as returned by
+/// [`load_signal`].
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+#[derive(Debug, Clone, PartialEq)]
+pub enum IrSignalBuf {
+    /// A message of a known protocol.
+    Parsed(Message),
+    /// The timings of a signal, such as of an unknown protocol.
+    Raw {
+        /// Carrier frequency, in Hz.
+        frequency: u32,
+        /// Fraction of the carrier period that the LED is on during marks.
+        duty_cycle: f32,
+        /// Durations in microseconds, alternating between marks and spaces and
+        /// starting with a mark.
+        timings: Vec<u32>,
+    },
+}
+
+#[cfg(feature = "alloc")]
+impl IrSignalBuf {
+    /// Borrows the signal, such as for sending it with [`send_signal`].
+    pub fn as_signal(&self) -> IrSignal<'_> {
+        match self {
+            Self::Parsed(message) => IrSignal::Parsed(*message),
+            Self::Raw {
+                frequency,
+                duty_cycle,
+                timings,
+            } => IrSignal::Raw(RawSignal {
+                frequency: *frequency,
+                duty_cycle: *duty_cycle,
+                timings,
+            }),
+        }
+    }
+}
+
+/// Checks the carrier of a raw signal.
+fn check_carrier(frequency: u32, duty_cycle: f32) -> Result<(), Error> {
+    if !(MIN_FREQUENCY..=MAX_FREQUENCY).contains(&frequency) {
+        return Err(Error::FrequencyOutOfRange(frequency));
+    }
+    if !(duty_cycle > 0.0 && duty_cycle <= 1.0) {
+        return Err(Error::DutyCycleOutOfRange);
+    }
+    Ok(())
+}
+
+fn check_idle() -> Result<(), Error> {
+    if unsafe { sys::furi_hal_infrared_is_busy() } {
+        Err(Error::Busy)
+    } else {
+        Ok(())
+    }
+}
+
+fn send_message(message: &Message) {
+    let message = sys::InfraredMessage {
+        protocol: message.protocol.0,
+        address: message.address,
+        command: message.command,
+        repeat: false,
+    };
+    // The firmware adds as many repeats as the protocol requires.
+    unsafe { sys::infrared_send(&message, 1) };
+}
+
+/// Sends the `command` to `address` of `protocol` `repeats` times in a row, blocking
+/// until it has been sent.
+///
+/// Each time, the firmware adds as many repeat frames as the protocol requires.
+///
+/// # Errors
+///
+/// Returns [`Error::Busy`] if infrared is in use.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use flipperzero::infrared::{self, Protocol};
+/// // Send the power button of a remote twice.
+/// infrared::send(Protocol::NEC, 0x07, 0x02, 2)?;
+/// # Ok::<(), infrared::Error>(())
+/// ```
+pub fn send(protocol: Protocol, address: u32, command: u32, repeats: u32) -> Result<(), Error> {
+    check_idle()?;
+    let message = Message {
+        protocol,
+        address,
+        command,
+    };
+    for _ in 0..repeats {
+        send_message(&message);
+    }
+    Ok(())
+}
+
+/// Sends `signal` once, blocking until it has been sent.
+///
+/// # Errors
+///
+/// Returns [`Error::FrequencyOutOfRange`] or [`Error::DutyCycleOutOfRange`] if the
+/// carrier of a raw signal is invalid, and [`Error::Busy`] if infrared is in use.
+pub fn send_signal(signal: &IrSignal<'_>) -> Result<(), Error> {
+    match signal {
+        IrSignal::Parsed(message) => {
+            check_idle()?;
+            send_message(message);
+        }
+        IrSignal::Raw(raw) => {
+            check_carrier(raw.frequency, raw.duty_cycle)?;
+            check_idle()?;
+            unsafe {
+                sys::infrared_send_raw_ext(
+                    raw.timings.as_ptr(),
+                    raw.timings.len() as u32,
+                    true,
+                    raw.frequency,
+                    raw.duty_cycle,
+                )
+            };
+        }
+    }
+    Ok(())
+}
+
+/// Sends the signal called `name` from the `.ir` file at `path`, or its first signal if
+/// `name` is `None`, `repeats` times in a row.
+///
+/// This blocks until the signal has been sent, or `cancel` is set. Raw signals are read
+/// from the file while they are sent, so they can be arbitrarily long.
+///
+/// # Errors
+///
+/// Returns [`Error::NotFound`] if the file has no such signal, [`Error::InvalidFile`]
+/// if it isn't an infrared signal file, [`Error::UnknownProtocol`],
+/// [`Error::FrequencyOutOfRange`] or [`Error::DutyCycleOutOfRange`] if the signal can't
+/// be sent, [`Error::Cancelled`] if `cancel` was set, and [`Error::Underrun`] if a raw
+/// signal was sent with gaps.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use core::sync::atomic::AtomicBool;
+/// # use flipperzero::infrared::{self, Error};
+/// # fn main() -> Result<(), Error> {
+/// let cancel = AtomicBool::new(false);
+/// infrared::send_file(c"/ext/infrared/TV.ir", Some(c"POWER"), 1, &cancel)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn send_file(
+    path: &CStr,
+    name: Option<&CStr>,
+    repeats: u32,
+    cancel: &AtomicBool,
+) -> Result<(), Error> {
+    let file = OpenOptions::new()
+        .read(true)
+        .open_existing(true)
+        .open(path)?;
+    let mut parser = Parser::new(file);
+    parser.read_header()?;
+
+    match parser.find(name.map(CStr::to_bytes))? {
+        Found::Parsed(message) => {
+            check_idle()?;
+            for _ in 0..repeats {
+                if cancel.load(Ordering::Relaxed) {
+                    return Err(Error::Cancelled);
+                }
+                send_message(&message);
+            }
+        }
+        Found::Raw {
+            frequency,
+            duty_cycle,
+            data,
+        } => {
+            check_carrier(frequency, duty_cycle)?;
+            check_idle()?;
+            for i in 0..repeats {
+                if cancel.load(Ordering::Relaxed) {
+                    return Err(Error::Cancelled);
+                }
+                if i > 0 {
+                    parser.rewind_to(data)?;
+                }
+                send_raw_stream(&mut parser, frequency, duty_cycle, cancel)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Adds `signal` to the `.ir` file at `path` as the signal called `name`, creating the
+/// file if it doesn't exist.
+///
+/// The file is written the way the Infrared app saves its remotes, so it can be opened
+/// there. Names aren't checked for being unique, and the Infrared app shows every
+/// signal of a file.
+///
+/// # Errors
+///
+/// Returns [`Error::FrequencyOutOfRange`] or [`Error::DutyCycleOutOfRange`] for raw
+/// signals that can't be sent, and [`Error::Io`] if the file couldn't be written.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use flipperzero::infrared::{self, IrSignal, Message, Protocol};
+/// let power = Message {
+///     protocol: Protocol::NEC,
+///     address: 0x07,
+///     command: 0x02,
+/// };
+/// infrared::save_signal(c"/ext/infrared/TV.ir", c"POWER", &IrSignal::Parsed(power))?;
+/// # Ok::<(), infrared::Error>(())
+/// ```
+pub fn save_signal(path: &CStr, name: &CStr, signal: &IrSignal<'_>) -> Result<(), Error> {
+    // Check before creating the file.
+    if let IrSignal::Raw(raw) = signal {
+        check_carrier(raw.frequency, raw.duty_cycle)?;
+    }
+    let mut file = match FlipperFormat::open_new(path) {
+        Ok(mut file) => {
+            format::write_header(&mut file)?;
+            file
+        }
+        Err(error) if error.is_already_exists() => FlipperFormat::open_append(path)?,
+        Err(error) => return Err(error.into()),
+    };
+    format::write_signal(&mut file, name, signal)?;
+    file.close()?;
+    Ok(())
+}
+
+/// Reads the signal called `name` from the `.ir` file at `path`, or its first signal if
+/// `name` is `None`.
+///
+/// Raw signals are read into memory. To send signals of any length, use [`send_file`].
+///
+/// # Errors
+///
+/// Returns [`Error::NotFound`] if the file has no such signal, [`Error::InvalidFile`]
+/// if it isn't an infrared signal file, and [`Error::UnknownProtocol`] if the firmware
+/// doesn't know the protocol of the signal.
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub fn load_signal(path: &CStr, name: Option<&CStr>) -> Result<IrSignalBuf, Error> {
+    let mut file = FlipperFormat::open_read(path)?;
+    format::check_header(&mut file)?;
+    format::read_signal(&mut file, name)
+}
+
+/// State shared with the transmit interrupt of a streamed raw signal.
+struct RawStream {
+    timings: NonNull<sys::FuriStreamBuffer>,
+    /// Whether all timings have been buffered.
+    finished: AtomicBool,
+    /// Whether the next timing is a mark.
+    mark: AtomicBool,
+    underruns: AtomicU32,
+}
+
+/// Returns the next timing to send, in interrupt context.
+unsafe extern "C" fn raw_stream_isr(
+    context: *mut c_void,
+    duration: *mut u32,
+    level: *mut bool,
+) -> sys::FuriHalInfraredTxGetDataState {
+    let stream = unsafe { &*(context as *const RawStream) };
+    let buffer = stream.timings.as_ptr();
+    // Read before checking the buffer, so no timing can slip in between.
+    let finished = stream.finished.load(Ordering::Acquire);
+
+    if unsafe { sys::furi_stream_buffer_bytes_available(buffer) } < TIMING_SIZE {
+        // Keep the LED off until the next timing arrives.
+        unsafe {
+            *duration = UNDERRUN_SPACE_US;
+            *level = false;
+        }
+        if finished {
+            return sys::FuriHalInfraredTxGetDataState_FuriHalInfraredTxGetDataStateLastDone;
+        }
+        stream.underruns.fetch_add(1, Ordering::Relaxed);
+        return sys::FuriHalInfraredTxGetDataState_FuriHalInfraredTxGetDataStateOk;
+    }
+
+    let mut timing = 0u32;
+    unsafe {
+        sys::furi_stream_buffer_receive(
+            buffer,
+            &mut timing as *mut u32 as *mut c_void,
+            TIMING_SIZE,
+            0,
+        )
+    };
+    let mark = stream.mark.load(Ordering::Relaxed);
+    stream.mark.store(!mark, Ordering::Relaxed);
+    unsafe {
+        *duration = timing;
+        *level = mark;
+    }
+
+    if finished && unsafe { sys::furi_stream_buffer_is_empty(buffer) } {
+        sys::FuriHalInfraredTxGetDataState_FuriHalInfraredTxGetDataStateLastDone
+    } else {
+        sys::FuriHalInfraredTxGetDataState_FuriHalInfraredTxGetDataStateOk
+    }
+}
+
+/// Sends the raw signal whose timings `parser` is at, reading them while the start of
+/// the signal is sent.
+fn send_raw_stream<R: io::Read + io::Seek>(
+    parser: &mut Parser<R>,
+    frequency: u32,
+    duty_cycle: f32,
+    cancel: &AtomicBool,
+) -> Result<(), Error> {
+    let stream = RawStream {
+        timings: unsafe {
+            NonNull::new_unchecked(sys::furi_stream_buffer_alloc(
+                RAW_BUFFER_LEN * TIMING_SIZE,
+                TIMING_SIZE,
+            ))
+        },
+        finished: AtomicBool::new(false),
+        mark: AtomicBool::new(true),
+        underruns: AtomicU32::new(0),
+    };
+    let buffer = stream.timings.as_ptr();
+
+    let mut started = false;
+    let result = feed_raw_stream(parser, &stream, &mut started, frequency, duty_cycle, cancel);
+
+    // The transmission ends after the buffered timings, also when reading failed.
+    stream.finished.store(true, Ordering::Release);
+    if result.is_ok() && !started && !unsafe { sys::furi_stream_buffer_is_empty(buffer) } {
+        start_raw_stream(&stream, frequency, duty_cycle);
+        started = true;
+    }
+    if started {
+        unsafe { sys::furi_hal_infrared_async_tx_wait_termination() };
+    }
+    unsafe {
+        sys::furi_hal_infrared_async_tx_set_data_isr_callback(None, core::ptr::null_mut());
+        sys::furi_stream_buffer_free(buffer);
+    }
+
+    result?;
+    if stream.underruns.load(Ordering::Relaxed) > 0 {
+        return Err(Error::Underrun);
+    }
+    Ok(())
+}
+
+/// Buffers the timings `parser` is at, starting the transmission once the buffer is
+/// full.
+fn feed_raw_stream<R: io::Read + io::Seek>(
+    parser: &mut Parser<R>,
+    stream: &RawStream,
+    started: &mut bool,
+    frequency: u32,
+    duty_cycle: f32,
+    cancel: &AtomicBool,
+) -> Result<(), Error> {
+    let buffer = stream.timings.as_ptr();
+    while let Some(timing) = parser.next_timing()? {
+        // Wait for room, and only send whole timings.
+        while unsafe { sys::furi_stream_buffer_spaces_available(buffer) } < TIMING_SIZE {
+            if !*started {
+                start_raw_stream(stream, frequency, duty_cycle);
+                *started = true;
+            }
+            if cancel.load(Ordering::Relaxed) {
+                return Err(Error::Cancelled);
+            }
+            unsafe { sys::furi_delay_ms(1) };
+        }
+        unsafe {
+            sys::furi_stream_buffer_send(
+                buffer,
+                &timing as *const u32 as *const c_void,
+                TIMING_SIZE,
+                0,
+            )
+        };
+    }
+    Ok(())
+}
+
+fn start_raw_stream(stream: &RawStream, frequency: u32, duty_cycle: f32) {
+    unsafe {
+        sys::furi_hal_infrared_async_tx_set_data_isr_callback(
+            Some(raw_stream_isr),
+            stream as *const RawStream as *mut c_void,
+        );
+        sys::furi_hal_infrared_async_tx_start(frequency, duty_cycle);
+    }
+}
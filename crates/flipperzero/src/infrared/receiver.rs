@@ -0,0 +1,164 @@
+//! Receiving signals with the infrared worker of the firmware.
+
+use alloc::boxed::Box;
+use core::ffi::c_void;
+use core::ptr::NonNull;
+use core::slice;
+
+use flipperzero_sys as sys;
+
+use super::{check_idle, Error, Message, Protocol};
+
+type ReceivedCallback = Box<dyn FnMut(Received<'_>) + Send>;
+
+/// A signal received by a [`Receiver`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Received<'a> {
+    /// A signal of a protocol known to the firmware, such as NEC, Samsung, RC5 or RC6.
+    Parsed {
+        message: Message,
+        /// Whether this is a repeat of the previous message, which remotes send while
+        /// a button is held.
+        repeat: bool,
+    },
+    /// A signal that no decoder recognized, as durations in microseconds alternating
+    /// between marks and spaces and starting with a mark.
+    Raw(&'a [u32]),
+}
+
+/// A closure called with the signals that the infrared receiver picks up.
+///
+/// The closure is called on the thread of the worker, so it should return quickly, such
+/// as by putting the signal in a
+/// [`MessageQueue`](crate::furi::message_queue::MessageQueue). Infrared can't send
+/// while receiving, so the worker is stopped once the `Receiver` is dropped.
+///
+/// # Examples
+///
+/// ```no_run
+/// # extern crate alloc;
+/// # use alloc::sync::Arc;
+/// # use core::time::Duration;
+/// use flipperzero::furi::message_queue::MessageQueue;
+/// use flipperzero::infrared::{Received, Receiver};
+///
+/// let queue = Arc::new(MessageQueue::new(8));
+/// let _receiver = Receiver::start({
+///     let queue = queue.clone();
+///     move |signal| {
+///         if let Received::Parsed { message, repeat: false } = signal {
+///             let _ = queue.put(message, Duration::ZERO);
+///         }
+///     }
+/// })
+/// .expect("infrared is free");
+/// let message = queue.get(Duration::MAX)?;
+/// # Ok::<(), flipperzero::furi::Status>(())
+/// ```
+pub struct Receiver {
+    worker: NonNull<sys::InfraredWorker>,
+    // Boxed twice for a thin pointer, see `furi::thread::Builder::spawn`.
+    #[allow(dead_code)]
+    callback: Box<ReceivedCallback>,
+}
+
+impl Receiver {
+    /// Starts receiving, calling `callback` with each signal.
+    ///
+    /// Signals are decoded when their protocol is known to the firmware, and the LED
+    /// blinks while a signal is received.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Busy`] if infrared is in use.
+    pub fn start<F>(callback: F) -> Result<Self, Error>
+    where
+        F: FnMut(Received<'_>) + Send + 'static,
+    {
+        unsafe extern "C" fn run_callback(
+            context: *mut c_void,
+            signal: *mut sys::InfraredWorkerSignal,
+        ) {
+            let callback = unsafe { &mut *(context as *mut ReceivedCallback) };
+            if unsafe { sys::infrared_worker_signal_is_decoded(signal) } {
+                let message = unsafe { &*sys::infrared_worker_get_decoded_signal(signal) };
+                callback(Received::Parsed {
+                    message: Message {
+                        protocol: Protocol(message.protocol),
+                        address: message.address,
+                        command: message.command,
+                    },
+                    repeat: message.repeat,
+                });
+            } else {
+                let mut timings = core::ptr::null();
+                let mut len = 0;
+                unsafe { sys::infrared_worker_get_raw_signal(signal, &mut timings, &mut len) };
+                let timings = if len == 0 {
+                    &[]
+                } else {
+                    unsafe { slice::from_raw_parts(timings, len) }
+                };
+                callback(Received::Raw(timings));
+            }
+        }
+
+        check_idle()?;
+        let worker = unsafe { NonNull::new_unchecked(sys::infrared_worker_alloc()) };
+        let mut callback: Box<ReceivedCallback> = Box::new(Box::new(callback));
+        unsafe {
+            sys::infrared_worker_rx_set_received_signal_callback(
+                worker.as_ptr(),
+                Some(run_callback),
+                &mut *callback as *mut ReceivedCallback as *mut c_void,
+            );
+            sys::infrared_worker_rx_enable_blink_on_receiving(worker.as_ptr(), true);
+            sys::infrared_worker_rx_start(worker.as_ptr());
+        }
+
+        Ok(Self { worker, callback })
+    }
+
+    /// Sets whether signals of known protocols are decoded, rather than all being
+    /// passed as [`Received::Raw`].
+    pub fn set_decoding(&mut self, decode: bool) {
+        unsafe { sys::infrared_worker_rx_enable_signal_decoding(self.worker.as_ptr(), decode) };
+    }
+
+    /// Sets whether the LED blinks while a signal is received.
+    pub fn set_blink(&mut self, blink: bool) {
+        unsafe { sys::infrared_worker_rx_enable_blink_on_receiving(self.worker.as_ptr(), blink) };
+    }
+}
+
+impl Drop for Receiver {
+    fn drop(&mut self) {
+        unsafe {
+            sys::infrared_worker_rx_stop(self.worker.as_ptr());
+            sys::infrared_worker_free(self.worker.as_ptr());
+        }
+        // The closure is dropped after this, once it can no longer be called.
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use super::Receiver;
+    use crate::infrared::{self, Error, Protocol};
+
+    #[test]
+    fn receiving_blocks_sending() {
+        let mut receiver = Receiver::start(|_| {}).unwrap();
+        receiver.set_decoding(false);
+        receiver.set_blink(false);
+        assert!(matches!(
+            infrared::send(Protocol::NEC, 0x07, 0x02, 1),
+            Err(Error::Busy)
+        ));
+        assert!(matches!(Receiver::start(|_| {}), Err(Error::Busy)));
+
+        drop(receiver);
+        let receiver = Receiver::start(|_| {});
+        assert!(receiver.is_ok());
+    }
+}
@@ -0,0 +1,240 @@
+//! Input service.
+
+#[cfg(feature = "alloc")]
+use alloc::sync::Arc;
+#[cfg(feature = "alloc")]
+use core::time::Duration;
+
+use flipperzero_sys as sys;
+#[cfg(feature = "alloc")]
+use sys::furi::UnsafeRecord;
+
+#[cfg(feature = "alloc")]
+use crate::furi::{self, message_queue::MessageQueue, pubsub::RecordSubscription};
+
+/// A button on the Flipper Zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Key {
+    Up,
+    Down,
+    Right,
+    Left,
+    Ok,
+    Back,
+    /// A key not known to this crate, with its raw value.
+    Unknown(sys::InputKey),
+}
+
+/// The kind of an input event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Kind {
+    /// The key was pressed.
+    Press,
+    /// The key was released.
+    Release,
+    /// The key was pressed and released quickly.
+    Short,
+    /// The key has been held down for a while.
+    Long,
+    /// The key is still held down after a [`Kind::Long`] event.
+    Repeat,
+    /// An event kind not known to this crate, with its raw value.
+    Unknown(sys::InputType),
+}
+
+/// An event from the input service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InputEvent {
+    pub key: Key,
+    pub kind: Kind,
+}
+
+impl Key {
+    pub fn from_sys(key: sys::InputKey) -> Self {
+        match key {
+            sys::InputKey_InputKeyUp => Self::Up,
+            sys::InputKey_InputKeyDown => Self::Down,
+            sys::InputKey_InputKeyRight => Self::Right,
+            sys::InputKey_InputKeyLeft => Self::Left,
+            sys::InputKey_InputKeyOk => Self::Ok,
+            sys::InputKey_InputKeyBack => Self::Back,
+            key => Self::Unknown(key),
+        }
+    }
+
+    pub fn to_sys(&self) -> sys::InputKey {
+        match self {
+            Self::Up => sys::InputKey_InputKeyUp,
+            Self::Down => sys::InputKey_InputKeyDown,
+            Self::Right => sys::InputKey_InputKeyRight,
+            Self::Left => sys::InputKey_InputKeyLeft,
+            Self::Ok => sys::InputKey_InputKeyOk,
+            Self::Back => sys::InputKey_InputKeyBack,
+            Self::Unknown(key) => *key,
+        }
+    }
+}
+
+impl Kind {
+    pub fn from_sys(kind: sys::InputType) -> Self {
+        match kind {
+            sys::InputType_InputTypePress => Self::Press,
+            sys::InputType_InputTypeRelease => Self::Release,
+            sys::InputType_InputTypeShort => Self::Short,
+            sys::InputType_InputTypeLong => Self::Long,
+            sys::InputType_InputTypeRepeat => Self::Repeat,
+            kind => Self::Unknown(kind),
+        }
+    }
+
+    pub fn to_sys(&self) -> sys::InputType {
+        match self {
+            Self::Press => sys::InputType_InputTypePress,
+            Self::Release => sys::InputType_InputTypeRelease,
+            Self::Short => sys::InputType_InputTypeShort,
+            Self::Long => sys::InputType_InputTypeLong,
+            Self::Repeat => sys::InputType_InputTypeRepeat,
+            Self::Unknown(kind) => *kind,
+        }
+    }
+}
+
+impl InputEvent {
+    pub fn from_sys(event: &sys::InputEvent) -> Self {
+        Self {
+            key: Key::from_sys(event.key),
+            kind: Kind::from_sys(event.type_),
+        }
+    }
+
+    /// Returns the raw event, with a sequence number of zero.
+    pub fn to_sys(&self) -> sys::InputEvent {
+        sys::InputEvent {
+            __bindgen_anon_1: sys::InputEvent__bindgen_ty_1 { sequence: 0 },
+            key: self.key.to_sys(),
+            type_: self.kind.to_sys(),
+        }
+    }
+}
+
+/// Subscribes to the events of the input service, calling `callback` for every event.
+///
+/// The closure is called on the input service thread, so it should return quickly. The
+/// subscription ends when the returned [`InputSubscription`] is dropped.
+///
+/// Events are received regardless of which app or view is shown, so this is mostly
+/// useful for apps without a GUI; apps with a GUI should use the input callbacks of
+/// their view ports or views instead.
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub fn subscribe<F>(callback: F) -> InputSubscription
+where
+    F: FnMut(InputEvent) + Send + 'static,
+{
+    let record = unsafe { UnsafeRecord::<sys::FuriPubSub>::open(c"input_events".as_ptr()) };
+    // The record is the pubsub itself.
+    let pubsub = record.as_ptr();
+    InputSubscription {
+        _subscription: unsafe {
+            RecordSubscription::new(record, pubsub, InputEvent::from_sys, callback)
+        },
+    }
+}
+
+/// Subscribes to the events of the input service, forwarding them to a queue with room
+/// for `capacity` events.
+///
+/// Events are dropped if the queue is full, as the input service thread must not
+/// block.
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub fn subscribe_queue(capacity: usize) -> InputQueue {
+    let queue = Arc::new(MessageQueue::new(capacity));
+    let subscription = subscribe({
+        let queue = queue.clone();
+        move |event| {
+            let _ = queue.put(event, Duration::ZERO);
+        }
+    });
+
+    InputQueue {
+        _subscription: subscription,
+        queue,
+    }
+}
+
+/// A subscription to the input service, created by [`subscribe`].
+///
+/// The subscription ends when this is dropped.
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub struct InputSubscription {
+    _subscription: RecordSubscription<UnsafeRecord<sys::FuriPubSub>, sys::InputEvent>,
+}
+
+/// A queue of events from the input service, created by [`subscribe_queue`].
+///
+/// The subscription ends when this is dropped.
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub struct InputQueue {
+    _subscription: InputSubscription,
+    queue: Arc<MessageQueue<InputEvent>>,
+}
+
+#[cfg(feature = "alloc")]
+impl InputQueue {
+    /// Removes the oldest event from the queue, waiting up to `timeout` for one to
+    /// arrive.
+    pub fn get(&self, timeout: Duration) -> furi::Result<InputEvent> {
+        self.queue.get(timeout)
+    }
+
+    /// Returns the underlying queue, for instance to check how many events it holds.
+    pub fn queue(&self) -> &MessageQueue<InputEvent> {
+        &self.queue
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use flipperzero_sys as sys;
+
+    use super::{InputEvent, Key, Kind};
+
+    #[test]
+    fn key_round_trip() {
+        for key in 0..sys::InputKey_InputKeyMAX + 2 {
+            assert_eq!(Key::from_sys(key).to_sys(), key);
+        }
+        assert_eq!(Key::from_sys(sys::InputKey_InputKeyOk), Key::Ok);
+        assert_eq!(
+            Key::from_sys(sys::InputKey_InputKeyMAX),
+            Key::Unknown(sys::InputKey_InputKeyMAX)
+        );
+    }
+
+    #[test]
+    fn kind_round_trip() {
+        for kind in 0..sys::InputType_InputTypeMAX + 2 {
+            assert_eq!(Kind::from_sys(kind).to_sys(), kind);
+        }
+        assert_eq!(Kind::from_sys(sys::InputType_InputTypeLong), Kind::Long);
+        assert_eq!(
+            Kind::from_sys(sys::InputType_InputTypeMAX),
+            Kind::Unknown(sys::InputType_InputTypeMAX)
+        );
+    }
+
+    #[test]
+    fn event_round_trip() {
+        let event = InputEvent {
+            key: Key::Back,
+            kind: Kind::Long,
+        };
+        let raw = event.to_sys();
+        assert_eq!(raw.key, sys::InputKey_InputKeyBack);
+        assert_eq!(raw.type_, sys::InputType_InputTypeLong);
+        assert_eq!(InputEvent::from_sys(&raw), event);
+    }
+}
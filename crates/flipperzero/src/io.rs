@@ -1,8 +1,21 @@
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec::Vec};
 use core::ffi::CStr;
 use core::fmt;
+use core::time::Duration;
 
 use flipperzero_sys as sys;
 
+use crate::furi::string::FuriString;
+
+#[cfg(test)]
+pub(crate) mod cursor;
+
+// The buffered readers and writers work with any `Read` or `Write`, not only files.
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use crate::storage::{BufReader, BufWriter, DEFAULT_BUF_SIZE};
+
 /// Stream and file system related error kinds.
 ///
 /// This list may grow over time, and it is not recommended to exhaustively
@@ -12,7 +25,8 @@ use flipperzero_sys as sys;
 ///
 /// In application code, use `match` for the `Error` values you are expecting;
 /// use `_` to match "all other errors".
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[non_exhaustive]
 pub enum Error {
     NotReady,
@@ -30,6 +44,20 @@ pub enum Error {
     /// completed.
     WriteZero,
 
+    /// I/O error specific to `flipperzero-rs` to represent the case a call to
+    /// `read` returned `Ok(0)` before the requested bytes were read, such as in
+    /// [`Read::read_exact`].
+    UnexpectedEof,
+
+    /// I/O error specific to `flipperzero-rs` to represent data that was read
+    /// successfully but is malformed, such as a file read as a string that isn't
+    /// valid UTF-8.
+    InvalidData,
+
+    /// I/O error specific to `flipperzero-rs` to represent an operation that didn't
+    /// complete within its timeout, such as in [`ReadTimeout::read_with_timeout`].
+    TimedOut,
+
     /// Any I/O error from the Flipper Zero SDK that's not part of this list.
     ///
     /// Errors that are `Uncategorized` now may move to a different or a new [`Error`]
@@ -39,6 +67,40 @@ pub enum Error {
     Uncategorized(sys::FS_Error),
 }
 
+/// A general category of [`Error`]s, grouping them as `std::io::ErrorKind` does.
+///
+/// This list may grow over time, and it is not recommended to exhaustively match
+/// against it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// A file or directory wasn't found.
+    NotFound,
+    /// A file or directory already exists.
+    AlreadyExists,
+    /// The operation isn't allowed, such as writing to a file not open for writing.
+    PermissionDenied,
+    /// A parameter or path was invalid.
+    InvalidInput,
+    /// The storage or device isn't ready, such as when the SD card is missing.
+    NotReady,
+    /// The file is in use elsewhere.
+    ResourceBusy,
+    /// The operation isn't supported by the storage or device.
+    Unsupported,
+    /// A write could not complete, as it returned `Ok(0)`.
+    WriteZero,
+    /// A read ended before the requested bytes were read.
+    UnexpectedEof,
+    /// The data that was read is malformed.
+    InvalidData,
+    /// The operation didn't complete within its timeout.
+    TimedOut,
+    /// Any other error.
+    Other,
+}
+
 impl Error {
     pub fn to_sys(&self) -> Option<sys::FS_Error> {
         match self {
@@ -56,7 +118,13 @@ impl Error {
         }
     }
 
-    pub fn from_sys(err: sys::FS_Error) -> Option<Self> {
+    /// Returns the error for a status of the storage service, or `None` for
+    /// `FSE_OK`.
+    ///
+    /// Statuses unknown to this crate become an uncategorized error, which keeps its
+    /// raw value for [`Error::to_sys`], so this never panics. To turn a status into a
+    /// result, use [`fs_result`].
+    pub fn try_from_sys(err: sys::FS_Error) -> Option<Self> {
         match err {
             sys::FS_Error_FSE_OK => None,
             sys::FS_Error_FSE_NOT_READY => Some(Self::NotReady),
@@ -72,15 +140,86 @@ impl Error {
         }
     }
 
+    /// Returns the general category of this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::NotExists => ErrorKind::NotFound,
+            Self::Exists => ErrorKind::AlreadyExists,
+            Self::Denied => ErrorKind::PermissionDenied,
+            Self::InvalidParameter | Self::InvalidName => ErrorKind::InvalidInput,
+            Self::NotReady => ErrorKind::NotReady,
+            Self::AlreadyOpen => ErrorKind::ResourceBusy,
+            Self::NotImplemented => ErrorKind::Unsupported,
+            Self::WriteZero => ErrorKind::WriteZero,
+            Self::UnexpectedEof => ErrorKind::UnexpectedEof,
+            Self::InvalidData => ErrorKind::InvalidData,
+            Self::TimedOut => ErrorKind::TimedOut,
+            Self::Internal | Self::Uncategorized(_) => ErrorKind::Other,
+        }
+    }
+
+    /// Returns `true` if a file or directory wasn't found.
+    pub fn is_not_found(&self) -> bool {
+        self.kind() == ErrorKind::NotFound
+    }
+
+    /// Returns `true` if a file or directory already exists.
+    pub fn is_already_exists(&self) -> bool {
+        self.kind() == ErrorKind::AlreadyExists
+    }
+
+    /// Returns `true` if the storage or device isn't ready.
+    pub fn is_not_ready(&self) -> bool {
+        self.kind() == ErrorKind::NotReady
+    }
+
+    /// Returns `true` if the operation isn't allowed.
+    pub fn is_permission_denied(&self) -> bool {
+        self.kind() == ErrorKind::PermissionDenied
+    }
+
     /// Description associated with [`Error`].
-    pub fn description(&self) -> &CStr {
-        unsafe { CStr::from_ptr(sys::filesystem_api_error_get_desc(self.to_sys().unwrap())) }
+    ///
+    /// For errors of the storage service, this matches the description of the firmware.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::NotReady => "filesystem not ready",
+            Self::Exists => "file/dir already exist",
+            Self::NotExists => "file/dir not exist",
+            Self::InvalidParameter => "invalid parameter",
+            Self::Denied => "access denied",
+            Self::InvalidName => "invalid name/path",
+            Self::Internal => "internal error",
+            Self::NotImplemented => "function not implemented",
+            Self::AlreadyOpen => "file is already open",
+            Self::WriteZero => "failed to write whole buffer",
+            Self::UnexpectedEof => "failed to fill whole buffer",
+            Self::InvalidData => "invalid data",
+            Self::TimedOut => "operation timed out",
+            Self::Uncategorized(_) => "unknown error",
+        }
+    }
+
+    /// Description of the error from the firmware, or `None` if this error isn't one
+    /// of the storage service.
+    pub fn sys_description(&self) -> Option<&'static CStr> {
+        let error = self.to_sys()?;
+        Some(unsafe { CStr::from_ptr(sys::filesystem_api_error_get_desc(error)) })
     }
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.description().to_bytes().escape_ascii().fmt(f)
+        f.write_str(self.description())
+    }
+}
+
+impl core::error::Error for Error {}
+
+impl From<Error> for fmt::Error {
+    /// Discards the error, for writing to a stream from a `fmt::Write` implementation.
+    fn from(_: Error) -> Self {
+        fmt::Error
     }
 }
 
@@ -89,11 +228,37 @@ impl ufmt::uDisplay for Error {
     where
         W: ufmt::uWrite + ?Sized,
     {
-        for c in self.description().to_bytes().escape_ascii() {
-            f.write_char(c as char)?;
+        f.write_str(self.description())
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl embedded_io::Error for Error {
+    fn kind(&self) -> embedded_io::ErrorKind {
+        match self.kind() {
+            ErrorKind::NotFound => embedded_io::ErrorKind::NotFound,
+            ErrorKind::AlreadyExists => embedded_io::ErrorKind::AlreadyExists,
+            ErrorKind::PermissionDenied => embedded_io::ErrorKind::PermissionDenied,
+            ErrorKind::InvalidInput => embedded_io::ErrorKind::InvalidInput,
+            ErrorKind::Unsupported => embedded_io::ErrorKind::Unsupported,
+            ErrorKind::WriteZero => embedded_io::ErrorKind::WriteZero,
+            ErrorKind::InvalidData => embedded_io::ErrorKind::InvalidData,
+            ErrorKind::TimedOut => embedded_io::ErrorKind::TimedOut,
+            // `embedded_io` has no kinds for these, and reports an unexpected end of
+            // the data through `ReadExactError` instead.
+            ErrorKind::NotReady
+            | ErrorKind::ResourceBusy
+            | ErrorKind::UnexpectedEof
+            | ErrorKind::Other => embedded_io::ErrorKind::Other,
         }
+    }
+}
 
-        Ok(())
+/// Turns a status of the storage service into a result, which is `Ok` for `FSE_OK`.
+pub fn fs_result(status: sys::FS_Error) -> Result<(), Error> {
+    match Error::try_from_sys(status) {
+        None => Ok(()),
+        Some(error) => Err(error),
     }
 }
 
@@ -102,8 +267,284 @@ pub trait Read {
     /// Reads some bytes from this source into the given buffer, returning how many bytes
     /// were read.
     fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error>;
+
+    /// Reads exactly enough bytes to fill `buf`.
+    ///
+    /// Fails with [`Error::UnexpectedEof`] if the source ends first. `buf` holds an
+    /// unspecified part of the bytes after any error.
+    fn read_exact(&mut self, mut buf: &mut [u8]) -> Result<(), Error> {
+        while !buf.is_empty() {
+            match self.read(buf) {
+                Ok(0) => return Err(Error::UnexpectedEof),
+                Ok(n) => buf = &mut buf[n..],
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads all bytes until the end of the source, appending them to `buf`, and
+    /// returns how many were read.
+    ///
+    /// Only a read returning `Ok(0)` ends the source; errors are returned rather than
+    /// treated as its end. The bytes read before an error are kept in `buf`. `buf`
+    /// grows by doubling, and is probed with a small read before growing it, so
+    /// reading into a `Vec` with the exact capacity of the data doesn't reallocate.
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+    fn read_to_end(&mut self, buf: &mut Vec<u8>) -> Result<usize, Error> {
+        const PROBE_SIZE: usize = 32;
+
+        let start = buf.len();
+        // The bytes of `buf` past `filled` are initialized, but weren't read.
+        let mut filled = start;
+        loop {
+            if filled == buf.capacity() {
+                // This grows `buf` only if there is more to read.
+                let mut probe = [0; PROBE_SIZE];
+                match self.read(&mut probe)? {
+                    0 => return Ok(filled - start),
+                    n => buf.extend_from_slice(&probe[..n]),
+                }
+                filled = buf.len();
+                buf.reserve(PROBE_SIZE);
+                continue;
+            }
+
+            // This only initializes the capacity that was just reserved.
+            buf.resize(buf.capacity(), 0);
+            match self.read(&mut buf[filled..]) {
+                Ok(0) => {
+                    buf.truncate(filled);
+                    return Ok(filled - start);
+                }
+                Ok(n) => filled += n,
+                Err(e) => {
+                    buf.truncate(filled);
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    /// Reads all bytes until the end of the source, appending them to `buf`, and
+    /// returns how many were read.
+    ///
+    /// Fails with [`Error::InvalidData`] if the bytes aren't valid UTF-8. `buf` is
+    /// left unchanged after any error.
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+    fn read_to_string(&mut self, buf: &mut String) -> Result<usize, Error> {
+        let mut bytes = core::mem::take(buf).into_bytes();
+        let start = bytes.len();
+        let result = self.read_to_end(&mut bytes).and_then(|n| {
+            match core::str::from_utf8(&bytes[start..]) {
+                Ok(_) => Ok(n),
+                Err(_) => Err(Error::InvalidData),
+            }
+        });
+        if result.is_err() {
+            bytes.truncate(start);
+        }
+        // Only valid UTF-8 was appended, if anything.
+        *buf = unsafe { String::from_utf8_unchecked(bytes) };
+        result
+    }
+
+    /// Returns a reader that reads at most `limit` bytes from this one.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use flipperzero::io::{Error, Read};
+    /// # use flipperzero::storage::File;
+    /// # fn main() -> Result<(), Error> {
+    /// let mut file = File::open(c"/ext/image.bin")?;
+    /// let mut header = [0; 16];
+    /// let n = (&mut file).take(16).read(&mut header)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn take(self, limit: u64) -> Take<Self>
+    where
+        Self: Sized,
+    {
+        Take { inner: self, limit }
+    }
+
+    /// Returns a reader that reads all bytes of this one, then those of `next`.
+    fn chain<R: Read>(self, next: R) -> Chain<Self, R>
+    where
+        Self: Sized,
+    {
+        Chain {
+            first: self,
+            second: next,
+            done_first: false,
+        }
+    }
 }
 
+/// A [`Read`]er whose reads can wait for data, such as a serial port.
+pub trait ReadTimeout: Read {
+    /// Reads some bytes into `buf`, waiting up to `timeout` for at least one byte to
+    /// arrive, instead of the stream's own read timeout.
+    ///
+    /// Returns `Ok(0)` only if `buf` is empty.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`Error::TimedOut`] if no byte arrived in time, so that a timeout can't
+    /// be mistaken for the end of the stream, besides the errors of [`Read::read`].
+    fn read_with_timeout(&mut self, buf: &mut [u8], timeout: Duration) -> Result<usize, Error>;
+}
+
+/// Trait comparable to `std::BufRead` for the Flipper Zero API
+pub trait BufRead: Read {
+    /// Returns the buffered bytes, reading more from the source if there are none.
+    ///
+    /// An empty slice means that the source has reached its end.
+    fn fill_buf(&mut self) -> Result<&[u8], Error>;
+
+    /// Marks the first `amt` bytes returned by [`BufRead::fill_buf`] as read.
+    fn consume(&mut self, amt: usize);
+
+    /// Reads a line of at most [`MAX_LINE_LEN`] bytes, appending it to `buf`, as by
+    /// [`BufRead::read_line_max`].
+    fn read_line(&mut self, buf: &mut FuriString) -> Result<usize, Error> {
+        self.read_line_max(buf, MAX_LINE_LEN)
+    }
+
+    /// Reads up to and including the next `\n`, appending the bytes to `buf`, and
+    /// returns how many were read.
+    ///
+    /// The last line of the source doesn't need to end with a `\n`. Zero means that
+    /// the source has reached its end.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`Error::InvalidData`] if the line, including its `\n`, is longer
+    /// than `max_len` bytes, or if it contains a nul byte, which a [`FuriString`]
+    /// can't hold. `buf` holds the bytes read before any error, and the rest of the
+    /// line is left unread.
+    fn read_line_max(&mut self, buf: &mut FuriString, max_len: usize) -> Result<usize, Error> {
+        let mut read = 0;
+        loop {
+            let available = self.fill_buf()?;
+            let (mut used, done) = match available.iter().position(|&b| b == b'\n') {
+                Some(i) => (i + 1, true),
+                None => (available.len(), available.is_empty()),
+            };
+            let mut error = None;
+            if let Some(i) = available[..used].iter().position(|&b| b == 0) {
+                used = i;
+                error = Some(Error::InvalidData);
+            }
+            if read + used > max_len {
+                used = max_len - read;
+                error = Some(Error::InvalidData);
+            }
+            buf.push_bytes(&available[..used]);
+            self.consume(used);
+            read += used;
+
+            if let Some(e) = error {
+                return Err(e);
+            } else if done {
+                return Ok(read);
+            }
+        }
+    }
+
+    /// Returns an iterator over the lines of the source, without their `\n` or `\r\n`
+    /// line endings.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use flipperzero::io::{BufRead, Error};
+    /// # use flipperzero::storage::{BufReader, File};
+    /// # fn main() -> Result<(), Error> {
+    /// let path = c"/ext/apps_data/myapp/config.txt";
+    /// let config = BufReader::for_path(path, File::open(path)?);
+    /// for line in config.lines() {
+    ///     let line = line?;
+    ///     if !line.starts_with('#') {
+    ///         flipperzero::println!("setting: {}", line);
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn lines(self) -> Lines<Self>
+    where
+        Self: Sized,
+    {
+        Lines {
+            reader: self,
+            max_len: MAX_LINE_LEN,
+            done: false,
+        }
+    }
+}
+
+/// Length in bytes of the longest line read by [`BufRead::read_line`] and
+/// [`BufRead::lines`], including its line ending.
+pub const MAX_LINE_LEN: usize = 1024;
+
+/// An iterator over the lines of a [`BufRead`], created by [`BufRead::lines`].
+///
+/// Lines are read as by [`BufRead::read_line_max`]. The iterator ends after the first
+/// error, so that a binary file doesn't yield an error for every chunk of it.
+pub struct Lines<B> {
+    reader: B,
+    max_len: usize,
+    done: bool,
+}
+
+impl<B> Lines<B> {
+    /// Sets the length in bytes of the longest line, including its line ending, which
+    /// is [`MAX_LINE_LEN`] by default.
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+
+    /// Returns the reader, positioned after the last line that was read.
+    pub fn into_inner(self) -> B {
+        self.reader
+    }
+}
+
+impl<B: BufRead> Iterator for Lines<B> {
+    type Item = Result<FuriString, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let mut line = FuriString::new();
+        match self.reader.read_line_max(&mut line, self.max_len) {
+            Ok(0) => {
+                self.done = true;
+                None
+            }
+            Ok(_) => {
+                if line.strip_suffix('\n') {
+                    let _ = line.strip_suffix('\r');
+                }
+                Some(Ok(line))
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+impl<B: BufRead> core::iter::FusedIterator for Lines<B> {}
+
 /// Trait comparable to `std::Seek` for the Flipper Zero API
 pub trait Seek {
     fn seek(&mut self, pos: SeekFrom) -> Result<usize, Error>;
@@ -148,13 +589,788 @@ pub trait Write {
         }
         Ok(())
     }
+
+    /// Writes formatted text, which makes `write!` work on writers.
+    ///
+    /// Returns the error of the first write that failed. If formatting itself failed,
+    /// because an implementation of `Display` returned an error, this fails with
+    /// [`Error::Internal`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use flipperzero::io::{Error, Write};
+    /// # use flipperzero::storage::OpenOptions;
+    /// # fn main() -> Result<(), Error> {
+    /// let mut file = OpenOptions::new()
+    ///     .write(true)
+    ///     .open_append(true)
+    ///     .open(c"/ext/log.txt")?;
+    /// let (temp, ts) = (21, 1234);
+    /// writeln!(file, "temp={} ts={}", temp, ts)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn write_fmt(&mut self, args: fmt::Arguments<'_>) -> Result<(), Error> {
+        let mut writer = FmtWriter::new(self);
+        match fmt::Write::write_fmt(&mut writer, args) {
+            Ok(()) => Ok(()),
+            Err(fmt::Error) => Err(writer.error().unwrap_or(Error::Internal)),
+        }
+    }
+}
+
+impl<R: Read + ?Sized> Read for &mut R {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        (**self).read(buf)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+        (**self).read_exact(buf)
+    }
+
+    #[cfg(feature = "alloc")]
+    fn read_to_end(&mut self, buf: &mut Vec<u8>) -> Result<usize, Error> {
+        (**self).read_to_end(buf)
+    }
+
+    #[cfg(feature = "alloc")]
+    fn read_to_string(&mut self, buf: &mut String) -> Result<usize, Error> {
+        (**self).read_to_string(buf)
+    }
+}
+
+impl<R: ReadTimeout + ?Sized> ReadTimeout for &mut R {
+    #[inline]
+    fn read_with_timeout(&mut self, buf: &mut [u8], timeout: Duration) -> Result<usize, Error> {
+        (**self).read_with_timeout(buf, timeout)
+    }
+}
+
+impl<B: BufRead + ?Sized> BufRead for &mut B {
+    fn fill_buf(&mut self) -> Result<&[u8], Error> {
+        (**self).fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        (**self).consume(amt)
+    }
+
+    fn read_line_max(&mut self, buf: &mut FuriString, max_len: usize) -> Result<usize, Error> {
+        (**self).read_line_max(buf, max_len)
+    }
+}
+
+impl<S: Seek + ?Sized> Seek for &mut S {
+    fn seek(&mut self, pos: SeekFrom) -> Result<usize, Error> {
+        (**self).seek(pos)
+    }
+
+    fn rewind(&mut self) -> Result<(), Error> {
+        (**self).rewind()
+    }
+
+    fn stream_len(&mut self) -> Result<usize, Error> {
+        (**self).stream_len()
+    }
+
+    fn stream_position(&mut self) -> Result<usize, Error> {
+        (**self).stream_position()
+    }
+}
+
+impl<W: Write + ?Sized> Write for &mut W {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        (**self).write(buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        (**self).flush()
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> Result<(), Error> {
+        (**self).write_all(buf)
+    }
+
+    fn write_fmt(&mut self, args: fmt::Arguments<'_>) -> Result<(), Error> {
+        (**self).write_fmt(args)
+    }
+}
+
+/// A reader that reads at most a number of bytes from another, created by
+/// [`Read::take`].
+///
+/// Once the limit is reached, reads return `Ok(0)` as at the end of a source, even if
+/// the inner reader has more bytes.
+pub struct Take<R> {
+    inner: R,
+    limit: u64,
+}
+
+impl<R> Take<R> {
+    /// Returns the number of bytes that can still be read.
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    /// Sets the number of bytes that can still be read.
+    pub fn set_limit(&mut self, limit: u64) {
+        self.limit = limit;
+    }
+
+    /// Returns a reference to the inner reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the inner reader.
+    ///
+    /// Reading from it directly doesn't count towards the limit.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Returns the inner reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Returns the length of the longest read into a buffer of `len` bytes.
+    fn max_len(&self, len: usize) -> usize {
+        usize::try_from(self.limit).map_or(len, |limit| limit.min(len))
+    }
+}
+
+impl<R: Read> Read for Take<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        if self.limit == 0 {
+            return Ok(0);
+        }
+        let max = self.max_len(buf.len());
+        let n = self.inner.read(&mut buf[..max])?;
+        self.limit -= n as u64;
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for Take<R> {
+    fn fill_buf(&mut self) -> Result<&[u8], Error> {
+        if self.limit == 0 {
+            return Ok(&[]);
+        }
+        let limit = self.limit;
+        let buf = self.inner.fill_buf()?;
+        let max = usize::try_from(limit).map_or(buf.len(), |limit| limit.min(buf.len()));
+        Ok(&buf[..max])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        let amt = self.max_len(amt);
+        self.limit -= amt as u64;
+        self.inner.consume(amt);
+    }
+}
+
+/// A reader that reads all bytes of one reader, then those of another, created by
+/// [`Read::chain`].
+pub struct Chain<A, B> {
+    first: A,
+    second: B,
+    done_first: bool,
+}
+
+impl<A, B> Chain<A, B> {
+    /// Returns references to the two readers.
+    pub fn get_ref(&self) -> (&A, &B) {
+        (&self.first, &self.second)
+    }
+
+    /// Returns mutable references to the two readers.
+    pub fn get_mut(&mut self) -> (&mut A, &mut B) {
+        (&mut self.first, &mut self.second)
+    }
+
+    /// Returns the two readers.
+    pub fn into_inner(self) -> (A, B) {
+        (self.first, self.second)
+    }
+}
+
+impl<A: Read, B: Read> Read for Chain<A, B> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        if !self.done_first {
+            match self.first.read(buf)? {
+                0 if !buf.is_empty() => self.done_first = true,
+                n => return Ok(n),
+            }
+        }
+        self.second.read(buf)
+    }
+}
+
+impl<A: BufRead, B: BufRead> BufRead for Chain<A, B> {
+    fn fill_buf(&mut self) -> Result<&[u8], Error> {
+        if !self.done_first {
+            match self.first.fill_buf()? {
+                [] => self.done_first = true,
+                buf => return Ok(buf),
+            }
+        }
+        self.second.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if self.done_first {
+            self.second.consume(amt);
+        } else {
+            self.first.consume(amt);
+        }
+    }
+}
+
+/// Adapts a [`Write`] to [`fmt::Write`] and [`ufmt::uWrite`], so that formatted text
+/// can be written to it with `write!` and `uwrite!`.
+///
+/// `fmt::Write` can only return [`fmt::Error`], so the [`Error`] of a failed write is
+/// kept for [`FmtWriter::error`]. With `uWrite`, it is returned directly.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use core::fmt::Write as _;
+/// # use flipperzero::io::{Error, FmtWriter};
+/// # use flipperzero::storage::OpenOptions;
+/// # fn main() -> Result<(), Error> {
+/// let mut file = OpenOptions::new()
+///     .write(true)
+///     .open_append(true)
+///     .open(c"/ext/log.txt")?;
+/// let (temp, ts) = (21, 1234);
+///
+/// let mut writer = FmtWriter::new(&mut file);
+/// if writeln!(writer, "temp={} ts={}", temp, ts).is_err() {
+///     return Err(writer.error().unwrap_or(Error::Internal));
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct FmtWriter<'a, W: Write + ?Sized> {
+    inner: &'a mut W,
+    error: Option<Error>,
+}
+
+impl<'a, W: Write + ?Sized> FmtWriter<'a, W> {
+    /// Writes formatted text to `inner`.
+    pub fn new(inner: &'a mut W) -> Self {
+        Self { inner, error: None }
+    }
+
+    /// Returns the error of the last write that failed, if any.
+    pub fn error(&self) -> Option<Error> {
+        self.error
+    }
+}
+
+impl<W: Write + ?Sized> fmt::Write for FmtWriter<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|e| {
+            self.error = Some(e);
+            fmt::Error
+        })
+    }
+}
+
+impl<W: Write + ?Sized> ufmt::uWrite for FmtWriter<'_, W> {
+    type Error = Error;
+
+    fn write_str(&mut self, s: &str) -> Result<(), Error> {
+        self.inner.write_all(s.as_bytes())
+    }
 }
 
 /// Enumeration of possible methods to seek within an I/O object.
 ///
 /// It is used by the Seek trait.
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum SeekFrom {
     Start(u64),
     End(i64),
     Current(i64),
 }
+
+#[cfg(feature = "embedded-io")]
+impl From<embedded_io::SeekFrom> for SeekFrom {
+    fn from(pos: embedded_io::SeekFrom) -> Self {
+        match pos {
+            embedded_io::SeekFrom::Start(offset) => Self::Start(offset),
+            embedded_io::SeekFrom::End(offset) => Self::End(offset),
+            embedded_io::SeekFrom::Current(offset) => Self::Current(offset),
+        }
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl From<SeekFrom> for embedded_io::SeekFrom {
+    fn from(pos: SeekFrom) -> Self {
+        match pos {
+            SeekFrom::Start(offset) => Self::Start(offset),
+            SeekFrom::End(offset) => Self::End(offset),
+            SeekFrom::Current(offset) => Self::Current(offset),
+        }
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    #[cfg(feature = "alloc")]
+    use alloc::{string::String, vec::Vec};
+
+    use flipperzero_sys as sys;
+
+    use super::cursor::Cursor;
+    use super::{fs_result, BufRead, Error, ErrorKind, FmtWriter, Read, Seek, SeekFrom, Write};
+    use crate::furi::string::FuriString;
+
+    #[test]
+    fn descriptions_match_firmware() {
+        for code in 1..=sys::FS_Error_FSE_ALREADY_OPEN + 1 {
+            let error = Error::try_from_sys(code).unwrap();
+            let sys_description = error.sys_description().unwrap();
+            assert_eq!(error.description().as_bytes(), sys_description.to_bytes());
+        }
+        assert!(Error::WriteZero.sys_description().is_none());
+    }
+
+    #[test]
+    fn every_error_has_a_kind() {
+        let kinds = [
+            (Error::NotReady, ErrorKind::NotReady),
+            (Error::Exists, ErrorKind::AlreadyExists),
+            (Error::NotExists, ErrorKind::NotFound),
+            (Error::InvalidParameter, ErrorKind::InvalidInput),
+            (Error::Denied, ErrorKind::PermissionDenied),
+            (Error::InvalidName, ErrorKind::InvalidInput),
+            (Error::Internal, ErrorKind::Other),
+            (Error::NotImplemented, ErrorKind::Unsupported),
+            (Error::AlreadyOpen, ErrorKind::ResourceBusy),
+            (Error::WriteZero, ErrorKind::WriteZero),
+            (Error::UnexpectedEof, ErrorKind::UnexpectedEof),
+            (Error::InvalidData, ErrorKind::InvalidData),
+            (Error::TimedOut, ErrorKind::TimedOut),
+            (
+                Error::try_from_sys(sys::FS_Error_FSE_ALREADY_OPEN + 1).unwrap(),
+                ErrorKind::Other,
+            ),
+        ];
+        for (error, kind) in kinds {
+            assert_eq!(error.kind(), kind);
+        }
+    }
+
+    #[test]
+    fn predicates_follow_kind() {
+        assert!(Error::NotExists.is_not_found());
+        assert!(Error::Exists.is_already_exists());
+        assert!(Error::NotReady.is_not_ready());
+        assert!(Error::Denied.is_permission_denied());
+        assert!(!Error::InvalidName.is_not_found());
+        assert!(!Error::Internal.is_not_ready());
+    }
+
+    #[test]
+    fn errors_compare_by_kind() {
+        assert_eq!(
+            Error::try_from_sys(sys::FS_Error_FSE_NOT_EXIST),
+            Some(Error::NotExists)
+        );
+        assert_ne!(Error::NotExists, Error::Exists);
+        assert!(Error::try_from_sys(sys::FS_Error_FSE_OK).is_none());
+    }
+
+    #[test]
+    fn unknown_statuses_are_kept() {
+        let error = Error::try_from_sys(sys::FS_Error::MAX).unwrap();
+        assert_eq!(error.to_sys(), Some(sys::FS_Error::MAX));
+        assert_eq!(error.kind(), ErrorKind::Other);
+        assert_eq!(error.description(), "unknown error");
+    }
+
+    #[test]
+    fn composes_with_core_errors() {
+        fn describe(error: &dyn core::error::Error) -> bool {
+            error.source().is_none()
+        }
+        assert!(describe(&Error::NotExists));
+    }
+
+    #[cfg(feature = "embedded-io")]
+    #[test]
+    fn errors_map_onto_embedded_io() {
+        use embedded_io::ErrorKind as Kind;
+
+        let kinds = [
+            (Error::NotExists, Kind::NotFound),
+            (Error::Exists, Kind::AlreadyExists),
+            (Error::Denied, Kind::PermissionDenied),
+            (Error::InvalidParameter, Kind::InvalidInput),
+            (Error::InvalidName, Kind::InvalidInput),
+            (Error::NotImplemented, Kind::Unsupported),
+            (Error::WriteZero, Kind::WriteZero),
+            (Error::InvalidData, Kind::InvalidData),
+            (Error::TimedOut, Kind::TimedOut),
+            (Error::NotReady, Kind::Other),
+            (Error::AlreadyOpen, Kind::Other),
+            (Error::UnexpectedEof, Kind::Other),
+            (Error::Internal, Kind::Other),
+        ];
+        for (error, kind) in kinds {
+            assert_eq!(embedded_io::Error::kind(&error), kind);
+        }
+    }
+
+    #[cfg(feature = "embedded-io")]
+    #[test]
+    fn seek_froms_convert_both_ways() {
+        use super::SeekFrom;
+
+        let seek_froms = [
+            (SeekFrom::Start(3), embedded_io::SeekFrom::Start(3)),
+            (SeekFrom::End(-2), embedded_io::SeekFrom::End(-2)),
+            (SeekFrom::Current(7), embedded_io::SeekFrom::Current(7)),
+        ];
+        for (ours, theirs) in seek_froms {
+            assert_eq!(embedded_io::SeekFrom::from(ours), theirs);
+            assert_eq!(embedded_io::SeekFrom::from(SeekFrom::from(theirs)), theirs);
+        }
+    }
+
+    #[test]
+    fn statuses_become_results() {
+        assert!(fs_result(sys::FS_Error_FSE_OK).is_ok());
+        assert_eq!(fs_result(sys::FS_Error_FSE_DENIED), Err(Error::Denied));
+    }
+
+    /// Keeps up to 16 bytes, failing with `Error::Denied` once it is full.
+    #[derive(Default)]
+    struct Sink {
+        buf: [u8; 16],
+        len: usize,
+    }
+
+    impl Write for Sink {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+            if self.len == self.buf.len() {
+                return Err(Error::Denied);
+            }
+            let n = buf.len().min(self.buf.len() - self.len);
+            self.buf[self.len..self.len + n].copy_from_slice(&buf[..n]);
+            self.len += n;
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn write_formats_text() {
+        let mut sink = Sink::default();
+        write!(sink, "t={} ", 21).unwrap();
+        write!(sink, "ts={}", 1234).unwrap();
+        assert_eq!(&sink.buf[..sink.len], b"t=21 ts=1234");
+
+        assert_eq!(write!(sink, "{}", 1234567890), Err(Error::Denied));
+        assert_eq!(sink.len, 16);
+    }
+
+    #[test]
+    fn fmt_writer_keeps_errors() {
+        let mut sink = Sink::default();
+        let mut writer = FmtWriter::new(&mut sink);
+        assert!(core::fmt::Write::write_str(&mut writer, "0123456789").is_ok());
+        assert!(writer.error().is_none());
+        assert!(core::fmt::Write::write_str(&mut writer, "0123456789").is_err());
+        assert_eq!(writer.error(), Some(Error::Denied));
+
+        let mut sink = Sink::default();
+        let mut writer = FmtWriter::new(&mut sink);
+        ufmt::uwrite!(writer, "n={}", 7u8).unwrap();
+        assert_eq!(
+            ufmt::uwrite!(writer, "{}", 1234567890123456u64),
+            Err(Error::Denied)
+        );
+    }
+
+    #[test]
+    fn read_exact_fills_across_short_reads() {
+        let mut reader = Cursor::new(b"0123456789").max(3);
+        let mut buf = [0; 8];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"01234567");
+        assert_eq!(reader.read_exact(&mut [0; 3]), Err(Error::UnexpectedEof));
+        assert!(reader.read_exact(&mut []).is_ok());
+
+        let mut reader = Cursor::new(b"01").max(3).fail_at(2, Error::Internal);
+        assert_eq!(reader.read_exact(&mut buf), Err(Error::Internal));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn read_to_end_reads_everything() {
+        let data: Vec<u8> = (0..1000u32).map(|i| i as u8).collect();
+        let mut reader = Cursor::new(&data).max(100);
+        let mut buf = Vec::from(*b"prefix");
+        assert_eq!(reader.read_to_end(&mut buf), Ok(1000));
+        assert_eq!(&buf[..6], b"prefix");
+        assert_eq!(buf[6..], data[..]);
+
+        // Growing by doubling takes far fewer reads than growing by the probe size.
+        let mut reader = Cursor::new(&data);
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf).unwrap();
+        assert_eq!(buf, data);
+        assert!(reader.reads < 20);
+
+        // An exact capacity is probed rather than grown.
+        let mut reader = Cursor::new(&data);
+        let mut buf = Vec::with_capacity(1000);
+        reader.read_to_end(&mut buf).unwrap();
+        assert_eq!(buf.capacity(), 1000);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn read_to_end_keeps_bytes_before_errors() {
+        let mut reader = Cursor::new(b"abcdef").max(4).fail_at(6, Error::NotReady);
+        let mut buf = Vec::new();
+        assert_eq!(reader.read_to_end(&mut buf), Err(Error::NotReady));
+        assert_eq!(buf, b"abcdef");
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn read_to_string_checks_utf8() {
+        let mut buf = String::from("a");
+        let mut reader = Cursor::new("bc ✓".as_bytes()).max(2);
+        assert_eq!(reader.read_to_string(&mut buf), Ok(6));
+        assert_eq!(buf, "abc ✓");
+
+        let mut reader = Cursor::new(b"d\xff").max(2);
+        assert_eq!(reader.read_to_string(&mut buf), Err(Error::InvalidData));
+        assert_eq!(buf, "abc ✓");
+    }
+
+    #[test]
+    fn read_line_reads_across_buffers() {
+        let mut reader = Cursor::new(b"first line\nsecond\r\nlast").max(3);
+        let mut line = FuriString::from("> ");
+        assert_eq!(reader.read_line(&mut line), Ok(11));
+        assert_eq!(line, "> first line\n");
+
+        line.clear();
+        assert_eq!(reader.read_line(&mut line), Ok(8));
+        assert_eq!(line, "second\r\n");
+
+        line.clear();
+        assert_eq!(reader.read_line(&mut line), Ok(4));
+        assert_eq!(line, "last");
+        assert_eq!(reader.read_line(&mut line), Ok(0));
+        assert_eq!(line, "last");
+    }
+
+    #[test]
+    fn lines_strip_line_endings() {
+        let mut lines = Cursor::new(b"a\r\nbb\n\n\r\nlast\r").max(2).lines();
+        for expected in ["a", "bb", "", "", "last\r"] {
+            assert_eq!(lines.next().unwrap().unwrap(), expected);
+        }
+        assert!(lines.next().is_none());
+        assert!(lines.next().is_none());
+
+        assert!(Cursor::new(b"").max(2).lines().next().is_none());
+    }
+
+    #[test]
+    fn long_lines_are_invalid() {
+        let mut reader = Cursor::new(b"abc\nabcd\nabcdefgh\n").max(3);
+        let mut line = FuriString::new();
+        assert_eq!(reader.read_line_max(&mut line, 4), Ok(4));
+        line.clear();
+        assert_eq!(reader.read_line_max(&mut line, 4), Err(Error::InvalidData));
+        assert_eq!(line, "abcd");
+        assert_eq!(reader.rest(), b"\nabcdefgh\n");
+
+        let mut lines = Cursor::new(b"ok\nabcdefgh\nok\n").max(3).lines().max_len(5);
+        assert_eq!(lines.next().unwrap().unwrap(), "ok");
+        assert_eq!(lines.next().unwrap().err(), Some(Error::InvalidData));
+        assert!(lines.next().is_none());
+        assert_eq!(lines.into_inner().rest(), b"fgh\nok\n");
+    }
+
+    #[test]
+    fn nul_bytes_are_invalid_in_lines() {
+        let mut reader = Cursor::new(b"ab\0cd\n").max(8);
+        let mut line = FuriString::new();
+        assert_eq!(reader.read_line(&mut line), Err(Error::InvalidData));
+        assert_eq!(line, "ab");
+        assert_eq!(reader.rest(), b"\0cd\n");
+    }
+
+    #[test]
+    fn read_line_keeps_bytes_before_errors() {
+        let mut reader = Cursor::new(b"abc").max(2).fail_at(3, Error::NotReady);
+        let mut line = FuriString::new();
+        assert_eq!(reader.read_line(&mut line), Err(Error::NotReady));
+        assert_eq!(line, "abc");
+
+        let mut lines = Cursor::new(b"abc")
+            .max(2)
+            .fail_at(3, Error::NotReady)
+            .lines();
+        assert_eq!(lines.next().unwrap().err(), Some(Error::NotReady));
+        assert!(lines.next().is_none());
+    }
+
+    #[test]
+    fn mutable_references_are_readers_and_writers() {
+        fn read_all(mut reader: impl Read, buf: &mut [u8]) -> usize {
+            let mut len = 0;
+            loop {
+                match reader.read(&mut buf[len..]).unwrap() {
+                    0 => return len,
+                    n => len += n,
+                }
+            }
+        }
+
+        let mut reader = Cursor::new(b"abcdef").max(2);
+        let mut buf = [0; 8];
+        assert_eq!(read_all((&mut reader).take(3), &mut buf), 3);
+        assert_eq!(read_all(&mut reader, &mut buf[3..]), 3);
+        assert_eq!(&buf[..6], b"abcdef");
+
+        fn first_line(mut reader: impl BufRead) -> FuriString {
+            let mut line = FuriString::new();
+            reader.read_line(&mut line).unwrap();
+            line
+        }
+
+        let mut chunks = Cursor::new(b"a\nb\n").max(1);
+        assert_eq!(first_line(&mut chunks), "a\n");
+        assert_eq!(chunks.rest(), b"b\n");
+
+        let mut sink = Sink::default();
+        write!(&mut sink, "{}", 12).unwrap();
+        (&mut &mut sink).write_all(b"3").unwrap();
+        assert_eq!(&sink.buf[..sink.len], b"123");
+    }
+
+    #[test]
+    fn take_stops_at_the_limit() {
+        let mut reader = Cursor::new(b"abcdefghij").max(3).take(7);
+        let mut buf = [0; 16];
+        assert_eq!(reader.read(&mut buf), Ok(3));
+        assert_eq!(reader.limit(), 4);
+        assert_eq!(reader.read(&mut buf[..2]), Ok(2));
+        assert_eq!(reader.limit(), 2);
+        assert_eq!(reader.read(&mut buf), Ok(2));
+        assert_eq!(reader.read(&mut buf), Ok(0));
+        assert_eq!(reader.get_ref().rest(), b"hij");
+
+        reader.set_limit(10);
+        assert_eq!(reader.read(&mut buf), Ok(3));
+        assert_eq!(reader.read(&mut buf), Ok(0));
+        assert_eq!(reader.limit(), 7);
+
+        let mut reader = Cursor::new(b"abc").max(8).take(0);
+        assert_eq!(reader.read(&mut buf), Ok(0));
+        assert_eq!(reader.into_inner().reads, 0);
+    }
+
+    #[test]
+    fn take_limits_buffered_reads() {
+        let mut reader = Cursor::new(b"one\ntwo\nthree\n").max(5).take(6);
+        assert_eq!(reader.fill_buf(), Ok(&b"one\nt"[..5]));
+        reader.consume(2);
+        assert_eq!(reader.limit(), 4);
+
+        let lines = reader.lines();
+        let mut lines = lines.max_len(16);
+        assert_eq!(lines.next().unwrap().unwrap(), "e");
+        assert_eq!(lines.next().unwrap().unwrap(), "tw");
+        assert!(lines.next().is_none());
+        assert_eq!(lines.into_inner().into_inner().rest(), b"o\nthree\n");
+    }
+
+    #[test]
+    fn chain_reads_both_readers() {
+        let mut reader = Cursor::new(b"abc").max(2).chain(Cursor::new(b"de").max(8));
+        let mut buf = [0; 8];
+        assert_eq!(reader.read(&mut buf), Ok(2));
+        assert_eq!(reader.read(&mut buf[2..]), Ok(1));
+        assert_eq!(reader.read(&mut buf[..0]), Ok(0));
+        assert_eq!(reader.read(&mut buf[3..]), Ok(2));
+        assert_eq!(reader.read(&mut buf), Ok(0));
+        assert_eq!(&buf[..5], b"abcde");
+
+        let (first, second) = reader.into_inner();
+        assert_eq!((first.reads, second.reads), (4, 2));
+
+        let mut lines = Cursor::new(b"a\nb")
+            .max(8)
+            .chain(Cursor::new(b"c\nd").max(8))
+            .lines();
+        assert_eq!(lines.next().unwrap().unwrap(), "a");
+        assert_eq!(lines.next().unwrap().unwrap(), "bc");
+        assert_eq!(lines.next().unwrap().unwrap(), "d");
+        assert!(lines.next().is_none());
+    }
+
+    #[test]
+    fn chain_passes_errors_through() {
+        let first = Cursor::new(b"a").max(8).fail_at(1, Error::NotReady);
+        let mut reader = first.chain(Cursor::new(b"b").max(8));
+        let mut buf = [0; 4];
+        assert_eq!(reader.read(&mut buf), Ok(1));
+        assert_eq!(reader.read(&mut buf), Err(Error::NotReady));
+        assert_eq!(reader.get_ref().1.reads, 0);
+
+        // The error is returned once.
+        assert_eq!(reader.read(&mut buf), Ok(1));
+        assert_eq!(buf[0], b'b');
+    }
+
+    #[test]
+    fn mutable_references_seek() {
+        /// A position in a source of 100 bytes.
+        struct Position(usize);
+
+        impl Seek for Position {
+            fn seek(&mut self, pos: SeekFrom) -> Result<usize, Error> {
+                self.0 = match pos {
+                    SeekFrom::Start(offset) => offset as usize,
+                    SeekFrom::End(offset) => (100 + offset) as usize,
+                    SeekFrom::Current(offset) => (self.0 as i64 + offset) as usize,
+                };
+                Ok(self.0)
+            }
+        }
+
+        fn skip(mut seeker: impl Seek) -> usize {
+            seeker.seek(SeekFrom::Current(5)).unwrap()
+        }
+
+        fn len_and_rewind(mut seeker: impl Seek) -> usize {
+            let len = seeker.stream_len().unwrap();
+            seeker.rewind().unwrap();
+            len
+        }
+
+        let mut position = Position(0);
+        assert_eq!(skip(&mut position), 5);
+        assert_eq!(skip(&mut position), 10);
+        assert_eq!(len_and_rewind(&mut position), 100);
+        assert_eq!(position.0, 0);
+    }
+}
@@ -0,0 +1,176 @@
+//! An in-memory stream for tests.
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use super::{BufRead, Error, Read, Seek, SeekFrom, Write};
+
+/// A stream over the bytes of `B`, such as a `&[u8]`, a `[u8; N]` or a `Vec<u8>`, which
+/// can be made to read and write a few bytes at a time and to fail.
+///
+/// Writes overwrite `B` in place, and extend the stream up to the length of `B`, or
+/// without limit for a `Vec<u8>`. Seeking past the end is allowed, and writing there
+/// fills the gap with zeros.
+pub(crate) struct Cursor<B> {
+    buf: B,
+    /// The length of the stream, which is at most the length of `buf`.
+    len: usize,
+    pos: usize,
+    max: usize,
+    fail_at: Option<(usize, Error)>,
+    /// The number of calls to [`Read::read`].
+    pub(crate) reads: usize,
+}
+
+impl<B: AsRef<[u8]>> Cursor<B> {
+    /// Creates a stream of the bytes of `buf`.
+    pub(crate) fn new(buf: B) -> Self {
+        let len = buf.as_ref().len();
+        Self {
+            buf,
+            len,
+            pos: 0,
+            max: usize::MAX,
+            fail_at: None,
+            reads: 0,
+        }
+    }
+
+    /// Creates an empty stream, which writes can extend up to the length of `buf`.
+    pub(crate) fn empty(buf: B) -> Self {
+        Self {
+            len: 0,
+            ..Self::new(buf)
+        }
+    }
+
+    /// Makes reads and writes take at most `max` bytes at a time.
+    pub(crate) fn max(mut self, max: usize) -> Self {
+        self.max = max;
+        self
+    }
+
+    /// Makes the next read at `pos` fail with `error`, and reads before it stop at `pos`.
+    ///
+    /// Reads continue from `pos` after the failure.
+    pub(crate) fn fail_at(mut self, pos: usize, error: Error) -> Self {
+        self.set_fail_at(pos, error);
+        self
+    }
+
+    /// Makes the next read at `pos` fail with `error`, as by [`Cursor::fail_at`].
+    pub(crate) fn set_fail_at(&mut self, pos: usize, error: Error) {
+        self.fail_at = Some((pos, error));
+    }
+
+    /// Returns the bytes of the stream.
+    pub(crate) fn data(&self) -> &[u8] {
+        &self.buf.as_ref()[..self.len]
+    }
+
+    /// Returns the bytes after the position, which are yet to be read.
+    pub(crate) fn rest(&self) -> &[u8] {
+        self.data().get(self.pos..).unwrap_or_default()
+    }
+
+    /// Returns how many bytes the next read may return, or `error` if it fails.
+    fn next_len(&mut self) -> Result<usize, Error> {
+        let mut n = self.rest().len().min(self.max);
+        match self.fail_at {
+            Some((at, error)) if at == self.pos => {
+                self.fail_at = None;
+                return Err(error);
+            }
+            Some((at, _)) if at > self.pos => n = n.min(at - self.pos),
+            _ => {}
+        }
+        Ok(n)
+    }
+}
+
+impl<B: Buffer> Cursor<B> {
+    /// Returns the bytes of the stream, for changing them behind the back of readers.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn data_mut(&mut self) -> &mut [u8] {
+        &mut self.buf.as_mut()[..self.len]
+    }
+}
+
+impl<B: AsRef<[u8]>> Read for Cursor<B> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        self.reads += 1;
+        let n = self.next_len()?.min(buf.len());
+        buf[..n].copy_from_slice(&self.rest()[..n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+impl<B: AsRef<[u8]>> BufRead for Cursor<B> {
+    fn fill_buf(&mut self) -> Result<&[u8], Error> {
+        let n = self.next_len()?;
+        Ok(&self.rest()[..n])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos += amt;
+    }
+}
+
+impl<B: Buffer> Write for Cursor<B> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        let end = self.pos.saturating_add(buf.len().min(self.max));
+        let room = self.buf.reserve(end);
+        let n = end.min(room).saturating_sub(self.pos);
+        if n == 0 {
+            return Ok(0);
+        }
+        if self.pos > self.len {
+            self.buf.as_mut()[self.len..self.pos].fill(0);
+        }
+        self.buf.as_mut()[self.pos..self.pos + n].copy_from_slice(&buf[..n]);
+        self.pos += n;
+        self.len = self.len.max(self.pos);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<B: AsRef<[u8]>> Seek for Cursor<B> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<usize, Error> {
+        let target = match pos {
+            SeekFrom::Start(offset) => i64::try_from(offset).unwrap_or(i64::MAX),
+            SeekFrom::End(offset) => (self.len as i64).saturating_add(offset),
+            SeekFrom::Current(offset) => (self.pos as i64).saturating_add(offset),
+        };
+        self.pos = usize::try_from(target).map_err(|_| Error::InvalidParameter)?;
+        Ok(self.pos)
+    }
+}
+
+/// The bytes of a [`Cursor`].
+pub(crate) trait Buffer: AsRef<[u8]> + AsMut<[u8]> {
+    /// Makes room for `len` bytes if it can, and returns how many bytes there is room
+    /// for.
+    fn reserve(&mut self, len: usize) -> usize {
+        let _ = len;
+        self.as_ref().len()
+    }
+}
+
+impl Buffer for &mut [u8] {}
+
+impl<const N: usize> Buffer for [u8; N] {}
+
+#[cfg(feature = "alloc")]
+impl Buffer for Vec<u8> {
+    fn reserve(&mut self, len: usize) -> usize {
+        if self.len() < len {
+            self.resize(len, 0);
+        }
+        self.len()
+    }
+}
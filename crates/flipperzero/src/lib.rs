@@ -8,22 +8,56 @@
 #![cfg_attr(all(test, not(miri)), no_main)]
 #![cfg_attr(all(test, miri), feature(start))]
 #![cfg_attr(docsrs, feature(doc_cfg))]
+// The test runner chains an iterator per test module, which is deeper than the default.
+#![cfg_attr(test, recursion_limit = "256")]
 #![deny(rustdoc::broken_intra_doc_links)]
 
 #[cfg(any(feature = "alloc", docsrs))]
 #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
 extern crate alloc;
 
+#[cfg(all(feature = "std", not(target_os = "none")))]
+extern crate std;
+
+pub mod audio;
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub mod ble;
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub mod cli;
+pub mod crypto;
+pub mod datetime;
+pub mod devices;
 pub mod dialogs;
 pub mod dolphin;
+pub mod expansion;
+pub mod fmt;
 pub mod furi;
 pub mod gpio;
 pub mod gui;
+pub mod infrared;
+pub mod input;
 pub mod io;
+pub mod loader;
 pub mod macros;
+pub mod nfc;
 pub mod notification;
+pub mod power;
+pub mod protocols;
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+pub mod serde;
+pub mod serial;
 pub mod storage;
+pub mod subghz;
+#[cfg(feature = "testsuite")]
+#[cfg_attr(docsrs, doc(cfg(feature = "testsuite")))]
+pub mod testsuite;
 pub mod toolbox;
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub mod usb;
 
 #[doc(hidden)]
 pub mod __macro_support {
@@ -57,14 +91,114 @@ flipperzero_test::tests_runner!(
     name = "flipperzero-rs Unit Tests",
     stack_size = 4096,
     [
+        crate::audio::tests,
+        #[cfg(feature = "alloc")]
+        crate::cli::tests,
+        crate::crypto::tests,
+        crate::datetime::tests,
+        #[cfg(feature = "alloc")]
+        crate::devices::eeprom::tests,
+        #[cfg(feature = "alloc")]
+        crate::devices::flash::tests,
+        crate::dialogs::tests,
+        crate::expansion::tests,
+        crate::fmt::tests,
+        crate::furi::event_flag::tests,
+        #[cfg(feature = "log")]
+        crate::furi::log::logger::tests,
         crate::furi::log::metadata::tests,
         crate::furi::message_queue::tests,
+        crate::furi::record::tests,
         crate::furi::rng::tests,
+        crate::furi::stream_buffer::tests,
         crate::furi::string::tests,
         crate::furi::sync::tests,
+        #[cfg(feature = "alloc")]
+        crate::furi::thread::tests,
         crate::furi::time::tests,
+        #[cfg(feature = "alloc")]
+        crate::furi::timer::tests,
+        #[cfg(feature = "alloc")]
+        crate::gpio::edge_logger::tests,
+        crate::gpio::tests,
+        crate::gpio::adc::tests,
         crate::gpio::i2c::tests,
+        #[cfg(feature = "alloc")]
+        crate::gpio::interrupt::tests,
+        crate::gui::canvas::tests,
+        crate::gui::error_dialog::tests,
+        #[cfg(feature = "alloc")]
+        crate::gui::hex_viewer::tests,
+        crate::gui::icon::tests,
+        #[cfg(feature = "alloc")]
+        crate::gui::image::tests,
+        #[cfg(feature = "alloc")]
+        crate::gui::loading::tests,
+        #[cfg(feature = "alloc")]
+        crate::gui::scene_manager::tests,
+        #[cfg(feature = "alloc")]
+        crate::gui::settings_screen::tests,
+        #[cfg(feature = "alloc")]
+        crate::gui::text_input::tests,
+        #[cfg(feature = "alloc")]
+        crate::gui::text_viewer::tests,
+        #[cfg(feature = "alloc")]
+        crate::gui::widget::tests,
+        #[cfg(feature = "alloc")]
+        crate::infrared::file::tests,
+        #[cfg(feature = "alloc")]
+        crate::infrared::format::tests,
+        #[cfg(feature = "alloc")]
+        crate::infrared::receiver::tests,
+        crate::input::tests,
+        crate::io::tests,
+        #[cfg(feature = "alloc")]
+        crate::loader::tests,
+        crate::nfc::tests,
+        crate::notification::tests,
+        crate::notification::backlight::tests,
+        crate::notification::vibro::tests,
+        #[cfg(feature = "alloc")]
+        crate::power::tests,
+        #[cfg(feature = "alloc")]
+        crate::protocols::expansion::tests,
+        #[cfg(feature = "alloc")]
+        crate::protocols::xmodem::tests,
+        #[cfg(feature = "alloc")]
+        crate::protocols::ymodem::tests,
+        #[cfg(feature = "serde")]
+        crate::serde::tests,
+        crate::storage::tests,
+        #[cfg(feature = "alloc")]
+        crate::storage::block_cache::tests,
+        #[cfg(feature = "alloc")]
+        crate::storage::buffered::tests,
+        #[cfg(feature = "alloc")]
+        crate::storage::context::tests,
+        #[cfg(feature = "alloc")]
+        crate::storage::double_buffered::tests,
+        #[cfg(feature = "alloc")]
+        crate::storage::events::tests,
+        crate::storage::format::tests,
+        #[cfg(feature = "alloc")]
+        crate::storage::mem::tests,
+        crate::storage::path::tests,
+        crate::storage::perf::tests,
+        #[cfg(feature = "alloc")]
+        crate::storage::read_ahead::tests,
+        crate::storage::record::tests,
+        #[cfg(feature = "alloc")]
+        crate::storage::tar::tests,
+        #[cfg(feature = "alloc")]
+        crate::storage::walk::tests,
+        crate::subghz::tests,
+        #[cfg(feature = "testsuite")]
+        crate::testsuite::tests,
         crate::toolbox::crc32::tests,
+        #[cfg(feature = "alloc")]
+        crate::usb::hid::tests,
+        #[cfg(feature = "alloc")]
+        crate::usb::mass_storage::tests,
         // crate::toolbox::md5::tests,
         // crate::toolbox::sha256::tests,
     ]
@@ -0,0 +1,221 @@
+//! Loader service: starting apps, and the events of apps starting and stopping.
+
+use core::ffi::CStr;
+use core::fmt;
+
+use flipperzero_sys as sys;
+
+#[cfg(feature = "alloc")]
+use crate::furi::pubsub::{RecordSubscription, TypeEvent};
+use crate::furi::record::Record;
+use crate::furi::string::FuriString;
+
+/// An event published by the loader.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LoaderEvent {
+    /// An app is about to be loaded.
+    BeforeLoad,
+    /// An app couldn't be loaded.
+    LoadFailed,
+    /// The running app exited.
+    Stopped,
+    /// An event not known to this crate, with its raw value.
+    Unknown(u8),
+}
+
+impl LoaderEvent {
+    #[cfg(feature = "alloc")]
+    pub(crate) fn from_sys(event: &TypeEvent) -> Self {
+        match event.type_ {
+            0 => Self::BeforeLoad,
+            1 => Self::LoadFailed,
+            2 => Self::Stopped,
+            other => Self::Unknown(other),
+        }
+    }
+}
+
+/// Reasons why an app couldn't be started.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// Another app is running, or the loader is locked.
+    AppStarted,
+    /// No app has the given name or path.
+    UnknownApp,
+    /// The app was found but couldn't be loaded, such as an app built for another
+    /// version of the firmware.
+    Internal,
+}
+
+impl Error {
+    fn from_sys(status: sys::LoaderStatus) -> Result<(), Self> {
+        match status {
+            sys::LoaderStatus_LoaderStatusOk => Ok(()),
+            sys::LoaderStatus_LoaderStatusErrorAppStarted => Err(Self::AppStarted),
+            sys::LoaderStatus_LoaderStatusErrorUnknownApp => Err(Self::UnknownApp),
+            _ => Err(Self::Internal),
+        }
+    }
+
+    /// Description associated with [`Error`].
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::AppStarted => "another app is running",
+            Self::UnknownApp => "unknown app",
+            Self::Internal => "app failed to load",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.description())
+    }
+}
+
+impl ufmt::uDisplay for Error {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        f.write_str(self.description())
+    }
+}
+
+impl core::error::Error for Error {}
+
+/// A handle to the loader, which starts apps.
+///
+/// Only one app runs at a time, so starting an app fails with [`Error::AppStarted`]
+/// while another one runs, including the calling app itself. Apps are started by the
+/// name they have in the menu, such as `"NFC"`, or by the path of a `.fap` file, with an
+/// argument string that the app interprets, usually the path of a file to open.
+///
+/// # Examples
+///
+/// ```no_run
+/// use flipperzero::loader::Loader;
+///
+/// let loader = Loader::open();
+/// if !loader.is_locked() {
+///     loader.start(c"NFC", Some(c"/ext/nfc/card.nfc")).ok();
+/// }
+/// ```
+pub struct Loader {
+    record: Record<sys::Loader>,
+}
+
+impl Loader {
+    /// Opens the loader.
+    pub fn open() -> Self {
+        Self {
+            record: Record::open(),
+        }
+    }
+
+    /// Starts the app `name` with `args`, and waits until it has been loaded.
+    pub fn start(&self, name: &CStr, args: Option<&CStr>) -> Result<(), Error> {
+        let args = args.map_or(core::ptr::null(), CStr::as_ptr);
+        let status = unsafe {
+            sys::loader_start(
+                self.record.as_ptr(),
+                name.as_ptr(),
+                args,
+                core::ptr::null_mut(),
+            )
+        };
+        Error::from_sys(status)
+    }
+
+    /// Starts the app `name` with `args`, showing the reason on the screen if it fails.
+    pub fn start_with_gui_error(&self, name: &CStr, args: Option<&CStr>) -> Result<(), Error> {
+        let args = args.map_or(core::ptr::null(), CStr::as_ptr);
+        let status =
+            unsafe { sys::loader_start_with_gui_error(self.record.as_ptr(), name.as_ptr(), args) };
+        Error::from_sys(status)
+    }
+
+    /// Returns `true` if apps can't be started, because an app is running or the
+    /// loader is locked.
+    pub fn is_locked(&self) -> bool {
+        unsafe { sys::loader_is_locked(self.record.as_ptr()) }
+    }
+
+    /// Returns the name of the running app, or `None` if no app is running.
+    pub fn running_app_name(&self) -> Option<FuriString> {
+        let mut name = FuriString::new();
+        unsafe { sys::loader_get_application_name(self.record.as_ptr(), name.as_mut_ptr()) }
+            .then_some(name)
+    }
+
+    /// Opens the main menu of the Flipper Zero.
+    pub fn show_menu(&self) {
+        unsafe { sys::loader_show_menu(self.record.as_ptr()) };
+    }
+
+    /// Subscribes to the events of the loader, calling `callback` for every event.
+    ///
+    /// The closure is called on the loader thread, so it should return quickly. The
+    /// subscription ends when the returned [`LoaderSubscription`] is dropped.
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+    pub fn subscribe<F>(&self, callback: F) -> LoaderSubscription
+    where
+        F: FnMut(LoaderEvent) + Send + 'static,
+    {
+        let loader = Self::open();
+        // Services own their pubsub, and are never freed.
+        let pubsub = unsafe { sys::loader_get_pubsub(loader.record.as_ptr()) };
+        LoaderSubscription {
+            _subscription: unsafe {
+                RecordSubscription::new(loader, pubsub, LoaderEvent::from_sys, callback)
+            },
+        }
+    }
+}
+
+/// A subscription to the events of the loader, created by [`Loader::subscribe`].
+///
+/// The subscription ends when this is dropped.
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub struct LoaderSubscription {
+    _subscription: RecordSubscription<Loader, TypeEvent>,
+}
+
+#[cfg(feature = "alloc")]
+#[flipperzero_test::tests]
+mod tests {
+    use super::{Error, Loader, LoaderEvent};
+    use crate::furi::pubsub::TypeEvent;
+
+    #[test]
+    fn events_map_from_sys() {
+        let events = [
+            LoaderEvent::BeforeLoad,
+            LoaderEvent::LoadFailed,
+            LoaderEvent::Stopped,
+            LoaderEvent::Unknown(3),
+        ];
+        for (type_, event) in events.into_iter().enumerate() {
+            let raw = TypeEvent { type_: type_ as u8 };
+            assert_eq!(LoaderEvent::from_sys(&raw), event);
+        }
+    }
+
+    #[test]
+    fn apps_cant_start_while_this_one_runs() {
+        let loader = Loader::open();
+        assert!(loader.is_locked());
+        assert!(loader.running_app_name().is_some());
+
+        let name = c"rs_no_such_app";
+        assert_eq!(loader.start(name, None), Err(Error::AppStarted));
+
+        // Nothing is started, so this unsubscribes without having seen an event.
+        let subscription = loader.subscribe(|_| {});
+        drop(subscription);
+    }
+}
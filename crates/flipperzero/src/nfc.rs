@@ -0,0 +1,609 @@
+//! NFC tags.
+//!
+//! An [`Nfc`] reads the UIDs and other data of tags, and emulates tags that were read
+//! or loaded from the `.nfc` files of the NFC app with [`NfcDevice`].
+
+use core::ffi::{c_void, CStr};
+use core::fmt;
+use core::marker::PhantomData;
+use core::ptr::NonNull;
+use core::slice;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use flipperzero_sys as sys;
+
+/// Whether an [`Nfc`] exists.
+static IN_USE: AtomicBool = AtomicBool::new(false);
+
+/// Longest UID of an ISO14443-3A tag, in bytes.
+pub const MAX_UID_LEN: usize = 10;
+
+/// Reasons why using NFC failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// Another [`Nfc`] exists.
+    Busy,
+    /// No tag of the protocol is in the field.
+    NotPresent,
+    /// The tag didn't answer in time.
+    Timeout,
+    /// The tag answered with invalid data, such as after being moved away.
+    Communication,
+    /// The UID has a length that the protocol doesn't allow.
+    InvalidUid,
+    /// The operation isn't supported for the protocol.
+    UnsupportedProtocol,
+    /// The file is not an NFC file, or couldn't be read.
+    InvalidFile,
+    /// The file couldn't be written.
+    WriteFailed,
+}
+
+impl Error {
+    /// Description associated with [`Error`].
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Busy => "NFC is busy",
+            Self::NotPresent => "no tag present",
+            Self::Timeout => "tag timed out",
+            Self::Communication => "communication with tag failed",
+            Self::InvalidUid => "invalid UID",
+            Self::UnsupportedProtocol => "unsupported protocol",
+            Self::InvalidFile => "invalid NFC file",
+            Self::WriteFailed => "NFC file couldn't be written",
+        }
+    }
+
+    fn from_iso14443_3a(error: sys::Iso14443_3aError) -> Option<Self> {
+        match error {
+            sys::Iso14443_3aError_Iso14443_3aErrorNone => None,
+            sys::Iso14443_3aError_Iso14443_3aErrorNotPresent => Some(Self::NotPresent),
+            sys::Iso14443_3aError_Iso14443_3aErrorTimeout => Some(Self::Timeout),
+            _ => Some(Self::Communication),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.description())
+    }
+}
+
+impl ufmt::uDisplay for Error {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        f.write_str(self.description())
+    }
+}
+
+/// A protocol of the NFC stack of the firmware.
+///
+/// Protocols build on each other: a MIFARE Classic tag is also an ISO14443-3A tag, which
+/// is its [`Protocol::parent`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    Iso14443_3a,
+    Iso14443_3b,
+    Iso14443_4a,
+    Iso14443_4b,
+    Iso15693_3,
+    Felica,
+    MfUltralight,
+    MfClassic,
+    MfPlus,
+    MfDesfire,
+    Slix,
+    St25tb,
+}
+
+impl Protocol {
+    /// Converts a raw protocol, returning `None` for a protocol not known to this crate.
+    pub fn from_sys(protocol: sys::NfcProtocol) -> Option<Self> {
+        match protocol {
+            sys::NfcProtocol_NfcProtocolIso14443_3a => Some(Self::Iso14443_3a),
+            sys::NfcProtocol_NfcProtocolIso14443_3b => Some(Self::Iso14443_3b),
+            sys::NfcProtocol_NfcProtocolIso14443_4a => Some(Self::Iso14443_4a),
+            sys::NfcProtocol_NfcProtocolIso14443_4b => Some(Self::Iso14443_4b),
+            sys::NfcProtocol_NfcProtocolIso15693_3 => Some(Self::Iso15693_3),
+            sys::NfcProtocol_NfcProtocolFelica => Some(Self::Felica),
+            sys::NfcProtocol_NfcProtocolMfUltralight => Some(Self::MfUltralight),
+            sys::NfcProtocol_NfcProtocolMfClassic => Some(Self::MfClassic),
+            sys::NfcProtocol_NfcProtocolMfPlus => Some(Self::MfPlus),
+            sys::NfcProtocol_NfcProtocolMfDesfire => Some(Self::MfDesfire),
+            sys::NfcProtocol_NfcProtocolSlix => Some(Self::Slix),
+            sys::NfcProtocol_NfcProtocolSt25tb => Some(Self::St25tb),
+            _ => None,
+        }
+    }
+
+    /// Returns the raw protocol, for use with the `sys` bindings.
+    pub fn to_sys(&self) -> sys::NfcProtocol {
+        match self {
+            Self::Iso14443_3a => sys::NfcProtocol_NfcProtocolIso14443_3a,
+            Self::Iso14443_3b => sys::NfcProtocol_NfcProtocolIso14443_3b,
+            Self::Iso14443_4a => sys::NfcProtocol_NfcProtocolIso14443_4a,
+            Self::Iso14443_4b => sys::NfcProtocol_NfcProtocolIso14443_4b,
+            Self::Iso15693_3 => sys::NfcProtocol_NfcProtocolIso15693_3,
+            Self::Felica => sys::NfcProtocol_NfcProtocolFelica,
+            Self::MfUltralight => sys::NfcProtocol_NfcProtocolMfUltralight,
+            Self::MfClassic => sys::NfcProtocol_NfcProtocolMfClassic,
+            Self::MfPlus => sys::NfcProtocol_NfcProtocolMfPlus,
+            Self::MfDesfire => sys::NfcProtocol_NfcProtocolMfDesfire,
+            Self::Slix => sys::NfcProtocol_NfcProtocolSlix,
+            Self::St25tb => sys::NfcProtocol_NfcProtocolSt25tb,
+        }
+    }
+
+    /// Returns the name of the protocol, as shown by the NFC app.
+    pub fn name(self) -> &'static CStr {
+        unsafe { CStr::from_ptr(sys::nfc_device_get_protocol_name(self.to_sys())) }
+    }
+
+    /// Returns the protocol that this protocol builds on, if any.
+    pub fn parent(self) -> Option<Self> {
+        Self::from_sys(unsafe { sys::nfc_protocol_get_parent(self.to_sys()) })
+    }
+
+    /// Returns `true` if this protocol builds on `parent`, directly or not.
+    pub fn has_parent(self, parent: Self) -> bool {
+        unsafe { sys::nfc_protocol_has_parent(self.to_sys(), parent.to_sys()) }
+    }
+
+    /// Returns `true` if tags of the protocol can be emulated with [`Nfc::emulate`].
+    pub fn can_emulate(self) -> bool {
+        matches!(
+            self,
+            Self::Iso14443_3a
+                | Self::Iso14443_4a
+                | Self::Iso15693_3
+                | Self::Felica
+                | Self::MfUltralight
+                | Self::MfClassic
+                | Self::Slix
+        )
+    }
+}
+
+/// The identity of an ISO14443-3A tag, which most tags, such as MIFARE and NTAG ones,
+/// build on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Iso14443a {
+    uid: [u8; MAX_UID_LEN],
+    uid_len: u8,
+    atqa: [u8; 2],
+    sak: u8,
+}
+
+impl Iso14443a {
+    /// Creates the identity of a tag with the given UID, ATQA and SAK.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidUid`] unless the UID is 4, 7 or 10 bytes long.
+    pub fn new(uid: &[u8], atqa: [u8; 2], sak: u8) -> Result<Self, Error> {
+        if !matches!(uid.len(), 4 | 7 | 10) {
+            return Err(Error::InvalidUid);
+        }
+        let mut data = Self {
+            uid: [0; MAX_UID_LEN],
+            uid_len: uid.len() as u8,
+            atqa,
+            sak,
+        };
+        data.uid[..uid.len()].copy_from_slice(uid);
+        Ok(data)
+    }
+
+    /// Returns the UID.
+    pub fn uid(&self) -> &[u8] {
+        &self.uid[..usize::from(self.uid_len)]
+    }
+
+    /// Returns the answer to request, which tells the type of the tag.
+    pub fn atqa(&self) -> [u8; 2] {
+        self.atqa
+    }
+
+    /// Returns the select acknowledge, which tells the protocols the tag supports.
+    pub fn sak(&self) -> u8 {
+        self.sak
+    }
+
+    /// Converts raw tag data, limiting the UID to [`MAX_UID_LEN`] bytes.
+    pub fn from_sys(data: &sys::Iso14443_3aData) -> Self {
+        Self {
+            uid: data.uid,
+            uid_len: data.uid_len.min(MAX_UID_LEN as u8),
+            atqa: data.atqa,
+            sak: data.sak,
+        }
+    }
+
+    /// Returns the raw tag data, for use with the `sys` bindings.
+    pub fn to_sys(&self) -> sys::Iso14443_3aData {
+        sys::Iso14443_3aData {
+            uid: self.uid,
+            uid_len: self.uid_len,
+            atqa: self.atqa,
+            sak: self.sak,
+        }
+    }
+}
+
+/// The data of a tag of any [`Protocol`], as read by [`Nfc::read`] or saved in a `.nfc`
+/// file.
+///
+/// # Examples
+///
+/// ```no_run
+/// use flipperzero::nfc::NfcDevice;
+///
+/// let device = NfcDevice::load(c"/ext/nfc/Badge.nfc")?;
+/// let uid = device.uid();
+/// # Ok::<(), flipperzero::nfc::Error>(())
+/// ```
+pub struct NfcDevice {
+    raw: NonNull<sys::NfcDevice>,
+}
+
+impl NfcDevice {
+    /// Creates a device holding the data of `protocol` read from the firmware's
+    /// `data`.
+    fn with_data(protocol: Protocol, data: *const sys::NfcDeviceData) -> Self {
+        let device = Self::new();
+        unsafe { sys::nfc_device_set_data(device.raw.as_ptr(), protocol.to_sys(), data) };
+        device
+    }
+
+    /// Creates a device without data, which has no protocol.
+    fn new() -> Self {
+        Self {
+            raw: unsafe { NonNull::new_unchecked(sys::nfc_device_alloc()) },
+        }
+    }
+
+    /// Creates an ISO14443-3A device with the identity of `tag`, such as for emulating a
+    /// stored UID.
+    pub fn from_iso14443a(tag: &Iso14443a) -> Self {
+        let data = tag.to_sys();
+        Self::with_data(
+            Protocol::Iso14443_3a,
+            &data as *const sys::Iso14443_3aData as *const sys::NfcDeviceData,
+        )
+    }
+
+    /// Loads the `.nfc` file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidFile`] if the file couldn't be read, or doesn't hold a
+    /// tag of a protocol the firmware knows.
+    pub fn load(path: &CStr) -> Result<Self, Error> {
+        let device = Self::new();
+        if unsafe { sys::nfc_device_load(device.raw.as_ptr(), path.as_ptr()) } {
+            Ok(device)
+        } else {
+            Err(Error::InvalidFile)
+        }
+    }
+
+    /// Saves the device to the `.nfc` file at `path`, replacing it if it exists, in the
+    /// format of the NFC app.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::WriteFailed`] if the file couldn't be written.
+    pub fn save(&mut self, path: &CStr) -> Result<(), Error> {
+        if unsafe { sys::nfc_device_save(self.raw.as_ptr(), path.as_ptr()) } {
+            Ok(())
+        } else {
+            Err(Error::WriteFailed)
+        }
+    }
+
+    /// Returns the raw pointer to the device.
+    pub fn as_ptr(&self) -> *mut sys::NfcDevice {
+        self.raw.as_ptr()
+    }
+
+    /// Returns the protocol of the data, which is the most specific one of the tag.
+    pub fn protocol(&self) -> Option<Protocol> {
+        Protocol::from_sys(unsafe { sys::nfc_device_get_protocol(self.raw.as_ptr()) })
+    }
+
+    /// Returns the name of the type of tag, such as `Mifare Classic 1K`.
+    pub fn name(&self) -> &CStr {
+        unsafe {
+            CStr::from_ptr(sys::nfc_device_get_name(
+                self.raw.as_ptr(),
+                sys::NfcDeviceNameType_NfcDeviceNameTypeFull,
+            ))
+        }
+    }
+
+    /// Returns the UID of the tag, whose length depends on the protocol.
+    pub fn uid(&self) -> &[u8] {
+        if self.protocol().is_none() {
+            return &[];
+        }
+        let mut len = 0;
+        let uid = unsafe { sys::nfc_device_get_uid(self.raw.as_ptr(), &mut len) };
+        if uid.is_null() || len == 0 {
+            &[]
+        } else {
+            unsafe { slice::from_raw_parts(uid, len) }
+        }
+    }
+
+    /// Changes the UID of the tag.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidUid`] if the protocol doesn't allow the length of `uid`.
+    pub fn set_uid(&mut self, uid: &[u8]) -> Result<(), Error> {
+        if self.protocol().is_some()
+            && unsafe { sys::nfc_device_set_uid(self.raw.as_ptr(), uid.as_ptr(), uid.len()) }
+        {
+            Ok(())
+        } else {
+            Err(Error::InvalidUid)
+        }
+    }
+
+    /// Returns the ISO14443-3A identity of the tag, if its protocol builds on
+    /// ISO14443-3A.
+    pub fn iso14443a(&self) -> Option<Iso14443a> {
+        let protocol = self.protocol()?;
+        if protocol != Protocol::Iso14443_3a && !protocol.has_parent(Protocol::Iso14443_3a) {
+            return None;
+        }
+        let data =
+            unsafe { sys::nfc_device_get_data(self.raw.as_ptr(), Protocol::Iso14443_3a.to_sys()) };
+        Some(Iso14443a::from_sys(unsafe {
+            &*(data as *const sys::Iso14443_3aData)
+        }))
+    }
+
+    /// Returns the firmware's data of the protocol of the tag.
+    fn data(&self) -> *const sys::NfcDeviceData {
+        let protocol = unsafe { sys::nfc_device_get_protocol(self.raw.as_ptr()) };
+        unsafe { sys::nfc_device_get_data(self.raw.as_ptr(), protocol) }
+    }
+}
+
+impl Drop for NfcDevice {
+    fn drop(&mut self) {
+        unsafe { sys::nfc_device_free(self.raw.as_ptr()) };
+    }
+}
+
+/// The NFC reader, which is released once this is dropped.
+///
+/// Only one `Nfc` exists at a time. The NFC app or other users of the NFC hardware must
+/// not run at the same time, which is the case while an app runs in the foreground.
+///
+/// # Examples
+///
+/// ```no_run
+/// use flipperzero::nfc::{Error, Nfc};
+///
+/// let mut nfc = Nfc::acquire()?;
+/// let tag = loop {
+///     match nfc.read_iso14443a() {
+///         Ok(tag) => break tag,
+///         Err(Error::NotPresent | Error::Timeout) => continue,
+///         Err(error) => return Err(error),
+///     }
+/// };
+/// let uid = tag.uid();
+/// # Ok::<(), Error>(())
+/// ```
+pub struct Nfc {
+    raw: NonNull<sys::Nfc>,
+}
+
+impl Nfc {
+    /// Takes the NFC reader.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Busy`] if another `Nfc` exists.
+    pub fn acquire() -> Result<Self, Error> {
+        if IN_USE.swap(true, Ordering::Acquire) {
+            return Err(Error::Busy);
+        }
+        Ok(Self {
+            raw: unsafe { NonNull::new_unchecked(sys::nfc_alloc()) },
+        })
+    }
+
+    /// Returns the raw pointer to the NFC instance.
+    pub fn as_ptr(&self) -> *mut sys::Nfc {
+        self.raw.as_ptr()
+    }
+
+    /// Reads the identity of the ISO14443-3A tag in the field, blocking until it has been
+    /// read or the tag didn't answer.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotPresent`] if there is no tag, and [`Error::Timeout`] or
+    /// [`Error::Communication`] if reading it failed.
+    pub fn read_iso14443a(&mut self) -> Result<Iso14443a, Error> {
+        let mut data = Iso14443a {
+            uid: [0; MAX_UID_LEN],
+            uid_len: 0,
+            atqa: [0; 2],
+            sak: 0,
+        }
+        .to_sys();
+        let error = unsafe { sys::iso14443_3a_poller_sync_read(self.raw.as_ptr(), &mut data) };
+        match Error::from_iso14443_3a(error) {
+            Some(error) => Err(error),
+            None => Ok(Iso14443a::from_sys(&data)),
+        }
+    }
+
+    /// Returns `true` if a tag of `protocol` is in the field.
+    pub fn detect(&mut self, protocol: Protocol) -> bool {
+        let poller = unsafe { sys::nfc_poller_alloc(self.raw.as_ptr(), protocol.to_sys()) };
+        let detected = unsafe { sys::nfc_poller_detect(poller) };
+        unsafe { sys::nfc_poller_free(poller) };
+        detected
+    }
+
+    /// Reads the tag of `protocol` in the field, which is one of
+    /// [`Protocol::Iso14443_3a`] and [`Protocol::Iso14443_3b`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::UnsupportedProtocol`] for other protocols, [`Error::NotPresent`]
+    /// if there is no tag, and [`Error::Timeout`] or [`Error::Communication`] if
+    /// reading it failed.
+    pub fn read(&mut self, protocol: Protocol) -> Result<NfcDevice, Error> {
+        match protocol {
+            Protocol::Iso14443_3a => Ok(NfcDevice::from_iso14443a(&self.read_iso14443a()?)),
+            Protocol::Iso14443_3b => {
+                let poller = unsafe { sys::nfc_poller_alloc(self.raw.as_ptr(), protocol.to_sys()) };
+                // Detecting an ISO14443-3B tag activates it, which reads its data.
+                let device = if unsafe { sys::nfc_poller_detect(poller) } {
+                    Ok(NfcDevice::with_data(protocol, unsafe {
+                        sys::nfc_poller_get_data(poller)
+                    }))
+                } else {
+                    Err(Error::NotPresent)
+                };
+                unsafe { sys::nfc_poller_free(poller) };
+                device
+            }
+            _ => Err(Error::UnsupportedProtocol),
+        }
+    }
+
+    /// Starts emulating the tag of `device`, until the returned [`Emulation`] is
+    /// dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::UnsupportedProtocol`] if the device has no protocol, or one that
+    /// can't be emulated.
+    pub fn emulate<'a>(&'a mut self, device: &'a NfcDevice) -> Result<Emulation<'a>, Error> {
+        unsafe extern "C" fn keep_listening(
+            _event: sys::NfcGenericEvent,
+            _context: *mut c_void,
+        ) -> sys::NfcCommand {
+            sys::NfcCommand_NfcCommandContinue
+        }
+
+        let protocol = device.protocol().ok_or(Error::UnsupportedProtocol)?;
+        if !protocol.can_emulate() {
+            return Err(Error::UnsupportedProtocol);
+        }
+        let listener = unsafe {
+            NonNull::new_unchecked(sys::nfc_listener_alloc(
+                self.raw.as_ptr(),
+                protocol.to_sys(),
+                device.data(),
+            ))
+        };
+        unsafe {
+            sys::nfc_listener_start(
+                listener.as_ptr(),
+                Some(keep_listening),
+                core::ptr::null_mut(),
+            )
+        };
+        Ok(Emulation {
+            listener,
+            _marker: PhantomData,
+        })
+    }
+}
+
+impl Drop for Nfc {
+    fn drop(&mut self) {
+        unsafe { sys::nfc_free(self.raw.as_ptr()) };
+        IN_USE.store(false, Ordering::Release);
+    }
+}
+
+/// An emulation started by [`Nfc::emulate`], which stops once this is dropped.
+pub struct Emulation<'a> {
+    listener: NonNull<sys::NfcListener>,
+    _marker: PhantomData<(&'a mut Nfc, &'a NfcDevice)>,
+}
+
+impl Drop for Emulation<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            sys::nfc_listener_stop(self.listener.as_ptr());
+            sys::nfc_listener_free(self.listener.as_ptr());
+        }
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use super::{Error, Iso14443a, Nfc, NfcDevice, Protocol};
+    use crate::storage::Storage;
+
+    #[test]
+    fn protocols_match_the_firmware() {
+        for protocol in [
+            Protocol::Iso14443_3a,
+            Protocol::Iso14443_3b,
+            Protocol::MfClassic,
+            Protocol::St25tb,
+        ] {
+            assert_eq!(Protocol::from_sys(protocol.to_sys()), Some(protocol));
+            assert!(!protocol.name().is_empty());
+        }
+        assert!(Protocol::Iso14443_3a.parent().is_none());
+        assert_eq!(Protocol::Iso14443_4a.parent(), Some(Protocol::Iso14443_3a));
+        assert!(Protocol::MfDesfire.has_parent(Protocol::Iso14443_3a));
+        assert!(!Protocol::MfDesfire.has_parent(Protocol::Iso14443_3b));
+    }
+
+    #[test]
+    fn uids_are_checked() {
+        let tag = Iso14443a::new(&[0x04, 0xA2, 0x5B, 0x1C], [0x44, 0x00], 0x08).unwrap();
+        assert_eq!(tag.uid(), [0x04, 0xA2, 0x5B, 0x1C]);
+        assert_eq!(tag.to_sys().uid_len, 4);
+        assert_eq!(Iso14443a::from_sys(&tag.to_sys()), tag);
+        assert_eq!(
+            Iso14443a::new(&[0x04, 0xA2], [0x44, 0x00], 0x08),
+            Err(Error::InvalidUid)
+        );
+    }
+
+    #[test]
+    fn devices_are_saved_and_loaded() {
+        let path = c"/ext/.tmp/rs_nfc_device.nfc";
+        let tag = Iso14443a::new(&[0x04, 0xA2, 0x5B, 0x1C], [0x44, 0x00], 0x08).unwrap();
+        let mut device = NfcDevice::from_iso14443a(&tag);
+        assert_eq!(device.protocol(), Some(Protocol::Iso14443_3a));
+        assert_eq!(device.uid(), tag.uid());
+        assert_eq!(device.iso14443a(), Some(tag));
+        assert_eq!(device.set_uid(&[0x01]), Err(Error::InvalidUid));
+
+        Storage::open().create_dir("/ext/.tmp").ok();
+        device.save(path).unwrap();
+        let loaded = NfcDevice::load(path).unwrap();
+        Storage::open().remove(path).unwrap();
+        assert_eq!(loaded.protocol(), Some(Protocol::Iso14443_3a));
+        assert_eq!(loaded.iso14443a(), Some(tag));
+        assert!(matches!(NfcDevice::load(path), Err(Error::InvalidFile)));
+    }
+
+    #[test]
+    fn reader_is_acquired_once() {
+        let nfc = Nfc::acquire().unwrap();
+        assert!(matches!(Nfc::acquire(), Err(Error::Busy)));
+        drop(nfc);
+        assert!(Nfc::acquire().is_ok());
+    }
+}
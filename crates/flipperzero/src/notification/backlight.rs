@@ -1,4 +1,6 @@
-use super::{messages, NotificationSequence};
+use flipperzero_sys as sys;
+
+use super::{messages, NotificationSequence, NotificationService};
 use crate::notification_sequence;
 
 pub const RESET_DISPLAY: NotificationSequence =
@@ -16,3 +18,82 @@ pub const DISPLAY_BACKLIGHT_ENFORCE_AUTO: NotificationSequence =
 
 pub const DISPLAY_BACKLIGHT_OFF_DELAY_1000: NotificationSequence =
     notification_sequence![messages::DELAY_1000, messages::DISPLAY_BACKLIGHT_OFF];
+
+/// Gives the display backlight back to the notification service, on at the brightness
+/// setting of the user.
+const RELEASE_DISPLAY: NotificationSequence = notification_sequence![
+    messages::DISPLAY_BACKLIGHT_ON,
+    messages::DISPLAY_BACKLIGHT_ENFORCE_AUTO,
+];
+
+/// Keeps the display backlight on at a brightness chosen by the app, until it is
+/// dropped.
+///
+/// The brightness is set directly, so it changes without delay and ignores the
+/// brightness setting of the user. The notification service still sets its own
+/// brightness when it shows a notification, such as when a key is pressed, so apps
+/// changing the brightness over time should keep setting it.
+///
+/// Dropping it hands the backlight back to the notification service: the backlight is
+/// turned on at the brightness setting of the user, and turned off again once the user
+/// is idle. The state from before [`Backlight::hold`] isn't restored, as the firmware
+/// can't tell what it was.
+///
+/// # Examples
+///
+/// ```no_run
+/// use core::time::Duration;
+///
+/// use flipperzero::furi::thread::sleep;
+/// use flipperzero::notification::backlight::Backlight;
+///
+/// // Flash the display, such as for Morse code.
+/// let mut backlight = Backlight::hold(0);
+/// for _ in 0..3 {
+///     backlight.set_brightness(255);
+///     sleep(Duration::from_millis(100));
+///     backlight.set_brightness(0);
+///     sleep(Duration::from_millis(100));
+/// }
+/// ```
+#[must_use = "the backlight is released when this is dropped"]
+pub struct Backlight {
+    notification: NotificationService,
+}
+
+impl Backlight {
+    /// Turns the backlight on at `brightness`, keeping the notification service from
+    /// turning it off.
+    pub fn hold(brightness: u8) -> Self {
+        let mut notification = NotificationService::open();
+        notification.notify_blocking(&DISPLAY_BACKLIGHT_ENFORCE_ON);
+        let mut backlight = Self { notification };
+        backlight.set_brightness(brightness);
+        backlight
+    }
+
+    /// Sets the brightness of the backlight, where 0 turns it off.
+    pub fn set_brightness(&mut self, brightness: u8) {
+        unsafe { sys::furi_hal_light_set(sys::Light_LightBacklight, brightness) };
+    }
+}
+
+impl Drop for Backlight {
+    fn drop(&mut self) {
+        self.notification.notify_blocking(&RELEASE_DISPLAY);
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use super::Backlight;
+
+    #[test]
+    fn backlight_is_held_and_released() {
+        let mut backlight = Backlight::hold(64);
+        backlight.set_brightness(128);
+        drop(backlight);
+        // Holding again works once the service has the backlight back.
+        drop(Backlight::hold(255));
+    }
+}
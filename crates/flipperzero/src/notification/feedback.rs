@@ -40,6 +40,27 @@ pub const ERROR: NotificationSequence = notification_sequence![
     messages::SOUND_OFF,
 ];
 
+/// A short green blink, confirming that something has been saved without interrupting
+/// the user.
+pub const SAVE_SUCCESS: NotificationSequence =
+    notification_sequence![messages::GREEN_255, messages::DELAY_100, messages::GREEN_0];
+
+/// A red light and two short vibrations, signalling that saving failed.
+///
+/// Unlike [`ERROR`], this doesn't make a sound.
+pub const SAVE_ERROR: NotificationSequence = notification_sequence![
+    messages::RED_255,
+    messages::VIBRO_ON,
+    messages::DELAY_100,
+    messages::VIBRO_OFF,
+    messages::DELAY_100,
+    messages::VIBRO_ON,
+    messages::DELAY_100,
+    messages::VIBRO_OFF,
+    messages::DELAY_250,
+    messages::RED_0,
+];
+
 pub const AUDIO_VISUAL_ALERT: NotificationSequence = notification_sequence![
     messages::FORCE_SPEAKER_VOLUME_SETTING_1,
     messages::FORCE_VIBRO_SETTING_ON,
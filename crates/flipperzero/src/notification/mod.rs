@@ -3,7 +3,8 @@
 use bitflags::bitflags;
 
 use flipperzero_sys as sys;
-use flipperzero_sys::furi::UnsafeRecord;
+
+use crate::furi::record::Record;
 
 ///Default backlight notification sequences.
 pub mod backlight;
@@ -20,14 +21,14 @@ pub mod vibro;
 
 /// A handle to the Notification service.
 pub struct NotificationService {
-    data: UnsafeRecord<sys::NotificationApp>,
+    data: Record<sys::NotificationApp>,
 }
 
 impl NotificationService {
     /// Obtains a handle to the Notifications service.
     pub fn open() -> Self {
         Self {
-            data: unsafe { UnsafeRecord::open(c"notification".as_ptr()) },
+            data: Record::open(),
         }
     }
 
@@ -75,6 +76,9 @@ impl Light {
 #[repr(transparent)]
 pub struct NotificationMessage(pub(super) sys::NotificationMessage);
 
+// SAFETY: messages are plain data, which the firmware only reads.
+unsafe impl Sync for NotificationMessage {}
+
 impl NotificationMessage {
     pub const fn vibro(on: bool) -> Self {
         Self(sys::NotificationMessage {
@@ -260,8 +264,13 @@ impl NotificationMessage {
     }
 }
 
+/// A sequence of [`NotificationMessage`]s, built with
+/// [`notification_sequence!`](crate::notification_sequence).
 pub struct NotificationSequence(&'static [*const NotificationMessage]);
 
+// SAFETY: the messages pointed to are `'static` and `Sync`.
+unsafe impl Sync for NotificationSequence {}
+
 impl NotificationSequence {
     #[doc(hidden)]
     pub const fn construct(sequence: &'static [*const NotificationMessage]) -> Self {
@@ -273,6 +282,25 @@ impl NotificationSequence {
     }
 }
 
+/// Builds a [`NotificationSequence`] from constant [`NotificationMessage`]s, which are
+/// run in order.
+///
+/// The sequence can be stored in a `const` or a `static`.
+///
+/// # Examples
+///
+/// ```
+/// use flipperzero::notification::{messages, NotificationMessage, NotificationSequence};
+/// use flipperzero::notification_sequence;
+///
+/// static DIM_ORANGE: NotificationSequence = notification_sequence![
+///     NotificationMessage::led_red(128),
+///     NotificationMessage::led_green(32),
+///     messages::DELAY_500,
+///     messages::RED_0,
+///     messages::GREEN_0,
+/// ];
+/// ```
 #[macro_export]
 macro_rules! notification_sequence {
     ($($x:expr),+ $(,)?) => {
@@ -285,3 +313,33 @@ macro_rules! notification_sequence {
         }
     };
 }
+
+#[flipperzero_test::tests]
+mod tests {
+    use flipperzero_sys as sys;
+
+    use super::{feedback, messages, NotificationMessage, NotificationSequence};
+    use crate::notification_sequence;
+
+    static CUSTOM: NotificationSequence = notification_sequence![
+        NotificationMessage::led_red(128),
+        messages::DELAY_10,
+        messages::RED_0,
+    ];
+
+    #[test]
+    fn sequences_end_with_null() {
+        assert_eq!(CUSTOM.0.len(), 4);
+        assert!(CUSTOM.0[..3].iter().all(|m| !m.is_null()));
+        assert!(CUSTOM.0[3].is_null());
+
+        let first = unsafe { &*CUSTOM.0[0] };
+        assert_eq!(
+            first.0.type_,
+            sys::NotificationMessageType_NotificationMessageTypeLedRed
+        );
+        assert_eq!(unsafe { first.0.data.led.value }, 128);
+
+        assert!(feedback::SUCCESS.0.last().unwrap().is_null());
+    }
+}
@@ -1,4 +1,10 @@
+use core::ptr::addr_of;
+use core::time::Duration;
+
+use flipperzero_sys as sys;
+
 use super::{messages, NotificationSequence};
+use crate::furi;
 use crate::notification_sequence;
 
 pub const RESET_VIBRO: NotificationSequence = notification_sequence![messages::VIBRO_OFF];
@@ -16,3 +22,77 @@ pub const DOUBLE_VIBRO: NotificationSequence = notification_sequence![
     messages::DELAY_100,
     messages::VIBRO_OFF,
 ];
+
+/// Returns `true` if the vibration motor is running.
+pub fn is_vibrating() -> bool {
+    unsafe { sys::furi_hal_gpio_read(addr_of!(sys::gpio_vibro)) }
+}
+
+/// Runs the vibration motor for `duration`, blocking until it has stopped again.
+///
+/// This drives the motor directly, so it is timed more precisely than a sequence, but
+/// ignores the vibration setting of the user.
+pub fn pulse(duration: Duration) {
+    let _vibration = Vibration::start();
+    furi::thread::sleep(duration);
+}
+
+/// Keeps the vibration motor running, bypassing the notification service, until it is
+/// dropped.
+///
+/// Dropping it restores the previous state of the motor, so a `Vibration` started while
+/// a notification vibrates leaves the motor running.
+///
+/// # Examples
+///
+/// ```no_run
+/// use core::time::Duration;
+///
+/// use flipperzero::furi::thread::sleep;
+/// use flipperzero::notification::vibro::Vibration;
+///
+/// // A metronome at 120 beats per minute.
+/// loop {
+///     let beat = Vibration::start();
+///     sleep(Duration::from_millis(30));
+///     drop(beat);
+///     sleep(Duration::from_millis(470));
+/// }
+/// ```
+#[must_use = "the motor stops when this is dropped"]
+pub struct Vibration {
+    was_on: bool,
+}
+
+impl Vibration {
+    /// Starts the vibration motor.
+    pub fn start() -> Self {
+        let was_on = is_vibrating();
+        unsafe { sys::furi_hal_vibro_on(true) };
+        Self { was_on }
+    }
+}
+
+impl Drop for Vibration {
+    fn drop(&mut self) {
+        unsafe { sys::furi_hal_vibro_on(self.was_on) };
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use core::time::Duration;
+
+    use super::{is_vibrating, pulse, Vibration};
+
+    #[test]
+    fn vibration_restores_the_motor() {
+        assert!(!is_vibrating());
+        let outer = Vibration::start();
+        assert!(is_vibrating());
+        pulse(Duration::from_millis(1));
+        assert!(is_vibrating());
+        drop(outer);
+        assert!(!is_vibrating());
+    }
+}
@@ -0,0 +1,298 @@
+//! Power service: the battery, the charger and turning the Flipper Zero off.
+
+use flipperzero_sys as sys;
+
+#[cfg(feature = "alloc")]
+use crate::furi::pubsub::RecordSubscription;
+use crate::furi::record::Record;
+
+/// `PowerEvent` from the firmware's `power.h`, which unlike a
+/// [`TypeEvent`](crate::furi::pubsub::TypeEvent) carries data: a union of a single
+/// byte.
+#[cfg(feature = "alloc")]
+#[repr(C)]
+pub(crate) struct RawEvent {
+    pub(crate) type_: u8,
+    pub(crate) battery_level: u8,
+}
+
+/// An event published by the power service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PowerEvent {
+    /// The charger was unplugged, or stopped charging.
+    StopCharging,
+    /// The charger was plugged in.
+    StartCharging,
+    /// The battery is fully charged.
+    FullyCharged,
+    /// The charge of the battery changed, to the given percentage.
+    BatteryLevelChanged(u8),
+    /// An event not known to this crate, with its raw value.
+    Unknown(u8),
+}
+
+impl PowerEvent {
+    #[cfg(feature = "alloc")]
+    pub(crate) fn from_sys(event: &RawEvent) -> Self {
+        match event.type_ {
+            0 => Self::StopCharging,
+            1 => Self::StartCharging,
+            2 => Self::FullyCharged,
+            3 => Self::BatteryLevelChanged(event.battery_level),
+            other => Self::Unknown(other),
+        }
+    }
+}
+
+/// Whether the battery is being charged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ChargeState {
+    /// Running on the battery.
+    Discharging,
+    /// Plugged in and charging.
+    Charging,
+    /// Plugged in, with the battery fully charged.
+    Charged,
+}
+
+/// The mode to boot into with [`Power::reboot`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BootMode {
+    /// The firmware, as usual.
+    Normal,
+    /// The DFU mode of the bootloader, for flashing firmware over USB.
+    Dfu,
+    /// The updater, to install the update prepared on the SD card.
+    Update,
+}
+
+impl BootMode {
+    /// Returns the raw mode, for use with the `sys` bindings.
+    pub fn to_sys(&self) -> sys::PowerBootMode {
+        match self {
+            Self::Normal => sys::PowerBootMode_PowerBootModeNormal,
+            Self::Dfu => sys::PowerBootMode_PowerBootModeDfu,
+            Self::Update => sys::PowerBootMode_PowerBootModeUpdateStart,
+        }
+    }
+}
+
+/// The state of the battery and the charger, as measured by [`Power::info`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Info {
+    /// The charge of the battery, in percent.
+    pub charge: u8,
+    /// The health of the battery, in percent of its design capacity.
+    pub health: u8,
+    /// Whether the fuel gauge works, without which the battery values are unreliable.
+    pub gauge_is_ok: bool,
+    /// Whether the charger is charging the battery.
+    pub is_charging: bool,
+    /// Whether the fuel gauge asks for the Flipper Zero to be turned off, as the battery
+    /// is nearly empty.
+    pub is_shutdown_requested: bool,
+    /// The voltage of the battery, in volts.
+    pub voltage: f32,
+    /// The current of the battery in amperes, which is negative while discharging.
+    pub current: f32,
+    /// The temperature of the battery, in degrees Celsius.
+    pub temperature: f32,
+    /// The remaining capacity of the battery, in mAh.
+    pub capacity_remaining: u32,
+    /// The capacity of the battery when fully charged, in mAh.
+    pub capacity_full: u32,
+    /// The voltage the charger charges the battery up to, in volts.
+    pub charge_voltage_limit: f32,
+    /// The voltage of USB, in volts, which is zero when unplugged.
+    pub usb_voltage: f32,
+}
+
+impl Info {
+    /// Converts the raw info, keeping the readings of the fuel gauge for the battery.
+    pub fn from_sys(info: &sys::PowerInfo) -> Self {
+        Self {
+            charge: info.charge,
+            health: info.health,
+            gauge_is_ok: info.gauge_is_ok,
+            is_charging: info.is_charging,
+            is_shutdown_requested: info.is_shutdown_requested,
+            voltage: info.voltage_gauge,
+            current: info.current_gauge,
+            temperature: info.temperature_gauge,
+            capacity_remaining: info.capacity_remaining,
+            capacity_full: info.capacity_full,
+            charge_voltage_limit: info.voltage_battery_charge_limit,
+            usb_voltage: info.voltage_vbus,
+        }
+    }
+}
+
+/// A handle to the power service.
+///
+/// # Examples
+///
+/// Stopping a logger before the battery runs out:
+///
+/// ```no_run
+/// # extern crate alloc;
+/// # use alloc::sync::Arc;
+/// use core::sync::atomic::{AtomicBool, Ordering};
+/// use flipperzero::power::{Power, PowerEvent};
+///
+/// let power = Power::open();
+/// let low = Arc::new(AtomicBool::new(power.battery_percent() < 5));
+/// let _subscription = power.subscribe({
+///     let low = low.clone();
+///     move |event| {
+///         if let PowerEvent::BatteryLevelChanged(level) = event {
+///             low.store(level < 5, Ordering::Relaxed);
+///         }
+///     }
+/// });
+/// while !low.load(Ordering::Relaxed) {
+///     // Log another value.
+/// }
+/// ```
+pub struct Power {
+    record: Record<sys::Power>,
+}
+
+impl Power {
+    /// Opens the power service.
+    pub fn open() -> Self {
+        Self {
+            record: Record::open(),
+        }
+    }
+
+    /// Measures the battery and the charger.
+    pub fn info(&self) -> Info {
+        let mut info = core::mem::MaybeUninit::uninit();
+        unsafe {
+            sys::power_get_info(self.record.as_ptr(), info.as_mut_ptr());
+            Info::from_sys(info.assume_init_ref())
+        }
+    }
+
+    /// Returns the charge of the battery, in percent.
+    pub fn battery_percent(&self) -> u8 {
+        unsafe { sys::furi_hal_power_get_pct() }
+    }
+
+    /// Returns the voltage of the battery, in volts.
+    pub fn battery_voltage(&self) -> f32 {
+        unsafe {
+            sys::furi_hal_power_get_battery_voltage(sys::FuriHalPowerIC_FuriHalPowerICFuelGauge)
+        }
+    }
+
+    /// Returns the current of the battery in amperes, which is negative while
+    /// discharging.
+    pub fn battery_current(&self) -> f32 {
+        unsafe {
+            sys::furi_hal_power_get_battery_current(sys::FuriHalPowerIC_FuriHalPowerICFuelGauge)
+        }
+    }
+
+    /// Returns whether the battery is being charged.
+    pub fn charge_state(&self) -> ChargeState {
+        if unsafe { sys::furi_hal_power_is_charging_done() } {
+            ChargeState::Charged
+        } else if unsafe { sys::furi_hal_power_is_charging() } {
+            ChargeState::Charging
+        } else {
+            ChargeState::Discharging
+        }
+    }
+
+    /// Returns `false` if the battery has worn out, or the fuel gauge doesn't work.
+    pub fn is_battery_healthy(&self) -> bool {
+        unsafe { sys::power_is_battery_healthy(self.record.as_ptr()) }
+    }
+
+    /// Sets whether the power service shows its low battery warning.
+    pub fn set_low_battery_notification(&self, enable: bool) {
+        unsafe { sys::power_enable_low_battery_level_notification(self.record.as_ptr(), enable) };
+    }
+
+    /// Turns the Flipper Zero off.
+    ///
+    /// While USB is plugged in, the Flipper Zero stays on and asks the user to unplug it
+    /// instead, so this returns.
+    pub fn power_off(&self) {
+        unsafe { sys::power_off(self.record.as_ptr()) };
+    }
+
+    /// Restarts the Flipper Zero into `mode`.
+    pub fn reboot(&self, mode: BootMode) -> ! {
+        unsafe { sys::power_reboot(self.record.as_ptr(), mode.to_sys()) };
+        unreachable!("the Flipper Zero restarts");
+    }
+
+    /// Subscribes to the events of the power service, calling `callback` for every
+    /// event.
+    ///
+    /// The closure is called on the power service thread, so it should return quickly.
+    /// The subscription ends when the returned [`PowerSubscription`] is dropped.
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+    pub fn subscribe<F>(&self, callback: F) -> PowerSubscription
+    where
+        F: FnMut(PowerEvent) + Send + 'static,
+    {
+        let power = Self::open();
+        let pubsub = unsafe { sys::power_get_pubsub(power.record.as_ptr()) };
+        PowerSubscription {
+            _subscription: unsafe {
+                RecordSubscription::new(power, pubsub, PowerEvent::from_sys, callback)
+            },
+        }
+    }
+}
+
+/// A subscription to the events of the power service, created by
+/// [`Power::subscribe`].
+///
+/// The subscription ends when this is dropped.
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub struct PowerSubscription {
+    _subscription: RecordSubscription<Power, RawEvent>,
+}
+
+#[cfg(feature = "alloc")]
+#[flipperzero_test::tests]
+mod tests {
+    use super::{Power, PowerEvent, RawEvent};
+
+    #[test]
+    fn events_map_from_sys() {
+        let events = [
+            PowerEvent::StopCharging,
+            PowerEvent::StartCharging,
+            PowerEvent::FullyCharged,
+            PowerEvent::BatteryLevelChanged(42),
+            PowerEvent::Unknown(4),
+        ];
+        for (type_, event) in events.into_iter().enumerate() {
+            let raw = RawEvent {
+                type_: type_ as u8,
+                battery_level: 42,
+            };
+            assert_eq!(PowerEvent::from_sys(&raw), event);
+        }
+    }
+
+    #[test]
+    fn battery_info_is_plausible() {
+        let power = Power::open();
+        let info = power.info();
+        assert!(info.charge <= 100);
+        assert!((2.5..5.0).contains(&info.voltage));
+        assert!(power.battery_percent() <= 100);
+
+        drop(power.subscribe(|_| {}));
+    }
+}
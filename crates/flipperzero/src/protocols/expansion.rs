@@ -0,0 +1,532 @@
+//! Frames of the expansion module protocol.
+//!
+//! Expansion modules talk to the Flipper Zero over the UART of the GPIO header, in
+//! frames that start with their type and end with a checksum. Once a module has been
+//! detected, the frames carry [RPC] messages between the module and the Flipper.
+//! [`FrameCodec`] reads and writes these frames over any transport, so apps can speak
+//! the protocol to a module themselves, or pretend to be one.
+//!
+//! Both sides send [`Frame::Heartbeat`] frames while they have nothing else to say,
+//! which hold the connection open; a side that hears nothing for [`TIMEOUT`] gives up.
+//!
+//! [RPC]: https://github.com/flipperdevices/flipperzero-protobuf
+
+use core::time::Duration;
+
+use super::Error;
+use crate::io::{Read, Write};
+
+/// Largest payload of a [`Frame::Data`] frame.
+pub const MAX_DATA_SIZE: usize = 64;
+/// Baud rate that every connection starts at, before it is changed with
+/// [`Frame::BaudRate`].
+pub const DEFAULT_BAUD_RATE: u32 = 9600;
+/// How long either side waits for the next frame before it considers the connection
+/// lost.
+pub const TIMEOUT: Duration = Duration::from_millis(250);
+
+const TYPE_HEARTBEAT: u8 = 1;
+const TYPE_STATUS: u8 = 2;
+const TYPE_BAUD_RATE: u8 = 3;
+const TYPE_CONTROL: u8 = 4;
+const TYPE_DATA: u8 = 5;
+
+/// Type, size of the data, data and checksum.
+const MAX_FRAME_SIZE: usize = 2 + MAX_DATA_SIZE + 1;
+
+/// The status reported by a [`Frame::Status`] frame, in reply to the previous frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Status {
+    /// The frame was accepted.
+    Ok,
+    /// The frame was rejected for an unspecified reason.
+    Unknown,
+    /// The requested baud rate isn't supported.
+    BaudRate,
+}
+
+impl Status {
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0x00 => Some(Self::Ok),
+            0x01 => Some(Self::Unknown),
+            0x02 => Some(Self::BaudRate),
+            _ => None,
+        }
+    }
+
+    fn to_byte(self) -> u8 {
+        match self {
+            Self::Ok => 0x00,
+            Self::Unknown => 0x01,
+            Self::BaudRate => 0x02,
+        }
+    }
+}
+
+/// The command of a [`Frame::Control`] frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Command {
+    /// Starts an RPC session, after which RPC messages are exchanged in data frames.
+    StartRpc,
+    /// Ends the RPC session.
+    StopRpc,
+    /// Turns on the 5V supply of the GPIO header.
+    EnableOtg,
+    /// Turns off the 5V supply of the GPIO header.
+    DisableOtg,
+}
+
+impl Command {
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0x00 => Some(Self::StartRpc),
+            0x01 => Some(Self::StopRpc),
+            0x02 => Some(Self::EnableOtg),
+            0x03 => Some(Self::DisableOtg),
+            _ => None,
+        }
+    }
+
+    fn to_byte(self) -> u8 {
+        match self {
+            Self::StartRpc => 0x00,
+            Self::StopRpc => 0x01,
+            Self::EnableOtg => 0x02,
+            Self::DisableOtg => 0x03,
+        }
+    }
+}
+
+/// A frame of the expansion module protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frame<'a> {
+    /// Holds the connection open while there is nothing else to send.
+    Heartbeat,
+    /// Acknowledges or rejects the previous frame.
+    Status(Status),
+    /// Asks the other side to switch to a baud rate, which it confirms with a status
+    /// frame before both sides switch.
+    BaudRate(u32),
+    /// Controls the RPC session and the 5V supply.
+    Control(Command),
+    /// Up to [`MAX_DATA_SIZE`] bytes of the RPC session.
+    Data(&'a [u8]),
+}
+
+/// Reads and writes frames, skipping over bytes that aren't part of a valid frame.
+///
+/// Timeouts are detected through the transport: a read returning 0 bytes counts as a
+/// timeout. The transport should therefore be set up to wait for about [`TIMEOUT`]
+/// before giving up on a read, for example with [`Uart::set_read_timeout`].
+///
+/// [`Uart::set_read_timeout`]: crate::serial::Uart::set_read_timeout
+///
+/// # Examples
+///
+/// ```no_run
+/// # use flipperzero::protocols::expansion::{Frame, FrameCodec, TIMEOUT};
+/// # use flipperzero::protocols::Error;
+/// # use flipperzero::serial::{Uart, UartChannel};
+/// # fn main() -> Result<(), Error> {
+/// let mut uart = Uart::open(UartChannel::Usart, 9600)?;
+/// uart.set_read_timeout(TIMEOUT);
+/// let mut codec = FrameCodec::new();
+/// let mut buf = [0; 64];
+/// loop {
+///     // Answer heartbeats to keep the connection open.
+///     if codec.read_frame(&mut uart, &mut buf)? == Frame::Heartbeat {
+///         codec.write_frame(&mut uart, Frame::Heartbeat)?;
+///     }
+/// }
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct FrameCodec {
+    /// Received bytes that haven't been decoded yet.
+    pending: [u8; MAX_FRAME_SIZE],
+    len: usize,
+    discarded: u64,
+}
+
+/// The outcome of decoding the start of the pending bytes.
+enum Decoded {
+    /// A valid frame of this many bytes.
+    Frame(usize),
+    /// The frame needs at least this many bytes.
+    Incomplete(usize),
+    /// The first byte doesn't start a valid frame.
+    Invalid,
+}
+
+impl FrameCodec {
+    /// Creates a codec that hasn't received anything yet.
+    pub fn new() -> Self {
+        Self {
+            pending: [0; MAX_FRAME_SIZE],
+            len: 0,
+            discarded: 0,
+        }
+    }
+
+    /// Returns the number of received bytes that were skipped because they weren't
+    /// part of a valid frame.
+    pub fn discarded(&self) -> u64 {
+        self.discarded
+    }
+
+    /// Reads the next valid frame from `reader`, copying the payload of data frames into
+    /// `buf`.
+    ///
+    /// Bytes that don't start a frame, and frames with a wrong checksum, are skipped one
+    /// byte at a time until a valid frame starts, so garbage on the line costs no more
+    /// than the frames it hit.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Timeout`] if the reader timed out, which also discards a
+    /// partially received frame, and [`Error::DataTooLong`] if the payload of a data
+    /// frame doesn't fit into `buf`. The frame is skipped in both cases.
+    pub fn read_frame<'b, R: Read + ?Sized>(
+        &mut self,
+        reader: &mut R,
+        buf: &'b mut [u8],
+    ) -> Result<Frame<'b>, Error> {
+        loop {
+            match decode(&self.pending[..self.len]) {
+                Decoded::Frame(size) => {
+                    let frame = parse(&self.pending[..size], buf);
+                    self.pending.copy_within(size..self.len, 0);
+                    self.len -= size;
+                    return frame;
+                }
+                Decoded::Incomplete(size) => {
+                    // Only read the rest of this frame, so a timeout after it isn't
+                    // waited for.
+                    match reader.read(&mut self.pending[self.len..size])? {
+                        0 => {
+                            self.discarded += self.len as u64;
+                            self.len = 0;
+                            return Err(Error::Timeout);
+                        }
+                        n => self.len += n,
+                    }
+                }
+                Decoded::Invalid => {
+                    self.pending.copy_within(1..self.len, 0);
+                    self.len -= 1;
+                    self.discarded += 1;
+                }
+            }
+        }
+    }
+
+    /// Writes `frame` to `writer`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::DataTooLong`] if the payload of a data frame is larger than
+    /// [`MAX_DATA_SIZE`], in which case nothing is written.
+    pub fn write_frame<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        frame: Frame<'_>,
+    ) -> Result<(), Error> {
+        let mut bytes = [0; MAX_FRAME_SIZE];
+        let len = encode(frame, &mut bytes)?;
+        writer.write_all(&bytes[..len])?;
+        Ok(())
+    }
+}
+
+impl Default for FrameCodec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Decodes the frame at the start of `bytes`.
+fn decode(bytes: &[u8]) -> Decoded {
+    let Some(&kind) = bytes.first() else {
+        return Decoded::Incomplete(1);
+    };
+    let content = match kind {
+        TYPE_HEARTBEAT => 0,
+        TYPE_STATUS | TYPE_CONTROL => 1,
+        TYPE_BAUD_RATE => 4,
+        TYPE_DATA => match bytes.get(1) {
+            None => return Decoded::Incomplete(2),
+            Some(&size) if usize::from(size) > MAX_DATA_SIZE => return Decoded::Invalid,
+            Some(&size) => 1 + usize::from(size),
+        },
+        _ => return Decoded::Invalid,
+    };
+
+    let size = 1 + content + 1;
+    if bytes.len() < size {
+        return Decoded::Incomplete(size);
+    }
+    let valid = checksum(&bytes[..size - 1]) == bytes[size - 1]
+        && match kind {
+            TYPE_STATUS => Status::from_byte(bytes[1]).is_some(),
+            TYPE_CONTROL => Command::from_byte(bytes[1]).is_some(),
+            _ => true,
+        };
+    if valid {
+        Decoded::Frame(size)
+    } else {
+        Decoded::Invalid
+    }
+}
+
+/// Parses the valid frame `bytes`, copying its data into `buf`.
+fn parse<'b>(bytes: &[u8], buf: &'b mut [u8]) -> Result<Frame<'b>, Error> {
+    Ok(match bytes[0] {
+        TYPE_HEARTBEAT => Frame::Heartbeat,
+        TYPE_STATUS => Frame::Status(Status::from_byte(bytes[1]).unwrap()),
+        TYPE_BAUD_RATE => Frame::BaudRate(u32::from_le_bytes(bytes[1..5].try_into().unwrap())),
+        TYPE_CONTROL => Frame::Control(Command::from_byte(bytes[1]).unwrap()),
+        _ => {
+            let data = &bytes[2..bytes.len() - 1];
+            let buf = buf.get_mut(..data.len()).ok_or(Error::DataTooLong)?;
+            buf.copy_from_slice(data);
+            Frame::Data(buf)
+        }
+    })
+}
+
+/// Encodes `frame` into `bytes`, returning its size.
+fn encode(frame: Frame<'_>, bytes: &mut [u8; MAX_FRAME_SIZE]) -> Result<usize, Error> {
+    let content = match frame {
+        Frame::Heartbeat => {
+            bytes[0] = TYPE_HEARTBEAT;
+            0
+        }
+        Frame::Status(status) => {
+            bytes[0] = TYPE_STATUS;
+            bytes[1] = status.to_byte();
+            1
+        }
+        Frame::BaudRate(baud) => {
+            bytes[0] = TYPE_BAUD_RATE;
+            bytes[1..5].copy_from_slice(&baud.to_le_bytes());
+            4
+        }
+        Frame::Control(command) => {
+            bytes[0] = TYPE_CONTROL;
+            bytes[1] = command.to_byte();
+            1
+        }
+        Frame::Data(data) => {
+            if data.len() > MAX_DATA_SIZE {
+                return Err(Error::DataTooLong);
+            }
+            bytes[0] = TYPE_DATA;
+            bytes[1] = data.len() as u8;
+            bytes[2..2 + data.len()].copy_from_slice(data);
+            1 + data.len()
+        }
+    };
+    bytes[1 + content] = checksum(&bytes[..1 + content]);
+    Ok(1 + content + 1)
+}
+
+/// The checksum of a frame, which is the XOR of all its other bytes.
+fn checksum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0, |checksum, byte| checksum ^ byte)
+}
+
+#[cfg(feature = "alloc")]
+#[flipperzero_test::tests]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::{Command, Error, Frame, FrameCodec, Status, MAX_DATA_SIZE};
+    use crate::io::cursor::Cursor;
+    use crate::protocols::xmodem::tests::{Port, Step};
+
+    // Frames as sent by the firmware and the example modules.
+    const HEARTBEAT: &[u8] = &[0x01, 0x01];
+    const STATUS_OK: &[u8] = &[0x02, 0x00, 0x02];
+    const STATUS_BAUD_RATE: &[u8] = &[0x02, 0x02, 0x00];
+    const BAUD_RATE_115200: &[u8] = &[0x03, 0x00, 0xC2, 0x01, 0x00, 0xC0];
+    const START_RPC: &[u8] = &[0x04, 0x00, 0x04];
+    const ENABLE_OTG: &[u8] = &[0x04, 0x02, 0x06];
+    const DATA_HI: &[u8] = &[0x05, 0x02, b'h', b'i', 0x06];
+
+    fn send(bytes: &[u8]) -> Step {
+        Step::Send(Vec::from(bytes))
+    }
+
+    fn written(frame: Frame<'_>) -> Vec<u8> {
+        let mut sink = Cursor::empty(Vec::new());
+        FrameCodec::new().write_frame(&mut sink, frame).unwrap();
+        sink.data().to_vec()
+    }
+
+    #[test]
+    fn encodes_captured_frames() {
+        assert_eq!(written(Frame::Heartbeat), HEARTBEAT);
+        assert_eq!(written(Frame::Status(Status::Ok)), STATUS_OK);
+        assert_eq!(written(Frame::Status(Status::BaudRate)), STATUS_BAUD_RATE);
+        assert_eq!(written(Frame::BaudRate(115200)), BAUD_RATE_115200);
+        assert_eq!(written(Frame::Control(Command::StartRpc)), START_RPC);
+        assert_eq!(written(Frame::Control(Command::EnableOtg)), ENABLE_OTG);
+        assert_eq!(written(Frame::Data(b"hi")), DATA_HI);
+    }
+
+    #[test]
+    fn decodes_captured_frames() {
+        let stream = [
+            HEARTBEAT,
+            STATUS_OK,
+            BAUD_RATE_115200,
+            START_RPC,
+            ENABLE_OTG,
+            DATA_HI,
+        ]
+        .concat();
+        let mut port = Port::new(Vec::from([Step::Send(stream)]));
+        let mut codec = FrameCodec::new();
+        let mut buf = [0; MAX_DATA_SIZE];
+
+        assert_eq!(
+            codec.read_frame(&mut port, &mut buf).unwrap(),
+            Frame::Heartbeat
+        );
+        assert_eq!(
+            codec.read_frame(&mut port, &mut buf).unwrap(),
+            Frame::Status(Status::Ok)
+        );
+        assert_eq!(
+            codec.read_frame(&mut port, &mut buf).unwrap(),
+            Frame::BaudRate(115200)
+        );
+        assert_eq!(
+            codec.read_frame(&mut port, &mut buf).unwrap(),
+            Frame::Control(Command::StartRpc)
+        );
+        assert_eq!(
+            codec.read_frame(&mut port, &mut buf).unwrap(),
+            Frame::Control(Command::EnableOtg)
+        );
+        assert_eq!(
+            codec.read_frame(&mut port, &mut buf).unwrap(),
+            Frame::Data(b"hi")
+        );
+        assert_eq!(codec.discarded(), 0);
+    }
+
+    #[test]
+    fn decodes_frames_split_across_reads() {
+        let script = DATA_HI.iter().map(|&b| send(&[b])).collect();
+        let mut port = Port::new(script);
+        let mut buf = [0; MAX_DATA_SIZE];
+        assert_eq!(
+            FrameCodec::new().read_frame(&mut port, &mut buf).unwrap(),
+            Frame::Data(b"hi")
+        );
+    }
+
+    #[test]
+    fn round_trips_largest_data_frame() {
+        let data = [0xA5; MAX_DATA_SIZE];
+        let bytes = written(Frame::Data(&data));
+        assert_eq!(bytes.len(), MAX_DATA_SIZE + 3);
+
+        let mut port = Port::new(Vec::from([Step::Send(bytes)]));
+        let mut buf = [0; MAX_DATA_SIZE];
+        assert_eq!(
+            FrameCodec::new().read_frame(&mut port, &mut buf).unwrap(),
+            Frame::Data(&data)
+        );
+    }
+
+    #[test]
+    fn resynchronizes_after_garbage() {
+        // Line noise, a frame type with a bad checksum, and the start of a data frame
+        // whose size is too large.
+        let stream = [&[0xFF, 0x00, 0x01, 0x00, 0x05, 0xF0][..], START_RPC].concat();
+        let mut port = Port::new(Vec::from([Step::Send(stream)]));
+        let mut codec = FrameCodec::new();
+        let mut buf = [0; MAX_DATA_SIZE];
+
+        assert_eq!(
+            codec.read_frame(&mut port, &mut buf).unwrap(),
+            Frame::Control(Command::StartRpc)
+        );
+        assert_eq!(codec.discarded(), 6);
+    }
+
+    #[test]
+    fn skips_frames_with_bad_checksum() {
+        let damaged = [0x05, 0x02, b'h', b'o', 0x06];
+        let stream = [&damaged[..], HEARTBEAT].concat();
+        let mut port = Port::new(Vec::from([Step::Send(stream)]));
+        let mut codec = FrameCodec::new();
+        let mut buf = [0; MAX_DATA_SIZE];
+
+        assert_eq!(
+            codec.read_frame(&mut port, &mut buf).unwrap(),
+            Frame::Heartbeat
+        );
+        assert_eq!(codec.discarded(), damaged.len() as u64);
+    }
+
+    #[test]
+    fn skips_unknown_commands() {
+        let stream = [&[0x04, 0x09, 0x0D][..], HEARTBEAT].concat();
+        let mut port = Port::new(Vec::from([Step::Send(stream)]));
+        let mut buf = [0; MAX_DATA_SIZE];
+        assert_eq!(
+            FrameCodec::new().read_frame(&mut port, &mut buf).unwrap(),
+            Frame::Heartbeat
+        );
+    }
+
+    #[test]
+    fn timeout_discards_partial_frame() {
+        let mut port = Port::new(Vec::from([
+            send(&DATA_HI[..3]),
+            Step::Timeout,
+            send(HEARTBEAT),
+        ]));
+        let mut codec = FrameCodec::new();
+        let mut buf = [0; MAX_DATA_SIZE];
+
+        assert!(matches!(
+            codec.read_frame(&mut port, &mut buf),
+            Err(Error::Timeout)
+        ));
+        assert_eq!(codec.discarded(), 3);
+        assert_eq!(
+            codec.read_frame(&mut port, &mut buf).unwrap(),
+            Frame::Heartbeat
+        );
+    }
+
+    #[test]
+    fn data_too_long() {
+        let mut port = Port::new(Vec::from([send(DATA_HI), send(HEARTBEAT)]));
+        let mut codec = FrameCodec::new();
+        let mut buf = [0; 1];
+        assert!(matches!(
+            codec.read_frame(&mut port, &mut buf),
+            Err(Error::DataTooLong)
+        ));
+        assert_eq!(
+            codec.read_frame(&mut port, &mut buf).unwrap(),
+            Frame::Heartbeat
+        );
+
+        let mut sink = Cursor::empty(Vec::new());
+        let data = [0; MAX_DATA_SIZE + 1];
+        assert!(matches!(
+            FrameCodec::new().write_frame(&mut sink, Frame::Data(&data)),
+            Err(Error::DataTooLong)
+        ));
+        assert!(sink.data().is_empty());
+    }
+}
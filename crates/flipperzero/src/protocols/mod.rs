@@ -0,0 +1,87 @@
+//! Protocols for exchanging data with other devices.
+//!
+//! The protocols work over any transport implementing the [`io`] traits, such
+//! as a [`Uart`](crate::serial::Uart) or a USB virtual serial port.
+
+use core::fmt;
+
+use crate::io;
+
+pub mod expansion;
+pub mod xmodem;
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub mod ymodem;
+
+/// Reasons why a transfer failed.
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub enum Error {
+    /// The other side cancelled the transfer.
+    Cancelled,
+    /// The retry budget was used up by timeouts or damaged blocks.
+    ///
+    /// The other side has been told to cancel the transfer.
+    TooManyRetries,
+    /// A block arrived that was neither the next one nor a repeat of the previous one.
+    ///
+    /// The other side has been told to cancel the transfer.
+    OutOfSequence,
+    /// The sender announced a file without a usable name.
+    ///
+    /// The other side has been told to cancel the transfer.
+    InvalidHeader,
+    /// The sender announced a file that is larger than the free space.
+    ///
+    /// The other side has been told to cancel the transfer.
+    InsufficientSpace,
+    /// The transport timed out before a frame arrived.
+    Timeout,
+    /// The data doesn't fit into a frame, or into the buffer it is read into.
+    DataTooLong,
+    /// The transport or the file failed.
+    Io(io::Error),
+}
+
+impl Error {
+    /// Description associated with [`Error`].
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Cancelled => "transfer cancelled",
+            Self::TooManyRetries => "too many retries",
+            Self::OutOfSequence => "block out of sequence",
+            Self::InvalidHeader => "invalid file header",
+            Self::InsufficientSpace => "not enough free space",
+            Self::Timeout => "timed out",
+            Self::DataTooLong => "data too long",
+            Self::Io(_) => "I/O error",
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => error.fmt(f),
+            _ => f.write_str(self.description()),
+        }
+    }
+}
+
+impl ufmt::uDisplay for Error {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        match self {
+            Self::Io(error) => ufmt::uDisplay::fmt(error, f),
+            _ => f.write_str(self.description()),
+        }
+    }
+}
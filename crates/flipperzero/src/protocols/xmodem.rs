@@ -0,0 +1,762 @@
+//! File transfers with XMODEM-CRC.
+//!
+//! XMODEM is supported by most terminal programs, such as `sx`/`rx` from lrzsz,
+//! minicom, or Tera Term. Files are sent in blocks of 128 bytes, each protected by a
+//! CRC16, and blocks that don't arrive intact are sent again.
+//!
+//! Timeouts are detected through the transport: a read returning 0 bytes counts as a
+//! timeout. The transport should therefore be set up to wait for about a second before
+//! giving up on a read, for example with [`Uart::set_read_timeout`].
+//!
+//! [`Uart::set_read_timeout`]: crate::serial::Uart::set_read_timeout
+
+use super::Error;
+use crate::io::{self, Read, Write};
+
+/// Size of the data in a block.
+pub const BLOCK_SIZE: usize = 128;
+
+/// Start of a 128-byte block.
+pub(super) const SOH: u8 = 0x01;
+/// End of transmission.
+pub(super) const EOT: u8 = 0x04;
+pub(super) const ACK: u8 = 0x06;
+pub(super) const NAK: u8 = 0x15;
+/// Cancel, which aborts the transfer when received twice in a row.
+pub(super) const CAN: u8 = 0x18;
+/// The byte the final block is padded with.
+const SUB: u8 = 0x1A;
+/// Sent by the receiver to start a transfer with CRC16 instead of a checksum.
+pub(super) const CRC_REQUEST: u8 = b'C';
+
+/// Start byte, block number, its complement, data and CRC.
+const PACKET_SIZE: usize = 3 + BLOCK_SIZE + 2;
+
+/// Options for XMODEM transfers, which can be used to [`send`](Options::send) and
+/// [`receive`](Options::receive) files.
+#[derive(Debug, Clone)]
+pub struct Options {
+    retries: u8,
+    trim_padding: bool,
+}
+
+impl Options {
+    /// Creates the default options, which allow 10 retries and keep padding.
+    pub fn new() -> Self {
+        Self {
+            retries: 10,
+            trim_padding: false,
+        }
+    }
+
+    /// Sets how many timeouts or damaged blocks in a row are tolerated before giving up.
+    ///
+    /// This also limits how long the sender waits for the receiver to start the
+    /// transfer, and vice versa.
+    pub fn retries(mut self, retries: u8) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Sets whether received files are stripped of the SUB bytes (`0x1A`) the final
+    /// block is padded with.
+    ///
+    /// XMODEM doesn't transfer the length of the file, so this also strips SUB bytes
+    /// that the file actually ended with. It is best suited for text files.
+    pub fn trim_padding(mut self, trim: bool) -> Self {
+        self.trim_padding = trim;
+        self
+    }
+
+    /// Sends the contents of `file` to the receiver on `port`, returning the number of
+    /// bytes sent.
+    ///
+    /// The final block is padded with SUB bytes.
+    pub fn send<F, P>(&self, file: &mut F, port: &mut P) -> Result<u64, Error>
+    where
+        F: Read + ?Sized,
+        P: Read + Write + ?Sized,
+    {
+        let mut failures = 0;
+        loop {
+            match read_byte(port)? {
+                Some(CRC_REQUEST) => break,
+                Some(CAN) if read_byte(port)? == Some(CAN) => return Err(Error::Cancelled),
+                // Includes requests for the checksum variant, which isn't supported.
+                _ => failures += 1,
+            }
+            if failures > self.retries {
+                cancel(port);
+                return Err(Error::TooManyRetries);
+            }
+        }
+
+        let mut seq = 1u8;
+        let mut sent = 0;
+        let mut data = [0; BLOCK_SIZE];
+        loop {
+            let len = read_full(file, &mut data)?;
+            if len == 0 {
+                break;
+            }
+            data[len..].fill(SUB);
+            let packet = encode_block(seq, &data);
+            self.transmit(port, &packet)?;
+
+            sent += len as u64;
+            seq = seq.wrapping_add(1);
+            if len < BLOCK_SIZE {
+                break;
+            }
+        }
+
+        self.transmit(port, &[EOT])?;
+        Ok(sent)
+    }
+
+    /// Receives a file from the sender on `port` and writes it to `dest`, returning the
+    /// number of bytes written.
+    ///
+    /// The final block is only written once the sender has signalled the end of the
+    /// file, so that its padding can be trimmed.
+    pub fn receive<P, D>(&self, port: &mut P, dest: &mut D) -> Result<u64, Error>
+    where
+        P: Read + Write + ?Sized,
+        D: Write + ?Sized,
+    {
+        let mut expected = 1u8;
+        let mut started = false;
+        let mut failures = 0;
+        let mut written = 0;
+        let mut pending: Option<[u8; BLOCK_SIZE]> = None;
+        let mut reply = CRC_REQUEST;
+        loop {
+            port.write_all(&[reply])?;
+            let ok = match read_byte(port)? {
+                Some(SOH) => {
+                    // Damaged blocks are answered with NAK from now on.
+                    started = true;
+                    match read_block(port)? {
+                        Some((seq, data)) if seq == expected => {
+                            if let Some(previous) = pending.replace(data) {
+                                dest.write_all(&previous)?;
+                                written += BLOCK_SIZE as u64;
+                            }
+                            expected = expected.wrapping_add(1);
+                            true
+                        }
+                        // Our acknowledgement was lost, so the sender repeated the block.
+                        Some((seq, _)) if pending.is_some() && seq == expected.wrapping_sub(1) => {
+                            true
+                        }
+                        Some(_) => {
+                            cancel(port);
+                            return Err(Error::OutOfSequence);
+                        }
+                        None => {
+                            purge(port)?;
+                            false
+                        }
+                    }
+                }
+                Some(EOT) => {
+                    if let Some(last) = pending {
+                        let len = if self.trim_padding {
+                            last.iter().rposition(|&b| b != SUB).map_or(0, |i| i + 1)
+                        } else {
+                            BLOCK_SIZE
+                        };
+                        dest.write_all(&last[..len])?;
+                        written += len as u64;
+                    }
+                    port.write_all(&[ACK])?;
+                    return Ok(written);
+                }
+                Some(CAN) if read_byte(port)? == Some(CAN) => return Err(Error::Cancelled),
+                Some(_) => {
+                    purge(port)?;
+                    false
+                }
+                None => false,
+            };
+
+            if ok {
+                failures = 0;
+                reply = ACK;
+            } else {
+                failures += 1;
+                if failures > self.retries {
+                    cancel(port);
+                    return Err(Error::TooManyRetries);
+                }
+                reply = if started { NAK } else { CRC_REQUEST };
+            }
+        }
+    }
+
+    /// Sends `packet` until the receiver acknowledges it.
+    fn transmit<P>(&self, port: &mut P, packet: &[u8]) -> Result<(), Error>
+    where
+        P: Read + Write + ?Sized,
+    {
+        let mut failures = 0;
+        loop {
+            port.write_all(packet)?;
+            loop {
+                match read_byte(port)? {
+                    Some(ACK) => return Ok(()),
+                    Some(NAK) | None => break,
+                    Some(CAN) if read_byte(port)? == Some(CAN) => return Err(Error::Cancelled),
+                    // Such as a late request to start the transfer.
+                    Some(_) => {}
+                }
+            }
+            failures += 1;
+            if failures > self.retries {
+                cancel(port);
+                return Err(Error::TooManyRetries);
+            }
+        }
+    }
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Sends the contents of `file` to the receiver on `port` with the default
+/// [`Options`], returning the number of bytes sent.
+pub fn send<F, P>(file: &mut F, port: &mut P) -> Result<u64, Error>
+where
+    F: Read + ?Sized,
+    P: Read + Write + ?Sized,
+{
+    Options::new().send(file, port)
+}
+
+/// Receives a file from the sender on `port` with the default [`Options`], returning
+/// the number of bytes written to `dest`.
+pub fn receive<P, D>(port: &mut P, dest: &mut D) -> Result<u64, Error>
+where
+    P: Read + Write + ?Sized,
+    D: Write + ?Sized,
+{
+    Options::new().receive(port, dest)
+}
+
+/// Reads a byte, returning `None` on timeout.
+pub(super) fn read_byte<R: Read + ?Sized>(port: &mut R) -> Result<Option<u8>, io::Error> {
+    let mut byte = 0;
+    let n = port.read(core::slice::from_mut(&mut byte))?;
+    Ok((n > 0).then_some(byte))
+}
+
+/// Reads until `buf` is full or the reader returns no more bytes, returning how many
+/// bytes were read.
+pub(super) fn read_full<R: Read + ?Sized>(
+    reader: &mut R,
+    buf: &mut [u8],
+) -> Result<usize, io::Error> {
+    let mut len = 0;
+    while len < buf.len() {
+        match reader.read(&mut buf[len..])? {
+            0 => break,
+            n => len += n,
+        }
+    }
+    Ok(len)
+}
+
+/// Reads the rest of a block after its start byte, returning its number and data, or
+/// `None` if it was incomplete or damaged.
+fn read_block<R: Read + ?Sized>(port: &mut R) -> Result<Option<(u8, [u8; BLOCK_SIZE])>, io::Error> {
+    let mut packet = [0; PACKET_SIZE - 1];
+    if read_full(port, &mut packet)? < packet.len() {
+        return Ok(None);
+    }
+    let (header, rest) = packet.split_at(2);
+    let (data, crc) = rest.split_at(BLOCK_SIZE);
+    if header[0] != !header[1] || crc16(data).to_be_bytes() != crc {
+        return Ok(None);
+    }
+    Ok(Some((header[0], data.try_into().unwrap())))
+}
+
+/// Discards bytes until the sender pauses, so that the rest of a damaged block isn't
+/// taken for the next one.
+pub(super) fn purge<R: Read + ?Sized>(port: &mut R) -> Result<(), io::Error> {
+    while read_byte(port)?.is_some() {}
+    Ok(())
+}
+
+/// Tells the other side to abort the transfer.
+pub(super) fn cancel<W: Write + ?Sized>(port: &mut W) {
+    // The transfer has failed anyway.
+    let _ = port.write_all(&[CAN, CAN]);
+}
+
+/// Encodes `data` as the block with number `seq`.
+fn encode_block(seq: u8, data: &[u8; BLOCK_SIZE]) -> [u8; PACKET_SIZE] {
+    let mut packet = [0; PACKET_SIZE];
+    packet[0] = SOH;
+    packet[1] = seq;
+    packet[2] = !seq;
+    packet[3..3 + BLOCK_SIZE].copy_from_slice(data);
+    packet[3 + BLOCK_SIZE..].copy_from_slice(&crc16(data).to_be_bytes());
+    packet
+}
+
+/// The CRC16 used by XMODEM, also known as CRC-16/XMODEM.
+pub(super) fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0, |mut crc, &byte| {
+        crc ^= u16::from(byte) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+#[cfg(feature = "alloc")]
+#[flipperzero_test::tests]
+mod tests {
+    use alloc::collections::VecDeque;
+    use alloc::vec::Vec;
+
+    use super::{
+        crc16, encode_block, Error, Options, ACK, BLOCK_SIZE, CAN, CRC_REQUEST, EOT, NAK, SUB,
+    };
+    use crate::io::cursor::Cursor;
+    use crate::io::{self, Read, Write};
+
+    /// What the other side of a [`Port`] does next.
+    pub(crate) enum Step {
+        Send(Vec<u8>),
+        Timeout,
+    }
+
+    /// A transport that replays a script of incoming bytes and timeouts, and records
+    /// everything written to it.
+    pub(crate) struct Port {
+        script: VecDeque<Step>,
+        pub(crate) written: Vec<u8>,
+    }
+
+    impl Port {
+        pub(crate) fn new(script: Vec<Step>) -> Self {
+            Self {
+                script: script.into(),
+                written: Vec::new(),
+            }
+        }
+    }
+
+    impl Read for Port {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
+            match self.script.pop_front() {
+                Some(Step::Send(mut bytes)) => {
+                    let n = buf.len().min(bytes.len());
+                    buf[..n].copy_from_slice(&bytes[..n]);
+                    if n < bytes.len() {
+                        bytes.drain(..n);
+                        self.script.push_front(Step::Send(bytes));
+                    }
+                    Ok(n)
+                }
+                Some(Step::Timeout) => Ok(0),
+                // Reading past the end of the script is a bug in the protocol or test.
+                None => Err(io::Error::NotReady),
+            }
+        }
+    }
+
+    impl Write for Port {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, io::Error> {
+            self.written.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> Result<(), io::Error> {
+            Ok(())
+        }
+    }
+
+    pub(crate) fn byte(b: u8) -> Step {
+        Step::Send(Vec::from([b]))
+    }
+
+    /// Returns the packet for block `seq`, padding `data` with SUB bytes.
+    fn block(seq: u8, data: &[u8]) -> Vec<u8> {
+        let mut padded = [SUB; BLOCK_SIZE];
+        padded[..data.len()].copy_from_slice(data);
+        Vec::from(encode_block(seq, &padded))
+    }
+
+    fn receive(options: Options, script: Vec<Step>) -> (Result<u64, Error>, Vec<u8>, Port) {
+        let mut port = Port::new(script);
+        let mut sink = Cursor::empty(Vec::new());
+        let result = options.receive(&mut port, &mut sink);
+        (result, sink.data().to_vec(), port)
+    }
+
+    fn send(options: Options, data: &[u8], script: Vec<Step>) -> (Result<u64, Error>, Port) {
+        let mut port = Port::new(script);
+        let result = options.send(&mut Cursor::new(data), &mut port);
+        (result, port)
+    }
+
+    #[test]
+    fn crc() {
+        assert_eq!(crc16(b""), 0);
+        assert_eq!(crc16(b"123456789"), 0x31C3);
+    }
+
+    #[test]
+    fn block_layout() {
+        let packet = block(1, b"hi");
+        assert_eq!(packet.len(), 133);
+        assert_eq!(packet[..5], [0x01, 1, 0xFE, b'h', b'i']);
+        assert_eq!(packet[5], SUB);
+        assert_eq!(packet[131..], crc16(&packet[3..131]).to_be_bytes());
+    }
+
+    #[test]
+    fn receive_trims_padding() {
+        let script = Vec::from([Step::Send(block(1, b"hello")), byte(EOT)]);
+        let (result, data, port) = receive(Options::new().trim_padding(true), script);
+
+        assert!(matches!(result, Ok(5)));
+        assert_eq!(data, b"hello");
+        assert_eq!(port.written, [CRC_REQUEST, ACK, ACK]);
+    }
+
+    #[test]
+    fn receive_keeps_padding() {
+        let script = Vec::from([Step::Send(block(1, b"hello")), byte(EOT)]);
+        let (result, data, _) = receive(Options::new(), script);
+
+        assert!(matches!(result, Ok(128)));
+        assert_eq!(&data[..5], b"hello");
+        assert!(data[5..].iter().all(|&b| b == SUB));
+    }
+
+    #[test]
+    fn receive_only_trims_final_block() {
+        let first = [SUB; BLOCK_SIZE];
+        let script = Vec::from([
+            Step::Send(block(1, &first)),
+            Step::Send(block(2, b"end")),
+            byte(EOT),
+        ]);
+        let (result, data, _) = receive(Options::new().trim_padding(true), script);
+
+        assert!(matches!(result, Ok(131)));
+        assert_eq!(data[..BLOCK_SIZE], first);
+        assert_eq!(&data[BLOCK_SIZE..], b"end");
+    }
+
+    #[test]
+    fn receive_empty_file() {
+        let (result, data, port) = receive(Options::new(), Vec::from([byte(EOT)]));
+
+        assert!(matches!(result, Ok(0)));
+        assert!(data.is_empty());
+        assert_eq!(port.written, [CRC_REQUEST, ACK]);
+    }
+
+    #[test]
+    fn receive_repeats_start_request() {
+        let script = Vec::from([
+            Step::Timeout,
+            Step::Timeout,
+            Step::Send(block(1, b"a")),
+            byte(EOT),
+        ]);
+        let (result, _, port) = receive(Options::new(), script);
+
+        assert!(result.is_ok());
+        assert_eq!(
+            port.written,
+            [CRC_REQUEST, CRC_REQUEST, CRC_REQUEST, ACK, ACK]
+        );
+    }
+
+    #[test]
+    fn receive_naks_damaged_block() {
+        let mut damaged = block(1, b"abc");
+        damaged[10] ^= 0xFF;
+        let script = Vec::from([
+            Step::Send(damaged),
+            // Ends the purge.
+            Step::Timeout,
+            Step::Send(block(1, b"abc")),
+            byte(EOT),
+        ]);
+        let (result, data, port) = receive(Options::new().trim_padding(true), script);
+
+        assert!(matches!(result, Ok(3)));
+        assert_eq!(data, b"abc");
+        assert_eq!(port.written, [CRC_REQUEST, NAK, ACK, ACK]);
+    }
+
+    #[test]
+    fn receive_naks_truncated_block() {
+        let mut truncated = block(1, b"abc");
+        truncated.truncate(50);
+        let script = Vec::from([
+            Step::Send(truncated),
+            Step::Timeout,
+            Step::Timeout,
+            Step::Send(block(1, b"abc")),
+            byte(EOT),
+        ]);
+        let (result, _, port) = receive(Options::new(), script);
+
+        assert!(result.is_ok());
+        assert_eq!(port.written, [CRC_REQUEST, NAK, ACK, ACK]);
+    }
+
+    #[test]
+    fn receive_naks_bad_block_number_complement() {
+        let mut bad = block(1, b"abc");
+        bad[2] = 0;
+        let script = Vec::from([
+            Step::Send(bad),
+            Step::Timeout,
+            Step::Send(block(1, b"abc")),
+            byte(EOT),
+        ]);
+        let (result, _, port) = receive(Options::new(), script);
+
+        assert!(result.is_ok());
+        assert_eq!(port.written, [CRC_REQUEST, NAK, ACK, ACK]);
+    }
+
+    #[test]
+    fn receive_naks_timeout_between_blocks() {
+        let script = Vec::from([
+            Step::Send(block(1, b"a")),
+            Step::Timeout,
+            Step::Send(block(2, b"b")),
+            byte(EOT),
+        ]);
+        let (result, _, port) = receive(Options::new(), script);
+
+        assert!(result.is_ok());
+        assert_eq!(port.written, [CRC_REQUEST, ACK, NAK, ACK, ACK]);
+    }
+
+    #[test]
+    fn receive_skips_duplicate_block() {
+        let script = Vec::from([
+            Step::Send(block(1, b"one")),
+            Step::Send(block(1, b"one")),
+            Step::Send(block(2, b"two")),
+            byte(EOT),
+        ]);
+        let (result, data, port) = receive(Options::new().trim_padding(true), script);
+
+        assert!(matches!(result, Ok(131)));
+        assert_eq!(&data[..3], b"one");
+        assert_eq!(&data[BLOCK_SIZE..], b"two");
+        assert_eq!(port.written, [CRC_REQUEST, ACK, ACK, ACK, ACK]);
+    }
+
+    #[test]
+    fn receive_wraps_block_numbers() {
+        let mut script: Vec<_> = (1..=256u32)
+            .map(|i| Step::Send(block(i as u8, &[i as u8])))
+            .collect();
+        script.push(byte(EOT));
+        let (result, data, _) = receive(Options::new(), script);
+
+        assert!(matches!(result, Ok(n) if n == 256 * BLOCK_SIZE as u64));
+        assert_eq!(data[255 * BLOCK_SIZE], 0);
+    }
+
+    #[test]
+    fn receive_cancels_out_of_sequence_block() {
+        let script = Vec::from([Step::Send(block(1, b"a")), Step::Send(block(3, b"c"))]);
+        let (result, data, port) = receive(Options::new(), script);
+
+        assert!(matches!(result, Err(Error::OutOfSequence)));
+        assert!(data.is_empty());
+        assert_eq!(port.written, [CRC_REQUEST, ACK, CAN, CAN]);
+    }
+
+    #[test]
+    fn receive_gives_up_after_retries() {
+        let script = Vec::from([Step::Timeout, Step::Timeout, Step::Timeout]);
+        let (result, _, port) = receive(Options::new().retries(2), script);
+
+        assert!(matches!(result, Err(Error::TooManyRetries)));
+        assert_eq!(
+            port.written,
+            [CRC_REQUEST, CRC_REQUEST, CRC_REQUEST, CAN, CAN]
+        );
+    }
+
+    #[test]
+    fn receive_resets_retries_after_good_block() {
+        let script = Vec::from([
+            Step::Timeout,
+            Step::Send(block(1, b"a")),
+            Step::Timeout,
+            Step::Send(block(2, b"b")),
+            byte(EOT),
+        ]);
+        let (result, _, _) = receive(Options::new().retries(1), script);
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn receive_sender_cancels() {
+        let script = Vec::from([Step::Send(block(1, b"a")), byte(CAN), byte(CAN)]);
+        let (result, _, port) = receive(Options::new(), script);
+
+        assert!(matches!(result, Err(Error::Cancelled)));
+        assert_eq!(port.written, [CRC_REQUEST, ACK]);
+    }
+
+    #[test]
+    fn receive_ignores_single_cancel() {
+        let script = Vec::from([
+            byte(CAN),
+            byte(b'x'),
+            Step::Timeout,
+            Step::Send(block(1, b"a")),
+            byte(EOT),
+        ]);
+        let (result, _, port) = receive(Options::new(), script);
+
+        assert!(result.is_ok());
+        assert_eq!(port.written, [CRC_REQUEST, CRC_REQUEST, ACK, ACK]);
+    }
+
+    #[test]
+    fn send_single_block() {
+        let script = Vec::from([byte(CRC_REQUEST), byte(ACK), byte(ACK)]);
+        let (result, port) = send(Options::new(), b"hello", script);
+
+        assert!(matches!(result, Ok(5)));
+        let mut expected = block(1, b"hello");
+        expected.push(EOT);
+        assert_eq!(port.written, expected);
+    }
+
+    #[test]
+    fn send_full_blocks_without_padding_block() {
+        let data = [0x55; 2 * BLOCK_SIZE];
+        let script = Vec::from([byte(CRC_REQUEST), byte(ACK), byte(ACK), byte(ACK)]);
+        let (result, port) = send(Options::new(), &data, script);
+
+        assert!(matches!(result, Ok(256)));
+        let mut expected = block(1, &data[..BLOCK_SIZE]);
+        expected.extend(block(2, &data[BLOCK_SIZE..]));
+        expected.push(EOT);
+        assert_eq!(port.written, expected);
+    }
+
+    #[test]
+    fn send_empty_file() {
+        let script = Vec::from([byte(CRC_REQUEST), byte(ACK)]);
+        let (result, port) = send(Options::new(), b"", script);
+
+        assert!(matches!(result, Ok(0)));
+        assert_eq!(port.written, [EOT]);
+    }
+
+    #[test]
+    fn send_waits_for_start_request() {
+        let script = Vec::from([
+            Step::Timeout,
+            byte(NAK),
+            byte(CRC_REQUEST),
+            byte(ACK),
+            byte(ACK),
+        ]);
+        let (result, _) = send(Options::new(), b"a", script);
+
+        assert!(matches!(result, Ok(1)));
+    }
+
+    #[test]
+    fn send_retransmits_on_nak_and_timeout() {
+        let script = Vec::from([
+            byte(CRC_REQUEST),
+            byte(NAK),
+            Step::Timeout,
+            byte(ACK),
+            byte(NAK),
+            byte(ACK),
+        ]);
+        let (result, port) = send(Options::new(), b"abc", script);
+
+        assert!(matches!(result, Ok(3)));
+        let packet = block(1, b"abc");
+        let mut expected = [packet.as_slice(); 3].concat();
+        expected.extend([EOT, EOT]);
+        assert_eq!(port.written, expected);
+    }
+
+    #[test]
+    fn send_ignores_stray_bytes() {
+        let script = Vec::from([byte(CRC_REQUEST), byte(CRC_REQUEST), byte(ACK), byte(ACK)]);
+        let (result, port) = send(Options::new(), b"a", script);
+
+        assert!(matches!(result, Ok(1)));
+        assert_eq!(port.written.len(), 133 + 1);
+    }
+
+    #[test]
+    fn send_gives_up_after_retries() {
+        let script = Vec::from([byte(CRC_REQUEST), byte(NAK), byte(NAK), byte(NAK)]);
+        let (result, port) = send(Options::new().retries(2), b"a", script);
+
+        assert!(matches!(result, Err(Error::TooManyRetries)));
+        assert_eq!(port.written.len(), 3 * 133 + 2);
+        assert_eq!(port.written[3 * 133..], [CAN, CAN]);
+    }
+
+    #[test]
+    fn send_gives_up_waiting_for_receiver() {
+        let script = Vec::from([Step::Timeout, Step::Timeout]);
+        let (result, port) = send(Options::new().retries(1), b"a", script);
+
+        assert!(matches!(result, Err(Error::TooManyRetries)));
+        assert_eq!(port.written, [CAN, CAN]);
+    }
+
+    #[test]
+    fn send_receiver_cancels() {
+        let script = Vec::from([byte(CRC_REQUEST), byte(CAN), byte(CAN)]);
+        let (result, port) = send(Options::new(), b"a", script);
+
+        assert!(matches!(result, Err(Error::Cancelled)));
+        assert_eq!(port.written.len(), 133);
+    }
+
+    #[test]
+    fn send_receiver_cancels_before_start() {
+        let script = Vec::from([byte(CAN), byte(CAN)]);
+        let (result, port) = send(Options::new(), b"a", script);
+
+        assert!(matches!(result, Err(Error::Cancelled)));
+        assert!(port.written.is_empty());
+    }
+
+    #[test]
+    fn transport_errors_are_reported() {
+        // The script ends before the transfer does.
+        let (result, _) = send(Options::new(), b"a", Vec::from([byte(CRC_REQUEST)]));
+
+        assert!(matches!(result, Err(Error::Io(io::Error::NotReady))));
+    }
+}
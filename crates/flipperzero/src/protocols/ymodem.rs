@@ -0,0 +1,569 @@
+//! Batch file transfers with YMODEM.
+//!
+//! YMODEM extends [XMODEM](super::xmodem) with a header block announcing the name and
+//! size of each file, so that several files can be sent in one session and are saved
+//! with their exact length. Blocks of 1024 bytes (YMODEM-1K) are supported as well.
+//!
+//! As with XMODEM, a read returning 0 bytes counts as a timeout, so the transport should
+//! wait for about a second before giving up on a read.
+
+use alloc::{ffi::CString, string::String, vec, vec::Vec};
+use core::ffi::CStr;
+
+use super::xmodem::{
+    cancel, crc16, purge, read_byte, read_full, ACK, CAN, CRC_REQUEST, EOT, NAK, SOH,
+};
+use super::Error;
+use crate::furi::string::FuriString;
+use crate::io::{self, Read, Write};
+use crate::storage::{sanitize_filename, BufWriter, OpenOptions, Storage};
+
+/// Start of a 1024-byte block.
+const STX: u8 = 0x02;
+/// Size of the data in a block started with SOH.
+const BLOCK_SIZE: usize = 128;
+/// Size of the data in a block started with STX.
+const LARGE_BLOCK_SIZE: usize = 1024;
+/// How many timeouts or damaged blocks in a row are tolerated before giving up.
+const RETRIES: u8 = 10;
+
+/// Receives a batch of files from the sender on `port`, saving them in `dest_dir`.
+///
+/// Returns the paths of the saved files. File names are reduced to their last path
+/// component and passed through [`sanitize_filename`], and existing files of the same
+/// name are overwritten.
+///
+/// `accept` is called with the name and size of each file before it is received, and
+/// files for which it returns `false` are received but discarded. The size is 0 if the
+/// sender didn't announce it.
+///
+/// # Errors
+///
+/// Returns [`Error::InsufficientSpace`] if an accepted file is larger than the free
+/// space in `dest_dir`, and [`Error::InvalidHeader`] if a file name is unusable even
+/// after sanitizing it. In both cases, and whenever the transfer fails, the transfer is
+/// cancelled and the file being received is removed. Files that were saved before are
+/// kept.
+pub fn receive_batch<P, F>(
+    port: &mut P,
+    dest_dir: &CStr,
+    mut accept: F,
+) -> Result<Vec<CString>, Error>
+where
+    P: Read + Write + ?Sized,
+    F: FnMut(&str, u64) -> bool,
+{
+    let storage = Storage::open();
+    let mut receiver = Receiver::new(port);
+    let mut saved = Vec::new();
+
+    while let Some(header) = receiver.next_header()? {
+        // Sanitized names only consist of ASCII characters.
+        let name = core::str::from_utf8(header.name.to_bytes()).unwrap();
+        if !accept(name, header.size.unwrap_or(0)) {
+            receiver.receive_file(header.size, &mut Discard)?;
+            continue;
+        }
+
+        let free_space = match storage.fs_info(dest_dir) {
+            Ok(info) => info.free_space,
+            Err(e) => return Err(receiver.abort(e)),
+        };
+        if header.size.is_some_and(|size| size > free_space) {
+            cancel(receiver.port);
+            return Err(Error::InsufficientSpace);
+        }
+
+        let path = join(dest_dir, header.name.as_c_str());
+        let file = match OpenOptions::new()
+            .write(true)
+            .create_always(true)
+            .open(&path)
+        {
+            Ok(file) => file,
+            Err(e) => return Err(receiver.abort(e)),
+        };
+        let mut writer = BufWriter::for_path(path.as_c_str(), file);
+        if let Err(e) = receiver.receive_file(header.size, &mut writer) {
+            // Close the file before removing it.
+            drop(writer);
+            let _ = storage.remove(&path);
+            return Err(e);
+        }
+        saved.push(path);
+    }
+
+    Ok(saved)
+}
+
+/// A file announced by the sender.
+struct Header {
+    /// The sanitized file name.
+    name: FuriString,
+    size: Option<u64>,
+}
+
+/// Parses the data of a header block, returning `None` for the empty header ending the
+/// batch.
+fn parse_header(data: &[u8]) -> Result<Option<Header>, Error> {
+    let nul = data
+        .iter()
+        .position(|&b| b == 0)
+        .ok_or(Error::InvalidHeader)?;
+    let (path, fields) = (&data[..nul], &data[nul + 1..]);
+    if path.is_empty() {
+        return Ok(None);
+    }
+
+    // The sender may include the directory the file was sent from.
+    let base = path.rsplit(|&b| b == b'/' || b == b'\\').next().unwrap();
+    let name = sanitize_filename(&String::from_utf8_lossy(base));
+    if name.is_empty() {
+        return Err(Error::InvalidHeader);
+    }
+
+    // The size is followed by optional fields such as the modification time, separated
+    // by spaces.
+    let digits = fields.iter().take_while(|b| b.is_ascii_digit()).count();
+    let size = if digits == 0 {
+        None
+    } else {
+        // Only contains ASCII digits.
+        let size = core::str::from_utf8(&fields[..digits]).unwrap();
+        Some(size.parse().map_err(|_| Error::InvalidHeader)?)
+    };
+
+    Ok(Some(Header { name, size }))
+}
+
+/// Returns `name` in `dir`.
+fn join(dir: &CStr, name: &CStr) -> CString {
+    let mut path = dir.to_bytes().to_vec();
+    if !path.ends_with(b"/") {
+        path.push(b'/');
+    }
+    path.extend_from_slice(name.to_bytes());
+    // Neither part contains a NUL byte.
+    CString::new(path).unwrap()
+}
+
+/// What the sender sent next.
+enum Packet {
+    /// An intact block with the given number, whose data is in the receive buffer.
+    Block { seq: u8, len: usize },
+    /// The end of the current file.
+    End,
+}
+
+/// The receiving side of a session.
+struct Receiver<'a, P: ?Sized> {
+    port: &'a mut P,
+    /// Block number, its complement, data and CRC of the last block.
+    buf: Vec<u8>,
+}
+
+impl<'a, P: Read + Write + ?Sized> Receiver<'a, P> {
+    fn new(port: &'a mut P) -> Self {
+        Self {
+            port,
+            buf: vec![0; 2 + LARGE_BLOCK_SIZE + 2],
+        }
+    }
+
+    /// Returns the data of the last block.
+    fn data(&self, len: usize) -> &[u8] {
+        &self.buf[2..2 + len]
+    }
+
+    /// Cancels the transfer because of a failure on our side.
+    fn abort(&mut self, error: io::Error) -> Error {
+        cancel(self.port);
+        Error::Io(error)
+    }
+
+    /// Sends `reply` and waits for the next intact block or the end of the file, sending
+    /// `retry` whenever none arrives.
+    fn next(&mut self, mut reply: u8, retry: u8) -> Result<Packet, Error> {
+        let mut failures = 0;
+        loop {
+            self.port.write_all(&[reply])?;
+            let start = match read_byte(self.port)? {
+                Some(SOH) => Some(BLOCK_SIZE),
+                Some(STX) => Some(LARGE_BLOCK_SIZE),
+                Some(EOT) => return Ok(Packet::End),
+                Some(CAN) if read_byte(self.port)? == Some(CAN) => return Err(Error::Cancelled),
+                Some(_) => Some(0),
+                // A timeout.
+                None => None,
+            };
+            if let Some(len) = start {
+                if len > 0 {
+                    if let Some(seq) = self.read_block(len)? {
+                        return Ok(Packet::Block { seq, len });
+                    }
+                }
+                // Don't take the rest of a damaged block for the next one.
+                purge(self.port)?;
+            }
+
+            failures += 1;
+            if failures > RETRIES {
+                cancel(self.port);
+                return Err(Error::TooManyRetries);
+            }
+            reply = retry;
+        }
+    }
+
+    /// Reads the rest of a block with `len` bytes of data after its start byte,
+    /// returning its number if it is intact.
+    fn read_block(&mut self, len: usize) -> Result<Option<u8>, io::Error> {
+        let packet = &mut self.buf[..2 + len + 2];
+        if read_full(self.port, packet)? < packet.len() {
+            return Ok(None);
+        }
+        let (header, rest) = packet.split_at(2);
+        let (data, crc) = rest.split_at(len);
+        if header[0] != !header[1] || crc16(data).to_be_bytes() != crc {
+            return Ok(None);
+        }
+        Ok(Some(header[0]))
+    }
+
+    /// Requests and acknowledges the header of the next file, returning `None` once
+    /// the batch is complete.
+    fn next_header(&mut self) -> Result<Option<Header>, Error> {
+        loop {
+            match self.next(CRC_REQUEST, CRC_REQUEST)? {
+                Packet::Block { seq: 0, len } => {
+                    let header = match parse_header(self.data(len)) {
+                        Ok(header) => header,
+                        Err(e) => {
+                            cancel(self.port);
+                            return Err(e);
+                        }
+                    };
+                    self.port.write_all(&[ACK])?;
+                    return Ok(header);
+                }
+                // Our acknowledgement of the end of the previous file was lost.
+                Packet::End => self.port.write_all(&[ACK])?,
+                Packet::Block { .. } => {
+                    cancel(self.port);
+                    return Err(Error::OutOfSequence);
+                }
+            }
+        }
+    }
+
+    /// Receives the data of the file whose header was just acknowledged, writing at
+    /// most `size` bytes of it to `dest`.
+    ///
+    /// `dest` is flushed before the end of the file is acknowledged, so that the sender
+    /// learns about failures to write it.
+    fn receive_file<W>(&mut self, size: Option<u64>, dest: &mut W) -> Result<(), Error>
+    where
+        W: Write + ?Sized,
+    {
+        let mut remaining = size;
+        let mut expected = 1u8;
+        let mut received = false;
+        let mut reply = CRC_REQUEST;
+        loop {
+            let retry = if received { NAK } else { CRC_REQUEST };
+            match self.next(reply, retry)? {
+                Packet::Block { seq, len } if seq == expected => {
+                    let len = match remaining {
+                        Some(r) => {
+                            // The rest of the final block is padding.
+                            let len = len.min(usize::try_from(r).unwrap_or(usize::MAX));
+                            remaining = Some(r - len as u64);
+                            len
+                        }
+                        None => len,
+                    };
+                    if let Err(e) = dest.write_all(&self.buf[2..2 + len]) {
+                        return Err(self.abort(e));
+                    }
+                    expected = expected.wrapping_add(1);
+                    received = true;
+                    reply = ACK;
+                }
+                // Our acknowledgement was lost, so the sender repeated the block. Before
+                // the first block, this is the header.
+                Packet::Block { seq, .. } if seq == expected.wrapping_sub(1) => reply = ACK,
+                Packet::Block { .. } => {
+                    cancel(self.port);
+                    return Err(Error::OutOfSequence);
+                }
+                Packet::End => {
+                    if let Err(e) = dest.flush() {
+                        return Err(self.abort(e));
+                    }
+                    self.port.write_all(&[ACK])?;
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+/// Drops everything written to it.
+struct Discard;
+
+impl Write for Discard {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, io::Error> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), io::Error> {
+        Ok(())
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::{join, parse_header, Receiver, BLOCK_SIZE, LARGE_BLOCK_SIZE, SOH, STX};
+    use crate::io::cursor::Cursor;
+    use crate::protocols::xmodem::tests::{byte, Port, Step};
+    use crate::protocols::xmodem::{crc16, ACK, CAN, CRC_REQUEST, EOT, NAK};
+    use crate::protocols::Error;
+
+    /// Returns the packet for block `seq`, padding `data` with `fill`.
+    fn packet(start: u8, seq: u8, data: &[u8], fill: u8) -> Vec<u8> {
+        let len = if start == STX {
+            LARGE_BLOCK_SIZE
+        } else {
+            BLOCK_SIZE
+        };
+        let mut padded = Vec::from(data);
+        padded.resize(len, fill);
+
+        let mut packet = Vec::from([start, seq, !seq]);
+        packet.extend_from_slice(&padded);
+        packet.extend_from_slice(&crc16(&padded).to_be_bytes());
+        packet
+    }
+
+    fn header(fields: &[u8]) -> Step {
+        Step::Send(packet(SOH, 0, fields, 0))
+    }
+
+    fn block(seq: u8, data: &[u8]) -> Step {
+        Step::Send(packet(SOH, seq, data, 0x1A))
+    }
+
+    #[test]
+    fn headers() {
+        let header = parse_header(b"foo.txt\x001234 14254736571 100644\0")
+            .unwrap()
+            .unwrap();
+        assert_eq!(header.name.to_bytes(), b"foo.txt");
+        assert_eq!(header.size, Some(1234));
+
+        let header = parse_header(b"dir/sub\\a:b.txt\0\0").unwrap().unwrap();
+        assert_eq!(header.name.to_bytes(), b"a_b.txt");
+        assert!(header.size.is_none());
+    }
+
+    #[test]
+    fn end_of_batch_header() {
+        assert!(parse_header(&[0; BLOCK_SIZE]).unwrap().is_none());
+    }
+
+    #[test]
+    fn invalid_headers() {
+        assert!(matches!(
+            parse_header(b"..\x005\0"),
+            Err(Error::InvalidHeader)
+        ));
+        assert!(matches!(
+            parse_header(b"dir/\x005\0"),
+            Err(Error::InvalidHeader)
+        ));
+        assert!(matches!(
+            parse_header(&[b'a'; BLOCK_SIZE]),
+            Err(Error::InvalidHeader)
+        ));
+        assert!(matches!(
+            parse_header(b"big\x0099999999999999999999\0"),
+            Err(Error::InvalidHeader)
+        ));
+    }
+
+    #[test]
+    fn joins_paths() {
+        let name = c"a.txt";
+        let dir = c"/ext";
+        assert_eq!(join(dir, name).as_bytes(), b"/ext/a.txt");
+        let dir = c"/ext/";
+        assert_eq!(join(dir, name).as_bytes(), b"/ext/a.txt");
+    }
+
+    #[test]
+    fn receives_exact_length() {
+        let data: Vec<u8> = (0..1100u32).map(|i| i as u8).collect();
+        let mut port = Port::new(Vec::from([
+            header(b"data.bin\x001100\0"),
+            Step::Send(packet(STX, 1, &data[..LARGE_BLOCK_SIZE], 0x1A)),
+            block(2, &data[LARGE_BLOCK_SIZE..]),
+            byte(EOT),
+        ]));
+        let mut sink = Cursor::empty(Vec::new());
+
+        let mut receiver = Receiver::new(&mut port);
+        let header = receiver.next_header().unwrap().unwrap();
+        assert_eq!(header.size, Some(1100));
+        receiver.receive_file(header.size, &mut sink).unwrap();
+
+        assert_eq!(sink.data(), data);
+        assert_eq!(port.written, [CRC_REQUEST, ACK, CRC_REQUEST, ACK, ACK, ACK]);
+    }
+
+    #[test]
+    fn keeps_padding_without_size() {
+        let mut port = Port::new(Vec::from([block(1, b"abc"), byte(EOT)]));
+        let mut sink = Cursor::empty(Vec::new());
+
+        Receiver::new(&mut port)
+            .receive_file(None, &mut sink)
+            .unwrap();
+
+        assert_eq!(sink.data().len(), BLOCK_SIZE);
+        assert_eq!(sink.data()[..3], *b"abc");
+    }
+
+    #[test]
+    fn ends_batch() {
+        let mut port = Port::new(Vec::from([header(&[])]));
+
+        assert!(Receiver::new(&mut port).next_header().unwrap().is_none());
+        assert_eq!(port.written, [CRC_REQUEST, ACK]);
+    }
+
+    #[test]
+    fn multiple_files() {
+        let mut port = Port::new(Vec::from([
+            header(b"a\x001\0"),
+            block(1, b"a"),
+            byte(EOT),
+            header(b"b\x002\0"),
+            block(1, b"bb"),
+            byte(EOT),
+            header(&[]),
+        ]));
+
+        let mut receiver = Receiver::new(&mut port);
+        let mut files = Vec::new();
+        while let Some(header) = receiver.next_header().unwrap() {
+            let mut sink = Cursor::empty(Vec::new());
+            receiver.receive_file(header.size, &mut sink).unwrap();
+            files.push(sink.data().to_vec());
+        }
+
+        assert_eq!(files, [&b"a"[..], &b"bb"[..]]);
+    }
+
+    #[test]
+    fn repeated_header_and_blocks() {
+        let mut port = Port::new(Vec::from([
+            header(b"a\x00200\0"),
+            // The sender didn't see our acknowledgement of the header.
+            header(b"a\x00200\0"),
+            block(1, &[1; BLOCK_SIZE]),
+            block(1, &[1; BLOCK_SIZE]),
+            block(2, &[2; 72]),
+            byte(EOT),
+        ]));
+        let mut sink = Cursor::empty(Vec::new());
+
+        let mut receiver = Receiver::new(&mut port);
+        let header = receiver.next_header().unwrap().unwrap();
+        receiver.receive_file(header.size, &mut sink).unwrap();
+
+        assert_eq!(sink.data().len(), 200);
+        assert!(sink.data()[..BLOCK_SIZE].iter().all(|&b| b == 1));
+        assert!(sink.data()[BLOCK_SIZE..].iter().all(|&b| b == 2));
+        assert_eq!(
+            port.written,
+            [CRC_REQUEST, ACK, CRC_REQUEST, ACK, ACK, ACK, ACK, ACK]
+        );
+    }
+
+    #[test]
+    fn repeated_end_of_file() {
+        let mut port = Port::new(Vec::from([byte(EOT), header(b"b\x001\0")]));
+
+        let header = Receiver::new(&mut port).next_header().unwrap().unwrap();
+
+        assert_eq!(header.name.to_bytes(), b"b");
+        assert_eq!(port.written, [CRC_REQUEST, ACK, CRC_REQUEST, ACK]);
+    }
+
+    #[test]
+    fn naks_damaged_blocks() {
+        let mut damaged = packet(SOH, 2, b"def", 0x1A);
+        damaged[10] ^= 0xFF;
+        let mut port = Port::new(Vec::from([
+            block(1, b"abc"),
+            Step::Send(damaged),
+            // Ends the purge.
+            Step::Timeout,
+            block(2, b"def"),
+            byte(EOT),
+        ]));
+        let mut sink = Cursor::empty(Vec::new());
+
+        Receiver::new(&mut port)
+            .receive_file(Some(131), &mut sink)
+            .unwrap();
+
+        assert_eq!(sink.data()[BLOCK_SIZE..], *b"def");
+        assert_eq!(port.written, [CRC_REQUEST, ACK, NAK, ACK, ACK]);
+    }
+
+    #[test]
+    fn sender_cancels() {
+        let mut port = Port::new(Vec::from([block(1, b"abc"), byte(CAN), byte(CAN)]));
+        let mut sink = Cursor::empty(Vec::new());
+
+        let result = Receiver::new(&mut port).receive_file(Some(3), &mut sink);
+
+        assert!(matches!(result, Err(Error::Cancelled)));
+        assert_eq!(port.written, [CRC_REQUEST, ACK]);
+    }
+
+    #[test]
+    fn cancels_invalid_header() {
+        let mut port = Port::new(Vec::from([header(b"..\0")]));
+
+        let result = Receiver::new(&mut port).next_header();
+
+        assert!(matches!(result, Err(Error::InvalidHeader)));
+        assert_eq!(port.written, [CRC_REQUEST, CAN, CAN]);
+    }
+
+    #[test]
+    fn cancels_out_of_sequence_block() {
+        let mut port = Port::new(Vec::from([block(2, b"abc")]));
+        let mut sink = Cursor::empty(Vec::new());
+
+        let result = Receiver::new(&mut port).receive_file(None, &mut sink);
+
+        assert!(matches!(result, Err(Error::OutOfSequence)));
+        assert_eq!(port.written, [CRC_REQUEST, CAN, CAN]);
+    }
+
+    #[test]
+    fn gives_up_after_retries() {
+        let mut port = Port::new((0..11).map(|_| Step::Timeout).collect());
+
+        let result = Receiver::new(&mut port).next_header();
+
+        assert!(matches!(result, Err(Error::TooManyRetries)));
+        assert_eq!(port.written.len(), 11 + 2);
+        assert_eq!(port.written[11..], [CAN, CAN]);
+    }
+}
@@ -0,0 +1,679 @@
+//! A compact binary format, for data that is only read by the app that wrote it.
+
+use core::fmt;
+
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor};
+use serde::ser::{self, Serialize};
+
+use super::Error;
+use crate::io::{self, Read, Write};
+
+/// Longest LEB128 encoding of a `u64`.
+const MAX_VARINT_LEN: usize = 10;
+
+/// Serializes `value` into `writer` with [`BinarySerializer`].
+pub fn to_writer<T, W>(value: &T, writer: W) -> Result<(), Error>
+where
+    T: Serialize + ?Sized,
+    W: Write,
+{
+    value.serialize(&mut BinarySerializer::new(writer))
+}
+
+/// Deserializes a value from `reader` with [`BinaryDeserializer`], using `scratch` to
+/// hold strings and byte strings while they are deserialized.
+pub fn from_reader<T, R>(reader: R, scratch: &mut [u8]) -> Result<T, Error>
+where
+    T: DeserializeOwned,
+    R: Read,
+{
+    T::deserialize(&mut BinaryDeserializer::new(reader, scratch))
+}
+
+/// A serializer writing a compact binary encoding to a [`Write`]r.
+///
+/// The encoding isn't self-describing, so it can only be read back as the type that was
+/// written:
+///
+/// - `bool`, `u8` and `i8` are one byte, and other integers are LEB128 varints, with
+///   signed integers zigzag-encoded first. `char`s are varints of their code point.
+/// - Floats are their little-endian bytes.
+/// - Strings, byte strings, sequences and maps start with their length as a varint.
+///   Sequences of unknown length fail with [`Error::Unsupported`].
+/// - Options start with a byte of 0 for `None` or 1 for `Some`, and enums with the index
+///   of the variant as a varint.
+/// - Tuples and structs are their fields in order, without names.
+///
+/// 128-bit integers are unsupported.
+pub struct BinarySerializer<W> {
+    writer: W,
+}
+
+impl<W: Write> BinarySerializer<W> {
+    /// Creates a serializer writing to `writer`.
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    /// Returns the writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn write_varint(&mut self, mut value: u64) -> Result<(), Error> {
+        let mut buf = [0; MAX_VARINT_LEN];
+        let mut len = 0;
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                buf[len] = byte;
+                len += 1;
+                break;
+            }
+            buf[len] = byte | 0x80;
+            len += 1;
+        }
+        Ok(self.writer.write_all(&buf[..len])?)
+    }
+
+    fn write_len(&mut self, len: usize) -> Result<(), Error> {
+        self.write_varint(len as u64)
+    }
+}
+
+/// Counts the length of formatted text.
+struct Counter(usize);
+
+impl fmt::Write for Counter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 += s.len();
+        Ok(())
+    }
+}
+
+/// Writes formatted text to a [`Write`]r, keeping its error.
+struct Adapter<'a, W> {
+    writer: &'a mut W,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> fmt::Write for Adapter<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.writer.write_all(s.as_bytes()).map_err(|error| {
+            self.error = Some(error);
+            fmt::Error
+        })
+    }
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+impl<'a, W: Write> ser::Serializer for &'a mut BinarySerializer<W> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(self, v: bool) -> Result<(), Error> {
+        self.serialize_u8(v.into())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), Error> {
+        self.serialize_u8(v as u8)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), Error> {
+        self.write_varint(zigzag(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), Error> {
+        Ok(self.writer.write_all(&[v])?)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), Error> {
+        self.write_varint(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), Error> {
+        self.write_varint(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), Error> {
+        self.write_varint(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), Error> {
+        Ok(self.writer.write_all(&v.to_le_bytes())?)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), Error> {
+        Ok(self.writer.write_all(&v.to_le_bytes())?)
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), Error> {
+        self.write_varint(u32::from(v).into())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), Error> {
+        self.serialize_bytes(v.as_bytes())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+        self.write_len(v.len())?;
+        Ok(self.writer.write_all(v)?)
+    }
+
+    fn serialize_none(self) -> Result<(), Error> {
+        self.serialize_u8(0)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), Error> {
+        self.serialize_u8(1)?;
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<(), Error> {
+        self.serialize_u32(variant_index)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.serialize_u32(variant_index)?;
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self, Error> {
+        self.write_len(len.ok_or(Error::Unsupported)?)?;
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self, Error> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self, Error> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self, Error> {
+        self.serialize_u32(variant_index)?;
+        Ok(self)
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self, Error> {
+        self.write_len(len.ok_or(Error::Unsupported)?)?;
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self, Error> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self, Error> {
+        self.serialize_u32(variant_index)?;
+        Ok(self)
+    }
+
+    /// Writes the formatted value as a string, formatting it twice: once to find its
+    /// length, and once to write it.
+    fn collect_str<T: fmt::Display + ?Sized>(self, value: &T) -> Result<(), Error> {
+        let mut counter = Counter(0);
+        fmt::Write::write_fmt(&mut counter, format_args!("{value}")).map_err(|_| Error::Invalid)?;
+        self.write_len(counter.0)?;
+
+        let mut adapter = Adapter {
+            writer: &mut self.writer,
+            error: None,
+        };
+        match fmt::Write::write_fmt(&mut adapter, format_args!("{value}")) {
+            Ok(()) => Ok(()),
+            Err(_) => Err(adapter.error.map_or(Error::Invalid, Error::Io)),
+        }
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+macro_rules! compound {
+    ($($trait:ident::$method:ident),*) => {
+        $(
+            impl<W: Write> ser::$trait for &mut BinarySerializer<W> {
+                type Ok = ();
+                type Error = Error;
+
+                fn $method<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+                    value.serialize(&mut **self)
+                }
+
+                fn end(self) -> Result<(), Error> {
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+compound!(
+    SerializeSeq::serialize_element,
+    SerializeTuple::serialize_element,
+    SerializeTupleStruct::serialize_field,
+    SerializeTupleVariant::serialize_field
+);
+
+impl<W: Write> ser::SerializeMap for &mut BinarySerializer<W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        key.serialize(&mut **self)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<W: Write> ser::SerializeStruct for &mut BinarySerializer<W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<W: Write> ser::SerializeStructVariant for &mut BinarySerializer<W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// A deserializer reading the encoding of [`BinarySerializer`] from a [`Read`]er.
+///
+/// Strings and byte strings are read into a scratch buffer, so the deserialized type
+/// must copy them, such as into a `heapless::String`. Longer ones fail with
+/// [`Error::OutOfRange`].
+pub struct BinaryDeserializer<'s, R> {
+    reader: R,
+    scratch: &'s mut [u8],
+}
+
+impl<'s, R: Read> BinaryDeserializer<'s, R> {
+    /// Creates a deserializer reading from `reader`, with `scratch` as the buffer for
+    /// strings and byte strings.
+    pub fn new(reader: R, scratch: &'s mut [u8]) -> Self {
+        Self { reader, scratch }
+    }
+
+    /// Returns the reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn read_u8(&mut self) -> Result<u8, Error> {
+        let mut byte = [0];
+        self.reader.read_exact(&mut byte)?;
+        Ok(byte[0])
+    }
+
+    fn read_varint(&mut self) -> Result<u64, Error> {
+        let mut value = 0;
+        for shift in (0..MAX_VARINT_LEN * 7).step_by(7) {
+            let byte = self.read_u8()?;
+            let bits = u64::from(byte & 0x7f);
+            if bits << shift >> shift != bits {
+                return Err(Error::OutOfRange);
+            }
+            value |= bits << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(Error::Invalid)
+    }
+
+    fn read_signed(&mut self) -> Result<i64, Error> {
+        self.read_varint().map(unzigzag)
+    }
+
+    fn read_len(&mut self) -> Result<usize, Error> {
+        self.read_varint()?
+            .try_into()
+            .map_err(|_| Error::OutOfRange)
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        let mut bytes = [0; N];
+        self.reader.read_exact(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Reads a string or byte string into the scratch buffer.
+    fn read_bytes(&mut self) -> Result<&[u8], Error> {
+        let len = self.read_len()?;
+        let bytes = self.scratch.get_mut(..len).ok_or(Error::OutOfRange)?;
+        self.reader.read_exact(bytes)?;
+        Ok(bytes)
+    }
+}
+
+macro_rules! deserialize_int {
+    ($($method:ident => $visit:ident($ty:ty, $read:ident),)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                let value = self.$read()?;
+                visitor.$visit(<$ty>::try_from(value).map_err(|_| Error::OutOfRange)?)
+            }
+        )*
+    };
+}
+
+impl<'de, R: Read> de::Deserializer<'de> for &mut BinaryDeserializer<'_, R> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Error> {
+        Err(Error::Unsupported)
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.read_u8()? {
+            0 => visitor.visit_bool(false),
+            1 => visitor.visit_bool(true),
+            _ => Err(Error::Invalid),
+        }
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_i8(self.read_u8()? as i8)
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_u8(self.read_u8()?)
+    }
+
+    deserialize_int! {
+        deserialize_i16 => visit_i16(i16, read_signed),
+        deserialize_i32 => visit_i32(i32, read_signed),
+        deserialize_i64 => visit_i64(i64, read_signed),
+        deserialize_u16 => visit_u16(u16, read_varint),
+        deserialize_u32 => visit_u32(u32, read_varint),
+        deserialize_u64 => visit_u64(u64, read_varint),
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_f32(f32::from_le_bytes(self.read_array()?))
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_f64(f64::from_le_bytes(self.read_array()?))
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let value = u32::try_from(self.read_varint()?).map_err(|_| Error::Invalid)?;
+        visitor.visit_char(char::from_u32(value).ok_or(Error::Invalid)?)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let bytes = self.read_bytes()?;
+        visitor.visit_str(core::str::from_utf8(bytes).map_err(|_| Error::Invalid)?)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_bytes(self.read_bytes()?)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.read_u8()? {
+            0 => visitor.visit_none(),
+            1 => visitor.visit_some(self),
+            _ => Err(Error::Invalid),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let len = self.read_len()?;
+        visitor.visit_seq(Access { de: self, len })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_seq(Access { de: self, len })
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let len = self.read_len()?;
+        visitor.visit_map(Access { de: self, len })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_tuple(fields.len(), visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_enum(self)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_u32(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Error> {
+        Err(Error::Unsupported)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+/// Passes `len` elements of a sequence, or entries of a map, to a visitor.
+struct Access<'a, 's, R> {
+    de: &'a mut BinaryDeserializer<'s, R>,
+    len: usize,
+}
+
+impl<'de, R: Read> de::SeqAccess<'de> for Access<'_, '_, R> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        if self.len == 0 {
+            return Ok(None);
+        }
+        self.len -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len)
+    }
+}
+
+impl<'de, R: Read> de::MapAccess<'de> for Access<'_, '_, R> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        if self.len == 0 {
+            return Ok(None);
+        }
+        self.len -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        seed.deserialize(&mut *self.de)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len)
+    }
+}
+
+impl<'de, R: Read> de::EnumAccess<'de> for &mut BinaryDeserializer<'_, R> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), Error> {
+        let index = u32::try_from(self.read_varint()?).map_err(|_| Error::OutOfRange)?;
+        let variant = seed.deserialize(IntoDeserializer::<Error>::into_deserializer(index))?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de, R: Read> de::VariantAccess<'de> for &mut BinaryDeserializer<'_, R> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_tuple(self, len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_tuple(self, fields.len(), visitor)
+    }
+}
@@ -0,0 +1,1132 @@
+//! The FlipperFormat layout: one key per field of a struct.
+
+use core::ffi::CStr;
+use core::fmt;
+
+use serde::de::value::{BorrowedStrDeserializer, StrDeserializer};
+use serde::de::{self, DeserializeOwned, DeserializeSeed, Visitor};
+use serde::ser::{self, Impossible, Serialize};
+
+use super::Error;
+use crate::furi::string::FuriString;
+use crate::storage::{FlipperFormat, FormatError};
+
+/// Longest key, which is the name of a field.
+pub const MAX_KEY_LEN: usize = 63;
+
+/// Largest number of values of a sequence, such as an array of numbers.
+pub const MAX_VALUES: usize = 64;
+
+/// Serializes the fields of `value`, which must be a struct, as keys of `format`.
+///
+/// The fields are written at the current position of `format`, such as after its
+/// header. See [`FlipperFormatSerializer`] for how each type is written.
+pub fn to_flipper_format<T>(value: &T, format: &mut FlipperFormat) -> Result<(), Error>
+where
+    T: Serialize + ?Sized,
+{
+    value.serialize(&mut FlipperFormatSerializer::new(format))
+}
+
+/// Deserializes a struct from the keys of `format`.
+///
+/// The keys may be in any order, and other keys are ignored. See
+/// [`FlipperFormatDeserializer`] for how each type is read.
+pub fn from_flipper_format<T>(format: &mut FlipperFormat) -> Result<T, Error>
+where
+    T: DeserializeOwned,
+{
+    T::deserialize(&mut FlipperFormatDeserializer::new(format))
+}
+
+/// A serializer writing the fields of a struct as the keys of a [`FlipperFormat`].
+///
+/// Each field becomes a `name: value` line:
+///
+/// - Booleans and integers up to 32 bits are written as single values, and `f32` and
+///   `f64` as a decimal number. Larger integers fail with [`Error::OutOfRange`] unless
+///   their value fits into 32 bits.
+/// - Strings and `char`s are written as strings. They must not contain line breaks.
+/// - Sequences and tuples are written as lists of up to [`MAX_VALUES`] values of the
+///   same type, with `u8` values and byte strings written as hex.
+/// - Enums with unit variants are written as the name of the variant.
+/// - `None` leaves out the key, and `Some` writes the value.
+///
+/// Nested structs, maps, and enum variants with data fail with
+/// [`Error::Unsupported`].
+pub struct FlipperFormatSerializer<'a> {
+    format: &'a mut FlipperFormat,
+}
+
+impl<'a> FlipperFormatSerializer<'a> {
+    /// Creates a serializer writing to `format`.
+    pub fn new(format: &'a mut FlipperFormat) -> Self {
+        Self { format }
+    }
+}
+
+/// Converts `key` into a C string, and passes it to `f`.
+fn with_key<R>(key: &str, f: impl FnOnce(&CStr) -> Result<R, Error>) -> Result<R, Error> {
+    if key.len() > MAX_KEY_LEN {
+        return Err(Error::OutOfRange);
+    }
+    if key.is_empty()
+        || key
+            .bytes()
+            .any(|b| matches!(b, b'\0' | b'\n' | b'\r' | b':'))
+    {
+        return Err(Error::Invalid);
+    }
+    let mut buf = [0; MAX_KEY_LEN + 1];
+    buf[..key.len()].copy_from_slice(key.as_bytes());
+    // The key has no nul bytes, and is followed by at least one.
+    f(CStr::from_bytes_until_nul(&buf).unwrap())
+}
+
+macro_rules! unsupported {
+    ($($method:ident($($ty:ty),*) -> $ret:ty;)*) => {
+        $(
+            fn $method(self, $(_: $ty),*) -> Result<$ret, Error> {
+                Err(Error::Unsupported)
+            }
+        )*
+    };
+}
+
+impl<'a, 'b> ser::Serializer for &'a mut FlipperFormatSerializer<'b> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Impossible<(), Error>;
+    type SerializeTuple = Impossible<(), Error>;
+    type SerializeTupleStruct = Impossible<(), Error>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Impossible<(), Error>;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    unsupported! {
+        serialize_bool(bool) -> ();
+        serialize_i8(i8) -> ();
+        serialize_i16(i16) -> ();
+        serialize_i32(i32) -> ();
+        serialize_i64(i64) -> ();
+        serialize_u8(u8) -> ();
+        serialize_u16(u16) -> ();
+        serialize_u32(u32) -> ();
+        serialize_u64(u64) -> ();
+        serialize_f32(f32) -> ();
+        serialize_f64(f64) -> ();
+        serialize_char(char) -> ();
+        serialize_str(&str) -> ();
+        serialize_bytes(&[u8]) -> ();
+        serialize_none() -> ();
+        serialize_unit() -> ();
+        serialize_unit_struct(&'static str) -> ();
+        serialize_unit_variant(&'static str, u32, &'static str) -> ();
+        serialize_seq(Option<usize>) -> Self::SerializeSeq;
+        serialize_tuple(usize) -> Self::SerializeTuple;
+        serialize_tuple_struct(&'static str, usize) -> Self::SerializeTupleStruct;
+        serialize_tuple_variant(&'static str, u32, &'static str, usize) -> Self::SerializeTupleVariant;
+        serialize_map(Option<usize>) -> Self::SerializeMap;
+        serialize_struct_variant(&'static str, u32, &'static str, usize) -> Self::SerializeStructVariant;
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<(), Error> {
+        Err(Error::Unsupported)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self, Error> {
+        Ok(self)
+    }
+
+    fn collect_str<T: fmt::Display + ?Sized>(self, _value: &T) -> Result<(), Error> {
+        Err(Error::Unsupported)
+    }
+}
+
+impl ser::SerializeStruct for &mut FlipperFormatSerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        with_key(key, |key| {
+            value.serialize(ValueSerializer {
+                format: self.format,
+                key,
+            })
+        })
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Writes a value as the value of `key`.
+struct ValueSerializer<'a> {
+    format: &'a mut FlipperFormat,
+    key: &'a CStr,
+}
+
+impl ValueSerializer<'_> {
+    fn write_str(self, value: &str) -> Result<(), Error> {
+        if value.contains('\0') {
+            return Err(Error::Invalid);
+        }
+        self.write_string(&FuriString::from(value))
+    }
+
+    fn write_string(self, value: &FuriString) -> Result<(), Error> {
+        let value = value.as_c_str();
+        if value.to_bytes().iter().any(|b| matches!(b, b'\n' | b'\r')) {
+            return Err(Error::Invalid);
+        }
+        Ok(self.format.write_string(self.key, value)?)
+    }
+}
+
+impl<'a> ser::Serializer for ValueSerializer<'a> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = SeqSerializer<'a>;
+    type SerializeTuple = SeqSerializer<'a>;
+    type SerializeTupleStruct = SeqSerializer<'a>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Impossible<(), Error>;
+    type SerializeStruct = Impossible<(), Error>;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), Error> {
+        Ok(self.format.write_bool(self.key, v)?)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), Error> {
+        self.serialize_i32(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), Error> {
+        self.serialize_i32(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), Error> {
+        Ok(self.format.write_i32(self.key, v)?)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), Error> {
+        self.serialize_i32(v.try_into().map_err(|_| Error::OutOfRange)?)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), Error> {
+        self.serialize_u32(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), Error> {
+        self.serialize_u32(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), Error> {
+        Ok(self.format.write_u32(self.key, v)?)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), Error> {
+        self.serialize_u32(v.try_into().map_err(|_| Error::OutOfRange)?)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), Error> {
+        Ok(self.format.write_f32s(self.key, &[v])?)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), Error> {
+        self.serialize_f32(v as f32)
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), Error> {
+        self.write_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), Error> {
+        self.write_str(v)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+        Ok(self.format.write_hex(self.key, v)?)
+    }
+
+    fn serialize_none(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), Error> {
+        Err(Error::Unsupported)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        Err(Error::Unsupported)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<(), Error> {
+        self.write_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<(), Error> {
+        Err(Error::Unsupported)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<SeqSerializer<'a>, Error> {
+        Ok(SeqSerializer {
+            format: self.format,
+            key: self.key,
+            values: Values::new(),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer<'a>, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer<'a>, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn collect_str<T: fmt::Display + ?Sized>(self, value: &T) -> Result<(), Error> {
+        let mut string = FuriString::new();
+        fmt::Write::write_fmt(&mut string, format_args!("{value}")).map_err(|_| Error::Invalid)?;
+        self.write_string(&string)
+    }
+
+    unsupported! {
+        serialize_tuple_variant(&'static str, u32, &'static str, usize) -> Self::SerializeTupleVariant;
+        serialize_map(Option<usize>) -> Self::SerializeMap;
+        serialize_struct(&'static str, usize) -> Self::SerializeStruct;
+        serialize_struct_variant(&'static str, u32, &'static str, usize) -> Self::SerializeStructVariant;
+    }
+}
+
+/// The type of the values of a sequence, which FlipperFormat stores in one line.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Bool,
+    U32,
+    I32,
+    F32,
+    /// Bytes, written as hex.
+    Hex,
+}
+
+/// The values of a sequence, stored as their bits.
+struct Values {
+    kind: Option<Kind>,
+    bits: [u32; MAX_VALUES],
+    len: usize,
+}
+
+impl Values {
+    fn new() -> Self {
+        Self {
+            kind: None,
+            bits: [0; MAX_VALUES],
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, kind: Kind, bits: u32) -> Result<(), Error> {
+        if *self.kind.get_or_insert(kind) != kind {
+            return Err(Error::Unsupported);
+        }
+        let slot = self.bits.get_mut(self.len).ok_or(Error::OutOfRange)?;
+        *slot = bits;
+        self.len += 1;
+        Ok(())
+    }
+
+    fn write(&self, format: &mut FlipperFormat, key: &CStr) -> Result<(), FormatError> {
+        let bits = &self.bits[..self.len];
+        match self.kind {
+            // An empty sequence has no type, and is written as a key without values.
+            None | Some(Kind::U32) => format.write_u32s(key, bits),
+            Some(Kind::I32) => {
+                let mut values = [0; MAX_VALUES];
+                for (value, &bits) in values.iter_mut().zip(bits) {
+                    *value = bits as i32;
+                }
+                format.write_i32s(key, &values[..self.len])
+            }
+            Some(Kind::F32) => {
+                let mut values = [0.0; MAX_VALUES];
+                for (value, &bits) in values.iter_mut().zip(bits) {
+                    *value = f32::from_bits(bits);
+                }
+                format.write_f32s(key, &values[..self.len])
+            }
+            Some(Kind::Bool) => {
+                let mut values = [false; MAX_VALUES];
+                for (value, &bits) in values.iter_mut().zip(bits) {
+                    *value = bits != 0;
+                }
+                format.write_bools(key, &values[..self.len])
+            }
+            Some(Kind::Hex) => {
+                let mut values = [0; MAX_VALUES];
+                for (value, &bits) in values.iter_mut().zip(bits) {
+                    *value = bits as u8;
+                }
+                format.write_hex(key, &values[..self.len])
+            }
+        }
+    }
+}
+
+/// Collects the values of a sequence, and writes them once it ends.
+struct SeqSerializer<'a> {
+    format: &'a mut FlipperFormat,
+    key: &'a CStr,
+    values: Values,
+}
+
+impl SeqSerializer<'_> {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        value.serialize(ElementSerializer {
+            values: &mut self.values,
+        })
+    }
+
+    fn finish(self) -> Result<(), Error> {
+        Ok(self.values.write(self.format, self.key)?)
+    }
+}
+
+impl ser::SerializeSeq for SeqSerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+/// Adds a value of a sequence to its values.
+struct ElementSerializer<'a> {
+    values: &'a mut Values,
+}
+
+impl ser::Serializer for ElementSerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Impossible<(), Error>;
+    type SerializeTuple = Impossible<(), Error>;
+    type SerializeTupleStruct = Impossible<(), Error>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Impossible<(), Error>;
+    type SerializeStruct = Impossible<(), Error>;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), Error> {
+        self.values.push(Kind::Bool, v.into())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), Error> {
+        self.serialize_i32(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), Error> {
+        self.serialize_i32(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), Error> {
+        self.values.push(Kind::I32, v as u32)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), Error> {
+        self.serialize_i32(v.try_into().map_err(|_| Error::OutOfRange)?)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), Error> {
+        self.values.push(Kind::Hex, v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), Error> {
+        self.serialize_u32(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), Error> {
+        self.values.push(Kind::U32, v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), Error> {
+        self.serialize_u32(v.try_into().map_err(|_| Error::OutOfRange)?)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), Error> {
+        self.values.push(Kind::F32, v.to_bits())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), Error> {
+        self.serialize_f32(v as f32)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, _value: &T) -> Result<(), Error> {
+        Err(Error::Unsupported)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<(), Error> {
+        Err(Error::Unsupported)
+    }
+
+    fn collect_str<T: fmt::Display + ?Sized>(self, _value: &T) -> Result<(), Error> {
+        Err(Error::Unsupported)
+    }
+
+    unsupported! {
+        serialize_char(char) -> ();
+        serialize_str(&str) -> ();
+        serialize_bytes(&[u8]) -> ();
+        serialize_none() -> ();
+        serialize_unit() -> ();
+        serialize_unit_struct(&'static str) -> ();
+        serialize_unit_variant(&'static str, u32, &'static str) -> ();
+        serialize_seq(Option<usize>) -> Self::SerializeSeq;
+        serialize_tuple(usize) -> Self::SerializeTuple;
+        serialize_tuple_struct(&'static str, usize) -> Self::SerializeTupleStruct;
+        serialize_tuple_variant(&'static str, u32, &'static str, usize) -> Self::SerializeTupleVariant;
+        serialize_map(Option<usize>) -> Self::SerializeMap;
+        serialize_struct(&'static str, usize) -> Self::SerializeStruct;
+        serialize_struct_variant(&'static str, u32, &'static str, usize) -> Self::SerializeStructVariant;
+    }
+}
+
+/// A deserializer reading a struct from the keys of a [`FlipperFormat`], in the layout
+/// written by [`FlipperFormatSerializer`].
+///
+/// Each field is searched for from the start of the file, so the keys may be in any
+/// order. A missing key is left to the type, which fails unless the field is an
+/// `Option` or has a default.
+pub struct FlipperFormatDeserializer<'a> {
+    format: &'a mut FlipperFormat,
+}
+
+impl<'a> FlipperFormatDeserializer<'a> {
+    /// Creates a deserializer reading from `format`.
+    pub fn new(format: &'a mut FlipperFormat) -> Self {
+        Self { format }
+    }
+}
+
+macro_rules! forward_unsupported {
+    ($($method:ident),*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Error> {
+                Err(Error::Unsupported)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for &mut FlipperFormatDeserializer<'_> {
+    type Error = Error;
+
+    forward_unsupported!(
+        deserialize_any,
+        deserialize_bool,
+        deserialize_i8,
+        deserialize_i16,
+        deserialize_i32,
+        deserialize_i64,
+        deserialize_u8,
+        deserialize_u16,
+        deserialize_u32,
+        deserialize_u64,
+        deserialize_f32,
+        deserialize_f64,
+        deserialize_char,
+        deserialize_str,
+        deserialize_string,
+        deserialize_bytes,
+        deserialize_byte_buf,
+        deserialize_option,
+        deserialize_unit,
+        deserialize_seq,
+        deserialize_map,
+        deserialize_identifier,
+        deserialize_ignored_any
+    );
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _visitor: V,
+    ) -> Result<V::Value, Error> {
+        Err(Error::Unsupported)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        _visitor: V,
+    ) -> Result<V::Value, Error> {
+        Err(Error::Unsupported)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        _visitor: V,
+    ) -> Result<V::Value, Error> {
+        Err(Error::Unsupported)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_map(FieldAccess {
+            format: self.format,
+            fields,
+            key: [0; MAX_KEY_LEN + 1],
+        })
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Error> {
+        Err(Error::Unsupported)
+    }
+}
+
+/// Passes the fields of a struct that are in the file to the visitor of the struct.
+struct FieldAccess<'a> {
+    format: &'a mut FlipperFormat,
+    /// The fields that haven't been looked for yet.
+    fields: &'static [&'static str],
+    /// The key of the last field found, with its nul terminator.
+    key: [u8; MAX_KEY_LEN + 1],
+}
+
+impl<'de> de::MapAccess<'de> for FieldAccess<'_> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        while let Some((&field, rest)) = self.fields.split_first() {
+            self.fields = rest;
+            let found = with_key(field, |key| {
+                self.format.rewind()?;
+                match self.format.value_count(key) {
+                    Ok(_) => {
+                        self.key = [0; MAX_KEY_LEN + 1];
+                        self.key[..field.len()].copy_from_slice(field.as_bytes());
+                        Ok(true)
+                    }
+                    Err(FormatError::MissingKey) => Ok(false),
+                    Err(error) => Err(Error::Format(error)),
+                }
+            })?;
+            if found {
+                return seed
+                    .deserialize(BorrowedStrDeserializer::new(field))
+                    .map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        // The key was checked when it was found.
+        let key = CStr::from_bytes_until_nul(&self.key).unwrap();
+        seed.deserialize(ValueDeserializer {
+            format: self.format,
+            key,
+        })
+    }
+}
+
+/// Reads the value of `key`.
+struct ValueDeserializer<'a> {
+    format: &'a mut FlipperFormat,
+    key: &'a CStr,
+}
+
+impl ValueDeserializer<'_> {
+    fn read_f32(self) -> Result<f32, Error> {
+        let mut value = [0.0];
+        self.format.read_f32s(self.key, &mut value)?;
+        Ok(value[0])
+    }
+
+    /// Reads the string value of `key`, and passes it to `f` as a `str`.
+    fn with_str<R>(self, f: impl FnOnce(&str) -> Result<R, Error>) -> Result<R, Error> {
+        let value = self.format.read_string(self.key)?;
+        f(value.to_str().map_err(|_| Error::Invalid)?)
+    }
+
+    fn value_count(&mut self) -> Result<usize, Error> {
+        let count = self.format.value_count(self.key)? as usize;
+        if count > MAX_VALUES {
+            return Err(Error::OutOfRange);
+        }
+        Ok(count)
+    }
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer<'_> {
+    type Error = Error;
+
+    forward_unsupported!(deserialize_any, deserialize_unit, deserialize_map);
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_bool(self.format.read_bool(self.key)?)
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_i32(visitor)
+    }
+
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_i32(visitor)
+    }
+
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_i32(self.format.read_i32(self.key)?)
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_i32(visitor)
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_u32(visitor)
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_u32(visitor)
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_u32(self.format.read_u32(self.key)?)
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_u32(visitor)
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_f32(self.read_f32()?)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_f64(self.read_f32()?.into())
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.with_str(|value| visitor.visit_str(value))
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, Error> {
+        let mut bytes = [0; MAX_VALUES];
+        let bytes = &mut bytes[..self.value_count()?];
+        self.format.read_hex(self.key, bytes)?;
+        visitor.visit_bytes(bytes)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        // The key was found, or the field wouldn't be deserialized.
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _visitor: V,
+    ) -> Result<V::Value, Error> {
+        Err(Error::Unsupported)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, Error> {
+        let len = self.value_count()?;
+        visitor.visit_seq(SeqAccess {
+            format: self.format,
+            key: self.key,
+            len,
+            values: Values::new(),
+            next: 0,
+        })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Error> {
+        Err(Error::Unsupported)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.with_str(|variant| visitor.visit_enum(StrDeserializer::<Error>::new(variant)))
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+}
+
+/// Passes the values of a sequence to its visitor, reading them all once the type of
+/// the first one is known.
+struct SeqAccess<'a> {
+    format: &'a mut FlipperFormat,
+    key: &'a CStr,
+    len: usize,
+    values: Values,
+    next: usize,
+}
+
+impl SeqAccess<'_> {
+    /// Returns the bits of the next value, reading the values as `kind` if they haven't
+    /// been read yet.
+    fn take(&mut self, kind: Kind) -> Result<u32, Error> {
+        match self.values.kind {
+            None => self.read(kind)?,
+            Some(read) if read != kind => return Err(Error::Unsupported),
+            Some(_) => {}
+        }
+        let bits = self.values.bits[self.next];
+        self.next += 1;
+        Ok(bits)
+    }
+
+    fn read(&mut self, kind: Kind) -> Result<(), Error> {
+        let (format, key, len) = (&mut *self.format, self.key, self.len);
+        let bits = &mut self.values.bits[..len];
+        match kind {
+            Kind::U32 => format.read_u32s(key, bits)?,
+            Kind::I32 => {
+                let mut values = [0; MAX_VALUES];
+                format.read_i32s(key, &mut values[..len])?;
+                for (bits, value) in bits.iter_mut().zip(values) {
+                    *bits = value as u32;
+                }
+            }
+            Kind::F32 => {
+                let mut values = [0.0; MAX_VALUES];
+                format.read_f32s(key, &mut values[..len])?;
+                for (bits, value) in bits.iter_mut().zip(values) {
+                    *bits = value.to_bits();
+                }
+            }
+            Kind::Bool => {
+                let mut values = [false; MAX_VALUES];
+                format.read_bools(key, &mut values[..len])?;
+                for (bits, value) in bits.iter_mut().zip(values) {
+                    *bits = value.into();
+                }
+            }
+            Kind::Hex => {
+                let mut values = [0; MAX_VALUES];
+                format.read_hex(key, &mut values[..len])?;
+                for (bits, value) in bits.iter_mut().zip(values) {
+                    *bits = value.into();
+                }
+            }
+        }
+        self.values.kind = Some(kind);
+        self.values.len = len;
+        Ok(())
+    }
+}
+
+impl<'de> de::SeqAccess<'de> for SeqAccess<'_> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        if self.next == self.len {
+            return Ok(None);
+        }
+        seed.deserialize(ElementDeserializer { seq: self })
+            .map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len - self.next)
+    }
+}
+
+/// Deserializes the next value of a sequence.
+struct ElementDeserializer<'a, 'b> {
+    seq: &'a mut SeqAccess<'b>,
+}
+
+impl<'de> de::Deserializer<'de> for ElementDeserializer<'_, '_> {
+    type Error = Error;
+
+    forward_unsupported!(
+        deserialize_any,
+        deserialize_char,
+        deserialize_str,
+        deserialize_string,
+        deserialize_bytes,
+        deserialize_byte_buf,
+        deserialize_option,
+        deserialize_unit,
+        deserialize_seq,
+        deserialize_map,
+        deserialize_identifier
+    );
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_bool(self.seq.take(Kind::Bool)? != 0)
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_i32(visitor)
+    }
+
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_i32(visitor)
+    }
+
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_i32(self.seq.take(Kind::I32)? as i32)
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_i32(visitor)
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_u8(self.seq.take(Kind::Hex)? as u8)
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_u32(visitor)
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_u32(self.seq.take(Kind::U32)?)
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_u32(visitor)
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_f32(f32::from_bits(self.seq.take(Kind::F32)?))
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_f64(f32::from_bits(self.seq.take(Kind::F32)?).into())
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _visitor: V,
+    ) -> Result<V::Value, Error> {
+        Err(Error::Unsupported)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        _visitor: V,
+    ) -> Result<V::Value, Error> {
+        Err(Error::Unsupported)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        _visitor: V,
+    ) -> Result<V::Value, Error> {
+        Err(Error::Unsupported)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Error> {
+        Err(Error::Unsupported)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Error> {
+        Err(Error::Unsupported)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        // The type of ignored values is unknown, so they can't be read.
+        self.seq.next += 1;
+        visitor.visit_unit()
+    }
+}
@@ -0,0 +1,287 @@
+//! Serialization of `serde` types into FlipperFormat files and a compact binary format.
+//!
+//! Both formats work without an allocator, so types deriving `Serialize` and
+//! `Deserialize` can be saved and loaded by any app:
+//!
+//! - [`to_flipper_format`] and [`from_flipper_format`] store the fields of a struct as
+//!   the keys of a [`FlipperFormat`] file, so the file stays readable and editable like
+//!   the files of the firmware's apps.
+//! - [`to_writer`] and [`from_reader`] use a compact binary encoding over any
+//!   [`io::Write`] and [`io::Read`], for data that doesn't need to be read by people.
+//!
+//! [`FlipperFormat`]: crate::storage::FlipperFormat
+//!
+//! # Examples
+//!
+//! ```ignore
+//! use flipperzero::serde::{from_flipper_format, to_flipper_format, Error};
+//! use flipperzero::storage::FlipperFormat;
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! enum Mode {
+//!     Easy,
+//!     Hard,
+//! }
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Settings {
+//!     mode: Mode,
+//!     volume: f32,
+//!     high_scores: [u32; 3],
+//!     last_level: Option<u32>,
+//! }
+//!
+//! fn save(settings: &Settings) -> Result<(), Error> {
+//!     let mut file = FlipperFormat::open_always(c"/ext/apps_data/myapp/settings.txt")?;
+//!     file.write_header(c"MyApp Settings", 1)?;
+//!     to_flipper_format(settings, &mut file)?;
+//!     Ok(file.close()?)
+//! }
+//!
+//! fn load() -> Result<Settings, Error> {
+//!     let mut file = FlipperFormat::open_read(c"/ext/apps_data/myapp/settings.txt")?;
+//!     file.read_header()?;
+//!     from_flipper_format(&mut file)
+//! }
+//! ```
+
+use core::fmt;
+
+use crate::io;
+use crate::storage::FormatError;
+
+mod binary;
+mod flipper_format;
+
+pub use self::binary::{from_reader, to_writer, BinaryDeserializer, BinarySerializer};
+pub use self::flipper_format::{
+    from_flipper_format, to_flipper_format, FlipperFormatDeserializer, FlipperFormatSerializer,
+    MAX_KEY_LEN, MAX_VALUES,
+};
+
+/// Reasons why serializing or deserializing failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// Reading or writing the FlipperFormat file failed.
+    Format(FormatError),
+    /// Reading or writing the binary data failed.
+    Io(io::Error),
+    /// The type can't be represented in the format, such as a nested struct in a
+    /// FlipperFormat file, or a type that needs a self-describing format.
+    Unsupported,
+    /// A value doesn't fit into the format or the buffers of the deserializer, such as a
+    /// number above `u32::MAX` in a FlipperFormat file, or a string longer than the
+    /// scratch buffer.
+    OutOfRange,
+    /// The data doesn't match the type, such as a missing field or an unknown variant.
+    ///
+    /// The message from `serde` is dropped, as storing it would need an allocator.
+    Invalid,
+}
+
+impl Error {
+    /// Description associated with [`Error`].
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Format(_) => "FlipperFormat error",
+            Self::Io(_) => "I/O error",
+            Self::Unsupported => "unsupported type",
+            Self::OutOfRange => "value out of range",
+            Self::Invalid => "invalid data",
+        }
+    }
+}
+
+impl From<FormatError> for Error {
+    fn from(error: FormatError) -> Self {
+        Self::Format(error)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Format(error) => error.fmt(f),
+            Self::Io(error) => error.fmt(f),
+            _ => f.write_str(self.description()),
+        }
+    }
+}
+
+impl ufmt::uDisplay for Error {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        match self {
+            Self::Format(error) => ufmt::uDisplay::fmt(error, f),
+            Self::Io(error) => ufmt::uDisplay::fmt(error, f),
+            _ => f.write_str(self.description()),
+        }
+    }
+}
+
+impl core::error::Error for Error {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::Format(error) => Some(error),
+            Self::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl ::serde::ser::Error for Error {
+    fn custom<T: fmt::Display>(_msg: T) -> Self {
+        Self::Invalid
+    }
+}
+
+impl ::serde::de::Error for Error {
+    fn custom<T: fmt::Display>(_msg: T) -> Self {
+        Self::Invalid
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use core::fmt;
+
+    use ::serde::de::Visitor;
+    use ::serde::{Deserialize, Deserializer, Serialize};
+
+    use super::{from_flipper_format, from_reader, to_flipper_format, to_writer, Error};
+    use crate::io::cursor::Cursor;
+    use crate::io::Seek;
+    use crate::storage::FlipperFormat;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Mode {
+        Easy,
+        Hard,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Settings {
+        mode: Mode,
+        volume: f32,
+        offset: i16,
+        enabled: bool,
+        high_scores: [u32; 3],
+        key: [u8; 4],
+        last_level: Option<u32>,
+    }
+
+    const SETTINGS: Settings = Settings {
+        mode: Mode::Hard,
+        volume: 0.5,
+        offset: -3,
+        enabled: true,
+        high_scores: [120, 80, 5],
+        key: [0x00, 0xa1, 0xff, 0x10],
+        last_level: None,
+    };
+
+    /// The length of a deserialized string, which needs no allocator to keep.
+    struct StrLen(usize);
+
+    impl<'de> Deserialize<'de> for StrLen {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            struct LenVisitor;
+
+            impl Visitor<'_> for LenVisitor {
+                type Value = StrLen;
+
+                fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    f.write_str("a string")
+                }
+
+                fn visit_str<E>(self, v: &str) -> Result<StrLen, E> {
+                    Ok(StrLen(v.len()))
+                }
+            }
+
+            deserializer.deserialize_str(LenVisitor)
+        }
+    }
+
+    #[test]
+    fn structs_round_trip_through_flipper_format() {
+        let mut file = FlipperFormat::in_memory();
+        file.write_header(c"Serde Test", 1).unwrap();
+        to_flipper_format(&SETTINGS, &mut file).unwrap();
+
+        file.rewind().unwrap();
+        assert_eq!(file.read_string(c"mode").unwrap().to_str(), Ok("Hard"));
+        assert_eq!(file.value_count(c"high_scores"), Ok(3));
+        assert!(!file.contains_key(c"last_level"));
+
+        file.rewind().unwrap();
+        let settings: Settings = from_flipper_format(&mut file).unwrap();
+        assert_eq!(settings, SETTINGS);
+    }
+
+    #[test]
+    fn flipper_format_rejects_unsupported_values() {
+        #[derive(Serialize)]
+        struct Nested {
+            settings: Settings,
+        }
+
+        #[derive(Serialize)]
+        struct Text<'a> {
+            text: &'a str,
+        }
+
+        let mut file = FlipperFormat::in_memory();
+        let nested = Nested { settings: SETTINGS };
+        assert!(matches!(
+            to_flipper_format(&nested, &mut file),
+            Err(Error::Unsupported)
+        ));
+        assert!(matches!(
+            to_flipper_format(&Text { text: "two\nlines" }, &mut file),
+            Err(Error::Invalid)
+        ));
+        assert!(matches!(
+            to_flipper_format(&5u32, &mut file),
+            Err(Error::Unsupported)
+        ));
+    }
+
+    #[test]
+    fn structs_round_trip_through_binary() {
+        let mut cursor = Cursor::empty([0; 64]);
+        to_writer(&SETTINGS, &mut cursor).unwrap();
+        // The variant index, 4 bytes of float, and a zigzag-encoded -3.
+        assert_eq!(cursor.data()[..6], [1, 0x00, 0x00, 0x00, 0x3f, 5]);
+
+        cursor.rewind().unwrap();
+        let mut scratch = [0; 16];
+        let settings: Settings = from_reader(&mut cursor, &mut scratch).unwrap();
+        assert_eq!(settings, SETTINGS);
+        assert!(cursor.rest().is_empty());
+
+        // Strings are read into the scratch buffer, and fail if they don't fit.
+        let mut cursor = Cursor::empty([0; 64]);
+        to_writer("short", &mut cursor).unwrap();
+        to_writer("a string longer than 16 bytes", &mut cursor).unwrap();
+        cursor.rewind().unwrap();
+        assert!(matches!(
+            from_reader(&mut cursor, &mut scratch),
+            Ok(StrLen(5))
+        ));
+        assert!(matches!(
+            from_reader::<StrLen, _>(&mut cursor, &mut scratch),
+            Err(Error::OutOfRange)
+        ));
+    }
+}
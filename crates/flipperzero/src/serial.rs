@@ -0,0 +1,324 @@
+//! Serial ports of the Flipper Zero.
+
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+use core::ffi::c_void;
+use core::ptr::NonNull;
+use core::time::Duration;
+
+use flipperzero_sys as sys;
+use flipperzero_sys::furi::duration_to_ticks;
+
+use crate::io::{Error, Read, ReadTimeout, Write};
+
+/// Number of received bytes that are buffered until they are read.
+const RX_BUFFER_SIZE: usize = 512;
+
+/// A serial port on the GPIO header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UartChannel {
+    /// USART on pins 13 (TX) and 14 (RX), which is also used by the console.
+    Usart,
+    /// LPUART on pins 15 (TX) and 16 (RX).
+    Lpuart,
+}
+
+impl UartChannel {
+    pub fn to_sys(&self) -> sys::FuriHalSerialId {
+        match self {
+            Self::Usart => sys::FuriHalSerialId_FuriHalSerialIdUsart,
+            Self::Lpuart => sys::FuriHalSerialId_FuriHalSerialIdLpuart,
+        }
+    }
+}
+
+/// An open serial port, which can be read from and written to like a file.
+///
+/// Received bytes are buffered as they arrive, and bytes that arrive while the buffer is
+/// full are dropped. The port is released when the `Uart` is dropped.
+pub struct Uart {
+    handle: NonNull<sys::FuriHalSerialHandle>,
+    rx: NonNull<sys::FuriStreamBuffer>,
+    read_timeout: Duration,
+}
+
+impl Uart {
+    /// Opens the serial port on `channel` with the given baud rate, such as `115200`.
+    ///
+    /// Reads don't wait for data by default; see [`Uart::set_read_timeout`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::AlreadyOpen`] if the channel is in use, for example by the
+    /// console or an expansion module, and [`Error::InvalidParameter`] if the baud rate
+    /// is not supported by the channel. [`Expansion::suspend`] frees the port from the
+    /// expansion service.
+    ///
+    /// [`Expansion::suspend`]: crate::expansion::Expansion::suspend
+    pub fn open(channel: UartChannel, baud: u32) -> Result<Self, Error> {
+        let handle = acquire(channel, baud)?;
+        let rx =
+            unsafe { NonNull::new_unchecked(sys::furi_stream_buffer_alloc(RX_BUFFER_SIZE, 1)) };
+        unsafe {
+            sys::furi_hal_serial_async_rx_start(
+                handle.as_ptr(),
+                Some(rx_callback),
+                rx.as_ptr() as *mut c_void,
+                false,
+            );
+        }
+
+        Ok(Self {
+            handle,
+            rx,
+            read_timeout: Duration::ZERO,
+        })
+    }
+
+    /// Returns how long reads wait for data to arrive.
+    pub fn read_timeout(&self) -> Duration {
+        self.read_timeout
+    }
+
+    /// Sets how long reads wait for data to arrive before returning 0.
+    ///
+    /// A timeout of zero makes reads return immediately with the bytes received so far,
+    /// and [`Duration::MAX`] makes them wait until at least one byte arrives.
+    pub fn set_read_timeout(&mut self, timeout: Duration) {
+        self.read_timeout = timeout;
+    }
+
+    /// Changes the baud rate of the port.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidParameter`] if the baud rate is not supported by the
+    /// channel, leaving the current baud rate unchanged.
+    pub fn set_baud_rate(&mut self, baud: u32) -> Result<(), Error> {
+        set_baud_rate(self.handle, baud)
+    }
+
+    /// Waits up to `timeout` for bytes to arrive, returning 0 if none did.
+    fn receive(&self, buf: &mut [u8], timeout: Duration) -> usize {
+        unsafe {
+            sys::furi_stream_buffer_receive(
+                self.rx.as_ptr(),
+                buf.as_mut_ptr() as *mut c_void,
+                buf.len(),
+                duration_to_ticks(timeout),
+            )
+        }
+    }
+}
+
+/// Acquires the serial port on `channel` and initializes it with the given baud rate.
+fn acquire(channel: UartChannel, baud: u32) -> Result<NonNull<sys::FuriHalSerialHandle>, Error> {
+    let handle = NonNull::new(unsafe { sys::furi_hal_serial_control_acquire(channel.to_sys()) })
+        .ok_or(Error::AlreadyOpen)?;
+    if !unsafe { sys::furi_hal_serial_is_baud_rate_supported(handle.as_ptr(), baud) } {
+        unsafe { sys::furi_hal_serial_control_release(handle.as_ptr()) };
+        return Err(Error::InvalidParameter);
+    }
+    unsafe { sys::furi_hal_serial_init(handle.as_ptr(), baud) };
+    Ok(handle)
+}
+
+fn set_baud_rate(handle: NonNull<sys::FuriHalSerialHandle>, baud: u32) -> Result<(), Error> {
+    if !unsafe { sys::furi_hal_serial_is_baud_rate_supported(handle.as_ptr(), baud) } {
+        return Err(Error::InvalidParameter);
+    }
+    unsafe { sys::furi_hal_serial_set_br(handle.as_ptr(), baud) };
+    Ok(())
+}
+
+/// Queues all of `buf` for transmission, blocking until it fits into the transmit pipe.
+fn tx(handle: NonNull<sys::FuriHalSerialHandle>, buf: &[u8]) {
+    unsafe { sys::furi_hal_serial_tx(handle.as_ptr(), buf.as_ptr(), buf.len()) };
+}
+
+/// Stops receiving, then releases the serial port.
+fn release(handle: NonNull<sys::FuriHalSerialHandle>) {
+    unsafe {
+        sys::furi_hal_serial_async_rx_stop(handle.as_ptr());
+        sys::furi_hal_serial_deinit(handle.as_ptr());
+        sys::furi_hal_serial_control_release(handle.as_ptr());
+    }
+}
+
+/// Moves received bytes into the stream buffer, in interrupt context.
+unsafe extern "C" fn rx_callback(
+    handle: *mut sys::FuriHalSerialHandle,
+    event: sys::FuriHalSerialRxEvent,
+    context: *mut c_void,
+) {
+    let rx = context as *mut sys::FuriStreamBuffer;
+    if event & sys::FuriHalSerialRxEvent_FuriHalSerialRxEventData != 0 {
+        while unsafe { sys::furi_hal_serial_async_rx_available(handle) } {
+            let byte = unsafe { sys::furi_hal_serial_async_rx(handle) };
+            // Drop the byte if the buffer is full, as we can't wait here.
+            unsafe { sys::furi_stream_buffer_send(rx, &byte as *const u8 as *const c_void, 1, 0) };
+        }
+    }
+}
+
+impl Read for Uart {
+    /// Reads the bytes received so far, waiting up to the [read timeout] for at least
+    /// one byte to arrive.
+    ///
+    /// Returns 0 if no byte arrived in time.
+    ///
+    /// [read timeout]: Uart::set_read_timeout
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        Ok(self.receive(buf, self.read_timeout))
+    }
+}
+
+impl ReadTimeout for Uart {
+    fn read_with_timeout(&mut self, buf: &mut [u8], timeout: Duration) -> Result<usize, Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        match self.receive(buf, timeout) {
+            0 => Err(Error::TimedOut),
+            n => Ok(n),
+        }
+    }
+}
+
+impl Write for Uart {
+    /// Queues all of `buf` for transmission, blocking until it fits into the transmit
+    /// pipe.
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        tx(self.handle, buf);
+        Ok(buf.len())
+    }
+
+    /// Waits until all written bytes have been transmitted.
+    fn flush(&mut self) -> Result<(), Error> {
+        unsafe { sys::furi_hal_serial_tx_wait_complete(self.handle.as_ptr()) };
+        Ok(())
+    }
+}
+
+impl Drop for Uart {
+    fn drop(&mut self) {
+        // Stop receiving before freeing the buffer the callback writes into.
+        release(self.handle);
+        unsafe { sys::furi_stream_buffer_free(self.rx.as_ptr()) };
+    }
+}
+
+#[cfg(feature = "alloc")]
+type RxCallback = Box<dyn FnMut(u8) + Send>;
+
+/// An open serial port, which passes each received byte to a closure.
+///
+/// This suits protocols that are parsed as bytes arrive, such as NMEA sentences from a
+/// GPS module, without a thread to read them. Use [`Uart`] to read received bytes
+/// like a file instead.
+///
+/// The closure is called in interrupt context, so it must be quick and must not block,
+/// which rules out allocating, locking mutexes and most of the Furi APIs. It can put
+/// the byte in a [`MessageQueue`](crate::furi::message_queue::MessageQueue) without
+/// waiting, for example. The port is released when the `CallbackUart` is dropped.
+///
+/// # Examples
+///
+/// ```no_run
+/// use core::sync::atomic::{AtomicU32, Ordering};
+///
+/// use flipperzero::serial::{CallbackUart, UartChannel};
+///
+/// static LINES: AtomicU32 = AtomicU32::new(0);
+///
+/// let uart = CallbackUart::open(UartChannel::Lpuart, 9600, |byte| {
+///     if byte == b'\n' {
+///         LINES.fetch_add(1, Ordering::Relaxed);
+///     }
+/// })
+/// .expect("the port is free");
+/// ```
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub struct CallbackUart {
+    handle: NonNull<sys::FuriHalSerialHandle>,
+    // Boxed twice for a thin pointer, see `furi::thread::Builder::spawn`.
+    #[allow(dead_code)]
+    callback: Box<RxCallback>,
+}
+
+#[cfg(feature = "alloc")]
+impl CallbackUart {
+    /// Opens the serial port on `channel` with the given baud rate, such as `115200`,
+    /// and starts passing received bytes to `callback`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::AlreadyOpen`] if the channel is in use, for example by the
+    /// console or an expansion module, and [`Error::InvalidParameter`] if the baud rate
+    /// is not supported by the channel.
+    pub fn open<F>(channel: UartChannel, baud: u32, callback: F) -> Result<Self, Error>
+    where
+        F: FnMut(u8) + Send + 'static,
+    {
+        unsafe extern "C" fn run_callback(
+            handle: *mut sys::FuriHalSerialHandle,
+            event: sys::FuriHalSerialRxEvent,
+            context: *mut c_void,
+        ) {
+            let callback = unsafe { &mut *(context as *mut RxCallback) };
+            if event & sys::FuriHalSerialRxEvent_FuriHalSerialRxEventData != 0 {
+                while unsafe { sys::furi_hal_serial_async_rx_available(handle) } {
+                    callback(unsafe { sys::furi_hal_serial_async_rx(handle) });
+                }
+            }
+        }
+
+        let handle = acquire(channel, baud)?;
+        let mut callback: Box<RxCallback> = Box::new(Box::new(callback));
+        unsafe {
+            sys::furi_hal_serial_async_rx_start(
+                handle.as_ptr(),
+                Some(run_callback),
+                &mut *callback as *mut RxCallback as *mut c_void,
+                false,
+            );
+        }
+
+        Ok(Self { handle, callback })
+    }
+
+    /// Changes the baud rate of the port.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidParameter`] if the baud rate is not supported by the
+    /// channel, leaving the current baud rate unchanged.
+    pub fn set_baud_rate(&mut self, baud: u32) -> Result<(), Error> {
+        set_baud_rate(self.handle, baud)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Write for CallbackUart {
+    /// Queues all of `buf` for transmission, blocking until it fits into the transmit
+    /// pipe.
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        tx(self.handle, buf);
+        Ok(buf.len())
+    }
+
+    /// Waits until all written bytes have been transmitted.
+    fn flush(&mut self) -> Result<(), Error> {
+        unsafe { sys::furi_hal_serial_tx_wait_complete(self.handle.as_ptr()) };
+        Ok(())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Drop for CallbackUart {
+    fn drop(&mut self) {
+        release(self.handle);
+        // The closure is dropped after this, once it can no longer be called.
+    }
+}
@@ -0,0 +1,161 @@
+//! The storage operations that the helpers of this module are built on.
+
+use alloc::ffi::CString;
+use alloc::vec::Vec;
+use core::ffi::CStr;
+
+use super::{ContextError, File, Metadata, Op, OpenOptions, ReadDir, ResultExt, Storage};
+use crate::io::{Error, Read, Seek, Write};
+
+/// An entry of a directory, returned by [`StorageBackend::read_dir`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryInfo {
+    name: CString,
+    metadata: Metadata,
+}
+
+impl EntryInfo {
+    pub(crate) fn new(name: CString, metadata: Metadata) -> Self {
+        Self { name, metadata }
+    }
+
+    /// Returns the name of the entry, without the path of the directory.
+    pub fn name(&self) -> &CStr {
+        &self.name
+    }
+
+    /// Returns whether the entry is a file or a directory, and its size.
+    pub fn metadata(&self) -> Metadata {
+        self.metadata
+    }
+}
+
+/// The operations on files and directories that helpers working with storage need.
+///
+/// [`Storage`] implements this with the storage service of the firmware. Code that is
+/// generic over a `StorageBackend` can also run on a [`MemStorage`](super::MemStorage),
+/// which keeps everything in memory and can simulate failures, so that it can be tested
+/// without an SD card.
+pub trait StorageBackend {
+    /// An open file.
+    type File: Read + Write + Seek;
+
+    /// An iterator over the entries of a directory.
+    type ReadDir: Iterator<Item = Result<EntryInfo, Error>>;
+
+    /// Opens the file at `path` as described by `options`.
+    fn open(&self, path: &CStr, options: OpenOptions) -> Result<Self::File, Error>;
+
+    /// Returns whether `path` is a file or a directory, and its size.
+    fn metadata(&self, path: &CStr) -> Result<Metadata, Error>;
+
+    /// Returns an iterator over the entries of the directory at `path`.
+    fn read_dir(&self, path: &CStr) -> Result<Self::ReadDir, Error>;
+
+    /// Creates a directory at `path`, whose parent must exist.
+    fn create_dir(&self, path: &CStr) -> Result<(), Error>;
+
+    /// Removes the file or empty directory at `path`.
+    fn remove(&self, path: &CStr) -> Result<(), Error>;
+
+    /// Moves the file or directory at `from` to `to`, which must not exist.
+    fn rename(&self, from: &CStr, to: &CStr) -> Result<(), Error>;
+
+    /// Returns `true` if a file or directory exists at `path`.
+    ///
+    /// Errors other than the path not existing are reported.
+    fn exists(&self, path: &CStr) -> Result<bool, Error> {
+        match self.metadata(path) {
+            Ok(_) => Ok(true),
+            Err(Error::NotExists) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl StorageBackend for Storage {
+    type File = File;
+    type ReadDir = EntryInfos;
+
+    fn open(&self, path: &CStr, options: OpenOptions) -> Result<File, Error> {
+        options.open(path)
+    }
+
+    fn metadata(&self, path: &CStr) -> Result<Metadata, Error> {
+        Storage::metadata(self, path)
+    }
+
+    fn read_dir(&self, path: &CStr) -> Result<EntryInfos, Error> {
+        Storage::read_dir(self, path).map(EntryInfos)
+    }
+
+    fn create_dir(&self, path: &CStr) -> Result<(), Error> {
+        Storage::create_dir(self, path)
+    }
+
+    fn remove(&self, path: &CStr) -> Result<(), Error> {
+        Storage::remove(self, path)
+    }
+
+    fn rename(&self, from: &CStr, to: &CStr) -> Result<(), Error> {
+        Storage::rename(self, from, to)
+    }
+
+    fn exists(&self, path: &CStr) -> Result<bool, Error> {
+        Storage::exists(self, path)
+    }
+}
+
+/// The entries of a directory on the storage service, returned by the
+/// [`StorageBackend::read_dir`] of [`Storage`].
+pub struct EntryInfos(ReadDir);
+
+impl Iterator for EntryInfos {
+    type Item = Result<EntryInfo, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|entry| {
+            entry.map(|entry| EntryInfo::new(CString::from(entry.name()), entry.metadata()))
+        })
+    }
+}
+
+/// Creates the directory at `path` along with any of its parents that are missing.
+///
+/// Succeeds if the directory already exists, and fails with [`Error::Exists`] if `path`
+/// or one of its parents is a file. The error records the directory it failed at.
+pub fn create_dir_all<B: StorageBackend + ?Sized>(
+    backend: &B,
+    path: &CStr,
+) -> Result<(), ContextError> {
+    let bytes = path.to_bytes();
+    let mut ends: Vec<usize> = bytes
+        .iter()
+        .enumerate()
+        .skip(1)
+        .filter(|&(_, &b)| b == b'/')
+        .map(|(i, _)| i)
+        .collect();
+    if !bytes.ends_with(b"/") {
+        ends.push(bytes.len());
+    }
+
+    for end in ends {
+        let dir = CString::new(&bytes[..end])
+            .map_err(|_| Error::InvalidName)
+            .during(Op::CreateDir)
+            .with_path(path)?;
+        match backend.metadata(&dir) {
+            Ok(metadata) if metadata.is_dir() => continue,
+            Ok(_) => return Err(ContextError::new(Error::Exists, Op::CreateDir, Some(&dir))),
+            Err(Error::NotExists) => {}
+            Err(e) => return Err(ContextError::new(e, Op::Metadata, Some(&dir))),
+        }
+        match backend.create_dir(&dir) {
+            // Another thread may have created it in the meantime.
+            Ok(()) | Err(Error::Exists) => {}
+            Err(e) => return Err(ContextError::new(e, Op::CreateDir, Some(&dir))),
+        }
+    }
+    Ok(())
+}
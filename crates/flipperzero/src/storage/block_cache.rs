@@ -0,0 +1,425 @@
+//! Caching recently read blocks of a reader.
+//!
+//! The `block_cache_bench` example counts the reads that reach the file for a hex
+//! viewer's access pattern, with and without a cache. Its results on a device haven't
+//! been recorded yet.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::io::{Error, Read, Seek, SeekFrom, Write};
+
+/// How often a [`BlockCache`] could serve reads from its blocks.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Number of blocks that were read from the cache.
+    pub hits: u32,
+    /// Number of blocks that had to be read from the wrapped reader.
+    pub misses: u32,
+    /// Number of calls to `read` on the wrapped reader.
+    pub reads: u32,
+}
+
+/// A block held by a [`BlockCache`].
+#[derive(Clone, Copy)]
+struct Slot {
+    /// Index of the block, or `None` if the slot is empty.
+    block: Option<usize>,
+    /// Number of bytes of the block that the wrapped reader returned, which is less than
+    /// a block at the end of the reader.
+    len: usize,
+    /// When the block was last used, for finding the least recently used one.
+    used: u32,
+}
+
+impl Slot {
+    const EMPTY: Slot = Slot {
+        block: None,
+        len: 0,
+        used: 0,
+    };
+}
+
+/// Keeps the most recently read blocks of a reader, for formats that jump around a file.
+///
+/// The reader is split into aligned blocks of `block_size` bytes. Reads are served from
+/// the up to `block_count` blocks that were used most recently, and only the missing
+/// blocks are read from the wrapped reader, replacing the least recently used ones.
+/// Seeking only moves the position of the `BlockCache`, so returning to a region that
+/// was read before costs no requests at all.
+///
+/// Writes go straight to the wrapped reader and drop the blocks they overlap. If the
+/// reader is changed in any other way, call [`BlockCache::invalidate`].
+///
+/// # Examples
+///
+/// ```no_run
+/// # use flipperzero::io::{Read, Seek, SeekFrom};
+/// # use flipperzero::storage::{BlockCache, OpenOptions};
+/// # fn main() -> Result<(), flipperzero::io::Error> {
+/// let file = OpenOptions::new()
+///     .read(true)
+///     .open_existing(true)
+///     .open(c"/ext/apps_data/archive.tar")?;
+/// let mut cache = BlockCache::new(file, 512, 8);
+/// let mut header = [0; 512];
+/// cache.read_at(&mut header, 0)?;
+/// cache.read_at(&mut header, 0)?;
+/// assert_eq!(cache.stats().hits, 1);
+/// # Ok(())
+/// # }
+/// ```
+pub struct BlockCache<R> {
+    inner: R,
+    block_size: usize,
+    /// The bytes of the blocks, `block_size` bytes per slot.
+    arena: Vec<u8>,
+    slots: Vec<Slot>,
+    /// Incremented on every use of a slot.
+    clock: u32,
+    position: usize,
+    /// Position of the wrapped reader, if known.
+    inner_position: Option<usize>,
+    stats: CacheStats,
+}
+
+impl<R: Read + Seek> BlockCache<R> {
+    /// Wraps `inner`, caching up to `block_count` blocks of `block_size` bytes.
+    ///
+    /// The current position of `inner` is ignored: reading starts at its beginning.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block_size` or `block_count` is zero.
+    pub fn new(inner: R, block_size: usize, block_count: usize) -> Self {
+        assert!(block_size > 0, "block size must not be zero");
+        assert!(block_count > 0, "block count must not be zero");
+        Self {
+            inner,
+            block_size,
+            arena: vec![0; block_size * block_count],
+            slots: vec![Slot::EMPTY; block_count],
+            clock: 0,
+            position: 0,
+            inner_position: None,
+            stats: CacheStats::default(),
+        }
+    }
+
+    /// Returns a reference to the wrapped reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the wrapped reader.
+    ///
+    /// Call [`BlockCache::invalidate`] after changing it.
+    pub fn get_mut(&mut self) -> &mut R {
+        self.inner_position = None;
+        &mut self.inner
+    }
+
+    /// Returns the wrapped reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Returns the counters of hits, misses and reads since creation or the last call to
+    /// [`BlockCache::reset_stats`].
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    /// Sets the counters of [`BlockCache::stats`] back to zero.
+    pub fn reset_stats(&mut self) {
+        self.stats = CacheStats::default();
+    }
+
+    /// Drops all cached blocks, for when the wrapped reader changed underneath.
+    pub fn invalidate(&mut self) {
+        self.slots.fill(Slot::EMPTY);
+        self.inner_position = None;
+    }
+
+    /// Reads bytes starting at `offset` into `buf`, returning how many bytes were read.
+    ///
+    /// Afterwards, the position is after the bytes that were read.
+    pub fn read_at(&mut self, buf: &mut [u8], offset: u64) -> Result<usize, Error> {
+        self.seek(SeekFrom::Start(offset))?;
+        self.read(buf)
+    }
+
+    /// Returns the slot holding `block`, reading it from the wrapped reader if needed.
+    fn load(&mut self, block: usize) -> Result<usize, Error> {
+        self.clock = self.clock.wrapping_add(1);
+        if let Some(slot) = self.slots.iter().position(|s| s.block == Some(block)) {
+            self.stats.hits += 1;
+            self.slots[slot].used = self.clock;
+            return Ok(slot);
+        }
+        self.stats.misses += 1;
+
+        // Empty slots are used first.
+        let clock = self.clock;
+        let slot = (0..self.slots.len())
+            .max_by_key(|&i| match self.slots[i].block {
+                None => u32::MAX,
+                Some(_) => clock.wrapping_sub(self.slots[i].used),
+            })
+            .unwrap();
+        self.slots[slot] = Slot::EMPTY;
+
+        let start = block * self.block_size;
+        if self.inner_position.take() != Some(start) {
+            self.inner.seek(SeekFrom::Start(start as u64))?;
+        }
+        let bytes = &mut self.arena[slot * self.block_size..][..self.block_size];
+        let mut len = 0;
+        while len < bytes.len() {
+            self.stats.reads += 1;
+            match self.inner.read(&mut bytes[len..]) {
+                Ok(0) => break,
+                Ok(n) => len += n,
+                Err(e) => return Err(e),
+            }
+        }
+        self.inner_position = Some(start + len);
+        self.slots[slot] = Slot {
+            block: Some(block),
+            len,
+            used: self.clock,
+        };
+        Ok(slot)
+    }
+}
+
+impl<R: Read + Seek> Read for BlockCache<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let mut filled = 0;
+        while filled < buf.len() {
+            let block = self.position / self.block_size;
+            let offset = self.position % self.block_size;
+            let slot = match self.load(block) {
+                Ok(slot) => slot,
+                Err(_) if filled > 0 => break,
+                Err(e) => return Err(e),
+            };
+            let len = self.slots[slot].len;
+            let bytes = &self.arena[slot * self.block_size..][offset.min(len)..len];
+            let n = bytes.len().min(buf.len() - filled);
+            buf[filled..filled + n].copy_from_slice(&bytes[..n]);
+            filled += n;
+            self.position += n;
+            if len < self.block_size {
+                // The end of the reader.
+                break;
+            }
+        }
+        Ok(filled)
+    }
+}
+
+impl<R: Read + Seek> Seek for BlockCache<R> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<usize, Error> {
+        let target = match pos {
+            SeekFrom::Start(offset) => i64::try_from(offset).unwrap_or(i64::MAX),
+            SeekFrom::End(offset) => (self.stream_len()? as i64).saturating_add(offset),
+            SeekFrom::Current(offset) => (self.position as i64).saturating_add(offset),
+        };
+        self.position = usize::try_from(target).map_err(|_| Error::InvalidParameter)?;
+        Ok(self.position)
+    }
+
+    fn stream_len(&mut self) -> Result<usize, Error> {
+        self.inner_position = None;
+        self.inner.stream_len()
+    }
+
+    fn stream_position(&mut self) -> Result<usize, Error> {
+        Ok(self.position)
+    }
+}
+
+impl<R: Read + Seek + Write> Write for BlockCache<R> {
+    /// Writes `buf` to the wrapped reader at the current position, dropping the cached
+    /// blocks it overlaps.
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        if self.inner_position.take() != Some(self.position) {
+            self.inner.seek(SeekFrom::Start(self.position as u64))?;
+        }
+        let result = self.inner.write(buf);
+        let n = *result.as_ref().unwrap_or(&0);
+
+        // A failed write may have changed some of the bytes.
+        let end = self.position + if result.is_ok() { n } else { buf.len() };
+        let first = self.position / self.block_size;
+        let last = end.div_ceil(self.block_size);
+        for slot in &mut self.slots {
+            if slot
+                .block
+                .is_some_and(|block| (first..last).contains(&block))
+            {
+                *slot = Slot::EMPTY;
+            }
+        }
+
+        self.position += n;
+        self.inner_position = result.is_ok().then_some(self.position);
+        result
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl<R: Read + Seek> embedded_io::ErrorType for BlockCache<R> {
+    type Error = Error;
+}
+
+#[cfg(feature = "embedded-io")]
+impl<R: Read + Seek> embedded_io::Read for BlockCache<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        Read::read(self, buf)
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl<R: Read + Seek> embedded_io::Seek for BlockCache<R> {
+    fn seek(&mut self, pos: embedded_io::SeekFrom) -> Result<u64, Error> {
+        Seek::seek(self, pos.into()).map(|position| position as u64)
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl<R: Read + Seek + Write> embedded_io::Write for BlockCache<R> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        Write::write(self, buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        Write::flush(self)
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::{BlockCache, CacheStats};
+    use crate::io::cursor::Cursor;
+    use crate::io::{Read, Seek, SeekFrom, Write};
+
+    /// An in-memory file of `len` bytes counting up from zero.
+    fn file(len: usize) -> Cursor<Vec<u8>> {
+        Cursor::new((0..len).map(|i| i as u8).collect())
+    }
+
+    fn read_at(cache: &mut BlockCache<Cursor<Vec<u8>>>, offset: u64, len: usize) -> Vec<u8> {
+        let mut buf = [0; 16];
+        let n = cache.read_at(&mut buf[..len], offset).unwrap();
+        buf[..n].to_vec()
+    }
+
+    #[test]
+    fn serves_repeated_reads_from_cache() {
+        let mut cache = BlockCache::new(file(100), 16, 2);
+        assert_eq!(read_at(&mut cache, 20, 4), [20, 21, 22, 23]);
+        assert_eq!(read_at(&mut cache, 28, 4), [28, 29, 30, 31]);
+        assert_eq!(read_at(&mut cache, 16, 2), [16, 17]);
+        let expected = CacheStats {
+            hits: 2,
+            misses: 1,
+            reads: 1,
+        };
+        assert_eq!(cache.stats(), expected);
+    }
+
+    #[test]
+    fn reads_across_blocks_and_to_the_end() {
+        let mut cache = BlockCache::new(file(40), 16, 4);
+        let mut buf = [0; 30];
+        assert_eq!(cache.read_at(&mut buf, 10).unwrap(), 30);
+        assert!(buf
+            .iter()
+            .enumerate()
+            .all(|(i, &b)| usize::from(b) == i + 10));
+        assert_eq!(cache.stats().misses, 3);
+
+        assert_eq!(cache.read(&mut buf).unwrap(), 0);
+        assert_eq!(cache.seek(SeekFrom::End(-2)).unwrap(), 38);
+        assert_eq!(cache.read(&mut buf).unwrap(), 2);
+        assert_eq!(buf[..2], [38, 39]);
+        assert_eq!(cache.stats().misses, 3);
+    }
+
+    #[test]
+    fn evicts_least_recently_used_block() {
+        let mut cache = BlockCache::new(file(100), 10, 2);
+        read_at(&mut cache, 0, 1);
+        read_at(&mut cache, 10, 1);
+        read_at(&mut cache, 0, 1);
+        read_at(&mut cache, 20, 1);
+        assert_eq!(cache.stats().misses, 3);
+
+        // Block 1 was used least recently, so it made room for block 2.
+        read_at(&mut cache, 0, 1);
+        assert_eq!(cache.stats().misses, 3);
+        read_at(&mut cache, 10, 1);
+        assert_eq!(cache.stats().misses, 4);
+    }
+
+    #[test]
+    fn invalidate_rereads_changed_data() {
+        let mut cache = BlockCache::new(file(32), 16, 2);
+        assert_eq!(read_at(&mut cache, 0, 2), [0, 1]);
+        cache.get_mut().data_mut()[0] = 0xAA;
+        assert_eq!(read_at(&mut cache, 0, 2), [0, 1]);
+
+        cache.invalidate();
+        assert_eq!(read_at(&mut cache, 0, 2), [0xAA, 1]);
+    }
+
+    #[test]
+    fn writes_drop_overlapping_blocks() {
+        let mut cache = BlockCache::new(file(64), 16, 4);
+        let mut buf = [0; 64];
+        assert_eq!(cache.read_at(&mut buf, 0).unwrap(), 64);
+        cache.reset_stats();
+
+        cache.seek(SeekFrom::Start(14)).unwrap();
+        cache.write_all(&[0xFF; 4]).unwrap();
+        assert_eq!(cache.stream_position().unwrap(), 18);
+
+        assert_eq!(read_at(&mut cache, 12, 6), [12, 13, 0xFF, 0xFF, 0xFF, 0xFF]);
+        assert_eq!(read_at(&mut cache, 40, 1), [40]);
+        assert_eq!(cache.stats().misses, 2);
+        assert_eq!(cache.stats().hits, 1);
+    }
+
+    #[cfg(feature = "testsuite")]
+    #[test]
+    fn cursor_conforms() {
+        let result =
+            crate::testsuite::check_read_write_seek(|contents| Ok(Cursor::new(contents.to_vec())));
+        assert!(result.is_ok());
+    }
+
+    #[cfg(feature = "embedded-io")]
+    #[test]
+    fn implements_embedded_io() {
+        let mut cache = BlockCache::new(file(32), 16, 2);
+        let pos = embedded_io::SeekFrom::End(-4);
+        assert_eq!(embedded_io::Seek::seek(&mut cache, pos), Ok(28));
+        let mut buf = [0; 4];
+        embedded_io::Read::read_exact(&mut cache, &mut buf).unwrap();
+        assert_eq!(buf, [28, 29, 30, 31]);
+
+        embedded_io::Seek::seek(&mut cache, embedded_io::SeekFrom::Start(30)).unwrap();
+        embedded_io::Write::write_all(&mut cache, &[0xAA; 2]).unwrap();
+        embedded_io::Seek::seek(&mut cache, embedded_io::SeekFrom::Start(30)).unwrap();
+        embedded_io::Read::read_exact(&mut cache, &mut buf[..2]).unwrap();
+        assert_eq!(buf[..2], [0xAA; 2]);
+    }
+}
@@ -0,0 +1,696 @@
+//! Buffered I/O.
+
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ffi::CStr;
+use core::mem::ManuallyDrop;
+use core::ptr;
+
+use super::perf;
+use crate::io::{BufRead, Error, Read, Seek, SeekFrom, Write};
+
+/// Capacity of the buffer of a [`BufReader`] or [`BufWriter`] created with `new`.
+pub const DEFAULT_BUF_SIZE: usize = perf::DEFAULT_CHUNK_SIZE;
+
+/// Reads ahead from the wrapped reader in large chunks, serving small reads from memory.
+///
+/// Every read from a [`File`](super::File) is a request to the storage service, so
+/// reading a file in small pieces is much faster through a `BufReader`. Reads at least
+/// as large as the buffer skip it when it is empty.
+///
+/// Seeking discards the buffer, after accounting for the bytes still in it, so reads
+/// after a seek always come from the new position.
+pub struct BufReader<R: Read> {
+    inner: R,
+    buf: Box<[u8]>,
+    /// Position of the next unread byte in `buf`.
+    pos: usize,
+    /// Number of bytes of `buf` that were read from `inner`.
+    filled: usize,
+}
+
+impl<R: Read> BufReader<R> {
+    /// Wraps `inner` with a buffer of [`DEFAULT_BUF_SIZE`] bytes.
+    pub fn new(inner: R) -> Self {
+        Self::with_capacity(DEFAULT_BUF_SIZE, inner)
+    }
+
+    /// Wraps `inner`, which reads the file at `path`, with a buffer of the size
+    /// recommended for the storage backend of `path`.
+    ///
+    /// See [`perf::recommended_chunk_size`].
+    pub fn for_path(path: &CStr, inner: R) -> Self {
+        Self::with_capacity(perf::recommended_chunk_size(path), inner)
+    }
+
+    /// Wraps `inner` with a buffer of `capacity` bytes.
+    pub fn with_capacity(capacity: usize, inner: R) -> Self {
+        Self {
+            inner,
+            buf: vec![0; capacity].into_boxed_slice(),
+            pos: 0,
+            filled: 0,
+        }
+    }
+
+    /// Returns a reference to the wrapped reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the wrapped reader.
+    ///
+    /// Reading from it directly skips the bytes that are still buffered.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Returns the bytes that were read ahead and not consumed yet.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf[self.pos..self.filled]
+    }
+
+    /// Returns the capacity of the buffer.
+    pub fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Returns the wrapped reader, discarding the buffered bytes.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn discard_buffer(&mut self) {
+        self.pos = 0;
+        self.filled = 0;
+    }
+}
+
+impl<R: Read + Seek> BufReader<R> {
+    /// Seeks `offset` bytes from the current position, keeping the buffer if the new
+    /// position is within it.
+    ///
+    /// Unlike [`Seek::seek`], this doesn't return the new position, so moving within
+    /// the buffer doesn't need a request to the wrapped reader.
+    pub fn seek_relative(&mut self, offset: i64) -> Result<(), Error> {
+        let pos = self.pos as i64;
+        match pos.checked_add(offset) {
+            Some(new) if (0..=self.filled as i64).contains(&new) => {
+                self.pos = new as usize;
+                Ok(())
+            }
+            _ => self.seek(SeekFrom::Current(offset)).map(drop),
+        }
+    }
+}
+
+impl<R: Read> Read for BufReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        if self.pos == self.filled && buf.len() >= self.buf.len() {
+            // Buffering wouldn't save any reads.
+            self.discard_buffer();
+            return self.inner.read(buf);
+        }
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<R: Read> BufRead for BufReader<R> {
+    fn fill_buf(&mut self) -> Result<&[u8], Error> {
+        if self.pos == self.filled {
+            self.filled = self.inner.read(&mut self.buf)?;
+            self.pos = 0;
+        }
+        Ok(self.buffer())
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.filled);
+    }
+}
+
+impl<R: Read + Seek> Seek for BufReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<usize, Error> {
+        let position = match pos {
+            SeekFrom::Current(offset) => {
+                // `inner` is ahead of the reader by the buffered bytes.
+                let remainder = (self.filled - self.pos) as i64;
+                match offset.checked_sub(remainder) {
+                    Some(offset) => self.inner.seek(SeekFrom::Current(offset))?,
+                    None => {
+                        self.inner.seek(SeekFrom::Current(-remainder))?;
+                        self.discard_buffer();
+                        self.inner.seek(SeekFrom::Current(offset))?
+                    }
+                }
+            }
+            pos => self.inner.seek(pos)?,
+        };
+        self.discard_buffer();
+        Ok(position)
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl<R: Read> embedded_io::ErrorType for BufReader<R> {
+    type Error = Error;
+}
+
+#[cfg(feature = "embedded-io")]
+impl<R: Read> embedded_io::Read for BufReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        Read::read(self, buf)
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl<R: Read> embedded_io::BufRead for BufReader<R> {
+    fn fill_buf(&mut self) -> Result<&[u8], Error> {
+        BufRead::fill_buf(self)
+    }
+
+    fn consume(&mut self, amt: usize) {
+        BufRead::consume(self, amt)
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl<R: Read + Seek> embedded_io::Seek for BufReader<R> {
+    fn seek(&mut self, pos: embedded_io::SeekFrom) -> Result<u64, Error> {
+        Seek::seek(self, pos.into()).map(|position| position as u64)
+    }
+}
+
+/// Collects small writes into larger ones before passing them to the wrapped writer.
+///
+/// Every write to a [`File`](super::File) is a request to the storage service, so
+/// writing a file in small pieces is much faster through a `BufWriter`.
+///
+/// The buffer is written out when the `BufWriter` is dropped, but errors are ignored
+/// then. Call [`Write::flush`] or [`BufWriter::into_inner`] to handle them.
+pub struct BufWriter<W: Write> {
+    inner: W,
+    buf: Vec<u8>,
+    /// The boundary that writes are aligned to, or 0.
+    align: usize,
+    /// Number of bytes written past the last boundary, if writes are aligned.
+    phase: usize,
+}
+
+impl<W: Write> BufWriter<W> {
+    /// Wraps `inner` with a buffer of [`DEFAULT_BUF_SIZE`] bytes.
+    pub fn new(inner: W) -> Self {
+        Self::with_capacity(DEFAULT_BUF_SIZE, inner)
+    }
+
+    /// Wraps `inner`, which writes to the file at `path` from its start, with a buffer of
+    /// the size recommended for the storage backend of `path`.
+    ///
+    /// Writes to the SD card are also aligned to its sectors, as with
+    /// [`BufWriter::align_to`]. See [`perf::recommended_chunk_size`] and
+    /// [`perf::recommended_alignment`].
+    pub fn for_path(path: &CStr, inner: W) -> Self {
+        let writer = Self::with_capacity(perf::recommended_chunk_size(path), inner);
+        match perf::recommended_alignment(path) {
+            Some(bytes) => writer.align_to(bytes),
+            None => writer,
+        }
+    }
+
+    /// Wraps `inner` with a buffer of `capacity` bytes.
+    pub fn with_capacity(capacity: usize, inner: W) -> Self {
+        Self {
+            inner,
+            buf: Vec::with_capacity(capacity),
+            align: 0,
+            phase: 0,
+        }
+    }
+
+    /// Aligns the writes of a full buffer to boundaries every `bytes` bytes.
+    ///
+    /// When the buffer fills up, it is written out up to the last boundary, and the
+    /// bytes after it stay at the start of the buffer, so that the card underneath a
+    /// [`File`](super::File) on the SD card doesn't have to read and rewrite partly
+    /// written sectors. [`Write::flush`], [`BufWriter::into_inner`] and dropping the
+    /// `BufWriter` still write out everything.
+    ///
+    /// Boundaries are counted from where the `BufWriter` starts writing, so `inner`
+    /// should be at an aligned position, such as the start of a file. After a seek, they
+    /// are counted from position 0. A buffer with no boundary in it is written out
+    /// completely. `bytes` of 0 or 1 turn alignment off.
+    pub fn align_to(mut self, bytes: usize) -> Self {
+        self.align = if bytes > 1 { bytes } else { 0 };
+        self.phase = 0;
+        self
+    }
+
+    /// Returns a reference to the wrapped writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the wrapped writer.
+    ///
+    /// Writing to it directly skips the bytes that are still buffered.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Returns the capacity of the buffer.
+    pub fn capacity(&self) -> usize {
+        self.buf.capacity()
+    }
+
+    /// Writes out the buffer and returns the wrapped writer.
+    pub fn into_inner(mut self) -> Result<W, Error> {
+        self.flush_buf()?;
+        let this = ManuallyDrop::new(self);
+        // `this` is never used again, so the fields are moved out exactly once.
+        let (inner, buf) = unsafe { (ptr::read(&this.inner), ptr::read(&this.buf)) };
+        drop(buf);
+        Ok(inner)
+    }
+
+    /// Writes out the buffered bytes, keeping those that could not be written.
+    fn flush_buf(&mut self) -> Result<(), Error> {
+        self.write_buf(self.buf.len())
+    }
+
+    /// Writes out the buffered bytes up to the last boundary, or all of them if there is
+    /// no boundary in the buffer.
+    fn flush_aligned(&mut self) -> Result<(), Error> {
+        let end = (self.phase + self.buf.len()) / self.align * self.align;
+        let len = if end > self.phase {
+            end - self.phase
+        } else {
+            self.buf.len()
+        };
+        self.write_buf(len)
+    }
+
+    /// Writes out the first `len` buffered bytes, keeping those that could not be
+    /// written and those after them.
+    fn write_buf(&mut self, len: usize) -> Result<(), Error> {
+        let mut written = 0;
+        let result = loop {
+            if written == len {
+                break Ok(());
+            }
+            match self.inner.write(&self.buf[written..len]) {
+                Ok(0) => break Err(Error::WriteZero),
+                Ok(n) => written += n,
+                Err(e) => break Err(e),
+            }
+        };
+        self.buf.drain(..written);
+        self.advance(written);
+        result
+    }
+
+    /// Records that `n` bytes were written to `inner`.
+    fn advance(&mut self, n: usize) {
+        if self.align > 0 {
+            self.phase = (self.phase + n) % self.align;
+        }
+    }
+
+    fn write_aligned(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        if self.buf.len() == self.buf.capacity() {
+            self.flush_aligned()?;
+        }
+
+        // Whole blocks starting at a boundary don't need to be buffered.
+        let whole = buf.len() / self.align * self.align;
+        if self.buf.is_empty() && self.phase == 0 && whole >= self.buf.capacity().max(1) {
+            let n = self.inner.write(&buf[..whole])?;
+            self.advance(n);
+            return Ok(n);
+        }
+
+        let n = buf.len().min(self.buf.capacity() - self.buf.len());
+        self.buf.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+}
+
+impl<W: Write> Write for BufWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        if self.align > 0 && self.buf.capacity() > 0 {
+            return self.write_aligned(buf);
+        }
+        if self.buf.len() + buf.len() > self.buf.capacity() {
+            self.flush_buf()?;
+        }
+        if buf.len() >= self.buf.capacity() {
+            // Buffering wouldn't save any writes.
+            self.inner.write(buf)
+        } else {
+            self.buf.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.flush_buf()?;
+        self.inner.flush()
+    }
+}
+
+/// Writes out the buffer before seeking.
+impl<W: Write + Seek> Seek for BufWriter<W> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<usize, Error> {
+        self.flush_buf()?;
+        let position = self.inner.seek(pos)?;
+        if self.align > 0 {
+            self.phase = position % self.align;
+        }
+        Ok(position)
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl<W: Write> embedded_io::ErrorType for BufWriter<W> {
+    type Error = Error;
+}
+
+#[cfg(feature = "embedded-io")]
+impl<W: Write> embedded_io::Write for BufWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        Write::write(self, buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        Write::flush(self)
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl<W: Write + Seek> embedded_io::Seek for BufWriter<W> {
+    fn seek(&mut self, pos: embedded_io::SeekFrom) -> Result<u64, Error> {
+        Seek::seek(self, pos.into()).map(|position| position as u64)
+    }
+}
+
+impl<W: Write> Drop for BufWriter<W> {
+    fn drop(&mut self) {
+        let _ = self.flush_buf();
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::{BufReader, BufWriter};
+    use crate::io::cursor::Cursor;
+    use crate::io::{BufRead, Error, Read, Seek, SeekFrom, Write};
+    use crate::storage::{MemStorage, OpenOptions, StorageBackend};
+
+    /// Records every write it receives.
+    #[derive(Default)]
+    struct Recorder {
+        data: Vec<u8>,
+        writes: usize,
+        /// Where each write ended.
+        ends: Vec<usize>,
+    }
+
+    impl Write for Recorder {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+            self.data.extend_from_slice(buf);
+            self.writes += 1;
+            self.ends.push(self.data.len());
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn coalesces_small_writes() {
+        let mut recorder = Recorder::default();
+        let mut writer = BufWriter::with_capacity(16, &mut recorder);
+        for i in 0..40u8 {
+            writer.write_all(&[i]).unwrap();
+        }
+        writer.flush().unwrap();
+        drop(writer);
+
+        assert_eq!(recorder.data, (0..40).collect::<Vec<u8>>());
+        assert_eq!(recorder.writes, 3);
+    }
+
+    #[test]
+    fn passes_large_writes_through() {
+        let mut recorder = Recorder::default();
+        let mut writer = BufWriter::with_capacity(4, &mut recorder);
+        writer.write_all(b"ab").unwrap();
+        writer.write_all(b"cdefgh").unwrap();
+        let recorder = writer.into_inner().unwrap();
+
+        assert_eq!(recorder.data, b"abcdefgh");
+        assert_eq!(recorder.writes, 2);
+    }
+
+    #[test]
+    fn buffers_are_sized_for_the_path() {
+        use crate::storage::perf::{EXT_CHUNK_SIZE, INT_CHUNK_SIZE};
+
+        let reader = BufReader::for_path(c"/int/config.txt", Cursor::new([]));
+        assert_eq!(reader.capacity(), INT_CHUNK_SIZE);
+        let writer = BufWriter::for_path(c"/ext/log.csv", Cursor::empty(Vec::new()));
+        assert_eq!(writer.capacity(), EXT_CHUNK_SIZE);
+    }
+
+    #[test]
+    fn flushes_on_drop() {
+        let mut recorder = Recorder::default();
+        BufWriter::new(&mut recorder).write_all(b"hello").unwrap();
+
+        assert_eq!(recorder.data, b"hello");
+    }
+
+    /// Bytes that differ from their neighbours, to catch lost or reordered bytes.
+    fn pattern(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn aligns_full_buffers() {
+        let data = pattern(2000);
+        let mut recorder = Recorder::default();
+        let mut writer = BufWriter::with_capacity(100, &mut recorder).align_to(64);
+        for record in data.chunks(37) {
+            writer.write_all(record).unwrap();
+        }
+        drop(writer);
+
+        assert_eq!(recorder.data, data);
+        let (last, aligned) = recorder.ends.split_last().unwrap();
+        assert!(aligned.iter().all(|end| end % 64 == 0));
+        assert_eq!(*last, 2000);
+    }
+
+    #[test]
+    fn flush_writes_everything() {
+        let data = pattern(700);
+        let mut recorder = Recorder::default();
+        let mut writer = BufWriter::with_capacity(128, &mut recorder).align_to(32);
+        writer.write_all(&data[..50]).unwrap();
+        writer.flush().unwrap();
+        assert_eq!(writer.get_ref().data.len(), 50);
+
+        // Boundaries are still counted from the start, so the next write ends on one
+        // rather than a buffer length after the flush.
+        writer.write_all(&data[50..300]).unwrap();
+        assert_eq!(writer.get_ref().ends[1], 160);
+        writer.write_all(&data[300..]).unwrap();
+        let recorder = writer.into_inner().unwrap();
+
+        assert_eq!(recorder.data, data);
+        let (_, middle) = recorder.ends.split_first().unwrap();
+        let (_, middle) = middle.split_last().unwrap();
+        assert!(middle.iter().all(|end| end % 32 == 0));
+    }
+
+    #[test]
+    fn passes_whole_blocks_through() {
+        let data = pattern(300);
+        let mut recorder = Recorder::default();
+        let mut writer = BufWriter::with_capacity(64, &mut recorder).align_to(64);
+        writer.write_all(&data).unwrap();
+        let recorder = writer.into_inner().unwrap();
+
+        assert_eq!(recorder.data, data);
+        assert_eq!(recorder.ends, [256, 300]);
+    }
+
+    #[test]
+    fn writes_buffers_without_boundaries() {
+        let data = pattern(100);
+        let mut recorder = Recorder::default();
+        let mut writer = BufWriter::with_capacity(16, &mut recorder).align_to(512);
+        for record in data.chunks(7) {
+            writer.write_all(record).unwrap();
+        }
+        let recorder = writer.into_inner().unwrap();
+
+        assert_eq!(recorder.data, data);
+    }
+
+    /// Reads everything from `reader` in pieces of `len` bytes.
+    fn read_in_pieces(reader: &mut impl Read, len: usize) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut piece = Vec::from([0; 64]);
+        loop {
+            let n = reader.read(&mut piece[..len]).unwrap();
+            if n == 0 {
+                return data;
+            }
+            data.extend_from_slice(&piece[..n]);
+        }
+    }
+
+    #[test]
+    fn coalesces_small_reads() {
+        let data = pattern(1000);
+        let mut direct = Cursor::new(data.clone());
+        assert_eq!(read_in_pieces(&mut direct, 7), data);
+
+        let mut source = Cursor::new(data.clone());
+        let mut reader = BufReader::with_capacity(100, &mut source);
+        assert_eq!(read_in_pieces(&mut reader, 7), data);
+        drop(reader);
+
+        // Ten full buffers, and one read finding the end.
+        assert_eq!(source.reads, 11);
+        assert!(source.reads * 10 < direct.reads);
+    }
+
+    #[test]
+    fn passes_large_reads_through() {
+        let data = pattern(300);
+        let mut source = Cursor::new(data.clone());
+        let mut reader = BufReader::with_capacity(16, &mut source);
+        let mut buf = [0; 3];
+        reader.read(&mut buf).unwrap();
+        assert_eq!(reader.buffer(), &data[3..16]);
+        assert_eq!(read_in_pieces(&mut reader, 32)[..], data[3..]);
+        drop(reader);
+
+        // One fill, then nine reads of the rest and one finding the end.
+        assert_eq!(source.reads, 1 + 9 + 1);
+    }
+
+    #[test]
+    fn fills_and_consumes() {
+        let mut source = Cursor::new(b"hello world".to_vec());
+        let mut reader = BufReader::with_capacity(8, &mut source);
+        assert_eq!(reader.fill_buf().unwrap(), b"hello wo");
+        reader.consume(6);
+        assert_eq!(reader.fill_buf().unwrap(), b"wo");
+        reader.consume(2);
+        assert_eq!(reader.fill_buf().unwrap(), b"rld");
+        reader.consume(100);
+        assert!(reader.fill_buf().unwrap().is_empty());
+    }
+
+    #[test]
+    fn seeking_discards_the_buffer() {
+        let data = pattern(200);
+        let mut source = Cursor::new(data.clone());
+        let mut reader = BufReader::with_capacity(32, &mut source);
+        let mut buf = [0; 10];
+        reader.read(&mut buf).unwrap();
+
+        // The buffered bytes are accounted for.
+        assert_eq!(reader.stream_position(), Ok(10));
+        assert_eq!(reader.seek(SeekFrom::Current(-4)), Ok(6));
+        reader.read(&mut buf).unwrap();
+        assert_eq!(buf[..], data[6..16]);
+
+        assert_eq!(reader.seek(SeekFrom::Start(150)), Ok(150));
+        reader.read(&mut buf).unwrap();
+        assert_eq!(buf[..], data[150..160]);
+
+        assert_eq!(reader.seek(SeekFrom::End(-5)), Ok(195));
+        assert_eq!(read_in_pieces(&mut reader, 10)[..], data[195..]);
+
+        assert_eq!(
+            reader.seek(SeekFrom::Current(i64::MIN)),
+            Err(Error::InvalidParameter)
+        );
+    }
+
+    #[test]
+    fn seeks_within_the_buffer() {
+        let data = pattern(100);
+        let mut source = Cursor::new(data.clone());
+        let mut reader = BufReader::with_capacity(32, &mut source);
+        let mut buf = [0; 8];
+        reader.read(&mut buf).unwrap();
+        reader.seek_relative(-8).unwrap();
+        reader.read(&mut buf).unwrap();
+        assert_eq!(buf[..], data[..8]);
+        reader.seek_relative(16).unwrap();
+        reader.read(&mut buf).unwrap();
+        assert_eq!(buf[..], data[24..32]);
+        // Past the buffer, seeking goes to the reader.
+        reader.seek_relative(40).unwrap();
+        reader.read(&mut buf).unwrap();
+        assert_eq!(buf[..], data[72..80]);
+        drop(reader);
+
+        assert_eq!(source.reads, 2);
+    }
+
+    #[cfg(feature = "embedded-io")]
+    #[test]
+    fn implements_embedded_io() {
+        let data = pattern(100);
+        let mut source = Cursor::new(data.clone());
+        let mut reader = BufReader::with_capacity(16, &mut source);
+        assert_eq!(
+            embedded_io::BufRead::fill_buf(&mut reader).unwrap(),
+            &data[..16]
+        );
+        embedded_io::BufRead::consume(&mut reader, 4);
+        let pos = embedded_io::SeekFrom::Current(2);
+        assert_eq!(embedded_io::Seek::seek(&mut reader, pos), Ok(6));
+        let mut buf = [0; 10];
+        embedded_io::Read::read_exact(&mut reader, &mut buf).unwrap();
+        assert_eq!(buf[..], data[6..16]);
+
+        let mut recorder = Recorder::default();
+        let mut writer = BufWriter::with_capacity(16, &mut recorder);
+        embedded_io::Write::write_all(&mut writer, b"hello").unwrap();
+        embedded_io::Write::flush(&mut writer).unwrap();
+        drop(writer);
+        assert_eq!(recorder.data, b"hello");
+    }
+
+    #[test]
+    fn writer_flushes_before_seeking() {
+        let path = c"/ext/seek.bin";
+        let storage = MemStorage::new();
+        let file = storage
+            .open(path, OpenOptions::new().write(true).create_new(true))
+            .unwrap();
+        let mut writer = BufWriter::with_capacity(16, file);
+        writer.write_all(b"0123456789").unwrap();
+        assert_eq!(writer.seek(SeekFrom::Start(2)), Ok(2));
+        writer.write_all(b"ab").unwrap();
+        assert_eq!(writer.stream_position(), Ok(4));
+        drop(writer);
+
+        assert_eq!(storage.contents(path).unwrap(), b"01ab456789");
+    }
+}
@@ -0,0 +1,221 @@
+//! Errors that remember the operation and path they happened at.
+
+use alloc::ffi::CString;
+use core::ffi::CStr;
+use core::fmt;
+
+use crate::io::Error;
+
+/// A storage operation, as recorded by a [`ContextError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Op {
+    Open,
+    Read,
+    Write,
+    Seek,
+    Sync,
+    Metadata,
+    ReadDir,
+    CreateDir,
+    Remove,
+    Rename,
+    Copy,
+    /// An operation that wasn't recorded.
+    Other,
+}
+
+impl Op {
+    /// Returns the name of the operation, as shown by [`ContextError`].
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Open => "open",
+            Self::Read => "read",
+            Self::Write => "write",
+            Self::Seek => "seek",
+            Self::Sync => "sync",
+            Self::Metadata => "stat",
+            Self::ReadDir => "read dir",
+            Self::CreateDir => "create dir",
+            Self::Remove => "remove",
+            Self::Rename => "rename",
+            Self::Copy => "copy",
+            Self::Other => "access",
+        }
+    }
+}
+
+/// An [`Error`] along with the operation that failed and the path it failed on.
+///
+/// Displays as the operation, the path and the error, such as
+/// `rename /ext/a.txt: file/dir not exist`.
+///
+/// Errors get their context from the combinators of [`ResultExt`], and lose it again
+/// when converted into an [`Error`], such as by `?` in a function returning one.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use flipperzero::storage::{ContextError, Op, ResultExt, Storage};
+/// fn remove_log(storage: &Storage) -> Result<(), ContextError> {
+///     let path = c"/ext/apps_data/demo/log.txt";
+///     storage.remove(path).during(Op::Remove).with_path(path)
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextError {
+    error: Error,
+    op: Op,
+    path: Option<CString>,
+}
+
+impl ContextError {
+    /// Adds context to `error`.
+    pub fn new(error: Error, op: Op, path: Option<&CStr>) -> Self {
+        Self {
+            error,
+            op,
+            path: path.map(CString::from),
+        }
+    }
+
+    /// Returns the error without its context.
+    pub fn error(&self) -> Error {
+        self.error
+    }
+
+    /// Returns the operation that failed.
+    pub fn op(&self) -> Op {
+        self.op
+    }
+
+    /// Returns the path the operation failed on, if it was recorded.
+    pub fn path(&self) -> Option<&CStr> {
+        self.path.as_deref()
+    }
+}
+
+impl From<Error> for ContextError {
+    fn from(error: Error) -> Self {
+        Self::new(error, Op::Other, None)
+    }
+}
+
+impl From<ContextError> for Error {
+    fn from(error: ContextError) -> Self {
+        error.error
+    }
+}
+
+impl core::error::Error for ContextError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl fmt::Display for ContextError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.op.name())?;
+        if let Some(path) = &self.path {
+            write!(f, " {}", path.to_bytes().escape_ascii())?;
+        }
+        write!(f, ": {}", self.error)
+    }
+}
+
+impl ufmt::uDisplay for ContextError {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        f.write_str(self.op.name())?;
+        if let Some(path) = &self.path {
+            f.write_char(' ')?;
+            for c in path.to_bytes().escape_ascii() {
+                f.write_char(c as char)?;
+            }
+        }
+        ufmt::uwrite!(f, ": {}", self.error)
+    }
+}
+
+/// Combinators adding context to the errors of results.
+pub trait ResultExt<T> {
+    /// Records `op` as the operation that failed, replacing any recorded before.
+    fn during(self, op: Op) -> Result<T, ContextError>;
+
+    /// Records `path` as the path the operation failed on, unless one was recorded
+    /// before.
+    fn with_path(self, path: &CStr) -> Result<T, ContextError>;
+}
+
+impl<T> ResultExt<T> for Result<T, Error> {
+    fn during(self, op: Op) -> Result<T, ContextError> {
+        self.map_err(|error| ContextError::new(error, op, None))
+    }
+
+    fn with_path(self, path: &CStr) -> Result<T, ContextError> {
+        self.map_err(|error| ContextError::new(error, Op::Other, Some(path)))
+    }
+}
+
+impl<T> ResultExt<T> for Result<T, ContextError> {
+    fn during(self, op: Op) -> Result<T, ContextError> {
+        self.map_err(|error| ContextError { op, ..error })
+    }
+
+    fn with_path(self, path: &CStr) -> Result<T, ContextError> {
+        self.map_err(|mut error| {
+            if error.path.is_none() {
+                error.path = Some(path.into());
+            }
+            error
+        })
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::{ContextError, Op, ResultExt};
+    use crate::io::Error;
+
+    #[test]
+    fn displays_op_and_path() {
+        let error = Err::<(), _>(Error::NotExists)
+            .during(Op::Rename)
+            .with_path(c"/ext/a.txt")
+            .unwrap_err();
+        assert_eq!(error.to_string(), "rename /ext/a.txt: file/dir not exist");
+
+        let error = ContextError::from(Error::Denied);
+        assert_eq!(error.to_string(), "access: access denied");
+    }
+
+    #[test]
+    fn innermost_path_is_kept() {
+        let error = Err::<(), _>(Error::Exists)
+            .with_path(c"/ext/file")
+            .during(Op::CreateDir)
+            .with_path(c"/ext/file/dir")
+            .unwrap_err();
+        assert_eq!(error.op(), Op::CreateDir);
+        assert_eq!(error.path(), Some(c"/ext/file"));
+        assert_eq!(Error::from(error), Error::Exists);
+    }
+
+    #[test]
+    fn source_is_the_error() {
+        use core::error::Error as _;
+
+        let error = ContextError::from(Error::NotReady);
+        let source = error.source().unwrap();
+        assert_eq!(source.to_string(), Error::NotReady.to_string());
+    }
+
+    #[test]
+    fn successes_are_untouched() {
+        assert_eq!(Ok::<_, Error>(3).during(Op::Read), Ok(3));
+    }
+}
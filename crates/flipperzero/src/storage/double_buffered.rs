@@ -0,0 +1,358 @@
+//! Writing from a worker thread while the next buffer fills.
+//!
+//! The `buffered_bench` example compares how long a SubGhz RAW capture waits in `write`
+//! with this writer and with a `BufWriter`. It hasn't been run on a device yet, so there
+//! are no numbers yet showing that the producer stalls less.
+
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::mem;
+use core::time::Duration;
+
+use super::DEFAULT_BUF_SIZE;
+use crate::furi::message_queue::MessageQueue;
+use crate::furi::thread::{self, JoinHandle};
+use crate::io::{Error, Write};
+
+const WORKER_STACK_SIZE: usize = 2048;
+
+/// Work for the worker thread.
+enum Job {
+    Write(Vec<u8>),
+    Flush,
+    /// Flush and hand back the writer.
+    Finish,
+}
+
+/// What the worker thread did.
+enum Done<W> {
+    /// The buffer was written, and is handed back empty.
+    Written(Vec<u8>),
+    Flushed,
+    Finished(W),
+}
+
+/// The queues between a [`DoubleBufferedWriter`] and its worker thread.
+struct Channels<W> {
+    jobs: MessageQueue<Job>,
+    done: MessageQueue<(Done<W>, Result<(), Error>)>,
+}
+
+/// Collects writes into a buffer that a worker thread writes out while the next one
+/// fills.
+///
+/// A [`BufWriter`](super::BufWriter) makes the caller wait whenever its buffer is
+/// written, which for a [`File`](super::File) on the SD card can take long enough to
+/// lose data from a capture. A `DoubleBufferedWriter` hands a full buffer to its worker
+/// thread and keeps accepting writes into a second one, so `write` only blocks when
+/// both buffers are full.
+///
+/// Errors from the worker thread are reported by the next call to `write`, `flush` or
+/// [`DoubleBufferedWriter::finish`], and every write after the first error fails.
+///
+/// The buffers are written out when the writer is dropped, but errors are ignored then.
+/// Call [`DoubleBufferedWriter::finish`] to handle them.
+pub struct DoubleBufferedWriter<W: Write + Send + 'static> {
+    current: Vec<u8>,
+    /// The other buffer, or `None` while the worker thread writes it.
+    spare: Option<Vec<u8>>,
+    channels: Arc<Channels<W>>,
+    /// `None` once the writer has been finished.
+    worker: Option<JoinHandle>,
+    error: Option<Error>,
+}
+
+impl<W: Write + Send + 'static> DoubleBufferedWriter<W> {
+    /// Moves `inner` to a worker thread, with two buffers of [`DEFAULT_BUF_SIZE`] bytes.
+    pub fn new(inner: W) -> Self {
+        Self::with_capacity(DEFAULT_BUF_SIZE, inner)
+    }
+
+    /// Moves `inner` to a worker thread, with two buffers of `capacity` bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn with_capacity(capacity: usize, inner: W) -> Self {
+        assert!(capacity > 0, "buffer capacity must not be zero");
+
+        let channels = Arc::new(Channels {
+            jobs: MessageQueue::new(1),
+            done: MessageQueue::new(1),
+        });
+        let worker_channels = channels.clone();
+        let worker = thread::Builder::new()
+            .stack_size(WORKER_STACK_SIZE)
+            .spawn(move || {
+                run(inner, &worker_channels);
+                0
+            });
+
+        Self {
+            current: Vec::with_capacity(capacity),
+            spare: Some(Vec::with_capacity(capacity)),
+            channels,
+            worker: Some(worker),
+            error: None,
+        }
+    }
+
+    /// Returns the capacity of each of the two buffers.
+    pub fn capacity(&self) -> usize {
+        self.current.capacity()
+    }
+
+    /// Writes out both buffers, flushes the wrapped writer and returns it.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error from writing, in which case the wrapped writer is dropped.
+    pub fn finish(mut self) -> Result<W, Error> {
+        self.finish_inner()
+    }
+
+    fn finish_inner(&mut self) -> Result<W, Error> {
+        // An error is reported below, once the worker thread has stopped.
+        let _ = self.send_current();
+        self.reclaim();
+        self.channels
+            .jobs
+            .put(Job::Finish, Duration::MAX)
+            .map_err(|(_, status)| status)
+            .unwrap();
+        let Done::Finished(inner) = self.receive() else {
+            unreachable!()
+        };
+        if let Some(worker) = self.worker.take() {
+            worker.join();
+        }
+        self.error.map_or(Ok(inner), Err)
+    }
+
+    /// Waits for the worker thread to finish a job, recording any error.
+    fn receive(&mut self) -> Done<W> {
+        let (done, result) = self.channels.done.get(Duration::MAX).unwrap();
+        if let Err(e) = result {
+            self.error.get_or_insert(e);
+        }
+        done
+    }
+
+    /// Waits for the buffer the worker thread is writing, if any.
+    fn reclaim(&mut self) {
+        if self.spare.is_none() {
+            let Done::Written(buf) = self.receive() else {
+                unreachable!()
+            };
+            self.spare = Some(buf);
+        }
+    }
+
+    /// Hands the current buffer to the worker thread, if it holds anything, and
+    /// continues with the other one.
+    fn send_current(&mut self) -> Result<(), Error> {
+        self.reclaim();
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        if !self.current.is_empty() {
+            let next = self.spare.take().unwrap();
+            let full = mem::replace(&mut self.current, next);
+            self.channels
+                .jobs
+                .put(Job::Write(full), Duration::MAX)
+                .map_err(|(_, status)| status)
+                .unwrap();
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write + Send + 'static> Write for DoubleBufferedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        if self.current.len() == self.current.capacity() {
+            self.send_current()?;
+        }
+        let n = buf.len().min(self.current.capacity() - self.current.len());
+        self.current.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+
+    /// Writes out both buffers and flushes the wrapped writer, waiting for the worker
+    /// thread.
+    fn flush(&mut self) -> Result<(), Error> {
+        self.send_current()?;
+        self.reclaim();
+        self.channels
+            .jobs
+            .put(Job::Flush, Duration::MAX)
+            .map_err(|(_, status)| status)
+            .unwrap();
+        self.receive();
+        self.error.map_or(Ok(()), Err)
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl<W: Write + Send + 'static> embedded_io::ErrorType for DoubleBufferedWriter<W> {
+    type Error = Error;
+}
+
+#[cfg(feature = "embedded-io")]
+impl<W: Write + Send + 'static> embedded_io::Write for DoubleBufferedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        Write::write(self, buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        Write::flush(self)
+    }
+}
+
+impl<W: Write + Send + 'static> Drop for DoubleBufferedWriter<W> {
+    fn drop(&mut self) {
+        if self.worker.is_some() {
+            let _ = self.finish_inner();
+        }
+    }
+}
+
+/// Runs the jobs for `inner` until asked to finish.
+///
+/// After the first error, buffers are handed back without writing them.
+fn run<W: Write>(mut inner: W, channels: &Channels<W>) {
+    let mut failed = false;
+    loop {
+        let Ok(job) = channels.jobs.get(Duration::MAX) else {
+            continue;
+        };
+        let reply = match job {
+            Job::Write(mut buf) => {
+                let result = if failed {
+                    Ok(())
+                } else {
+                    inner.write_all(&buf)
+                };
+                buf.clear();
+                (Done::Written(buf), result)
+            }
+            Job::Flush => (Done::Flushed, if failed { Ok(()) } else { inner.flush() }),
+            Job::Finish => {
+                let result = if failed { Ok(()) } else { inner.flush() };
+                let _ = channels
+                    .done
+                    .put((Done::Finished(inner), result), Duration::MAX);
+                return;
+            }
+        };
+        failed |= reply.1.is_err();
+        let _ = channels.done.put(reply, Duration::MAX);
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use alloc::sync::Arc;
+    use alloc::vec::Vec;
+
+    use super::DoubleBufferedWriter;
+    use crate::furi::sync::Mutex;
+    use crate::io::cursor::Cursor;
+    use crate::io::{Error, Write};
+
+    /// Accepts `limit` bytes, then fails.
+    struct Failing {
+        limit: usize,
+    }
+
+    impl Write for Failing {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+            if self.limit == 0 {
+                return Err(Error::Denied);
+            }
+            let n = buf.len().min(self.limit);
+            self.limit -= n;
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    /// Keeps what is written where the test can see it after the writer is dropped.
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+            self.0.lock().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn keeps_write_order() {
+        let mut writer = DoubleBufferedWriter::with_capacity(4, Cursor::empty(Vec::new()));
+        for i in 0..40u8 {
+            writer.write_all(&[i, i]).unwrap();
+        }
+        writer.write_all(&[0xFF; 9]).unwrap();
+        let sink = writer.finish().unwrap();
+
+        let mut expected: Vec<u8> = (0..40u8).flat_map(|i| [i, i]).collect();
+        expected.extend_from_slice(&[0xFF; 9]);
+        assert_eq!(sink.data(), expected);
+    }
+
+    #[test]
+    fn flush_writes_both_buffers() {
+        let data = Arc::new(Mutex::new(Vec::new()));
+        let mut writer = DoubleBufferedWriter::with_capacity(8, Shared(data.clone()));
+        writer.write_all(b"hello, world").unwrap();
+        writer.flush().unwrap();
+        assert_eq!(data.lock().as_slice(), b"hello, world".as_slice());
+
+        writer.write_all(b"!").unwrap();
+        drop(writer);
+        assert_eq!(data.lock().as_slice(), b"hello, world!".as_slice());
+    }
+
+    #[test]
+    fn reports_deferred_errors() {
+        let mut writer = DoubleBufferedWriter::with_capacity(4, Failing { limit: 6 });
+        let mut result = Ok(());
+        for _ in 0..16 {
+            result = writer.write_all(b"ab");
+            if result.is_err() {
+                break;
+            }
+        }
+        assert!(matches!(result, Err(Error::Denied)));
+        assert!(matches!(writer.flush(), Err(Error::Denied)));
+        assert!(matches!(writer.finish(), Err(Error::Denied)));
+    }
+
+    #[test]
+    fn finish_reports_last_error() {
+        let mut writer = DoubleBufferedWriter::with_capacity(4, Failing { limit: 2 });
+        writer.write_all(b"abc").unwrap();
+        assert!(matches!(writer.finish(), Err(Error::Denied)));
+    }
+
+    #[cfg(feature = "embedded-io")]
+    #[test]
+    fn implements_embedded_io() {
+        let data = Arc::new(Mutex::new(Vec::new()));
+        let mut writer = DoubleBufferedWriter::with_capacity(4, Shared(data.clone()));
+        embedded_io::Write::write_all(&mut writer, b"hello").unwrap();
+        embedded_io::Write::flush(&mut writer).unwrap();
+        assert_eq!(data.lock().as_slice(), b"hello".as_slice());
+    }
+}
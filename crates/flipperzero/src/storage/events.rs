@@ -0,0 +1,130 @@
+//! Events published by the storage service.
+
+#[cfg(feature = "alloc")]
+use flipperzero_sys as sys;
+
+#[cfg(feature = "alloc")]
+use super::record::StorageRecord;
+#[cfg(feature = "alloc")]
+use crate::furi::pubsub::{RecordSubscription, TypeEvent};
+
+/// An event published by the storage service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum StorageEvent {
+    /// The SD card was inserted and mounted.
+    CardMounted,
+    /// The SD card was unmounted, because it was ejected or removed.
+    CardUnmounted,
+    /// The SD card was inserted, but couldn't be mounted.
+    CardMountError,
+    /// A file was closed.
+    FileClosed,
+    /// A directory was closed.
+    DirClosed,
+    /// An event not known to this crate, with its raw value.
+    Unknown(u8),
+}
+
+impl StorageEvent {
+    #[cfg(feature = "alloc")]
+    pub(crate) fn from_sys(event: &TypeEvent) -> Self {
+        match event.type_ {
+            0 => Self::CardMounted,
+            1 => Self::CardUnmounted,
+            2 => Self::CardMountError,
+            3 => Self::FileClosed,
+            4 => Self::DirClosed,
+            other => Self::Unknown(other),
+        }
+    }
+
+    /// Returns `true` for the events about the SD card being mounted or unmounted.
+    pub fn is_card_event(self) -> bool {
+        matches!(
+            self,
+            Self::CardMounted | Self::CardUnmounted | Self::CardMountError
+        )
+    }
+}
+
+/// A subscription to the events of the storage service, created by
+/// [`Storage::subscribe`](super::Storage::subscribe).
+///
+/// The subscription ends when this is dropped.
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub struct StorageSubscription {
+    _subscription: RecordSubscription<StorageRecord, TypeEvent>,
+}
+
+#[cfg(feature = "alloc")]
+impl StorageSubscription {
+    pub(crate) fn new<F>(record: StorageRecord, callback: F) -> Self
+    where
+        F: FnMut(StorageEvent) + Send + 'static,
+    {
+        let pubsub = unsafe { sys::storage_get_pubsub(record.as_ptr()) };
+        Self {
+            _subscription: unsafe {
+                RecordSubscription::new(record, pubsub, StorageEvent::from_sys, callback)
+            },
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[flipperzero_test::tests]
+mod tests {
+    use super::StorageEvent;
+    use crate::furi::pubsub::TypeEvent;
+
+    #[test]
+    fn events_map_from_sys() {
+        let events = [
+            StorageEvent::CardMounted,
+            StorageEvent::CardUnmounted,
+            StorageEvent::CardMountError,
+            StorageEvent::FileClosed,
+            StorageEvent::DirClosed,
+            StorageEvent::Unknown(5),
+        ];
+        for (type_, event) in events.into_iter().enumerate() {
+            let raw = TypeEvent { type_: type_ as u8 };
+            assert_eq!(StorageEvent::from_sys(&raw), event);
+        }
+        assert!(StorageEvent::CardUnmounted.is_card_event());
+        assert!(!StorageEvent::FileClosed.is_card_event());
+    }
+
+    #[test]
+    fn closing_files_is_published() {
+        use alloc::sync::Arc;
+        use core::sync::atomic::{AtomicUsize, Ordering};
+
+        use crate::storage::{File, Storage};
+
+        let storage = Storage::open();
+        storage.create_dir("/ext/.tmp").ok();
+        let path = "/ext/.tmp/rs_storage_events";
+
+        let closed = Arc::new(AtomicUsize::new(0));
+        let subscription = storage.subscribe({
+            let closed = closed.clone();
+            move |event| {
+                if event == StorageEvent::FileClosed {
+                    closed.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        });
+        File::create(path).unwrap().close().unwrap();
+        assert!(closed.load(Ordering::Relaxed) >= 1);
+
+        // The callback is freed without being called again.
+        drop(subscription);
+        File::open(path).unwrap().close().unwrap();
+        assert_eq!(Arc::strong_count(&closed), 1);
+
+        storage.remove(path).unwrap();
+    }
+}
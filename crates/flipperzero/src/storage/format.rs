@@ -0,0 +1,636 @@
+//! FlipperFormat files, the `Key: value` text files used by most apps of the firmware.
+
+use core::ffi::CStr;
+use core::fmt;
+use core::ptr::NonNull;
+
+use flipperzero_sys as sys;
+
+use super::path::AsPath;
+use super::record::StorageRecord;
+use crate::furi::string::FuriString;
+use crate::io::Error;
+
+/// Reasons why reading or writing a [`FlipperFormat`] failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FormatError {
+    /// The key wasn't found after the current position.
+    MissingKey,
+    /// The value of the key couldn't be read as the requested type or number of values,
+    /// or the values to write don't fit into one line.
+    InvalidValue,
+    /// The underlying file failed.
+    Io(Error),
+}
+
+impl FormatError {
+    /// Description associated with [`FormatError`].
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::MissingKey => "key not found",
+            Self::InvalidValue => "invalid value",
+            Self::Io(_) => "I/O error",
+        }
+    }
+}
+
+impl From<Error> for FormatError {
+    fn from(error: Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl core::error::Error for FormatError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => error.fmt(f),
+            _ => f.write_str(self.description()),
+        }
+    }
+}
+
+impl ufmt::uDisplay for FormatError {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        match self {
+            Self::Io(error) => ufmt::uDisplay::fmt(error, f),
+            _ => f.write_str(self.description()),
+        }
+    }
+}
+
+/// What a [`FlipperFormat`] reads from and writes to.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Backend {
+    File,
+    /// A file read through a buffer, which is opened read-only.
+    BufferedFile,
+    String,
+}
+
+/// A FlipperFormat file, such as a `.sub`, `.nfc` or `.ir` file.
+///
+/// The file starts with a header of a file type and a version, followed by one
+/// `Key: value` line per key. Values are strings, or lists of numbers or bytes
+/// separated by spaces. Reads search for their key from the current position onwards,
+/// so keys are best read in the order they were written, or after a
+/// [`FlipperFormat::rewind`].
+///
+/// The file is closed and the instance freed when the `FlipperFormat` is dropped.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use flipperzero::storage::{FlipperFormat, FormatError};
+/// # fn main() -> Result<(), FormatError> {
+/// let mut file = FlipperFormat::open_read(c"/ext/subghz/remote.sub")?;
+/// let (file_type, version) = file.read_header()?;
+/// let frequency = file.read_u32(c"Frequency")?;
+/// let preset = file.read_string(c"Preset")?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct FlipperFormat {
+    raw: NonNull<sys::FlipperFormat>,
+    backend: Backend,
+    /// Keeps the storage service open for file backends.
+    _record: Option<StorageRecord>,
+}
+
+impl FlipperFormat {
+    /// Allocates an instance over a file, which then has to be opened with `open`.
+    fn file(buffered: bool) -> Self {
+        let record = StorageRecord::open();
+        let raw = unsafe {
+            if buffered {
+                sys::flipper_format_buffered_file_alloc(record.as_ptr())
+            } else {
+                sys::flipper_format_file_alloc(record.as_ptr())
+            }
+        };
+        Self {
+            raw: unsafe { NonNull::new_unchecked(raw) },
+            backend: if buffered {
+                Backend::BufferedFile
+            } else {
+                Backend::File
+            },
+            _record: Some(record),
+        }
+    }
+
+    /// Opens the file at `path` with `open`, one of the `flipper_format_*_open_*`
+    /// functions.
+    fn open<P: AsPath + ?Sized>(
+        path: &P,
+        buffered: bool,
+        open: unsafe extern "C" fn(*mut sys::FlipperFormat, *const core::ffi::c_char) -> bool,
+    ) -> Result<Self, Error> {
+        path.with_c_str(|path| {
+            let file = Self::file(buffered);
+            if unsafe { open(file.as_ptr(), path.as_ptr()) } {
+                Ok(file)
+            } else {
+                Err(file.stream_error().unwrap_or(Error::Internal))
+            }
+        })
+    }
+
+    /// Opens the existing file at `path` for reading.
+    ///
+    /// Reads go through a buffer, which is faster than [`FlipperFormat::open_existing`],
+    /// and writes fail.
+    pub fn open_read<P: AsPath + ?Sized>(path: &P) -> Result<Self, Error> {
+        Self::open(path, true, sys::flipper_format_buffered_file_open_existing)
+    }
+
+    /// Opens the existing file at `path` for reading and writing.
+    pub fn open_existing<P: AsPath + ?Sized>(path: &P) -> Result<Self, Error> {
+        Self::open(path, false, sys::flipper_format_file_open_existing)
+    }
+
+    /// Opens the existing file at `path` for adding keys to its end.
+    pub fn open_append<P: AsPath + ?Sized>(path: &P) -> Result<Self, Error> {
+        Self::open(path, false, sys::flipper_format_file_open_append)
+    }
+
+    /// Creates the file at `path`, or empties it if it exists, for writing.
+    pub fn open_always<P: AsPath + ?Sized>(path: &P) -> Result<Self, Error> {
+        Self::open(path, false, sys::flipper_format_file_open_always)
+    }
+
+    /// Creates the file at `path` for writing, failing if it exists.
+    pub fn open_new<P: AsPath + ?Sized>(path: &P) -> Result<Self, Error> {
+        Self::open(path, false, sys::flipper_format_file_open_new)
+    }
+
+    /// Creates an empty FlipperFormat kept in memory, such as for building a file
+    /// before saving it, or for parsing text received from elsewhere.
+    pub fn in_memory() -> Self {
+        Self {
+            raw: unsafe { NonNull::new_unchecked(sys::flipper_format_string_alloc()) },
+            backend: Backend::String,
+            _record: None,
+        }
+    }
+
+    /// Returns the raw pointer to the FlipperFormat instance.
+    pub fn as_ptr(&self) -> *mut sys::FlipperFormat {
+        self.raw.as_ptr()
+    }
+
+    fn stream(&self) -> *mut sys::Stream {
+        unsafe { sys::flipper_format_get_raw_stream(self.as_ptr()) }
+    }
+
+    /// Returns the error of the last operation on the underlying file, if any.
+    fn stream_error(&self) -> Option<Error> {
+        let status = match self.backend {
+            Backend::File => unsafe { sys::file_stream_get_error(self.stream()) },
+            Backend::BufferedFile => unsafe { sys::buffered_file_stream_get_error(self.stream()) },
+            Backend::String => sys::FS_Error_FSE_OK,
+        };
+        Error::try_from_sys(status)
+    }
+
+    /// Turns the result of a write into the error of the underlying file, if it failed.
+    fn check_write(&self, ok: bool) -> Result<(), FormatError> {
+        if ok {
+            return Ok(());
+        }
+        // Formatting errors leave no error on the file.
+        Err(FormatError::Io(
+            self.stream_error().unwrap_or(Error::Internal),
+        ))
+    }
+
+    /// Reads the value of `key` with `read`, telling apart why it failed if it did.
+    fn read_value(
+        &mut self,
+        key: &CStr,
+        read: impl FnOnce(*mut sys::FlipperFormat) -> bool,
+    ) -> Result<(), FormatError> {
+        let position = unsafe { sys::stream_tell(self.stream()) };
+        if read(self.as_ptr()) {
+            return Ok(());
+        }
+        if let Some(error) = self.stream_error() {
+            return Err(FormatError::Io(error));
+        }
+        // A failed read may have moved past the key, so look for it from where the
+        // read started.
+        let position = i32::try_from(position).map_err(|_| FormatError::InvalidValue)?;
+        unsafe {
+            sys::stream_seek(
+                self.stream(),
+                position,
+                sys::StreamOffset_StreamOffsetFromStart,
+            )
+        };
+        match self.value_count(key) {
+            Ok(_) => Err(FormatError::InvalidValue),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Closes the file, returning whether the last writes reached it.
+    ///
+    /// Dropping the `FlipperFormat` also closes the file, but ignores errors.
+    pub fn close(self) -> Result<(), Error> {
+        let closed = unsafe {
+            match self.backend {
+                Backend::File => sys::flipper_format_file_close(self.as_ptr()),
+                Backend::BufferedFile => sys::flipper_format_buffered_file_close(self.as_ptr()),
+                Backend::String => true,
+            }
+        };
+        if closed {
+            Ok(())
+        } else {
+            Err(self.stream_error().unwrap_or(Error::Internal))
+        }
+    }
+
+    /// Sets whether reads fail at the first key that isn't the one they look for,
+    /// rather than skipping ahead to it.
+    pub fn set_strict(&mut self, strict: bool) {
+        unsafe { sys::flipper_format_set_strict_mode(self.as_ptr(), strict) };
+    }
+
+    /// Moves back to the start of the file, so keys before the current position can be
+    /// read.
+    pub fn rewind(&mut self) -> Result<(), FormatError> {
+        let ok = unsafe { sys::flipper_format_rewind(self.as_ptr()) };
+        self.check_write(ok)
+    }
+
+    /// Moves to the end of the file, such as for adding keys after reading.
+    pub fn seek_to_end(&mut self) -> Result<(), FormatError> {
+        let ok = unsafe { sys::flipper_format_seek_to_end(self.as_ptr()) };
+        self.check_write(ok)
+    }
+
+    /// Returns `true` if `key` is anywhere in the file.
+    pub fn contains_key(&mut self, key: &CStr) -> bool {
+        unsafe { sys::flipper_format_key_exist(self.as_ptr(), key.as_ptr()) }
+    }
+
+    /// Returns the number of values of `key`, without moving past it.
+    pub fn value_count(&mut self, key: &CStr) -> Result<u32, FormatError> {
+        let mut count = 0;
+        if unsafe { sys::flipper_format_get_value_count(self.as_ptr(), key.as_ptr(), &mut count) } {
+            Ok(count)
+        } else {
+            Err(self
+                .stream_error()
+                .map_or(FormatError::MissingKey, FormatError::Io))
+        }
+    }
+
+    /// Returns an iterator over the keys from the current position onwards, which
+    /// moves to the end of the file.
+    ///
+    /// The header is made of the keys `Filetype` and `Version`. Comments and lines
+    /// without a key are skipped.
+    pub fn keys(&mut self) -> Keys<'_> {
+        Keys { format: self }
+    }
+
+    /// Reads the header, returning the file type and version.
+    pub fn read_header(&mut self) -> Result<(FuriString, u32), FormatError> {
+        let mut file_type = FuriString::new();
+        let mut version = 0;
+        self.read_value(c"Filetype", |ff| unsafe {
+            sys::flipper_format_read_header(ff, file_type.as_mut_ptr(), &mut version)
+        })?;
+        Ok((file_type, version))
+    }
+
+    /// Writes the header, which should be the first thing in the file.
+    pub fn write_header(&mut self, file_type: &CStr, version: u32) -> Result<(), FormatError> {
+        let ok = unsafe {
+            sys::flipper_format_write_header_cstr(self.as_ptr(), file_type.as_ptr(), version)
+        };
+        self.check_write(ok)
+    }
+
+    /// Writes a comment line, starting with `#`.
+    pub fn write_comment(&mut self, comment: &CStr) -> Result<(), FormatError> {
+        let ok = unsafe { sys::flipper_format_write_comment_cstr(self.as_ptr(), comment.as_ptr()) };
+        self.check_write(ok)
+    }
+
+    /// Reads the string value of `key`, which is the rest of its line.
+    pub fn read_string(&mut self, key: &CStr) -> Result<FuriString, FormatError> {
+        let mut value = FuriString::new();
+        self.read_value(key, |ff| unsafe {
+            sys::flipper_format_read_string(ff, key.as_ptr(), value.as_mut_ptr())
+        })?;
+        Ok(value)
+    }
+
+    /// Writes `value` as the string value of `key`.
+    pub fn write_string(&mut self, key: &CStr, value: &CStr) -> Result<(), FormatError> {
+        let ok = unsafe {
+            sys::flipper_format_write_string_cstr(self.as_ptr(), key.as_ptr(), value.as_ptr())
+        };
+        self.check_write(ok)
+    }
+
+    /// Reads the single number of `key`.
+    pub fn read_u32(&mut self, key: &CStr) -> Result<u32, FormatError> {
+        let mut value = [0];
+        self.read_u32s(key, &mut value)?;
+        Ok(value[0])
+    }
+
+    /// Writes `value` as the single number of `key`.
+    pub fn write_u32(&mut self, key: &CStr, value: u32) -> Result<(), FormatError> {
+        self.write_u32s(key, &[value])
+    }
+
+    /// Reads the numbers of `key`, which must be exactly as many as fit into `values`.
+    pub fn read_u32s(&mut self, key: &CStr, values: &mut [u32]) -> Result<(), FormatError> {
+        let len = value_len(values.len())?;
+        self.read_value(key, |ff| unsafe {
+            sys::flipper_format_read_uint32(ff, key.as_ptr(), values.as_mut_ptr(), len)
+        })
+    }
+
+    /// Writes `values` as the numbers of `key`.
+    pub fn write_u32s(&mut self, key: &CStr, values: &[u32]) -> Result<(), FormatError> {
+        let len = value_len(values.len())?;
+        let ok = unsafe {
+            sys::flipper_format_write_uint32(self.as_ptr(), key.as_ptr(), values.as_ptr(), len)
+        };
+        self.check_write(ok)
+    }
+
+    /// Reads the single signed number of `key`.
+    pub fn read_i32(&mut self, key: &CStr) -> Result<i32, FormatError> {
+        let mut value = [0];
+        self.read_i32s(key, &mut value)?;
+        Ok(value[0])
+    }
+
+    /// Writes `value` as the single signed number of `key`.
+    pub fn write_i32(&mut self, key: &CStr, value: i32) -> Result<(), FormatError> {
+        self.write_i32s(key, &[value])
+    }
+
+    /// Reads the signed numbers of `key`, which must be exactly as many as fit into
+    /// `values`.
+    pub fn read_i32s(&mut self, key: &CStr, values: &mut [i32]) -> Result<(), FormatError> {
+        let len = value_len(values.len())?;
+        self.read_value(key, |ff| unsafe {
+            sys::flipper_format_read_int32(ff, key.as_ptr(), values.as_mut_ptr(), len)
+        })
+    }
+
+    /// Writes `values` as the signed numbers of `key`.
+    pub fn write_i32s(&mut self, key: &CStr, values: &[i32]) -> Result<(), FormatError> {
+        let len = value_len(values.len())?;
+        let ok = unsafe {
+            sys::flipper_format_write_int32(self.as_ptr(), key.as_ptr(), values.as_ptr(), len)
+        };
+        self.check_write(ok)
+    }
+
+    /// Reads the single flag of `key`, written as `true` or `false`.
+    pub fn read_bool(&mut self, key: &CStr) -> Result<bool, FormatError> {
+        let mut value = [false];
+        self.read_bools(key, &mut value)?;
+        Ok(value[0])
+    }
+
+    /// Writes `value` as the single flag of `key`.
+    pub fn write_bool(&mut self, key: &CStr, value: bool) -> Result<(), FormatError> {
+        self.write_bools(key, &[value])
+    }
+
+    /// Reads the flags of `key`, which must be exactly as many as fit into `values`.
+    pub fn read_bools(&mut self, key: &CStr, values: &mut [bool]) -> Result<(), FormatError> {
+        let len = value_len(values.len())?;
+        self.read_value(key, |ff| unsafe {
+            sys::flipper_format_read_bool(ff, key.as_ptr(), values.as_mut_ptr(), len)
+        })
+    }
+
+    /// Writes `values` as the flags of `key`.
+    pub fn write_bools(&mut self, key: &CStr, values: &[bool]) -> Result<(), FormatError> {
+        let len = value_len(values.len())?;
+        let ok = unsafe {
+            sys::flipper_format_write_bool(self.as_ptr(), key.as_ptr(), values.as_ptr(), len)
+        };
+        self.check_write(ok)
+    }
+
+    /// Reads the decimal numbers of `key`, which must be exactly as many as fit into
+    /// `values`.
+    pub fn read_f32s(&mut self, key: &CStr, values: &mut [f32]) -> Result<(), FormatError> {
+        let len = value_len(values.len())?;
+        self.read_value(key, |ff| unsafe {
+            sys::flipper_format_read_float(ff, key.as_ptr(), values.as_mut_ptr(), len)
+        })
+    }
+
+    /// Writes `values` as the decimal numbers of `key`.
+    pub fn write_f32s(&mut self, key: &CStr, values: &[f32]) -> Result<(), FormatError> {
+        let len = value_len(values.len())?;
+        let ok = unsafe {
+            sys::flipper_format_write_float(self.as_ptr(), key.as_ptr(), values.as_ptr(), len)
+        };
+        self.check_write(ok)
+    }
+
+    /// Reads the bytes of `key`, written as hex such as `A0 1F`, which must be exactly
+    /// as many as fit into `bytes`.
+    pub fn read_hex(&mut self, key: &CStr, bytes: &mut [u8]) -> Result<(), FormatError> {
+        let len = value_len(bytes.len())?;
+        self.read_value(key, |ff| unsafe {
+            sys::flipper_format_read_hex(ff, key.as_ptr(), bytes.as_mut_ptr(), len)
+        })
+    }
+
+    /// Writes `bytes` as hex as the value of `key`.
+    pub fn write_hex(&mut self, key: &CStr, bytes: &[u8]) -> Result<(), FormatError> {
+        let len = value_len(bytes.len())?;
+        let ok = unsafe {
+            sys::flipper_format_write_hex(self.as_ptr(), key.as_ptr(), bytes.as_ptr(), len)
+        };
+        self.check_write(ok)
+    }
+}
+
+impl Drop for FlipperFormat {
+    fn drop(&mut self) {
+        // Freeing also closes the file if it is still open.
+        unsafe { sys::flipper_format_free(self.as_ptr()) };
+    }
+}
+
+/// Returns the number of values the firmware is passed for `len`, at most `u16::MAX`.
+fn value_len(len: usize) -> Result<u16, FormatError> {
+    u16::try_from(len).map_err(|_| FormatError::InvalidValue)
+}
+
+/// The keys of a [`FlipperFormat`], as returned by [`FlipperFormat::keys`].
+pub struct Keys<'a> {
+    format: &'a mut FlipperFormat,
+}
+
+impl Iterator for Keys<'_> {
+    type Item = Result<FuriString, FormatError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut line = FuriString::new();
+        loop {
+            if !unsafe { sys::stream_read_line(self.format.stream(), line.as_mut_ptr()) } {
+                return self.format.stream_error().map(|e| Err(FormatError::Io(e)));
+            }
+            if line.starts_with('#') {
+                continue;
+            }
+            if let Some(end) = line.find(':') {
+                line.truncate(end);
+                return Some(Ok(line));
+            }
+        }
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use super::{FlipperFormat, FormatError};
+    use crate::io::Error;
+    use crate::storage::Storage;
+
+    /// Returns a FlipperFormat holding a small `.sub`-style file, rewound to its start.
+    fn sample() -> FlipperFormat {
+        let mut file = FlipperFormat::in_memory();
+        file.write_header(c"Flipper SubGhz Key File", 1).unwrap();
+        file.write_comment(c"Made by the tests").unwrap();
+        file.write_u32(c"Frequency", 433920000).unwrap();
+        file.write_string(c"Preset", c"FuriHalSubGhzPresetOok650Async")
+            .unwrap();
+        file.write_hex(c"Key", &[0x00, 0xA1, 0xFF]).unwrap();
+        file.write_f32s(c"Levels", &[0.5, -1.25]).unwrap();
+        file.rewind().unwrap();
+        file
+    }
+
+    #[test]
+    fn reads_back_what_was_written() {
+        let mut file = sample();
+        let (file_type, version) = file.read_header().unwrap();
+        assert_eq!(file_type, "Flipper SubGhz Key File");
+        assert_eq!(version, 1);
+        assert_eq!(file.read_u32(c"Frequency"), Ok(433920000));
+        assert_eq!(
+            file.read_string(c"Preset").unwrap(),
+            "FuriHalSubGhzPresetOok650Async"
+        );
+        let mut key = [0; 3];
+        file.read_hex(c"Key", &mut key).unwrap();
+        assert_eq!(key, [0x00, 0xA1, 0xFF]);
+        let mut levels = [0.0; 2];
+        file.read_f32s(c"Levels", &mut levels).unwrap();
+        assert_eq!(levels, [0.5, -1.25]);
+    }
+
+    #[test]
+    fn missing_keys_and_wrong_types_are_told_apart() {
+        let mut file = sample();
+        assert_eq!(file.read_u32(c"Protocol"), Err(FormatError::MissingKey));
+        file.rewind().unwrap();
+        assert_eq!(file.read_u32(c"Preset"), Err(FormatError::InvalidValue));
+        // The failed read didn't skip the key.
+        assert!(file.read_string(c"Preset").is_ok());
+
+        let mut key = [0; 4];
+        assert_eq!(
+            file.read_hex(c"Key", &mut key),
+            Err(FormatError::InvalidValue)
+        );
+        assert_eq!(file.value_count(c"Key"), Ok(3));
+
+        // Reads only search forwards.
+        assert_eq!(file.read_u32(c"Frequency"), Err(FormatError::MissingKey));
+        assert!(file.contains_key(c"Frequency"));
+    }
+
+    #[test]
+    fn reads_back_signed_numbers_and_flags() {
+        let mut file = FlipperFormat::in_memory();
+        file.write_i32(c"Offset", -40).unwrap();
+        file.write_i32s(c"Deltas", &[3, -7, i32::MIN]).unwrap();
+        file.write_bool(c"Enabled", true).unwrap();
+        file.write_bools(c"Channels", &[false, true]).unwrap();
+        file.rewind().unwrap();
+
+        assert_eq!(file.read_i32(c"Offset"), Ok(-40));
+        let mut deltas = [0; 3];
+        file.read_i32s(c"Deltas", &mut deltas).unwrap();
+        assert_eq!(deltas, [3, -7, i32::MIN]);
+        assert_eq!(file.read_bool(c"Enabled"), Ok(true));
+        let mut channels = [true; 2];
+        file.read_bools(c"Channels", &mut channels).unwrap();
+        assert_eq!(channels, [false, true]);
+        // Reads only search forwards.
+        assert_eq!(file.read_i32(c"Offset"), Err(FormatError::MissingKey));
+    }
+
+    #[test]
+    fn iterates_over_keys() {
+        let mut file = sample();
+        let mut keys = file.keys();
+        for expected in [
+            "Filetype",
+            "Version",
+            "Frequency",
+            "Preset",
+            "Key",
+            "Levels",
+        ] {
+            assert_eq!(keys.next().unwrap().unwrap(), expected);
+        }
+        assert!(keys.next().is_none());
+    }
+
+    #[test]
+    fn read_only_files_reject_writes() {
+        let path = c"/ext/.tmp/rs_flipper_format.sub";
+        let storage = Storage::open();
+        storage.create_dir("/ext/.tmp").ok();
+        let mut file = FlipperFormat::open_always(path).unwrap();
+        file.write_header(c"Flipper SubGhz Key File", 1).unwrap();
+        file.write_u32(c"Frequency", 315000000).unwrap();
+        file.close().unwrap();
+
+        let mut file = FlipperFormat::open_read(path).unwrap();
+        assert!(matches!(
+            file.write_u32(c"Bit", 24),
+            Err(FormatError::Io(_))
+        ));
+        file.rewind().unwrap();
+        assert_eq!(file.read_header().unwrap().1, 1);
+        assert_eq!(file.read_u32(c"Frequency"), Ok(315000000));
+        drop(file);
+
+        assert!(FlipperFormat::open_new(path).is_err());
+        storage.remove(path).unwrap();
+        assert_eq!(FlipperFormat::open_read(path).err(), Some(Error::NotExists));
+    }
+}
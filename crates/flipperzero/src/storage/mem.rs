@@ -0,0 +1,530 @@
+//! An in-memory storage backend for tests.
+
+use alloc::collections::BTreeMap;
+use alloc::ffi::CString;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::cell::{Cell, RefCell};
+use core::ffi::CStr;
+
+use flipperzero_sys as sys;
+
+use super::backend::{EntryInfo, StorageBackend};
+use super::{Metadata, OpenOptions};
+use crate::io::{Error, Read, Seek, SeekFrom, Write};
+
+/// The SD card, which [`MemStorage::eject`] removes.
+const EXT: &[u8] = b"/ext";
+
+/// What `MemStorage` holds at a path.
+enum Node {
+    Dir,
+    File(Rc<RefCell<Vec<u8>>>),
+}
+
+/// The state shared by a [`MemStorage`] and its files.
+struct State {
+    nodes: RefCell<BTreeMap<Vec<u8>, Node>>,
+    ejected: Cell<bool>,
+    capacity: Cell<Option<u64>>,
+    next_error: Cell<Option<Error>>,
+}
+
+impl State {
+    /// Fails if an error was injected, or if `path` is on the SD card while it is
+    /// ejected.
+    fn check(&self, path: &[u8]) -> Result<(), Error> {
+        if let Some(e) = self.next_error.take() {
+            return Err(e);
+        }
+        if self.ejected.get() && is_under(path, EXT) {
+            return Err(Error::NotReady);
+        }
+        Ok(())
+    }
+
+    /// Returns the number of bytes that can still be written.
+    fn free_space(&self) -> u64 {
+        let Some(capacity) = self.capacity.get() else {
+            return u64::MAX;
+        };
+        let used: u64 = self
+            .nodes
+            .borrow()
+            .values()
+            .map(|node| match node {
+                Node::Dir => 0,
+                Node::File(data) => data.borrow().len() as u64,
+            })
+            .sum();
+        capacity.saturating_sub(used)
+    }
+}
+
+/// A [`StorageBackend`] that keeps a tree of files and directories in memory.
+///
+/// It starts out with empty `/ext` and `/int` directories. Besides the operations
+/// of the trait, it can simulate failures of the SD card:
+///
+/// - [`MemStorage::eject`] makes every operation on `/ext`, including on files that are
+///   already open, fail with [`Error::NotReady`] until [`MemStorage::insert`].
+/// - [`MemStorage::set_capacity`] limits the bytes the files may take up in total. Like
+///   on a full SD card, writes then write fewer bytes than asked, down to none.
+/// - [`MemStorage::fail_next`] makes the next operation fail with a given error.
+///
+/// Clones of a `MemStorage` share the same tree.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use flipperzero::io::{Error, Write};
+/// # use flipperzero::storage::{MemStorage, OpenOptions, StorageBackend};
+/// let storage = MemStorage::new();
+/// let options = OpenOptions::new().write(true).create_new(true);
+/// let mut file = storage.open(c"/ext/log.txt", options).unwrap();
+/// storage.eject();
+/// assert!(matches!(file.write(b"hello"), Err(Error::NotReady)));
+/// ```
+#[derive(Clone)]
+pub struct MemStorage {
+    state: Rc<State>,
+}
+
+impl MemStorage {
+    /// Creates a tree with empty `/ext` and `/int` directories.
+    pub fn new() -> Self {
+        let mut nodes = BTreeMap::new();
+        nodes.insert(b"/".to_vec(), Node::Dir);
+        nodes.insert(EXT.to_vec(), Node::Dir);
+        nodes.insert(b"/int".to_vec(), Node::Dir);
+        Self {
+            state: Rc::new(State {
+                nodes: RefCell::new(nodes),
+                ejected: Cell::new(false),
+                capacity: Cell::new(None),
+                next_error: Cell::new(None),
+            }),
+        }
+    }
+
+    /// Simulates removing the SD card.
+    pub fn eject(&self) {
+        self.state.ejected.set(true);
+    }
+
+    /// Simulates inserting the SD card again, with the same contents.
+    pub fn insert(&self) {
+        self.state.ejected.set(false);
+    }
+
+    /// Limits the bytes all files may take up together, or removes the limit.
+    pub fn set_capacity(&self, bytes: Option<u64>) {
+        self.state.capacity.set(bytes);
+    }
+
+    /// Makes the next operation on the storage or on one of its files fail with
+    /// `error`.
+    pub fn fail_next(&self, error: Error) {
+        self.state.next_error.set(Some(error));
+    }
+
+    /// Returns the contents of the file at `path`, if there is one.
+    pub fn contents(&self, path: &CStr) -> Option<Vec<u8>> {
+        match self.state.nodes.borrow().get(path.to_bytes()) {
+            Some(Node::File(data)) => Some(data.borrow().clone()),
+            _ => None,
+        }
+    }
+
+    /// Fails unless the parent directory of `path` exists.
+    fn check_parent(&self, path: &[u8]) -> Result<(), Error> {
+        match self.state.nodes.borrow().get(parent(path)?) {
+            Some(Node::Dir) => Ok(()),
+            Some(Node::File(_)) => Err(Error::InvalidName),
+            None => Err(Error::NotExists),
+        }
+    }
+}
+
+impl Default for MemStorage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StorageBackend for MemStorage {
+    type File = MemFile;
+    type ReadDir = alloc::vec::IntoIter<Result<EntryInfo, Error>>;
+
+    fn open(&self, path: &CStr, options: OpenOptions) -> Result<MemFile, Error> {
+        let path = path.to_bytes();
+        self.state.check(path)?;
+        self.check_parent(path)?;
+
+        options.validate()?;
+        let mode = options.canonical_open_mode();
+        let mut nodes = self.state.nodes.borrow_mut();
+        let data = match nodes.get(path) {
+            Some(Node::Dir) => return Err(Error::Denied),
+            Some(Node::File(_)) if mode == sys::FS_OpenMode_FSOM_CREATE_NEW => {
+                return Err(Error::Exists)
+            }
+            Some(Node::File(data)) => {
+                if mode == sys::FS_OpenMode_FSOM_CREATE_ALWAYS {
+                    data.borrow_mut().clear();
+                }
+                data.clone()
+            }
+            None if mode == sys::FS_OpenMode_FSOM_OPEN_EXISTING => return Err(Error::NotExists),
+            None => {
+                let data = Rc::new(RefCell::new(Vec::new()));
+                nodes.insert(path.to_vec(), Node::File(data.clone()));
+                data
+            }
+        };
+
+        let position = if mode == sys::FS_OpenMode_FSOM_OPEN_APPEND {
+            data.borrow().len()
+        } else {
+            0
+        };
+        Ok(MemFile {
+            state: self.state.clone(),
+            on_ext: is_under(path, EXT),
+            data,
+            position,
+            readable: options.access_mode() & sys::FS_AccessMode_FSAM_READ != 0,
+            writable: options.access_mode() & sys::FS_AccessMode_FSAM_WRITE != 0,
+        })
+    }
+
+    fn metadata(&self, path: &CStr) -> Result<Metadata, Error> {
+        let path = path.to_bytes();
+        self.state.check(path)?;
+        match self.state.nodes.borrow().get(path) {
+            Some(Node::Dir) => Ok(Metadata::new(true, 0)),
+            Some(Node::File(data)) => Ok(Metadata::new(false, data.borrow().len() as u64)),
+            None => Err(Error::NotExists),
+        }
+    }
+
+    fn read_dir(&self, path: &CStr) -> Result<Self::ReadDir, Error> {
+        let path = path.to_bytes();
+        self.state.check(path)?;
+        let nodes = self.state.nodes.borrow();
+        match nodes.get(path) {
+            Some(Node::Dir) => {}
+            Some(Node::File(_)) => return Err(Error::InvalidParameter),
+            None => return Err(Error::NotExists),
+        }
+
+        let entries: Vec<_> = nodes
+            .iter()
+            .filter(|(child, _)| parent(child).ok() == Some(path) && child.as_slice() != path)
+            .map(|(child, node)| {
+                let name = CString::new(&child[child.len() - name_len(child)..]).unwrap();
+                let metadata = match node {
+                    Node::Dir => Metadata::new(true, 0),
+                    Node::File(data) => Metadata::new(false, data.borrow().len() as u64),
+                };
+                Ok(EntryInfo::new(name, metadata))
+            })
+            .collect();
+        Ok(entries.into_iter())
+    }
+
+    fn create_dir(&self, path: &CStr) -> Result<(), Error> {
+        let path = path.to_bytes();
+        self.state.check(path)?;
+        self.check_parent(path)?;
+        let mut nodes = self.state.nodes.borrow_mut();
+        if nodes.contains_key(path) {
+            return Err(Error::Exists);
+        }
+        nodes.insert(path.to_vec(), Node::Dir);
+        Ok(())
+    }
+
+    fn remove(&self, path: &CStr) -> Result<(), Error> {
+        let path = path.to_bytes();
+        self.state.check(path)?;
+        let mut nodes = self.state.nodes.borrow_mut();
+        match nodes.get(path) {
+            None => return Err(Error::NotExists),
+            Some(Node::Dir) if nodes.keys().any(|key| is_inside(key, path)) => {
+                return Err(Error::Denied)
+            }
+            Some(_) => {}
+        }
+        nodes.remove(path);
+        Ok(())
+    }
+
+    fn rename(&self, from: &CStr, to: &CStr) -> Result<(), Error> {
+        let (from, to) = (from.to_bytes(), to.to_bytes());
+        self.state.check(from)?;
+        self.state.check(to)?;
+        self.check_parent(to)?;
+        if is_inside(to, from) {
+            return Err(Error::InvalidParameter);
+        }
+        let mut nodes = self.state.nodes.borrow_mut();
+        if !nodes.contains_key(from) {
+            return Err(Error::NotExists);
+        }
+        if nodes.contains_key(to) {
+            return Err(Error::Exists);
+        }
+
+        let moved: Vec<Vec<u8>> = nodes
+            .keys()
+            .filter(|key| key.as_slice() == from || is_inside(key, from))
+            .cloned()
+            .collect();
+        for key in moved {
+            let node = nodes.remove(&key).unwrap();
+            let mut new_key = to.to_vec();
+            new_key.extend_from_slice(&key[from.len()..]);
+            nodes.insert(new_key, node);
+        }
+        Ok(())
+    }
+}
+
+/// A file of a [`MemStorage`].
+///
+/// The file keeps working after it is removed or renamed, and sees the writes through
+/// other handles to the same file.
+pub struct MemFile {
+    state: Rc<State>,
+    on_ext: bool,
+    data: Rc<RefCell<Vec<u8>>>,
+    position: usize,
+    readable: bool,
+    writable: bool,
+}
+
+impl MemFile {
+    fn check(&self) -> Result<(), Error> {
+        self.state.check(if self.on_ext { EXT } else { b"" })
+    }
+}
+
+impl Read for MemFile {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        self.check()?;
+        if !self.readable {
+            return Err(Error::Denied);
+        }
+        let data = self.data.borrow();
+        let rest = data.get(self.position..).unwrap_or_default();
+        let n = rest.len().min(buf.len());
+        buf[..n].copy_from_slice(&rest[..n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+impl Write for MemFile {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        self.check()?;
+        if !self.writable {
+            return Err(Error::Denied);
+        }
+        let free = self.state.free_space();
+        let mut data = self.data.borrow_mut();
+        // Overwriting existing bytes takes no extra space.
+        let overwritten = data.len().saturating_sub(self.position).min(buf.len());
+        let grown = usize::try_from(free).unwrap_or(usize::MAX);
+        let n = buf.len().min(overwritten.saturating_add(grown));
+        let end = self.position + n;
+        if data.len() < end {
+            data.resize(end, 0);
+        }
+        data[self.position..end].copy_from_slice(&buf[..n]);
+        self.position = end;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.check()
+    }
+}
+
+impl Seek for MemFile {
+    fn seek(&mut self, pos: SeekFrom) -> Result<usize, Error> {
+        self.check()?;
+        let target = match pos {
+            SeekFrom::Start(offset) => i64::try_from(offset).unwrap_or(i64::MAX),
+            SeekFrom::End(offset) => (self.data.borrow().len() as i64).saturating_add(offset),
+            SeekFrom::Current(offset) => (self.position as i64).saturating_add(offset),
+        };
+        self.position = usize::try_from(target).map_err(|_| Error::InvalidParameter)?;
+        Ok(self.position)
+    }
+}
+
+/// Returns the directory containing `path`.
+fn parent(path: &[u8]) -> Result<&[u8], Error> {
+    match path.iter().rposition(|&b| b == b'/') {
+        Some(0) if path.len() > 1 => Ok(b"/"),
+        Some(i) if i > 0 && i + 1 < path.len() => Ok(&path[..i]),
+        _ => Err(Error::InvalidName),
+    }
+}
+
+/// Returns the length of the last component of `path`.
+fn name_len(path: &[u8]) -> usize {
+    path.iter().rev().take_while(|&&b| b != b'/').count()
+}
+
+/// Returns `true` if `path` is `root` or inside it.
+fn is_under(path: &[u8], root: &[u8]) -> bool {
+    path == root || is_inside(path, root)
+}
+
+/// Returns `true` if `path` is inside the directory `dir`.
+fn is_inside(path: &[u8], dir: &[u8]) -> bool {
+    let dir = dir.strip_suffix(b"/").unwrap_or(dir);
+    path.strip_prefix(dir)
+        .is_some_and(|rest| rest.len() > 1 && rest[0] == b'/')
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use alloc::vec::Vec;
+    use core::ffi::CStr;
+
+    use super::MemStorage;
+    use crate::io::{Error, Read, Seek, SeekFrom, Write};
+    use crate::storage::backend::{create_dir_all, StorageBackend};
+    use crate::storage::{Op, OpenOptions};
+
+    fn create() -> OpenOptions {
+        OpenOptions::new().read(true).write(true).create_new(true)
+    }
+
+    fn names(storage: &MemStorage, dir: &CStr) -> Vec<Vec<u8>> {
+        storage
+            .read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().name().to_bytes().to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn files_keep_their_contents() {
+        let storage = MemStorage::new();
+        let mut file = storage.open(c"/ext/a.txt", create()).unwrap();
+        file.write_all(b"hello, world").unwrap();
+        file.seek(SeekFrom::Start(7)).unwrap();
+        let mut buf = [0; 8];
+        assert_eq!(file.read(&mut buf).unwrap(), 5);
+        assert_eq!(buf[..5], *b"world");
+
+        let metadata = storage.metadata(c"/ext/a.txt").unwrap();
+        assert!(metadata.is_file());
+        assert_eq!(metadata.size(), 12);
+        assert!(matches!(
+            storage.open(c"/ext/a.txt", create()),
+            Err(Error::Exists)
+        ));
+
+        let append = OpenOptions::new().write(true).open_append(true);
+        let mut file = storage.open(c"/ext/a.txt", append).unwrap();
+        file.write_all(b"!").unwrap();
+        assert_eq!(storage.contents(c"/ext/a.txt").unwrap(), b"hello, world!");
+    }
+
+    #[test]
+    fn directories_list_their_children() {
+        let storage = MemStorage::new();
+        storage.create_dir(c"/ext/nfc").unwrap();
+        storage.create_dir(c"/ext/nfc/assets").unwrap();
+        storage.open(c"/ext/nfc/card.nfc", create()).unwrap();
+
+        assert_eq!(
+            names(&storage, c"/ext/nfc"),
+            [b"assets".to_vec(), b"card.nfc".to_vec()]
+        );
+        assert_eq!(names(&storage, c"/"), [b"ext".to_vec(), b"int".to_vec()]);
+        assert!(matches!(
+            storage.create_dir(c"/ext/missing/dir"),
+            Err(Error::NotExists)
+        ));
+        assert!(matches!(storage.remove(c"/ext/nfc"), Err(Error::Denied)));
+    }
+
+    #[test]
+    fn rename_moves_children() {
+        let storage = MemStorage::new();
+        storage.create_dir(c"/ext/old").unwrap();
+        storage.open(c"/ext/old/file", create()).unwrap();
+        storage.rename(c"/ext/old", c"/ext/new").unwrap();
+
+        assert!(!storage.exists(c"/ext/old/file").unwrap());
+        assert!(storage.exists(c"/ext/new/file").unwrap());
+        assert!(matches!(
+            storage.rename(c"/ext/new", c"/ext/new/inner"),
+            Err(Error::InvalidParameter)
+        ));
+    }
+
+    #[test]
+    fn eject_fails_sd_card_operations() {
+        let storage = MemStorage::new();
+        let mut file = storage.open(c"/ext/log", create()).unwrap();
+        let mut internal = storage.open(c"/int/log", create()).unwrap();
+
+        storage.eject();
+        assert!(matches!(file.write(b"x"), Err(Error::NotReady)));
+        assert!(matches!(storage.exists(c"/ext/log"), Err(Error::NotReady)));
+        internal.write_all(b"x").unwrap();
+
+        storage.insert();
+        file.write_all(b"x").unwrap();
+    }
+
+    #[test]
+    fn full_storage_writes_short() {
+        let storage = MemStorage::new();
+        storage.set_capacity(Some(10));
+        let mut file = storage.open(c"/ext/big", create()).unwrap();
+        assert_eq!(file.write(b"0123456789abc").unwrap(), 10);
+        assert!(matches!(file.write_all(b"d"), Err(Error::WriteZero)));
+
+        // Overwriting takes no extra space.
+        file.seek(SeekFrom::Start(0)).unwrap();
+        file.write_all(b"ABC").unwrap();
+        assert_eq!(storage.contents(c"/ext/big").unwrap(), b"ABC3456789");
+    }
+
+    #[test]
+    fn fail_next_fails_one_operation() {
+        let storage = MemStorage::new();
+        storage.fail_next(Error::Internal);
+        assert!(matches!(
+            storage.create_dir(c"/ext/dir"),
+            Err(Error::Internal)
+        ));
+        storage.create_dir(c"/ext/dir").unwrap();
+    }
+
+    #[test]
+    fn create_dir_all_creates_parents() {
+        let storage = MemStorage::new();
+        create_dir_all(&storage, c"/ext/apps_data/demo/cache").unwrap();
+        assert!(storage.metadata(c"/ext/apps_data/demo").unwrap().is_dir());
+        create_dir_all(&storage, c"/ext/apps_data/demo/").unwrap();
+
+        storage.open(c"/ext/file", create()).unwrap();
+        let error = create_dir_all(&storage, c"/ext/file/dir").unwrap_err();
+        assert_eq!(error.error(), Error::Exists);
+        assert_eq!(error.path(), Some(c"/ext/file"));
+
+        storage.eject();
+        let error = create_dir_all(&storage, c"/ext/other").unwrap_err();
+        assert_eq!(error.error(), Error::NotReady);
+        assert_eq!(error.op(), Op::Metadata);
+    }
+}
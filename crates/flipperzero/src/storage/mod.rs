@@ -0,0 +1,2819 @@
+use core::cmp::Ordering;
+use core::ffi::{c_char, c_void, CStr};
+use core::fmt;
+use core::mem::{ManuallyDrop, MaybeUninit};
+use core::ptr::{self, NonNull};
+use core::time::Duration;
+
+use flipperzero_sys as sys;
+
+use crate::furi;
+use crate::furi::string::FuriString;
+use crate::io::*;
+
+use self::record::StorageRecord;
+
+#[cfg(feature = "alloc")]
+pub(crate) mod backend;
+#[cfg(feature = "alloc")]
+pub(crate) mod block_cache;
+#[cfg(feature = "alloc")]
+pub(crate) mod buffered;
+#[cfg(feature = "alloc")]
+pub(crate) mod context;
+#[cfg(feature = "alloc")]
+pub(crate) mod double_buffered;
+pub(crate) mod events;
+pub(crate) mod format;
+#[cfg(feature = "alloc")]
+pub(crate) mod mem;
+pub(crate) mod path;
+pub mod perf;
+#[cfg(feature = "alloc")]
+pub(crate) mod read_ahead;
+pub(crate) mod record;
+#[cfg(feature = "alloc")]
+pub(crate) mod settings;
+#[cfg(all(feature = "std", not(target_os = "none")))]
+pub(crate) mod std_fs;
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub mod tar;
+#[cfg(feature = "alloc")]
+pub(crate) mod walk;
+
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use self::backend::{create_dir_all, EntryInfo, EntryInfos, StorageBackend};
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use self::block_cache::{BlockCache, CacheStats};
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use self::buffered::{BufReader, BufWriter, DEFAULT_BUF_SIZE};
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use self::context::{ContextError, Op, ResultExt};
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use self::double_buffered::DoubleBufferedWriter;
+pub use self::events::StorageEvent;
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use self::events::StorageSubscription;
+pub use self::format::{FlipperFormat, FormatError, Keys};
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use self::mem::{MemFile, MemStorage};
+pub use self::path::{AsPath, PathBuf};
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use self::read_ahead::{ReadAhead, DEFAULT_READ_AHEAD_CHUNKS, READ_AHEAD_STACK_SIZE};
+pub use self::record::{deinit, init};
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use self::settings::Settings;
+#[cfg(all(feature = "std", not(target_os = "none")))]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub use self::std_fs::{StdFile, StdReadDir, StdStorage};
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use self::walk::{Walk, WalkDir, WalkEntry, MAX_WALK_DEPTH};
+
+/// Placeholder shown in place of the characters removed by [`display_path`].
+const ELLIPSIS: &str = "...";
+
+/// Maximum length of a file name, in bytes.
+pub const MAX_FILENAME_LEN: usize = 255;
+
+/// The root of the SD card.
+pub const EXT_PATH: &CStr = c"/ext";
+
+/// The root of the internal storage.
+pub const INT_PATH: &CStr = c"/int";
+
+/// The directory holding a data directory for each app, named after its app id.
+pub const APPS_DATA_PATH: &CStr = c"/ext/apps_data";
+
+/// The directory holding an assets directory for each app, named after its app id.
+pub const APPS_ASSETS_PATH: &CStr = c"/ext/apps_assets";
+
+/// The alias of the data directory of the current app, `/ext/apps_data/<app id>`.
+///
+/// Paths starting with it are resolved by the storage service, or by
+/// [`Storage::resolve_path`].
+pub const APP_DATA_ALIAS: &CStr = c"/data";
+
+/// The alias of the assets directory of the current app, `/ext/apps_assets/<app id>`,
+/// which holds the files bundled with the app.
+pub const APP_ASSETS_ALIAS: &CStr = c"/assets";
+
+/// Characters which are not allowed in file names.
+const RESERVED_CHARS: &[u8] = b"/\\:*?\"<>|";
+
+/// Reasons why a file name is invalid, as returned by [`validate_filename`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FilenameError {
+    /// The name is empty.
+    Empty,
+    /// The name is longer than [`MAX_FILENAME_LEN`].
+    TooLong,
+    /// The name contains a reserved, control or non-ASCII character.
+    InvalidChar,
+    /// The name is `.` or `..`, or ends with a `.` or a space.
+    InvalidName,
+}
+
+/// The capacity of a filesystem, as returned by [`Storage::fs_info`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct FsInfo {
+    /// The total size of the filesystem in bytes.
+    pub total_space: u64,
+    /// The number of bytes available for new data.
+    pub free_space: u64,
+}
+
+impl FsInfo {
+    /// Formats the free and total space, such as `3.2 MiB`, and passes them to `f`.
+    fn with_sizes<R>(&self, f: impl FnOnce(&str, &str) -> R) -> R {
+        let mut free = [0; crate::fmt::HUMAN_BYTES_MAX_LEN];
+        let mut total = [0; crate::fmt::HUMAN_BYTES_MAX_LEN];
+        f(
+            crate::fmt::human_bytes(self.free_space, &mut free),
+            crate::fmt::human_bytes(self.total_space, &mut total),
+        )
+    }
+}
+
+/// Shows the free and total space with binary units, such as `3.2 MiB free of 31.9 GiB`.
+impl fmt::Display for FsInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.with_sizes(|free, total| write!(f, "{} free of {}", free, total))
+    }
+}
+
+impl ufmt::uDisplay for FsInfo {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        self.with_sizes(|free, total| ufmt::uwrite!(f, "{} free of {}", free, total))
+    }
+}
+
+/// The state of the SD card, as returned by [`Storage::sd_status`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum SdStatus {
+    /// The card is mounted and ready to use.
+    Ok,
+    /// There is no card, or it isn't mounted yet.
+    NotReady,
+    /// The card couldn't be mounted, such as because it isn't formatted.
+    Error(Error),
+}
+
+impl SdStatus {
+    fn from_sys(status: sys::FS_Error) -> Self {
+        match Error::try_from_sys(status) {
+            None => Self::Ok,
+            Some(Error::NotReady) => Self::NotReady,
+            Some(error) => Self::Error(error),
+        }
+    }
+
+    /// Returns `true` if the card is ready to use.
+    pub fn is_ok(&self) -> bool {
+        matches!(self, Self::Ok)
+    }
+}
+
+/// The filesystem of the SD card.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum SdFsType {
+    Fat12,
+    Fat16,
+    Fat32,
+    ExFat,
+    /// A filesystem not known to the firmware.
+    Unknown,
+}
+
+impl SdFsType {
+    fn from_sys(fs_type: sys::SDFsType) -> Self {
+        match fs_type {
+            sys::SDFsType_FST_FAT12 => Self::Fat12,
+            sys::SDFsType_FST_FAT16 => Self::Fat16,
+            sys::SDFsType_FST_FAT32 => Self::Fat32,
+            sys::SDFsType_FST_EXFAT => Self::ExFat,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+/// The filesystem and identification of the SD card, as returned by
+/// [`Storage::sd_info`].
+#[derive(Debug, Clone, Copy)]
+pub struct SdInfo(sys::SDInfo);
+
+impl SdInfo {
+    /// Returns the type of the filesystem.
+    pub fn fs_type(&self) -> SdFsType {
+        SdFsType::from_sys(self.0.fs_type)
+    }
+
+    /// Returns the label of the filesystem, which is empty if it has none.
+    pub fn label(&self) -> &CStr {
+        c_str_field(&self.0.label)
+    }
+
+    /// Returns the total and free space of the filesystem.
+    ///
+    /// The firmware reports them in KiB, so they are multiples of 1024.
+    pub fn fs_info(&self) -> FsInfo {
+        FsInfo {
+            total_space: u64::from(self.0.kb_total) * 1024,
+            free_space: u64::from(self.0.kb_free) * 1024,
+        }
+    }
+
+    /// Returns the size of a cluster of the filesystem, in sectors.
+    pub fn cluster_size(&self) -> u16 {
+        self.0.cluster_size
+    }
+
+    /// Returns the size of a sector in bytes.
+    pub fn sector_size(&self) -> u16 {
+        self.0.sector_size
+    }
+
+    /// Returns the manufacturer ID of the card, as assigned by the SD Association.
+    pub fn manufacturer_id(&self) -> u8 {
+        self.0.manufacturer_id
+    }
+
+    /// Returns the two-character ID of the OEM of the card.
+    pub fn oem_id(&self) -> &CStr {
+        c_str_field(&self.0.oem_id)
+    }
+
+    /// Returns the product name of the card, of up to five characters.
+    pub fn product_name(&self) -> &CStr {
+        c_str_field(&self.0.product_name)
+    }
+
+    /// Returns the major and minor product revision of the card.
+    pub fn product_revision(&self) -> (u8, u8) {
+        (self.0.product_revision_major, self.0.product_revision_minor)
+    }
+
+    /// Returns the serial number of the card.
+    pub fn serial_number(&self) -> u32 {
+        self.0.product_serial_number
+    }
+
+    /// Returns the year and month, from 1 to 12, in which the card was manufactured.
+    pub fn manufacturing_date(&self) -> (u16, u8) {
+        (self.0.manufacturing_year, self.0.manufacturing_month)
+    }
+}
+
+/// Returns the string in a `char` array of the firmware, up to its nul terminator.
+///
+/// The arrays of [`sys::SDInfo`] are terminated, but a missing terminator returns an
+/// empty string rather than reading past the array.
+fn c_str_field(field: &[c_char]) -> &CStr {
+    // SAFETY: `c_char` is a byte.
+    let bytes = unsafe { core::slice::from_raw_parts(field.as_ptr() as *const u8, field.len()) };
+    CStr::from_bytes_until_nul(bytes).unwrap_or(c"")
+}
+
+/// Whether a path is a file or a directory, and its size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Metadata {
+    is_dir: bool,
+    size: u64,
+}
+
+impl Metadata {
+    pub(crate) fn new(is_dir: bool, size: u64) -> Self {
+        Self { is_dir, size }
+    }
+
+    /// Returns `true` if the path is a directory.
+    pub fn is_dir(&self) -> bool {
+        self.is_dir
+    }
+
+    /// Returns `true` if the path is a file.
+    pub fn is_file(&self) -> bool {
+        !self.is_dir
+    }
+
+    /// Returns the size in bytes, which is zero for directories.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+/// How often [`Storage::retry_while_open`] calls its function again.
+const OPEN_RETRY_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A handle to the storage service.
+///
+/// # Concurrent access
+///
+/// The storage service handles the requests of all threads one at a time, so handles
+/// can be shared between threads. It allows each path to be open only once, in any
+/// mode: opening a file that is already open waits until it is closed, even for
+/// reading, with no timeout. Removing and renaming an open file fail with
+/// [`Error::AlreadyOpen`] instead. This includes files opened by other apps and by the
+/// firmware, such as a file being copied over USB.
+///
+/// An open [`File`] therefore locks its path until it is closed, and
+/// [`Storage::with_file_locked`] makes that explicit. A thread must not open a file it
+/// already holds open, as it would wait for itself forever.
+/// [`Storage::retry_while_open`] retries removing or renaming a file until it is closed.
+pub struct Storage {
+    data: StorageRecord,
+}
+
+// SAFETY: the storage service handles requests from any thread.
+unsafe impl Send for Storage {}
+unsafe impl Sync for Storage {}
+
+impl Storage {
+    /// Obtains a handle to the storage service.
+    pub fn open() -> Self {
+        Self {
+            data: StorageRecord::open(),
+        }
+    }
+
+    /// Opens the file at `path` with `options`, as by [`OpenOptions::open`].
+    ///
+    /// The file shares this handle's reference to the storage record.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use flipperzero::io::Error;
+    /// # use flipperzero::storage::{OpenOptions, Storage};
+    /// # fn main() -> Result<(), Error> {
+    /// let storage = Storage::open();
+    /// let append = OpenOptions::new().write(true).open_append(true);
+    /// let log = storage.open_file(c"/ext/log.txt", append)?;
+    /// let config = storage.open_file(c"/ext/config.txt", OpenOptions::new().read(true))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn open_file<P: AsPath + ?Sized>(
+        &self,
+        path: &P,
+        options: OpenOptions,
+    ) -> Result<File, Error> {
+        options.validate()?;
+        path.with_c_str(|path| options.open_c_str(path, self.data.clone()))
+    }
+
+    /// Opens the file at `path` with `options`, and calls `f` with it.
+    ///
+    /// If the file is open elsewhere, opening blocks until the other handle closes it,
+    /// with no timeout. Other threads and apps opening the file then block until `f`
+    /// returns, and removing or renaming it fails with [`Error::AlreadyOpen`]. The file
+    /// is then closed, and an error of closing it is returned unless `f` failed.
+    ///
+    /// `f` must not open the file again, as that would block forever.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use flipperzero::io::{Error, Read, Seek, SeekFrom, Write};
+    /// # use flipperzero::storage::{OpenOptions, Storage};
+    /// # fn main() -> Result<(), Error> {
+    /// let options = OpenOptions::new().read(true).write(true).open_always(true);
+    /// // Increment a counter shared by several threads.
+    /// Storage::open().with_file_locked(c"/ext/apps_data/myapp/count", options, |file| {
+    ///     let mut count = [0];
+    ///     file.read(&mut count)?;
+    ///     file.seek(SeekFrom::Start(0))?;
+    ///     file.write_all(&[count[0].wrapping_add(1)])
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_file_locked<P, T, F>(
+        &self,
+        path: &P,
+        options: OpenOptions,
+        f: F,
+    ) -> Result<T, Error>
+    where
+        P: AsPath + ?Sized,
+        F: FnOnce(&mut File) -> Result<T, Error>,
+    {
+        let mut file = self.open_file(path, options)?;
+        let result = f(&mut file);
+        let closed = file.close();
+        let value = result?;
+        closed.map(|()| value)
+    }
+
+    /// Calls `f` with this handle until it no longer fails with [`Error::AlreadyOpen`],
+    /// trying again for up to `timeout`.
+    ///
+    /// Removing or renaming a file that another thread or app holds open fails rather
+    /// than waiting for it to be closed, so this is how to wait for it instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::AlreadyOpen`] if `f` still fails with it after `timeout`, and
+    /// other errors of `f` immediately.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use core::time::Duration;
+    /// # use flipperzero::io::Error;
+    /// # use flipperzero::storage::Storage;
+    /// # fn main() -> Result<(), Error> {
+    /// // The log may still be open for writing on another thread.
+    /// Storage::open().retry_while_open(Duration::from_secs(1), |storage| {
+    ///     storage.rename(c"/ext/log.txt", c"/ext/log.old.txt")
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn retry_while_open<T, F>(&self, timeout: Duration, mut f: F) -> Result<T, Error>
+    where
+        F: FnMut(&Self) -> Result<T, Error>,
+    {
+        let start = furi::time::Instant::now();
+        loop {
+            match f(self) {
+                Err(Error::AlreadyOpen)
+                    if u128::from(start.elapsed().as_millis()) < timeout.as_millis() =>
+                {
+                    furi::thread::sleep(OPEN_RETRY_INTERVAL)
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Returns `true` if a file or directory exists at `path`.
+    ///
+    /// Unlike `storage_common_exists`, errors other than the path not existing (for
+    /// example the SD card not being mounted) are reported instead of being treated as
+    /// the path not existing.
+    pub fn exists<P: AsPath + ?Sized>(&self, path: &P) -> Result<bool, Error> {
+        match path.with_c_str(|path| {
+            fs_result(unsafe {
+                sys::storage_common_stat(self.data.as_ptr(), path.as_ptr(), ptr::null_mut())
+            })
+        }) {
+            Ok(()) => Ok(true),
+            Err(Error::NotExists) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Returns `true` if a file exists at `path`.
+    ///
+    /// As with [`Storage::exists`], errors other than the path not existing are
+    /// reported.
+    pub fn file_exists<P: AsPath + ?Sized>(&self, path: &P) -> Result<bool, Error> {
+        match self.metadata(path) {
+            Ok(metadata) => Ok(metadata.is_file()),
+            Err(Error::NotExists) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Returns `true` if a directory exists at `path`.
+    ///
+    /// As with [`Storage::exists`], errors other than the path not existing are
+    /// reported.
+    pub fn dir_exists<P: AsPath + ?Sized>(&self, path: &P) -> Result<bool, Error> {
+        match self.metadata(path) {
+            Ok(metadata) => Ok(metadata.is_dir()),
+            Err(Error::NotExists) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Removes the file or empty directory at `path`.
+    pub fn remove<P: AsPath + ?Sized>(&self, path: &P) -> Result<(), Error> {
+        path.with_c_str(|path| {
+            fs_result(unsafe { sys::storage_common_remove(self.data.as_ptr(), path.as_ptr()) })
+        })
+    }
+
+    /// Removes the file or directory at `path`, with everything in it.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`Error::NotExists`] if nothing exists at `path`. The firmware doesn't
+    /// report why removing an entry within the directory failed, so such failures are
+    /// reported as removing the directory itself fails, such as with [`Error::Denied`]
+    /// if it isn't empty. Whatever was removed before the failure stays removed.
+    pub fn remove_dir_all<P: AsPath + ?Sized>(&self, path: &P) -> Result<(), Error> {
+        path.with_c_str(|path| {
+            self.metadata(path)?;
+            if unsafe { sys::storage_simply_remove_recursive(self.data.as_ptr(), path.as_ptr()) } {
+                Ok(())
+            } else {
+                self.remove(path)
+            }
+        })
+    }
+
+    /// Returns whether `path` is a file or a directory, and its size.
+    pub fn metadata<P: AsPath + ?Sized>(&self, path: &P) -> Result<Metadata, Error> {
+        let mut info = sys::FileInfo { flags: 0, size: 0 };
+        path.with_c_str(|path| {
+            fs_result(unsafe {
+                sys::storage_common_stat(self.data.as_ptr(), path.as_ptr(), &mut info)
+            })
+        })?;
+        Ok(Metadata::new(
+            unsafe { sys::file_info_is_dir(&info) },
+            info.size,
+        ))
+    }
+
+    /// Creates a directory at `path`, whose parent must exist.
+    pub fn create_dir<P: AsPath + ?Sized>(&self, path: &P) -> Result<(), Error> {
+        path.with_c_str(|path| {
+            fs_result(unsafe { sys::storage_common_mkdir(self.data.as_ptr(), path.as_ptr()) })
+        })
+    }
+
+    /// Returns the absolute path that `path` refers to.
+    ///
+    /// Paths starting with [`APP_DATA_ALIAS`] or [`APP_ASSETS_ALIAS`] are resolved to
+    /// the directories of the current app, and the app's data directory is created if
+    /// it doesn't exist yet. Relative paths are taken to be relative to the app's data
+    /// directory. Other absolute paths are returned as they are.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`Error::InvalidParameter`] if the path is within the app's data or
+    /// assets directory, but the current thread doesn't belong to an app started by
+    /// the loader, which is what app ids are known for.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use flipperzero::io::Error;
+    /// # use flipperzero::storage::Storage;
+    /// # fn main() -> Result<(), Error> {
+    /// let path = Storage::open().resolve_path(c"/data/config.txt")?;
+    /// // Prints `/ext/apps_data/<app id>/config.txt`.
+    /// flipperzero::println!("{}", path);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn resolve_path<P: AsPath + ?Sized>(&self, path: &P) -> Result<PathBuf, Error> {
+        path.with_c_str(|path| {
+            let mut resolved = if path.to_bytes().starts_with(b"/") {
+                FuriString::from(path)
+            } else {
+                PathBuf::from(APP_DATA_ALIAS).join(path)?.into_furi_string()
+            };
+            let bytes = resolved.to_bytes();
+            let aliased = starts_with_dir(bytes, APP_DATA_ALIAS.to_bytes())
+                || starts_with_dir(bytes, APP_ASSETS_ALIAS.to_bytes());
+            if aliased && !in_app() {
+                return Err(Error::InvalidParameter);
+            }
+            unsafe {
+                sys::storage_common_resolve_path_and_ensure_app_directory(
+                    self.data.as_ptr(),
+                    resolved.as_mut_ptr(),
+                )
+            };
+            Ok(PathBuf::from(resolved))
+        })
+    }
+
+    /// Returns the absolute path of `name` within the data directory of the current
+    /// app, creating the directory if it doesn't exist yet.
+    ///
+    /// This is [`Storage::resolve_path`] for `name` under [`APP_DATA_ALIAS`].
+    pub fn app_data_path<P: AsPath + ?Sized>(&self, name: &P) -> Result<PathBuf, Error> {
+        self.resolve_path(&PathBuf::from(APP_DATA_ALIAS).join(name)?)
+    }
+
+    /// Returns the absolute path of `name` within the assets directory of the current
+    /// app.
+    ///
+    /// This is [`Storage::resolve_path`] for `name` under [`APP_ASSETS_ALIAS`].
+    pub fn app_assets_path<P: AsPath + ?Sized>(&self, name: &P) -> Result<PathBuf, Error> {
+        self.resolve_path(&PathBuf::from(APP_ASSETS_ALIAS).join(name)?)
+    }
+
+    /// Moves the file or directory at `from` to `to`, which must not exist.
+    pub fn rename<P, Q>(&self, from: &P, to: &Q) -> Result<(), Error>
+    where
+        P: AsPath + ?Sized,
+        Q: AsPath + ?Sized,
+    {
+        from.with_c_str(|from| {
+            to.with_c_str(|to| {
+                fs_result(unsafe {
+                    sys::storage_common_rename(self.data.as_ptr(), from.as_ptr(), to.as_ptr())
+                })
+            })
+        })
+    }
+
+    /// Copies the file at `from` to `to`, which may be on another filesystem.
+    ///
+    /// Neither file may be open.
+    pub fn copy<P, Q>(&self, from: &P, to: &Q) -> Result<(), Error>
+    where
+        P: AsPath + ?Sized,
+        Q: AsPath + ?Sized,
+    {
+        from.with_c_str(|from| {
+            to.with_c_str(|to| {
+                fs_result(unsafe {
+                    sys::storage_common_copy(self.data.as_ptr(), from.as_ptr(), to.as_ptr())
+                })
+            })
+        })
+    }
+
+    /// Returns the capacity of the filesystem containing `path`, such as `/ext` or
+    /// `/int`.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`Error::NotReady`] for paths on the SD card while it is missing or
+    /// not mounted, see [`Storage::sd_status`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use flipperzero::io::Error;
+    /// # use flipperzero::storage::Storage;
+    /// fn check_space() -> Result<bool, Error> {
+    ///     let info = Storage::open().fs_info("/ext")?;
+    ///     let percent_free = info.free_space * 100 / info.total_space.max(1);
+    ///     if percent_free < 10 {
+    ///         flipperzero::println!("only {}% of the SD card is free", percent_free);
+    ///         return Ok(false);
+    ///     }
+    ///     Ok(true)
+    /// }
+    /// ```
+    pub fn fs_info<P: AsPath + ?Sized>(&self, path: &P) -> Result<FsInfo, Error> {
+        let mut info = FsInfo {
+            total_space: 0,
+            free_space: 0,
+        };
+        path.with_c_str(|path| {
+            fs_result(unsafe {
+                sys::storage_common_fs_info(
+                    self.data.as_ptr(),
+                    path.as_ptr(),
+                    &mut info.total_space,
+                    &mut info.free_space,
+                )
+            })
+        })?;
+        Ok(info)
+    }
+
+    /// Returns the MD5 digest of the file at `path`.
+    ///
+    /// The file is read in small chunks, so files of any size can be hashed without
+    /// holding them in memory. Use [`crate::fmt::hex`] to show or store the digest as
+    /// text.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the file can't be opened, such as with [`Error::NotExists`], or with
+    /// [`Error::NotReady`] for files on the SD card while it is missing, and if reading
+    /// it fails partway.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use flipperzero::io::Error;
+    /// # use flipperzero::storage::Storage;
+    /// # fn main() -> Result<(), Error> {
+    /// let digest = Storage::open().md5sum("/ext/subghz/remote.sub")?;
+    /// let mut buf = [0; 32];
+    /// flipperzero::println!("md5: {}", flipperzero::fmt::hex(&digest, &mut buf));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn md5sum<P: AsPath + ?Sized>(&self, path: &P) -> Result<[u8; 16], Error> {
+        path.with_c_str(|path| {
+            // `md5_calc_file` opens and closes the file itself.
+            let file = File::new();
+            let mut digest = [0; 16];
+            let mut status = sys::FS_Error_FSE_OK;
+            let opened = unsafe {
+                sys::md5_calc_file(
+                    file.0.as_ptr(),
+                    path.as_ptr(),
+                    digest.as_mut_ptr(),
+                    &mut status,
+                )
+            };
+            // A read that fails partway only ends the digest early, and leaves its
+            // error as the status.
+            fs_result(status)?;
+            if opened {
+                Ok(digest)
+            } else {
+                Err(Error::Internal)
+            }
+        })
+    }
+
+    /// Returns the CRC32 checksum of the file at `path`, as computed by
+    /// [`Crc32`](crate::toolbox::Crc32).
+    ///
+    /// The file is read in small chunks, like in [`Storage::md5sum`].
+    ///
+    /// # Errors
+    ///
+    /// Fails if the file can't be opened or read.
+    pub fn crc32sum<P: AsPath + ?Sized>(&self, path: &P) -> Result<u32, Error> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .open_existing(true)
+            .open(path)?;
+        let mut crc = crate::toolbox::Crc32::new();
+        crc.update_reader(&mut file)?;
+        Ok(crc.finalize())
+    }
+
+    /// Returns the state of the SD card.
+    ///
+    /// Operations on the card fail with [`Error::NotReady`] while it isn't mounted.
+    /// Apps writing over a long time, such as loggers, can pause while the card is gone
+    /// by watching for [`StorageEvent::CardUnmounted`] and [`StorageEvent::CardMounted`]
+    /// with `Storage::subscribe`.
+    pub fn sd_status(&self) -> SdStatus {
+        SdStatus::from_sys(unsafe { sys::storage_sd_status(self.data.as_ptr()) })
+    }
+
+    /// Returns the filesystem and identification of the SD card.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotReady`] if there is no card mounted.
+    pub fn sd_info(&self) -> Result<SdInfo, Error> {
+        let mut info = MaybeUninit::<sys::SDInfo>::uninit();
+        let status = unsafe { sys::storage_sd_info(self.data.as_ptr(), info.as_mut_ptr()) };
+        match Error::try_from_sys(status) {
+            None => Ok(SdInfo(unsafe { info.assume_init() })),
+            Some(error) => Err(error),
+        }
+    }
+
+    /// Subscribes to the events of the storage service, calling `callback` for every
+    /// event.
+    ///
+    /// The closure is called on the storage service thread, so it should return
+    /// quickly, and must not call into the storage service itself. The subscription
+    /// ends when the returned [`StorageSubscription`] is dropped.
+    ///
+    /// # Examples
+    ///
+    /// Forwarding the events about the SD card to the app's queue, as the closure
+    /// can't block:
+    ///
+    /// ```no_run
+    /// # extern crate alloc;
+    /// # use alloc::sync::Arc;
+    /// # use core::time::Duration;
+    /// # use flipperzero::furi::message_queue::MessageQueue;
+    /// # use flipperzero::storage::{Storage, StorageEvent};
+    /// let queue = Arc::new(MessageQueue::new(4));
+    /// let _subscription = Storage::open().subscribe({
+    ///     let queue = queue.clone();
+    ///     move |event: StorageEvent| {
+    ///         if event.is_card_event() {
+    ///             let _ = queue.put(event, Duration::ZERO);
+    ///         }
+    ///     }
+    /// });
+    /// if let Ok(StorageEvent::CardUnmounted) = queue.get(Duration::MAX) {
+    ///     flipperzero::println!("SD card removed");
+    /// }
+    /// ```
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+    pub fn subscribe<F>(&self, callback: F) -> StorageSubscription
+    where
+        F: FnMut(StorageEvent) + Send + 'static,
+    {
+        StorageSubscription::new(self.data.clone(), callback)
+    }
+
+    /// Returns an iterator over the entries of the directory at `path`.
+    ///
+    /// The entries are returned in the order of the filesystem, which is not
+    /// necessarily sorted, and don't include `.` and `..`. The directory is closed when
+    /// the iterator is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use flipperzero::io::Error;
+    /// # use flipperzero::println;
+    /// # use flipperzero::storage::Storage;
+    /// # fn main() -> Result<(), Error> {
+    /// for entry in Storage::open().read_dir(c"/ext/apps_data/myapp")? {
+    ///     let entry = entry?;
+    ///     if entry.is_dir() {
+    ///         println!("{}/", entry.name().to_str().unwrap_or("?"));
+    ///     } else {
+    ///         println!("{} ({} bytes)", entry.name().to_str().unwrap_or("?"), entry.size());
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_dir<P: AsPath + ?Sized>(&self, path: &P) -> Result<ReadDir, Error> {
+        path.with_c_str(|path| ReadDir::open(path, self.data.clone()))
+    }
+
+    /// Finds a file name in `dir` that doesn't conflict with any existing file.
+    ///
+    /// Returns `prefix` if `dir/{prefix}{extension}` doesn't exist, and otherwise the
+    /// first of `{prefix}1`, `{prefix}2`, ... that is free. `extension` must include the
+    /// leading `.` if it isn't empty, and is not included in the returned name.
+    ///
+    /// Numbers are tried from 1 upwards, so a number freed by removing its file is
+    /// returned again. If `prefix` isn't shorter than `max_len`, `prefix` is returned
+    /// as-is even if it is taken. See [`Storage::create_next_file`] for creating the
+    /// file without racing other writers.
+    pub fn next_free_filename(
+        &self,
+        dir: &CStr,
+        prefix: &CStr,
+        extension: &CStr,
+        max_len: u8,
+    ) -> FuriString {
+        let mut name = FuriString::new();
+        unsafe {
+            sys::storage_get_next_filename(
+                self.data.as_ptr(),
+                dir.as_ptr(),
+                prefix.as_ptr(),
+                extension.as_ptr(),
+                name.as_mut_ptr(),
+                max_len,
+            );
+        }
+        name
+    }
+
+    /// Creates the file named by [`Storage::next_free_filename`], such as
+    /// `signal1.sub` for the prefix `signal` and the extension `.sub`, returning it
+    /// open for reading and writing along with its path.
+    ///
+    /// The file is only created if it doesn't exist, so a name taken by someone else in
+    /// the meantime is never overwritten, and the next free name is tried instead.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`Error::NotExists`] if `dir` doesn't exist, as it isn't created,
+    /// and with [`Error::Exists`] if no name is free, such as when `prefix` is too long
+    /// to be numbered.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use flipperzero::io::{Error, Write};
+    /// # use flipperzero::storage::Storage;
+    /// # fn main() -> Result<(), Error> {
+    /// let storage = Storage::open();
+    /// let (mut file, path) = storage.create_next_file(c"/ext/subghz", c"signal", c".sub")?;
+    /// file.write_all(b"Filetype: Flipper SubGhz RAW File\n")?;
+    /// flipperzero::println!("saved to {}", path);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn create_next_file<P: AsPath + ?Sized>(
+        &self,
+        dir: &P,
+        prefix: &CStr,
+        extension: &CStr,
+    ) -> Result<(File, PathBuf), Error> {
+        // Another writer can only take a few names between two attempts.
+        const ATTEMPTS: usize = 4;
+
+        dir.with_c_str(|dir| {
+            let mut result = Err(Error::Exists);
+            for _ in 0..ATTEMPTS {
+                let mut name =
+                    self.next_free_filename(dir, prefix, extension, MAX_FILENAME_LEN as u8);
+                name.push_c_str(extension);
+                let path = PathBuf::from(dir).join(&name)?;
+                result = OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .create_new(true)
+                    .open(&path)
+                    .map(|file| (file, path));
+                if !matches!(result, Err(Error::Exists)) {
+                    break;
+                }
+            }
+            result
+        })
+    }
+
+    /// Replaces the contents of the file at `path` with `bytes`, such that the file
+    /// either has its old or its new contents if power is lost, and is never partially
+    /// written.
+    ///
+    /// See [`Storage::write_atomic_with`] for how this works and fails.
+    pub fn write_atomic<P: AsPath + ?Sized>(&self, path: &P, bytes: &[u8]) -> Result<(), Error> {
+        self.write_atomic_with(path, |file| file.write_all(bytes))
+    }
+
+    /// Replaces the contents of the file at `path` with what `write` writes to the
+    /// given file, such that the file is never partially written.
+    ///
+    /// The contents are written to a new temporary file next to `path`, such as
+    /// `settings.txt.tmp`, or `settings.txt1.tmp` if that is taken. Once `write`
+    /// returned and the temporary file is synced, it is renamed to `path`, replacing the
+    /// file there, if any. The firmware does that by removing the old file first, so if
+    /// power is lost right in between, the new contents are left at the temporary path.
+    ///
+    /// # Errors
+    ///
+    /// On any error, including those returned by `write`, the temporary file is removed
+    /// and the file at `path` keeps its old contents. The rename fails with
+    /// [`Error::AlreadyOpen`] if the file at `path` is open, and the directory of `path`
+    /// must exist.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use flipperzero::io::{Error, Write};
+    /// # use flipperzero::storage::Storage;
+    /// # fn main() -> Result<(), Error> {
+    /// Storage::open().write_atomic_with(c"/ext/apps_data/myapp/state.txt", |file| {
+    ///     writeln!(file, "level={}", 3)?;
+    ///     writeln!(file, "score={}", 1200)
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn write_atomic_with<P, F>(&self, path: &P, write: F) -> Result<(), Error>
+    where
+        P: AsPath + ?Sized,
+        F: FnOnce(&mut File) -> Result<(), Error>,
+    {
+        path.with_c_str(|path| {
+            let bytes = path.to_bytes_with_nul();
+            let Some(slash) = bytes.iter().rposition(|&b| b == b'/') else {
+                return Err(Error::InvalidName);
+            };
+            let mut dir = FuriString::from(path);
+            dir.truncate(slash);
+            // Only a prefix was skipped, which keeps the terminator.
+            let name = CStr::from_bytes_with_nul(&bytes[slash + 1..]).unwrap();
+
+            let (mut file, temp) = self.create_next_file(&dir, name, c".tmp")?;
+            let written = write(&mut file)
+                .and_then(|()| file.sync())
+                .and_then(|()| file.close())
+                .and_then(|()| self.rename(&temp, path));
+            if written.is_err() {
+                // The file was closed or dropped by now.
+                self.remove(&temp).ok();
+            }
+            written
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct OpenOptions {
+    access_mode: u8,
+    open_mode: u8,
+}
+
+impl OpenOptions {
+    /// Returns the access mode, a combination of `FS_AccessMode` flags.
+    pub(crate) fn access_mode(&self) -> u8 {
+        self.access_mode
+    }
+
+    /// Returns the one `FS_OpenMode` that the options amount to, which is
+    /// `FSOM_OPEN_EXISTING` if no open mode was set.
+    ///
+    /// Only meaningful for options that passed [`OpenOptions::validate`].
+    pub(crate) fn canonical_open_mode(&self) -> u8 {
+        if self.open_mode == 0 {
+            sys::FS_OpenMode_FSOM_OPEN_EXISTING
+        } else {
+            self.open_mode
+        }
+    }
+
+    /// Checks that the options ask for read or write access, and for at most one open
+    /// mode.
+    ///
+    /// Backends call this before opening anything, so that conflicting options such as
+    /// `open_append(true).create_new(true)` fail the same way everywhere.
+    pub(crate) fn validate(&self) -> Result<(), Error> {
+        if self.access_mode == 0 || self.open_mode.count_ones() > 1 {
+            Err(Error::InvalidParameter)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn from_parts(access_mode: u8, open_mode: u8) -> Self {
+        OpenOptions {
+            access_mode,
+            open_mode,
+        }
+    }
+
+    /// Read access
+    pub fn read(self, set: bool) -> Self {
+        OpenOptions::from_parts(
+            if set {
+                self.access_mode | sys::FS_AccessMode_FSAM_READ
+            } else {
+                self.access_mode & !sys::FS_AccessMode_FSAM_READ
+            },
+            self.open_mode,
+        )
+    }
+
+    /// Write access
+    pub fn write(self, set: bool) -> Self {
+        OpenOptions::from_parts(
+            if set {
+                self.access_mode | sys::FS_AccessMode_FSAM_WRITE
+            } else {
+                self.access_mode & !sys::FS_AccessMode_FSAM_WRITE
+            },
+            self.open_mode,
+        )
+    }
+
+    /// Open file, fail if file doesn't exist
+    pub fn open_existing(self, set: bool) -> Self {
+        OpenOptions::from_parts(
+            self.access_mode,
+            if set {
+                self.open_mode | sys::FS_OpenMode_FSOM_OPEN_EXISTING
+            } else {
+                self.open_mode & !sys::FS_OpenMode_FSOM_OPEN_EXISTING
+            },
+        )
+    }
+
+    /// Open file. Create new file if not exist
+    pub fn open_always(self, set: bool) -> Self {
+        OpenOptions::from_parts(
+            self.access_mode,
+            if set {
+                self.open_mode | sys::FS_OpenMode_FSOM_OPEN_ALWAYS
+            } else {
+                self.open_mode & !sys::FS_OpenMode_FSOM_OPEN_ALWAYS
+            },
+        )
+    }
+
+    /// Open file. Create new file if not exist. Set R/W pointer to EOF
+    pub fn open_append(self, set: bool) -> Self {
+        OpenOptions::from_parts(
+            self.access_mode,
+            if set {
+                self.open_mode | sys::FS_OpenMode_FSOM_OPEN_APPEND
+            } else {
+                self.open_mode & !sys::FS_OpenMode_FSOM_OPEN_APPEND
+            },
+        )
+    }
+
+    /// Creates a new file. Fails if the file is exist
+    pub fn create_new(self, set: bool) -> Self {
+        OpenOptions::from_parts(
+            self.access_mode,
+            if set {
+                self.open_mode | sys::FS_OpenMode_FSOM_CREATE_NEW
+            } else {
+                self.open_mode & !sys::FS_OpenMode_FSOM_CREATE_NEW
+            },
+        )
+    }
+
+    /// Creates a new file. If file exist, truncate to zero size
+    pub fn create_always(self, set: bool) -> Self {
+        OpenOptions::from_parts(
+            self.access_mode,
+            if set {
+                self.open_mode | sys::FS_OpenMode_FSOM_CREATE_ALWAYS
+            } else {
+                self.open_mode & !sys::FS_OpenMode_FSOM_CREATE_ALWAYS
+            },
+        )
+    }
+
+    /// Opens the file at `path` with these options.
+    ///
+    /// `path` may be a [`CStr`] or a [`str`], among others. A path containing a nul byte
+    /// fails with [`Error::InvalidName`].
+    ///
+    /// Without an open mode, the file must exist, as with `open_existing(true)`.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`Error::InvalidParameter`], without calling into the firmware, if
+    /// neither read nor write access is set, or if more than one open mode is set.
+    pub fn open<P: AsPath + ?Sized>(self, path: &P) -> Result<File, Error> {
+        self.validate()?;
+        path.with_c_str(|path| self.open_c_str(path, StorageRecord::open()))
+    }
+
+    fn open_c_str(self, path: &CStr, record: StorageRecord) -> Result<File, Error> {
+        let f = File::with_record(record);
+        if unsafe {
+            sys::storage_file_open(
+                f.0.as_ptr(),
+                path.as_ptr() as *const c_char,
+                self.access_mode(),
+                self.canonical_open_mode(),
+            )
+        } {
+            Ok(f)
+        } else {
+            // Per docs, "you need to close the file even if the open operation
+            // failed," but this is handled by `Drop`.
+            Err(f.last_error())
+        }
+    }
+}
+
+/// Basic, unbuffered file handle
+///
+/// The last field is the position after a seek past the end of a file that the firmware
+/// clamped to its end, as FatFS does for files that aren't open for writing.
+#[allow(dead_code)]
+pub struct File(NonNull<sys::File>, StorageRecord, Option<usize>);
+
+// SAFETY: the storage service handles requests from any thread, and only one thread at a
+// time can use a `File`.
+unsafe impl Send for File {}
+
+impl File {
+    /// Allocates a handle that isn't open yet, which is what [`OpenOptions::open`]
+    /// opens.
+    pub(crate) fn new() -> Self {
+        Self::with_record(StorageRecord::open())
+    }
+
+    /// Allocates a handle that isn't open yet, keeping `record` for as long as it lives.
+    fn with_record(record: StorageRecord) -> Self {
+        File(
+            unsafe { NonNull::new_unchecked(sys::storage_file_alloc(record.as_ptr())) },
+            record,
+            None,
+        )
+    }
+
+    /// Opens the existing file at `path` for reading.
+    ///
+    /// This is `OpenOptions::new().read(true).open_existing(true).open(path)`.
+    pub fn open<P: AsPath + ?Sized>(path: &P) -> Result<Self, Error> {
+        OpenOptions::new().read(true).open_existing(true).open(path)
+    }
+
+    /// Creates the file at `path` for writing, emptying it if it exists.
+    ///
+    /// This is `OpenOptions::new().write(true).create_always(true).open(path)`.
+    pub fn create<P: AsPath + ?Sized>(path: &P) -> Result<Self, Error> {
+        OpenOptions::new()
+            .write(true)
+            .create_always(true)
+            .open(path)
+    }
+
+    /// Reads bytes starting at `offset` into `buf`, returning how many bytes were read.
+    ///
+    /// Afterwards, the cursor is positioned after the bytes that were read.
+    pub fn read_at(&mut self, buf: &mut [u8], offset: u64) -> Result<usize, Error> {
+        self.seek(SeekFrom::Start(offset))?;
+        self.read(buf)
+    }
+
+    /// Writes bytes from `buf` starting at `offset`, returning how many bytes were
+    /// written.
+    ///
+    /// The rest of the file is left untouched. Afterwards, the cursor is positioned after
+    /// the bytes that were written.
+    pub fn write_at(&mut self, buf: &[u8], offset: u64) -> Result<usize, Error> {
+        self.seek(SeekFrom::Start(offset))?;
+        self.write(buf)
+    }
+
+    /// Returns an adapter for writing formatted text to the file with `write!` or
+    /// `uwrite!`.
+    ///
+    /// `write!` also works on the file itself, through [`Write::write_fmt`]. The
+    /// adapter is for APIs that take a [`core::fmt::Write`] or a [`ufmt::uWrite`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use flipperzero::io::Error;
+    /// # use flipperzero::storage::OpenOptions;
+    /// # fn main() -> Result<(), Error> {
+    /// let mut file = OpenOptions::new()
+    ///     .write(true)
+    ///     .open_append(true)
+    ///     .open(c"/ext/log.txt")?;
+    /// ufmt::uwriteln!(file.writer_fmt(), "temp={} ts={}", 21, 1234)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn writer_fmt(&mut self) -> FmtWriter<'_, Self> {
+        FmtWriter::new(self)
+    }
+
+    /// Writes any data that is still buffered to the storage.
+    ///
+    /// This is also what [`Write::flush`] does.
+    pub fn sync(&mut self) -> Result<(), Error> {
+        if unsafe { sys::storage_file_sync(self.0.as_ptr()) } {
+            Ok(())
+        } else {
+            Err(self.last_error())
+        }
+    }
+
+    /// Returns the size of the file in bytes.
+    pub fn size(&self) -> usize {
+        unsafe { sys::storage_file_size(self.0.as_ptr()) as usize }
+    }
+
+    /// Returns `true` if the firmware's position is at the end of the file.
+    pub fn is_eof(&self) -> bool {
+        unsafe { sys::storage_file_eof(self.0.as_ptr()) }
+    }
+
+    /// Truncates or extends the file to `len` bytes.
+    ///
+    /// A file that is extended is filled with zeros, whichever storage it is on. The
+    /// cursor stays where it was, unless it was past `len`, in which case it is moved to
+    /// `len`, as seeking past the end would extend the file again.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the file isn't open for writing, leaving the cursor where it was. The
+    /// file may have been partially extended if writing the zeros failed.
+    pub fn set_len(&mut self, len: usize) -> Result<(), Error> {
+        let position = self.position();
+        if let Err(e) = self.resize(len) {
+            self.seek(SeekFrom::Start(position as u64))?;
+            return Err(e);
+        }
+        self.seek(SeekFrom::Start(position.min(len) as u64))
+            .map(|_| ())
+    }
+
+    /// Truncates or extends the file to `len` bytes, leaving the cursor anywhere.
+    fn resize(&mut self, len: usize) -> Result<(), Error> {
+        let size = self.size();
+        match len.cmp(&size) {
+            Ordering::Less => {
+                // The firmware truncates at the current position.
+                self.seek(SeekFrom::Start(len as u64))?;
+                if !unsafe { sys::storage_file_truncate(self.0.as_ptr()) } {
+                    return Err(self.last_error());
+                }
+            }
+            Ordering::Greater => {
+                // Seeking past the end only extends files on the SD card, and leaves what
+                // was there before in the new part, so the zeros are written out instead.
+                const ZEROS: [u8; 64] = [0; 64];
+                self.seek(SeekFrom::Start(size as u64))?;
+                let mut remaining = len - size;
+                while remaining > 0 {
+                    let n = remaining.min(ZEROS.len());
+                    self.write_all(&ZEROS[..n])?;
+                    remaining -= n;
+                }
+            }
+            Ordering::Equal => {}
+        }
+        Ok(())
+    }
+
+    /// Truncates the file at the cursor, dropping everything after it.
+    ///
+    /// This is `set_len` with the current position, so a cursor past the end extends
+    /// the file with zeros instead.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the file isn't open for writing.
+    pub fn truncate(&mut self) -> Result<(), Error> {
+        let position = self.position();
+        self.set_len(position)
+    }
+
+    /// Returns `true` if the storage service has the file open.
+    pub fn is_open(&self) -> bool {
+        unsafe { sys::storage_file_is_open(self.0.as_ptr()) }
+    }
+
+    /// Closes the file, returning any error from writing out its data.
+    ///
+    /// Dropping the file also closes it, but ignores errors.
+    pub fn close(self) -> Result<(), Error> {
+        // Dropping the file would close it a second time.
+        let file = ManuallyDrop::new(self);
+        let result = if unsafe { sys::storage_file_close(file.0.as_ptr()) } {
+            Ok(())
+        } else {
+            Err(file.last_error())
+        };
+        unsafe { sys::storage_file_free(file.0.as_ptr()) };
+        // SAFETY: `file` isn't used after this, and is never dropped.
+        drop(unsafe { ptr::read(&file.1) });
+        result
+    }
+
+    /// Returns the error of the last operation on the file.
+    fn last_error(&self) -> Error {
+        Error::try_from_sys(unsafe { sys::storage_file_get_error(self.0.as_ptr()) })
+            .unwrap_or(Error::Internal)
+    }
+
+    /// Returns `value` if the last operation on the file succeeded.
+    fn check<T>(&self, value: T) -> Result<T, Error> {
+        fs_result(unsafe { sys::storage_file_get_error(self.0.as_ptr()) }).map(|()| value)
+    }
+
+    // Offsets within a file fit in a `u32`, as that is all `storage_file_seek` takes,
+    // so they always fit in a `usize` on the Flipper.
+
+    fn tell(&self) -> usize {
+        unsafe { sys::storage_file_tell(self.0.as_ptr()) as usize }
+    }
+
+    /// Returns the position of the last seek past the end, or that of the firmware.
+    fn position(&self) -> usize {
+        self.2.unwrap_or_else(|| self.tell())
+    }
+}
+
+impl Drop for File {
+    fn drop(&mut self) {
+        unsafe {
+            // `storage_file_close` calls `storage_file_sync`
+            // internally, so it's not necesssary to call it here.
+            sys::storage_file_close(self.0.as_ptr());
+            sys::storage_file_free(self.0.as_ptr());
+        }
+        // The record is released after this, once the handle no longer needs it.
+    }
+}
+
+impl Read for File {
+    /// Reads into `buf` in one request, which the storage service splits into parts it
+    /// can handle itself.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let bytes_read = unsafe {
+            sys::storage_file_read(self.0.as_ptr(), buf.as_mut_ptr() as *mut c_void, buf.len())
+        };
+        self.check(bytes_read)
+    }
+}
+
+impl Seek for File {
+    /// Moves to a position within the file.
+    ///
+    /// Positions relative to the end or the current position are turned into offsets
+    /// from the start, so negative offsets work for both.
+    ///
+    /// Seeking past the end of a file open for writing moves there, and on the SD card
+    /// also extends the file to that position. In a file only open for reading, reads
+    /// there return nothing, and the position is still the one that was sought to.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidParameter`] if the position is before the start of the
+    /// file or past `u32::MAX`, the largest offset the firmware can seek to.
+    fn seek(&mut self, pos: SeekFrom) -> Result<usize, Error> {
+        let target = match pos {
+            SeekFrom::Start(offset) => i64::try_from(offset).unwrap_or(i64::MAX),
+            SeekFrom::End(offset) => (self.size() as i64).saturating_add(offset),
+            SeekFrom::Current(offset) => (self.position() as i64).saturating_add(offset),
+        };
+        let offset = u32::try_from(target).map_err(|_| Error::InvalidParameter)?;
+        if !unsafe { sys::storage_file_seek(self.0.as_ptr(), offset, true) } {
+            return Err(self.last_error());
+        }
+        // Seeking past the end of a file that is only open for reading leaves the
+        // firmware at the end, where reads return nothing, as they would at `offset`.
+        let offset = offset as usize;
+        self.2 = (self.tell() < offset).then_some(offset);
+        Ok(offset)
+    }
+
+    fn rewind(&mut self) -> Result<(), Error> {
+        self.seek(SeekFrom::Start(0)).map(|_| {})
+    }
+
+    fn stream_len(&mut self) -> Result<usize, Error> {
+        Ok(self.size())
+    }
+
+    fn stream_position(&mut self) -> Result<usize, Error> {
+        Ok(self.position())
+    }
+}
+
+impl Write for File {
+    /// Writes `buf` in one request, which the storage service splits into parts it can
+    /// handle itself.
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        let bytes_written = unsafe {
+            sys::storage_file_write(self.0.as_ptr(), buf.as_ptr() as *const c_void, buf.len())
+        };
+        self.check(bytes_written)
+    }
+
+    /// Writes any data that is still buffered to the storage, as by [`File::sync`].
+    fn flush(&mut self) -> Result<(), Error> {
+        self.sync()
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl embedded_io::ErrorType for File {
+    type Error = Error;
+}
+
+#[cfg(feature = "embedded-io")]
+impl embedded_io::Read for File {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        Read::read(self, buf)
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl embedded_io::Write for File {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        Write::write(self, buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        Write::flush(self)
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl embedded_io::Seek for File {
+    fn seek(&mut self, pos: embedded_io::SeekFrom) -> Result<u64, Error> {
+        Seek::seek(self, pos.into()).map(|position| position as u64)
+    }
+}
+
+/// A buffer for the name of a directory entry, for [`ReadDir::next_entry`].
+pub type DirEntryName = [c_char; MAX_FILENAME_LEN + 1];
+
+/// An iterator over the entries of a directory, returned by [`Storage::read_dir`].
+///
+/// The storage service returns one entry per call, and every entry returned by the
+/// iterator owns its name. [`ReadDir::next_entry`] reads the next entry without
+/// allocating.
+pub struct ReadDir {
+    dir: NonNull<sys::File>,
+    _storage: StorageRecord,
+    done: bool,
+}
+
+impl ReadDir {
+    fn open(path: &CStr, storage: StorageRecord) -> Result<Self, Error> {
+        let dir = Self {
+            dir: unsafe { NonNull::new_unchecked(sys::storage_file_alloc(storage.as_ptr())) },
+            _storage: storage,
+            done: false,
+        };
+
+        if unsafe { sys::storage_dir_open(dir.dir.as_ptr(), path.as_ptr()) } {
+            Ok(dir)
+        } else {
+            let error = unsafe { sys::storage_file_get_error(dir.dir.as_ptr()) };
+            Err(Error::try_from_sys(error).unwrap_or(Error::Internal))
+        }
+    }
+
+    /// Reads the next entry, with its name in `name`.
+    ///
+    /// This is [`Iterator::next`] without allocating the name, for scanning large
+    /// directories. The same buffer can be reused for every entry.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use flipperzero::io::Error;
+    /// # use flipperzero::storage::{DirEntryName, Storage, MAX_FILENAME_LEN};
+    /// # fn main() -> Result<(), Error> {
+    /// let mut dir = Storage::open().read_dir(c"/ext/nfc")?;
+    /// let mut name: DirEntryName = [0; MAX_FILENAME_LEN + 1];
+    /// let mut files = 0;
+    /// while let Some(entry) = dir.next_entry(&mut name) {
+    ///     files += u32::from(!entry?.is_dir());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn next_entry<'a>(
+        &mut self,
+        name: &'a mut DirEntryName,
+    ) -> Option<Result<DirEntryRef<'a>, Error>> {
+        if self.done {
+            return None;
+        }
+
+        let mut info = sys::FileInfo { flags: 0, size: 0 };
+        if !unsafe {
+            sys::storage_dir_read(
+                self.dir.as_ptr(),
+                &mut info,
+                name.as_mut_ptr(),
+                name.len() as u16,
+            )
+        } {
+            // Stop after the first error, as the directory can't be read any further.
+            self.done = true;
+            return match fs_result(unsafe { sys::storage_file_get_error(self.dir.as_ptr()) }) {
+                Ok(()) | Err(Error::NotExists) => None,
+                Err(e) => Some(Err(e)),
+            };
+        }
+
+        let name = unsafe { CStr::from_ptr(name.as_ptr()) };
+        Some(Ok(DirEntryRef { name, info }))
+    }
+}
+
+impl Iterator for ReadDir {
+    type Item = Result<DirEntry, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut name: DirEntryName = [0; MAX_FILENAME_LEN + 1];
+        let entry = self.next_entry(&mut name)?;
+        Some(entry.map(|entry| entry.to_entry()))
+    }
+}
+
+impl Drop for ReadDir {
+    fn drop(&mut self) {
+        unsafe {
+            sys::storage_dir_close(self.dir.as_ptr());
+            sys::storage_file_free(self.dir.as_ptr());
+        }
+    }
+}
+
+/// An entry of a directory, returned by [`ReadDir`].
+pub struct DirEntry {
+    name: FuriString,
+    info: sys::FileInfo,
+}
+
+impl DirEntry {
+    /// Returns the name of the entry, without the path of the directory.
+    pub fn name(&self) -> &CStr {
+        self.name.as_c_str()
+    }
+
+    /// Returns `true` if the entry is a directory.
+    pub fn is_dir(&self) -> bool {
+        unsafe { sys::file_info_is_dir(&self.info) }
+    }
+
+    /// Returns the size of the entry in bytes, which is zero for directories.
+    pub fn size(&self) -> u64 {
+        self.info.size
+    }
+
+    /// Returns whether the entry is a file or a directory, and its size.
+    pub fn metadata(&self) -> Metadata {
+        Metadata::new(self.is_dir(), self.size())
+    }
+}
+
+/// An entry of a directory whose name is borrowed, returned by [`ReadDir::next_entry`].
+#[derive(Clone, Copy)]
+pub struct DirEntryRef<'a> {
+    name: &'a CStr,
+    info: sys::FileInfo,
+}
+
+impl<'a> DirEntryRef<'a> {
+    /// Returns the name of the entry, without the path of the directory.
+    pub fn name(&self) -> &'a CStr {
+        self.name
+    }
+
+    /// Returns `true` if the entry is a directory.
+    pub fn is_dir(&self) -> bool {
+        unsafe { sys::file_info_is_dir(&self.info) }
+    }
+
+    /// Returns the size of the entry in bytes, which is zero for directories.
+    pub fn size(&self) -> u64 {
+        self.info.size
+    }
+
+    /// Returns whether the entry is a file or a directory, and its size.
+    pub fn metadata(&self) -> Metadata {
+        Metadata::new(self.is_dir(), self.size())
+    }
+
+    /// Copies the entry, so that it no longer borrows the name.
+    pub fn to_entry(&self) -> DirEntry {
+        DirEntry {
+            name: FuriString::from(self.name),
+            info: self.info,
+        }
+    }
+}
+
+/// Reads the whole file at `path`.
+///
+/// The buffer is sized from the size of the file, but the file is read until its end
+/// even if it grew or shrank since, so its contents are never cut short.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use flipperzero::io::Error;
+/// # use flipperzero::storage;
+/// # fn main() -> Result<(), Error> {
+/// let key = storage::read(c"/ext/apps_data/myapp/key.bin")?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub fn read<P: AsPath + ?Sized>(path: &P) -> Result<alloc::vec::Vec<u8>, Error> {
+    let mut file = File::open(path)?;
+    let mut bytes = alloc::vec::Vec::with_capacity(file.size());
+    file.read_to_end(&mut bytes)?;
+    file.close()?;
+    Ok(bytes)
+}
+
+/// Reads the whole file at `path` into a string.
+///
+/// This reads the file as [`read`] does, and fails with [`Error::InvalidData`] if it
+/// isn't valid UTF-8.
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub fn read_to_string<P: AsPath + ?Sized>(path: &P) -> Result<alloc::string::String, Error> {
+    alloc::string::String::from_utf8(read(path)?).map_err(|_| Error::InvalidData)
+}
+
+/// Replaces the contents of the file at `path` with `contents`, creating the file if it
+/// doesn't exist.
+///
+/// The file is synced before this returns. If that fails, or any write does, the file
+/// may be left with only some of `contents`; [`Storage::write_atomic`] avoids that.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use flipperzero::io::Error;
+/// # use flipperzero::storage;
+/// # fn main() -> Result<(), Error> {
+/// storage::write(c"/ext/apps_data/myapp/log.txt", b"started\n")?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub fn write<P: AsPath + ?Sized>(path: &P, contents: &[u8]) -> Result<(), Error> {
+    let mut file = File::create(path)?;
+    file.write_all(contents)?;
+    file.sync()?;
+    file.close()
+}
+
+/// Size of the buffer used by [`copy`].
+const COPY_BUF_SIZE: usize = perf::DEFAULT_CHUNK_SIZE;
+
+/// Copies everything `reader` returns into `writer`, returning the number of bytes
+/// copied.
+///
+/// This works with any pair of readers and writers, such as a file and an
+/// [`I2cEeprom`](crate::devices::I2cEeprom) to dump or restore the EEPROM.
+///
+/// The copy goes through a buffer of [`perf::DEFAULT_CHUNK_SIZE`] bytes on the stack.
+/// Use [`copy_buf`] to pick the buffer, and [`copy_with_progress`] to follow the copy.
+pub fn copy<R, W>(reader: &mut R, writer: &mut W) -> Result<u64, Error>
+where
+    R: Read + ?Sized,
+    W: Write + ?Sized,
+{
+    let mut buf = [0; COPY_BUF_SIZE];
+    copy_buf(reader, writer, &mut buf)
+}
+
+/// Copies everything `reader` returns into `writer` through `buf`, returning the
+/// number of bytes copied.
+///
+/// Each read is for up to the length of `buf`, and is written out with
+/// [`Write::write_all`], so short reads and writes are handled. A read returning 0
+/// bytes ends the copy, as it means the end of the reader.
+///
+/// # Errors
+///
+/// Returns the first error of `reader` or `writer`. The bytes copied until then have
+/// been written, but their number is lost, see [`copy_with_progress`] to keep it.
+///
+/// # Panics
+///
+/// Panics if `buf` is empty.
+pub fn copy_buf<R, W>(reader: &mut R, writer: &mut W, buf: &mut [u8]) -> Result<u64, Error>
+where
+    R: Read + ?Sized,
+    W: Write + ?Sized,
+{
+    copy_with_progress(reader, writer, buf, |_| {})
+}
+
+/// Copies like [`copy_buf`], calling `progress` with the total number of bytes copied
+/// after every chunk.
+///
+/// `progress` is called once per read, so its rate depends on the length of `buf`.
+/// After an error, the last number it was called with is how many bytes were copied.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use flipperzero::io::Error;
+/// # use flipperzero::storage::{self, File};
+/// # fn main() -> Result<(), Error> {
+/// let mut from = File::open(c"/ext/big.bin")?;
+/// let mut to = File::create(c"/ext/big.bin.bak")?;
+/// let total = from.size() as u64;
+/// let mut buf = [0; 4096];
+/// storage::copy_with_progress(&mut from, &mut to, &mut buf, |copied| {
+///     let percent = flipperzero::fmt::usage_percent(copied, total);
+///     flipperzero::println!("{}%", percent);
+/// })?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Panics
+///
+/// Panics if `buf` is empty.
+pub fn copy_with_progress<R, W, F>(
+    reader: &mut R,
+    writer: &mut W,
+    buf: &mut [u8],
+    mut progress: F,
+) -> Result<u64, Error>
+where
+    R: Read + ?Sized,
+    W: Write + ?Sized,
+    F: FnMut(u64),
+{
+    assert!(!buf.is_empty(), "buffer must not be empty");
+
+    let mut copied = 0;
+    loop {
+        let n = reader.read(buf)?;
+        if n == 0 {
+            return Ok(copied);
+        }
+        writer.write_all(&buf[..n])?;
+        copied += n as u64;
+        progress(copied);
+    }
+}
+
+/// Shortens `path` for display by replacing characters in its middle with `...`.
+///
+/// The result is at most `max_chars` characters long (or exactly `...` if `max_chars`
+/// is too small to keep anything), keeping both the start of the path and the end
+/// where the file name and extension usually are. Invalid UTF-8 sequences are
+/// replaced with [`char::REPLACEMENT_CHARACTER`].
+pub fn display_path(path: &CStr, max_chars: usize) -> FuriString {
+    let path = FuriString::from(path);
+    let len = path.chars_lossy().count();
+    if len <= max_chars {
+        return path;
+    }
+
+    let keep = max_chars.saturating_sub(ELLIPSIS.len());
+    let tail = keep / 2;
+    let head = keep - tail;
+
+    path.chars_lossy()
+        .take(head)
+        .chain(ELLIPSIS.chars())
+        .chain(path.chars_lossy().skip(len - tail))
+        .collect()
+}
+
+/// Checks that `name` can be used as the name of a file.
+///
+/// Besides being non-empty and at most [`MAX_FILENAME_LEN`] bytes long, valid names only
+/// contain printable ASCII characters other than `/\\:*?"<>|`, and don't end with a `.`
+/// or a space. This is stricter than what the filesystems on the Flipper Zero accept,
+/// but ensures that the file can be accessed from other devices too.
+pub fn validate_filename(name: &[u8]) -> Result<(), FilenameError> {
+    if name.is_empty() {
+        Err(FilenameError::Empty)
+    } else if name.len() > MAX_FILENAME_LEN {
+        Err(FilenameError::TooLong)
+    } else if !name.iter().copied().all(is_filename_char) {
+        Err(FilenameError::InvalidChar)
+    } else if name.ends_with(b".") || name.ends_with(b" ") {
+        Err(FilenameError::InvalidName)
+    } else {
+        Ok(())
+    }
+}
+
+/// Turns `name` into a valid file name, if possible.
+///
+/// Characters that can't be used in file names are replaced with `_`, trailing dots and
+/// spaces are removed, and the name is cut short at [`MAX_FILENAME_LEN`] bytes. The
+/// result is only empty if `name` consists of nothing but dots and spaces.
+pub fn sanitize_filename(name: &str) -> FuriString {
+    let mut sanitized: FuriString = name
+        .chars()
+        .take(MAX_FILENAME_LEN)
+        .map(|c| match u8::try_from(c) {
+            Ok(b) if is_filename_char(b) => c,
+            _ => '_',
+        })
+        .collect();
+    sanitized.trim_end_matches(&['.', ' '][..]);
+    sanitized
+}
+
+fn is_filename_char(b: u8) -> bool {
+    b == b' ' || (b.is_ascii_graphic() && !RESERVED_CHARS.contains(&b))
+}
+
+/// Returns `true` if `path` is the directory `dir` or a path within it.
+fn starts_with_dir(path: &[u8], dir: &[u8]) -> bool {
+    path.strip_prefix(dir)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(b"/"))
+}
+
+/// Returns `true` if the current thread belongs to an app started by the loader.
+fn in_app() -> bool {
+    let id = unsafe { sys::furi_thread_get_appid(sys::furi_thread_get_current_id()) };
+    // The firmware gives the threads that don't belong to an app these ids.
+    !id.is_null()
+        && !matches!(
+            unsafe { CStr::from_ptr(id) }.to_bytes(),
+            b"unknown" | b"driver"
+        )
+}
+
+impl fmt::Display for FilenameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.description())
+    }
+}
+
+impl ufmt::uDisplay for FilenameError {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        f.write_str(self.description())
+    }
+}
+
+impl FilenameError {
+    /// Description associated with [`FilenameError`].
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Empty => "Name is empty",
+            Self::TooLong => "Name is too long",
+            Self::InvalidChar => "Invalid character",
+            Self::InvalidName => "Invalid name",
+        }
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use flipperzero_sys as sys;
+
+    use super::{
+        copy, copy_buf, copy_with_progress, display_path, sanitize_filename, starts_with_dir,
+        validate_filename, DirEntryName, File, FilenameError, OpenOptions, PathBuf, SdFsType,
+        SdStatus, Storage, MAX_FILENAME_LEN,
+    };
+    use crate::furi::string::FuriString;
+    use crate::io::cursor::Cursor;
+    use crate::io::{Error, Read, Seek, SeekFrom, Write};
+
+    /// Returns the path of `name` in the directory for temporary files on the storage
+    /// mounted at `root`, creating the directory if needed and removing whatever a
+    /// previous run left at the path.
+    fn temp_path_on(root: &str, name: &str) -> PathBuf {
+        let storage = Storage::open();
+        let dir = PathBuf::try_from(root).unwrap().join(".tmp").unwrap();
+        assert!(matches!(
+            storage.create_dir(&dir),
+            Ok(()) | Err(Error::Exists)
+        ));
+        let path = dir.join(name).unwrap();
+        assert!(matches!(
+            storage.remove_dir_all(&path),
+            Ok(()) | Err(Error::NotExists)
+        ));
+        path
+    }
+
+    /// Returns the path of `name` in the directory for temporary files on the SD card,
+    /// as by [`temp_path_on`].
+    fn temp_path(name: &str) -> PathBuf {
+        temp_path_on("/ext", name)
+    }
+
+    /// Creates the file at `path` for reading and writing, replacing any file there.
+    fn create_read_write(path: &PathBuf) -> File {
+        OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_always(true)
+            .open(path)
+            .unwrap()
+    }
+
+    #[test]
+    fn display_path_short() {
+        let path = c"/ext/foo.txt";
+        assert_eq!(display_path(path, 12).to_bytes(), b"/ext/foo.txt");
+        assert_eq!(display_path(path, 20).to_bytes(), b"/ext/foo.txt");
+    }
+
+    #[test]
+    fn display_path_truncates_middle() {
+        let path = c"/ext/foo.txt";
+        assert_eq!(display_path(path, 11).to_bytes(), b"/ext....txt");
+        assert_eq!(display_path(path, 8).to_bytes(), b"/ex...xt");
+        assert_eq!(display_path(path, 3).to_bytes(), b"...");
+        assert_eq!(display_path(path, 0).to_bytes(), b"...");
+    }
+
+    #[test]
+    fn display_path_multibyte() {
+        let path = c"\u{e4}\u{f6}\u{fc}\u{df}\u{e9}\u{e8}";
+        assert_eq!(
+            display_path(path, 5).to_bytes(),
+            "\u{e4}...\u{e8}".as_bytes()
+        );
+    }
+
+    #[test]
+    fn valid_filenames() {
+        assert_eq!(validate_filename(b"cookies.txt"), Ok(()));
+        assert_eq!(validate_filename(b"my file (1)"), Ok(()));
+        assert_eq!(validate_filename(b".hidden"), Ok(()));
+        assert_eq!(validate_filename(&[b'a'; MAX_FILENAME_LEN]), Ok(()));
+    }
+
+    #[test]
+    fn invalid_filenames() {
+        assert_eq!(validate_filename(b""), Err(FilenameError::Empty));
+        assert_eq!(
+            validate_filename(&[b'a'; MAX_FILENAME_LEN + 1]),
+            Err(FilenameError::TooLong)
+        );
+        assert_eq!(validate_filename(b"a/b"), Err(FilenameError::InvalidChar));
+        assert_eq!(validate_filename(b"what?"), Err(FilenameError::InvalidChar));
+        assert_eq!(validate_filename(b"tab\t"), Err(FilenameError::InvalidChar));
+        assert_eq!(
+            validate_filename("caf\u{e9}".as_bytes()),
+            Err(FilenameError::InvalidChar)
+        );
+        assert_eq!(validate_filename(b".."), Err(FilenameError::InvalidName));
+        assert_eq!(validate_filename(b"name."), Err(FilenameError::InvalidName));
+        assert_eq!(validate_filename(b"name "), Err(FilenameError::InvalidName));
+    }
+
+    #[test]
+    fn sanitized_filenames() {
+        assert_eq!(sanitize_filename("cookies.txt").to_bytes(), b"cookies.txt");
+        assert_eq!(sanitize_filename("a/b:c").to_bytes(), b"a_b_c");
+        assert_eq!(sanitize_filename("caf\u{e9}").to_bytes(), b"caf_");
+        assert_eq!(sanitize_filename("name. . ").to_bytes(), b"name");
+        assert!(sanitize_filename("..").is_empty());
+
+        let sanitized = sanitize_filename("what?");
+        assert_eq!(validate_filename(sanitized.to_bytes()), Ok(()));
+    }
+
+    #[test]
+    fn copies_through_short_reads_and_writes() {
+        let data: [u8; 40] = core::array::from_fn(|i| i as u8);
+        let mut reader = Cursor::new(&data).max(7);
+        let mut writer = Cursor::empty([0; 64]).max(3);
+        let mut reported = [0; 8];
+        let mut calls = 0;
+        let copied = copy_with_progress(&mut reader, &mut writer, &mut [0; 16], |n| {
+            reported[calls] = n;
+            calls += 1;
+        });
+        assert_eq!(copied, Ok(40));
+        assert_eq!(writer.data(), data);
+        // Reads are limited by the reader to 7 bytes.
+        assert_eq!(reported[..calls], [7, 14, 21, 28, 35, 40]);
+
+        let mut reader = Cursor::new(&data);
+        let mut writer = Cursor::empty([0; 64]);
+        assert_eq!(copy_buf(&mut reader, &mut writer, &mut [0; 1]), Ok(40));
+        assert_eq!(writer.data(), data);
+        assert_eq!(copy_buf(&mut reader, &mut writer, &mut [0; 1]), Ok(0));
+    }
+
+    #[test]
+    fn copy_errors_stop_the_copy() {
+        let data = [1; 40];
+        let mut reader = Cursor::new(&data).max(8).fail_at(20, Error::NotReady);
+        let mut writer = Cursor::empty([0; 64]);
+        let mut last = 0;
+        let result = copy_with_progress(&mut reader, &mut writer, &mut [0; 8], |n| last = n);
+        assert_eq!(result, Err(Error::NotReady));
+        assert_eq!(last, 20);
+        assert_eq!(writer.data().len(), 20);
+
+        let mut reader = Cursor::new(&data).max(8);
+        let mut writer = Cursor::empty([0; 64]).max(0);
+        assert_eq!(copy(&mut reader, &mut writer), Err(Error::WriteZero));
+    }
+
+    #[test]
+    fn removes_dirs_recursively() {
+        let storage = Storage::open();
+        let dir = temp_path("rs_storage_remove_all");
+        let sub = dir.join("sub").unwrap();
+        storage.create_dir(&dir).unwrap();
+        storage.create_dir(&sub).unwrap();
+        for path in [dir.join("a.txt").unwrap(), sub.join("b.txt").unwrap()] {
+            File::create(&path).unwrap().close().unwrap();
+        }
+
+        // A directory that isn't empty can only be removed recursively.
+        assert!(storage.remove(&dir).is_err());
+        storage.remove_dir_all(&dir).unwrap();
+        assert_eq!(storage.exists(&dir), Ok(false));
+        assert_eq!(storage.remove_dir_all(&dir), Err(Error::NotExists));
+
+        let file = temp_path("rs_storage_remove_all.txt");
+        File::create(&file).unwrap().close().unwrap();
+        storage.remove_dir_all(&file).unwrap();
+        assert_eq!(storage.exists(&file), Ok(false));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn reads_and_writes_whole_files() {
+        let storage = Storage::open();
+        let path = &temp_path("rs_storage_whole");
+
+        super::write(path, b"first").unwrap();
+        assert_eq!(super::read(path).unwrap(), b"first");
+        // Writing replaces the contents rather than overwriting a prefix.
+        super::write(path, b"2nd").unwrap();
+        assert_eq!(super::read_to_string(path).unwrap(), "2nd");
+        super::write(path, b"").unwrap();
+        assert_eq!(super::read(path).unwrap(), b"");
+
+        super::write(path, b"\xff\xfe").unwrap();
+        assert_eq!(super::read_to_string(path), Err(Error::InvalidData));
+
+        storage.remove(path).unwrap();
+        assert_eq!(super::read(path), Err(Error::NotExists));
+        assert_eq!(super::read("/ext/.tmp/a\0b"), Err(Error::InvalidName));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn reads_and_writes_longer_than_u16_max() {
+        use alloc::vec;
+
+        let path = &temp_path("rs_file_huge");
+        let storage = Storage::open();
+        let mut file = create_read_write(path);
+
+        let len = u16::MAX as usize + 100;
+        let mut buf = vec![0u8; len];
+        for (i, byte) in buf.iter_mut().enumerate() {
+            *byte = (i % 251) as u8;
+        }
+        assert_eq!(file.write(&buf).unwrap(), len);
+        assert_eq!(file.stream_len().unwrap(), len);
+
+        file.rewind().unwrap();
+        buf.fill(0);
+        assert_eq!(file.read(&mut buf).unwrap(), len);
+        assert!(buf
+            .iter()
+            .enumerate()
+            .all(|(i, &byte)| byte == (i % 251) as u8));
+
+        drop(file);
+        storage.remove(path).unwrap();
+    }
+
+    #[test]
+    fn seek_computes_positions() {
+        let path = &temp_path("rs_file_seek");
+        let storage = Storage::open();
+        let mut file = create_read_write(path);
+        file.write_all(b"0123456789").unwrap();
+
+        assert_eq!(file.seek(SeekFrom::End(0)).unwrap(), 10);
+        assert_eq!(file.seek(SeekFrom::End(-1)).unwrap(), 9);
+        assert_eq!(file.seek(SeekFrom::Current(-3)).unwrap(), 6);
+        assert_eq!(file.seek(SeekFrom::Start(2)).unwrap(), 2);
+        assert_eq!(file.stream_position().unwrap(), 2);
+        assert_eq!(
+            file.seek(SeekFrom::Current(-3)),
+            Err(Error::InvalidParameter)
+        );
+        assert_eq!(file.seek(SeekFrom::End(-11)), Err(Error::InvalidParameter));
+        assert_eq!(file.stream_position().unwrap(), 2);
+        assert!(matches!(
+            file.seek(SeekFrom::Start(u64::from(u32::MAX) + 1)),
+            Err(Error::InvalidParameter)
+        ));
+
+        let mut byte = [0];
+        assert_eq!(file.read(&mut byte).unwrap(), 1);
+        assert_eq!(byte, *b"2");
+
+        drop(file);
+        storage.remove(path).unwrap();
+    }
+
+    #[test]
+    fn seek_past_end_of_read_only_file() {
+        let path = &temp_path("rs_file_past_end");
+        let storage = Storage::open();
+        File::create(path)
+            .unwrap()
+            .write_all(b"0123456789")
+            .unwrap();
+
+        let mut file = OpenOptions::new().read(true).open(path).unwrap();
+        assert_eq!(file.seek(SeekFrom::Start(15)).unwrap(), 15);
+        assert_eq!(file.stream_position().unwrap(), 15);
+        assert_eq!(file.read(&mut [0; 4]).unwrap(), 0);
+        assert_eq!(file.seek(SeekFrom::Current(-7)).unwrap(), 8);
+        assert_eq!(file.stream_position().unwrap(), 8);
+
+        let mut byte = [0];
+        assert_eq!(file.read(&mut byte).unwrap(), 1);
+        assert_eq!(byte, *b"8");
+
+        drop(file);
+        storage.remove(path).unwrap();
+    }
+
+    #[test]
+    fn access_mode_is_enforced() {
+        let path = &temp_path("rs_file_access");
+        let storage = Storage::open();
+
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_always(true)
+            .open(path)
+            .unwrap();
+        file.write_all(b"0123456789").unwrap();
+        file.rewind().unwrap();
+        // Tells a failed read apart from the end of the file.
+        assert!(file.read(&mut [0; 4]).is_err());
+        drop(file);
+
+        let mut file = OpenOptions::new().read(true).open(path).unwrap();
+        assert!(file.write(b"abc").is_err());
+        assert!(file.write_all(b"abc").is_err());
+        let mut buf = [0; 10];
+        assert_eq!(file.read(&mut buf).unwrap(), 10);
+        assert_eq!(buf, *b"0123456789");
+        assert_eq!(file.read(&mut buf).unwrap(), 0);
+
+        drop(file);
+        storage.remove(path).unwrap();
+    }
+
+    #[test]
+    fn set_len_truncates_and_extends() {
+        let path = &temp_path("rs_file_set_len");
+        let storage = Storage::open();
+        let mut file = create_read_write(path);
+        file.write_all(b"0123456789").unwrap();
+        file.flush().unwrap();
+        assert_eq!(file.size(), 10);
+        assert!(file.is_eof());
+
+        file.seek(SeekFrom::Start(2)).unwrap();
+        file.set_len(4).unwrap();
+        assert_eq!(file.size(), 4);
+        assert_eq!(file.stream_position().unwrap(), 2);
+        assert!(!file.is_eof());
+
+        file.set_len(100).unwrap();
+        assert_eq!(file.size(), 100);
+        assert_eq!(file.stream_position().unwrap(), 2);
+        let mut buf = [0xff; 100];
+        file.rewind().unwrap();
+        file.read_exact(&mut buf).unwrap();
+        assert_eq!(buf[..4], *b"0123");
+        assert!(buf[4..].iter().all(|&b| b == 0));
+        assert!(file.is_eof());
+
+        // The cursor is moved back to the new end.
+        file.set_len(1).unwrap();
+        assert_eq!(file.size(), 1);
+        assert_eq!(file.stream_position().unwrap(), 1);
+        file.set_len(1).unwrap();
+        assert_eq!(file.size(), 1);
+
+        file.write_all(b"abc").unwrap();
+        file.seek(SeekFrom::Start(2)).unwrap();
+        file.truncate().unwrap();
+        assert_eq!(file.size(), 2);
+        assert!(file.is_eof());
+        file.rewind().unwrap();
+        file.read_exact(&mut buf[..2]).unwrap();
+        assert_eq!(buf[..2], *b"0a");
+        file.set_len(1).unwrap();
+        file.close().unwrap();
+
+        let mut file = OpenOptions::new().read(true).open(path).unwrap();
+        assert_eq!(file.size(), 1);
+        file.seek(SeekFrom::Start(1)).unwrap();
+        assert!(file.set_len(0).is_err());
+        assert_eq!(file.stream_position().unwrap(), 1);
+        assert!(file.set_len(5).is_err());
+        assert_eq!(file.stream_position().unwrap(), 1);
+        assert_eq!(file.size(), 1);
+        file.close().unwrap();
+
+        storage.remove(path).unwrap();
+    }
+
+    #[test]
+    fn files_release_the_record() {
+        use core::sync::atomic::Ordering;
+
+        use super::record::USERS;
+
+        let users = || USERS.load(Ordering::Relaxed);
+        let storage = Storage::open();
+        let path = &temp_path("rs_file_record");
+        let missing = &temp_path("rs_file_record_missing");
+        let before = users();
+
+        let write = OpenOptions::new().write(true).create_always(true);
+        let file = storage.open_file(path, write).unwrap();
+        let other = File::create(path).unwrap();
+        assert_eq!(users(), before + 2);
+        drop(file);
+        other.close().unwrap();
+        assert_eq!(users(), before);
+
+        let read = OpenOptions::new().read(true).open_existing(true);
+        assert_eq!(
+            storage.open_file(missing, read).err(),
+            Some(Error::NotExists)
+        );
+        assert_eq!(File::open(missing).err(), Some(Error::NotExists));
+        assert!(storage.open_file(path, OpenOptions::new()).is_err());
+        storage.md5sum(path).unwrap();
+        assert_eq!(users(), before);
+
+        storage.remove(path).unwrap();
+    }
+
+    #[test]
+    fn resolves_app_aliases() {
+        let storage = Storage::open();
+        let data = storage.app_data_path("").unwrap();
+        let data = data.as_c_str().to_bytes();
+        assert!(data.starts_with(b"/ext/apps_data/"));
+        assert!(data.len() > b"/ext/apps_data/".len());
+        assert_eq!(
+            storage.dir_exists(&storage.app_data_path("").unwrap()),
+            Ok(true)
+        );
+
+        let config = storage.app_data_path("config.txt").unwrap();
+        assert!(config.as_c_str().to_bytes().starts_with(data));
+        assert!(config.as_c_str().to_bytes().ends_with(b"/config.txt"));
+        assert_eq!(storage.resolve_path("config.txt"), Ok(config.clone()));
+        assert_eq!(storage.resolve_path("/data/config.txt"), Ok(config));
+
+        let assets = storage.app_assets_path("icon.png").unwrap();
+        assert!(assets
+            .as_c_str()
+            .to_bytes()
+            .starts_with(b"/ext/apps_assets/"));
+        assert!(assets.as_c_str().to_bytes().ends_with(b"/icon.png"));
+
+        assert_eq!(
+            storage.resolve_path("/ext/file.txt").unwrap().as_c_str(),
+            c"/ext/file.txt"
+        );
+        assert_eq!(storage.resolve_path("a\0b"), Err(Error::InvalidName));
+    }
+
+    #[test]
+    fn alias_prefixes_are_directories() {
+        assert!(starts_with_dir(b"/data", b"/data"));
+        assert!(starts_with_dir(b"/data/a.txt", b"/data"));
+        assert!(!starts_with_dir(b"/database", b"/data"));
+        assert!(!starts_with_dir(b"/ext/data", b"/data"));
+    }
+
+    #[test]
+    fn fs_info_reports_capacity() {
+        let storage = Storage::open();
+        for path in ["/int", "/ext"] {
+            let info = storage.fs_info(path).unwrap();
+            assert!(info.total_space > 0);
+            assert!(info.free_space <= info.total_space);
+        }
+        // The tests run from the SD card.
+        assert_eq!(storage.sd_status(), SdStatus::Ok);
+        assert!(storage.fs_info("/nowhere").is_err());
+    }
+
+    #[test]
+    fn sd_statuses_map_from_errors() {
+        assert!(SdStatus::from_sys(sys::FS_Error_FSE_OK).is_ok());
+        assert_eq!(
+            SdStatus::from_sys(sys::FS_Error_FSE_NOT_READY),
+            SdStatus::NotReady
+        );
+        assert_eq!(
+            SdStatus::from_sys(sys::FS_Error_FSE_INTERNAL),
+            SdStatus::Error(Error::Internal)
+        );
+    }
+
+    #[test]
+    fn creates_numbered_files() {
+        let storage = Storage::open();
+        let dir = &temp_path("rs_numbered");
+        storage.create_dir(dir).unwrap();
+        let prefix = c"signal";
+        let extension = c".sub";
+
+        let mut paths = [const { None }; 3];
+        for (name, path) in ["signal.sub", "signal1.sub", "signal2.sub"]
+            .into_iter()
+            .zip(&mut paths)
+        {
+            let (mut file, created) = storage.create_next_file(dir, prefix, extension).unwrap();
+            file.write_all(b"data").unwrap();
+            let expected = dir.join(name).unwrap();
+            assert_eq!(created, expected);
+            *path = Some(created);
+        }
+        assert_eq!(
+            storage.metadata(paths[0].as_ref().unwrap()).unwrap().size(),
+            4
+        );
+
+        // Gaps are filled first.
+        storage.remove(paths[1].as_ref().unwrap()).unwrap();
+        let (_, created) = storage.create_next_file(dir, prefix, extension).unwrap();
+        assert_eq!(Some(created), paths[1]);
+
+        assert!(matches!(
+            storage.create_next_file(&dir.join("missing").unwrap(), prefix, extension),
+            Err(Error::NotExists)
+        ));
+
+        for path in paths.iter().flatten() {
+            storage.remove(path).unwrap();
+        }
+        storage.remove(dir).unwrap();
+    }
+
+    #[test]
+    fn conflicting_options_are_rejected() {
+        type Mode = fn(OpenOptions, bool) -> OpenOptions;
+        let modes: [Mode; 5] = [
+            OpenOptions::open_existing,
+            OpenOptions::open_always,
+            OpenOptions::open_append,
+            OpenOptions::create_new,
+            OpenOptions::create_always,
+        ];
+        let path = &temp_path("rs_file_conflict");
+        let storage = Storage::open();
+
+        for (i, first) in modes.iter().enumerate() {
+            for second in &modes[i + 1..] {
+                let options = second(first(OpenOptions::new().write(true), true), true);
+                assert!(matches!(options.open(path), Err(Error::InvalidParameter)));
+            }
+            // Setting a mode again or clearing it is fine.
+            let options = first(OpenOptions::new().write(true), true);
+            assert!(first(options, true).validate().is_ok());
+            assert!(first(options.create_always(true), false).validate().is_ok());
+        }
+        assert!(matches!(
+            OpenOptions::new().create_always(true).open(path),
+            Err(Error::InvalidParameter)
+        ));
+        assert!(!storage.exists(path).unwrap());
+    }
+
+    #[test]
+    fn open_and_create_files() {
+        let path = &temp_path("rs_file_open");
+        let storage = Storage::open();
+
+        assert!(matches!(File::open(path), Err(Error::NotExists)));
+
+        let mut file = File::create(path).unwrap();
+        file.write_all(b"0123456789").unwrap();
+        file.close().unwrap();
+
+        let mut file = File::open(path).unwrap();
+        assert!(file.write(b"abc").is_err());
+        let mut buf = [0; 10];
+        file.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, *b"0123456789");
+        drop(file);
+
+        // Creating an existing file empties it.
+        let mut file = File::create(path).unwrap();
+        assert_eq!(file.size(), 0);
+        file.write_all(b"ab").unwrap();
+        drop(file);
+        assert_eq!(storage.metadata(path).unwrap().size(), 2);
+
+        storage.remove(path).unwrap();
+    }
+
+    #[test]
+    fn md5sums_files() {
+        let path = &temp_path("rs_file_md5");
+        let storage = Storage::open();
+        let mut buf = [0; 32];
+
+        File::create(path).unwrap().close().unwrap();
+        let digest = storage.md5sum(path).unwrap();
+        assert_eq!(
+            crate::fmt::hex(&digest, &mut buf),
+            "d41d8cd98f00b204e9800998ecf8427e"
+        );
+
+        let mut file = File::create(path).unwrap();
+        file.write_all(b"abc").unwrap();
+        file.close().unwrap();
+        let digest = storage.md5sum(path).unwrap();
+        assert_eq!(
+            crate::fmt::hex(&digest, &mut buf),
+            "900150983cd24fb0d6963f7d28e17f72"
+        );
+
+        storage.remove(path).unwrap();
+        assert_eq!(storage.md5sum(path), Err(Error::NotExists));
+        assert!(storage.md5sum("/nowhere/rs_file_md5").is_err());
+    }
+
+    #[test]
+    fn crc32sums_files() {
+        let path = &temp_path("rs_file_crc32");
+        let storage = Storage::open();
+
+        File::create(path).unwrap().close().unwrap();
+        assert_eq!(storage.crc32sum(path), Ok(0));
+
+        let mut file = File::create(path).unwrap();
+        file.write_all(b"123456789").unwrap();
+        file.close().unwrap();
+        assert_eq!(storage.crc32sum(path), Ok(0xcbf4_3926));
+
+        storage.remove(path).unwrap();
+        assert_eq!(storage.crc32sum(path), Err(Error::NotExists));
+    }
+
+    #[test]
+    fn writes_atomically() {
+        let dir = &temp_path("rs_atomic");
+        let path = &dir.join("state.txt").unwrap();
+        let temp = &dir.join("state.txt.tmp").unwrap();
+        let storage = Storage::open();
+        storage.create_dir(dir).unwrap();
+        let read = || {
+            let mut buf = [0; 16];
+            let mut file = File::open(path).unwrap();
+            let n = file.read(&mut buf).unwrap();
+            (buf, n)
+        };
+
+        storage.write_atomic(path, b"first").unwrap();
+        let (buf, n) = read();
+        assert_eq!(buf[..n], *b"first");
+        storage.write_atomic(path, b"second").unwrap();
+        let (buf, n) = read();
+        assert_eq!(buf[..n], *b"second");
+        assert!(!storage.exists(temp).unwrap());
+
+        // A taken temporary name is left alone.
+        File::create(temp).unwrap().write_all(b"other").unwrap();
+        storage
+            .write_atomic_with(path, |file| {
+                file.write_all(b"th")?;
+                file.write_all(b"ird")
+            })
+            .unwrap();
+        let (buf, n) = read();
+        assert_eq!(buf[..n], *b"third");
+        assert_eq!(storage.metadata(temp).unwrap().size(), 5);
+        assert!(!storage
+            .exists(&dir.join("state.txt1.tmp").unwrap())
+            .unwrap());
+        storage.remove(temp).unwrap();
+
+        // Failures keep the old contents and remove the temporary file.
+        assert_eq!(
+            storage.write_atomic_with(path, |file| {
+                file.write_all(b"partial")?;
+                Err(Error::Internal)
+            }),
+            Err(Error::Internal)
+        );
+        let open = File::open(path).unwrap();
+        assert_eq!(
+            storage.write_atomic(path, b"fourth"),
+            Err(Error::AlreadyOpen)
+        );
+        drop(open);
+        let (buf, n) = read();
+        assert_eq!(buf[..n], *b"third");
+        assert!(!storage.exists(temp).unwrap());
+
+        assert_eq!(
+            storage.write_atomic(&dir.join("missing/state.txt").unwrap(), b""),
+            Err(Error::NotExists)
+        );
+        assert_eq!(
+            storage.write_atomic("state.txt", b""),
+            Err(Error::InvalidName)
+        );
+
+        storage.remove(path).unwrap();
+        storage.remove(dir).unwrap();
+    }
+
+    #[test]
+    fn sd_info_describes_the_card() {
+        let storage = Storage::open();
+        let info = storage.sd_info().unwrap();
+        assert_ne!(info.fs_type(), SdFsType::Unknown);
+        let fs_info = info.fs_info();
+        assert!(fs_info.free_space <= fs_info.total_space);
+        assert!(fs_info.total_space > 0);
+        assert_eq!(info.sector_size(), 512);
+        assert!(info.oem_id().to_bytes().len() <= 2);
+        assert!(info.product_name().to_bytes().len() <= 5);
+        let (_, month) = info.manufacturing_date();
+        assert!((1..=12).contains(&month));
+    }
+
+    #[test]
+    fn open_files_lock_their_path() {
+        // Opening a path this thread holds open would block forever, so only removing
+        // the file is checked while it is open.
+        let storage = Storage::open();
+        let path = &temp_path("rs_locked.txt");
+        let options = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_always(true);
+        let file = storage.open_file(path, options).unwrap();
+        assert!(file.is_open());
+        assert!(matches!(storage.remove(path), Err(Error::AlreadyOpen)));
+        drop(file);
+
+        let len = storage
+            .with_file_locked(path, options, |file| {
+                assert!(matches!(storage.remove(path), Err(Error::AlreadyOpen)));
+                file.write_all(b"locked")?;
+                Ok(file.size())
+            })
+            .unwrap();
+        assert_eq!(len, 6);
+        assert_eq!(
+            storage.with_file_locked(path, options, |_| Err::<(), _>(Error::Internal)),
+            Err(Error::Internal)
+        );
+        // The file was closed after the error.
+        storage.remove(path).unwrap();
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn retry_while_open_waits_for_the_file_to_close() {
+        use core::time::Duration;
+
+        use crate::furi::{self, time::Instant};
+
+        let storage = Storage::open();
+        let path = &temp_path("rs_retry.txt");
+        let file = File::create(path).unwrap();
+
+        let start = Instant::now();
+        assert!(matches!(
+            storage.retry_while_open(Duration::from_millis(50), |s| s.remove(path)),
+            Err(Error::AlreadyOpen)
+        ));
+        assert!(start.elapsed().as_millis() >= 50);
+
+        let holder = furi::thread::spawn(move || {
+            furi::thread::sleep(Duration::from_millis(50));
+            drop(file);
+            0
+        });
+        storage
+            .retry_while_open(Duration::from_secs(5), |s| s.remove(path))
+            .unwrap();
+        holder.join();
+        assert!(!storage.exists(path).unwrap());
+
+        // Other errors are returned without trying again.
+        let start = Instant::now();
+        assert!(matches!(
+            storage.retry_while_open(Duration::from_secs(5), |s| s.remove(path)),
+            Err(Error::NotExists)
+        ));
+        assert!(start.elapsed().as_millis() < 1000);
+    }
+
+    #[test]
+    fn storage_handles_are_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Storage>();
+    }
+
+    #[test]
+    fn common_operations_report_errors() {
+        let dir = &temp_path("rs_common");
+        let file = &dir.join("file").unwrap();
+        let copy = &dir.join("copy").unwrap();
+        let moved = &temp_path_on("/int", "rs_common_moved");
+        let storage = Storage::open();
+
+        assert_eq!(storage.metadata(dir), Err(Error::NotExists));
+        assert_eq!(storage.dir_exists(dir), Ok(false));
+        storage.create_dir(dir).unwrap();
+        assert_eq!(storage.dir_exists(dir), Ok(true));
+        assert_eq!(storage.file_exists(dir), Ok(false));
+
+        let mut f = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(file)
+            .unwrap();
+        f.write_all(b"0123456789").unwrap();
+        drop(f);
+        assert_eq!(storage.file_exists(file), Ok(true));
+
+        storage.copy(file, copy).unwrap();
+        assert_eq!(storage.metadata(copy).map(|m| m.size()), Ok(10));
+
+        // The directory isn't empty.
+        assert!(storage.remove(dir).is_err());
+        assert_eq!(storage.dir_exists(dir), Ok(true));
+
+        // The firmware moves files across filesystems by copying them.
+        storage.rename(copy, moved).unwrap();
+        assert_eq!(storage.file_exists(copy), Ok(false));
+        assert_eq!(storage.metadata(moved).map(|m| m.size()), Ok(10));
+        storage.remove(moved).unwrap();
+
+        storage.remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(feature = "embedded-io")]
+    #[test]
+    fn files_implement_embedded_io() {
+        fn assert_impls<F: embedded_io::Read + embedded_io::Write + embedded_io::Seek>() {}
+        assert_impls::<super::File>();
+
+        let path = &temp_path("rs_embedded_io");
+        let storage = Storage::open();
+        let mut file = create_read_write(path);
+        embedded_io::Write::write_all(&mut file, b"0123456789").unwrap();
+        let pos = embedded_io::SeekFrom::End(-4);
+        assert_eq!(embedded_io::Seek::seek(&mut file, pos), Ok(6));
+        let mut buf = [0; 4];
+        embedded_io::Read::read_exact(&mut file, &mut buf).unwrap();
+        assert_eq!(&buf, b"6789");
+        assert!(matches!(
+            embedded_io::Read::read_exact(&mut file, &mut buf),
+            Err(embedded_io::ReadExactError::UnexpectedEof)
+        ));
+
+        drop(file);
+        storage.remove(path).unwrap();
+    }
+
+    #[test]
+    fn str_paths_are_accepted() {
+        let storage = Storage::open();
+        let path = temp_path("rs_str_path");
+
+        drop(
+            OpenOptions::new()
+                .write(true)
+                .create_always(true)
+                .open(&path)
+                .unwrap(),
+        );
+        assert_eq!(storage.file_exists("/ext/.tmp/rs_str_path"), Ok(true));
+        assert!(OpenOptions::new()
+            .read(true)
+            .open("/ext/.tmp/rs_str_path")
+            .is_ok());
+        assert_eq!(
+            OpenOptions::new()
+                .read(true)
+                .open("/ext/.tmp/rs\0str_path")
+                .err(),
+            Some(Error::InvalidName)
+        );
+        storage.remove(&path).unwrap();
+    }
+
+    #[test]
+    fn read_dir_reads_every_entry() {
+        let dir = &temp_path("rs_read_dir");
+        let storage = Storage::open();
+        storage.create_dir(dir).unwrap();
+        for i in 0..20u8 {
+            let mut name = FuriString::from("file");
+            name.push(char::from(b'a' + i));
+            OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&dir.join(&name).unwrap())
+                .unwrap();
+        }
+
+        let mut seen = [false; 20];
+        let mut entries = storage.read_dir(dir).unwrap();
+        let mut name: DirEntryName = [0; MAX_FILENAME_LEN + 1];
+        while let Some(entry) = entries.next_entry(&mut name) {
+            let entry = entry.unwrap();
+            let name = entry.name().to_bytes();
+            assert_eq!(&name[..4], b"file");
+            assert!(entry.metadata().is_file());
+            assert_eq!(entry.to_entry().name().to_bytes(), name);
+            seen[usize::from(name[4] - b'a')] = true;
+        }
+        assert!(seen.iter().all(|&seen| seen));
+        assert!(entries.next().is_none());
+
+        assert_eq!(storage.read_dir(dir).unwrap().count(), 20);
+
+        storage.remove_dir_all(dir).unwrap();
+    }
+}
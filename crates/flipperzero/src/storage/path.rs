@@ -0,0 +1,319 @@
+//! Paths given to the storage service as either C strings or Rust strings.
+
+use core::ffi::CStr;
+use core::fmt;
+
+#[cfg(feature = "alloc")]
+use alloc::{ffi::CString, string::String};
+
+use crate::furi::string::FuriString;
+use crate::io::Error;
+
+/// A path that can be passed to the storage APIs, such as [`OpenOptions::open`].
+///
+/// This is implemented for [`CStr`], which is passed to the storage service as-is, and
+/// for [`str`], which is first copied into a [`FuriString`]. Paths containing a nul
+/// byte fail with [`Error::InvalidName`].
+///
+/// [`OpenOptions::open`]: super::OpenOptions::open
+pub trait AsPath {
+    /// Calls `f` with the path as a C string.
+    ///
+    /// Fails with [`Error::InvalidName`] without calling `f` if the path contains a nul
+    /// byte.
+    fn with_c_str<R>(&self, f: impl FnOnce(&CStr) -> Result<R, Error>) -> Result<R, Error>;
+}
+
+impl AsPath for CStr {
+    fn with_c_str<R>(&self, f: impl FnOnce(&CStr) -> Result<R, Error>) -> Result<R, Error> {
+        f(self)
+    }
+}
+
+impl AsPath for str {
+    fn with_c_str<R>(&self, f: impl FnOnce(&CStr) -> Result<R, Error>) -> Result<R, Error> {
+        if self.contains('\0') {
+            return Err(Error::InvalidName);
+        }
+        f(FuriString::from(self).as_c_str())
+    }
+}
+
+impl AsPath for FuriString {
+    fn with_c_str<R>(&self, f: impl FnOnce(&CStr) -> Result<R, Error>) -> Result<R, Error> {
+        f(self.as_c_str())
+    }
+}
+
+impl AsPath for PathBuf {
+    fn with_c_str<R>(&self, f: impl FnOnce(&CStr) -> Result<R, Error>) -> Result<R, Error> {
+        f(self.as_c_str())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl AsPath for CString {
+    fn with_c_str<R>(&self, f: impl FnOnce(&CStr) -> Result<R, Error>) -> Result<R, Error> {
+        f(self)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl AsPath for String {
+    fn with_c_str<R>(&self, f: impl FnOnce(&CStr) -> Result<R, Error>) -> Result<R, Error> {
+        self.as_str().with_c_str(f)
+    }
+}
+
+impl<P: AsPath + ?Sized> AsPath for &P {
+    fn with_c_str<R>(&self, f: impl FnOnce(&CStr) -> Result<R, Error>) -> Result<R, Error> {
+        (**self).with_c_str(f)
+    }
+}
+
+/// An owned path, for building paths at runtime.
+///
+/// The path is kept in a [`FuriString`], so this doesn't require the `alloc` feature.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use flipperzero::io::Error;
+/// # use flipperzero::storage::{OpenOptions, PathBuf};
+/// # fn main() -> Result<(), Error> {
+/// let dir = PathBuf::from(c"/ext/apps_data/myapp/");
+/// let file = OpenOptions::new().read(true).open(&dir.join("saves/slot1.bin")?)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct PathBuf(FuriString);
+
+impl PathBuf {
+    /// Creates an empty path.
+    pub fn new() -> Self {
+        Self(FuriString::new())
+    }
+
+    /// Returns the path as a C string.
+    pub fn as_c_str(&self) -> &CStr {
+        self.0.as_c_str()
+    }
+
+    /// Returns the path as a [`FuriString`].
+    pub fn as_furi_str(&self) -> &FuriString {
+        &self.0
+    }
+
+    /// Returns the [`FuriString`] holding the path.
+    pub fn into_furi_string(self) -> FuriString {
+        self.0
+    }
+
+    /// Appends `name` to the path, separated by a single `/`.
+    ///
+    /// Unlike `std::path::PathBuf::push`, an absolute `name` is appended rather than
+    /// replacing the path, so `/ext/` and `/data.bin` make `/ext/data.bin`. Nothing is
+    /// added for an empty `name`.
+    ///
+    /// Fails with [`Error::InvalidName`], leaving the path unchanged, if `name` contains
+    /// a nul byte.
+    pub fn push<P: AsPath + ?Sized>(&mut self, name: &P) -> Result<(), Error> {
+        name.with_c_str(|name| {
+            let name = name.to_bytes_with_nul();
+            let start = name.iter().take_while(|&&b| b == b'/').count();
+            if name[start] == 0 {
+                return Ok(());
+            }
+            if !self.0.is_empty() && !self.0.ends_with('/') {
+                self.0.push('/');
+            }
+            // Only leading separators were skipped, which keeps the terminator.
+            self.0
+                .push_c_str(CStr::from_bytes_with_nul(&name[start..]).unwrap());
+            Ok(())
+        })
+    }
+
+    /// Returns the path with `name` appended, as by [`PathBuf::push`].
+    pub fn join<P: AsPath + ?Sized>(&self, name: &P) -> Result<Self, Error> {
+        let mut path = self.clone();
+        path.push(name)?;
+        Ok(path)
+    }
+
+    /// Removes the last component of the path and the `/` before it, returning `false`
+    /// if there is none.
+    ///
+    /// The `/` at the start of an absolute path is kept, so `/ext` becomes `/`.
+    pub fn pop(&mut self) -> bool {
+        let bytes = self.0.to_bytes();
+        let end = bytes.len() - trailing_separators(bytes);
+        if end == 0 {
+            return false;
+        }
+        let parent = match bytes[..end].iter().rposition(|&b| b == b'/') {
+            Some(i) => (i - trailing_separators(&bytes[..i])).max(1),
+            None => 0,
+        };
+        self.0.truncate(parent);
+        true
+    }
+
+    /// Returns the last component of the path, or `None` if the path is empty or ends
+    /// with a `/`.
+    pub fn file_name(&self) -> Option<&CStr> {
+        let bytes = self.0.to_bytes_with_nul();
+        let start = bytes.iter().rposition(|&b| b == b'/').map_or(0, |i| i + 1);
+        (bytes[start] != 0).then(|| CStr::from_bytes_with_nul(&bytes[start..]).unwrap())
+    }
+
+    /// Returns the part of the [file name](PathBuf::file_name) after its last `.`, or
+    /// `None` if it has none.
+    ///
+    /// A `.` at the start of the file name, as in `.config`, doesn't start an
+    /// extension.
+    pub fn extension(&self) -> Option<&CStr> {
+        let name = self.file_name()?.to_bytes_with_nul();
+        let dot = name.iter().rposition(|&b| b == b'.').filter(|&i| i > 0)?;
+        Some(CStr::from_bytes_with_nul(&name[dot + 1..]).unwrap())
+    }
+}
+
+/// Returns the number of `/` at the end of `path`.
+fn trailing_separators(path: &[u8]) -> usize {
+    path.iter().rev().take_while(|&&b| b == b'/').count()
+}
+
+impl From<&CStr> for PathBuf {
+    fn from(path: &CStr) -> Self {
+        Self(FuriString::from(path))
+    }
+}
+
+impl From<FuriString> for PathBuf {
+    fn from(path: FuriString) -> Self {
+        Self(path)
+    }
+}
+
+impl TryFrom<&str> for PathBuf {
+    type Error = Error;
+
+    /// Fails with [`Error::InvalidName`] if `path` contains a nul byte.
+    fn try_from(path: &str) -> Result<Self, Error> {
+        path.with_c_str(|path| Ok(Self::from(path)))
+    }
+}
+
+impl AsRef<CStr> for PathBuf {
+    fn as_ref(&self) -> &CStr {
+        self.as_c_str()
+    }
+}
+
+impl fmt::Debug for PathBuf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Display for PathBuf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl ufmt::uDisplay for PathBuf {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        ufmt::uDisplay::fmt(&self.0, f)
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use super::{AsPath, PathBuf};
+    use crate::io::Error;
+
+    #[test]
+    fn strs_become_c_strs() {
+        assert_eq!(
+            "/ext/a.txt".with_c_str(|path| Ok(path.to_bytes().len())),
+            Ok(10)
+        );
+        assert_eq!(c"/ext".with_c_str(|path| Ok(path.to_bytes().len())), Ok(4));
+    }
+
+    #[test]
+    fn nul_bytes_are_invalid_names() {
+        assert_eq!(
+            "/ext/a\0.txt".with_c_str(|_| -> Result<(), Error> { unreachable!() }),
+            Err(Error::InvalidName)
+        );
+        assert!(PathBuf::try_from("/ext\0").is_err());
+
+        let mut dir = PathBuf::try_from("/ext").unwrap();
+        assert_eq!(dir.push("a\0b"), Err(Error::InvalidName));
+        assert_eq!(dir.as_c_str(), c"/ext");
+    }
+
+    #[test]
+    fn joins_with_one_separator() {
+        for (dir, name) in [
+            ("/ext", "a.txt"),
+            ("/ext/", "a.txt"),
+            ("/ext", "/a.txt"),
+            ("/ext/", "//a.txt"),
+        ] {
+            let dir = PathBuf::try_from(dir).unwrap();
+            assert_eq!(dir.join(name).unwrap().as_c_str(), c"/ext/a.txt");
+        }
+
+        let dir = PathBuf::from(c"/ext/apps_data");
+        let file = dir.join("myapp").unwrap().join(c"save.bin").unwrap();
+        assert_eq!(file.as_c_str(), c"/ext/apps_data/myapp/save.bin");
+
+        assert_eq!(dir.join("").unwrap(), dir);
+        assert_eq!(PathBuf::new().join("ext").unwrap().as_c_str(), c"ext");
+    }
+
+    #[test]
+    fn file_names_and_extensions() {
+        for (file, name, extension) in [
+            (c"/ext/apps_data/save.bin", Some(c"save.bin"), Some(c"bin")),
+            (c"archive.tar.gz", Some(c"archive.tar.gz"), Some(c"gz")),
+            (c"/ext/.config", Some(c".config"), None),
+            (c"/ext/notes.", Some(c"notes."), Some(c"")),
+            (c"/ext/README", Some(c"README"), None),
+            (c"/ext/dir/", None, None),
+            (c"", None, None),
+        ] {
+            let file = PathBuf::from(file);
+            assert_eq!(file.file_name(), name);
+            assert_eq!(file.extension(), extension);
+        }
+    }
+
+    #[test]
+    fn pop_removes_the_last_component() {
+        let mut file = PathBuf::from(c"/ext//apps_data/myapp/");
+        assert!(file.pop());
+        assert_eq!(file.as_c_str(), c"/ext//apps_data");
+        assert!(file.pop());
+        assert_eq!(file.as_c_str(), c"/ext");
+        assert!(file.pop());
+        assert_eq!(file.as_c_str(), c"/");
+        assert!(!file.pop());
+        assert_eq!(file.as_c_str(), c"/");
+
+        let mut file = PathBuf::from(c"saves/slot1.bin");
+        assert!(file.pop());
+        assert_eq!(file.as_c_str(), c"saves");
+        assert!(file.pop());
+        assert!(file.as_c_str().is_empty());
+        assert!(!file.pop());
+    }
+}
@@ -0,0 +1,120 @@
+//! Default I/O sizes for each storage backend.
+//!
+//! The internal flash (`/int`) runs LittleFS, which rewrites whole flash pages and gains
+//! little from large requests, while the SD card (`/ext`) runs FatFS over SPI, where
+//! every request has a fixed cost and larger requests write several sectors at once.
+//!
+//! # Methodology
+//!
+//! The chunk sizes below are starting points, which haven't been measured yet. To
+//! measure them, run the `storage_bench` example on a device with an SD card that has
+//! been freshly formatted by the Flipper. For each backend it writes and then reads
+//! back a file as a series of requests of one chunk size, for chunk sizes from 16 bytes
+//! to 4 KiB, and prints the throughput of each. The recommended size is the smallest
+//! one within roughly 10% of the best throughput of both reading and writing, since
+//! buffers come out of a small heap, and often out of a thread's stack.
+//!
+//! The example then writes records of an odd size through a `BufWriter` with and without
+//! [`recommended_alignment`]. Whether aligned writes are faster on a real card hasn't
+//! been measured either.
+
+use core::ffi::CStr;
+
+/// Chunk size for files on the internal flash, in bytes.
+pub const INT_CHUNK_SIZE: usize = 256;
+
+/// Chunk size for files on the SD card, in bytes.
+pub const EXT_CHUNK_SIZE: usize = 4096;
+
+/// Chunk size used when the backend isn't known, in bytes.
+///
+/// This is [`SD_SECTOR_SIZE`], so that it doesn't split sectors of the SD card, and is
+/// still cheap to keep on the stack.
+pub const DEFAULT_CHUNK_SIZE: usize = SD_SECTOR_SIZE;
+
+/// Size of a sector of the SD card, in bytes.
+///
+/// The card writes whole sectors, so a write that starts or ends within a sector makes
+/// it read and rewrite the rest of that sector.
+pub const SD_SECTOR_SIZE: usize = 512;
+
+/// Returns the recommended size of reads and writes, and of buffers for them, for files
+/// at `path`.
+///
+/// Paths under `/int` get [`INT_CHUNK_SIZE`] and paths under `/ext` or `/data`, the app
+/// data directory on the SD card, get [`EXT_CHUNK_SIZE`]. Other paths, including `/any`
+/// which may be on either backend, get [`DEFAULT_CHUNK_SIZE`].
+///
+/// # Examples
+///
+/// ```no_run
+/// # use flipperzero::storage::perf::{recommended_chunk_size, EXT_CHUNK_SIZE};
+/// assert_eq!(recommended_chunk_size(c"/ext/subghz/capture.sub"), EXT_CHUNK_SIZE);
+/// ```
+pub fn recommended_chunk_size(path: &CStr) -> usize {
+    let path = path.to_bytes();
+    if is_under(path, b"/int") {
+        INT_CHUNK_SIZE
+    } else if is_under(path, b"/ext") || is_under(path, b"/data") {
+        EXT_CHUNK_SIZE
+    } else {
+        DEFAULT_CHUNK_SIZE
+    }
+}
+
+/// Returns the boundary that writes to the file at `path` should be aligned to, if any.
+///
+/// Paths under `/ext` or `/data` get [`SD_SECTOR_SIZE`]. The internal flash gains
+/// nothing from aligned writes, and paths under `/any` may be on either backend, so they
+/// get `None`.
+pub fn recommended_alignment(path: &CStr) -> Option<usize> {
+    let path = path.to_bytes();
+    (is_under(path, b"/ext") || is_under(path, b"/data")).then_some(SD_SECTOR_SIZE)
+}
+
+/// Returns `true` if `path` is `root` or inside it.
+fn is_under(path: &[u8], root: &[u8]) -> bool {
+    path.strip_prefix(root)
+        .is_some_and(|rest| rest.is_empty() || rest[0] == b'/')
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use super::{
+        recommended_alignment, recommended_chunk_size, DEFAULT_CHUNK_SIZE, EXT_CHUNK_SIZE,
+        INT_CHUNK_SIZE, SD_SECTOR_SIZE,
+    };
+
+    #[test]
+    fn chunk_size_by_backend() {
+        assert_eq!(recommended_chunk_size(c"/int"), INT_CHUNK_SIZE);
+        assert_eq!(recommended_chunk_size(c"/int/.region_data"), INT_CHUNK_SIZE);
+        assert_eq!(recommended_chunk_size(c"/ext/nfc/card.nfc"), EXT_CHUNK_SIZE);
+        assert_eq!(
+            recommended_chunk_size(c"/data/settings.txt"),
+            EXT_CHUNK_SIZE
+        );
+    }
+
+    #[test]
+    fn chunk_size_of_other_paths() {
+        assert_eq!(recommended_chunk_size(c"/any/file"), DEFAULT_CHUNK_SIZE);
+        assert_eq!(recommended_chunk_size(c"/extra/file"), DEFAULT_CHUNK_SIZE);
+        assert_eq!(recommended_chunk_size(c"ext/file"), DEFAULT_CHUNK_SIZE);
+        assert_eq!(recommended_chunk_size(c""), DEFAULT_CHUNK_SIZE);
+    }
+
+    #[test]
+    fn alignment_by_backend() {
+        assert_eq!(
+            recommended_alignment(c"/ext/subghz/raw.sub"),
+            Some(SD_SECTOR_SIZE)
+        );
+        assert_eq!(
+            recommended_alignment(c"/data/log.csv"),
+            Some(SD_SECTOR_SIZE)
+        );
+        assert!(recommended_alignment(c"/int/.region_data").is_none());
+        assert!(recommended_alignment(c"/any/file").is_none());
+    }
+}
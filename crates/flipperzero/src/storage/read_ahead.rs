@@ -0,0 +1,374 @@
+//! Reading from a worker thread ahead of the caller.
+//!
+//! How much this speeds up scanning an NFC dictionary hasn't been measured yet. The
+//! `dict_bench` example times the same scan with and without a `ReadAhead`.
+
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::mem;
+use core::time::Duration;
+
+use flipperzero_sys as sys;
+
+use super::DEFAULT_BUF_SIZE;
+use crate::furi::message_queue::MessageQueue;
+use crate::furi::thread::{self, JoinHandle};
+use crate::io::{Error, Read, Seek, SeekFrom};
+
+/// Number of chunks of a [`ReadAhead`] created with [`ReadAhead::new`].
+pub const DEFAULT_READ_AHEAD_CHUNKS: usize = 4;
+
+/// Stack size of the worker thread of a [`ReadAhead`].
+pub const READ_AHEAD_STACK_SIZE: usize = 2048;
+
+/// Work for the worker thread.
+enum Job<R> {
+    /// Read the next chunk into the buffer.
+    Fill(Vec<u8>),
+    /// Seek with the given function, which is only available if `R` implements [`Seek`].
+    Seek(SeekFrom, fn(&mut R, SeekFrom) -> Result<usize, Error>),
+    Stop,
+}
+
+/// What the worker thread did.
+enum Done {
+    /// The buffer holds the bytes that were read, none at the end of the reader.
+    Filled(Vec<u8>, Result<(), Error>),
+    Sought(Result<usize, Error>),
+}
+
+/// The queues between a [`ReadAhead`] and its worker thread.
+struct Channels<R> {
+    jobs: MessageQueue<Job<R>>,
+    done: MessageQueue<Done>,
+}
+
+/// The state of a [`ReadAhead`] with a worker thread.
+struct Prefetch<R> {
+    channels: Arc<Channels<R>>,
+    worker: Option<JoinHandle>,
+    /// The chunk being read from, or an empty `Vec` without capacity if none.
+    current: Vec<u8>,
+    /// Position of the next byte to read in `current`.
+    pos: usize,
+    /// Number of chunks the worker thread holds or has filled.
+    outstanding: usize,
+    /// Chunks that are neither being read from nor with the worker thread.
+    idle: Vec<Vec<u8>>,
+    /// Whether the worker thread reached the end of the reader.
+    eof: bool,
+}
+
+/// Reads chunks from a worker thread before they are needed.
+///
+/// Parsing a file in small reads leaves the SD card idle while the parser runs. A
+/// `ReadAhead` moves the reader to a worker thread that keeps up to `chunks` chunks of
+/// `chunk_size` bytes read ahead, so a read usually returns bytes that are already in
+/// memory. This costs `chunks * chunk_size` bytes for the chunks plus a thread with a
+/// stack of [`READ_AHEAD_STACK_SIZE`] bytes.
+///
+/// If there is no room for the worker thread, or `chunks` is zero, the reader is used
+/// directly from the calling thread instead, see [`ReadAhead::is_prefetching`].
+///
+/// Seeking waits for the chunks being read and discards them, so it is as slow as
+/// reading them.
+pub struct ReadAhead<R: Read + Send + 'static> {
+    /// `Some` when reading synchronously.
+    inner: Option<R>,
+    prefetch: Option<Prefetch<R>>,
+}
+
+impl<R: Read + Send + 'static> ReadAhead<R> {
+    /// Moves `inner` to a worker thread that reads up to [`DEFAULT_READ_AHEAD_CHUNKS`]
+    /// chunks of [`DEFAULT_BUF_SIZE`] bytes ahead.
+    pub fn new(inner: R) -> Self {
+        Self::with_capacity(DEFAULT_READ_AHEAD_CHUNKS, DEFAULT_BUF_SIZE, inner)
+    }
+
+    /// Moves `inner` to a worker thread that reads up to `chunks` chunks of `chunk_size`
+    /// bytes ahead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is zero.
+    pub fn with_capacity(chunks: usize, chunk_size: usize, inner: R) -> Self {
+        assert!(chunk_size > 0, "chunk size must not be zero");
+
+        // Creating a thread without enough memory crashes the firmware.
+        let needed = chunks * chunk_size + READ_AHEAD_STACK_SIZE;
+        if chunks == 0 || unsafe { sys::memmgr_heap_get_max_free_block() } < needed {
+            return Self {
+                inner: Some(inner),
+                prefetch: None,
+            };
+        }
+
+        let channels = Arc::new(Channels {
+            jobs: MessageQueue::new(chunks + 1),
+            done: MessageQueue::new(chunks + 1),
+        });
+        for _ in 0..chunks {
+            channels
+                .jobs
+                .put(Job::Fill(Vec::with_capacity(chunk_size)), Duration::MAX)
+                .map_err(|(_, status)| status)
+                .unwrap();
+        }
+
+        let worker_channels = channels.clone();
+        let worker = thread::Builder::new()
+            .stack_size(READ_AHEAD_STACK_SIZE)
+            .spawn(move || {
+                run(inner, &worker_channels);
+                0
+            });
+
+        Self {
+            inner: None,
+            prefetch: Some(Prefetch {
+                channels,
+                worker: Some(worker),
+                current: Vec::new(),
+                pos: 0,
+                outstanding: chunks,
+                idle: Vec::with_capacity(chunks),
+                eof: false,
+            }),
+        }
+    }
+
+    /// Returns `true` if a worker thread reads ahead, or `false` if reads go directly to
+    /// the wrapped reader.
+    pub fn is_prefetching(&self) -> bool {
+        self.prefetch.is_some()
+    }
+}
+
+impl<R> Prefetch<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        if self.pos == self.current.len() {
+            if self.eof {
+                return Ok(0);
+            }
+            self.next_chunk()?;
+        }
+        let n = buf.len().min(self.current.len() - self.pos);
+        buf[..n].copy_from_slice(&self.current[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+
+    /// Gives the finished chunk back to the worker thread and waits for the next one.
+    fn next_chunk(&mut self) -> Result<(), Error> {
+        let finished = mem::take(&mut self.current);
+        if finished.capacity() > 0 {
+            self.channels
+                .jobs
+                .put(Job::Fill(finished), Duration::MAX)
+                .map_err(|(_, status)| status)
+                .unwrap();
+            self.outstanding += 1;
+        }
+        self.pos = 0;
+
+        let Done::Filled(chunk, result) = self.channels.done.get(Duration::MAX).unwrap() else {
+            unreachable!()
+        };
+        self.outstanding -= 1;
+        if result.is_ok() && chunk.is_empty() {
+            self.eof = true;
+        }
+        self.current = chunk;
+        result
+    }
+
+    fn seek(
+        &mut self,
+        pos: SeekFrom,
+        seek: fn(&mut R, SeekFrom) -> Result<usize, Error>,
+    ) -> Result<usize, Error> {
+        // The worker thread is ahead by the bytes not read yet.
+        let mut ahead = self.current.len() - self.pos;
+        let current = mem::take(&mut self.current);
+        if current.capacity() > 0 {
+            self.idle.push(current);
+        }
+        while self.outstanding > 0 {
+            let Done::Filled(chunk, _) = self.channels.done.get(Duration::MAX).unwrap() else {
+                unreachable!()
+            };
+            self.outstanding -= 1;
+            ahead += chunk.len();
+            self.idle.push(chunk);
+        }
+
+        let pos = match pos {
+            SeekFrom::Current(offset) => SeekFrom::Current(offset - ahead as i64),
+            pos => pos,
+        };
+        self.channels
+            .jobs
+            .put(Job::Seek(pos, seek), Duration::MAX)
+            .map_err(|(_, status)| status)
+            .unwrap();
+        let Done::Sought(result) = self.channels.done.get(Duration::MAX).unwrap() else {
+            unreachable!()
+        };
+
+        self.pos = 0;
+        self.eof = false;
+        for chunk in self.idle.drain(..) {
+            self.channels
+                .jobs
+                .put(Job::Fill(chunk), Duration::MAX)
+                .map_err(|(_, status)| status)
+                .unwrap();
+            self.outstanding += 1;
+        }
+        result
+    }
+}
+
+impl<R> Drop for Prefetch<R> {
+    fn drop(&mut self) {
+        self.channels
+            .jobs
+            .put(Job::Stop, Duration::MAX)
+            .map_err(|(_, status)| status)
+            .unwrap();
+        if let Some(worker) = self.worker.take() {
+            worker.join();
+        }
+    }
+}
+
+impl<R: Read + Send + 'static> Read for ReadAhead<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        match (&mut self.inner, &mut self.prefetch) {
+            (Some(inner), _) => inner.read(buf),
+            (None, Some(prefetch)) => prefetch.read(buf),
+            (None, None) => unreachable!(),
+        }
+    }
+}
+
+impl<R: Read + Seek + Send + 'static> Seek for ReadAhead<R> {
+    /// Seeks the wrapped reader, discarding the chunks read ahead.
+    fn seek(&mut self, pos: SeekFrom) -> Result<usize, Error> {
+        match (&mut self.inner, &mut self.prefetch) {
+            (Some(inner), _) => inner.seek(pos),
+            (None, Some(prefetch)) => prefetch.seek(pos, R::seek),
+            (None, None) => unreachable!(),
+        }
+    }
+}
+
+/// Runs the jobs for `inner` until asked to stop.
+fn run<R: Read>(mut inner: R, channels: &Channels<R>) {
+    loop {
+        let Ok(job) = channels.jobs.get(Duration::MAX) else {
+            continue;
+        };
+        let done = match job {
+            Job::Fill(mut chunk) => {
+                chunk.resize(chunk.capacity(), 0);
+                let result = inner.read(&mut chunk);
+                chunk.truncate(*result.as_ref().unwrap_or(&0));
+                Done::Filled(chunk, result.map(|_| ()))
+            }
+            Job::Seek(pos, seek) => Done::Sought(seek(&mut inner, pos)),
+            Job::Stop => return,
+        };
+        let _ = channels.done.put(done, Duration::MAX);
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::ReadAhead;
+    use crate::io::cursor::Cursor;
+    use crate::io::{Error, Read, Seek, SeekFrom};
+
+    /// A file of `len` bytes counting up from zero, which returns at most 7 bytes per
+    /// read.
+    fn file(len: usize) -> Cursor<Vec<u8>> {
+        Cursor::new((0..len).map(|i| i as u8).collect()).max(7)
+    }
+
+    fn read_all(reader: &mut impl Read, step: usize) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut buf = [0; 16];
+        loop {
+            let n = reader.read(&mut buf[..step]).unwrap();
+            if n == 0 {
+                return data;
+            }
+            data.extend_from_slice(&buf[..n]);
+        }
+    }
+
+    fn read_three(reader: &mut impl Read) -> [u8; 3] {
+        let mut buf = [0; 3];
+        let mut filled = 0;
+        while filled < buf.len() {
+            filled += reader.read(&mut buf[filled..]).unwrap();
+        }
+        buf
+    }
+
+    #[test]
+    fn reads_everything_in_order() {
+        let mut reader = ReadAhead::with_capacity(3, 5, file(200));
+        assert!(reader.is_prefetching());
+        assert_eq!(read_all(&mut reader, 3), (0..200).collect::<Vec<u8>>());
+        assert_eq!(reader.read(&mut [0; 4]).unwrap(), 0);
+    }
+
+    #[test]
+    fn reads_synchronously_without_chunks() {
+        let mut reader = ReadAhead::with_capacity(0, 5, file(50));
+        assert!(!reader.is_prefetching());
+        assert_eq!(read_all(&mut reader, 16), (0..50).collect::<Vec<u8>>());
+    }
+
+    #[test]
+    fn seeks_from_the_read_position() {
+        let mut reader = ReadAhead::with_capacity(4, 8, file(100));
+        assert_eq!(read_three(&mut reader), [0, 1, 2]);
+
+        assert_eq!(reader.seek(SeekFrom::Current(10)).unwrap(), 13);
+        assert_eq!(read_three(&mut reader), [13, 14, 15]);
+
+        assert_eq!(reader.seek(SeekFrom::Current(-6)).unwrap(), 10);
+        assert_eq!(read_three(&mut reader), [10, 11, 12]);
+
+        assert_eq!(reader.seek(SeekFrom::Start(98)).unwrap(), 98);
+        assert_eq!(read_all(&mut reader, 16), [98, 99]);
+
+        // Seeking back works after reaching the end.
+        assert_eq!(reader.seek(SeekFrom::End(-1)).unwrap(), 99);
+        assert_eq!(read_all(&mut reader, 16), [99]);
+    }
+
+    #[test]
+    fn reports_errors_in_order() {
+        let file = file(30).fail_at(10, Error::Internal);
+        let mut reader = ReadAhead::with_capacity(2, 4, file);
+
+        let mut data = Vec::new();
+        let mut buf = [0; 4];
+        let error = loop {
+            match reader.read(&mut buf) {
+                Ok(n) => data.extend_from_slice(&buf[..n]),
+                Err(e) => break e,
+            }
+        };
+        assert!(matches!(error, Error::Internal));
+        assert_eq!(data, (0..10).collect::<Vec<u8>>());
+
+        // Reading continues after the error.
+        assert_eq!(read_all(&mut reader, 4), (10..30).collect::<Vec<u8>>());
+    }
+}
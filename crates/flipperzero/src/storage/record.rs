@@ -0,0 +1,169 @@
+//! The storage record shared by all storage handles.
+//!
+//! Sharing the record is meant to save a directory scan from opening and closing it for
+//! every handle, but how much that saves hasn't been measured yet. The last part of the
+//! `storage_bench` example scans a directory with and without [`init`] to measure it.
+
+use core::ptr;
+use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+
+use flipperzero_sys as sys;
+
+const RECORD_STORAGE: &core::ffi::CStr = c"storage";
+
+/// Number of [`StorageRecord`]s alive, plus one while [`init`] keeps the record open.
+pub(super) static USERS: AtomicUsize = AtomicUsize::new(0);
+
+/// Whether [`init`] keeps the record open.
+static PINNED: AtomicBool = AtomicBool::new(false);
+
+/// The record, which stays at the same address for as long as the firmware runs.
+static RECORD: AtomicPtr<sys::Storage> = AtomicPtr::new(ptr::null_mut());
+
+/// Runs `f` with interrupts and thread switches disabled.
+///
+/// The atomics above are only read and written in here, so they can't be seen half
+/// updated. `f` must not block.
+fn critical<R>(f: impl FnOnce() -> R) -> R {
+    let info = unsafe { sys::__furi_critical_enter() };
+    let result = f();
+    unsafe { sys::__furi_critical_exit(info) };
+    result
+}
+
+/// Takes a reference to the record, opening it if there is none yet.
+fn acquire() -> *mut sys::Storage {
+    let record = critical(|| {
+        let users = USERS.load(Ordering::Relaxed);
+        if users > 0 {
+            USERS.store(users + 1, Ordering::Relaxed);
+        }
+        (users > 0).then(|| RECORD.load(Ordering::Relaxed))
+    });
+    if let Some(record) = record {
+        return record;
+    }
+
+    // Opening the record can block, so it happens outside of the critical section. If
+    // another thread opened it in the meantime, its reference is used and this one is
+    // given back, as the firmware counts the references to records itself.
+    let opened = unsafe { sys::furi_record_open(RECORD_STORAGE.as_ptr()) } as *mut sys::Storage;
+    let raced = critical(|| {
+        let users = USERS.load(Ordering::Relaxed);
+        USERS.store(users + 1, Ordering::Relaxed);
+        if users == 0 {
+            RECORD.store(opened, Ordering::Relaxed);
+        }
+        users > 0
+    });
+    if raced {
+        unsafe { sys::furi_record_close(RECORD_STORAGE.as_ptr()) };
+    }
+    opened
+}
+
+/// Gives back a reference taken by [`acquire`], closing the record after the last one.
+fn release() {
+    let last = critical(|| {
+        let users = USERS.load(Ordering::Relaxed) - 1;
+        USERS.store(users, Ordering::Relaxed);
+        users == 0
+    });
+    if last {
+        unsafe { sys::furi_record_close(RECORD_STORAGE.as_ptr()) };
+    }
+}
+
+/// A counted reference to the storage record.
+///
+/// The record is opened when the first `StorageRecord` is created and closed when the
+/// last one is dropped, instead of once per handle.
+pub(crate) struct StorageRecord(*mut sys::Storage);
+
+impl StorageRecord {
+    pub(crate) fn open() -> Self {
+        StorageRecord(acquire())
+    }
+
+    pub(crate) fn as_ptr(&self) -> *mut sys::Storage {
+        self.0
+    }
+}
+
+impl Clone for StorageRecord {
+    fn clone(&self) -> Self {
+        Self::open()
+    }
+}
+
+impl Drop for StorageRecord {
+    fn drop(&mut self) {
+        release();
+    }
+}
+
+/// Opens the storage record and keeps it open until [`deinit`].
+///
+/// Without this, the record is opened by the first [`File`](super::File),
+/// [`Storage`](super::Storage) or [`ReadDir`](super::ReadDir) and closed when the last
+/// of them is dropped, so an app that creates one handle at a time pays for opening the
+/// record every time. Calling `init` at the start of the app moves that cost to a
+/// known point.
+///
+/// Calling `init` again before [`deinit`] does nothing.
+pub fn init() {
+    acquire();
+    if critical(|| PINNED.swap(true, Ordering::Relaxed)) {
+        release();
+    }
+}
+
+/// Closes the storage record opened by [`init`], once no handle uses it.
+///
+/// Call this before the app exits if it called [`init`]. Calling it without [`init`]
+/// does nothing.
+pub fn deinit() {
+    if critical(|| PINNED.swap(false, Ordering::Relaxed)) {
+        release();
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use core::sync::atomic::Ordering;
+
+    use super::{deinit, init, StorageRecord, USERS};
+
+    fn users() -> usize {
+        USERS.load(Ordering::Relaxed)
+    }
+
+    #[test]
+    fn handles_share_the_record() {
+        let before = users();
+        let first = StorageRecord::open();
+        let second = first.clone();
+        assert_eq!(users(), before + 2);
+        assert_eq!(first.as_ptr(), second.as_ptr());
+        assert!(!first.as_ptr().is_null());
+
+        drop(first);
+        drop(second);
+        assert_eq!(users(), before);
+    }
+
+    #[test]
+    fn init_keeps_the_record_open() {
+        let before = users();
+        init();
+        init();
+        assert_eq!(users(), before + 1);
+
+        drop(StorageRecord::open());
+        assert_eq!(users(), before + 1);
+
+        deinit();
+        deinit();
+        assert_eq!(users(), before);
+    }
+}
@@ -0,0 +1,173 @@
+//! Settings persisted to FlipperFormat files.
+
+use alloc::{boxed::Box, ffi::CString, vec::Vec};
+use core::ffi::CStr;
+
+use flipperzero_sys as sys;
+
+use super::format::{FlipperFormat, FormatError};
+use super::Storage;
+use crate::furi::string::FuriString;
+use crate::io::{fs_result, Error};
+
+type Getter<T> = Box<dyn Fn(&T) -> u32>;
+type Setter<T> = Box<dyn Fn(&mut T, u32)>;
+
+/// A value stored under a key of the settings file.
+struct Field<T> {
+    key: &'static CStr,
+    get: Getter<T>,
+    set: Setter<T>,
+}
+
+/// App settings of type `T`, which are loaded from and saved to a FlipperFormat file.
+///
+/// The file starts with a header consisting of a file type and a version, followed by
+/// one line per field registered with [`Settings::field`]. Every field is stored as a
+/// number, such as the index of an enum variant.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use flipperzero::storage::Settings;
+/// #[derive(Default)]
+/// struct Config {
+///     volume: u32,
+///     muted: bool,
+/// }
+///
+/// let mut settings = Settings::new(
+///     c"/ext/apps_data/example/settings.txt",
+///     c"Example Settings",
+///     1,
+///     Config::default(),
+/// )
+/// .field(c"Volume", |c| c.volume, |c, v| c.volume = v)
+/// .field(c"Muted", |c| c.muted.into(), |c, v| c.muted = v != 0);
+/// settings.load().ok();
+/// settings.get_mut().volume += 1;
+/// settings.save().ok();
+/// ```
+pub struct Settings<T> {
+    path: CString,
+    file_type: &'static CStr,
+    version: u32,
+    value: T,
+    fields: Vec<Field<T>>,
+}
+
+impl<T> Settings<T> {
+    /// Creates settings stored at `path`, starting out as `value`.
+    ///
+    /// `file_type` and `version` are written to the header of the file, and files with
+    /// a different header are ignored when loading.
+    pub fn new(path: &CStr, file_type: &'static CStr, version: u32, value: T) -> Self {
+        Self {
+            path: path.into(),
+            file_type,
+            version,
+            value,
+            fields: Vec::new(),
+        }
+    }
+
+    /// Registers a field stored under `key`, which is read from the settings with `get`
+    /// and written back with `set`.
+    pub fn field<G, S>(mut self, key: &'static CStr, get: G, set: S) -> Self
+    where
+        G: Fn(&T) -> u32 + 'static,
+        S: Fn(&mut T, u32) + 'static,
+    {
+        self.fields.push(Field {
+            key,
+            get: Box::new(get),
+            set: Box::new(set),
+        });
+        self
+    }
+
+    /// Returns the path of the settings file.
+    pub fn path(&self) -> &CStr {
+        &self.path
+    }
+
+    /// Returns the current settings.
+    pub fn get(&self) -> &T {
+        &self.value
+    }
+
+    /// Returns the current settings for modification.
+    ///
+    /// Changes are only persisted by [`Settings::save`].
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+
+    /// Loads the settings from the file, returning `false` if it doesn't exist or has
+    /// a different header.
+    ///
+    /// Fields missing from the file keep their current value, which allows adding
+    /// fields without changing the version.
+    pub fn load(&mut self) -> Result<bool, Error> {
+        let storage = Storage::open();
+        if !storage.exists(&self.path)? {
+            return Ok(false);
+        }
+
+        let mut file = FlipperFormat::open_existing(&self.path)?;
+        match file.read_header() {
+            Ok((file_type, version)) if file_type == *self.file_type && version == self.version => {
+            }
+            _ => return Ok(false),
+        }
+
+        for field in &self.fields {
+            // Keys are searched from the current position, so start over for every field
+            // to not depend on their order.
+            if file.rewind().is_ok() {
+                if let Ok(value) = file.read_u32(field.key) {
+                    (field.set)(&mut self.value, value);
+                }
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Saves the settings to the file.
+    ///
+    /// The settings are first written to a temporary file next to it, which then
+    /// replaces the file, so that it is never left partially written.
+    pub fn save(&self) -> Result<(), Error> {
+        let mut temp_path = FuriString::from(self.path.as_c_str());
+        temp_path.push_str(".tmp");
+
+        let storage = Storage::open();
+        {
+            let mut file = FlipperFormat::open_always(&temp_path)?;
+            file.write_header(self.file_type, self.version)
+                .map_err(into_io)?;
+            for field in &self.fields {
+                file.write_u32(field.key, (field.get)(&self.value))
+                    .map_err(into_io)?;
+            }
+            file.close()?;
+        }
+
+        fs_result(unsafe {
+            sys::storage_common_rename(
+                storage.data.as_ptr(),
+                temp_path.as_c_ptr(),
+                self.path.as_ptr(),
+            )
+        })
+    }
+}
+
+/// Returns the error of the underlying file, as writes of numbers can't fail otherwise.
+fn into_io(error: FormatError) -> Error {
+    match error {
+        FormatError::Io(error) => error,
+        _ => Error::Internal,
+    }
+}
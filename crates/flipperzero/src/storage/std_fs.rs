@@ -0,0 +1,268 @@
+//! A storage backend over a directory of the host, for running storage code off the
+//! Flipper.
+
+use alloc::ffi::CString;
+use core::ffi::CStr;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use flipperzero_sys as sys;
+
+use super::backend::{EntryInfo, StorageBackend};
+use super::{validate_filename, Metadata, OpenOptions};
+use crate::io::{Error, Read, Seek, SeekFrom, Write};
+
+/// A [`StorageBackend`] that keeps files in a directory of the host.
+///
+/// Paths are mapped onto subdirectories of the root directory: `/ext/nfc/card.nfc` is
+/// `{root}/ext/nfc/card.nfc`, and the same goes for `/int` and `/data`. `/any` is
+/// mapped onto `{root}/ext`, as it is on a Flipper with an SD card. Other paths, and
+/// paths containing `.` or `..`, fail with [`Error::InvalidName`]. The subdirectories
+/// are not created automatically.
+///
+/// The options of [`OpenOptions`] behave as on the Flipper. Most notably,
+/// [`OpenOptions::open_append`] only starts at the end of the file, without forcing
+/// every write there, and [`StorageBackend::rename`] fails if the target exists.
+///
+/// Host file systems accept names that FAT doesn't. In [strict](StdStorage::strict)
+/// mode, creating a file or directory whose name fails [`validate_filename`] fails with
+/// [`Error::InvalidName`], as it would on the SD card.
+pub struct StdStorage {
+    root: PathBuf,
+    strict: bool,
+}
+
+impl StdStorage {
+    /// Keeps files in subdirectories of `root`.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            strict: false,
+        }
+    }
+
+    /// Sets whether the names of new files and directories must be valid on FAT.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Returns the directory that paths are mapped onto.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the host path for `path`.
+    fn host_path(&self, path: &CStr) -> Result<PathBuf, Error> {
+        let path = path.to_str().map_err(|_| Error::InvalidName)?;
+        let mut components = path
+            .strip_prefix('/')
+            .ok_or(Error::InvalidName)?
+            .split('/')
+            .filter(|component| !component.is_empty());
+        let mut host = self.root.clone();
+        match components.next() {
+            Some("ext") | Some("any") => host.push("ext"),
+            Some(storage @ ("int" | "data")) => host.push(storage),
+            _ => return Err(Error::InvalidName),
+        }
+        for component in components {
+            if component == "." || component == ".." {
+                return Err(Error::InvalidName);
+            }
+            host.push(component);
+        }
+        Ok(host)
+    }
+
+    /// Fails in strict mode if the name of a new file or directory isn't valid on FAT.
+    fn check_new_name(&self, host: &Path) -> Result<(), Error> {
+        if !self.strict {
+            return Ok(());
+        }
+        let name = host.file_name().and_then(|name| name.to_str());
+        match name {
+            Some(name) if validate_filename(name.as_bytes()).is_ok() => Ok(()),
+            _ => Err(Error::InvalidName),
+        }
+    }
+}
+
+impl StorageBackend for StdStorage {
+    type File = StdFile;
+    type ReadDir = StdReadDir;
+
+    fn open(&self, path: &CStr, options: OpenOptions) -> Result<StdFile, Error> {
+        let host = self.host_path(path)?;
+        let read = options.access_mode() & sys::FS_AccessMode_FSAM_READ != 0;
+        let write = options.access_mode() & sys::FS_AccessMode_FSAM_WRITE != 0;
+        options.validate()?;
+        let mode = options.canonical_open_mode();
+
+        let exists = host.exists();
+        let creates = match mode {
+            sys::FS_OpenMode_FSOM_CREATE_NEW if exists => return Err(Error::Exists),
+            sys::FS_OpenMode_FSOM_OPEN_EXISTING => false,
+            _ => !exists,
+        };
+        if creates {
+            self.check_new_name(&host)?;
+        }
+        if host.is_dir() {
+            return Err(Error::Denied);
+        }
+
+        // `std` only creates and truncates files opened for writing, which the Flipper
+        // doesn't require, so that is done separately.
+        if creates || mode == sys::FS_OpenMode_FSOM_CREATE_ALWAYS {
+            fs::OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(&host)
+                .map_err(map_error)?;
+        }
+        let mut file = fs::OpenOptions::new()
+            .read(read)
+            .write(write)
+            .open(&host)
+            .map_err(map_error)?;
+        if mode == sys::FS_OpenMode_FSOM_OPEN_APPEND {
+            io::Seek::seek(&mut file, io::SeekFrom::End(0)).map_err(map_error)?;
+        }
+        Ok(StdFile(file))
+    }
+
+    fn metadata(&self, path: &CStr) -> Result<Metadata, Error> {
+        let metadata = fs::metadata(self.host_path(path)?).map_err(map_error)?;
+        let size = if metadata.is_dir() { 0 } else { metadata.len() };
+        Ok(Metadata::new(metadata.is_dir(), size))
+    }
+
+    fn read_dir(&self, path: &CStr) -> Result<StdReadDir, Error> {
+        let host = self.host_path(path)?;
+        if !host.is_dir() && host.exists() {
+            return Err(Error::InvalidParameter);
+        }
+        fs::read_dir(host).map(StdReadDir).map_err(map_error)
+    }
+
+    fn create_dir(&self, path: &CStr) -> Result<(), Error> {
+        let host = self.host_path(path)?;
+        if host.exists() {
+            return Err(Error::Exists);
+        }
+        self.check_new_name(&host)?;
+        fs::create_dir(host).map_err(map_error)
+    }
+
+    fn remove(&self, path: &CStr) -> Result<(), Error> {
+        let host = self.host_path(path)?;
+        let metadata = fs::metadata(&host).map_err(map_error)?;
+        if metadata.is_dir() {
+            // Removing a directory that isn't empty is denied, as on the Flipper.
+            if fs::read_dir(&host).map_err(map_error)?.next().is_some() {
+                return Err(Error::Denied);
+            }
+            fs::remove_dir(host).map_err(map_error)
+        } else {
+            fs::remove_file(host).map_err(map_error)
+        }
+    }
+
+    fn rename(&self, from: &CStr, to: &CStr) -> Result<(), Error> {
+        let from = self.host_path(from)?;
+        let to = self.host_path(to)?;
+        if !from.exists() {
+            return Err(Error::NotExists);
+        }
+        if to.exists() {
+            return Err(Error::Exists);
+        }
+        if to.starts_with(&from) {
+            return Err(Error::InvalidParameter);
+        }
+        self.check_new_name(&to)?;
+        fs::rename(from, to).map_err(map_error)
+    }
+}
+
+/// A file of a [`StdStorage`].
+pub struct StdFile(fs::File);
+
+impl StdFile {
+    /// Returns the file of the host.
+    pub fn get_ref(&self) -> &fs::File {
+        &self.0
+    }
+}
+
+impl Read for StdFile {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        io::Read::read(&mut self.0, buf).map_err(map_error)
+    }
+}
+
+impl Write for StdFile {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        io::Write::write(&mut self.0, buf).map_err(map_error)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        io::Write::flush(&mut self.0).map_err(map_error)
+    }
+}
+
+impl Seek for StdFile {
+    fn seek(&mut self, pos: SeekFrom) -> Result<usize, Error> {
+        let pos = match pos {
+            SeekFrom::Start(offset) => io::SeekFrom::Start(offset),
+            SeekFrom::End(offset) => io::SeekFrom::End(offset),
+            SeekFrom::Current(offset) => io::SeekFrom::Current(offset),
+        };
+        let position = io::Seek::seek(&mut self.0, pos).map_err(map_error)?;
+        usize::try_from(position).map_err(|_| Error::InvalidParameter)
+    }
+}
+
+/// The entries of a directory of a [`StdStorage`].
+pub struct StdReadDir(fs::ReadDir);
+
+impl Iterator for StdReadDir {
+    type Item = Result<EntryInfo, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry = match self.0.next()? {
+            Ok(entry) => entry,
+            Err(e) => return Some(Err(map_error(e))),
+        };
+        Some((|| {
+            let name = entry
+                .file_name()
+                .into_string()
+                .map_err(|_| Error::InvalidName)?;
+            let name = CString::new(name).map_err(|_| Error::InvalidName)?;
+            let metadata = entry.metadata().map_err(map_error)?;
+            let size = if metadata.is_dir() { 0 } else { metadata.len() };
+            Ok(EntryInfo::new(name, Metadata::new(metadata.is_dir(), size)))
+        })())
+    }
+}
+
+/// Translates an error of `std` into the closest error of the storage service.
+fn map_error(error: io::Error) -> Error {
+    match error.kind() {
+        ErrorKind::NotFound => Error::NotExists,
+        ErrorKind::AlreadyExists => Error::Exists,
+        ErrorKind::PermissionDenied => Error::Denied,
+        ErrorKind::InvalidInput => Error::InvalidParameter,
+        ErrorKind::WriteZero => Error::WriteZero,
+        ErrorKind::UnexpectedEof => Error::UnexpectedEof,
+        ErrorKind::InvalidData => Error::InvalidData,
+        ErrorKind::Unsupported => Error::NotImplemented,
+        ErrorKind::TimedOut => Error::TimedOut,
+        ErrorKind::WouldBlock | ErrorKind::Interrupted => Error::NotReady,
+        _ => Error::Internal,
+    }
+}
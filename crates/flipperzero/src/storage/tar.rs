@@ -0,0 +1,530 @@
+//! Tar archives, through the firmware's tar archive library.
+//!
+//! The firmware uses tar archives for backups and for the resources of updates, which
+//! also makes them a convenient way for an app to ship a bundle of assets as one file.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # use flipperzero::io::Error;
+//! # use flipperzero::storage::tar::TarArchive;
+//! # fn main() -> Result<(), Error> {
+//! let mut bundle = TarArchive::open_read(c"/ext/apps_data/myapp/assets.tar")?;
+//! bundle.unpack_to_with(c"/ext/apps_data/myapp/assets", |entry| {
+//!     entry.is_dir() || entry.name().to_bytes().ends_with(b".png")
+//! })?;
+//! # Ok(())
+//! # }
+//! ```
+
+use alloc::ffi::CString;
+use alloc::vec::Vec;
+use core::ffi::{c_char, c_void, CStr};
+use core::ptr::{self, NonNull};
+
+use flipperzero_sys as sys;
+
+use super::record::StorageRecord;
+use super::{create_dir_all, AsPath, File, Storage};
+use crate::io::Error;
+
+/// An entry of a [`TarArchive`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TarEntry {
+    name: CString,
+    is_dir: bool,
+}
+
+impl TarEntry {
+    /// Returns the path of the entry within the archive.
+    pub fn name(&self) -> &CStr {
+        &self.name
+    }
+
+    /// Returns `true` if the entry is a directory.
+    pub fn is_dir(&self) -> bool {
+        self.is_dir
+    }
+
+    /// Returns `true` if unpacking the entry would leave the destination, because a
+    /// component of its path is `..`.
+    fn escapes(&self) -> bool {
+        self.name
+            .to_bytes()
+            .split(|&b| b == b'/')
+            .any(|c| c == b"..")
+    }
+}
+
+/// A tar archive, open either for reading or for writing.
+///
+/// An archive opened for writing must be [finished](TarArchive::finish), which writes
+/// the records marking its end. The archive is closed when this is dropped.
+pub struct TarArchive {
+    raw: NonNull<sys::TarArchive>,
+    writable: bool,
+    _record: StorageRecord,
+}
+
+impl TarArchive {
+    fn alloc(writable: bool) -> Self {
+        let record = StorageRecord::open();
+        Self {
+            raw: unsafe { NonNull::new_unchecked(sys::tar_archive_alloc(record.as_ptr())) },
+            writable,
+            _record: record,
+        }
+    }
+
+    /// Opens the archive at `path` for reading.
+    ///
+    /// Archives whose name ends with `.ths` are read as compressed with heatshrink, as
+    /// the firmware does for the resources of updates.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`Error::NotExists`] if there is no file at `path`, and with
+    /// [`Error::InvalidData`] if the firmware can't open it as an archive. Damage
+    /// further into the archive is only found when reading it.
+    pub fn open_read<P: AsPath + ?Sized>(path: &P) -> Result<Self, Error> {
+        path.with_c_str(|path| {
+            let archive = Self::alloc(false);
+            let opened = unsafe {
+                let mode = sys::tar_archive_get_mode_for_path(path.as_ptr());
+                sys::tar_archive_open(archive.raw.as_ptr(), path.as_ptr(), mode)
+            };
+            if opened {
+                Ok(archive)
+            } else if !Storage::open().exists(path)? {
+                Err(Error::NotExists)
+            } else {
+                Err(Error::InvalidData)
+            }
+        })
+    }
+
+    /// Creates an archive at `path` for writing, replacing any file there.
+    pub fn create<P: AsPath + ?Sized>(path: &P) -> Result<Self, Error> {
+        path.with_c_str(|path| {
+            // The firmware doesn't report why opening failed, so the file is created
+            // here first to find out.
+            File::create(path)?.close()?;
+            let archive = Self::alloc(true);
+            check(unsafe {
+                sys::tar_archive_open(
+                    archive.raw.as_ptr(),
+                    path.as_ptr(),
+                    sys::TarOpenMode_TarOpenModeWrite,
+                )
+            })?;
+            Ok(archive)
+        })
+    }
+
+    /// Adds the file at `source` to the archive, as `name`.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`Error::Denied`] if the archive isn't open for writing, and with
+    /// [`Error::InvalidParameter`] if the file is 2 GiB or larger.
+    pub fn add_file<P, Q>(&mut self, name: &P, source: &Q) -> Result<(), Error>
+    where
+        P: AsPath + ?Sized,
+        Q: AsPath + ?Sized,
+    {
+        self.check_writable()?;
+        name.with_c_str(|name| {
+            source.with_c_str(|source| {
+                let size = Storage::open().metadata(source)?.size();
+                let size = i32::try_from(size).map_err(|_| Error::InvalidParameter)?;
+                check(unsafe {
+                    sys::tar_archive_add_file(
+                        self.raw.as_ptr(),
+                        source.as_ptr(),
+                        name.as_ptr(),
+                        size,
+                    )
+                })
+            })
+        })
+    }
+
+    /// Adds the directory at `source` and everything below it to the archive, with
+    /// their paths relative to `source` under `prefix`.
+    ///
+    /// Unless it is empty, `prefix` itself is added as a directory first, so that the
+    /// entries below it can be unpacked on their own.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`Error::Denied`] if the archive isn't open for writing, and with
+    /// [`Error::NotExists`] if there is no directory at `source`.
+    pub fn add_dir<P, Q>(&mut self, source: &P, prefix: &Q) -> Result<(), Error>
+    where
+        P: AsPath + ?Sized,
+        Q: AsPath + ?Sized,
+    {
+        self.check_writable()?;
+        source.with_c_str(|source| {
+            if !Storage::open().dir_exists(source)? {
+                return Err(Error::NotExists);
+            }
+            prefix.with_c_str(|prefix| {
+                if !prefix.is_empty() {
+                    check(unsafe {
+                        sys::tar_archive_dir_add_element(self.raw.as_ptr(), prefix.as_ptr())
+                    })?;
+                }
+                check(unsafe {
+                    sys::tar_archive_add_dir(self.raw.as_ptr(), source.as_ptr(), prefix.as_ptr())
+                })
+            })
+        })
+    }
+
+    /// Adds a file holding `data` to the archive, as `name`.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`Error::Denied`] if the archive isn't open for writing, and with
+    /// [`Error::InvalidParameter`] if `data` is 2 GiB or larger.
+    pub fn add_data<P: AsPath + ?Sized>(&mut self, name: &P, data: &[u8]) -> Result<(), Error> {
+        self.check_writable()?;
+        let len = i32::try_from(data.len()).map_err(|_| Error::InvalidParameter)?;
+        name.with_c_str(|name| {
+            check(unsafe {
+                sys::tar_archive_store_data(self.raw.as_ptr(), name.as_ptr(), data.as_ptr(), len)
+            })
+        })
+    }
+
+    /// Writes the end of the archive and closes it.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`Error::Denied`] if the archive isn't open for writing.
+    pub fn finish(self) -> Result<(), Error> {
+        self.check_writable()?;
+        check(unsafe { sys::tar_archive_finalize(self.raw.as_ptr()) })
+    }
+
+    /// Returns the number of entries in the archive.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`Error::Denied`] if the archive isn't open for reading, and with
+    /// [`Error::InvalidData`] if it is truncated or corrupt.
+    pub fn entry_count(&mut self) -> Result<usize, Error> {
+        self.check_readable()?;
+        let count = unsafe { sys::tar_archive_get_entries_count(self.raw.as_ptr()) };
+        usize::try_from(count).map_err(|_| Error::InvalidData)
+    }
+
+    /// Returns the entries of the archive, in the order they are stored in.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`Error::Denied`] if the archive isn't open for reading, and with
+    /// [`Error::InvalidData`] if it is truncated or corrupt.
+    pub fn entries(&mut self) -> Result<Vec<TarEntry>, Error> {
+        self.check_readable()?;
+        let mut entries = Vec::new();
+        // Every entry is skipped, so nothing is unpacked to the destination.
+        let listed = self.for_each(c"", |entry| {
+            entries.push(entry);
+            false
+        });
+        if listed {
+            Ok(entries)
+        } else {
+            Err(Error::InvalidData)
+        }
+    }
+
+    /// Unpacks every entry of the archive into the directory at `dest`, as by
+    /// [`TarArchive::unpack_to_with`].
+    pub fn unpack_to<P: AsPath + ?Sized>(&mut self, dest: &P) -> Result<(), Error> {
+        self.unpack_to_with(dest, |_| true)
+    }
+
+    /// Unpacks the entries of the archive for which `filter` returns `true` into the
+    /// directory at `dest`, which is created if it doesn't exist.
+    ///
+    /// Files that exist are replaced. The directories of the files unpacked must be in
+    /// the archive before them, or exist already, as they are when the archive was
+    /// made with [`TarArchive::add_dir`].
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`Error::Denied`] if the archive isn't open for reading.
+    ///
+    /// Fails with [`Error::InvalidName`], before unpacking anything, if a component of
+    /// the name of an entry is `..`, which would unpack it outside of `dest`.
+    ///
+    /// Fails with [`Error::InvalidData`] if the archive is truncated or corrupt. The
+    /// entries before the damage are unpacked.
+    pub fn unpack_to_with<P, F>(&mut self, dest: &P, mut filter: F) -> Result<(), Error>
+    where
+        P: AsPath + ?Sized,
+        F: FnMut(&TarEntry) -> bool,
+    {
+        if self.entries()?.iter().any(TarEntry::escapes) {
+            return Err(Error::InvalidName);
+        }
+        dest.with_c_str(|dest| {
+            create_dir_all(&Storage::open(), dest)?;
+            if self.for_each(dest, |entry| filter(&entry)) {
+                Ok(())
+            } else if self.entry_count().is_err() {
+                Err(Error::InvalidData)
+            } else {
+                Err(Error::Internal)
+            }
+        })
+    }
+
+    /// Calls `f` for every entry of the archive, unpacking those it returns `true` for
+    /// into `dest`, and returns whether the firmware succeeded.
+    fn for_each<F: FnMut(TarEntry) -> bool>(&mut self, dest: &CStr, mut f: F) -> bool {
+        unsafe extern "C" fn run_callback<F: FnMut(TarEntry) -> bool>(
+            name: *const c_char,
+            is_dir: bool,
+            context: *mut c_void,
+        ) -> bool {
+            let f = unsafe { &mut *(context as *mut F) };
+            f(TarEntry {
+                name: unsafe { CStr::from_ptr(name) }.into(),
+                is_dir,
+            })
+        }
+
+        unsafe {
+            sys::tar_archive_set_file_callback(
+                self.raw.as_ptr(),
+                Some(run_callback::<F>),
+                &mut f as *mut F as *mut c_void,
+            );
+            let unpacked = sys::tar_archive_unpack_to(self.raw.as_ptr(), dest.as_ptr(), None);
+            // `f` doesn't outlive this call.
+            sys::tar_archive_set_file_callback(self.raw.as_ptr(), None, ptr::null_mut());
+            unpacked
+        }
+    }
+
+    fn check_readable(&self) -> Result<(), Error> {
+        if self.writable {
+            Err(Error::Denied)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn check_writable(&self) -> Result<(), Error> {
+        if self.writable {
+            Ok(())
+        } else {
+            Err(Error::Denied)
+        }
+    }
+}
+
+impl Drop for TarArchive {
+    fn drop(&mut self) {
+        // This closes the archive, without finishing it.
+        unsafe { sys::tar_archive_free(self.raw.as_ptr()) };
+    }
+}
+
+/// Turns the result of a firmware call that doesn't report why it failed into a
+/// `Result`.
+fn check(ok: bool) -> Result<(), Error> {
+    if ok {
+        Ok(())
+    } else {
+        Err(Error::Internal)
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use alloc::vec::Vec;
+
+    use flipperzero_sys as sys;
+
+    use super::{TarArchive, TarEntry};
+    use crate::io::{Error, Read, Write};
+    use crate::storage::{File, Storage};
+
+    const DIR: &str = "/ext/.tmp/rs_tar";
+
+    /// Creates an empty `DIR` for the test.
+    fn setup(storage: &Storage) {
+        storage.create_dir("/ext/.tmp").ok();
+        let dir = c"/ext/.tmp/rs_tar";
+        unsafe { sys::storage_simply_remove_recursive(storage.data.as_ptr(), dir.as_ptr()) };
+        storage.create_dir(DIR).unwrap();
+    }
+
+    fn cleanup(storage: &Storage) {
+        let dir = c"/ext/.tmp/rs_tar";
+        unsafe { sys::storage_simply_remove_recursive(storage.data.as_ptr(), dir.as_ptr()) };
+    }
+
+    fn read(path: &str) -> Vec<u8> {
+        let mut data = Vec::new();
+        File::open(path).unwrap().read_to_end(&mut data).unwrap();
+        data
+    }
+
+    fn names(entries: &[TarEntry]) -> Vec<&[u8]> {
+        entries
+            .iter()
+            .map(|entry| entry.name().to_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn packs_and_unpacks() {
+        let storage = Storage::open();
+        setup(&storage);
+        storage.create_dir("/ext/.tmp/rs_tar/src").unwrap();
+        let mut file = File::create("/ext/.tmp/rs_tar/src/b.txt").unwrap();
+        file.write_all(b"bravo").unwrap();
+        drop(file);
+
+        let archive_path = "/ext/.tmp/rs_tar/bundle.tar";
+        let mut archive = TarArchive::create(archive_path).unwrap();
+        archive.add_data("a.txt", b"alpha").unwrap();
+        archive
+            .add_file("copy.txt", "/ext/.tmp/rs_tar/src/b.txt")
+            .unwrap();
+        archive.add_dir("/ext/.tmp/rs_tar/src", "src").unwrap();
+        archive.finish().unwrap();
+
+        let mut archive = TarArchive::open_read(archive_path).unwrap();
+        let entries = archive.entries().unwrap();
+        assert_eq!(archive.entry_count(), Ok(entries.len()));
+        let names = names(&entries);
+        assert!(names.contains(&&b"a.txt"[..]));
+        assert!(names.contains(&&b"copy.txt"[..]));
+        assert!(names
+            .iter()
+            .any(|name| name.ends_with(b"b.txt") && name.starts_with(b"src")));
+
+        // The destination and its parents are created.
+        archive.unpack_to("/ext/.tmp/rs_tar/out/nested").unwrap();
+        assert_eq!(read("/ext/.tmp/rs_tar/out/nested/a.txt"), b"alpha");
+        assert_eq!(read("/ext/.tmp/rs_tar/out/nested/copy.txt"), b"bravo");
+        assert_eq!(read("/ext/.tmp/rs_tar/out/nested/src/b.txt"), b"bravo");
+
+        cleanup(&storage);
+    }
+
+    #[test]
+    fn filters_entries() {
+        let storage = Storage::open();
+        setup(&storage);
+
+        let archive_path = "/ext/.tmp/rs_tar/filter.tar";
+        let mut archive = TarArchive::create(archive_path).unwrap();
+        archive.add_data("keep.png", b"png").unwrap();
+        archive.add_data("skip.txt", b"txt").unwrap();
+        archive.finish().unwrap();
+
+        let mut seen = 0;
+        TarArchive::open_read(archive_path)
+            .unwrap()
+            .unpack_to_with(DIR, |entry| {
+                seen += 1;
+                entry.name().to_bytes().ends_with(b".png")
+            })
+            .unwrap();
+        assert_eq!(seen, 2);
+        assert_eq!(storage.file_exists("/ext/.tmp/rs_tar/keep.png"), Ok(true));
+        assert_eq!(storage.file_exists("/ext/.tmp/rs_tar/skip.txt"), Ok(false));
+
+        cleanup(&storage);
+    }
+
+    #[test]
+    fn escaping_entries_are_rejected() {
+        let storage = Storage::open();
+        setup(&storage);
+
+        let archive_path = "/ext/.tmp/rs_tar/escape.tar";
+        let mut archive = TarArchive::create(archive_path).unwrap();
+        archive.add_data("fine.txt", b"fine").unwrap();
+        archive.add_data("sub/../../evil.txt", b"evil").unwrap();
+        archive.finish().unwrap();
+
+        let mut archive = TarArchive::open_read(archive_path).unwrap();
+        assert_eq!(
+            archive.unpack_to("/ext/.tmp/rs_tar/out"),
+            Err(Error::InvalidName)
+        );
+        assert_eq!(storage.exists("/ext/.tmp/rs_tar/out"), Ok(false));
+        assert_eq!(storage.exists("/ext/.tmp/evil.txt"), Ok(false));
+
+        cleanup(&storage);
+    }
+
+    #[test]
+    fn truncated_archives_are_invalid() {
+        let storage = Storage::open();
+        setup(&storage);
+
+        let archive_path = "/ext/.tmp/rs_tar/full.tar";
+        let mut archive = TarArchive::create(archive_path).unwrap();
+        archive.add_data("first.bin", &[1; 600]).unwrap();
+        archive.add_data("second.bin", &[2; 600]).unwrap();
+        archive.finish().unwrap();
+
+        // The first header, and part of the data of the first file.
+        let data = read(archive_path);
+        let truncated = "/ext/.tmp/rs_tar/truncated.tar";
+        let mut file = File::create(truncated).unwrap();
+        file.write_all(&data[..700]).unwrap();
+        drop(file);
+
+        let mut archive = TarArchive::open_read(truncated).unwrap();
+        assert_eq!(archive.entries(), Err(Error::InvalidData));
+        assert_eq!(archive.entry_count(), Err(Error::InvalidData));
+        assert_eq!(
+            archive.unpack_to("/ext/.tmp/rs_tar/out"),
+            Err(Error::InvalidData)
+        );
+
+        let mut garbage = File::create("/ext/.tmp/rs_tar/garbage.tar").unwrap();
+        garbage.write_all(&[0xff; 100]).unwrap();
+        drop(garbage);
+        // The firmware only checks the first header once the archive is read.
+        let mut archive = TarArchive::open_read("/ext/.tmp/rs_tar/garbage.tar").unwrap();
+        assert_eq!(archive.entries(), Err(Error::InvalidData));
+        assert_eq!(
+            TarArchive::open_read("/ext/.tmp/rs_tar/missing.tar").err(),
+            Some(Error::NotExists)
+        );
+
+        cleanup(&storage);
+    }
+
+    #[test]
+    fn modes_are_checked() {
+        let storage = Storage::open();
+        setup(&storage);
+
+        let archive_path = "/ext/.tmp/rs_tar/modes.tar";
+        let mut archive = TarArchive::create(archive_path).unwrap();
+        assert_eq!(archive.entries().err(), Some(Error::Denied));
+        assert_eq!(archive.unpack_to(DIR), Err(Error::Denied));
+        archive.add_data("a", b"a").unwrap();
+        archive.finish().unwrap();
+
+        let mut archive = TarArchive::open_read(archive_path).unwrap();
+        assert_eq!(archive.add_data("b", b"b"), Err(Error::Denied));
+        assert_eq!(archive.add_dir(DIR, "dir"), Err(Error::Denied));
+        assert_eq!(archive.finish(), Err(Error::Denied));
+
+        cleanup(&storage);
+    }
+}
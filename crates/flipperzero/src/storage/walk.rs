@@ -0,0 +1,424 @@
+//! Recursive directory traversal.
+
+use alloc::vec::Vec;
+use core::ffi::CStr;
+
+use super::{AsPath, ContextError, EntryInfo, Metadata, Op, PathBuf, StorageBackend};
+use crate::io::Error;
+
+/// Deepest level below the root that a [`WalkDir`] descends to.
+///
+/// Every level keeps one directory open, so this bounds the handles and memory a walk
+/// takes up.
+pub const MAX_WALK_DEPTH: usize = 16;
+
+/// A builder for walking a directory tree, depth-first.
+///
+/// The walk yields every file and directory below the root, each directory followed by
+/// its contents. The root itself isn't yielded. Entries are returned in the order of
+/// the filesystem, which is not necessarily sorted.
+///
+/// Directories are read through [`StorageBackend::read_dir`], keeping one open per
+/// level of the walk and none once their entries have been returned.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use flipperzero::storage::{ContextError, Storage, WalkDir};
+/// # fn main() -> Result<(), ContextError> {
+/// let storage = Storage::open();
+/// for entry in WalkDir::new(&storage, c"/ext/subghz").dirs(false) {
+///     let entry = entry?;
+///     if entry.has_extension("sub") {
+///         flipperzero::println!("{}", entry.path());
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct WalkDir<'a, B: StorageBackend + ?Sized> {
+    backend: &'a B,
+    root: Result<PathBuf, Error>,
+    max_depth: usize,
+    dirs: bool,
+}
+
+impl<'a, B: StorageBackend + ?Sized> WalkDir<'a, B> {
+    /// Walks the directory at `root` on `backend`.
+    ///
+    /// A `root` containing a nul byte is returned as an [`Error::InvalidName`] by the
+    /// walk.
+    pub fn new<P: AsPath + ?Sized>(backend: &'a B, root: &P) -> Self {
+        Self {
+            backend,
+            root: root.with_c_str(|root| Ok(PathBuf::from(root))),
+            max_depth: MAX_WALK_DEPTH,
+            dirs: true,
+        }
+    }
+
+    /// Only descends `depth` levels below the root, which is [`MAX_WALK_DEPTH`] by
+    /// default and at most.
+    ///
+    /// A depth of 1 only yields the entries of the root, and directories at the last
+    /// level are yielded without their contents.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth.min(MAX_WALK_DEPTH);
+        self
+    }
+
+    /// Sets whether directories are yielded or only descended into, which they are by
+    /// default.
+    pub fn dirs(mut self, yes: bool) -> Self {
+        self.dirs = yes;
+        self
+    }
+
+    /// Only yields the entries for which `predicate` returns `true`, and doesn't
+    /// descend into directories for which it returns `false`.
+    pub fn filter_entry<F>(self, predicate: F) -> Walk<'a, B, F>
+    where
+        F: FnMut(&WalkEntry) -> bool,
+    {
+        Walk {
+            backend: self.backend,
+            root: Some(self.root),
+            stack: Vec::new(),
+            pending: None,
+            max_depth: self.max_depth,
+            dirs: self.dirs,
+            predicate,
+        }
+    }
+}
+
+impl<'a, B: StorageBackend + ?Sized> IntoIterator for WalkDir<'a, B> {
+    type Item = Result<WalkEntry, ContextError>;
+    type IntoIter = Walk<'a, B, fn(&WalkEntry) -> bool>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let all: fn(&WalkEntry) -> bool = |_| true;
+        self.filter_entry(all)
+    }
+}
+
+/// A directory of the walk whose entries haven't all been returned yet.
+struct Level<D> {
+    entries: D,
+    path: PathBuf,
+}
+
+/// An iterator over a directory tree, as returned by [`WalkDir`].
+///
+/// A directory that can't be read is returned as an error recording the directory,
+/// after which the walk continues with its siblings.
+pub struct Walk<'a, B: StorageBackend + ?Sized, F> {
+    backend: &'a B,
+    /// The root, until it is opened.
+    root: Option<Result<PathBuf, Error>>,
+    stack: Vec<Level<B::ReadDir>>,
+    /// The error opening a directory that was yielded, returned next.
+    pending: Option<ContextError>,
+    max_depth: usize,
+    dirs: bool,
+    predicate: F,
+}
+
+impl<B: StorageBackend + ?Sized, F> Walk<'_, B, F> {
+    /// Opens the directory at `path` as the next level of the walk.
+    fn push(&mut self, path: PathBuf) -> Result<(), ContextError> {
+        match self.backend.read_dir(path.as_c_str()) {
+            Ok(entries) => {
+                self.stack.push(Level { entries, path });
+                Ok(())
+            }
+            Err(e) => Err(ContextError::new(e, Op::ReadDir, Some(path.as_c_str()))),
+        }
+    }
+}
+
+impl<B, F> Iterator for Walk<'_, B, F>
+where
+    B: StorageBackend + ?Sized,
+    F: FnMut(&WalkEntry) -> bool,
+{
+    type Item = Result<WalkEntry, ContextError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(error) = self.pending.take() {
+            return Some(Err(error));
+        }
+        if let Some(root) = self.root.take() {
+            let pushed = match root {
+                Ok(root) if self.max_depth > 0 => self.push(root),
+                Ok(_) => Ok(()),
+                Err(e) => Err(ContextError::new(e, Op::ReadDir, None)),
+            };
+            if let Err(e) = pushed {
+                return Some(Err(e));
+            }
+        }
+
+        loop {
+            let depth = self.stack.len();
+            let level = self.stack.last_mut()?;
+            let info: EntryInfo = match level.entries.next() {
+                Some(Ok(info)) => info,
+                Some(Err(e)) => {
+                    let path = level.path.as_c_str();
+                    return Some(Err(ContextError::new(e, Op::ReadDir, Some(path))));
+                }
+                None => {
+                    // Closes the directory before going on with its siblings.
+                    self.stack.pop();
+                    continue;
+                }
+            };
+            let path = match level.path.join(info.name()) {
+                Ok(path) => path,
+                Err(e) => {
+                    let path = level.path.as_c_str();
+                    return Some(Err(ContextError::new(e, Op::ReadDir, Some(path))));
+                }
+            };
+
+            let entry = WalkEntry {
+                path,
+                depth,
+                metadata: info.metadata(),
+            };
+            if !(self.predicate)(&entry) {
+                continue;
+            }
+            if entry.is_dir() && depth < self.max_depth {
+                if let Err(e) = self.push(entry.path.clone()) {
+                    if !self.dirs {
+                        return Some(Err(e));
+                    }
+                    self.pending = Some(e);
+                }
+            }
+            if entry.is_dir() && !self.dirs {
+                continue;
+            }
+            return Some(Ok(entry));
+        }
+    }
+}
+
+/// A file or directory found by a [`Walk`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WalkEntry {
+    path: PathBuf,
+    depth: usize,
+    metadata: Metadata,
+}
+
+impl WalkEntry {
+    /// Returns the path of the entry, starting with the root of the walk.
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// Returns the path of the entry.
+    pub fn into_path(self) -> PathBuf {
+        self.path
+    }
+
+    /// Returns the name of the entry, without the path of its directory.
+    pub fn file_name(&self) -> &CStr {
+        let path = self.path.as_c_str().to_bytes_with_nul();
+        let start = path.iter().rposition(|&b| b == b'/').map_or(0, |i| i + 1);
+        // Only a prefix was skipped, which keeps the terminator.
+        CStr::from_bytes_with_nul(&path[start..]).unwrap()
+    }
+
+    /// Returns how many levels below the root the entry is, starting at 1 for the
+    /// entries of the root.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Returns `true` if the entry is a directory.
+    pub fn is_dir(&self) -> bool {
+        self.metadata.is_dir()
+    }
+
+    /// Returns whether the entry is a file or a directory, and its size.
+    pub fn metadata(&self) -> Metadata {
+        self.metadata
+    }
+
+    /// Returns `true` if the entry is a file whose name ends with `.` and `extension`,
+    /// ignoring ASCII case.
+    ///
+    /// `extension` is given without the leading dot, such as `"sub"`.
+    pub fn has_extension(&self, extension: &str) -> bool {
+        let name = self.file_name().to_bytes();
+        let extension = extension.as_bytes();
+        !self.is_dir()
+            && name.len() > extension.len() + 1
+            && name[name.len() - extension.len() - 1] == b'.'
+            && name[name.len() - extension.len()..].eq_ignore_ascii_case(extension)
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::{WalkDir, WalkEntry};
+    use crate::io::Error;
+    use crate::storage::{MemStorage, Op, OpenOptions, StorageBackend};
+
+    /// Returns a storage with a small tree below `/ext/walk`.
+    fn tree() -> MemStorage {
+        let storage = MemStorage::new();
+        for dir in [
+            c"/ext/walk",
+            c"/ext/walk/a",
+            c"/ext/walk/a/b",
+            c"/ext/walk/c",
+        ] {
+            storage.create_dir(dir).unwrap();
+        }
+        let options = OpenOptions::new().write(true).create_new(true);
+        for file in [
+            c"/ext/walk/a/b/deep.SUB",
+            c"/ext/walk/a/x.sub",
+            c"/ext/walk/c/notes.txt",
+            c"/ext/walk/top.sub",
+        ] {
+            storage.open(file, options).unwrap();
+        }
+        storage
+    }
+
+    /// Checks that `entries` are at the paths `expected`, in order.
+    fn assert_paths(entries: impl IntoIterator<Item = WalkEntry>, expected: &[&str]) {
+        let mut count = 0;
+        for (entry, expected) in entries.into_iter().zip(expected) {
+            assert_eq!(entry.path().as_c_str().to_bytes(), expected.as_bytes());
+            count += 1;
+        }
+        assert_eq!(count, expected.len());
+    }
+
+    #[test]
+    fn walks_depth_first() {
+        let storage = tree();
+        let entries: Vec<_> = WalkDir::new(&storage, "/ext/walk")
+            .into_iter()
+            .map(Result::unwrap)
+            .collect();
+        let depths: Vec<_> = entries.iter().map(WalkEntry::depth).collect();
+        assert_paths(
+            entries,
+            &[
+                "/ext/walk/a",
+                "/ext/walk/a/b",
+                "/ext/walk/a/b/deep.SUB",
+                "/ext/walk/a/x.sub",
+                "/ext/walk/c",
+                "/ext/walk/c/notes.txt",
+                "/ext/walk/top.sub",
+            ],
+        );
+        assert_eq!(depths, [1, 2, 3, 2, 1, 2, 1]);
+    }
+
+    #[test]
+    fn limits_depth_and_skips_dirs() {
+        let storage = tree();
+        let walk = WalkDir::new(&storage, "/ext/walk").max_depth(1);
+        assert_paths(
+            walk.into_iter().map(Result::unwrap),
+            &["/ext/walk/a", "/ext/walk/c", "/ext/walk/top.sub"],
+        );
+
+        let walk = WalkDir::new(&storage, "/ext/walk").max_depth(2).dirs(false);
+        assert_paths(
+            walk.into_iter().map(Result::unwrap),
+            &[
+                "/ext/walk/a/x.sub",
+                "/ext/walk/c/notes.txt",
+                "/ext/walk/top.sub",
+            ],
+        );
+
+        assert!(WalkDir::new(&storage, "/ext/walk")
+            .max_depth(0)
+            .into_iter()
+            .next()
+            .is_none());
+    }
+
+    #[test]
+    fn filters_entries() {
+        let storage = tree();
+        let walk = WalkDir::new(&storage, "/ext/walk")
+            .filter_entry(|entry| entry.file_name() != c"a")
+            .map(Result::unwrap)
+            .filter(|entry| entry.has_extension("sub"));
+        assert_paths(walk, &["/ext/walk/top.sub"]);
+
+        let walk = WalkDir::new(&storage, "/ext/walk")
+            .into_iter()
+            .map(Result::unwrap)
+            .filter(|entry| entry.has_extension("sub"));
+        assert_paths(
+            walk,
+            &[
+                "/ext/walk/a/b/deep.SUB",
+                "/ext/walk/a/x.sub",
+                "/ext/walk/top.sub",
+            ],
+        );
+    }
+
+    #[test]
+    fn continues_after_unreadable_dirs() {
+        let storage = tree();
+        // Fails reading `a`, which happens right after it passed the filter.
+        let walk = WalkDir::new(&storage, "/ext/walk").filter_entry(|entry| {
+            if entry.file_name() == c"a" {
+                storage.fail_next(Error::Denied);
+            }
+            true
+        });
+        let entries: Vec<_> = walk.collect();
+        assert_eq!(entries.len(), 5);
+        assert_eq!(
+            entries[0].as_ref().unwrap().path().as_c_str(),
+            c"/ext/walk/a"
+        );
+        let error = entries[1].as_ref().unwrap_err();
+        assert_eq!(error.error(), Error::Denied);
+        assert_eq!(error.op(), Op::ReadDir);
+        assert_eq!(error.path(), Some(c"/ext/walk/a"));
+        let rest: Vec<_> = entries[2..].iter().map(|e| e.clone().unwrap()).collect();
+        assert_paths(
+            rest,
+            &["/ext/walk/c", "/ext/walk/c/notes.txt", "/ext/walk/top.sub"],
+        );
+    }
+
+    #[test]
+    fn reports_unreadable_roots() {
+        let storage = tree();
+        let mut walk = WalkDir::new(&storage, "/ext/missing").into_iter();
+        assert_eq!(walk.next().unwrap().unwrap_err().error(), Error::NotExists);
+        assert!(walk.next().is_none());
+
+        storage.eject();
+        let mut walk = WalkDir::new(&storage, "/ext/walk").into_iter();
+        assert_eq!(walk.next().unwrap().unwrap_err().error(), Error::NotReady);
+        assert!(walk.next().is_none());
+
+        let mut walk = WalkDir::new(&storage, "/ext/wa\0lk").into_iter();
+        assert_eq!(
+            walk.next().unwrap().unwrap_err().error(),
+            Error::InvalidName
+        );
+    }
+}
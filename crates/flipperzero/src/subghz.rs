@@ -0,0 +1,398 @@
+//! Sub-GHz radio.
+//!
+//! The CC1101 radio of the Flipper Zero sends and receives signals as the durations of
+//! their high and low levels, which protocol encoders and decoders turn into bits.
+//! Transmitting is only allowed on the frequencies of the region the device is
+//! provisioned for.
+
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+use core::ffi::c_void;
+use core::fmt;
+use core::marker::PhantomData;
+use core::ptr::addr_of;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use flipperzero_sys as sys;
+
+#[cfg(feature = "alloc")]
+type CaptureCallback = Box<dyn FnMut(Pulse) + Send>;
+
+/// Whether a [`Radio`] exists.
+static IN_USE: AtomicBool = AtomicBool::new(false);
+
+/// `LEVEL_DURATION_RESET` in the firmware, which ends a transmission.
+const LEVEL_RESET: u8 = 0;
+/// `LEVEL_DURATION_LEVEL_LOW` in the firmware.
+const LEVEL_LOW: u8 = 1;
+/// `LEVEL_DURATION_LEVEL_HIGH` in the firmware.
+const LEVEL_HIGH: u8 = 2;
+
+/// Longest duration of a [`Pulse`] that can be transmitted, in microseconds.
+pub const MAX_DURATION: u32 = (1 << 30) - 1;
+
+/// Reasons why using the radio failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// Another [`Radio`] exists.
+    Busy,
+    /// The frequency, in Hz, is outside the bands the radio can be tuned to.
+    InvalidFrequency(u32),
+    /// Transmitting on the frequency, in Hz, isn't allowed in the region of the device.
+    TxNotAllowed(u32),
+}
+
+impl Error {
+    /// Description associated with [`Error`].
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Busy => "radio is busy",
+            Self::InvalidFrequency(_) => "invalid frequency",
+            Self::TxNotAllowed(_) => "transmission not allowed in this region",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.description())
+    }
+}
+
+impl ufmt::uDisplay for Error {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        f.write_str(self.description())
+    }
+}
+
+/// A modulation and bandwidth setting of the firmware, for signals timed by the
+/// sender.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    /// On-off keying with a bandwidth of 270kHz.
+    Ook270Async,
+    /// On-off keying with a bandwidth of 650kHz, which most remotes use.
+    Ook650Async,
+    /// 2-FSK with a deviation of 2.38kHz.
+    Fsk2Dev238Async,
+    /// 2-FSK with a deviation of 47.6kHz.
+    Fsk2Dev476Async,
+    /// MSK at 99.97kBaud.
+    Msk99_97KbAsync,
+    /// GFSK at 9.99kBaud.
+    Gfsk9_99KbAsync,
+}
+
+impl Preset {
+    /// Returns the register values of the preset, in the format of
+    /// `furi_hal_subghz_load_custom_preset`.
+    fn registers(self) -> *const u8 {
+        let registers = match self {
+            Self::Ook270Async => {
+                addr_of!(sys::subghz_device_cc1101_preset_ook_270khz_async_regs)
+            }
+            Self::Ook650Async => {
+                addr_of!(sys::subghz_device_cc1101_preset_ook_650khz_async_regs)
+            }
+            Self::Fsk2Dev238Async => {
+                addr_of!(sys::subghz_device_cc1101_preset_2fsk_dev2_38khz_async_regs)
+            }
+            Self::Fsk2Dev476Async => {
+                addr_of!(sys::subghz_device_cc1101_preset_2fsk_dev47_6khz_async_regs)
+            }
+            Self::Msk99_97KbAsync => {
+                addr_of!(sys::subghz_device_cc1101_preset_msk_99_97kb_async_regs)
+            }
+            Self::Gfsk9_99KbAsync => {
+                addr_of!(sys::subghz_device_cc1101_preset_gfsk_9_99kb_async_regs)
+            }
+        };
+        registers.cast()
+    }
+}
+
+/// A level of a signal and how long it lasted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pulse {
+    /// `true` for the high level, which is the carrier being on for on-off keying.
+    pub level: bool,
+    /// Duration in microseconds.
+    pub duration: u32,
+}
+
+impl Pulse {
+    /// Returns the pulse in the form the firmware transmits.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the duration is above [`MAX_DURATION`].
+    pub fn to_sys(&self) -> sys::LevelDuration {
+        assert!(
+            self.duration <= MAX_DURATION,
+            "pulses can last at most `MAX_DURATION`"
+        );
+        let level = if self.level { LEVEL_HIGH } else { LEVEL_LOW };
+        level_duration(level, self.duration)
+    }
+}
+
+fn level_duration(level: u8, duration: u32) -> sys::LevelDuration {
+    sys::LevelDuration {
+        _bitfield_align_1: [],
+        _bitfield_1: sys::LevelDuration::new_bitfield_1(duration, level),
+    }
+}
+
+/// The internal sub-GHz radio, which is put to sleep once this is dropped.
+///
+/// Only one `Radio` exists at a time. The firmware's Sub-GHz app must not run at the
+/// same time, which is the case while an app runs in the foreground.
+///
+/// # Examples
+///
+/// ```no_run
+/// use flipperzero::subghz::{Preset, Pulse, Radio};
+///
+/// let mut radio = Radio::acquire(Preset::Ook650Async, 433_920_000).expect("radio is free");
+/// let bit = |level| Pulse { level, duration: 400 };
+/// radio.transmit(&[bit(true), bit(false), bit(true), bit(false)])?;
+/// # Ok::<(), flipperzero::subghz::Error>(())
+/// ```
+pub struct Radio {
+    frequency: u32,
+    // The radio is driven through global state of the firmware.
+    _marker: PhantomData<*const ()>,
+}
+
+impl Radio {
+    /// Takes the radio, configured with `preset` and tuned to `frequency` Hz.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Busy`] if another `Radio` exists, and
+    /// [`Error::InvalidFrequency`] if the radio can't be tuned to `frequency`.
+    pub fn acquire(preset: Preset, frequency: u32) -> Result<Self, Error> {
+        if IN_USE.swap(true, Ordering::Acquire) {
+            return Err(Error::Busy);
+        }
+        let mut radio = Self {
+            frequency: 0,
+            _marker: PhantomData,
+        };
+        unsafe {
+            sys::furi_hal_subghz_reset();
+            sys::furi_hal_subghz_idle();
+        }
+        radio.load_preset(preset);
+        radio.set_frequency(frequency)?;
+        Ok(radio)
+    }
+
+    /// Configures the radio with `preset`.
+    pub fn load_preset(&mut self, preset: Preset) {
+        unsafe { sys::furi_hal_subghz_load_custom_preset(preset.registers()) };
+    }
+
+    /// Tunes the radio to `frequency` Hz, returning the frequency it was tuned to, which
+    /// is the closest the radio supports.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidFrequency`] if the radio can't be tuned to `frequency`,
+    /// leaving its frequency unchanged.
+    pub fn set_frequency(&mut self, frequency: u32) -> Result<u32, Error> {
+        if !unsafe { sys::furi_hal_subghz_is_frequency_valid(frequency) } {
+            return Err(Error::InvalidFrequency(frequency));
+        }
+        self.frequency = unsafe { sys::furi_hal_subghz_set_frequency_and_path(frequency) };
+        Ok(self.frequency)
+    }
+
+    /// Returns the frequency the radio is tuned to, in Hz.
+    pub fn frequency(&self) -> u32 {
+        self.frequency
+    }
+
+    /// Returns whether transmitting is allowed on the frequency the radio is tuned to.
+    pub fn is_tx_allowed(&self) -> bool {
+        unsafe { sys::furi_hal_region_is_frequency_allowed(self.frequency) }
+    }
+
+    /// Measures the strength of the signal being received, in dBm.
+    pub fn rssi(&mut self) -> f32 {
+        unsafe {
+            sys::furi_hal_subghz_rx();
+            let rssi = sys::furi_hal_subghz_get_rssi();
+            sys::furi_hal_subghz_idle();
+            rssi
+        }
+    }
+
+    /// Transmits `pulses`, blocking until they have been sent.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::TxNotAllowed`] without transmitting if transmitting isn't
+    /// allowed on the frequency of the radio.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a pulse lasts longer than [`MAX_DURATION`].
+    pub fn transmit(&mut self, pulses: &[Pulse]) -> Result<(), Error> {
+        struct Transmission<'a> {
+            pulses: &'a [Pulse],
+            next: AtomicUsize,
+        }
+
+        unsafe extern "C" fn next_pulse(context: *mut c_void) -> sys::LevelDuration {
+            let transmission = unsafe { &*(context as *const Transmission) };
+            let next = transmission.next.fetch_add(1, Ordering::Relaxed);
+            match transmission.pulses.get(next) {
+                Some(pulse) => pulse.to_sys(),
+                None => level_duration(LEVEL_RESET, 0),
+            }
+        }
+
+        // Check the durations now, as panicking in the interrupt would abort.
+        for pulse in pulses {
+            pulse.to_sys();
+        }
+        let transmission = Transmission {
+            pulses,
+            next: AtomicUsize::new(0),
+        };
+        let started = unsafe {
+            sys::furi_hal_subghz_start_async_tx(
+                Some(next_pulse),
+                &transmission as *const Transmission as *mut c_void,
+            )
+        };
+        if !started {
+            return Err(Error::TxNotAllowed(self.frequency));
+        }
+        unsafe {
+            while !sys::furi_hal_subghz_is_async_tx_complete() {
+                sys::furi_delay_ms(1);
+            }
+            sys::furi_hal_subghz_stop_async_tx();
+            sys::furi_hal_subghz_idle();
+        }
+        Ok(())
+    }
+
+    /// Starts receiving, calling `callback` with each pulse of the signal until the
+    /// returned [`Receiver`] is dropped.
+    ///
+    /// The closure is called in interrupt context, so it must be quick and must not
+    /// block, which rules out allocating, locking mutexes and most of the Furi APIs. It
+    /// can put the pulses in a
+    /// [`MessageQueue`](crate::furi::message_queue::MessageQueue) without waiting, for
+    /// a thread to decode them.
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+    pub fn receive<F>(&mut self, callback: F) -> Receiver<'_>
+    where
+        F: FnMut(Pulse) + Send + 'static,
+    {
+        unsafe extern "C" fn run_callback(level: bool, duration: u32, context: *mut c_void) {
+            let callback = unsafe { &mut *(context as *mut CaptureCallback) };
+            callback(Pulse { level, duration });
+        }
+
+        // Boxed twice for a thin pointer, see `furi::thread::Builder::spawn`.
+        let mut callback: Box<CaptureCallback> = Box::new(Box::new(callback));
+        unsafe {
+            sys::furi_hal_subghz_flush_rx();
+            sys::furi_hal_subghz_rx();
+            sys::furi_hal_subghz_start_async_rx(
+                Some(run_callback),
+                &mut *callback as *mut CaptureCallback as *mut c_void,
+            );
+        }
+
+        Receiver {
+            _radio: self,
+            _callback: callback,
+        }
+    }
+}
+
+impl Drop for Radio {
+    fn drop(&mut self) {
+        unsafe {
+            sys::furi_hal_subghz_idle();
+            sys::furi_hal_subghz_sleep();
+        }
+        IN_USE.store(false, Ordering::Release);
+    }
+}
+
+/// A reception started by [`Radio::receive`], which stops once this is dropped.
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub struct Receiver<'a> {
+    _radio: &'a mut Radio,
+    _callback: Box<CaptureCallback>,
+}
+
+#[cfg(feature = "alloc")]
+impl Drop for Receiver<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            sys::furi_hal_subghz_stop_async_rx();
+            sys::furi_hal_subghz_idle();
+        }
+        // The closure is dropped after this, once it can no longer be called.
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use super::{Error, Preset, Pulse, Radio, MAX_DURATION};
+
+    #[test]
+    fn pulses_map_to_sys() {
+        let high = Pulse {
+            level: true,
+            duration: MAX_DURATION,
+        }
+        .to_sys();
+        assert_eq!(high.duration(), MAX_DURATION);
+        assert_eq!(high.level(), 2);
+
+        let low = Pulse {
+            level: false,
+            duration: 350,
+        }
+        .to_sys();
+        assert_eq!(low.duration(), 350);
+        assert_eq!(low.level(), 1);
+    }
+
+    #[test]
+    fn radio_is_acquired_once() {
+        let mut radio = Radio::acquire(Preset::Ook650Async, 433_920_000).unwrap();
+        assert!(matches!(
+            Radio::acquire(Preset::Ook270Async, 433_920_000),
+            Err(Error::Busy)
+        ));
+
+        // The radio is tuned in steps of about 400Hz.
+        let tuned = radio.set_frequency(315_000_000).unwrap();
+        assert!(tuned.abs_diff(315_000_000) < 1000);
+        assert_eq!(radio.frequency(), tuned);
+        assert_eq!(
+            radio.set_frequency(100_000_000),
+            Err(Error::InvalidFrequency(100_000_000))
+        );
+        assert_eq!(radio.frequency(), tuned);
+        assert!((-140.0..0.0).contains(&radio.rssi()));
+
+        drop(radio);
+        assert!(Radio::acquire(Preset::Ook650Async, 868_350_000).is_ok());
+    }
+}
@@ -0,0 +1,510 @@
+//! Conformance checks for implementations of [`Read`], [`Write`] and [`Seek`].
+//!
+//! Files, caches, devices and mocks all implement the same traits, and code written
+//! against the traits relies on them behaving alike. The checks here spell out that
+//! behavior, following `std::io`:
+//!
+//! - Reads return the bytes in order, and `Ok(0)` at and after the end, every time.
+//! - Seeks return the new position, which [`Seek::stream_position`] then returns too.
+//!   Seeking before the start fails and leaves the position alone. Seeking past the end
+//!   succeeds, and reads there return `Ok(0)`.
+//! - [`Seek::stream_len`] and [`Seek::rewind`] behave like the seeks they stand for,
+//!   and `stream_len` doesn't move the position.
+//! - Written bytes can be read back. Writing in the middle overwrites without changing
+//!   the length, and writing past the end extends the stream up to the written bytes.
+//! - Empty reads and writes return `Ok(0)`.
+//!
+//! To run the checks, pass a closure that opens a stream with the given contents,
+//! positioned at its start, to [`check_read_seek`] or [`check_read_write_seek`]. The
+//! closure is called once per check.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # use flipperzero::io::{Seek, Write};
+//! # use flipperzero::storage::OpenOptions;
+//! # use flipperzero::testsuite;
+//! let result = testsuite::check_read_write_seek(|contents| {
+//!     let mut file = OpenOptions::new()
+//!         .read(true)
+//!         .write(true)
+//!         .create_always(true)
+//!         .open(c"/ext/.tmp/conformance")?;
+//!     file.write_all(contents)?;
+//!     file.rewind()?;
+//!     Ok(file)
+//! });
+//! if let Err(failure) = result {
+//!     flipperzero::println!("{}", failure);
+//! }
+//! ```
+
+use core::fmt;
+
+use crate::io::{Error, Read, Seek, SeekFrom, Write};
+
+/// Length of the contents that streams are opened with.
+pub const CONTENTS_LEN: usize = 100;
+
+/// A check that a stream failed.
+#[derive(Debug, Clone, Copy)]
+pub struct Failure {
+    check: &'static str,
+    reason: &'static str,
+    error: Option<Error>,
+}
+
+impl Failure {
+    /// Returns the name of the check.
+    pub fn check(&self) -> &'static str {
+        self.check
+    }
+
+    /// Returns what the stream did wrong.
+    pub fn reason(&self) -> &'static str {
+        self.reason
+    }
+
+    /// Returns the error that the stream returned, if it returned one where it
+    /// shouldn't have.
+    pub fn error(&self) -> Option<Error> {
+        self.error
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.check, self.reason)?;
+        match self.error {
+            Some(error) => write!(f, " ({})", error),
+            None => Ok(()),
+        }
+    }
+}
+
+impl ufmt::uDisplay for Failure {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        ufmt::uwrite!(f, "{}: {}", self.check, self.reason)?;
+        match self.error {
+            Some(error) => ufmt::uwrite!(f, " ({})", error),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Why a check failed, before the name of the check is known.
+struct Problem {
+    reason: &'static str,
+    error: Option<Error>,
+}
+
+type Check<S> = (&'static str, fn(&mut S) -> Result<(), Problem>);
+
+/// Runs the checks for reading and seeking on streams opened by `open`.
+///
+/// # Errors
+///
+/// Returns the first check that failed, or that `open` failed for.
+pub fn check_read_seek<S, F>(open: F) -> Result<(), Failure>
+where
+    S: Read + Seek,
+    F: FnMut(&[u8]) -> Result<S, Error>,
+{
+    let checks: [Check<S>; 8] = [
+        ("reads_to_end", reads_to_end),
+        ("empty_read", empty_read),
+        ("seek_start", seek_start),
+        ("seek_current", seek_current),
+        ("seek_end", seek_end),
+        ("seek_before_start", seek_before_start),
+        ("seek_past_end", seek_past_end),
+        ("rewind", rewind),
+    ];
+    run(open, &checks)
+}
+
+/// Runs the checks of [`check_read_seek`], and those for writing, on streams opened by
+/// `open`.
+///
+/// # Errors
+///
+/// Returns the first check that failed, or that `open` failed for.
+pub fn check_read_write_seek<S, F>(mut open: F) -> Result<(), Failure>
+where
+    S: Read + Write + Seek,
+    F: FnMut(&[u8]) -> Result<S, Error>,
+{
+    check_read_seek(&mut open)?;
+    let checks: [Check<S>; 5] = [
+        ("round_trip", round_trip),
+        ("overwrite", overwrite),
+        ("write_past_end", write_past_end),
+        ("empty_write", empty_write),
+        ("write_advances", write_advances),
+    ];
+    run(open, &checks)
+}
+
+fn run<S, F>(mut open: F, checks: &[Check<S>]) -> Result<(), Failure>
+where
+    F: FnMut(&[u8]) -> Result<S, Error>,
+{
+    for &(check, run_check) in checks {
+        let failure = |problem: Problem| Failure {
+            check,
+            reason: problem.reason,
+            error: problem.error,
+        };
+        let mut stream = open(&contents()).map_err(|e| {
+            failure(Problem {
+                reason: "couldn't open the stream",
+                error: Some(e),
+            })
+        })?;
+        run_check(&mut stream).map_err(failure)?;
+    }
+    Ok(())
+}
+
+/// Returns the contents that streams are opened with.
+fn contents() -> [u8; CONTENTS_LEN] {
+    let mut contents = [0; CONTENTS_LEN];
+    for (i, byte) in contents.iter_mut().enumerate() {
+        *byte = (i * 7 + 3) as u8;
+    }
+    contents
+}
+
+/// Fails with `reason` if `result` is an error.
+fn step<T>(result: Result<T, Error>, reason: &'static str) -> Result<T, Problem> {
+    result.map_err(|e| Problem {
+        reason,
+        error: Some(e),
+    })
+}
+
+/// Fails with `reason` unless `condition` holds.
+fn ensure(condition: bool, reason: &'static str) -> Result<(), Problem> {
+    if condition {
+        Ok(())
+    } else {
+        Err(Problem {
+            reason,
+            error: None,
+        })
+    }
+}
+
+/// Reads until `buf` is full or the stream ends.
+fn read_fill<S: Read>(stream: &mut S, buf: &mut [u8]) -> Result<usize, Problem> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match step(stream.read(&mut buf[filled..]), "read failed")? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+fn position<S: Seek>(stream: &mut S) -> Result<usize, Problem> {
+    step(stream.stream_position(), "stream_position failed")
+}
+
+fn seek<S: Seek>(stream: &mut S, pos: SeekFrom) -> Result<usize, Problem> {
+    step(stream.seek(pos), "seek failed")
+}
+
+fn reads_to_end<S: Read + Seek>(stream: &mut S) -> Result<(), Problem> {
+    let mut data = [0; CONTENTS_LEN + 16];
+    let mut len = 0;
+    loop {
+        let end = (len + 7).min(data.len());
+        match step(stream.read(&mut data[len..end]), "read failed")? {
+            0 => break,
+            n => len += n,
+        }
+        ensure(len < data.len(), "read past the end of the contents")?;
+    }
+    ensure(
+        data[..len] == contents(),
+        "read other bytes than the contents",
+    )?;
+    for _ in 0..2 {
+        let n = step(stream.read(&mut data), "read at the end failed")?;
+        ensure(n == 0, "read at the end returned bytes")?;
+    }
+    Ok(())
+}
+
+fn empty_read<S: Read + Seek>(stream: &mut S) -> Result<(), Problem> {
+    let n = step(stream.read(&mut []), "empty read failed")?;
+    ensure(n == 0, "empty read returned bytes")?;
+    ensure(position(stream)? == 0, "empty read moved the position")
+}
+
+fn seek_start<S: Read + Seek>(stream: &mut S) -> Result<(), Problem> {
+    ensure(
+        seek(stream, SeekFrom::Start(40))? == 40,
+        "seek returned another position",
+    )?;
+    ensure(
+        position(stream)? == 40,
+        "stream_position disagrees with seek",
+    )?;
+    let mut buf = [0; 10];
+    ensure(
+        read_fill(stream, &mut buf)? == 10,
+        "read after seek was short",
+    )?;
+    ensure(
+        buf == contents()[40..50],
+        "read after seek returned other bytes",
+    )?;
+    ensure(position(stream)? == 50, "read didn't advance the position")
+}
+
+fn seek_current<S: Read + Seek>(stream: &mut S) -> Result<(), Problem> {
+    seek(stream, SeekFrom::Start(40))?;
+    ensure(
+        seek(stream, SeekFrom::Current(10))? == 50,
+        "seeking forward returned another position",
+    )?;
+    ensure(
+        seek(stream, SeekFrom::Current(-20))? == 30,
+        "seeking back returned another position",
+    )?;
+    let mut byte = [0];
+    ensure(
+        read_fill(stream, &mut byte)? == 1,
+        "read after seek was short",
+    )?;
+    ensure(
+        byte[0] == contents()[30],
+        "read after seek returned another byte",
+    )
+}
+
+fn seek_end<S: Read + Seek>(stream: &mut S) -> Result<(), Problem> {
+    ensure(
+        seek(stream, SeekFrom::End(0))? == CONTENTS_LEN,
+        "seeking to the end returned another position",
+    )?;
+    ensure(
+        seek(stream, SeekFrom::End(-1))? == CONTENTS_LEN - 1,
+        "seeking before the end returned another position",
+    )?;
+    let mut byte = [0];
+    ensure(
+        read_fill(stream, &mut byte)? == 1,
+        "read of the last byte was short",
+    )?;
+    ensure(
+        byte[0] == contents()[CONTENTS_LEN - 1],
+        "read of the last byte returned another byte",
+    )?;
+
+    seek(stream, SeekFrom::Start(10))?;
+    let len = step(stream.stream_len(), "stream_len failed")?;
+    ensure(len == CONTENTS_LEN, "stream_len returned another length")?;
+    ensure(position(stream)? == 10, "stream_len moved the position")
+}
+
+fn seek_before_start<S: Read + Seek>(stream: &mut S) -> Result<(), Problem> {
+    seek(stream, SeekFrom::Start(10))?;
+    ensure(
+        stream.seek(SeekFrom::Current(-11)).is_err(),
+        "seeking before the start succeeded",
+    )?;
+    ensure(position(stream)? == 10, "failed seek moved the position")
+}
+
+fn seek_past_end<S: Read + Seek>(stream: &mut S) -> Result<(), Problem> {
+    let target = CONTENTS_LEN + 10;
+    ensure(
+        seek(stream, SeekFrom::Start(target as u64))? == target,
+        "seeking past the end returned another position",
+    )?;
+    ensure(
+        position(stream)? == target,
+        "stream_position disagrees with seek past the end",
+    )?;
+    let n = step(stream.read(&mut [0; 4]), "read past the end failed")?;
+    ensure(n == 0, "read past the end returned bytes")
+}
+
+fn rewind<S: Read + Seek>(stream: &mut S) -> Result<(), Problem> {
+    read_fill(stream, &mut [0; 20])?;
+    step(stream.rewind(), "rewind failed")?;
+    ensure(position(stream)? == 0, "rewind didn't move to the start")?;
+    let mut buf = [0; 5];
+    ensure(
+        read_fill(stream, &mut buf)? == 5,
+        "read after rewind was short",
+    )?;
+    ensure(
+        buf == contents()[..5],
+        "read after rewind returned other bytes",
+    )
+}
+
+fn round_trip<S: Read + Write + Seek>(stream: &mut S) -> Result<(), Problem> {
+    seek(stream, SeekFrom::End(0))?;
+    step(stream.write_all(b"appended"), "write failed")?;
+    step(stream.flush(), "flush failed")?;
+    step(stream.rewind(), "rewind failed")?;
+
+    let mut data = [0; CONTENTS_LEN + 16];
+    let len = read_fill(stream, &mut data)?;
+    ensure(len == CONTENTS_LEN + 8, "read back another length")?;
+    ensure(
+        data[..CONTENTS_LEN] == contents(),
+        "write changed earlier bytes",
+    )?;
+    ensure(
+        &data[CONTENTS_LEN..len] == b"appended",
+        "read back other bytes than were written",
+    )
+}
+
+fn overwrite<S: Read + Write + Seek>(stream: &mut S) -> Result<(), Problem> {
+    seek(stream, SeekFrom::Start(10))?;
+    step(stream.write_all(b"XYZ"), "write failed")?;
+    let len = step(stream.stream_len(), "stream_len failed")?;
+    ensure(len == CONTENTS_LEN, "overwriting changed the length")?;
+
+    seek(stream, SeekFrom::Start(9))?;
+    let mut buf = [0; 5];
+    ensure(read_fill(stream, &mut buf)? == 5, "read back was short")?;
+    let expected = [contents()[9], b'X', b'Y', b'Z', contents()[13]];
+    ensure(buf == expected, "read back other bytes than were written")
+}
+
+fn write_past_end<S: Read + Write + Seek>(stream: &mut S) -> Result<(), Problem> {
+    seek(stream, SeekFrom::Start(CONTENTS_LEN as u64 + 4))?;
+    step(stream.write_all(&[1, 2]), "write past the end failed")?;
+    let len = step(stream.stream_len(), "stream_len failed")?;
+    ensure(
+        len == CONTENTS_LEN + 6,
+        "writing past the end didn't extend the stream",
+    )?;
+
+    seek(stream, SeekFrom::Start(CONTENTS_LEN as u64 + 4))?;
+    let mut buf = [0; 2];
+    ensure(read_fill(stream, &mut buf)? == 2, "read back was short")?;
+    ensure(buf == [1, 2], "read back other bytes than were written")
+}
+
+fn empty_write<S: Read + Write + Seek>(stream: &mut S) -> Result<(), Problem> {
+    let n = step(stream.write(&[]), "empty write failed")?;
+    ensure(n == 0, "empty write claimed to write bytes")?;
+    ensure(position(stream)? == 0, "empty write moved the position")
+}
+
+fn write_advances<S: Read + Write + Seek>(stream: &mut S) -> Result<(), Problem> {
+    seek(stream, SeekFrom::Start(20))?;
+    step(stream.write_all(b"abcd"), "write failed")?;
+    ensure(position(stream)? == 24, "write didn't advance the position")?;
+    let mut byte = [0];
+    ensure(
+        read_fill(stream, &mut byte)? == 1,
+        "read after write was short",
+    )?;
+    ensure(
+        byte[0] == contents()[24],
+        "read after write returned another byte",
+    )
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use super::{check_read_seek, check_read_write_seek};
+    use crate::io::{Error, Read, Seek, SeekFrom};
+
+    /// Reads the contents, but seeks like a reader that clamps to its end.
+    struct Clamping {
+        contents: [u8; super::CONTENTS_LEN],
+        pos: usize,
+    }
+
+    impl Read for Clamping {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+            let rest = &self.contents[self.pos..];
+            let n = rest.len().min(buf.len());
+            buf[..n].copy_from_slice(&rest[..n]);
+            self.pos += n;
+            Ok(n)
+        }
+    }
+
+    impl Seek for Clamping {
+        fn seek(&mut self, pos: SeekFrom) -> Result<usize, Error> {
+            let pos = match pos {
+                SeekFrom::Start(offset) => offset as i64,
+                SeekFrom::End(offset) => self.contents.len() as i64 + offset,
+                SeekFrom::Current(offset) => self.pos as i64 + offset,
+            };
+            if pos < 0 {
+                return Err(Error::InvalidParameter);
+            }
+            self.pos = (pos as usize).min(self.contents.len());
+            Ok(self.pos)
+        }
+    }
+
+    #[test]
+    fn finds_divergences() {
+        let failure = check_read_seek(|contents| {
+            Ok(Clamping {
+                contents: contents.try_into().unwrap(),
+                pos: 0,
+            })
+        })
+        .unwrap_err();
+        assert_eq!(failure.check(), "seek_past_end");
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn mem_storage_conforms() {
+        use crate::io::Write;
+        use crate::storage::{MemStorage, OpenOptions, StorageBackend};
+
+        let storage = MemStorage::new();
+        let path = c"/ext/file";
+        let result = check_read_write_seek(|contents| {
+            let options = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create_always(true);
+            let mut file = storage.open(path, options)?;
+            file.write_all(contents)?;
+            file.rewind()?;
+            Ok(file)
+        });
+        assert!(result.is_ok());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn block_cache_conforms() {
+        use crate::io::Write;
+        use crate::storage::{BlockCache, MemStorage, OpenOptions, StorageBackend};
+
+        let storage = MemStorage::new();
+        let path = c"/ext/file";
+        let result = check_read_write_seek(|contents| {
+            let options = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create_always(true);
+            let mut file = storage.open(path, options)?;
+            file.write_all(contents)?;
+            Ok(BlockCache::new(file, 16, 2))
+        });
+        assert!(result.is_ok());
+    }
+}
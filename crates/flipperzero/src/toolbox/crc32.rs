@@ -1,5 +1,7 @@
 use flipperzero_sys as sys;
 
+use crate::io::{Error, Read};
+
 /// The [CRC32 error-detecting code][1].
 ///
 /// Equivalent to [`crc32fast::Hasher`].
@@ -28,6 +30,20 @@ impl Crc32 {
         self.state = unsafe { sys::crc32_calc_buffer(self.state, buf.as_ptr().cast(), buf.len()) };
     }
 
+    /// Processes everything `reader` produces until its end, in chunks of 512 bytes.
+    ///
+    /// The bytes read before an error are processed, so the state can't be reused after
+    /// one.
+    pub fn update_reader<R: Read + ?Sized>(&mut self, reader: &mut R) -> Result<(), Error> {
+        let mut buf = [0; 512];
+        loop {
+            match reader.read(&mut buf)? {
+                0 => return Ok(()),
+                n => self.update(&buf[..n]),
+            }
+        }
+    }
+
     /// Retrieves the computed CRC32 value and consumes the calculator instance.
     pub fn finalize(self) -> u32 {
         self.state
@@ -51,6 +67,7 @@ impl Default for Crc32 {
 #[flipperzero_test::tests]
 mod tests {
     use super::Crc32;
+    use crate::io::cursor::Cursor;
 
     #[test]
     fn crc32fast() {
@@ -66,6 +83,17 @@ mod tests {
         assert_eq!(fz.finalize(), rs.finalize());
     }
 
+    #[test]
+    fn update_reader_matches_update() {
+        let data: [u8; 1300] = core::array::from_fn(|i| i as u8);
+        let mut fz = Crc32::new();
+        // Reads of up to 100 bytes don't line up with the length.
+        fz.update_reader(&mut Cursor::new(&data).max(100)).unwrap();
+        let mut rs = crc32fast::Hasher::new();
+        rs.update(&data);
+        assert_eq!(fz.finalize(), rs.finalize());
+    }
+
     #[test]
     fn crc32fast_with_init() {
         for init in 0..5 {
@@ -0,0 +1,553 @@
+//! USB HID keyboard and mouse.
+//!
+//! [`Hid`] switches the USB device to a keyboard and mouse, which the computer uses like
+//! any other. Keys are given by their [`Key`] codes, and text is typed with a
+//! [`Layout`] that tells which key types each character, like the keyboard layouts of
+//! the Bad USB app.
+
+use core::ffi::CStr;
+use core::marker::PhantomData;
+use core::ptr::{self, addr_of_mut};
+
+use bitflags::bitflags;
+
+use flipperzero_sys as sys;
+
+use crate::io::{Error, Read};
+use crate::storage::File;
+
+/// Size of the `.kl` keyboard layout files of the Bad USB app.
+pub const LAYOUT_FILE_SIZE: usize = 256;
+
+bitflags! {
+    /// The modifier keys held while a [`Key`] is pressed.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Modifiers: u8 {
+        const LEFT_CTRL = 1 << 0;
+        const LEFT_SHIFT = 1 << 1;
+        const LEFT_ALT = 1 << 2;
+        const LEFT_GUI = 1 << 3;
+        const RIGHT_CTRL = 1 << 4;
+        const RIGHT_SHIFT = 1 << 5;
+        const RIGHT_ALT = 1 << 6;
+        const RIGHT_GUI = 1 << 7;
+    }
+}
+
+bitflags! {
+    /// The keyboard LEDs, as set by the computer.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Leds: u8 {
+        const NUM_LOCK = 1 << 0;
+        const CAPS_LOCK = 1 << 1;
+        const SCROLL_LOCK = 1 << 2;
+    }
+}
+
+/// A key of the keyboard, given by its HID usage ID, together with the modifiers held
+/// while it is pressed.
+///
+/// The names are those of the keys of a US keyboard, whose characters may differ from
+/// the layout the computer uses.
+///
+/// # Examples
+///
+/// ```
+/// use flipperzero::usb::hid::{Key, Modifiers};
+///
+/// let copy = Key::C.with(Modifiers::LEFT_CTRL);
+/// let windows = Key::NONE.with(Modifiers::LEFT_GUI);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Key(u16);
+
+impl Key {
+    /// No key, for pressing only modifiers.
+    pub const NONE: Self = Self(0);
+    pub const A: Self = Self::from_usage(0x04);
+    pub const B: Self = Self::from_usage(0x05);
+    pub const C: Self = Self::from_usage(0x06);
+    pub const D: Self = Self::from_usage(0x07);
+    pub const E: Self = Self::from_usage(0x08);
+    pub const F: Self = Self::from_usage(0x09);
+    pub const G: Self = Self::from_usage(0x0A);
+    pub const H: Self = Self::from_usage(0x0B);
+    pub const I: Self = Self::from_usage(0x0C);
+    pub const J: Self = Self::from_usage(0x0D);
+    pub const K: Self = Self::from_usage(0x0E);
+    pub const L: Self = Self::from_usage(0x0F);
+    pub const M: Self = Self::from_usage(0x10);
+    pub const N: Self = Self::from_usage(0x11);
+    pub const O: Self = Self::from_usage(0x12);
+    pub const P: Self = Self::from_usage(0x13);
+    pub const Q: Self = Self::from_usage(0x14);
+    pub const R: Self = Self::from_usage(0x15);
+    pub const S: Self = Self::from_usage(0x16);
+    pub const T: Self = Self::from_usage(0x17);
+    pub const U: Self = Self::from_usage(0x18);
+    pub const V: Self = Self::from_usage(0x19);
+    pub const W: Self = Self::from_usage(0x1A);
+    pub const X: Self = Self::from_usage(0x1B);
+    pub const Y: Self = Self::from_usage(0x1C);
+    pub const Z: Self = Self::from_usage(0x1D);
+    pub const DIGIT_1: Self = Self::from_usage(0x1E);
+    pub const DIGIT_2: Self = Self::from_usage(0x1F);
+    pub const DIGIT_3: Self = Self::from_usage(0x20);
+    pub const DIGIT_4: Self = Self::from_usage(0x21);
+    pub const DIGIT_5: Self = Self::from_usage(0x22);
+    pub const DIGIT_6: Self = Self::from_usage(0x23);
+    pub const DIGIT_7: Self = Self::from_usage(0x24);
+    pub const DIGIT_8: Self = Self::from_usage(0x25);
+    pub const DIGIT_9: Self = Self::from_usage(0x26);
+    pub const DIGIT_0: Self = Self::from_usage(0x27);
+    pub const ENTER: Self = Self::from_usage(0x28);
+    pub const ESCAPE: Self = Self::from_usage(0x29);
+    pub const BACKSPACE: Self = Self::from_usage(0x2A);
+    pub const TAB: Self = Self::from_usage(0x2B);
+    pub const SPACE: Self = Self::from_usage(0x2C);
+    pub const MINUS: Self = Self::from_usage(0x2D);
+    pub const EQUAL: Self = Self::from_usage(0x2E);
+    pub const LEFT_BRACKET: Self = Self::from_usage(0x2F);
+    pub const RIGHT_BRACKET: Self = Self::from_usage(0x30);
+    pub const BACKSLASH: Self = Self::from_usage(0x31);
+    pub const SEMICOLON: Self = Self::from_usage(0x33);
+    pub const APOSTROPHE: Self = Self::from_usage(0x34);
+    pub const GRAVE: Self = Self::from_usage(0x35);
+    pub const COMMA: Self = Self::from_usage(0x36);
+    pub const DOT: Self = Self::from_usage(0x37);
+    pub const SLASH: Self = Self::from_usage(0x38);
+    pub const CAPS_LOCK: Self = Self::from_usage(0x39);
+    pub const F1: Self = Self::from_usage(0x3A);
+    pub const F2: Self = Self::from_usage(0x3B);
+    pub const F3: Self = Self::from_usage(0x3C);
+    pub const F4: Self = Self::from_usage(0x3D);
+    pub const F5: Self = Self::from_usage(0x3E);
+    pub const F6: Self = Self::from_usage(0x3F);
+    pub const F7: Self = Self::from_usage(0x40);
+    pub const F8: Self = Self::from_usage(0x41);
+    pub const F9: Self = Self::from_usage(0x42);
+    pub const F10: Self = Self::from_usage(0x43);
+    pub const F11: Self = Self::from_usage(0x44);
+    pub const F12: Self = Self::from_usage(0x45);
+    pub const PRINT_SCREEN: Self = Self::from_usage(0x46);
+    pub const SCROLL_LOCK: Self = Self::from_usage(0x47);
+    pub const PAUSE: Self = Self::from_usage(0x48);
+    pub const INSERT: Self = Self::from_usage(0x49);
+    pub const HOME: Self = Self::from_usage(0x4A);
+    pub const PAGE_UP: Self = Self::from_usage(0x4B);
+    pub const DELETE: Self = Self::from_usage(0x4C);
+    pub const END: Self = Self::from_usage(0x4D);
+    pub const PAGE_DOWN: Self = Self::from_usage(0x4E);
+    pub const RIGHT_ARROW: Self = Self::from_usage(0x4F);
+    pub const LEFT_ARROW: Self = Self::from_usage(0x50);
+    pub const DOWN_ARROW: Self = Self::from_usage(0x51);
+    pub const UP_ARROW: Self = Self::from_usage(0x52);
+    pub const NUM_LOCK: Self = Self::from_usage(0x53);
+    pub const APPLICATION: Self = Self::from_usage(0x65);
+
+    /// Returns the key with the given HID usage ID.
+    pub const fn from_usage(usage: u8) -> Self {
+        Self(usage as u16)
+    }
+
+    /// Returns the key with `modifiers` held as well.
+    pub const fn with(self, modifiers: Modifiers) -> Self {
+        Self(self.0 | (modifiers.bits() as u16) << 8)
+    }
+
+    /// Returns the HID usage ID of the key.
+    pub const fn usage(self) -> u8 {
+        self.0 as u8
+    }
+
+    /// Returns the modifiers held with the key.
+    pub const fn modifiers(self) -> Modifiers {
+        Modifiers::from_bits_retain((self.0 >> 8) as u8)
+    }
+
+    /// Returns the key in the format of `furi_hal_hid_kb_press`, with the modifiers in
+    /// the high byte.
+    pub const fn to_sys(self) -> u16 {
+        self.0
+    }
+}
+
+/// A media or system control key, given by its HID consumer usage ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConsumerKey(pub u16);
+
+impl ConsumerKey {
+    /// Makes the screen brighter.
+    pub const BRIGHTNESS_UP: Self = Self(0x006F);
+    /// Makes the screen darker.
+    pub const BRIGHTNESS_DOWN: Self = Self(0x0070);
+    /// Skips to the next track.
+    pub const NEXT_TRACK: Self = Self(0x00B5);
+    /// Goes back to the previous track.
+    pub const PREVIOUS_TRACK: Self = Self(0x00B6);
+    /// Stops playback.
+    pub const STOP: Self = Self(0x00B7);
+    /// Starts or pauses playback.
+    pub const PLAY_PAUSE: Self = Self(0x00CD);
+    /// Mutes or unmutes the sound.
+    pub const MUTE: Self = Self(0x00E2);
+    /// Turns the volume up.
+    pub const VOLUME_UP: Self = Self(0x00E9);
+    /// Turns the volume down.
+    pub const VOLUME_DOWN: Self = Self(0x00EA);
+}
+
+/// A button of the mouse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseButton {
+    Left,
+    Right,
+    /// The wheel, when it is pressed.
+    Middle,
+}
+
+impl MouseButton {
+    /// Returns the button as a bit of the mask taken by `furi_hal_hid_mouse_press`.
+    pub fn to_sys(&self) -> u8 {
+        match self {
+            Self::Left => 1 << 0,
+            Self::Right => 1 << 1,
+            Self::Middle => 1 << 2,
+        }
+    }
+}
+
+/// Maps the ASCII characters to the keys that type them with a keyboard layout of the
+/// computer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layout {
+    keys: [u16; 128],
+}
+
+impl Layout {
+    /// The US layout.
+    pub const US: Self = Self::us();
+
+    const fn us() -> Self {
+        let mut keys = [0; 128];
+        let mut c = 0;
+        while c < keys.len() {
+            keys[c] = us_key(c as u8).0;
+            c += 1;
+        }
+        Self { keys }
+    }
+
+    /// Reads a layout from the contents of a `.kl` file of the Bad USB app, which holds
+    /// the key of each ASCII character in little-endian order.
+    pub fn from_kl_bytes(bytes: &[u8; LAYOUT_FILE_SIZE]) -> Self {
+        let mut keys = [0; 128];
+        for (key, bytes) in keys.iter_mut().zip(bytes.chunks_exact(2)) {
+            *key = u16::from_le_bytes([bytes[0], bytes[1]]);
+        }
+        Self { keys }
+    }
+
+    /// Loads the `.kl` file at `path`, such as one of the layouts in
+    /// `/ext/badusb/assets/layouts`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::UnexpectedEof`] if the file is too short, and any error of
+    /// reading it.
+    pub fn load(path: &CStr) -> Result<Self, Error> {
+        let mut bytes = [0; LAYOUT_FILE_SIZE];
+        File::open(path)?.read_exact(&mut bytes)?;
+        Ok(Self::from_kl_bytes(&bytes))
+    }
+
+    /// Returns the key that types `c`, or `None` if the layout has none.
+    pub fn key(&self, c: char) -> Option<Key> {
+        let key = *self.keys.get(c as usize)?;
+        (key != 0).then_some(Key(key))
+    }
+}
+
+/// Returns the key that types `c` with the US layout.
+const fn us_key(c: u8) -> Key {
+    let shift = Modifiers::LEFT_SHIFT;
+    match c {
+        b'a'..=b'z' => Key::from_usage(Key::A.usage() + (c - b'a')),
+        b'A'..=b'Z' => Key::from_usage(Key::A.usage() + (c - b'A')).with(shift),
+        b'1'..=b'9' => Key::from_usage(Key::DIGIT_1.usage() + (c - b'1')),
+        b'0' => Key::DIGIT_0,
+        b'\n' => Key::ENTER,
+        b'\t' => Key::TAB,
+        b' ' => Key::SPACE,
+        b'-' => Key::MINUS,
+        b'=' => Key::EQUAL,
+        b'[' => Key::LEFT_BRACKET,
+        b']' => Key::RIGHT_BRACKET,
+        b'\\' => Key::BACKSLASH,
+        b';' => Key::SEMICOLON,
+        b'\'' => Key::APOSTROPHE,
+        b'`' => Key::GRAVE,
+        b',' => Key::COMMA,
+        b'.' => Key::DOT,
+        b'/' => Key::SLASH,
+        b'!' => Key::DIGIT_1.with(shift),
+        b'@' => Key::DIGIT_2.with(shift),
+        b'#' => Key::DIGIT_3.with(shift),
+        b'$' => Key::DIGIT_4.with(shift),
+        b'%' => Key::DIGIT_5.with(shift),
+        b'^' => Key::DIGIT_6.with(shift),
+        b'&' => Key::DIGIT_7.with(shift),
+        b'*' => Key::DIGIT_8.with(shift),
+        b'(' => Key::DIGIT_9.with(shift),
+        b')' => Key::DIGIT_0.with(shift),
+        b'_' => Key::MINUS.with(shift),
+        b'+' => Key::EQUAL.with(shift),
+        b'{' => Key::LEFT_BRACKET.with(shift),
+        b'}' => Key::RIGHT_BRACKET.with(shift),
+        b'|' => Key::BACKSLASH.with(shift),
+        b':' => Key::SEMICOLON.with(shift),
+        b'"' => Key::APOSTROPHE.with(shift),
+        b'~' => Key::GRAVE.with(shift),
+        b'<' => Key::COMMA.with(shift),
+        b'>' => Key::DOT.with(shift),
+        b'?' => Key::SLASH.with(shift),
+        _ => Key::NONE,
+    }
+}
+
+/// Turns the result of a HID call into an error if the computer isn't connected.
+fn check(sent: bool) -> Result<(), Error> {
+    if sent {
+        Ok(())
+    } else {
+        Err(Error::NotReady)
+    }
+}
+
+/// The USB HID mode, in which the computer sees the Flipper Zero as a keyboard and a
+/// mouse.
+///
+/// Starting it switches the USB device to the HID mode, and dropping it releases all
+/// keys and buttons and switches back to the previous mode.
+///
+/// # Examples
+///
+/// ```no_run
+/// use flipperzero::usb::hid::{Hid, Key, Layout, Modifiers};
+///
+/// let hid = Hid::start()?;
+/// // Open the run dialog on Windows and start Notepad.
+/// hid.keyboard().tap(Key::R.with(Modifiers::LEFT_GUI))?;
+/// hid.keyboard().type_str("notepad\n", &Layout::US)?;
+/// # Ok::<(), flipperzero::io::Error>(())
+/// ```
+pub struct Hid {
+    previous_config: *mut sys::FuriHalUsbInterface,
+}
+
+impl Hid {
+    /// Switches the USB device to the HID mode.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::AlreadyOpen`] if the USB mode is locked, for example by another
+    /// app using USB.
+    pub fn start() -> Result<Self, Error> {
+        let previous_config = unsafe { sys::furi_hal_usb_get_config() };
+        if !unsafe { sys::furi_hal_usb_set_config(addr_of_mut!(sys::usb_hid), ptr::null_mut()) } {
+            return Err(Error::AlreadyOpen);
+        }
+        Ok(Self { previous_config })
+    }
+
+    /// Returns `true` if the computer has set up the device.
+    pub fn is_connected(&self) -> bool {
+        unsafe { sys::furi_hal_hid_is_connected() }
+    }
+
+    /// Returns the keyboard LEDs that the computer has turned on.
+    pub fn leds(&self) -> Leds {
+        Leds::from_bits_truncate(unsafe { sys::furi_hal_hid_get_led_state() })
+    }
+
+    /// Returns the keyboard.
+    pub fn keyboard(&self) -> HidKeyboard<'_> {
+        HidKeyboard { _hid: PhantomData }
+    }
+
+    /// Returns the mouse.
+    pub fn mouse(&self) -> HidMouse<'_> {
+        HidMouse { _hid: PhantomData }
+    }
+}
+
+impl Drop for Hid {
+    fn drop(&mut self) {
+        unsafe {
+            sys::furi_hal_hid_kb_release_all();
+            sys::furi_hal_hid_consumer_key_release_all();
+            for button in [MouseButton::Left, MouseButton::Right, MouseButton::Middle] {
+                sys::furi_hal_hid_mouse_release(button.to_sys());
+            }
+            if !self.previous_config.is_null() {
+                sys::furi_hal_usb_set_config(self.previous_config, ptr::null_mut());
+            }
+        }
+    }
+}
+
+/// The keyboard of a [`Hid`].
+///
+/// Presses and releases fail with [`Error::NotReady`] if the computer isn't connected.
+pub struct HidKeyboard<'a> {
+    _hid: PhantomData<&'a Hid>,
+}
+
+impl HidKeyboard<'_> {
+    /// Presses `key` and its modifiers, which stay held until they are released.
+    ///
+    /// Up to six keys can be held at once.
+    pub fn press(&mut self, key: Key) -> Result<(), Error> {
+        check(unsafe { sys::furi_hal_hid_kb_press(key.to_sys()) })
+    }
+
+    /// Releases `key` and its modifiers.
+    pub fn release(&mut self, key: Key) -> Result<(), Error> {
+        check(unsafe { sys::furi_hal_hid_kb_release(key.to_sys()) })
+    }
+
+    /// Releases all keys and modifiers.
+    pub fn release_all(&mut self) -> Result<(), Error> {
+        check(unsafe { sys::furi_hal_hid_kb_release_all() })
+    }
+
+    /// Presses and releases `key`.
+    pub fn tap(&mut self, key: Key) -> Result<(), Error> {
+        self.press(key)?;
+        self.release(key)
+    }
+
+    /// Types `text` by tapping the key of each character in `layout`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidParameter`] without typing anything if `layout` has no
+    /// key for a character of `text`.
+    pub fn type_str(&mut self, text: &str, layout: &Layout) -> Result<(), Error> {
+        if text.chars().any(|c| layout.key(c).is_none()) {
+            return Err(Error::InvalidParameter);
+        }
+        for key in text.chars().filter_map(|c| layout.key(c)) {
+            self.tap(key)?;
+        }
+        Ok(())
+    }
+
+    /// Presses the media or system control `key`.
+    pub fn press_consumer(&mut self, key: ConsumerKey) -> Result<(), Error> {
+        check(unsafe { sys::furi_hal_hid_consumer_key_press(key.0) })
+    }
+
+    /// Releases the media or system control `key`.
+    pub fn release_consumer(&mut self, key: ConsumerKey) -> Result<(), Error> {
+        check(unsafe { sys::furi_hal_hid_consumer_key_release(key.0) })
+    }
+
+    /// Presses and releases the media or system control `key`.
+    pub fn tap_consumer(&mut self, key: ConsumerKey) -> Result<(), Error> {
+        self.press_consumer(key)?;
+        self.release_consumer(key)
+    }
+}
+
+/// The mouse of a [`Hid`].
+///
+/// Moves and clicks fail with [`Error::NotReady`] if the computer isn't connected.
+pub struct HidMouse<'a> {
+    _hid: PhantomData<&'a Hid>,
+}
+
+impl HidMouse<'_> {
+    /// Moves the pointer by `dx` to the right and `dy` down.
+    ///
+    /// Moves of more than 127 are sent as several steps.
+    pub fn move_by(&mut self, mut dx: i32, mut dy: i32) -> Result<(), Error> {
+        while dx != 0 || dy != 0 {
+            let step_x = dx.clamp(-127, 127);
+            let step_y = dy.clamp(-127, 127);
+            check(unsafe { sys::furi_hal_hid_mouse_move(step_x as i8, step_y as i8) })?;
+            dx -= step_x;
+            dy -= step_y;
+        }
+        Ok(())
+    }
+
+    /// Presses `button`, which stays held until it is released.
+    pub fn press(&mut self, button: MouseButton) -> Result<(), Error> {
+        check(unsafe { sys::furi_hal_hid_mouse_press(button.to_sys()) })
+    }
+
+    /// Releases `button`.
+    pub fn release(&mut self, button: MouseButton) -> Result<(), Error> {
+        check(unsafe { sys::furi_hal_hid_mouse_release(button.to_sys()) })
+    }
+
+    /// Presses and releases `button`.
+    pub fn click(&mut self, button: MouseButton) -> Result<(), Error> {
+        self.press(button)?;
+        self.release(button)
+    }
+
+    /// Turns the wheel by `delta` steps, up for positive values.
+    pub fn scroll(&mut self, delta: i8) -> Result<(), Error> {
+        check(unsafe { sys::furi_hal_hid_mouse_scroll(delta) })
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use super::{Key, Layout, Modifiers, LAYOUT_FILE_SIZE};
+
+    #[test]
+    fn keys_carry_modifiers() {
+        let key = Key::C.with(Modifiers::LEFT_CTRL | Modifiers::LEFT_SHIFT);
+        assert_eq!(key.usage(), 0x06);
+        assert_eq!(
+            key.modifiers(),
+            Modifiers::LEFT_CTRL | Modifiers::LEFT_SHIFT
+        );
+        assert_eq!(key.to_sys(), 0x0306);
+        assert_eq!(Key::NONE.with(Modifiers::LEFT_GUI).to_sys(), 1 << 11);
+        assert_eq!(Key::F12.usage(), 0x45);
+    }
+
+    #[test]
+    fn us_layout_types_ascii() {
+        let layout = Layout::US;
+        let shift = Modifiers::LEFT_SHIFT;
+        assert_eq!(layout.key('a'), Some(Key::A));
+        assert_eq!(layout.key('Z'), Some(Key::Z.with(shift)));
+        assert_eq!(layout.key('0'), Some(Key::DIGIT_0));
+        assert_eq!(layout.key('9'), Some(Key::DIGIT_9));
+        assert_eq!(layout.key('?'), Some(Key::SLASH.with(shift)));
+        assert_eq!(layout.key('\n'), Some(Key::ENTER));
+        assert!(layout.key('\u{7}').is_none());
+        assert!(layout.key('é').is_none());
+        for c in ' '..='~' {
+            assert!(layout.key(c).is_some());
+        }
+    }
+
+    #[test]
+    fn layouts_are_read_from_kl_files() {
+        let mut bytes = [0; LAYOUT_FILE_SIZE];
+        // A German layout swaps Y and Z.
+        bytes[usize::from(b'y') * 2] = Key::Z.usage();
+        bytes[usize::from(b'Z') * 2] = Key::Y.usage();
+        bytes[usize::from(b'Z') * 2 + 1] = Modifiers::LEFT_SHIFT.bits();
+        let layout = Layout::from_kl_bytes(&bytes);
+        assert_eq!(layout.key('y'), Some(Key::Z));
+        assert_eq!(layout.key('Z'), Some(Key::Y.with(Modifiers::LEFT_SHIFT)));
+        assert!(layout.key('a').is_none());
+    }
+}
@@ -0,0 +1,1021 @@
+//! USB mass storage mode, which presents a disk image to the host as a USB drive.
+//!
+//! The host talks SCSI over the Bulk-Only Transport. The USB callbacks run in interrupt
+//! context, so they only signal a worker thread, which answers the commands and reads
+//! and writes the image.
+
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use core::ffi::{c_void, CStr};
+use core::ptr::{self, addr_of, addr_of_mut};
+use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicU32, Ordering};
+use core::time::Duration;
+
+use flipperzero_sys as sys;
+
+use crate::furi::event_flag::{EventFlag, Wait};
+use crate::furi::sync::Mutex;
+use crate::furi::thread::{self, JoinHandle};
+use crate::io::{Error, Read, Seek, SeekFrom, Write};
+use crate::storage::{File, OpenOptions};
+
+/// Size of the blocks the host reads and writes.
+const BLOCK_SIZE: usize = 512;
+/// Maximum size of a bulk packet.
+const PACKET_SIZE: usize = 64;
+const EP_OUT: u8 = 0x01;
+const EP_IN: u8 = 0x81;
+const WORKER_STACK_SIZE: usize = 2048;
+
+/// Data was received on the OUT endpoint.
+const FLAG_RX: u32 = 1 << 0;
+/// The IN endpoint finished sending a packet.
+const FLAG_TX: u32 = 1 << 1;
+/// The host reset the device, so the current command is abandoned.
+const FLAG_RESET: u32 = 1 << 2;
+/// The session is stopping.
+const FLAG_STOP: u32 = 1 << 3;
+
+/// `bmRequestType` of class requests to the interface, ignoring the direction.
+const CLASS_INTERFACE_REQUEST: u8 = 0x21;
+const REQUEST_GET_MAX_LUN: u8 = 0xFE;
+const REQUEST_RESET: u8 = 0xFF;
+
+const CBW_SIGNATURE: u32 = 0x4342_5355;
+const CBW_SIZE: usize = 31;
+const CSW_SIGNATURE: u32 = 0x5342_5355;
+const CSW_SIZE: usize = 13;
+const CSW_PASSED: u8 = 0;
+const CSW_FAILED: u8 = 1;
+const CSW_PHASE_ERROR: u8 = 2;
+
+const TEST_UNIT_READY: u8 = 0x00;
+const REQUEST_SENSE: u8 = 0x03;
+const INQUIRY: u8 = 0x12;
+const MODE_SENSE_6: u8 = 0x1A;
+const START_STOP_UNIT: u8 = 0x1B;
+const PREVENT_ALLOW_MEDIUM_REMOVAL: u8 = 0x1E;
+const READ_FORMAT_CAPACITIES: u8 = 0x23;
+const READ_CAPACITY_10: u8 = 0x25;
+const READ_10: u8 = 0x28;
+const WRITE_10: u8 = 0x2A;
+const VERIFY_10: u8 = 0x2F;
+const SYNCHRONIZE_CACHE_10: u8 = 0x35;
+
+/// Size of the largest reply to a command that isn't a read, which is the one to
+/// INQUIRY.
+const REPLY_SIZE: usize = 36;
+
+static DEVICE_DESCRIPTOR: sys::usb_device_descriptor = sys::usb_device_descriptor {
+    bLength: 18,
+    bDescriptorType: 0x01,
+    bcdUSB: 0x0200,
+    bDeviceClass: 0,
+    bDeviceSubClass: 0,
+    bDeviceProtocol: 0,
+    bMaxPacketSize0: 8,
+    idVendor: 0x0483,
+    idProduct: 0x5720,
+    bcdDevice: 0x0100,
+    iManufacturer: 1,
+    iProduct: 2,
+    iSerialNumber: 3,
+    bNumConfigurations: 1,
+};
+
+/// The configuration, with one mass storage interface using SCSI over the Bulk-Only
+/// Transport, and its two bulk endpoints.
+static CONFIG_DESCRIPTOR: [u8; 32] = [
+    9,
+    0x02,
+    32,
+    0,
+    1,
+    1,
+    0,
+    0xC0,
+    50, // configuration
+    9,
+    0x04,
+    0,
+    0,
+    2,
+    0x08,
+    0x06,
+    0x50,
+    0, // interface
+    7,
+    0x05,
+    EP_OUT,
+    sys::USB_EPTYPE_BULK,
+    PACKET_SIZE as u8,
+    0,
+    0, // OUT endpoint
+    7,
+    0x05,
+    EP_IN,
+    sys::USB_EPTYPE_BULK,
+    PACKET_SIZE as u8,
+    0,
+    0, // IN endpoint
+];
+
+#[repr(C)]
+struct StringDescriptor<const N: usize> {
+    length: u8,
+    kind: u8,
+    chars: [u16; N],
+}
+
+const fn string_descriptor<const N: usize>(s: &[u8; N]) -> StringDescriptor<N> {
+    let mut chars = [0; N];
+    let mut i = 0;
+    while i < N {
+        chars[i] = s[i] as u16;
+        i += 1;
+    }
+    StringDescriptor {
+        length: (2 + 2 * N) as u8,
+        kind: 0x03,
+        chars,
+    }
+}
+
+static MANUFACTURER: StringDescriptor<7> = string_descriptor(b"Flipper");
+static PRODUCT: StringDescriptor<12> = string_descriptor(b"Mass Storage");
+/// The Bulk-Only Transport requires a serial number of at least 12 hex digits.
+static SERIAL: StringDescriptor<12> = string_descriptor(b"000000000001");
+
+/// The only logical unit is number 0.
+static MAX_LUN: u8 = 0;
+
+static mut INTERFACE: sys::FuriHalUsbInterface = sys::FuriHalUsbInterface {
+    init: Some(interface_init),
+    deinit: Some(interface_deinit),
+    wakeup: Some(interface_wakeup),
+    suspend: Some(interface_suspend),
+    // The descriptors are only read by the firmware.
+    dev_descr: addr_of!(DEVICE_DESCRIPTOR) as *mut sys::usb_device_descriptor,
+    str_manuf_descr: addr_of!(MANUFACTURER) as *mut c_void,
+    str_prod_descr: addr_of!(PRODUCT) as *mut c_void,
+    str_serial_descr: addr_of!(SERIAL) as *mut c_void,
+    cfg_descr: addr_of!(CONFIG_DESCRIPTOR) as *mut c_void,
+};
+
+/// The USB device and the session, while the interface is active.
+///
+/// The endpoint and control callbacks have no context, so they are found here.
+static DEVICE: AtomicPtr<sys::usbd_device> = AtomicPtr::new(ptr::null_mut());
+static SHARED: AtomicPtr<Shared> = AtomicPtr::new(ptr::null_mut());
+
+unsafe extern "C" fn interface_init(
+    dev: *mut sys::usbd_device,
+    _interface: *mut sys::FuriHalUsbInterface,
+    context: *mut c_void,
+) {
+    SHARED.store(context.cast(), Ordering::Release);
+    DEVICE.store(dev, Ordering::Release);
+    unsafe {
+        sys::usbd_reg_config(dev, Some(config_callback));
+        sys::usbd_reg_control(dev, Some(control_callback));
+        sys::usbd_connect(dev, true);
+    }
+}
+
+unsafe extern "C" fn interface_deinit(dev: *mut sys::usbd_device) {
+    unsafe {
+        sys::usbd_reg_config(dev, None);
+        sys::usbd_reg_control(dev, None);
+    }
+    DEVICE.store(ptr::null_mut(), Ordering::Release);
+    SHARED.store(ptr::null_mut(), Ordering::Release);
+}
+
+unsafe extern "C" fn interface_wakeup(_dev: *mut sys::usbd_device) {}
+
+unsafe extern "C" fn interface_suspend(_dev: *mut sys::usbd_device) {}
+
+/// Signals the worker, if the interface is active.
+fn signal(flags: u32) {
+    let shared = SHARED.load(Ordering::Acquire);
+    if !shared.is_null() {
+        unsafe { (*shared).set(flags) };
+    }
+}
+
+unsafe extern "C" fn config_callback(dev: *mut sys::usbd_device, cfg: u8) -> sys::usbd_respond {
+    match cfg {
+        0 => unsafe {
+            sys::usbd_reg_endpoint(dev, EP_OUT, None);
+            sys::usbd_ep_deconfig(dev, EP_OUT);
+            sys::usbd_ep_deconfig(dev, EP_IN);
+        },
+        1 => unsafe {
+            sys::usbd_ep_config(dev, EP_OUT, sys::USB_EPTYPE_BULK, PACKET_SIZE as u16);
+            sys::usbd_ep_config(dev, EP_IN, sys::USB_EPTYPE_BULK, PACKET_SIZE as u16);
+            sys::usbd_reg_endpoint(dev, EP_OUT, Some(endpoint_callback));
+            // Anything the worker was doing belonged to the previous configuration.
+            signal(FLAG_RESET);
+        },
+        _ => return sys::_usbd_respond_usbd_fail,
+    }
+    sys::_usbd_respond_usbd_ack
+}
+
+unsafe extern "C" fn control_callback(
+    dev: *mut sys::usbd_device,
+    req: *mut sys::usbd_ctlreq,
+    _callback: *mut sys::usbd_rqc_callback,
+) -> sys::usbd_respond {
+    let req = unsafe { &*req };
+    if req.bmRequestType & 0x7F != CLASS_INTERFACE_REQUEST || req.wIndex != 0 {
+        // Leaves standard requests to the core.
+        return sys::_usbd_respond_usbd_fail;
+    }
+    match req.bRequest {
+        REQUEST_GET_MAX_LUN => unsafe {
+            (*dev).status.data_ptr = addr_of!(MAX_LUN) as *mut c_void;
+            (*dev).status.data_count = 1;
+        },
+        REQUEST_RESET => signal(FLAG_RESET),
+        _ => return sys::_usbd_respond_usbd_fail,
+    }
+    sys::_usbd_respond_usbd_ack
+}
+
+unsafe extern "C" fn endpoint_callback(_dev: *mut sys::usbd_device, event: u8, _ep: u8) {
+    match event {
+        sys::usbd_evt_eprx => signal(FLAG_RX),
+        sys::usbd_evt_eptx => signal(FLAG_TX),
+        _ => {}
+    }
+}
+
+/// The number of blocks the host has read from and written to the image.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Activity {
+    pub blocks_read: u32,
+    pub blocks_written: u32,
+}
+
+/// A callback that is called after the host read or wrote blocks.
+type ActivityCallback = Box<dyn FnMut(Activity) + Send>;
+
+/// State shared between the session, its worker thread and the USB callbacks.
+struct Shared {
+    events: EventFlag,
+    blocks_read: AtomicU32,
+    blocks_written: AtomicU32,
+    /// Whether the host has ejected the drive.
+    ejected: AtomicBool,
+    on_activity: Mutex<Option<ActivityCallback>>,
+    /// The result of syncing the image when the worker stopped.
+    synced: Mutex<Result<(), Error>>,
+}
+
+impl Shared {
+    fn set(&self, flags: u32) {
+        // This is also called from interrupts, where a failure couldn't be handled.
+        let _ = self.events.set(flags);
+    }
+
+    fn activity(&self) -> Activity {
+        Activity {
+            blocks_read: self.blocks_read.load(Ordering::Relaxed),
+            blocks_written: self.blocks_written.load(Ordering::Relaxed),
+        }
+    }
+
+    fn report_activity(&self) {
+        if let Some(callback) = self.on_activity.lock().as_mut() {
+            callback(self.activity());
+        }
+    }
+}
+
+/// USB mass storage mode, which presents a disk image file to the host as a USB drive.
+///
+/// This is the fastest way to move lots of data between the SD card and a computer.
+/// The image is a raw disk image, such as one created with `mkfs.vfat -C disk.img
+/// 65536`, whose size is a multiple of 512 bytes.
+pub struct MassStorage;
+
+impl MassStorage {
+    /// Opens the image at `image_path` exclusively and switches the USB device to mass
+    /// storage mode, presenting the image to the host.
+    ///
+    /// The host sees the drive until the returned session is stopped or dropped, which
+    /// switches back to the previous USB mode. Nothing else can open the image in the
+    /// meantime, but the filesystem inside it isn't locked in any way: what happens
+    /// when the host and the Flipper both access the filesystem in the image, for
+    /// example because it is also mounted on the Flipper, is undefined.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::AlreadyOpen`] if the image is already open, or the USB mode is
+    /// locked, for example by another app using USB, and
+    /// [`Error::InvalidParameter`] if the size of the image isn't a multiple of 512
+    /// bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use flipperzero::furi::thread::sleep;
+    /// # use flipperzero::io::Error;
+    /// # use flipperzero::usb::MassStorage;
+    /// # use core::time::Duration;
+    /// # fn main() -> Result<(), Error> {
+    /// let session = MassStorage::start(c"/ext/disk.img")?;
+    /// while !session.is_ejected() {
+    ///     sleep(Duration::from_millis(100));
+    /// }
+    /// session.stop()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn start(image_path: &CStr) -> Result<MassStorageSession, Error> {
+        let mut image = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open_existing(true)
+            .open(image_path)?;
+        let size = image.stream_len()?;
+        if size == 0 || size % BLOCK_SIZE != 0 {
+            return Err(Error::InvalidParameter);
+        }
+        if unsafe { sys::furi_hal_usb_is_locked() } {
+            return Err(Error::AlreadyOpen);
+        }
+
+        let shared = Arc::new(Shared {
+            events: EventFlag::new(),
+            blocks_read: AtomicU32::new(0),
+            blocks_written: AtomicU32::new(0),
+            ejected: AtomicBool::new(false),
+            on_activity: Mutex::new(None),
+            synced: Mutex::new(Ok(())),
+        });
+        let worker = Worker {
+            port: Port {
+                shared: shared.clone(),
+            },
+            image,
+            blocks: (size / BLOCK_SIZE) as u32,
+            sense: Sense::NONE,
+            buf: [0; BLOCK_SIZE],
+        };
+        let worker = thread::Builder::new()
+            .stack_size(WORKER_STACK_SIZE)
+            .spawn(move || worker.run());
+
+        let mut session = MassStorageSession {
+            shared,
+            worker: Some(worker),
+            previous_config: unsafe { sys::furi_hal_usb_get_config() },
+        };
+        let context = Arc::as_ptr(&session.shared) as *mut c_void;
+        if !unsafe { sys::furi_hal_usb_set_config(addr_of_mut!(INTERFACE), context) } {
+            session.shared.set(FLAG_STOP);
+            if let Some(worker) = session.worker.take() {
+                worker.join();
+            }
+            return Err(Error::AlreadyOpen);
+        }
+        Ok(session)
+    }
+}
+
+/// A running [`MassStorage`] session.
+///
+/// Dropping the session stops it like [`MassStorageSession::stop`], ignoring errors.
+pub struct MassStorageSession {
+    shared: Arc<Shared>,
+    /// `None` once the session has been stopped.
+    worker: Option<JoinHandle>,
+    previous_config: *mut sys::FuriHalUsbInterface,
+}
+
+impl MassStorageSession {
+    /// Returns the number of blocks the host has read and written so far.
+    pub fn activity(&self) -> Activity {
+        self.shared.activity()
+    }
+
+    /// Sets a callback that is called with the [`activity`] after every read and write
+    /// of the host, replacing any previous callback.
+    ///
+    /// The callback is called from the worker thread, and delays the host until it
+    /// returns, so it should only signal the app, for example through a message queue.
+    ///
+    /// [`activity`]: MassStorageSession::activity
+    pub fn set_activity_callback<F>(&mut self, callback: F)
+    where
+        F: FnMut(Activity) + Send + 'static,
+    {
+        *self.shared.on_activity.lock() = Some(Box::new(callback));
+    }
+
+    /// Returns `true` if the host has ejected the drive, after which the session can be
+    /// stopped safely.
+    pub fn is_ejected(&self) -> bool {
+        self.shared.ejected.load(Ordering::Relaxed)
+    }
+
+    /// Switches back to the previous USB mode, and syncs and closes the image.
+    ///
+    /// Writes the host hasn't finished are lost, so the drive should be ejected first.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of syncing the image.
+    pub fn stop(mut self) -> Result<(), Error> {
+        self.finish()
+    }
+
+    fn finish(&mut self) -> Result<(), Error> {
+        let Some(worker) = self.worker.take() else {
+            return Ok(());
+        };
+        let config = if self.previous_config.is_null() {
+            addr_of_mut!(sys::usb_cdc_single)
+        } else {
+            self.previous_config
+        };
+        unsafe { sys::furi_hal_usb_set_config(config, ptr::null_mut()) };
+
+        self.shared.set(FLAG_STOP);
+        worker.join();
+        *self.shared.synced.lock()
+    }
+}
+
+impl Drop for MassStorageSession {
+    fn drop(&mut self) {
+        let _ = self.finish();
+    }
+}
+
+/// Why the worker abandoned a command.
+enum Abort {
+    Reset,
+    Stop,
+}
+
+/// The bulk endpoints, as used by the worker.
+struct Port {
+    shared: Arc<Shared>,
+}
+
+impl Port {
+    /// Waits for any of `flags`, or a reset or stop.
+    fn wait(&self, flags: u32) -> Result<(), Abort> {
+        loop {
+            let Ok(set) =
+                self.shared
+                    .events
+                    .wait(flags | FLAG_RESET | FLAG_STOP, Wait::Any, Duration::MAX)
+            else {
+                continue;
+            };
+            if set & FLAG_STOP != 0 {
+                return Err(Abort::Stop);
+            } else if set & FLAG_RESET != 0 {
+                return Err(Abort::Reset);
+            }
+            return Ok(());
+        }
+    }
+
+    /// Receives a packet into `buf`, which must be large enough for it, returning its
+    /// size.
+    fn receive(&self, buf: &mut [u8]) -> Result<usize, Abort> {
+        loop {
+            let dev = DEVICE.load(Ordering::Acquire);
+            if !dev.is_null() {
+                let len = unsafe {
+                    sys::usbd_ep_read(dev, EP_OUT, buf.as_mut_ptr().cast(), buf.len() as u16)
+                };
+                if len > 0 {
+                    return Ok(len as usize);
+                }
+            }
+            self.wait(FLAG_RX)?;
+        }
+    }
+
+    /// Sends a packet of up to [`PACKET_SIZE`] bytes, waiting until it was sent.
+    fn send(&self, packet: &[u8]) -> Result<(), Abort> {
+        let _ = self.shared.events.clear(FLAG_TX);
+        let dev = DEVICE.load(Ordering::Acquire);
+        if dev.is_null() {
+            // Only a reset or stop can follow.
+            self.wait(0)?;
+            return Err(Abort::Reset);
+        }
+        unsafe { sys::usbd_ep_write(dev, EP_IN, packet.as_ptr().cast(), packet.len() as u16) };
+        self.wait(FLAG_TX)
+    }
+
+    /// Sends `data`, ending the transfer with a short packet if the host expects more.
+    fn send_data(&self, data: &[u8], expected: u32) -> Result<(), Abort> {
+        for packet in data.chunks(PACKET_SIZE) {
+            self.send(packet)?;
+        }
+        if (data.len() as u32) < expected && data.len() % PACKET_SIZE == 0 {
+            self.send(&[])?;
+        }
+        Ok(())
+    }
+
+    /// Sends `len` zeros to the host, or receives and drops `len` bytes from it.
+    fn skip(&self, len: u32, to_host: bool) -> Result<(), Abort> {
+        let mut packet = [0; PACKET_SIZE];
+        let mut remaining = len as usize;
+        while remaining > 0 {
+            let n = if to_host {
+                let n = remaining.min(PACKET_SIZE);
+                self.send(&packet[..n])?;
+                n
+            } else {
+                self.receive(&mut packet)?
+            };
+            remaining = remaining.saturating_sub(n);
+        }
+        Ok(())
+    }
+}
+
+/// The worker thread, which answers the commands of the host.
+struct Worker {
+    port: Port,
+    image: File,
+    blocks: u32,
+    /// Why the last command failed.
+    sense: Sense,
+    buf: [u8; BLOCK_SIZE],
+}
+
+// SAFETY: the image is only used by the worker thread, once it has been moved there.
+unsafe impl Send for Worker {}
+
+impl Worker {
+    fn run(mut self) -> i32 {
+        loop {
+            if let Err(Abort::Stop) = self.serve() {
+                break;
+            }
+        }
+        *self.port.shared.synced.lock() = self.image.sync();
+        0
+    }
+
+    /// Answers the next command.
+    fn serve(&mut self) -> Result<(), Abort> {
+        let mut packet = [0; PACKET_SIZE];
+        let len = self.port.receive(&mut packet)?;
+        // The host recovers from invalid commands with a reset.
+        let Some(cbw) = Cbw::parse(&packet[..len]) else {
+            return Ok(());
+        };
+
+        let (status, residue) = self.execute(&cbw)?;
+        if status == CSW_PASSED {
+            self.sense = Sense::NONE;
+        }
+        self.port.send(&csw(cbw.tag, residue, status))
+    }
+
+    /// Runs the command of `cbw`, transferring its data, and returns the status and
+    /// residue to report.
+    fn execute(&mut self, cbw: &Cbw) -> Result<(u8, u32), Abort> {
+        let mut reply = [0; REPLY_SIZE];
+        let ejected = self.port.shared.ejected.load(Ordering::Relaxed);
+        let action = decode_command(&cbw.command, self.blocks, ejected, self.sense, &mut reply);
+
+        let action = match action {
+            Ok(action) => action,
+            Err(sense) => {
+                self.sense = sense;
+                self.port.skip(cbw.length, cbw.to_host)?;
+                return Ok((CSW_FAILED, cbw.length));
+            }
+        };
+        match action {
+            Action::Done => {}
+            Action::Eject => self.port.shared.ejected.store(true, Ordering::Relaxed),
+            Action::Sync => {
+                if self.image.sync().is_err() {
+                    self.sense = Sense::WRITE_ERROR;
+                    self.port.skip(cbw.length, cbw.to_host)?;
+                    return Ok((CSW_FAILED, cbw.length));
+                }
+            }
+            Action::Reply(len) => {
+                if !cbw.to_host {
+                    self.port.skip(cbw.length, false)?;
+                    return Ok((CSW_PHASE_ERROR, cbw.length));
+                }
+                let len = len.min(cbw.length as usize);
+                self.port.send_data(&reply[..len], cbw.length)?;
+                return Ok((CSW_PASSED, cbw.length - len as u32));
+            }
+            Action::Read { lba, count } => return self.read(cbw, lba, count),
+            Action::Write { lba, count } => return self.write(cbw, lba, count),
+        }
+        self.port.skip(cbw.length, cbw.to_host)?;
+        Ok((CSW_PASSED, cbw.length))
+    }
+
+    fn read(&mut self, cbw: &Cbw, lba: u32, count: u32) -> Result<(u8, u32), Abort> {
+        if !cbw.to_host || cbw.length != count * BLOCK_SIZE as u32 {
+            self.port.skip(cbw.length, cbw.to_host)?;
+            return Ok((CSW_PHASE_ERROR, cbw.length));
+        }
+
+        let mut result = self.seek(lba);
+        for block in 0..count {
+            if result.is_ok() {
+                result = read_block(&mut self.image, &mut self.buf);
+            }
+            if result.is_err() {
+                self.sense = Sense::READ_ERROR;
+                let remaining = (count - block) * BLOCK_SIZE as u32;
+                self.port.skip(remaining, true)?;
+                return Ok((CSW_FAILED, remaining));
+            }
+            self.port.send_data(&self.buf, BLOCK_SIZE as u32)?;
+            self.port.shared.blocks_read.fetch_add(1, Ordering::Relaxed);
+        }
+        self.port.shared.report_activity();
+        Ok((CSW_PASSED, 0))
+    }
+
+    fn write(&mut self, cbw: &Cbw, lba: u32, count: u32) -> Result<(u8, u32), Abort> {
+        if cbw.to_host || cbw.length != count * BLOCK_SIZE as u32 {
+            self.port.skip(cbw.length, cbw.to_host)?;
+            return Ok((CSW_PHASE_ERROR, cbw.length));
+        }
+
+        let mut result = self.seek(lba);
+        for block in 0..count {
+            let mut len = 0;
+            while len < BLOCK_SIZE {
+                len += self
+                    .port
+                    .receive(&mut self.buf[len..(len + PACKET_SIZE).min(BLOCK_SIZE)])?;
+            }
+            if result.is_ok() {
+                result = self.image.write_all(&self.buf);
+            }
+            if result.is_err() {
+                self.sense = Sense::WRITE_ERROR;
+                let remaining = (count - block - 1) * BLOCK_SIZE as u32;
+                self.port.skip(remaining, false)?;
+                return Ok((CSW_FAILED, remaining));
+            }
+            self.port
+                .shared
+                .blocks_written
+                .fetch_add(1, Ordering::Relaxed);
+        }
+        self.port.shared.report_activity();
+        Ok((CSW_PASSED, 0))
+    }
+
+    fn seek(&mut self, lba: u32) -> Result<(), Error> {
+        let offset = u64::from(lba) * BLOCK_SIZE as u64;
+        self.image.seek(SeekFrom::Start(offset)).map(|_| ())
+    }
+}
+
+/// Reads a whole block from `image`.
+fn read_block(image: &mut File, buf: &mut [u8; BLOCK_SIZE]) -> Result<(), Error> {
+    let mut len = 0;
+    while len < BLOCK_SIZE {
+        match image.read(&mut buf[len..])? {
+            // The image has shrunk.
+            0 => return Err(Error::Internal),
+            n => len += n,
+        }
+    }
+    Ok(())
+}
+
+/// A Command Block Wrapper, which carries a command from the host.
+#[derive(Debug, PartialEq, Eq)]
+struct Cbw {
+    tag: u32,
+    /// Number of bytes the host expects to transfer.
+    length: u32,
+    /// Whether the data is sent to the host.
+    to_host: bool,
+    command: [u8; 16],
+}
+
+impl Cbw {
+    /// Parses a CBW for the only logical unit.
+    fn parse(packet: &[u8]) -> Option<Self> {
+        let le = |offset: usize| u32::from_le_bytes(packet[offset..offset + 4].try_into().unwrap());
+        if packet.len() != CBW_SIZE
+            || le(0) != CBW_SIGNATURE
+            || packet[13] != 0
+            || !(1..=16).contains(&packet[14])
+        {
+            return None;
+        }
+        Some(Self {
+            tag: le(4),
+            length: le(8),
+            to_host: packet[12] & 0x80 != 0,
+            command: packet[15..].try_into().unwrap(),
+        })
+    }
+}
+
+/// Encodes a Command Status Wrapper, which reports the outcome of a command.
+fn csw(tag: u32, residue: u32, status: u8) -> [u8; CSW_SIZE] {
+    let mut csw = [0; CSW_SIZE];
+    csw[0..4].copy_from_slice(&CSW_SIGNATURE.to_le_bytes());
+    csw[4..8].copy_from_slice(&tag.to_le_bytes());
+    csw[8..12].copy_from_slice(&residue.to_le_bytes());
+    csw[12] = status;
+    csw
+}
+
+/// Why a command failed, as reported to REQUEST SENSE.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Sense {
+    key: u8,
+    /// Additional sense code.
+    asc: u8,
+}
+
+impl Sense {
+    const NONE: Self = Self {
+        key: 0x00,
+        asc: 0x00,
+    };
+    const NO_MEDIUM: Self = Self {
+        key: 0x02,
+        asc: 0x3A,
+    };
+    const READ_ERROR: Self = Self {
+        key: 0x03,
+        asc: 0x11,
+    };
+    const WRITE_ERROR: Self = Self {
+        key: 0x03,
+        asc: 0x0C,
+    };
+    const INVALID_COMMAND: Self = Self {
+        key: 0x05,
+        asc: 0x20,
+    };
+    const LBA_OUT_OF_RANGE: Self = Self {
+        key: 0x05,
+        asc: 0x21,
+    };
+    const INVALID_FIELD: Self = Self {
+        key: 0x05,
+        asc: 0x24,
+    };
+}
+
+/// What the worker does to complete a command.
+#[derive(Debug, PartialEq, Eq)]
+enum Action {
+    Done,
+    /// Sends the first bytes of the reply.
+    Reply(usize),
+    Read {
+        lba: u32,
+        count: u32,
+    },
+    Write {
+        lba: u32,
+        count: u32,
+    },
+    Eject,
+    Sync,
+}
+
+/// Decodes a SCSI command for an image of `blocks` blocks, writing the reply of
+/// commands that return data into `reply`.
+fn decode_command(
+    command: &[u8; 16],
+    blocks: u32,
+    ejected: bool,
+    sense: Sense,
+    reply: &mut [u8; REPLY_SIZE],
+) -> Result<Action, Sense> {
+    let be16 =
+        |offset: usize| usize::from(u16::from_be_bytes([command[offset], command[offset + 1]]));
+    let present = || {
+        if ejected {
+            Err(Sense::NO_MEDIUM)
+        } else {
+            Ok(())
+        }
+    };
+
+    match command[0] {
+        TEST_UNIT_READY => present().map(|_| Action::Done),
+        REQUEST_SENSE => {
+            reply[..18].fill(0);
+            reply[0] = 0x70;
+            reply[2] = sense.key;
+            reply[7] = 10;
+            reply[12] = sense.asc;
+            Ok(Action::Reply(usize::from(command[4]).min(18)))
+        }
+        INQUIRY => {
+            if command[1] & 0x01 != 0 {
+                // Vital product data isn't supported.
+                return Err(Sense::INVALID_FIELD);
+            }
+            reply[..8].copy_from_slice(&[0x00, 0x80, 0x04, 0x02, 31, 0, 0, 0]);
+            reply[8..16].copy_from_slice(b"Flipper ");
+            reply[16..32].copy_from_slice(b"Mass Storage    ");
+            reply[32..36].copy_from_slice(b"1.0 ");
+            Ok(Action::Reply(be16(3).min(36)))
+        }
+        MODE_SENSE_6 => {
+            reply[..4].copy_from_slice(&[3, 0, 0, 0]);
+            Ok(Action::Reply(usize::from(command[4]).min(4)))
+        }
+        START_STOP_UNIT => {
+            let start = command[4] & 0x01 != 0;
+            let load_eject = command[4] & 0x02 != 0;
+            Ok(if load_eject && !start {
+                Action::Eject
+            } else {
+                Action::Done
+            })
+        }
+        PREVENT_ALLOW_MEDIUM_REMOVAL => Ok(Action::Done),
+        READ_FORMAT_CAPACITIES => {
+            present()?;
+            reply[..4].copy_from_slice(&[0, 0, 0, 8]);
+            reply[4..8].copy_from_slice(&blocks.to_be_bytes());
+            // Formatted media, and the block length in 3 bytes.
+            reply[8..12].copy_from_slice(&[0x02, 0x00, 0x02, 0x00]);
+            Ok(Action::Reply(be16(7).min(12)))
+        }
+        READ_CAPACITY_10 => {
+            present()?;
+            reply[..4].copy_from_slice(&(blocks - 1).to_be_bytes());
+            reply[4..8].copy_from_slice(&(BLOCK_SIZE as u32).to_be_bytes());
+            Ok(Action::Reply(8))
+        }
+        READ_10 | WRITE_10 => {
+            present()?;
+            let lba = u32::from_be_bytes(command[2..6].try_into().unwrap());
+            let count = be16(7) as u32;
+            if u64::from(lba) + u64::from(count) > u64::from(blocks) {
+                return Err(Sense::LBA_OUT_OF_RANGE);
+            }
+            Ok(if command[0] == READ_10 {
+                Action::Read { lba, count }
+            } else {
+                Action::Write { lba, count }
+            })
+        }
+        VERIFY_10 => present().map(|_| Action::Done),
+        SYNCHRONIZE_CACHE_10 => present().map(|_| Action::Sync),
+        _ => Err(Sense::INVALID_COMMAND),
+    }
+}
+
+#[flipperzero_test::tests]
+mod tests {
+    use super::{csw, decode_command, Action, Cbw, Sense, REPLY_SIZE};
+
+    /// A CBW as sent by Linux for READ(10) of 8 blocks at block 32.
+    const READ_CBW: [u8; 31] = [
+        0x55, 0x53, 0x42, 0x43, 0x2A, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x80, 0x00, 0x0A,
+        0x28, 0x00, 0x00, 0x00, 0x00, 0x20, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00,
+    ];
+
+    fn command(bytes: &[u8]) -> [u8; 16] {
+        let mut command = [0; 16];
+        command[..bytes.len()].copy_from_slice(bytes);
+        command
+    }
+
+    fn decode(bytes: &[u8], ejected: bool) -> (Result<Action, Sense>, [u8; REPLY_SIZE]) {
+        let mut reply = [0; REPLY_SIZE];
+        let action = decode_command(&command(bytes), 2048, ejected, Sense::NONE, &mut reply);
+        (action, reply)
+    }
+
+    #[test]
+    fn parses_cbw() {
+        let cbw = Cbw::parse(&READ_CBW).unwrap();
+        assert_eq!(cbw.tag, 0x2A);
+        assert_eq!(cbw.length, 4096);
+        assert!(cbw.to_host);
+        assert_eq!(
+            cbw.command,
+            command(&[0x28, 0, 0, 0, 0, 0x20, 0, 0, 0x08, 0])
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_cbw() {
+        assert!(Cbw::parse(&READ_CBW[..30]).is_none());
+
+        let mut cbw = READ_CBW;
+        cbw[0] = 0;
+        assert!(Cbw::parse(&cbw).is_none());
+
+        // The second logical unit doesn't exist.
+        let mut cbw = READ_CBW;
+        cbw[13] = 1;
+        assert!(Cbw::parse(&cbw).is_none());
+    }
+
+    #[test]
+    fn encodes_csw() {
+        assert_eq!(
+            csw(0x2A, 0x200, 1),
+            [0x55, 0x53, 0x42, 0x53, 0x2A, 0, 0, 0, 0x00, 0x02, 0, 0, 0x01]
+        );
+    }
+
+    #[test]
+    fn read_and_write() {
+        let (action, _) = decode(&[0x28, 0, 0, 0, 0, 0x20, 0, 0, 0x08, 0], false);
+        assert_eq!(action, Ok(Action::Read { lba: 32, count: 8 }));
+
+        let (action, _) = decode(&[0x2A, 0, 0, 0, 0x07, 0xFF, 0, 0, 0x01, 0], false);
+        assert_eq!(
+            action,
+            Ok(Action::Write {
+                lba: 2047,
+                count: 1
+            })
+        );
+
+        let (action, _) = decode(&[0x2A, 0, 0, 0, 0x07, 0xFF, 0, 0, 0x02, 0], false);
+        assert_eq!(action, Err(Sense::LBA_OUT_OF_RANGE));
+    }
+
+    #[test]
+    fn read_capacity() {
+        let (action, reply) = decode(&[0x25], false);
+        assert_eq!(action, Ok(Action::Reply(8)));
+        assert_eq!(reply[..8], [0x00, 0x00, 0x07, 0xFF, 0x00, 0x00, 0x02, 0x00]);
+    }
+
+    #[test]
+    fn inquiry() {
+        let (action, reply) = decode(&[0x12, 0, 0, 0, 0xFF, 0], false);
+        assert_eq!(action, Ok(Action::Reply(36)));
+        assert_eq!(reply[..2], [0x00, 0x80]);
+        assert_eq!(reply[8..16], *b"Flipper ");
+
+        // Short allocation lengths truncate the reply.
+        let (action, _) = decode(&[0x12, 0, 0, 0, 0x05, 0], false);
+        assert_eq!(action, Ok(Action::Reply(5)));
+
+        let (action, _) = decode(&[0x12, 0x01, 0x80, 0, 0xFF, 0], false);
+        assert_eq!(action, Err(Sense::INVALID_FIELD));
+    }
+
+    #[test]
+    fn request_sense() {
+        let mut reply = [0; REPLY_SIZE];
+        let action = decode_command(
+            &command(&[0x03, 0, 0, 0, 18, 0]),
+            2048,
+            false,
+            Sense::NO_MEDIUM,
+            &mut reply,
+        );
+        assert_eq!(action, Ok(Action::Reply(18)));
+        assert_eq!(reply[0], 0x70);
+        assert_eq!(reply[2], 0x02);
+        assert_eq!(reply[12], 0x3A);
+    }
+
+    #[test]
+    fn eject() {
+        let (action, _) = decode(&[0x1B, 0, 0, 0, 0x02, 0], false);
+        assert_eq!(action, Ok(Action::Eject));
+        let (action, _) = decode(&[0x1B, 0, 0, 0, 0x01, 0], false);
+        assert_eq!(action, Ok(Action::Done));
+
+        // Once ejected, the medium is gone.
+        let (action, _) = decode(&[0x00], true);
+        assert_eq!(action, Err(Sense::NO_MEDIUM));
+        let (action, _) = decode(&[0x28, 0, 0, 0, 0, 0, 0, 0, 0x01, 0], true);
+        assert_eq!(action, Err(Sense::NO_MEDIUM));
+    }
+
+    #[test]
+    fn unknown_command() {
+        let (action, _) = decode(&[0xA0], false);
+        assert_eq!(action, Err(Sense::INVALID_COMMAND));
+    }
+}
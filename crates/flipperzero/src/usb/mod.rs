@@ -0,0 +1,363 @@
+//! USB device modes of the Flipper Zero.
+//!
+//! [`CdcPort`] provides a virtual serial port, [`MassStorage`] presents a disk image to
+//! the computer as a USB drive, and [`hid::Hid`] acts as a keyboard and mouse.
+
+use alloc::boxed::Box;
+use core::ffi::c_void;
+use core::ptr::{self, addr_of_mut, NonNull};
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
+
+use flipperzero_sys as sys;
+use flipperzero_sys::furi::duration_to_ticks;
+
+use crate::furi::record::Record;
+use crate::io::{Error, Read, ReadTimeout, Write};
+
+pub mod hid;
+pub(crate) mod mass_storage;
+
+pub use self::mass_storage::{Activity, MassStorage, MassStorageSession};
+
+/// Maximum size of a CDC packet.
+const PACKET_SIZE: usize = 64;
+/// Number of received bytes that are buffered until they are read.
+const RX_BUFFER_SIZE: usize = 1024;
+/// How often blocked reads and writes check whether the host is still connected.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// `CdcStateConnected` in the firmware.
+const CDC_STATE_CONNECTED: u8 = 1;
+/// `CdcCtrlLineDTR` in the firmware, which the host sets while the port is open.
+const CDC_CTRL_LINE_DTR: u8 = 1 << 0;
+
+/// A virtual serial port of the USB CDC device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CdcInterface {
+    /// The first port, which is normally used by the CLI.
+    ///
+    /// The CLI is disconnected while the port is open.
+    Primary,
+    /// The second port, which is only available while the device exposes two ports.
+    Secondary,
+}
+
+impl CdcInterface {
+    fn index(self) -> u8 {
+        match self {
+            Self::Primary => 0,
+            Self::Secondary => 1,
+        }
+    }
+}
+
+/// State shared with the CDC callbacks, which are called from the USB driver.
+struct Shared {
+    interface: u8,
+    rx: NonNull<sys::FuriStreamBuffer>,
+    /// Whether a received packet is waiting for room in `rx`.
+    ///
+    /// The host can't send further packets until it has been read.
+    rx_pending: AtomicBool,
+    /// Available while no packet is being transmitted.
+    tx_idle: NonNull<sys::FuriSemaphore>,
+    /// Whether the host has opened the port.
+    connected: AtomicBool,
+}
+
+impl Shared {
+    /// Moves a received packet into `rx` if there is room for it, and otherwise marks it
+    /// as pending.
+    fn receive(&self) {
+        if unsafe { sys::furi_stream_buffer_spaces_available(self.rx.as_ptr()) } < PACKET_SIZE {
+            self.rx_pending.store(true, Ordering::Release);
+            return;
+        }
+
+        let mut packet = [0u8; PACKET_SIZE];
+        let len = unsafe {
+            sys::furi_hal_cdc_receive(self.interface, packet.as_mut_ptr(), PACKET_SIZE as u16)
+        };
+        if len > 0 {
+            unsafe {
+                sys::furi_stream_buffer_send(
+                    self.rx.as_ptr(),
+                    packet.as_ptr() as *const c_void,
+                    len as usize,
+                    0,
+                )
+            };
+        }
+    }
+
+    fn set_connected(&self, connected: bool) {
+        self.connected.store(connected, Ordering::Release);
+        if connected {
+            // A packet that was in flight when the host went away is never completed.
+            // Releasing an available semaphore fails, which is fine.
+            unsafe { sys::furi_semaphore_release(self.tx_idle.as_ptr()) };
+        }
+    }
+
+    fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Acquire)
+    }
+}
+
+unsafe extern "C" fn tx_ep_callback(context: *mut c_void) {
+    let shared = unsafe { &*(context as *const Shared) };
+    unsafe { sys::furi_semaphore_release(shared.tx_idle.as_ptr()) };
+}
+
+unsafe extern "C" fn rx_ep_callback(context: *mut c_void) {
+    let shared = unsafe { &*(context as *const Shared) };
+    shared.receive();
+}
+
+unsafe extern "C" fn state_callback(context: *mut c_void, state: u8) {
+    let shared = unsafe { &*(context as *const Shared) };
+    if state != CDC_STATE_CONNECTED {
+        shared.set_connected(false);
+    }
+}
+
+unsafe extern "C" fn ctrl_line_callback(context: *mut c_void, state: u8) {
+    let shared = unsafe { &*(context as *const Shared) };
+    shared.set_connected(state & CDC_CTRL_LINE_DTR != 0);
+}
+
+static CALLBACKS: sys::CdcCallbacks = sys::CdcCallbacks {
+    tx_ep_callback: Some(tx_ep_callback),
+    rx_ep_callback: Some(rx_ep_callback),
+    state_callback: Some(state_callback),
+    ctrl_line_callback: Some(ctrl_line_callback),
+    config_callback: None,
+};
+
+/// A virtual serial port of the USB CDC device, which the host sees as a COM port.
+///
+/// Opening the port switches the USB device to the CDC mode providing it, and dropping
+/// it switches back to the previous mode.
+///
+/// Reads and writes fail with [`Error::NotReady`] when the host closes the port or the
+/// cable is unplugged, instead of waiting for it to come back.
+pub struct CdcPort {
+    shared: Box<Shared>,
+    previous_config: *mut sys::FuriHalUsbInterface,
+    /// The CLI, while its session is closed to free the primary port.
+    cli: Option<Record<sys::Cli>>,
+    read_timeout: Duration,
+    write_timeout: Duration,
+}
+
+impl CdcPort {
+    /// Opens the given virtual serial port.
+    ///
+    /// Reads don't wait for data by default, and writes wait up to one second for the
+    /// host to read the previous packet; see [`CdcPort::set_read_timeout`] and
+    /// [`CdcPort::set_write_timeout`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::AlreadyOpen`] if the USB mode is locked, for example by another
+    /// app using USB.
+    pub fn open(interface: CdcInterface) -> Result<Self, Error> {
+        let previous_config = unsafe { sys::furi_hal_usb_get_config() };
+
+        let (config, cli) = match interface {
+            CdcInterface::Primary => {
+                let cli = Record::<sys::Cli>::open();
+                unsafe { sys::cli_session_close(cli.as_ptr()) };
+                (addr_of_mut!(sys::usb_cdc_single), Some(cli))
+            }
+            CdcInterface::Secondary => (addr_of_mut!(sys::usb_cdc_dual), None),
+        };
+        if !unsafe { sys::furi_hal_usb_set_config(config, ptr::null_mut()) } {
+            if let Some(cli) = &cli {
+                unsafe { sys::cli_session_open(cli.as_ptr(), addr_of_mut!(sys::cli_vcp).cast()) };
+            }
+            return Err(Error::AlreadyOpen);
+        }
+
+        let index = interface.index();
+        let shared = Box::new(Shared {
+            interface: index,
+            rx: unsafe { NonNull::new_unchecked(sys::furi_stream_buffer_alloc(RX_BUFFER_SIZE, 1)) },
+            rx_pending: AtomicBool::new(false),
+            tx_idle: unsafe { NonNull::new_unchecked(sys::furi_semaphore_alloc(1, 1)) },
+            connected: AtomicBool::new(false),
+        });
+        shared.set_connected(
+            unsafe { sys::furi_hal_cdc_get_ctrl_line_state(index) } & CDC_CTRL_LINE_DTR != 0,
+        );
+        unsafe {
+            // The callbacks are only read by the driver.
+            sys::furi_hal_cdc_set_callbacks(
+                index,
+                &CALLBACKS as *const sys::CdcCallbacks as *mut sys::CdcCallbacks,
+                &*shared as *const Shared as *mut c_void,
+            )
+        };
+
+        Ok(Self {
+            shared,
+            previous_config,
+            cli,
+            read_timeout: Duration::ZERO,
+            write_timeout: Duration::from_secs(1),
+        })
+    }
+
+    /// Returns `true` if the host has opened the port.
+    pub fn is_connected(&self) -> bool {
+        self.shared.is_connected()
+    }
+
+    /// Sets how long reads wait for data to arrive before returning 0.
+    ///
+    /// A timeout of zero makes reads return immediately with the bytes received so far,
+    /// and [`Duration::MAX`] makes them wait until at least one byte arrives or the host
+    /// disconnects.
+    pub fn set_read_timeout(&mut self, timeout: Duration) {
+        self.read_timeout = timeout;
+    }
+
+    /// Sets how long writes wait for the host to read the previous packet.
+    pub fn set_write_timeout(&mut self, timeout: Duration) {
+        self.write_timeout = timeout;
+    }
+
+    /// Waits up to `timeout` for the transmission of the previous packet to complete,
+    /// taking the transmitter.
+    fn acquire_tx(&self, timeout: Duration) -> Result<(), Error> {
+        let mut remaining = timeout;
+        loop {
+            if !self.shared.is_connected() {
+                return Err(Error::NotReady);
+            }
+            let slice = remaining.min(POLL_INTERVAL);
+            let status = unsafe {
+                sys::furi_semaphore_acquire(self.shared.tx_idle.as_ptr(), duration_to_ticks(slice))
+            };
+            if status == sys::FuriStatus_FuriStatusOk {
+                return Ok(());
+            }
+            if remaining <= slice {
+                // The host isn't reading.
+                return Err(Error::NotReady);
+            }
+            remaining -= slice;
+        }
+    }
+
+    /// Waits up to `timeout` for bytes to arrive, returning 0 if none did.
+    fn receive(&self, buf: &mut [u8], timeout: Duration) -> Result<usize, Error> {
+        let mut remaining = timeout;
+        loop {
+            let slice = remaining.min(POLL_INTERVAL);
+            let n = unsafe {
+                sys::furi_stream_buffer_receive(
+                    self.shared.rx.as_ptr(),
+                    buf.as_mut_ptr() as *mut c_void,
+                    buf.len(),
+                    duration_to_ticks(slice),
+                )
+            };
+            // The host can send again once the pending packet has been moved into the
+            // buffer, which only happens here while it is pending.
+            if self.shared.rx_pending.swap(false, Ordering::AcqRel) {
+                self.shared.receive();
+            }
+
+            if n > 0 {
+                return Ok(n);
+            } else if !self.shared.is_connected() {
+                return Err(Error::NotReady);
+            } else if remaining <= slice {
+                return Ok(0);
+            }
+            remaining -= slice;
+        }
+    }
+}
+
+impl Read for CdcPort {
+    /// Reads the bytes received so far, waiting up to the [read timeout] for at least
+    /// one byte to arrive.
+    ///
+    /// Returns 0 if no byte arrived in time.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotReady`] if no bytes are left and the host isn't connected.
+    ///
+    /// [read timeout]: CdcPort::set_read_timeout
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        self.receive(buf, self.read_timeout)
+    }
+}
+
+impl ReadTimeout for CdcPort {
+    fn read_with_timeout(&mut self, buf: &mut [u8], timeout: Duration) -> Result<usize, Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        match self.receive(buf, timeout)? {
+            0 => Err(Error::TimedOut),
+            n => Ok(n),
+        }
+    }
+}
+
+impl Write for CdcPort {
+    /// Sends a packet of up to 64 bytes from `buf`, waiting up to the [write timeout]
+    /// for the host to read the previous one.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotReady`] if the host isn't connected, or didn't read the
+    /// previous packet in time.
+    ///
+    /// [write timeout]: CdcPort::set_write_timeout
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        self.acquire_tx(self.write_timeout)?;
+
+        let len = buf.len().min(PACKET_SIZE);
+        // The buffer is only read by the driver.
+        unsafe {
+            sys::furi_hal_cdc_send(self.shared.interface, buf.as_ptr() as *mut u8, len as u16)
+        };
+        Ok(len)
+    }
+
+    /// Waits up to the write timeout for the host to read the last packet.
+    fn flush(&mut self) -> Result<(), Error> {
+        self.acquire_tx(self.write_timeout)?;
+        unsafe { sys::furi_semaphore_release(self.shared.tx_idle.as_ptr()) };
+        Ok(())
+    }
+}
+
+impl Drop for CdcPort {
+    fn drop(&mut self) {
+        unsafe {
+            sys::furi_hal_cdc_set_callbacks(
+                self.shared.interface,
+                ptr::null_mut(),
+                ptr::null_mut(),
+            );
+            if !self.previous_config.is_null() {
+                sys::furi_hal_usb_set_config(self.previous_config, ptr::null_mut());
+            }
+            if let Some(cli) = &self.cli {
+                sys::cli_session_open(cli.as_ptr(), addr_of_mut!(sys::cli_vcp).cast());
+            }
+            sys::furi_semaphore_free(self.shared.tx_idle.as_ptr());
+            sys::furi_stream_buffer_free(self.shared.rx.as_ptr());
+        }
+    }
+}
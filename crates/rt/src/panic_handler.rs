@@ -1,5 +1,8 @@
 //! Panic handler for Furi applications.
 //! This will print the panic info to stdout and then trigger a crash.
+//!
+//! With the `panic-screen` feature, the panic message is also logged to the Furi log
+//! and shown on the screen, with its location, until the user presses Back.
 
 use core::ffi::c_char;
 use core::panic::PanicInfo;
@@ -40,6 +43,145 @@ pub fn panic(panic_info: &PanicInfo<'_>) -> ! {
         sys::furi_thread_stdout_flush();
         sys::furi_thread_yield(); // Allow console to flush
 
+        #[cfg(feature = "panic-screen")]
+        screen::show(panic_info);
+
         sys::crash!("Rust panic")
     }
 }
+
+#[cfg(feature = "panic-screen")]
+mod screen {
+    use core::ffi::{c_char, c_void, CStr};
+    use core::fmt::{self, Write};
+    use core::panic::PanicInfo;
+    use core::ptr::addr_of_mut;
+    use core::sync::atomic::{AtomicBool, Ordering};
+
+    use flipperzero_sys as sys;
+
+    /// Size of the buffer the panic message is formatted into, including the nul.
+    const TEXT_SIZE: usize = 256;
+
+    /// Whether a panic is being shown, so that panics of other threads don't replace it.
+    static SHOWING: AtomicBool = AtomicBool::new(false);
+
+    /// Whether the user pressed Back.
+    static DISMISSED: AtomicBool = AtomicBool::new(false);
+
+    /// The message and location, written once before the screen is shown.
+    static mut TEXT: Text = Text {
+        buf: [0; TEXT_SIZE],
+        len: 0,
+    };
+
+    /// A nul-terminated string that drops what doesn't fit.
+    struct Text {
+        buf: [u8; TEXT_SIZE],
+        len: usize,
+    }
+
+    impl Text {
+        fn as_ptr(&self) -> *const c_char {
+            self.buf.as_ptr().cast()
+        }
+    }
+
+    impl Write for Text {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            // Keep room for the nul, and never split a character.
+            let room = TEXT_SIZE - 1 - self.len;
+            let mut len = s.len().min(room);
+            while !s.is_char_boundary(len) {
+                len -= 1;
+            }
+            self.buf[self.len..self.len + len].copy_from_slice(&s.as_bytes()[..len]);
+            self.len += len;
+            self.buf[self.len] = 0;
+            Ok(())
+        }
+    }
+
+    /// Logs the panic, and shows it until the user presses Back.
+    pub(super) fn show(panic_info: &PanicInfo<'_>) {
+        if SHOWING.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        // This is only written here, before the draw callback can read it.
+        let text = unsafe { &mut *addr_of_mut!(TEXT) };
+        let _ = write!(text, "{}", panic_info.message());
+        if let Some(location) = panic_info.location() {
+            let _ = write!(text, "\n{}:{}", location.file(), location.line());
+        }
+        unsafe {
+            sys::furi_log_print_format(
+                sys::FuriLogLevel_FuriLogLevelError,
+                c"rust".as_ptr(),
+                c"panicked: %s".as_ptr(),
+                text.as_ptr(),
+            );
+        }
+
+        if !can_wait_for_gui() {
+            return;
+        }
+        unsafe {
+            let gui = sys::furi_record_open(c"gui".as_ptr()) as *mut sys::Gui;
+            let view_port = sys::view_port_alloc();
+            sys::view_port_draw_callback_set(view_port, Some(draw), core::ptr::null_mut());
+            sys::view_port_input_callback_set(view_port, Some(input), core::ptr::null_mut());
+            sys::gui_add_view_port(gui, view_port, sys::GuiLayer_GuiLayerFullscreen);
+
+            while !DISMISSED.load(Ordering::Acquire) {
+                sys::furi_delay_ms(50);
+            }
+
+            sys::gui_remove_view_port(gui, view_port);
+            sys::view_port_free(view_port);
+            sys::furi_record_close(c"gui".as_ptr());
+        }
+    }
+
+    /// Returns `false` if the GUI can't draw while this thread waits, which is the case
+    /// for interrupts and panics of the GUI thread itself, such as in draw callbacks.
+    fn can_wait_for_gui() -> bool {
+        if unsafe { sys::furi_kernel_is_irq_or_masked() } {
+            return false;
+        }
+        let thread = unsafe { sys::furi_thread_get_current_id() };
+        if thread.is_null() {
+            return false;
+        }
+        let name = unsafe { sys::furi_thread_get_name(thread) };
+        name.is_null() || unsafe { CStr::from_ptr(name) } != c"GuiSrv"
+    }
+
+    unsafe extern "C" fn draw(canvas: *mut sys::Canvas, _context: *mut c_void) {
+        unsafe {
+            sys::canvas_clear(canvas);
+            sys::canvas_set_font(canvas, sys::Font_FontPrimary);
+            sys::canvas_draw_str(canvas, 0, 10, c"Rust panic".as_ptr());
+            sys::canvas_set_font(canvas, sys::Font_FontSecondary);
+            sys::elements_text_box(
+                canvas,
+                0,
+                13,
+                128,
+                40,
+                sys::Align_AlignLeft,
+                sys::Align_AlignTop,
+                (*addr_of_mut!(TEXT)).as_ptr(),
+                true,
+            );
+            sys::canvas_draw_str(canvas, 0, 63, c"Press Back to exit".as_ptr());
+        }
+    }
+
+    unsafe extern "C" fn input(event: *mut sys::InputEvent, _context: *mut c_void) {
+        let event = unsafe { &*event };
+        if event.key == sys::InputKey_InputKeyBack && event.type_ == sys::InputType_InputTypeShort {
+            DISMISSED.store(true, Ordering::Release);
+        }
+    }
+}
@@ -111,6 +111,19 @@ impl<T> ::core::fmt::Debug for __IncompleteArrayField<T> {
     }
 }
 pub const API_VERSION: u32 = 4784128;
+pub const USB_EPTYPE_CONTROL: u8 = 0;
+pub const USB_EPTYPE_ISOCHRONUS: u8 = 1;
+pub const USB_EPTYPE_BULK: u8 = 2;
+pub const USB_EPTYPE_INTERRUPT: u8 = 3;
+pub const usbd_evt_reset: u8 = 0;
+pub const usbd_evt_sof: u8 = 1;
+pub const usbd_evt_susp: u8 = 2;
+pub const usbd_evt_wkup: u8 = 3;
+pub const usbd_evt_eptx: u8 = 4;
+pub const usbd_evt_eprx: u8 = 5;
+pub const usbd_evt_epsetup: u8 = 6;
+pub const usbd_evt_error: u8 = 7;
+pub const usbd_evt_count: u8 = 8;
 pub type wint_t = core::ffi::c_int;
 pub type __uint_least8_t = core::ffi::c_uchar;
 pub type __uint_least16_t = core::ffi::c_ushort;
@@ -5,3 +5,4 @@
 //! and thus need to provide one ourselves.
 
 pub mod furi_hal_gpio;
+pub mod usbd_core;
@@ -0,0 +1,130 @@
+//! Inlines for the USB device core of libusb_stm32.
+//!
+//! See: [`usbd_core.h`][1]
+//!
+//! [1]: https://github.com/flipperdevices/libusb_stm32/blob/master/inc/usbd_core.h
+
+use crate as sys;
+
+/// Returns a function of the driver of a device.
+///
+/// # Safety
+///
+/// `f` must be a function of the driver of an initialized device. `usbd_init` only takes
+/// drivers that implement every function, so none of them are null.
+#[inline]
+unsafe fn driver<F>(f: Option<F>) -> F {
+    debug_assert!(f.is_some(), "USB driver function is null");
+    // SAFETY: the driver functions are non-null, as the caller guarantees.
+    f.unwrap_unchecked()
+}
+
+/// Registers the callback for SET_CONFIGURATION requests.
+///
+/// # Safety
+///
+/// `dev` must be non-null, and the memory it points to must be initialized.
+#[inline]
+pub unsafe extern "C" fn usbd_reg_config(
+    dev: *mut sys::usbd_device,
+    callback: sys::usbd_cfg_callback,
+) {
+    (*dev).config_callback = callback;
+}
+
+/// Registers the callback for control requests.
+///
+/// # Safety
+///
+/// `dev` must be non-null, and the memory it points to must be initialized.
+#[inline]
+pub unsafe extern "C" fn usbd_reg_control(
+    dev: *mut sys::usbd_device,
+    callback: sys::usbd_ctl_callback,
+) {
+    (*dev).control_callback = callback;
+}
+
+/// Registers the callback for the events of an endpoint.
+///
+/// The IN and OUT endpoints with the same number share a callback.
+///
+/// # Safety
+///
+/// `dev` must be non-null, and the memory it points to must be initialized.
+#[inline]
+pub unsafe extern "C" fn usbd_reg_endpoint(
+    dev: *mut sys::usbd_device,
+    ep: u8,
+    callback: sys::usbd_evt_callback,
+) {
+    (*dev).endpoint[usize::from(ep & 0x07)] = callback;
+}
+
+/// Connects the device to the host, or disconnects it.
+///
+/// # Safety
+///
+/// `dev` must be non-null, and the memory it points to must be initialized.
+#[inline]
+pub unsafe extern "C" fn usbd_connect(dev: *mut sys::usbd_device, connect: bool) {
+    (*dev).status.device_state = driver((*(*dev).driver).connect)(connect);
+}
+
+/// Configures an endpoint, returning `true` on success.
+///
+/// # Safety
+///
+/// `dev` must be non-null, and the memory it points to must be initialized.
+#[inline]
+pub unsafe extern "C" fn usbd_ep_config(
+    dev: *mut sys::usbd_device,
+    ep: u8,
+    eptype: u8,
+    epsize: u16,
+) -> bool {
+    driver((*(*dev).driver).ep_config)(ep, eptype, epsize)
+}
+
+/// De-configures an endpoint.
+///
+/// # Safety
+///
+/// `dev` must be non-null, and the memory it points to must be initialized.
+#[inline]
+pub unsafe extern "C" fn usbd_ep_deconfig(dev: *mut sys::usbd_device, ep: u8) {
+    driver((*(*dev).driver).ep_deconfig)(ep)
+}
+
+/// Reads a received packet from an OUT endpoint, returning its size, or -1 if no packet
+/// was received.
+///
+/// # Safety
+///
+/// `dev` must be non-null, and the memory it points to must be initialized. `buf` must
+/// be valid for writes of `blen` bytes.
+#[inline]
+pub unsafe extern "C" fn usbd_ep_read(
+    dev: *mut sys::usbd_device,
+    ep: u8,
+    buf: *mut core::ffi::c_void,
+    blen: u16,
+) -> i32 {
+    driver((*(*dev).driver).ep_read)(ep, buf, blen)
+}
+
+/// Writes a packet to an IN endpoint, returning the number of bytes written.
+///
+/// # Safety
+///
+/// `dev` must be non-null, and the memory it points to must be initialized. `buf` must
+/// be valid for reads of `blen` bytes.
+#[inline]
+pub unsafe extern "C" fn usbd_ep_write(
+    dev: *mut sys::usbd_device,
+    ep: u8,
+    buf: *const core::ffi::c_void,
+    blen: u16,
+) -> i32 {
+    driver((*(*dev).driver).ep_write)(ep, buf, blen)
+}
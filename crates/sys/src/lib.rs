@@ -73,3 +73,4 @@ pub use bindings::*;
 
 // Definition of inline functions
 pub use inlines::furi_hal_gpio::*;
+pub use inlines::usbd_core::*;
@@ -1,5 +1,5 @@
 use quote::quote;
-use syn::{parse, Block, Expr, ExprMacro, ExprTuple, Stmt};
+use syn::{parse, Block, Expr, ExprMacro, ExprTuple, Stmt, StmtMacro};
 
 /// Find and replace macro assertions inside the given block with `return Err(..)`.
 ///
@@ -23,12 +23,23 @@ fn block_stmts(stmts: Vec<Stmt>) -> parse::Result<Vec<Stmt>> {
     stmts
         .into_iter()
         .map(|stmt| match stmt {
-            Stmt::Expr(Expr::Block(mut e)) => {
+            Stmt::Expr(Expr::Block(mut e), trailing) => {
                 e.block.stmts = block_stmts(e.block.stmts)?;
-                Ok(Stmt::Expr(Expr::Block(e)))
+                Ok(Stmt::Expr(Expr::Block(e), trailing))
             }
-            Stmt::Expr(Expr::Macro(m)) => expr_macro(m).map(Stmt::Expr),
-            Stmt::Semi(Expr::Macro(m), trailing) => expr_macro(m).map(|m| Stmt::Semi(m, trailing)),
+            Stmt::Expr(Expr::Macro(m), trailing) => expr_macro(m).map(|m| Stmt::Expr(m, trailing)),
+            Stmt::Macro(StmtMacro {
+                attrs,
+                mac,
+                semi_token,
+            }) => expr_macro(ExprMacro { attrs, mac }).map(|m| match m {
+                Expr::Macro(ExprMacro { attrs, mac }) => Stmt::Macro(StmtMacro {
+                    attrs,
+                    mac,
+                    semi_token,
+                }),
+                m => Stmt::Expr(m, semi_token),
+            }),
             _ => Ok(stmt),
         })
         .collect::<Result<_, _>>()
@@ -5,7 +5,7 @@ use syn::{
     parse::{self, Parse},
     punctuated::Punctuated,
     spanned::Spanned,
-    token, Expr, ExprArray, Ident, Item, ItemMod, ReturnType, Stmt, Token,
+    token, BinOp, Expr, ExprArray, Ident, Item, ItemMod, ReturnType, Stmt, Token,
 };
 
 mod deassert;
@@ -78,7 +78,14 @@ fn tests_runner_impl(args: TokenStream) -> parse::Result<TokenStream> {
     let test_suites = test_suites
         .elems
         .into_iter()
-        .map(|attr| {
+        .map(|mut attr| {
+            // Attributes on a test suite (such as `#[cfg(..)]`) are applied to the
+            // statements referencing it.
+            let attrs = match &mut attr {
+                Expr::Path(path) => core::mem::take(&mut path.attrs),
+                _ => vec![],
+            };
+
             let mut module = String::new();
             for token in attr.to_token_stream() {
                 module.push_str(&token.to_string());
@@ -86,14 +93,19 @@ fn tests_runner_impl(args: TokenStream) -> parse::Result<TokenStream> {
             let module = module.trim_start_matches("crate::");
 
             (
+                attrs,
                 quote!(#attr::__test_list().len()),
                 quote!(#attr::__test_list().iter().copied().map(|(name, test_fn)| (#module, name, test_fn))),
             )
         })
         .collect::<Vec<_>>();
 
-    let test_counts = test_suites.iter().map(|(count, _)| count);
-    let test_lists = test_suites.iter().map(|(_, list)| list);
+    let test_attrs = test_suites
+        .iter()
+        .map(|(attrs, _, _)| attrs)
+        .collect::<Vec<_>>();
+    let test_counts = test_suites.iter().map(|(_, count, _)| count);
+    let test_lists = test_suites.iter().map(|(_, _, list)| list);
 
     let manifest_args = manifest_args.into_iter().map(
         |TestRunnerArg {
@@ -120,13 +132,13 @@ fn tests_runner_impl(args: TokenStream) -> parse::Result<TokenStream> {
 
             const fn test_count() -> usize {
                 let ret = 0;
-                #( let ret = ret + #test_counts; )*
+                #( #(#test_attrs)* let ret = ret + #test_counts; )*
                 ret
             }
 
             fn test_list() -> impl Iterator<Item = (&'static str, &'static str, ::flipperzero_test::TestFn)> + Clone {
                 let ret = ::core::iter::empty();
-                #( let ret = ret.chain(#test_lists); )*
+                #( #(#test_attrs)* let ret = ret.chain(#test_lists); )*
                 ret
             }
 
@@ -194,11 +206,11 @@ fn tests_impl(args: TokenStream, input: TokenStream) -> parse::Result<TokenStrea
 
                 // Find and extract the `#[test]` and `#[cfg(..)] attributes, if present.
                 f.attrs.retain(|attr| {
-                    if attr.path.is_ident("test") {
+                    if attr.path().is_ident("test") {
                         is_test = true;
                         false
                     } else {
-                        if attr.path.is_ident("cfg") {
+                        if attr.path().is_ident("cfg") {
                             cfg.push(attr.clone());
                         }
                         true
@@ -234,9 +246,10 @@ fn tests_impl(args: TokenStream, input: TokenStream) -> parse::Result<TokenStrea
                     check_ret_block(&mut f.block.stmts)?;
 
                     // Append an `Ok(())` to the test.
-                    f.block.stmts.push(Stmt::Expr(syn::parse(
-                        quote!(::core::result::Result::Ok(())).into(),
-                    )?));
+                    f.block.stmts.push(Stmt::Expr(
+                        syn::parse(quote!(::core::result::Result::Ok(())).into())?,
+                        None,
+                    ));
 
                     tests.push(f);
                     test_cfgs.push(cfg);
@@ -277,10 +290,20 @@ fn tests_impl(args: TokenStream, input: TokenStream) -> parse::Result<TokenStrea
 
 fn check_ret_block(stmts: &mut [Stmt]) -> parse::Result<()> {
     if let Some(stmt) = stmts.last_mut() {
-        if let Stmt::Expr(expr) = stmt {
-            if let Some(new_stmt) = check_ret_expr(expr)? {
-                *stmt = new_stmt;
+        match stmt {
+            Stmt::Expr(expr, None) => {
+                if let Some(new_stmt) = check_ret_expr(expr)? {
+                    *stmt = new_stmt;
+                }
+            }
+            // A trailing macro call is an expression, which may return a value.
+            Stmt::Macro(m) if m.semi_token.is_none() => {
+                return Err(parse::Error::new(
+                    m.span(),
+                    "`#[test]` function must not return anything",
+                ));
             }
+            _ => {}
         }
     }
     Ok(())
@@ -310,11 +333,12 @@ fn check_ret_expr(expr: &mut Expr) -> parse::Result<Option<Stmt>> {
         Expr::TryBlock(e) => check_ret_block(&mut e.block.stmts).map(|()| None),
         Expr::Unsafe(e) => check_ret_block(&mut e.block.stmts).map(|()| None),
         // If `expr` implicitly returns `()`, append a semicolon.
-        Expr::Assign(_) | Expr::AssignOp(_) => {
-            Ok(Some(Stmt::Semi(expr.clone(), Token!(;)(expr.span()))))
+        Expr::Assign(_) => Ok(Some(Stmt::Expr(expr.clone(), Some(Token!(;)(expr.span()))))),
+        Expr::Binary(e) if is_assign_op(&e.op) => {
+            Ok(Some(Stmt::Expr(expr.clone(), Some(Token!(;)(expr.span())))))
         }
         Expr::Break(brk) if brk.expr.is_none() => {
-            Ok(Some(Stmt::Semi(expr.clone(), Token!(;)(expr.span()))))
+            Ok(Some(Stmt::Expr(expr.clone(), Some(Token!(;)(expr.span())))))
         }
         // For all other expressions, raise an error.
         _ => Err(parse::Error::new(
@@ -323,3 +347,20 @@ fn check_ret_expr(expr: &mut Expr) -> parse::Result<Option<Stmt>> {
         )),
     }
 }
+
+/// Returns `true` if `op` is a compound assignment operator, such as `+=`.
+fn is_assign_op(op: &BinOp) -> bool {
+    matches!(
+        op,
+        BinOp::AddAssign(_)
+            | BinOp::SubAssign(_)
+            | BinOp::MulAssign(_)
+            | BinOp::DivAssign(_)
+            | BinOp::RemAssign(_)
+            | BinOp::BitXorAssign(_)
+            | BinOp::BitAndAssign(_)
+            | BinOp::BitOrAssign(_)
+            | BinOp::ShlAssign(_)
+            | BinOp::ShrAssign(_)
+    )
+}
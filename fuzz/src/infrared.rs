@@ -0,0 +1,73 @@
+//! The `.ir` file parser of `flipperzero::infrared`.
+
+use core::ffi::CStr;
+
+use crate::io;
+use crate::Cursor;
+
+// Newer lints than those of the toolchain of `flipperzero` don't apply.
+#[allow(unknown_lints, clippy::unnecessary_map_or)]
+#[path = "../../crates/flipperzero/src/infrared/file.rs"]
+mod file;
+
+use self::file::{Found, Parser};
+
+/// Stand-in for the errors of `flipperzero::infrared`.
+#[derive(Debug, Clone, Copy)]
+pub enum Error {
+    UnknownProtocol,
+    InvalidFile,
+    NotFound,
+    Io(io::Error),
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+/// Stand-in for the protocols known to the firmware, which rejects names longer than
+/// those of any real protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Protocol(usize);
+
+impl Protocol {
+    pub fn from_name(name: &CStr) -> Result<Self, Error> {
+        match name.to_bytes().len() {
+            len @ 1..=16 => Ok(Self(len)),
+            _ => Err(Error::UnknownProtocol),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Message {
+    pub protocol: Protocol,
+    pub address: u32,
+    pub command: u32,
+}
+
+/// Parses `data` as an `.ir` file the way sending a signal from it does: it finds the
+/// first signal, reads all timings of a raw one, and then finds a signal by name.
+pub fn parse_file(data: &[u8]) {
+    let mut parser = Parser::new(Cursor::new(data));
+    if parser.read_header().is_err() {
+        return;
+    }
+    if let Ok(Found::Raw { data, .. }) = parser.find(None) {
+        while let Ok(Some(_)) = parser.next_timing() {}
+        // Repeating a raw signal reads its timings again.
+        if parser.rewind_to(data).is_ok() {
+            while let Ok(Some(_)) = parser.next_timing() {}
+        }
+    }
+    let _ = parser.rewind_to(0);
+    let _ = parser.read_header();
+    let _ = parser.find(Some(b"POWER"));
+}
+
+/// Parses a value of little-endian hex bytes, such as `07 00 00 00`.
+pub fn parse_hex_le(value: &[u8]) -> Result<u32, Error> {
+    file::parse_hex_le(value)
+}
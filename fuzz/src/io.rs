@@ -0,0 +1,21 @@
+//! Stand-ins for the I/O traits of `flipperzero::io`.
+
+/// The errors of the readers used for fuzzing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    InvalidParameter,
+}
+
+pub trait Read {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error>;
+}
+
+pub trait Seek {
+    fn seek(&mut self, pos: SeekFrom) -> Result<usize, Error>;
+}
+
+pub enum SeekFrom {
+    Start(u64),
+    End(i64),
+    Current(i64),
+}
@@ -0,0 +1,78 @@
+//! Host builds of the parsers of `flipperzero` that read untrusted files, for fuzzing.
+//!
+//! `flipperzero` only builds for the Flipper, so the parsers are included from its
+//! sources, with the few items they use from the rest of the crate replaced by the
+//! stand-ins in [`io`] and [`infrared`]. The parsers themselves don't call into the
+//! firmware.
+//!
+//! Run a target from this directory with
+//! `cargo +nightly fuzz run --fuzz-dir . <target> corpus/<target> seeds/<target>`.
+//! Inputs that made a target fail are added to its seeds once fixed, and the tests of
+//! this crate replay all seeds.
+
+pub mod infrared;
+pub mod io;
+
+use io::{Error, Read, Seek, SeekFrom};
+
+/// A reader over a byte slice.
+pub struct Cursor<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Cursor<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+}
+
+impl Read for Cursor<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let rest = self.data.get(self.position..).unwrap_or_default();
+        let n = rest.len().min(buf.len());
+        buf[..n].copy_from_slice(&rest[..n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+impl Seek for Cursor<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<usize, Error> {
+        let target = match pos {
+            SeekFrom::Start(offset) => i64::try_from(offset).unwrap_or(i64::MAX),
+            SeekFrom::End(offset) => (self.data.len() as i64).saturating_add(offset),
+            SeekFrom::Current(offset) => (self.position as i64).saturating_add(offset),
+        };
+        self.position = usize::try_from(target).map_err(|_| Error::InvalidParameter)?;
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    /// Replays the inputs in `seeds/<target>` through `run`.
+    fn replay(target: &str, run: fn(&[u8])) {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("seeds")
+            .join(target);
+        for entry in fs::read_dir(dir).unwrap() {
+            run(&fs::read(entry.unwrap().path()).unwrap());
+        }
+    }
+
+    #[test]
+    fn infrared_file_seeds() {
+        replay("infrared_file", crate::infrared::parse_file);
+    }
+
+    #[test]
+    fn hex_le_seeds() {
+        replay("hex_le", |data| {
+            let _ = crate::infrared::parse_hex_le(data);
+        });
+    }
+}
@@ -5,7 +5,7 @@
 use std::borrow::Cow;
 use std::{env, fs};
 
-use bindgen::callbacks::ParseCallbacks;
+use bindgen::callbacks::{IntKind, ParseCallbacks};
 use camino::{Utf8Path, Utf8PathBuf};
 use clap::{crate_authors, crate_description, crate_version, value_parser};
 use once_cell::sync::Lazy;
@@ -160,6 +160,11 @@ impl ParseCallbacks for Cb {
             comment,
         )))
     }
+
+    fn int_macro(&self, name: &str, _value: i64) -> Option<IntKind> {
+        // USB events and endpoint types are passed around as `u8`.
+        (name.starts_with("usbd_evt_") || name.starts_with("USB_EPTYPE_")).then_some(IntKind::U8)
+    }
 }
 
 fn main() {
@@ -230,6 +235,8 @@ fn main() {
         .parse_callbacks(Box::new(Cb))
         .ctypes_prefix("core::ffi")
         .allowlist_var("API_VERSION")
+        .allowlist_var("usbd_evt_.*")
+        .allowlist_var("USB_EPTYPE_.*")
         .header_contents("header.h", &bindings_header);
 
     for function in &symbols.functions {
@@ -261,7 +268,7 @@ fn main() {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use bindgen::callbacks::ParseCallbacks;
+    use bindgen::callbacks::{IntKind, ParseCallbacks};
 
     #[test]
     fn doxygen_comments_simple_adhoc_fix() {